@@ -2,19 +2,16 @@ use std::env;
 use std::process;
 use vma_socket::udp::VmaUdpSocket;
 use vma_socket::tcp::VmaTcpSocket;
-use vma_socket::common::VmaOptions;
+use vma_socket::common::{self, clock, VmaOptions};
 
 const ITERATIONS: usize = 10000;
 const BUFFER_SIZE: usize = 4096;
 
-use flashlog::get_unix_nano;
-
 fn main() {
     async_std::task::block_on(async {
         let args: Vec<String> = env::args().collect();
         env::set_var("VMA_INTERRUPT_AFFINITY", "0");
         env::set_var("VMA_THREAD_AFFINITY", "0");
-        env::set_var("VMA_RX_POLL_OS_RATIO", "1000000");
 
         if args.len() < 2 {
             println!("Usage: {} [udp|tcp] [recv|send|both]", args[0]);
@@ -72,8 +69,7 @@ fn benchmark_udp_recv() {
     benchmark_udp_recv_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_udp_recv_with_options("Non-Polling", non_polling_options);
 }
 
@@ -82,7 +78,8 @@ fn benchmark_udp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     println!("\n--- {} Configuration ---", config_name);
     options.clear_cores();
     options.add_core(0).expect("Failed to set CPU core");
-    let mut socket = match VmaUdpSocket::with_options(options.clone()) {
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
+    let mut socket = match VmaUdpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create UDP socket: {}", e);
@@ -100,24 +97,20 @@ fn benchmark_udp_recv_with_options(config_name: &str, mut options: VmaOptions) {
 
     let task = std::thread::spawn(move || {
         // Set CPU core
-        core_affinity::set_for_current(core_affinity::CoreId { id: 0});
+        common::pin_current_thread(&options).expect("Failed to pin thread");
 
         let mut buffer = vec![0u8; BUFFER_SIZE];
 
         println!("Warming up...");
         // Warmup
         for _ in 0..1000 {
-            let _ = if options.use_polling {
-                socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for polling
-            } else {
-                socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for non-polling
-            };
+            let _ = socket.recv(&mut buffer, Some(0)); // 0 = non-blocking
         }
 
         println!("Starting UDP recv benchmark ({} iterations)...", ITERATIONS);
         
         // Single timestamp at the beginning
-        let start_time = get_unix_nano();
+        let start_time = clock::now_nanos();
         
         // recv benchmark (empty socket polling)
         for _i in 0..ITERATIONS {
@@ -125,7 +118,7 @@ fn benchmark_udp_recv_with_options(config_name: &str, mut options: VmaOptions) {
         }
 
         // Single timestamp at the end
-        let end_time = get_unix_nano();
+        let end_time = clock::now_nanos();
         
         let total_time_ns = end_time - start_time;
         let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -148,16 +141,16 @@ fn benchmark_udp_send() {
     benchmark_udp_send_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_udp_send_with_options("Non-Polling", non_polling_options);
 }
 
 fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("\n--- {} Configuration ---", config_name);
-    core_affinity::set_for_current(core_affinity::CoreId { id: 0 });
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
+    common::pin_current_thread(&options).expect("Failed to pin thread");
     
     // Create socket
     let mut socket = match VmaUdpSocket::with_options(options) {
@@ -182,7 +175,7 @@ fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("Starting UDP send benchmark ({} iterations)...", ITERATIONS);
     
     // Single timestamp at the beginning
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     
     // send benchmark
     for _i in 0..ITERATIONS {
@@ -190,7 +183,7 @@ fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     }
 
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -208,8 +201,7 @@ fn benchmark_tcp_recv() {
     benchmark_tcp_recv_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_tcp_recv_with_options("Non-Polling", non_polling_options);
 }
 
@@ -218,9 +210,10 @@ fn benchmark_tcp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
     
     // Create TCP socket (not connected, just for polling benchmark)
-    let mut socket = match VmaTcpSocket::with_options(options.clone()) {
+    let mut socket = match VmaTcpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create TCP socket: {}", e);
@@ -243,19 +236,15 @@ fn benchmark_tcp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     println!("Starting TCP socket polling benchmark ({} iterations)...", ITERATIONS);
     
     // Single timestamp at the beginning
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     
     // recv benchmark (empty socket polling)
     for _i in 0..ITERATIONS {
-        let _ = if options.use_polling {
-            socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for polling
-        } else {
-            socket.recv(&mut buffer, Some(0)) // 1ms timeout for non-polling
-        };
+        let _ = socket.recv(&mut buffer, Some(0)); // 0 = non-blocking
     }
 
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -273,8 +262,7 @@ fn benchmark_tcp_send() {
     benchmark_tcp_send_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_tcp_send_with_options("Non-Polling", non_polling_options);
 }
 
@@ -283,9 +271,10 @@ fn benchmark_tcp_send_with_options(config_name: &str, mut options: VmaOptions) {
     
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
     
     // Create TCP socket
-    let mut socket = match VmaTcpSocket::with_options(options.clone()) {
+    let mut socket = match VmaTcpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create TCP socket: {}", e);
@@ -307,13 +296,13 @@ fn benchmark_tcp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("Starting TCP send benchmark ({} iterations)...", ITERATIONS);
     // Single timestamp at the beginning
 
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     // send benchmark
     for _i in 0..ITERATIONS {
         let _ = socket.send(test_data);
     }
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
     let avg_time_us = avg_time_ns as f64 / 1000.0;
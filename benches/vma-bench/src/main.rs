@@ -0,0 +1,321 @@
+//! `vma-bench`: iperf/sockperf-style benchmark for VMA UDP and TCP sockets.
+//!
+//! Run a server on one host and a client on the other, both with the same protocol and mode:
+//!
+//! ```text
+//! # receiver / echo side
+//! LD_PRELOAD=libvma.so vma-bench udp server --port 5001 --mode ping-pong --affinity 2
+//! # sender side: 64-byte round trips for 10 seconds, JSON result
+//! LD_PRELOAD=libvma.so vma-bench udp client 192.168.1.10 --port 5001 --mode ping-pong \
+//!     --size 64 --duration 10 --affinity 2 --json
+//! ```
+//!
+//! In throughput mode the client sends as fast as allowed (`--rate` caps messages per
+//! second) and the server reports what arrived; with `--bidir` on both ends the server
+//! reflects the traffic and the client measures both directions. In ping-pong mode the
+//! server echoes and the client reports round-trip latency percentiles (min/p50/p99/p99.9/max
+//! from an HDR histogram, via `vma_socket::latency::PingPong`).
+//!
+//! `--streams N` runs N streams in parallel on ports `port..port+N`, one thread each,
+//! pinned to the `--affinity` cores in turn (`--affinity 2,3,4,5`), so scaling across VMA
+//! rings and cores can be measured. The client prints every stream and their totals.
+
+mod report;
+mod sequence;
+mod tcp;
+mod udp;
+
+use std::io;
+use std::net::Ipv4Addr;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use vma_socket::common::{self, clock, VmaOptions};
+use vma_socket::latency::{PingPong, RoundTrip};
+use vma_socket::pacing::{Pacer, Rate};
+
+use report::{Direction, Interval, Report, Summary};
+
+#[derive(Debug, Parser)]
+#[command(name = "vma-bench", version, about = "UDP/TCP throughput and latency benchmark over VMA sockets")]
+struct Cli {
+    #[command(subcommand)]
+    protocol: Protocol,
+}
+
+#[derive(Debug, Subcommand)]
+enum Protocol {
+    /// Benchmark UDP sockets
+    Udp {
+        #[command(subcommand)]
+        role: Role,
+    },
+    /// Benchmark TCP sockets
+    Tcp {
+        #[command(subcommand)]
+        role: Role,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum Role {
+    /// Receive (throughput) or echo (ping-pong) until interrupted
+    Server(ServerArgs),
+    /// Generate traffic towards a server and report the results
+    Client(ClientArgs),
+}
+
+/// Traffic pattern, identical on both ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// One-way stream measuring message and bit rates
+    Throughput,
+    /// Request/echo round trips measuring latency
+    PingPong,
+    /// Sequence-checked UDP stream reporting loss, reordering and duplicates (e.g. of a multicast feed)
+    Loss,
+}
+
+/// VMA option preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// `VmaOptions::low_latency()`
+    Latency,
+    /// `VmaOptions::high_throughput()`
+    Throughput,
+    /// `VmaOptions::default()`
+    Default,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CommonArgs {
+    /// Port to bind or connect to (stream `i` uses `port + i`)
+    #[arg(short, long, default_value_t = 5001)]
+    pub port: u16,
+    /// Traffic pattern
+    #[arg(short, long, value_enum, default_value_t = Mode::Throughput)]
+    pub mode: Mode,
+    /// Message size in bytes (at least 8 for the sequence number)
+    #[arg(short, long, default_value_t = 64)]
+    pub size: usize,
+    /// Parallel streams, one thread and port each
+    #[arg(short = 'P', long, default_value_t = 1)]
+    pub streams: usize,
+    /// Reflect throughput traffic so both directions are loaded (set on both ends)
+    #[arg(long)]
+    pub bidir: bool,
+    /// CPU cores to pin the streams (and VMA's rings) to, stream `i` on the `i`-th in turn
+    #[arg(short, long, value_delimiter = ',')]
+    pub affinity: Vec<i32>,
+    /// VMA option preset
+    #[arg(long, value_enum, default_value_t = Profile::Latency)]
+    profile: Profile,
+    /// Print results as JSON instead of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ServerArgs {
+    /// Address to bind
+    #[arg(short, long, default_value = "0.0.0.0")]
+    pub bind: String,
+    /// Multicast group to join (UDP)
+    #[arg(short, long)]
+    pub group: Option<Ipv4Addr>,
+    /// Address of the interface to join the group on
+    #[arg(short, long, default_value_t = Ipv4Addr::UNSPECIFIED)]
+    pub interface: Ipv4Addr,
+    /// Seconds between loss-mode interval reports
+    #[arg(long, default_value_t = 1.0)]
+    pub interval: f64,
+    #[command(flatten)]
+    pub common: CommonArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ClientArgs {
+    /// Server address
+    pub host: String,
+    /// Test duration in seconds
+    #[arg(short, long, default_value_t = 10.0)]
+    pub duration: f64,
+    /// Messages per second and stream (unlimited when absent)
+    #[arg(short, long)]
+    pub rate: Option<u64>,
+    /// Unrecorded round trips before measuring (ping-pong mode)
+    #[arg(short, long, default_value_t = 1000)]
+    pub warmup: u64,
+    #[command(flatten)]
+    pub common: CommonArgs,
+}
+
+impl ServerArgs {
+    /// Serve every stream until interrupted; a failing stream ends the process.
+    pub fn run<F>(&self, stream: F) -> Result<(), String>
+    where
+        F: Fn(usize, VmaOptions) -> Result<(), String> + Sync,
+    {
+        self.common
+            .run_streams(|index, options| {
+                let result = stream(index, options);
+                if let Err(e) = &result {
+                    eprintln!("vma-bench: stream {}: {}", index, e);
+                    process::exit(1);
+                }
+                result
+            })
+            .map(|_| ())
+    }
+}
+
+impl ClientArgs {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration.max(0.0))
+    }
+
+    /// Build the `--rate` pacer, if any.
+    pub fn pacer(&self) -> Result<Option<Pacer>, String> {
+        self.rate
+            .map(|rate| Pacer::new(Rate::PacketsPerSec(rate)).map_err(|e| format!("Invalid rate: {}", e)))
+            .transpose()
+    }
+
+    /// Measure round trips for the test duration after the warm-up, paced by `--rate`.
+    pub fn ping_pong<T: RoundTrip>(&self, protocol: &'static str, stream: usize, transport: &mut T) -> Result<Report, io::Error> {
+        let mut pacer = self.pacer().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut ping_pong = PingPong::new(self.common.size).with_warmup(self.warmup);
+        ping_pong.warm_up(transport)?;
+
+        let duration = self.duration();
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            if let Some(pacer) = pacer.as_mut() {
+                pacer.acquire(ping_pong.size());
+            }
+            ping_pong.round_trip(transport)?;
+        }
+
+        let histogram = ping_pong.histogram().clone();
+        let mut report = Report::new(protocol, "client", Mode::PingPong, Direction::RoundTrip, stream, ping_pong.size(), start.elapsed(), histogram.len())
+            .with_histogram(histogram);
+        report.lost = Some(ping_pong.lost());
+        Ok(report)
+    }
+
+    /// Run every stream and print their reports with the totals.
+    pub fn run<F>(&self, stream: F) -> Result<(), String>
+    where
+        F: Fn(usize, VmaOptions) -> Result<Vec<Report>, String> + Sync,
+    {
+        let reports = self.common.run_streams(stream)?;
+        let summary = Summary::new(reports.into_iter().flatten().collect());
+        if self.common.json {
+            println!("{}", serde_json::to_string(&summary).expect("summary serializes"));
+        } else {
+            summary.print_text();
+        }
+        Ok(())
+    }
+}
+
+impl CommonArgs {
+    /// Check the arguments and calibrate the clock, before any stream starts.
+    fn validate(&self) -> Result<(), String> {
+        if self.size < 8 {
+            return Err("--size must be at least 8 bytes".to_string());
+        }
+        if self.streams == 0 {
+            return Err("--streams must be at least 1".to_string());
+        }
+        if self.port as usize + self.streams - 1 > u16::MAX as usize {
+            return Err("--port + --streams exceeds the port range".to_string());
+        }
+        if self.bidir && self.mode != Mode::Throughput {
+            return Err("--bidir applies to throughput mode only".to_string());
+        }
+        clock::calibrate();
+        Ok(())
+    }
+
+    /// Get the port of a stream.
+    pub fn port(&self, stream: usize) -> u16 {
+        self.port + stream as u16
+    }
+
+    /// Build the socket options of a stream and pin the calling thread to its core.
+    fn stream_setup(&self, stream: usize) -> Result<VmaOptions, String> {
+        let mut options = match self.profile {
+            Profile::Latency => VmaOptions::low_latency(),
+            Profile::Throughput => VmaOptions::high_throughput(),
+            Profile::Default => VmaOptions::default(),
+        };
+        options.clear_cores();
+        if !self.affinity.is_empty() {
+            let core = self.affinity[stream % self.affinity.len()];
+            options.add_core(core).map_err(|e| format!("Invalid affinity: {}", e))?;
+            common::pin_current_thread(&options).map_err(|e| format!("Failed to pin thread to core {}: {}", core, e))?;
+        }
+        Ok(options)
+    }
+
+    /// Run `stream` for every stream on its own pinned thread and collect the results.
+    ///
+    /// The first failing stream's error is returned once all streams finished.
+    pub fn run_streams<T, F>(&self, stream: F) -> Result<Vec<T>, String>
+    where
+        T: Send,
+        F: Fn(usize, VmaOptions) -> Result<T, String> + Sync,
+    {
+        self.validate()?;
+        let stream = &stream;
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.streams)
+                .map(|index| {
+                    scope.spawn(move || {
+                        let options = self.stream_setup(index)?;
+                        stream(index, options).map_err(|e| format!("stream {}: {}", index, e))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err("stream thread panicked".to_string())))
+                .collect()
+        })
+    }
+
+    /// Print a server report in the requested format.
+    pub fn print(&self, report: &Report) {
+        if self.json {
+            println!("{}", serde_json::to_string(report).expect("report serializes"));
+        } else {
+            report.print_text();
+        }
+    }
+
+    /// Print a loss-mode interval in the requested format.
+    pub fn print_interval(&self, interval: &Interval) {
+        if self.json {
+            println!("{}", serde_json::to_string(interval).expect("interval serializes"));
+        } else {
+            interval.print_text();
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.protocol {
+        Protocol::Udp { role: Role::Server(args) } => udp::server(&args),
+        Protocol::Udp { role: Role::Client(args) } => udp::client(&args),
+        Protocol::Tcp { role: Role::Server(args) } => tcp::server(&args),
+        Protocol::Tcp { role: Role::Client(args) } => tcp::client(&args),
+    };
+    if let Err(e) = result {
+        eprintln!("vma-bench: {}", e);
+        process::exit(1);
+    }
+}
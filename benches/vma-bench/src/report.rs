@@ -0,0 +1,240 @@
+//! Benchmark results and their text/JSON rendering.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use vma_socket::instrument::{LatencyHistogram, LatencySnapshot};
+
+use crate::sequence::SequenceStats;
+use crate::Mode;
+
+/// Which traffic a [`Report`] measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    /// Messages this end sent
+    Send,
+    /// Messages this end received
+    Receive,
+    /// Request/echo round trips
+    RoundTrip,
+}
+
+/// Outcome of one stream's run on one end.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub protocol: &'static str,
+    pub role: &'static str,
+    pub mode: &'static str,
+    pub direction: Direction,
+    /// Stream index, absent on totals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<usize>,
+    pub message_size: usize,
+    pub duration_secs: f64,
+    pub messages: u64,
+    pub bytes: u64,
+    pub msgs_per_sec: f64,
+    pub mbit_per_sec: f64,
+    /// Messages sent but not received (UDP receivers and round trips)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lost: Option<u64>,
+    /// Messages received after a later one (loss mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reordered: Option<u64>,
+    /// Messages received more than once (loss mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencySnapshot>,
+    /// Round-trip samples behind `latency`, kept for totals
+    #[serde(skip)]
+    pub histogram: Option<LatencyHistogram>,
+}
+
+/// Loss-mode counters of one reporting interval of a receiving stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct Interval {
+    pub protocol: &'static str,
+    pub stream: usize,
+    /// Interval bounds in seconds since the first message of the run
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub messages: u64,
+    /// Sequence numbers skipped in the interval and not received by its end
+    pub lost: u64,
+    pub reordered: u64,
+    pub duplicates: u64,
+}
+
+impl Interval {
+    pub fn print_text(&self) {
+        println!(
+            "[{}] {:.1}-{:.1}s: {} msgs, {} lost, {} reordered, {} duplicates",
+            self.stream, self.start_secs, self.end_secs, self.messages, self.lost, self.reordered, self.duplicates
+        );
+    }
+}
+
+/// Client results: every stream plus one total per direction.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub streams: Vec<Report>,
+    pub total: Vec<Report>,
+}
+
+impl Report {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        protocol: &'static str,
+        role: &'static str,
+        mode: Mode,
+        direction: Direction,
+        stream: usize,
+        message_size: usize,
+        elapsed: Duration,
+        messages: u64,
+    ) -> Self {
+        let secs = elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+        let bytes = messages * message_size as u64;
+        Report {
+            protocol,
+            role,
+            mode: match mode {
+                Mode::Throughput => "throughput",
+                Mode::PingPong => "ping-pong",
+                Mode::Loss => "loss",
+            },
+            direction,
+            stream: Some(stream),
+            message_size,
+            duration_secs: elapsed.as_secs_f64(),
+            messages,
+            bytes,
+            msgs_per_sec: messages as f64 / secs,
+            mbit_per_sec: bytes as f64 * 8.0 / secs / 1e6,
+            lost: None,
+            reordered: None,
+            duplicates: None,
+            latency: None,
+            histogram: None,
+        }
+    }
+
+    /// Attach the loss-mode counters.
+    pub fn with_sequence(mut self, stats: &SequenceStats) -> Self {
+        self.lost = Some(stats.missing);
+        self.reordered = Some(stats.reordered);
+        self.duplicates = Some(stats.duplicates);
+        self
+    }
+
+    /// Attach round-trip samples and their percentiles.
+    pub fn with_histogram(mut self, histogram: LatencyHistogram) -> Self {
+        self.latency = Some(histogram.snapshot());
+        self.histogram = Some(histogram);
+        self
+    }
+
+    /// Combine the reports of streams run in parallel in one direction.
+    ///
+    /// Counts and rates add up, the duration is the longest stream's and round-trip
+    /// percentiles are computed over the samples of all streams.
+    pub fn sum(reports: &[&Report]) -> Report {
+        let mut total = Report {
+            stream: None,
+            duration_secs: 0.0,
+            messages: 0,
+            bytes: 0,
+            msgs_per_sec: 0.0,
+            mbit_per_sec: 0.0,
+            lost: None,
+            reordered: None,
+            duplicates: None,
+            latency: None,
+            histogram: None,
+            ..reports[0].clone()
+        };
+        for report in reports {
+            total.duration_secs = total.duration_secs.max(report.duration_secs);
+            total.messages += report.messages;
+            total.bytes += report.bytes;
+            total.msgs_per_sec += report.msgs_per_sec;
+            total.mbit_per_sec += report.mbit_per_sec;
+            for (total, count) in [(&mut total.lost, report.lost), (&mut total.reordered, report.reordered), (&mut total.duplicates, report.duplicates)] {
+                if let Some(count) = count {
+                    *total = Some(total.unwrap_or(0) + count);
+                }
+            }
+            if let Some(histogram) = &report.histogram {
+                total.histogram.get_or_insert_with(LatencyHistogram::new).merge(histogram);
+            }
+        }
+        total.latency = total.histogram.as_ref().map(LatencyHistogram::snapshot);
+        total
+    }
+
+    pub fn print_text(&self) {
+        let stream = match self.stream {
+            Some(stream) => format!("[{}]", stream),
+            None => "[sum]".to_string(),
+        };
+        let direction = match self.direction {
+            Direction::Send => "send",
+            Direction::Receive => "receive",
+            Direction::RoundTrip => "round-trip",
+        };
+        println!(
+            "{} {} {} {} {}: {} x {} bytes in {:.3}s = {:.0} msg/s, {:.2} Mbit/s",
+            stream,
+            self.protocol,
+            self.mode,
+            self.role,
+            direction,
+            self.messages,
+            self.message_size,
+            self.duration_secs,
+            self.msgs_per_sec,
+            self.mbit_per_sec
+        );
+        if let Some(lost) = self.lost {
+            let total = self.messages + lost;
+            println!("  lost: {} ({:.3}%)", lost, if total == 0 { 0.0 } else { lost as f64 * 100.0 / total as f64 });
+        }
+        if let (Some(reordered), Some(duplicates)) = (self.reordered, self.duplicates) {
+            println!("  reordered: {}, duplicates: {}", reordered, duplicates);
+        }
+        if let Some(latency) = &self.latency {
+            println!(
+                "  rtt ns: min {} mean {:.0} p50 {} p90 {} p99 {} p99.9 {} max {} ({} samples)",
+                latency.min, latency.mean, latency.p50, latency.p90, latency.p99, latency.p999, latency.max, latency.count
+            );
+        }
+    }
+}
+
+impl Summary {
+    /// Collect the per-stream reports and total them per direction.
+    pub fn new(streams: Vec<Report>) -> Self {
+        let mut total = Vec::new();
+        for direction in [Direction::Send, Direction::Receive, Direction::RoundTrip] {
+            let reports: Vec<&Report> = streams.iter().filter(|report| report.direction == direction).collect();
+            if !reports.is_empty() {
+                total.push(Report::sum(&reports));
+            }
+        }
+        Summary { streams, total }
+    }
+
+    pub fn print_text(&self) {
+        for report in &self.streams {
+            report.print_text();
+        }
+        // With a single stream per direction the totals repeat the streams
+        if self.streams.len() > self.total.len() {
+            for report in &self.total {
+                report.print_text();
+            }
+        }
+    }
+}
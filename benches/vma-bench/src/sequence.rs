@@ -0,0 +1,103 @@
+//! Sequence-number bookkeeping for the loss mode.
+
+/// Sequence numbers remembered behind the highest one, for reorder and duplicate detection.
+const WINDOW: u64 = 1 << 16;
+
+/// Counters of a [`SequenceTracker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SequenceStats {
+    /// Distinct sequence numbers received
+    pub received: u64,
+    /// Sequence numbers skipped and not (yet) received
+    pub missing: u64,
+    /// Sequence numbers received after a higher one
+    pub reordered: u64,
+    /// Sequence numbers received more than once
+    pub duplicates: u64,
+}
+
+impl SequenceStats {
+    /// Get the counters accumulated since `earlier`; missing numbers may have been filled in since.
+    pub fn since(&self, earlier: &SequenceStats) -> SequenceStats {
+        SequenceStats {
+            received: self.received - earlier.received,
+            missing: self.missing.saturating_sub(earlier.missing),
+            reordered: self.reordered - earlier.reordered,
+            duplicates: self.duplicates - earlier.duplicates,
+        }
+    }
+}
+
+/// Classifies received sequence numbers as in order, reordered, duplicated or missing.
+///
+/// Numbers more than [`WINDOW`] behind the highest one are counted as reordered without
+/// duplicate detection.
+#[derive(Debug)]
+pub struct SequenceTracker {
+    /// One bit per sequence number in `(highest - WINDOW, highest]`
+    seen: Vec<u64>,
+    highest: Option<u64>,
+    stats: SequenceStats,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        SequenceTracker {
+            seen: vec![0; (WINDOW / 64) as usize],
+            highest: None,
+            stats: SequenceStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> SequenceStats {
+        self.stats
+    }
+
+    pub fn record(&mut self, seq: u64) {
+        let Some(highest) = self.highest else {
+            // The first number starts the run: nothing before it is missing
+            self.highest = Some(seq);
+            self.mark(seq);
+            self.stats.received += 1;
+            return;
+        };
+
+        if seq > highest {
+            // Forget the numbers that slide out of the window, including the skipped ones
+            let skipped = seq - highest - 1;
+            for stale in (highest + 1)..seq.min(highest + 1 + WINDOW) {
+                self.clear(stale);
+            }
+            self.stats.missing += skipped;
+            self.stats.received += 1;
+            self.highest = Some(seq);
+            self.mark(seq);
+        } else if highest - seq >= WINDOW {
+            self.stats.reordered += 1;
+            self.stats.received += 1;
+            self.stats.missing = self.stats.missing.saturating_sub(1);
+        } else if self.is_marked(seq) {
+            self.stats.duplicates += 1;
+        } else {
+            self.mark(seq);
+            self.stats.reordered += 1;
+            self.stats.received += 1;
+            self.stats.missing = self.stats.missing.saturating_sub(1);
+        }
+    }
+
+    fn mark(&mut self, seq: u64) {
+        let bit = seq % WINDOW;
+        self.seen[(bit / 64) as usize] |= 1 << (bit % 64);
+    }
+
+    fn clear(&mut self, seq: u64) {
+        let bit = seq % WINDOW;
+        self.seen[(bit / 64) as usize] &= !(1 << (bit % 64));
+    }
+
+    fn is_marked(&self, seq: u64) -> bool {
+        let bit = seq % WINDOW;
+        self.seen[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+    }
+}
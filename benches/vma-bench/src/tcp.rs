@@ -0,0 +1,172 @@
+//! TCP benchmarks: one-way or reflected byte streams and fixed-size request/echo round trips.
+
+use std::io::{Error, ErrorKind};
+use std::net::Shutdown;
+use std::time::Instant;
+
+use vma_socket::common::MsgFlags;
+use vma_socket::framed::FrameTransport;
+use vma_socket::tcp::VmaTcpSocket;
+
+use crate::report::{Direction, Report};
+use crate::{ClientArgs, Mode, ServerArgs};
+
+/// Connect timeout of the client, in nanoseconds.
+const CONNECT_TIMEOUT_NS: u64 = 5_000_000_000;
+/// Receive chunk of throughput receivers.
+const CHUNK: usize = 256 * 1024;
+
+/// Reject the loss mode: TCP delivers in order without loss, so there is nothing to measure.
+fn check_mode(mode: Mode) -> Result<(), String> {
+    match mode {
+        Mode::Loss => Err("loss mode is UDP only".to_string()),
+        _ => Ok(()),
+    }
+}
+
+pub fn server(args: &ServerArgs) -> Result<(), String> {
+    check_mode(args.common.mode)?;
+    args.run(|stream, options| {
+        let mut socket = VmaTcpSocket::with_options(options).map_err(|e| format!("Failed to create socket: {}", e))?;
+        let port = args.common.port(stream);
+        socket
+            .bind(args.bind.as_str(), port)
+            .map_err(|e| format!("Failed to bind {}:{}: {}", args.bind, port, e))?;
+        socket.listen(128).map_err(|e| format!("Failed to listen: {}", e))?;
+        eprintln!("tcp {:?} server listening on {}:{}", args.common.mode, args.bind, port);
+
+        // One connection per stream at a time, like iperf
+        loop {
+            let Some(mut client) = socket.accept(None).map_err(|e| format!("Accept failed: {}", e))? else {
+                continue;
+            };
+            eprintln!("connection from {}", client.address);
+            let result = match args.common.mode {
+                Mode::Throughput => drain(&mut client, args.common.size, args.common.bidir, stream).map(|report| args.common.print(&report)),
+                Mode::PingPong => echo(&mut client, args.common.size),
+                Mode::Loss => unreachable!("rejected by check_mode"),
+            };
+            if let Err(e) = result {
+                eprintln!("connection from {} failed: {}", client.address, e);
+            }
+        }
+    })
+}
+
+/// Read until the client shuts down its side, then report what arrived.
+///
+/// With `bidir` every chunk is written back as it arrives.
+fn drain<S: FrameTransport>(connection: &mut S, size: usize, bidir: bool, stream: usize) -> Result<Report, Error> {
+    let mut buffer = vec![0u8; CHUNK];
+    let mut bytes = 0u64;
+    let mut first = None;
+    loop {
+        match connection.read(&mut buffer, None)? {
+            Some(0) => break,
+            Some(n) => {
+                first.get_or_insert_with(Instant::now);
+                bytes += n as u64;
+                if bidir {
+                    connection.write_all(&buffer[..n])?;
+                }
+            }
+            None => continue,
+        }
+    }
+    let elapsed = first.map(|first| first.elapsed()).unwrap_or_default();
+    Ok(Report::new("tcp", "server", Mode::Throughput, Direction::Receive, stream, size, elapsed, bytes / size as u64))
+}
+
+/// Send every `size`-byte message back until the client disconnects.
+fn echo<S: FrameTransport>(connection: &mut S, size: usize) -> Result<(), Error> {
+    let mut message = vec![0u8; size];
+    while read_exact(connection, &mut message)? {
+        connection.write_all(&message)?;
+    }
+    Ok(())
+}
+
+/// Fill `buffer`, returning `false` on a clean end of stream before the first byte.
+fn read_exact<S: FrameTransport>(connection: &mut S, buffer: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match connection.read(&mut buffer[filled..], None)? {
+            Some(0) if filled == 0 => return Ok(false),
+            Some(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Connection closed mid-message")),
+            Some(n) => filled += n,
+            None => continue,
+        }
+    }
+    Ok(true)
+}
+
+pub fn client(args: &ClientArgs) -> Result<(), String> {
+    check_mode(args.common.mode)?;
+    args.run(|stream, options| {
+        let mut socket = VmaTcpSocket::with_options(options).map_err(|e| format!("Failed to create socket: {}", e))?;
+        let port = args.common.port(stream);
+        let connected = socket
+            .connect(args.host.as_str(), port, Some(CONNECT_TIMEOUT_NS))
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", args.host, port, e))?;
+        if !connected {
+            return Err(format!("Timed out connecting to {}:{}", args.host, port));
+        }
+
+        match args.common.mode {
+            Mode::Throughput => send(&mut socket, args, stream),
+            Mode::PingPong => args.ping_pong("tcp", stream, &mut socket).map(|report| vec![report]),
+            Mode::Loss => unreachable!("rejected by check_mode"),
+        }
+        .map_err(|e| format!("Benchmark failed: {}", e))
+    })
+}
+
+/// Write messages for the test duration, reading the reflected stream back with `--bidir`.
+fn send(socket: &mut VmaTcpSocket, args: &ClientArgs, stream: usize) -> Result<Vec<Report>, Error> {
+    let bidir = args.common.bidir;
+    let mut pacer = args.pacer().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let message = vec![0u8; args.common.size];
+    let mut buffer = vec![0u8; if bidir { CHUNK } else { 0 }];
+    let mut reflected = 0u64;
+    let mut last_reflected = None;
+    let duration = args.duration();
+    let start = Instant::now();
+    let mut sent = 0u64;
+    while start.elapsed() < duration {
+        if let Some(pacer) = pacer.as_mut() {
+            pacer.acquire(message.len());
+        }
+        if !bidir {
+            FrameTransport::write_all(socket, &message)?;
+            sent += 1;
+            continue;
+        }
+
+        // Never block on a full send buffer while the reflection waits to be read
+        let mut written = 0;
+        while written < message.len() {
+            written += socket.send_with_flags(&message[written..], MsgFlags::DONTWAIT)?;
+            let n = socket.recv_with_flags(&mut buffer, None, MsgFlags::DONTWAIT)?;
+            if n > 0 {
+                reflected += n as u64;
+                last_reflected = Some(Instant::now());
+            }
+        }
+        sent += 1;
+    }
+    let elapsed = start.elapsed();
+    socket.shutdown(Shutdown::Write)?;
+
+    let mut reports = vec![Report::new("tcp", "client", Mode::Throughput, Direction::Send, stream, message.len(), elapsed, sent)];
+    if bidir {
+        // The server closes once it has reflected everything
+        while let Some(n) = socket.recv_checked(&mut buffer, None)?.filter(|&n| n > 0) {
+            reflected += n as u64;
+            last_reflected = Some(Instant::now());
+        }
+        let elapsed = last_reflected.map(|last| last - start).unwrap_or_default();
+        let messages = reflected / message.len() as u64;
+        reports.push(Report::new("tcp", "client", Mode::Throughput, Direction::Receive, stream, message.len(), elapsed, messages));
+    }
+    Ok(reports)
+}
@@ -0,0 +1,291 @@
+//! UDP benchmarks: one-way or reflected datagram streams, sequence checking and request/echo
+//! round trips.
+
+use std::time::{Duration, Instant};
+
+use vma_socket::common::MsgFlags;
+use vma_socket::udp::VmaUdpSocket;
+
+use crate::report::{Direction, Interval, Report};
+use crate::sequence::{SequenceStats, SequenceTracker};
+use crate::{ClientArgs, Mode, ServerArgs};
+
+/// Sequence number marking the end of a throughput run.
+const END_MARKER: u64 = u64::MAX;
+/// End markers sent so that at least one survives loss.
+const END_MARKERS: usize = 10;
+/// Receive timeout of the throughput server, so it notices a client that vanished.
+const IDLE_TIMEOUT_NS: u64 = 2_000_000_000;
+/// Wait for reflected datagrams still in flight after a bidirectional run.
+const DRAIN_TIMEOUT_NS: u64 = 200_000_000;
+/// Socket receive buffer of throughput receivers, to absorb bursts.
+const RECV_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+pub fn server(args: &ServerArgs) -> Result<(), String> {
+    args.run(|stream, options| {
+        let mut socket = VmaUdpSocket::with_options(options).map_err(|e| format!("Failed to create socket: {}", e))?;
+        let port = args.common.port(stream);
+        socket
+            .bind(args.bind.as_str(), port)
+            .map_err(|e| format!("Failed to bind {}:{}: {}", args.bind, port, e))?;
+        if let Some(group) = &args.group {
+            socket
+                .join_multicast_v4(group, &args.interface)
+                .map_err(|e| format!("Failed to join {} on {}: {}", group, args.interface, e))?;
+        }
+        eprintln!("udp {:?} server listening on {}:{}", args.common.mode, args.bind, port);
+
+        match args.common.mode {
+            Mode::Throughput => receive(&mut socket, args, stream),
+            Mode::PingPong => echo(&mut socket),
+            Mode::Loss => track(&mut socket, args, stream),
+        }
+    })
+}
+
+/// Count datagrams per run, reporting each run once its end marker (or silence) arrives.
+///
+/// With `--bidir` every datagram is also sent back to its source.
+fn receive(socket: &mut VmaUdpSocket, args: &ServerArgs, stream: usize) -> Result<(), String> {
+    socket
+        .set_recv_buffer_size(RECV_BUFFER_SIZE)
+        .map_err(|e| format!("Failed to set receive buffer: {}", e))?;
+    let mut buffer = vec![0u8; 65536];
+    let mut run: Option<(Instant, Instant)> = None;
+    let (mut received, mut highest_seq, mut size) = (0u64, 0u64, 0usize);
+    loop {
+        let len = if args.common.bidir {
+            match socket.recv_from(&mut buffer, Some(IDLE_TIMEOUT_NS)).map_err(|e| format!("Receive failed: {}", e))? {
+                Some(packet) => {
+                    // Reflection is best effort, like the datagrams themselves
+                    let _ = socket.send_to(&packet.data, packet.src_addr.ip().to_string(), packet.src_addr.port());
+                    packet.data.len()
+                }
+                None => 0,
+            }
+        } else {
+            socket
+                .recv(&mut buffer, Some(IDLE_TIMEOUT_NS))
+                .map_err(|e| format!("Receive failed: {}", e))?
+        };
+        if let Some(seq) = sequence(&buffer[..len]).filter(|&seq| seq != END_MARKER) {
+            let now = Instant::now();
+            run = Some((run.map_or(now, |(first, _)| first), now));
+            received += 1;
+            highest_seq = highest_seq.max(seq);
+            size = len;
+            continue;
+        }
+
+        // End marker or idle timeout: close the current run, if any
+        if let Some((first, last)) = run.take() {
+            let mut report = Report::new("udp", "server", Mode::Throughput, Direction::Receive, stream, size, last - first, received);
+            report.lost = Some((highest_seq + 1).saturating_sub(received));
+            args.common.print(&report);
+            received = 0;
+            highest_seq = 0;
+        }
+    }
+}
+
+/// Classify sequence numbers per run, reporting every interval and at the end of the run.
+fn track(socket: &mut VmaUdpSocket, args: &ServerArgs, stream: usize) -> Result<(), String> {
+    socket
+        .set_recv_buffer_size(RECV_BUFFER_SIZE)
+        .map_err(|e| format!("Failed to set receive buffer: {}", e))?;
+    let interval = Duration::from_secs_f64(args.interval.max(0.001));
+    let mut buffer = vec![0u8; 65536];
+    let mut run: Option<LossRun> = None;
+    loop {
+        let timeout = match &run {
+            Some(run) => run.next_report.saturating_duration_since(Instant::now()).as_nanos() as u64,
+            None => IDLE_TIMEOUT_NS,
+        };
+        let len = socket
+            .recv(&mut buffer, Some(timeout))
+            .map_err(|e| format!("Receive failed: {}", e))?;
+        let now = Instant::now();
+        let seq = sequence(&buffer[..len]);
+        if let Some(seq) = seq.filter(|&seq| seq != END_MARKER) {
+            let run = run.get_or_insert_with(|| LossRun::new(now, interval));
+            run.tracker.record(seq);
+            run.last = now;
+            run.size = len;
+        }
+
+        let Some(current) = run.as_mut() else {
+            continue;
+        };
+        if now >= current.next_report {
+            current.report_interval(args, stream, now);
+        }
+        // End marker or silence: close the run
+        if seq == Some(END_MARKER) || now - current.last >= Duration::from_nanos(IDLE_TIMEOUT_NS) {
+            let mut current = run.take().unwrap();
+            current.report_interval(args, stream, now);
+            let stats = current.tracker.stats();
+            let elapsed = current.last - current.first;
+            let report = Report::new("udp", "server", Mode::Loss, Direction::Receive, stream, current.size, elapsed, stats.received)
+                .with_sequence(&stats);
+            args.common.print(&report);
+        }
+    }
+}
+
+/// Loss-mode state of one run on a stream.
+struct LossRun {
+    tracker: SequenceTracker,
+    first: Instant,
+    last: Instant,
+    size: usize,
+    interval: Duration,
+    next_report: Instant,
+    reported: SequenceStats,
+    reported_at: Instant,
+}
+
+impl LossRun {
+    fn new(first: Instant, interval: Duration) -> Self {
+        LossRun {
+            tracker: SequenceTracker::new(),
+            first,
+            last: first,
+            size: 0,
+            interval,
+            next_report: first + interval,
+            reported: SequenceStats::default(),
+            reported_at: first,
+        }
+    }
+
+    /// Print the counters accumulated since the previous interval report, if any.
+    fn report_interval(&mut self, args: &ServerArgs, stream: usize, now: Instant) {
+        let stats = self.tracker.stats();
+        let delta = stats.since(&self.reported);
+        if delta != SequenceStats::default() {
+            args.common.print_interval(&Interval {
+                protocol: "udp",
+                stream,
+                start_secs: (self.reported_at - self.first).as_secs_f64(),
+                end_secs: (now - self.first).as_secs_f64(),
+                messages: delta.received,
+                lost: delta.missing,
+                reordered: delta.reordered,
+                duplicates: delta.duplicates,
+            });
+        }
+        self.reported = stats;
+        self.reported_at = now;
+        self.next_report = now + self.interval;
+    }
+}
+
+/// Send every datagram back to where it came from.
+fn echo(socket: &mut VmaUdpSocket) -> Result<(), String> {
+    let mut buffer = vec![0u8; 65536];
+    loop {
+        let Some(packet) = socket.recv_from(&mut buffer, None).map_err(|e| format!("Receive failed: {}", e))? else {
+            continue;
+        };
+        socket
+            .send_to(&packet.data, packet.src_addr.ip().to_string(), packet.src_addr.port())
+            .map_err(|e| format!("Echo failed: {}", e))?;
+    }
+}
+
+pub fn client(args: &ClientArgs) -> Result<(), String> {
+    args.run(|stream, options| {
+        let mut socket = VmaUdpSocket::with_options(options).map_err(|e| format!("Failed to create socket: {}", e))?;
+        let port = args.common.port(stream);
+        socket
+            .connect(args.host.as_str(), port)
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", args.host, port, e))?;
+
+        match args.common.mode {
+            Mode::Throughput | Mode::Loss => send(&mut socket, args, stream),
+            Mode::PingPong => {
+                let report = args.ping_pong("udp", stream, &mut socket).map_err(|e| format!("Benchmark failed: {}", e))?;
+                Ok(vec![report])
+            }
+        }
+    })
+}
+
+/// Send sequence-stamped datagrams for the test duration, counting reflections with `--bidir`.
+fn send(socket: &mut VmaUdpSocket, args: &ClientArgs, stream: usize) -> Result<Vec<Report>, String> {
+    let bidir = args.common.bidir;
+    if bidir {
+        socket
+            .set_recv_buffer_size(RECV_BUFFER_SIZE)
+            .map_err(|e| format!("Failed to set receive buffer: {}", e))?;
+    }
+    let mut pacer = args.pacer()?;
+    let mut message = vec![0u8; args.common.size];
+    let mut buffer = vec![0u8; 65536];
+    let mut reflected = Reflected::default();
+    let duration = args.duration();
+    let start = Instant::now();
+    let mut seq = 0u64;
+    while start.elapsed() < duration {
+        if let Some(pacer) = pacer.as_mut() {
+            pacer.acquire(message.len());
+        }
+        message[..8].copy_from_slice(&seq.to_le_bytes());
+        if socket.send(&message).map_err(|e| format!("Send failed: {}", e))? == message.len() {
+            seq += 1;
+        }
+        if bidir {
+            reflected.drain(socket, &mut buffer, None)?;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    message[..8].copy_from_slice(&END_MARKER.to_le_bytes());
+    for _ in 0..END_MARKERS {
+        socket.send(&message).map_err(|e| format!("Send failed: {}", e))?;
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let mut reports = vec![Report::new("udp", "client", args.common.mode, Direction::Send, stream, message.len(), elapsed, seq)];
+    if bidir {
+        reflected.drain(socket, &mut buffer, Some(DRAIN_TIMEOUT_NS))?;
+        let elapsed = reflected.last.map(|last| last - start).unwrap_or_default();
+        let mut report = Report::new("udp", "client", Mode::Throughput, Direction::Receive, stream, message.len(), elapsed, reflected.count);
+        report.lost = Some(seq.saturating_sub(reflected.count));
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// Reflected datagrams received by a bidirectional client.
+#[derive(Default)]
+struct Reflected {
+    count: u64,
+    last: Option<Instant>,
+}
+
+impl Reflected {
+    /// Count the reflections already queued, or the ones arriving until `linger` nanoseconds pass without one.
+    fn drain(&mut self, socket: &mut VmaUdpSocket, buffer: &mut [u8], linger: Option<u64>) -> Result<(), String> {
+        loop {
+            let len = match linger {
+                Some(linger) => socket.recv(buffer, Some(linger)),
+                None => socket.recv_with_flags(buffer, None, MsgFlags::DONTWAIT),
+            }
+            .map_err(|e| format!("Receive failed: {}", e))?;
+            match sequence(&buffer[..len]) {
+                Some(END_MARKER) => continue,
+                Some(_) => {
+                    self.count += 1;
+                    self.last = Some(Instant::now());
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Read the sequence number at the start of a datagram.
+fn sequence(datagram: &[u8]) -> Option<u64> {
+    datagram.get(..8).map(|seq| u64::from_le_bytes(seq.try_into().unwrap()))
+}
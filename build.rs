@@ -1,3 +1,5 @@
+use std::env;
+use std::fs;
 use std::path::Path;
 
 fn main() {
@@ -11,6 +13,14 @@ fn main() {
     println!("cargo:rerun-if-changed=src/c/tcp_socket.h");
     println!("cargo:rerun-if-changed=src/c/vma_common.c");
     println!("cargo:rerun-if-changed=src/c/vma_common.h");
+    println!("cargo:rerun-if-changed=src/c/netmon.c");
+    println!("cargo:rerun-if-changed=src/c/netmon.h");
+    println!("cargo:rerun-if-changed=src/c/hw.c");
+    println!("cargo:rerun-if-changed=src/c/hw.h");
+    println!("cargo:rerun-if-changed=src/c/raw_socket.c");
+    println!("cargo:rerun-if-changed=src/c/raw_socket.h");
+    println!("cargo:rerun-if-changed=src/c/vma_layout.c");
+    println!("cargo:rerun-if-changed=src/c/vma_layout.h");
     
     // Basic build configuration
     let mut common_build = cc::Build::new();
@@ -39,6 +49,83 @@ fn main() {
         .file(c_src_path.join("tcp_socket.c"))
         .compile("tcp_socket");
     
+    // Compile link monitoring code
+    common_build
+        .clone()
+        .file(c_src_path.join("netmon.c"))
+        .compile("netmon");
+    
+    // Compile NIC capability queries
+    common_build
+        .clone()
+        .file(c_src_path.join("hw.c"))
+        .compile("hw");
+    
+    // Compile packet socket code
+    common_build
+        .clone()
+        .file(c_src_path.join("raw_socket.c"))
+        .compile("raw_socket");
+    
+    // Check the shared structs against vma_layout.h on the C side...
+    common_build
+        .clone()
+        .file(c_src_path.join("vma_layout.c"))
+        .compile("vma_layout");
+    
+    // ...and hand the same table to the Rust side (src/layout.rs)
+    generate_layout(&c_src_path.join("vma_layout.h"));
+    
+    // C header for the `extern "C"` API (src/capi.rs)
+    #[cfg(feature = "capi")]
+    generate_capi_header();
+    
     // Link VMA library - needed for symbols
     println!("cargo:rustc-link-lib=vma");
-}
\ No newline at end of file
+}
+
+/// Turn the `VMA_LAYOUT_<type>__<item> <value>` defines into one Rust module per type.
+fn generate_layout(header: &Path) {
+    let table = fs::read_to_string(header).expect("Failed to read vma_layout.h");
+    let mut modules: Vec<(String, Vec<String>)> = Vec::new();
+    for line in table.lines() {
+        let mut words = line.split_whitespace();
+        let (Some("#define"), Some(name), Some(value)) = (words.next(), words.next(), words.next()) else {
+            continue;
+        };
+        let Some((ty, item)) = name.strip_prefix("VMA_LAYOUT_").and_then(|name| name.split_once("__")) else {
+            continue;
+        };
+        let constant = format!("    pub const {}: usize = {};", item, value);
+        match modules.iter_mut().find(|(name, _)| name == ty) {
+            Some((_, constants)) => constants.push(constant),
+            None => modules.push((ty.to_string(), vec![constant])),
+        }
+    }
+    
+    let mut source = String::from("// Generated by build.rs from src/c/vma_layout.h\n");
+    for (ty, constants) in modules {
+        source.push_str(&format!("\npub mod {} {{\n{}\n}}\n", ty, constants.join("\n")));
+    }
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    fs::write(Path::new(&out_dir).join("vma_layout.rs"), source).expect("Failed to write vma_layout.rs");
+}
+
+/// Write `vma_socket.h` to OUT_DIR with cbindgen from the `capi` module (see cbindgen.toml).
+///
+/// The copy shipped in `include/` is checked against it by the `capi` tests.
+#[cfg(feature = "capi")]
+fn generate_capi_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("Failed to read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_src(Path::new(&crate_dir).join("src/capi.rs"))
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate the C API header")
+        .write_to_file(Path::new(&env::var("OUT_DIR").expect("OUT_DIR not set")).join("vma_socket.h"));
+}
@@ -3,18 +3,15 @@ use std::process;
 use std::thread;
 use vma_socket::udp::VmaUdpSocket;
 use vma_socket::tcp::VmaTcpSocket;
-use vma_socket::common::VmaOptions;
+use vma_socket::common::{self, clock, VmaOptions};
 
 const ITERATIONS: usize = 10000;
 const BUFFER_SIZE: usize = 4096;
 
-use flashlog::get_unix_nano;
-
 fn main() {
     let args: Vec<String> = env::args().collect();
     env::set_var("VMA_INTERRUPT_AFFINITY", "0");
     env::set_var("VMA_THREAD_AFFINITY", "0");
-    env::set_var("VMA_RX_POLL_OS_RATIO", "1000000");
     
     if args.len() < 2 {
         println!("Usage: {} [udp|tcp] [recv|send|both]", args[0]);
@@ -71,8 +68,7 @@ fn benchmark_udp_recv() {
     benchmark_udp_recv_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_udp_recv_with_options("Non-Polling", non_polling_options);
 }
 
@@ -81,7 +77,8 @@ fn benchmark_udp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     println!("\n--- {} Configuration ---", config_name);
     options.clear_cores();
     options.add_core(0).expect("Failed to set CPU core");
-    let mut socket = match VmaUdpSocket::with_options(options.clone()) {
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
+    let mut socket = match VmaUdpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create UDP socket: {}", e);
@@ -99,32 +96,29 @@ fn benchmark_udp_recv_with_options(config_name: &str, mut options: VmaOptions) {
 
     let task = thread::spawn(move || {
         // Set CPU core
-        core_affinity::set_for_current(core_affinity::CoreId { id: 0});
+        common::pin_current_thread(&options).expect("Failed to pin thread");
 
         let mut buffer = vec![0u8; BUFFER_SIZE];
+        socket.set_nonblocking(true);
 
         println!("Warming up...");
         // Warmup
         for _ in 0..1000 {
-            let _ = if options.use_polling {
-                socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for polling
-            } else {
-                socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for non-polling
-            };
+            let _ = socket.recv(&mut buffer, None);
         }
 
         println!("Starting UDP recv benchmark ({} iterations)...", ITERATIONS);
         
         // Single timestamp at the beginning
-        let start_time = get_unix_nano();
+        let start_time = clock::now_nanos();
         
         // recv benchmark (empty socket polling)
         for _i in 0..ITERATIONS {
-            socket.recv(&mut buffer, Some(0)).expect("Failed to receive data");
+            let _ = socket.recv(&mut buffer, None);
         }
 
         // Single timestamp at the end
-        let end_time = get_unix_nano();
+        let end_time = clock::now_nanos();
         
         let total_time_ns = end_time - start_time;
         let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -147,16 +141,16 @@ fn benchmark_udp_send() {
     benchmark_udp_send_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_udp_send_with_options("Non-Polling", non_polling_options);
 }
 
 fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("\n--- {} Configuration ---", config_name);
-    core_affinity::set_for_current(core_affinity::CoreId { id: 0 });
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
+    common::pin_current_thread(&options).expect("Failed to pin thread");
     
     // Create socket
     let mut socket = match VmaUdpSocket::with_options(options) {
@@ -181,7 +175,7 @@ fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("Starting UDP send benchmark ({} iterations)...", ITERATIONS);
     
     // Single timestamp at the beginning
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     
     // send benchmark
     for _i in 0..ITERATIONS {
@@ -189,7 +183,7 @@ fn benchmark_udp_send_with_options(config_name: &str, mut options: VmaOptions) {
     }
 
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -207,8 +201,7 @@ fn benchmark_tcp_recv() {
     benchmark_tcp_recv_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_tcp_recv_with_options("Non-Polling", non_polling_options);
 }
 
@@ -217,9 +210,10 @@ fn benchmark_tcp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
     
     // Create TCP socket (not connected, just for polling benchmark)
-    let mut socket = match VmaTcpSocket::with_options(options.clone()) {
+    let mut socket = match VmaTcpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create TCP socket: {}", e);
@@ -228,33 +222,26 @@ fn benchmark_tcp_recv_with_options(config_name: &str, mut options: VmaOptions) {
     };
 
     let mut buffer = vec![0u8; BUFFER_SIZE];
+    socket.set_nonblocking(true);
 
     println!("Warming up...");
     // Warmup
     for _ in 0..1000 {
-        let _ = if options.use_polling {
-            socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for polling
-        } else {
-            socket.recv(&mut buffer, Some(1_000_000)) // 1ms timeout for non-polling
-        };
+        let _ = socket.recv(&mut buffer, None);
     }
 
     println!("Starting TCP socket polling benchmark ({} iterations)...", ITERATIONS);
     
     // Single timestamp at the beginning
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     
     // recv benchmark (empty socket polling)
     for _i in 0..ITERATIONS {
-        let _ = if options.use_polling {
-            socket.recv(&mut buffer, Some(0)) // 0 = non-blocking for polling
-        } else {
-            socket.recv(&mut buffer, Some(0)) // 1ms timeout for non-polling
-        };
+        let _ = socket.recv(&mut buffer, None);
     }
 
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
@@ -272,8 +259,7 @@ fn benchmark_tcp_send() {
     benchmark_tcp_send_with_options("High Throughput", VmaOptions::high_throughput());
     
     // Non-polling configuration
-    let mut non_polling_options = VmaOptions::default();
-    non_polling_options.use_polling = false;
+    let non_polling_options = VmaOptions { use_polling: false, ..VmaOptions::default() };
     benchmark_tcp_send_with_options("Non-Polling", non_polling_options);
 }
 
@@ -282,9 +268,10 @@ fn benchmark_tcp_send_with_options(config_name: &str, mut options: VmaOptions) {
     
     // Set CPU core
     options.add_core(0).expect("Failed to set CPU core");
+    options.rx_poll_os_ratio = 1_000_000; // Rarely poll the OS stack
     
     // Create TCP socket
-    let mut socket = match VmaTcpSocket::with_options(options.clone()) {
+    let mut socket = match VmaTcpSocket::with_options(options) {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to create TCP socket: {}", e);
@@ -306,13 +293,13 @@ fn benchmark_tcp_send_with_options(config_name: &str, mut options: VmaOptions) {
     println!("Starting TCP send benchmark ({} iterations)...", ITERATIONS);
     // Single timestamp at the beginning
 
-    let start_time = get_unix_nano();
+    let start_time = clock::now_nanos();
     // send benchmark
     for _i in 0..ITERATIONS {
         let _ = socket.send(test_data);
     }
     // Single timestamp at the end
-    let end_time = get_unix_nano();
+    let end_time = clock::now_nanos();
     let total_time_ns = end_time - start_time;
     let avg_time_ns = total_time_ns / ITERATIONS as u64;
     let avg_time_us = avg_time_ns as f64 / 1000.0;
@@ -2,13 +2,13 @@ use std::env;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use vma_socket::pacing::{Pacer, Rate};
 use vma_socket::udp::VmaUdpSocket;
 use vma_socket::common::VmaOptions;
 
 const BUFFER_SIZE: usize = 4096;
 const TEST_DURATION: u64 = 10; // Test duration in seconds
+const SEND_RATE_PPS: u64 = 100_000; // Client send rate in packets per second
 
 fn main() {
     // Parse command line arguments
@@ -127,6 +127,9 @@ fn run_client(running: Arc<AtomicBool>, ip: &str, port: u16) {
         return;
     }
 
+    // Limit the send rate precisely instead of sleeping between packets
+    socket.set_pacer(Some(Pacer::new(Rate::PacketsPerSec(SEND_RATE_PPS)).expect("Invalid send rate")));
+
     println!("UDP client sending data to {}:{}", ip, port);
 
     // Create test data
@@ -152,9 +155,6 @@ fn run_client(running: Arc<AtomicBool>, ip: &str, port: u16) {
                 break;
             }
         }
-
-        // Short delay to limit speed
-        thread::sleep(Duration::from_micros(10));
     }
 
     let elapsed = start_time.elapsed().as_secs_f64();
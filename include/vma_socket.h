@@ -0,0 +1,305 @@
+/**
+ * vma_socket.h - C API of the vma-socket crate
+ *
+ * Generated by cbindgen from src/capi.rs when building with the `capi` feature.
+ */
+
+#ifndef VMA_SOCKET_H
+#define VMA_SOCKET_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Opaque handles; create with the *_new / vma_tcp_accept functions, release with *_free
+typedef struct vma_udp_socket_t vma_udp_socket_t;
+typedef struct vma_tcp_socket_t vma_tcp_socket_t;
+typedef struct vma_tcp_client_t vma_tcp_client_t;
+
+/**
+ * Traffic counters of a socket, as returned by `vma_udp_stats` and `vma_tcp_stats`.
+ *
+ * Kept apart from `common::SocketStats` so the C layout only changes when this API does;
+ * fields are only ever appended.
+ */
+typedef struct {
+  /**
+   * Number of received packets
+   */
+  uint64_t rx_packets;
+  /**
+   * Number of transmitted packets
+   */
+  uint64_t tx_packets;
+  /**
+   * Number of received bytes
+   */
+  uint64_t rx_bytes;
+  /**
+   * Number of transmitted bytes
+   */
+  uint64_t tx_bytes;
+  /**
+   * Receive calls that failed with an error
+   */
+  uint64_t rx_errors;
+  /**
+   * Send calls that failed with an error
+   */
+  uint64_t tx_errors;
+  /**
+   * Datagrams that did not fit in the receive buffer (UDP only)
+   */
+  uint64_t rx_truncated;
+  /**
+   * Receive calls that returned `EAGAIN`/`EWOULDBLOCK`
+   */
+  uint64_t rx_eagain;
+  /**
+   * Send calls that returned `EAGAIN`/`EWOULDBLOCK`
+   */
+  uint64_t tx_eagain;
+  /**
+   * Successful reconnects (TCP only)
+   */
+  uint64_t reconnects;
+  /**
+   * Time of the last successful receive in nanoseconds since the epoch (0 if none)
+   */
+  uint64_t last_rx_ns;
+  /**
+   * Time of the last successful send in nanoseconds since the epoch (0 if none)
+   */
+  uint64_t last_tx_ns;
+} vma_socket_stats_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a UDP socket with the given options JSON (NULL for the defaults).
+ *
+ * # Safety
+ * `options_json` must be NULL or a NUL-terminated string; `socket` must be writable.
+ */
+int vma_udp_socket_new(const char *options_json, vma_udp_socket_t **socket);
+
+/**
+ * Close and free a UDP socket (NULL is ignored).
+ *
+ * # Safety
+ * `socket` must come from `vma_udp_socket_new` and not be used afterwards.
+ */
+void vma_udp_socket_free(vma_udp_socket_t *socket);
+
+/**
+ * Get the file descriptor of a UDP socket (-1 for NULL).
+ *
+ * # Safety
+ * `socket` must be NULL or a live handle.
+ */
+int vma_udp_fd(const vma_udp_socket_t *socket);
+
+/**
+ * Bind a UDP socket to a local IPv4 address and port.
+ *
+ * # Safety
+ * `socket` must be a live handle and `ip` a NUL-terminated string.
+ */
+int vma_udp_bind(vma_udp_socket_t *socket, const char *ip, uint16_t port);
+
+/**
+ * Set the default destination of a UDP socket.
+ *
+ * # Safety
+ * `socket` must be a live handle and `ip` a NUL-terminated string.
+ */
+int vma_udp_connect(vma_udp_socket_t *socket, const char *ip, uint16_t port);
+
+/**
+ * Send a datagram to the default destination.
+ *
+ * # Safety
+ * `socket` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+ */
+int vma_udp_send(vma_udp_socket_t *socket, const uint8_t *data, size_t len, size_t *sent);
+
+/**
+ * Send a datagram to an IPv4 address and port.
+ *
+ * # Safety
+ * `socket` must be a live handle, `data` readable for `len` bytes, `ip` a
+ * NUL-terminated string and `sent` writable.
+ */
+int vma_udp_send_to(vma_udp_socket_t *socket, const uint8_t *data, size_t len, const char *ip, uint16_t port, size_t *sent);
+
+/**
+ * Receive a datagram, waiting up to `timeout_ns` (`-ETIMEDOUT` when none arrived).
+ *
+ * # Safety
+ * `socket` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+ */
+int vma_udp_recv(vma_udp_socket_t *socket, uint8_t *buffer, size_t len, int64_t timeout_ns, size_t *received);
+
+/**
+ * Receive a datagram and its source address (`src_ip` and `src_port` may be NULL).
+ *
+ * # Safety
+ * As for `vma_udp_recv`; `src_ip` and `src_port` must be NULL or writable.
+ */
+int vma_udp_recv_from(vma_udp_socket_t *socket, uint8_t *buffer, size_t len, int64_t timeout_ns, size_t *received, uint32_t *src_ip, uint16_t *src_port);
+
+/**
+ * Get the traffic counters of a UDP socket.
+ *
+ * # Safety
+ * `socket` must be a live handle and `stats` writable.
+ */
+int vma_udp_stats(vma_udp_socket_t *socket, vma_socket_stats_t *stats);
+
+/**
+ * Create a TCP socket with the given options JSON (NULL for the defaults).
+ *
+ * # Safety
+ * `options_json` must be NULL or a NUL-terminated string; `socket` must be writable.
+ */
+int vma_tcp_socket_new(const char *options_json, vma_tcp_socket_t **socket);
+
+/**
+ * Close and free a TCP socket (NULL is ignored).
+ *
+ * Clients accepted from it stay open until they are freed themselves.
+ *
+ * # Safety
+ * `socket` must come from `vma_tcp_socket_new` and not be used afterwards.
+ */
+void vma_tcp_socket_free(vma_tcp_socket_t *socket);
+
+/**
+ * Get the file descriptor of a TCP socket (-1 for NULL).
+ *
+ * # Safety
+ * `socket` must be NULL or a live handle.
+ */
+int vma_tcp_fd(const vma_tcp_socket_t *socket);
+
+/**
+ * Bind a TCP socket to a local IPv4 address and port.
+ *
+ * # Safety
+ * `socket` must be a live handle and `ip` a NUL-terminated string.
+ */
+int vma_tcp_bind(vma_tcp_socket_t *socket, const char *ip, uint16_t port);
+
+/**
+ * Start listening for connections.
+ *
+ * # Safety
+ * `socket` must be a live handle.
+ */
+int vma_tcp_listen(vma_tcp_socket_t *socket, int backlog);
+
+/**
+ * Accept a connection, waiting up to `timeout_ns` (`-ETIMEDOUT` when none arrived).
+ *
+ * Release the client with `vma_tcp_client_free`.
+ *
+ * # Safety
+ * `socket` must be a live handle and `client` writable.
+ */
+int vma_tcp_accept(vma_tcp_socket_t *socket, int64_t timeout_ns, vma_tcp_client_t **client);
+
+/**
+ * Connect to a server, waiting up to `timeout_ns` (`-ETIMEDOUT` when not connected in time).
+ *
+ * # Safety
+ * `socket` must be a live handle and `ip` a NUL-terminated string.
+ */
+int vma_tcp_connect(vma_tcp_socket_t *socket, const char *ip, uint16_t port, int64_t timeout_ns);
+
+/**
+ * Check whether the socket is connected.
+ *
+ * # Safety
+ * `socket` must be NULL or a live handle.
+ */
+bool vma_tcp_is_connected(vma_tcp_socket_t *socket);
+
+/**
+ * Send on a connected socket (`*sent` may be 0 when the send buffer is full).
+ *
+ * # Safety
+ * `socket` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+ */
+int vma_tcp_send(vma_tcp_socket_t *socket, const uint8_t *data, size_t len, size_t *sent);
+
+/**
+ * Receive from a connected socket (`*received == 0` once the peer closed the connection).
+ *
+ * # Safety
+ * `socket` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+ */
+int vma_tcp_recv(vma_tcp_socket_t *socket, uint8_t *buffer, size_t len, int64_t timeout_ns, size_t *received);
+
+/**
+ * Enable or disable TCP_NODELAY (kept across reconnects).
+ *
+ * # Safety
+ * `socket` must be a live handle.
+ */
+int vma_tcp_set_nodelay(vma_tcp_socket_t *socket, bool nodelay);
+
+/**
+ * Get the traffic counters of a TCP socket.
+ *
+ * # Safety
+ * `socket` must be a live handle and `stats` writable.
+ */
+int vma_tcp_stats(vma_tcp_socket_t *socket, vma_socket_stats_t *stats);
+
+/**
+ * Close and free an accepted client (NULL is ignored).
+ *
+ * # Safety
+ * `client` must come from `vma_tcp_accept` and not be used afterwards.
+ */
+void vma_tcp_client_free(vma_tcp_client_t *client);
+
+/**
+ * Get the file descriptor of an accepted client (-1 for NULL).
+ *
+ * # Safety
+ * `client` must be NULL or a live handle.
+ */
+int vma_tcp_client_fd(const vma_tcp_client_t *client);
+
+/**
+ * Get the remote address of an accepted client (`ip` and `port` may be NULL).
+ *
+ * # Safety
+ * `client` must be a live handle; `ip` and `port` must be NULL or writable.
+ */
+int vma_tcp_client_peer(const vma_tcp_client_t *client, uint32_t *ip, uint16_t *port);
+
+/**
+ * Send to an accepted client.
+ *
+ * # Safety
+ * `client` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+ */
+int vma_tcp_client_send(vma_tcp_client_t *client, const uint8_t *data, size_t len, size_t *sent);
+
+/**
+ * Receive from an accepted client (`*received == 0` once the client closed the connection).
+ *
+ * # Safety
+ * `client` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+ */
+int vma_tcp_client_recv(vma_tcp_client_t *client, uint8_t *buffer, size_t len, int64_t timeout_ns, size_t *received);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* VMA_SOCKET_H */
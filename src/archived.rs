@@ -0,0 +1,107 @@
+//! Zero-copy access to `rkyv`-archived messages on the receive path.
+//!
+//! Enabled with the `rkyv` feature. An archived message is read in place: after the
+//! datagram lands in the receive buffer, it is validated once and then used as
+//! `&T::Archived` directly, with no copy out of the buffer and no allocation for strings
+//! or vectors inside the message.
+//!
+//! Archived data must be aligned, so datagrams are received into an [`ArchiveBuffer`]
+//! (16-byte aligned) and one buffer is reused across receives. [`access`] validates bytes
+//! obtained any other way, as long as they are aligned.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use rkyv::{Archive, Deserialize, Serialize};
+//! use vma_socket::archived::ArchiveBuffer;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! #[derive(Archive, Serialize, Deserialize)]
+//! struct Quote {
+//!     symbol: String,
+//!     bid: u64,
+//!     ask: u64,
+//! }
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//!
+//! let mut buffer = ArchiveBuffer::with_capacity(1500);
+//! while let Some(quote) = socket.recv_archived::<Quote>(&mut buffer, Some(1_000_000_000)).unwrap() {
+//!     // `quote` borrows `buffer`: no memcpy, no allocation
+//!     println!("{} {}/{}", quote.symbol, quote.bid, quote.ask);
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind};
+
+use rkyv::api::high::HighValidator;
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor;
+use rkyv::{Archive, Portable};
+
+use crate::udp::VmaUdpSocket;
+
+/// Aligned receive buffer for archived messages.
+pub type ArchiveBuffer = rkyv::util::AlignedVec<16>;
+
+/// Validate `bytes` as an archived `T` and return a view over them.
+///
+/// Fails with `ErrorKind::InvalidData` when the bytes are not a valid archive of `T`
+/// (including when they are misaligned for it).
+pub fn access<T>(bytes: &[u8]) -> Result<&T::Archived, Error>
+where
+    T: Archive,
+    T::Archived: Portable + for<'a> CheckBytes<HighValidator<'a, rancor::Error>>,
+{
+    rkyv::access::<T::Archived, rancor::Error>(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+impl VmaUdpSocket {
+    /// Receive one datagram into `buffer` and return it validated as an archived `T`.
+    ///
+    /// The datagram may use all of `buffer`'s capacity; the view borrows `buffer` until the
+    /// next receive into it. Returns `Ok(None)` on timeout. A datagram larger than the
+    /// capacity, or one that fails validation, fails with `ErrorKind::InvalidData`.
+    pub fn recv_archived<'a, T>(&mut self, buffer: &'a mut ArchiveBuffer, timeout_nano: Option<u64>) -> Result<Option<&'a T::Archived>, Error>
+    where
+        T: Archive,
+        T::Archived: Portable + for<'b> CheckBytes<HighValidator<'b, rancor::Error>>,
+    {
+        // Expose the whole allocation without reallocating (and so moving) the buffer
+        buffer.resize(buffer.capacity(), 0);
+        let received = self.recv_full(buffer.as_mut_slice(), timeout_nano);
+        let copied = received.as_ref().ok().copied().flatten().map_or(0, |length| length.copied);
+        buffer.resize(copied, 0);
+
+        let Some(length) = received? else { return Ok(None) };
+        if length.is_truncated() {
+            return Err(Error::new(ErrorKind::InvalidData, "Message truncated to the receive buffer"));
+        }
+        access::<T>(buffer.as_slice()).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Archive, rkyv::Serialize)]
+    struct Quote {
+        symbol: String,
+        levels: Vec<u64>,
+    }
+
+    #[test]
+    fn test_access() {
+        let quote = Quote { symbol: "AAPL".into(), levels: vec![101, 102] };
+        let bytes = rkyv::to_bytes::<rancor::Error>(&quote).unwrap();
+
+        let archived = access::<Quote>(&bytes).unwrap();
+        assert_eq!(archived.symbol.as_str(), "AAPL");
+        assert_eq!(archived.levels.iter().map(|level| level.to_native()).collect::<Vec<_>>(), [101, 102]);
+
+        let err = access::<Quote>(&bytes[..bytes.len() - 1]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
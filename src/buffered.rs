@@ -0,0 +1,316 @@
+//! Buffered reading and writing over TCP with hugepage-backed buffers.
+//!
+//! `std::io::BufReader`/`BufWriter` stage data in unaligned heap buffers and flush on
+//! their own schedule. [`VmaBufReader`] and [`VmaBufWriter`] keep their buffer in a
+//! [`HugeBuf`] instead: page-aligned (so every cache line is whole), prefaulted, and
+//! backed by hugepages when any are reserved. The writer only sends when its buffer is
+//! full or on an explicit [`flush_now`](VmaBufWriter::flush_now), so a batch of small
+//! writes goes out in one send at the moment the caller picks.
+//!
+//! Both run over any [`FrameTransport`]: a connected [`VmaTcpSocket`](crate::tcp::VmaTcpSocket),
+//! an accepted [`Client`](crate::tcp::Client) or a [`MockTransport`](crate::mock::MockTransport).
+//! They also implement `std::io::BufRead` and `std::io::Write`, which block without a timeout.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::buffered::{VmaBufReader, VmaBufWriter};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let mut writer = VmaBufWriter::new(socket).unwrap();
+//! writer.write(b"NEW AAPL 100 @ 187.10\n").unwrap();
+//! writer.write(b"NEW MSFT 50 @ 402.55\n").unwrap();
+//! writer.flush_now().unwrap(); // both orders in one send
+//!
+//! let mut reader = VmaBufReader::new(writer.into_inner().unwrap()).unwrap();
+//! let mut header = [0u8; 8];
+//! if reader.read_exact(&mut header, Some(100_000_000)).unwrap() {
+//!     println!("header {:?}", header);
+//! }
+//! ```
+
+use std::io::{self, BufRead, Error, ErrorKind, Read, Write};
+
+use crate::common::HugeBuf;
+use crate::framed::FrameTransport;
+
+/// Default buffer capacity of the readers and writers (64 KiB).
+pub const DEFAULT_BUF_CAPACITY: usize = 64 * 1024;
+
+/// Reader refilling a hugepage-backed buffer from a byte stream.
+#[derive(Debug)]
+pub struct VmaBufReader<T> {
+    inner: T,
+    buf: HugeBuf,
+    pos: usize,
+    filled: usize,
+}
+
+impl<T: FrameTransport> VmaBufReader<T> {
+    /// Wrap `inner` with a buffer of [`DEFAULT_BUF_CAPACITY`] bytes.
+    pub fn new(inner: T) -> Result<Self, Error> {
+        Self::with_capacity(DEFAULT_BUF_CAPACITY, inner)
+    }
+
+    /// Wrap `inner` with a buffer of `capacity` bytes, from hugepages when available.
+    pub fn with_capacity(capacity: usize, inner: T) -> Result<Self, Error> {
+        Ok(VmaBufReader {
+            inner,
+            buf: HugeBuf::new(capacity)?,
+            pos: 0,
+            filled: 0,
+        })
+    }
+
+    /// Fill the buffer if it is empty, waiting up to `timeout` nanoseconds.
+    ///
+    /// Returns the buffered bytes, `Ok(None)` on timeout and an empty slice at end-of-stream.
+    pub fn fill(&mut self, timeout: Option<u64>) -> Result<Option<&[u8]>, Error> {
+        if self.pos == self.filled {
+            match self.inner.read(&mut self.buf, timeout)? {
+                Some(bytes) => {
+                    self.pos = 0;
+                    self.filled = bytes;
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(&self.buf[self.pos..self.filled]))
+    }
+
+    /// Read into `buffer`, waiting up to `timeout` nanoseconds if nothing is buffered.
+    ///
+    /// Returns `Ok(None)` on timeout and `Ok(Some(0))` at end-of-stream. Reads at least
+    /// as large as the buffer bypass it.
+    pub fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        if self.pos == self.filled && buffer.len() >= self.buf.len() {
+            return self.inner.read(buffer, timeout);
+        }
+        let Some(available) = self.fill(timeout)? else {
+            return Ok(None);
+        };
+        let bytes = available.len().min(buffer.len());
+        buffer[..bytes].copy_from_slice(&available[..bytes]);
+        self.pos += bytes;
+        Ok(Some(bytes))
+    }
+
+    /// Fill all of `buffer`, e.g. a fixed-size header, waiting up to `timeout` for each refill.
+    ///
+    /// Returns `Ok(false)` if the timeout expires before any byte arrived; a timeout in the
+    /// middle of `buffer` or end-of-stream fails with `ErrorKind::UnexpectedEof` or
+    /// `ErrorKind::TimedOut`.
+    pub fn read_exact(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<bool, Error> {
+        let mut done = 0;
+        while done < buffer.len() {
+            match self.read(&mut buffer[done..], timeout)? {
+                Some(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Stream ended inside a read")),
+                Some(bytes) => done += bytes,
+                None if done == 0 => return Ok(false),
+                None => return Err(Error::new(ErrorKind::TimedOut, "Timed out inside a read")),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Get the bytes received but not read yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Get the buffer capacity in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Check whether the buffer is backed by hugepages.
+    pub fn is_hugepage(&self) -> bool {
+        self.buf.is_hugepage()
+    }
+
+    /// Get a reference to the underlying transport.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying transport.
+    ///
+    /// Reading from it directly skips the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport, discarding the buffered bytes.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: FrameTransport> Read for VmaBufReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(VmaBufReader::read(self, buf, None)?.unwrap_or(0))
+    }
+}
+
+impl<T: FrameTransport> BufRead for VmaBufReader<T> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.fill(None)?.unwrap_or(&[]))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+/// Writer collecting small writes in a hugepage-backed buffer until it is full or flushed.
+#[derive(Debug)]
+pub struct VmaBufWriter<T: FrameTransport> {
+    inner: Option<T>,
+    buf: HugeBuf,
+    len: usize,
+}
+
+impl<T: FrameTransport> VmaBufWriter<T> {
+    /// Wrap `inner` with a buffer of [`DEFAULT_BUF_CAPACITY`] bytes.
+    pub fn new(inner: T) -> Result<Self, Error> {
+        Self::with_capacity(DEFAULT_BUF_CAPACITY, inner)
+    }
+
+    /// Wrap `inner` with a buffer of `capacity` bytes, from hugepages when available.
+    pub fn with_capacity(capacity: usize, inner: T) -> Result<Self, Error> {
+        Ok(VmaBufWriter {
+            inner: Some(inner),
+            buf: HugeBuf::new(capacity)?,
+            len: 0,
+        })
+    }
+
+    /// Append `data` to the buffer, sending the buffer first if `data` does not fit.
+    ///
+    /// Data at least as large as the buffer is sent directly after the buffered bytes.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if self.len + data.len() > self.buf.len() {
+            self.flush_now()?;
+        }
+        if data.len() >= self.buf.len() {
+            return self.transport().write_all(data);
+        }
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+        Ok(())
+    }
+
+    /// Send the buffered bytes now, waiting for room in the send buffer as needed.
+    pub fn flush_now(&mut self) -> Result<(), Error> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let len = self.len;
+        // Dropped even if the send fails, since part of it may be on the wire
+        self.len = 0;
+        let transport = self.inner.as_mut().expect("transport present until into_inner");
+        transport.write_all(&self.buf[..len])
+    }
+
+    /// Get the bytes written but not sent yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Get the buffer capacity in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Check whether the buffer is backed by hugepages.
+    pub fn is_hugepage(&self) -> bool {
+        self.buf.is_hugepage()
+    }
+
+    /// Get a reference to the underlying transport.
+    pub fn get_ref(&self) -> &T {
+        self.inner.as_ref().expect("transport present until into_inner")
+    }
+
+    /// Get a mutable reference to the underlying transport.
+    ///
+    /// Writing to it directly goes out ahead of the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.transport()
+    }
+
+    /// Send the buffered bytes and unwrap the transport.
+    pub fn into_inner(mut self) -> Result<T, Error> {
+        self.flush_now()?;
+        Ok(self.inner.take().expect("transport present until into_inner"))
+    }
+
+    fn transport(&mut self) -> &mut T {
+        self.inner.as_mut().expect("transport present until into_inner")
+    }
+}
+
+impl<T: FrameTransport> Write for VmaBufWriter<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        VmaBufWriter::write(self, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_now()
+    }
+}
+
+impl<T: FrameTransport> Drop for VmaBufWriter<T> {
+    /// Send the buffered bytes, ignoring errors (call `flush_now()` to see them).
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush_now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn test_buffered_round_trip() {
+        let (client, server) = MockTransport::stream_pair();
+        let mut writer = VmaBufWriter::with_capacity(16, client).unwrap();
+        let mut reader = VmaBufReader::with_capacity(8, server).unwrap();
+        assert_eq!((writer.capacity(), reader.capacity()), (16, 8));
+
+        // Small writes stay buffered until flushed
+        writer.write(b"abc").unwrap();
+        writer.write(b"defg").unwrap();
+        assert_eq!(writer.buffer(), b"abcdefg");
+        assert_eq!(reader.read(&mut [0u8; 4], Some(0)).unwrap(), None);
+        writer.flush_now().unwrap();
+        assert!(writer.buffer().is_empty());
+
+        let mut header = [0u8; 5];
+        assert!(reader.read_exact(&mut header, Some(1_000_000)).unwrap());
+        assert_eq!(&header, b"abcde");
+        assert_eq!(reader.buffer(), b"fg");
+
+        // A write larger than the buffer goes out directly, after the buffered bytes
+        writer.write(b"h").unwrap();
+        writer.write(&[b'x'; 20]).unwrap();
+        let mut rest = [0u8; 23];
+        assert!(reader.read_exact(&mut rest, Some(1_000_000)).unwrap());
+        assert_eq!(&rest[..3], b"fgh");
+        assert!(rest[3..].iter().all(|&b| b == b'x'));
+
+        // Dropping the writer flushes and closes the stream
+        writer.write(b"yz").unwrap();
+        drop(writer);
+        let mut line = String::new();
+        reader.read_to_string(&mut line).unwrap();
+        assert_eq!(line, "yz");
+    }
+}
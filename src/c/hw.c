@@ -0,0 +1,140 @@
+/**
+ * hw.c - NIC capability queries over the ethtool ioctl
+ */
+
+#include <errno.h>
+#include <stdlib.h>
+#include <ifaddrs.h>
+#include <string.h>
+#include <unistd.h>
+#include <net/if.h>
+#include <netinet/in.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <linux/ethtool.h>
+#include <linux/sockios.h>
+#include "hw.h"
+
+// Run an ethtool command on an interface through a throwaway datagram socket
+static int ethtool_ioctl(const char* ifname, void* command) {
+    if (!ifname || strlen(ifname) >= IF_NAMESIZE) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    int fd = socket(AF_INET, SOCK_DGRAM | SOCK_CLOEXEC, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    
+    struct ifreq request;
+    memset(&request, 0, sizeof(request));
+    strncpy(request.ifr_name, ifname, IF_NAMESIZE - 1);
+    request.ifr_data = command;
+    
+    int result = ioctl(fd, SIOCETHTOOL, &request);
+    int saved_errno = errno;
+    close(fd);
+    errno = saved_errno;
+    return result;
+}
+
+int hw_get_ts_info(const char* ifname, hw_ts_info_t* info) {
+    if (!info) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct ethtool_ts_info ts_info;
+    memset(&ts_info, 0, sizeof(ts_info));
+    ts_info.cmd = ETHTOOL_GET_TS_INFO;
+    if (ethtool_ioctl(ifname, &ts_info) < 0) {
+        return -1;
+    }
+    
+    info->so_timestamping = ts_info.so_timestamping;
+    info->phc_index = ts_info.phc_index;
+    info->tx_types = ts_info.tx_types;
+    info->rx_filters = ts_info.rx_filters;
+    return 0;
+}
+
+int hw_get_ipv4_addr(const char* ifname, uint32_t* addr) {
+    if (!ifname || !addr) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct ifaddrs* interfaces;
+    if (getifaddrs(&interfaces) < 0) {
+        return -1;
+    }
+    
+    int found = 0;
+    for (struct ifaddrs* entry = interfaces; entry; entry = entry->ifa_next) {
+        if (entry->ifa_addr && entry->ifa_addr->sa_family == AF_INET &&
+            strcmp(entry->ifa_name, ifname) == 0) {
+            *addr = ((struct sockaddr_in*)entry->ifa_addr)->sin_addr.s_addr;
+            found = 1;
+            break;
+        }
+    }
+    
+    freeifaddrs(interfaces);
+    return found;
+}
+
+int hw_get_ethtool_stats(const char* ifname, hw_stat_t* stats, uint32_t capacity) {
+    if (capacity > 0 && !stats) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct {
+        struct ethtool_sset_info info;
+        uint32_t length;
+    } sset;
+    memset(&sset, 0, sizeof(sset));
+    sset.info.cmd = ETHTOOL_GSSET_INFO;
+    sset.info.sset_mask = 1ULL << ETH_SS_STATS;
+    if (ethtool_ioctl(ifname, &sset) < 0) {
+        return -1;
+    }
+    uint32_t count = sset.info.sset_mask ? sset.length : 0;
+    if (count == 0 || capacity == 0) {
+        return (int)count;
+    }
+    
+    struct ethtool_gstrings* strings = calloc(1, sizeof(*strings) + (size_t)count * ETH_GSTRING_LEN);
+    struct ethtool_stats* values = calloc(1, sizeof(*values) + (size_t)count * sizeof(uint64_t));
+    if (!strings || !values) {
+        free(strings);
+        free(values);
+        errno = ENOMEM;
+        return -1;
+    }
+    strings->cmd = ETHTOOL_GSTRINGS;
+    strings->string_set = ETH_SS_STATS;
+    strings->len = count;
+    values->cmd = ETHTOOL_GSTATS;
+    values->n_stats = count;
+    
+    int result = -1;
+    if (ethtool_ioctl(ifname, strings) == 0 && ethtool_ioctl(ifname, values) == 0) {
+        // The driver may report fewer statistics than announced
+        uint32_t reported = strings->len < values->n_stats ? strings->len : values->n_stats;
+        uint32_t stored = reported < capacity ? reported : capacity;
+        for (uint32_t i = 0; i < stored; i++) {
+            memcpy(stats[i].name, strings->data + (size_t)i * ETH_GSTRING_LEN, ETH_GSTRING_LEN);
+            stats[i].name[HW_STAT_NAME_LEN - 1] = '\0';
+            stats[i].value = values->data[i];
+        }
+        result = (int)reported;
+    }
+    
+    int saved_errno = errno;
+    free(strings);
+    free(values);
+    errno = saved_errno;
+    return result;
+}
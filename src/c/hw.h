@@ -0,0 +1,58 @@
+/**
+ * hw.h - NIC capability queries over the ethtool ioctl
+ */
+
+#ifndef HW_H
+#define HW_H
+
+#include <stdint.h>
+
+// Timestamping capabilities of a network interface (ETHTOOL_GET_TS_INFO)
+typedef struct {
+    uint32_t so_timestamping;   // Supported SOF_TIMESTAMPING_* flags
+    int32_t phc_index;          // Index of the PTP hardware clock, -1 if none
+    uint32_t tx_types;          // Supported HWTSTAMP_TX_* modes (bitmask)
+    uint32_t rx_filters;        // Supported HWTSTAMP_FILTER_* modes (bitmask)
+} hw_ts_info_t;
+
+// Length of an ethtool statistic name, including the terminating NUL
+#define HW_STAT_NAME_LEN 32
+
+// One driver statistic of a network interface (ETHTOOL_GSTATS)
+typedef struct {
+    char name[HW_STAT_NAME_LEN];    // Statistic name, NUL terminated
+    uint64_t value;                 // Counter value
+} hw_stat_t;
+
+/**
+ * Get the timestamping capabilities of a network interface
+ * 
+ * @param ifname Interface name
+ * @param info Output pointer to store the capabilities
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int hw_get_ts_info(const char* ifname, hw_ts_info_t* info);
+
+/**
+ * Get the first IPv4 address of a network interface
+ * 
+ * @param ifname Interface name
+ * @param addr Output pointer to store the address (network byte order)
+ * @return 1 if found, 0 if the interface has no IPv4 address, -1 on failure (errno is set)
+ */
+int hw_get_ipv4_addr(const char* ifname, uint32_t* addr);
+
+/**
+ * Get the driver statistics of a network interface (`ethtool -S`)
+ * 
+ * Call with capacity 0 to learn the number of statistics.
+ * 
+ * @param ifname Interface name
+ * @param stats Output array to store the statistics
+ * @param capacity Number of entries in stats
+ * @return Number of statistics the driver reports (only the first capacity are stored),
+ *         or -1 on failure (errno is set)
+ */
+int hw_get_ethtool_stats(const char* ifname, hw_stat_t* stats, uint32_t capacity);
+
+#endif // HW_H
@@ -0,0 +1,141 @@
+/**
+ * netmon.c - Link state notifications over rtnetlink
+ */
+
+#include <errno.h>
+#include <ifaddrs.h>
+#include <poll.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <linux/netlink.h>
+#include <linux/rtnetlink.h>
+#include "netmon.h"
+
+// Large enough for a full dump batch of RTM_NEWLINK messages
+#define NETMON_BUFFER_SIZE 16384
+
+// Ask the kernel for the current state of every link
+static int request_dump(int fd) {
+    struct {
+        struct nlmsghdr header;
+        struct ifinfomsg info;
+    } request;
+    
+    memset(&request, 0, sizeof(request));
+    request.header.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    request.header.nlmsg_type = RTM_GETLINK;
+    request.header.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+    request.info.ifi_family = AF_UNSPEC;
+    
+    return send(fd, &request, request.header.nlmsg_len, 0) < 0 ? -1 : 0;
+}
+
+// Fill a link from an RTM_NEWLINK/RTM_DELLINK message
+static void parse_link(const struct nlmsghdr* header, netmon_link_t* link) {
+    const struct ifinfomsg* info = NLMSG_DATA(header);
+    
+    memset(link, 0, sizeof(*link));
+    link->if_index = (uint32_t)info->ifi_index;
+    link->flags = info->ifi_flags;
+    link->removed = header->nlmsg_type == RTM_DELLINK;
+    
+    int length = IFLA_PAYLOAD(header);
+    for (struct rtattr* attr = IFLA_RTA(info); RTA_OK(attr, length); attr = RTA_NEXT(attr, length)) {
+        if (attr->rta_type == IFLA_IFNAME) {
+            size_t name_length = RTA_PAYLOAD(attr) < IF_NAMESIZE - 1 ? RTA_PAYLOAD(attr) : IF_NAMESIZE - 1;
+            memcpy(link->name, RTA_DATA(attr), name_length);
+            link->name[name_length] = '\0';
+        } else if (attr->rta_type == IFLA_OPERSTATE && RTA_PAYLOAD(attr) >= sizeof(uint8_t)) {
+            link->operstate = *(const uint8_t*)RTA_DATA(attr);
+        }
+    }
+}
+
+int netmon_open(bool dump) {
+    int fd = socket(AF_NETLINK, SOCK_RAW | SOCK_CLOEXEC, NETLINK_ROUTE);
+    if (fd < 0) {
+        return -1;
+    }
+    
+    struct sockaddr_nl addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.nl_family = AF_NETLINK;
+    addr.nl_groups = RTMGRP_LINK;
+    
+    if (bind(fd, (struct sockaddr*)&addr, sizeof(addr)) < 0 || (dump && request_dump(fd) < 0)) {
+        int error = errno;
+        close(fd);
+        errno = error;
+        return -1;
+    }
+    
+    return fd;
+}
+
+int netmon_read(int fd, netmon_link_t* links, size_t max_links, int timeout_ms) {
+    if (fd < 0 || !links || max_links == 0) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct pollfd pfd = { .fd = fd, .events = POLLIN };
+    int ready = poll(&pfd, 1, timeout_ms);
+    if (ready <= 0) {
+        return ready < 0 && errno == EINTR ? 0 : ready;
+    }
+    
+    char buffer[NETMON_BUFFER_SIZE] __attribute__((aligned(NLMSG_ALIGNTO)));
+    ssize_t received = recv(fd, buffer, sizeof(buffer), MSG_DONTWAIT);
+    if (received < 0) {
+        if (errno == ENOBUFS) {
+            // Notifications were lost: resynchronize with a full dump
+            return request_dump(fd) < 0 ? -1 : 0;
+        }
+        return errno == EAGAIN || errno == EWOULDBLOCK || errno == EINTR ? 0 : -1;
+    }
+    
+    size_t count = 0;
+    int length = (int)received;
+    for (struct nlmsghdr* header = (struct nlmsghdr*)buffer; NLMSG_OK(header, length) && count < max_links;
+         header = NLMSG_NEXT(header, length)) {
+        if (header->nlmsg_type == RTM_NEWLINK || header->nlmsg_type == RTM_DELLINK) {
+            parse_link(header, &links[count++]);
+        }
+    }
+    
+    return (int)count;
+}
+
+void netmon_close(int fd) {
+    if (fd >= 0) {
+        close(fd);
+    }
+}
+
+int netmon_interface_for_addr(uint32_t addr, char* name) {
+    if (!name) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct ifaddrs* interfaces;
+    if (getifaddrs(&interfaces) < 0) {
+        return -1;
+    }
+    
+    int found = 0;
+    for (struct ifaddrs* entry = interfaces; entry; entry = entry->ifa_next) {
+        if (entry->ifa_addr && entry->ifa_addr->sa_family == AF_INET &&
+            ((struct sockaddr_in*)entry->ifa_addr)->sin_addr.s_addr == addr) {
+            strncpy(name, entry->ifa_name, IF_NAMESIZE - 1);
+            name[IF_NAMESIZE - 1] = '\0';
+            found = 1;
+            break;
+        }
+    }
+    
+    freeifaddrs(interfaces);
+    return found;
+}
@@ -0,0 +1,63 @@
+/**
+ * netmon.h - Link state notifications over rtnetlink
+ */
+
+#ifndef NETMON_H
+#define NETMON_H
+
+#include <stdint.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <net/if.h>
+
+// Number of links a netmon_read buffer must hold to never lose a notification
+#define NETMON_MAX_LINKS 64
+
+// State of one network interface, from an RTM_NEWLINK or RTM_DELLINK message
+typedef struct {
+    uint32_t if_index;          // Interface index
+    uint32_t flags;             // Interface flags (IFF_UP, IFF_RUNNING, IFF_LOWER_UP, ...)
+    uint8_t operstate;          // RFC 2863 operational state (IF_OPER_*)
+    bool removed;               // Whether the interface was deleted
+    char name[IF_NAMESIZE];     // Interface name, NUL-terminated
+} netmon_link_t;
+
+/**
+ * Open a netlink socket receiving link changes (RTMGRP_LINK)
+ * 
+ * @param dump Whether to request the current state of all links first
+ * @return Socket file descriptor, or -1 on failure (errno is set)
+ */
+int netmon_open(bool dump);
+
+/**
+ * Wait for link notifications and read the next batch
+ * 
+ * On a receive buffer overrun the current state of all links is requested again, so a
+ * lost change is followed by a full snapshot.
+ * 
+ * @param fd Socket returned by netmon_open
+ * @param links Output array for the links (NETMON_MAX_LINKS entries)
+ * @param max_links Number of entries in links
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @return Number of links read, 0 on timeout, or -1 on failure (errno is set)
+ */
+int netmon_read(int fd, netmon_link_t* links, size_t max_links, int timeout_ms);
+
+/**
+ * Close a socket returned by netmon_open
+ * 
+ * @param fd Socket file descriptor
+ */
+void netmon_close(int fd);
+
+/**
+ * Find the interface holding a local IPv4 address
+ * 
+ * @param addr Address in network byte order
+ * @param name Output buffer of IF_NAMESIZE bytes for the interface name
+ * @return 1 if found, 0 if no interface has the address, -1 on failure (errno is set)
+ */
+int netmon_interface_for_addr(uint32_t addr, char* name);
+
+#endif /* NETMON_H */
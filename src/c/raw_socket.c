@@ -0,0 +1,181 @@
+/**
+ * raw_socket.c - Packet (AF_PACKET) sockets bound to one interface
+ */
+
+#include <errno.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include <arpa/inet.h>
+#include <net/if.h>
+#include <sys/socket.h>
+#include <linux/if_ether.h>
+#include <linux/if_packet.h>
+#include "raw_socket.h"
+
+int raw_socket_open(const char* ifname, uint16_t protocol, bool promiscuous,
+                    const struct sock_filter* filter, uint16_t filter_len, const vma_options_t* options) {
+    if (!ifname || (filter_len > 0 && !filter)) {
+        errno = EINVAL;
+        return -1;
+    }
+    unsigned int if_index = if_nametoindex(ifname);
+    if (if_index == 0) {
+        return -1;
+    }
+    
+    vma_options_t defaults;
+    if (!options) {
+        set_default_options(&defaults);
+        options = &defaults;
+    }
+    vma_setup_environment(options);
+    
+    // Protocol 0 receives nothing until bind, so no frame slips past the filter
+    int fd = socket(AF_PACKET, SOCK_RAW | SOCK_CLOEXEC, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    
+    if (filter_len > 0 && raw_socket_set_filter(fd, filter, filter_len) < 0) {
+        goto fail;
+    }
+    
+    if (options->buffer_size > 0) {
+        int buffer_size = options->buffer_size;
+        if (setsockopt(fd, SOL_SOCKET, SO_RCVBUF, &buffer_size, sizeof(buffer_size)) < 0 ||
+            setsockopt(fd, SOL_SOCKET, SO_SNDBUF, &buffer_size, sizeof(buffer_size)) < 0) {
+            goto fail;
+        }
+    }
+    
+    if (options->enable_timestamps) {
+        int optval = 1;
+        setsockopt(fd, SOL_SOCKET, SO_TIMESTAMPNS, &optval, sizeof(optval));
+    }
+    
+    // Frames the socket sends itself are not looped back to it
+    int ignore_outgoing = 1;
+    setsockopt(fd, SOL_PACKET, PACKET_IGNORE_OUTGOING, &ignore_outgoing, sizeof(ignore_outgoing));
+    
+    struct sockaddr_ll addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sll_family = AF_PACKET;
+    addr.sll_protocol = htons(protocol);
+    addr.sll_ifindex = (int)if_index;
+    if (bind(fd, (struct sockaddr*)&addr, sizeof(addr)) < 0) {
+        goto fail;
+    }
+    
+    // The membership is dropped by the kernel when the socket closes
+    if (promiscuous) {
+        struct packet_mreq mreq;
+        memset(&mreq, 0, sizeof(mreq));
+        mreq.mr_ifindex = (int)if_index;
+        mreq.mr_type = PACKET_MR_PROMISC;
+        if (setsockopt(fd, SOL_PACKET, PACKET_ADD_MEMBERSHIP, &mreq, sizeof(mreq)) < 0) {
+            goto fail;
+        }
+    }
+    
+    return fd;
+    
+fail: ;
+    int saved_errno = errno;
+    close(fd);
+    errno = saved_errno;
+    return -1;
+}
+
+int raw_socket_set_filter(int fd, const struct sock_filter* filter, uint16_t filter_len) {
+    if (!filter || filter_len == 0) {
+        int dummy = 0;
+        if (setsockopt(fd, SOL_SOCKET, SO_DETACH_FILTER, &dummy, sizeof(dummy)) < 0 && errno != ENOENT) {
+            return -1;
+        }
+        return 0;
+    }
+    
+    struct sock_fprog program;
+    program.len = filter_len;
+    program.filter = (struct sock_filter*)filter;
+    return setsockopt(fd, SOL_SOCKET, SO_ATTACH_FILTER, &program, sizeof(program));
+}
+
+int raw_socket_recv(int fd, void* buffer, size_t buffer_size, raw_packet_info_t* info,
+                    vma_wait_strategy_t strategy, uint32_t spin_budget_us, uint64_t last_rx_ns, int timeout_ms) {
+    if (!buffer || !info) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    if (timeout_ms != 0) {
+        int ready = vma_common_wait_readable(fd, strategy, spin_budget_us, last_rx_ns, timeout_ms);
+        if (ready <= 0) {
+            return ready;
+        }
+    }
+    
+    struct sockaddr_ll addr;
+    struct iovec iov = { .iov_base = buffer, .iov_len = buffer_size };
+    char control[CMSG_SPACE(sizeof(struct timespec))];
+    struct msghdr msg;
+    memset(&msg, 0, sizeof(msg));
+    msg.msg_name = &addr;
+    msg.msg_namelen = sizeof(addr);
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control;
+    msg.msg_controllen = sizeof(control);
+    
+    ssize_t res = recvmsg(fd, &msg, MSG_TRUNC | MSG_DONTWAIT);
+    if (res < 0) {
+        return (errno == EAGAIN || errno == EWOULDBLOCK) ? 0 : -1;
+    }
+    
+    memset(info, 0, sizeof(*info));
+    info->full_length = (size_t)res;
+    info->length = (size_t)res < buffer_size ? (size_t)res : buffer_size;
+    info->if_index = addr.sll_ifindex;
+    info->protocol = ntohs(addr.sll_protocol);
+    info->pkt_type = addr.sll_pkttype;
+    for (struct cmsghdr* cmsg = CMSG_FIRSTHDR(&msg); cmsg; cmsg = CMSG_NXTHDR(&msg, cmsg)) {
+        if (cmsg->cmsg_level == SOL_SOCKET && cmsg->cmsg_type == SO_TIMESTAMPNS) {
+            struct timespec ts;
+            memcpy(&ts, CMSG_DATA(cmsg), sizeof(ts));
+            info->timestamp = (uint64_t)ts.tv_sec * 1000000000ULL + (uint64_t)ts.tv_nsec;
+        }
+    }
+    if (info->timestamp == 0) {
+        info->timestamp = vma_now_ns();
+    }
+    return 1;
+}
+
+ssize_t raw_socket_send(int fd, const void* frame, size_t length) {
+    if (!frame || length < ETH_HLEN) {
+        errno = EINVAL;
+        return -1;
+    }
+    return send(fd, frame, length, 0);
+}
+
+int raw_socket_stats(int fd, uint32_t* packets, uint32_t* drops) {
+    if (!packets || !drops) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct tpacket_stats stats;
+    socklen_t length = sizeof(stats);
+    if (getsockopt(fd, SOL_PACKET, PACKET_STATISTICS, &stats, &length) < 0) {
+        return -1;
+    }
+    *packets = stats.tp_packets;
+    *drops = stats.tp_drops;
+    return 0;
+}
+
+int raw_socket_close(int fd) {
+    return close(fd);
+}
@@ -0,0 +1,96 @@
+/**
+ * raw_socket.h - Packet (AF_PACKET) sockets bound to one interface
+ */
+
+#ifndef RAW_SOCKET_H
+#define RAW_SOCKET_H
+
+#include <stdint.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <sys/types.h>
+#include <linux/filter.h>
+#include "vma_common.h"
+
+// Metadata of a frame received by raw_socket_recv
+typedef struct {
+    size_t length;              // Bytes stored in the buffer
+    size_t full_length;         // Frame length (greater than length if truncated)
+    uint64_t timestamp;         // Receive time in ns since the epoch (kernel timestamp when enabled)
+    int if_index;               // Interface the frame was seen on
+    uint16_t protocol;          // EtherType, host byte order
+    uint8_t pkt_type;           // PACKET_HOST, PACKET_BROADCAST, PACKET_MULTICAST, PACKET_OTHERHOST or PACKET_OUTGOING
+} raw_packet_info_t;
+
+/**
+ * Open a packet socket receiving and sending whole Ethernet frames on one interface
+ * 
+ * The filter is attached before the socket is bound, so no frame rejected by it is
+ * ever queued.
+ * 
+ * @param ifname Interface name
+ * @param protocol EtherType to receive (host byte order, ETH_P_ALL for every frame)
+ * @param promiscuous Whether to put the interface in promiscuous mode while the socket is open
+ * @param filter Classic BPF program (NULL for none)
+ * @param filter_len Number of instructions in filter
+ * @param options VMA options (use default if NULL)
+ * @return Socket file descriptor, or -1 on failure (errno is set)
+ */
+int raw_socket_open(const char* ifname, uint16_t protocol, bool promiscuous,
+                    const struct sock_filter* filter, uint16_t filter_len, const vma_options_t* options);
+
+/**
+ * Replace the BPF filter of a packet socket
+ * 
+ * @param fd Socket returned by raw_socket_open
+ * @param filter Classic BPF program (NULL removes the filter)
+ * @param filter_len Number of instructions in filter
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int raw_socket_set_filter(int fd, const struct sock_filter* filter, uint16_t filter_len);
+
+/**
+ * Wait for a frame and receive it
+ * 
+ * @param fd Socket returned by raw_socket_open
+ * @param buffer Buffer for the frame
+ * @param buffer_size Size of buffer
+ * @param info Output pointer for the frame metadata
+ * @param strategy How to wait
+ * @param spin_budget_us Busy-poll time before yielding or parking
+ * @param last_rx_ns Time of the last successful receive (vma_now_ns, 0 if none)
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @return 1 if a frame was received, 0 on timeout, -1 on failure (errno is set)
+ */
+int raw_socket_recv(int fd, void* buffer, size_t buffer_size, raw_packet_info_t* info,
+                    vma_wait_strategy_t strategy, uint32_t spin_budget_us, uint64_t last_rx_ns, int timeout_ms);
+
+/**
+ * Send a whole Ethernet frame on the interface the socket is bound to
+ * 
+ * @param fd Socket returned by raw_socket_open
+ * @param frame Frame starting with the Ethernet header
+ * @param length Frame length
+ * @return Number of bytes sent, or -1 on failure (errno is set)
+ */
+ssize_t raw_socket_send(int fd, const void* frame, size_t length);
+
+/**
+ * Get the receive counters of a packet socket (PACKET_STATISTICS), resetting them
+ * 
+ * @param fd Socket returned by raw_socket_open
+ * @param packets Output pointer for the frames received since the last call
+ * @param drops Output pointer for the frames dropped since the last call (queue full)
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int raw_socket_stats(int fd, uint32_t* packets, uint32_t* drops);
+
+/**
+ * Close a packet socket, leaving promiscuous mode if it was entered
+ * 
+ * @param fd Socket returned by raw_socket_open
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int raw_socket_close(int fd);
+
+#endif /* RAW_SOCKET_H */
@@ -4,6 +4,7 @@
 #define _POSIX_C_SOURCE 199309L
 
 #include <fcntl.h>
+#include <poll.h>
 #include <time.h>
 #include <sys/time.h>
 #include <sys/types.h>
@@ -29,6 +30,7 @@ static bool would_block(void);
 static int wait_for_socket(int fd, bool for_read, int timeout_ms);
 static int set_nonblocking(int fd);
 static int set_blocking(int fd);
+static int open_socket(const vma_options_t* options);
 
 // Check if an operation would block
 static bool would_block(void) {
@@ -69,6 +71,39 @@ static int wait_for_socket(int fd, bool for_read, int timeout_ms) {
     }
 }
 
+// Open a replacement descriptor with the options applied on reconnect
+static int open_socket(const vma_options_t* options) {
+    int fd = socket(AF_INET, SOCK_STREAM, IPPROTO_TCP);
+    if (fd < 0) {
+        return -1;
+    }
+    
+    // Keep the ring allocation of the original socket
+    vma_common_apply_ring_alloc(fd, options);
+    
+    // Set buffer size
+    if (options->buffer_size > 0) {
+        int buffer_size = options->buffer_size;
+        setsockopt(fd, SOL_SOCKET, SO_SNDBUF, &buffer_size, sizeof(buffer_size));
+        setsockopt(fd, SOL_SOCKET, SO_RCVBUF, &buffer_size, sizeof(buffer_size));
+    }
+    
+    // Set TCP keepalive
+    int keepalive = 1;
+    setsockopt(fd, SOL_SOCKET, SO_KEEPALIVE, &keepalive, sizeof(keepalive));
+    
+    // Set TCP nodelay as configured
+    int nodelay = options->tcp_nodelay ? 1 : 0;
+    setsockopt(fd, IPPROTO_TCP, TCP_NODELAY, &nodelay, sizeof(nodelay));
+    
+    // Set non-blocking if polling is enabled
+    if (options->use_polling) {
+        set_nonblocking(fd);
+    }
+    
+    return fd;
+}
+
 tcp_result_t tcp_socket_init(tcp_socket_t* sock, const vma_options_t* options) {
     if (!sock) {
         return TCP_ERROR_INVALID_PARAM;
@@ -92,6 +127,13 @@ tcp_result_t tcp_socket_init(tcp_socket_t* sock, const vma_options_t* options) {
         return TCP_ERROR_SOCKET_CREATE;
     }
     
+    // Select the VMA rings before bind/connect attaches them
+    if (vma_common_apply_ring_alloc(sock->socket_fd, &sock->vma_options) < 0) {
+        close(sock->socket_fd);
+        sock->socket_fd = -1;
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
     // Set buffer size
     if (sock->vma_options.buffer_size > 0) {
         int buffer_size = sock->vma_options.buffer_size;
@@ -152,8 +194,8 @@ tcp_result_t tcp_socket_init(tcp_socket_t* sock, const vma_options_t* options) {
         }
     }
     
-    // Set TCP nodelay (disable Nagle's algorithm)
-    int nodelay = 1;
+    // Set TCP nodelay (disable Nagle's algorithm) as configured
+    int nodelay = sock->vma_options.tcp_nodelay ? 1 : 0;
     if (setsockopt(sock->socket_fd, IPPROTO_TCP, TCP_NODELAY, 
                 &nodelay, sizeof(nodelay)) < 0) {
         // Not fatal, just continue
@@ -174,14 +216,140 @@ tcp_result_t tcp_socket_close(tcp_socket_t* sock) {
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    close(sock->socket_fd);
+    int rc = close(sock->socket_fd);
     sock->socket_fd = -1;
     sock->is_bound = false;
     sock->state = TCP_STATE_DISCONNECTED;
     
+    return rc < 0 ? TCP_ERROR_CLOSE : TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_new(tcp_socket_t** sock, const vma_options_t* options) {
+    if (!sock) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    *sock = NULL;
+    tcp_socket_t* handle = calloc(1, sizeof(tcp_socket_t));
+    if (!handle) {
+        return TCP_ERROR_SOCKET_CREATE;
+    }
+    
+    tcp_result_t result = tcp_socket_init(handle, options);
+    if (result != TCP_SUCCESS) {
+        // init may fail after creating the descriptor
+        if (handle->socket_fd >= 0) {
+            close(handle->socket_fd);
+        }
+        free(handle);
+        return result;
+    }
+    
+    *sock = handle;
     return TCP_SUCCESS;
 }
 
+void tcp_socket_free(tcp_socket_t* sock) {
+    if (!sock) {
+        return;
+    }
+    
+    if (sock->socket_fd >= 0) {
+        tcp_socket_close(sock);
+    }
+    free(sock);
+}
+
+tcp_result_t tcp_socket_from_fd(tcp_socket_t** sock, int fd, const vma_options_t* options) {
+    if (!sock) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    *sock = NULL;
+    if (vma_common_check_socket_fd(fd, SOCK_STREAM) < 0) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    tcp_socket_t* handle = calloc(1, sizeof(tcp_socket_t));
+    if (!handle) {
+        return TCP_ERROR_SOCKET_CREATE;
+    }
+    
+    if (options) {
+        handle->vma_options = *options;
+    } else {
+        set_default_options(&handle->vma_options);
+    }
+    handle->socket_fd = fd;
+    handle->state = TCP_STATE_DISCONNECTED;
+    
+    if (vma_common_get_local_addr(fd, &handle->local_addr) == 0 && handle->local_addr.sin_port != 0) {
+        handle->is_bound = true;
+    }
+    
+    int listening = 0;
+    socklen_t len = sizeof(listening);
+    socklen_t addr_len = sizeof(handle->remote_addr);
+    if (getsockopt(fd, SOL_SOCKET, SO_ACCEPTCONN, &listening, &len) == 0 && listening) {
+        handle->state = TCP_STATE_LISTENING;
+        handle->backlog = SOMAXCONN;
+    } else if (getpeername(fd, (struct sockaddr*)&handle->remote_addr, &addr_len) == 0) {
+        handle->state = TCP_STATE_CONNECTED;
+    } else {
+        memset(&handle->remote_addr, 0, sizeof(handle->remote_addr));
+    }
+    
+    *sock = handle;
+    return TCP_SUCCESS;
+}
+
+int tcp_socket_release_fd(tcp_socket_t* sock) {
+    if (!sock || sock->socket_fd < 0) {
+        return -1;
+    }
+    
+    int fd = sock->socket_fd;
+    sock->socket_fd = -1;
+    sock->is_bound = false;
+    sock->state = TCP_STATE_DISCONNECTED;
+    return fd;
+}
+
+int tcp_socket_fd(const tcp_socket_t* sock) {
+    return sock ? sock->socket_fd : -1;
+}
+
+tcp_connection_state_t tcp_socket_get_state(const tcp_socket_t* sock) {
+    return sock ? sock->state : TCP_STATE_DISCONNECTED;
+}
+
+bool tcp_socket_get_peer(const tcp_socket_t* sock, struct sockaddr_in* addr) {
+    if (!sock || !addr || sock->remote_addr.sin_family == 0) {
+        return false;
+    }
+    
+    *addr = sock->remote_addr;
+    return true;
+}
+
+void tcp_socket_set_nodelay_option(tcp_socket_t* sock, bool nodelay) {
+    if (sock) {
+        sock->vma_options.tcp_nodelay = nodelay;
+    }
+}
+
+void tcp_socket_get_options(const tcp_socket_t* sock, vma_options_t* options) {
+    if (sock && options) {
+        *options = sock->vma_options;
+    }
+}
+
+void tcp_socket_set_options(tcp_socket_t* sock, const vma_options_t* options) {
+    if (sock && options) {
+        sock->vma_options = *options;
+    }
+}
+
 tcp_result_t tcp_socket_bind(tcp_socket_t* sock, const char* ip, uint16_t port) {
     if (!sock || sock->socket_fd < 0) {
         return TCP_ERROR_INVALID_PARAM;
@@ -237,15 +405,19 @@ tcp_result_t tcp_socket_accept(tcp_socket_t* sock, tcp_client_t* client, int tim
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    // Wait for a connection with timeout
-    if (timeout_ms != 0) {
-        int select_result = wait_for_socket(sock->socket_fd, true, timeout_ms);
-        
-        if (select_result == 0) {
-            return TCP_ERROR_TIMEOUT;
-        } else if (select_result < 0) {
-            return TCP_ERROR_ACCEPT;
-        }
+    // A zero timeout never blocks, even on a blocking listener
+    if (timeout_ms == 0 && wait_for_socket(sock->socket_fd, true, 0) == 0) {
+        return TCP_ERROR_TIMEOUT;
+    }
+    
+    // Wait for a connection according to the configured strategy
+    int wait_result = vma_common_wait_readable(sock->socket_fd, sock->vma_options.wait_strategy,
+                                               sock->vma_options.spin_budget_us, 0, timeout_ms);
+    
+    if (wait_result == 0) {
+        return TCP_ERROR_TIMEOUT;
+    } else if (wait_result < 0) {
+        return TCP_ERROR_ACCEPT;
     }
     
     // Accept connection
@@ -262,6 +434,13 @@ tcp_result_t tcp_socket_accept(tcp_socket_t* sock, tcp_client_t* client, int tim
     // Initialize client structure
     client->rx_bytes = 0;
     client->tx_bytes = 0;
+    client->wait_strategy = sock->vma_options.wait_strategy;
+    client->spin_budget_us = sock->vma_options.spin_budget_us;
+    client->last_rx_ns = 0;
+    
+    // Apply the listener's Nagle setting to the accepted connection
+    int nodelay = sock->vma_options.tcp_nodelay ? 1 : 0;
+    setsockopt(client->socket_fd, IPPROTO_TCP, TCP_NODELAY, &nodelay, sizeof(nodelay));
     
     // Set non-blocking if polling is enabled
     if (sock->vma_options.use_polling) {
@@ -275,6 +454,23 @@ tcp_result_t tcp_socket_accept(tcp_socket_t* sock, tcp_client_t* client, int tim
     return TCP_SUCCESS;
 }
 
+tcp_result_t tcp_socket_pending_connections(tcp_socket_t* sock, uint32_t* pending) {
+    if (!sock || sock->socket_fd < 0 || !pending || sock->state != TCP_STATE_LISTENING) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    struct tcp_info info;
+    socklen_t len = sizeof(info);
+    memset(&info, 0, sizeof(info));
+    if (getsockopt(sock->socket_fd, IPPROTO_TCP, TCP_INFO, &info, &len) < 0) {
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
+    // For listening sockets Linux reports the accept queue length in tcpi_unacked
+    *pending = info.tcpi_unacked;
+    return TCP_SUCCESS;
+}
+
 tcp_result_t tcp_socket_connect(tcp_socket_t* sock, const char* ip, uint16_t port, int timeout_ms) {
     if (!sock || sock->socket_fd < 0 || !ip) {
         return TCP_ERROR_INVALID_PARAM;
@@ -357,6 +553,94 @@ tcp_result_t tcp_socket_connect(tcp_socket_t* sock, const char* ip, uint16_t por
     return TCP_SUCCESS;
 }
 
+tcp_result_t tcp_socket_connect_start(tcp_socket_t* sock, const char* ip, uint16_t port) {
+    if (!sock || sock->socket_fd < 0 || !ip) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state == TCP_STATE_CONNECTED || sock->state == TCP_STATE_CONNECTING) {
+        return TCP_ERROR_ALREADY_CONNECTED;
+    }
+    
+    // Set remote address
+    memset(&sock->remote_addr, 0, sizeof(sock->remote_addr));
+    sock->remote_addr.sin_family = AF_INET;
+    sock->remote_addr.sin_port = htons(port);
+    
+    if (inet_pton(AF_INET, ip, &sock->remote_addr.sin_addr) <= 0) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    // Stay non-blocking until tcp_socket_connect_poll sees the connection complete
+    if (!sock->vma_options.use_polling) {
+        if (set_nonblocking(sock->socket_fd) < 0) {
+            return TCP_ERROR_SOCKET_OPTION;
+        }
+    }
+    
+    sock->state = TCP_STATE_CONNECTING;
+    
+    int connect_result = connect(sock->socket_fd, (struct sockaddr*)&sock->remote_addr, 
+                               sizeof(sock->remote_addr));
+    
+    if (connect_result < 0 && errno == EINPROGRESS) {
+        return TCP_ERROR_WOULD_BLOCK;
+    }
+    
+    if (!sock->vma_options.use_polling) {
+        set_blocking(sock->socket_fd);
+    }
+    
+    if (connect_result < 0) {
+        sock->state = TCP_STATE_DISCONNECTED;
+        return TCP_ERROR_CONNECT;
+    }
+    
+    sock->state = TCP_STATE_CONNECTED;
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_connect_poll(tcp_socket_t* sock, int timeout_ms) {
+    if (!sock || sock->socket_fd < 0) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state == TCP_STATE_CONNECTED) {
+        return TCP_SUCCESS;
+    }
+    
+    if (sock->state != TCP_STATE_CONNECTING) {
+        return TCP_ERROR_NOT_INITIALIZED;
+    }
+    
+    int select_result = wait_for_socket(sock->socket_fd, false, timeout_ms);
+    
+    if (select_result == 0) {
+        // Still in progress
+        return TCP_ERROR_TIMEOUT;
+    }
+    
+    // Check if connection succeeded
+    int error = 0;
+    socklen_t error_len = sizeof(error);
+    bool failed = select_result < 0 ||
+                  getsockopt(sock->socket_fd, SOL_SOCKET, SO_ERROR, &error, &error_len) < 0 ||
+                  error != 0;
+    
+    // Restore socket mode
+    if (!sock->vma_options.use_polling) {
+        set_blocking(sock->socket_fd);
+    }
+    
+    if (failed) {
+        sock->state = TCP_STATE_DISCONNECTED;
+        return TCP_ERROR_CONNECT;
+    }
+    
+    sock->state = TCP_STATE_CONNECTED;
+    return TCP_SUCCESS;
+}
+
 tcp_result_t tcp_socket_reconnect(tcp_socket_t* sock, int timeout_ms) {
     if (!sock || sock->socket_fd < 0) {
         return TCP_ERROR_INVALID_PARAM;
@@ -374,34 +658,15 @@ tcp_result_t tcp_socket_reconnect(tcp_socket_t* sock, int timeout_ms) {
     
     // Close existing socket
     close(sock->socket_fd);
+    memset(&sock->zerocopy, 0, sizeof(sock->zerocopy));
     
-    // Create a new socket - use the socket() function directly, not via parameter name
-    sock->socket_fd = socket(AF_INET, SOCK_STREAM, IPPROTO_TCP);
+    // Create a new socket configured like the original one
+    sock->socket_fd = open_socket(&sock->vma_options);
     if (sock->socket_fd < 0) {
         sock->state = TCP_STATE_DISCONNECTED;
         return TCP_ERROR_SOCKET_CREATE;
     }
     
-    // Set buffer size
-    if (sock->vma_options.buffer_size > 0) {
-        int buffer_size = sock->vma_options.buffer_size;
-        setsockopt(sock->socket_fd, SOL_SOCKET, SO_SNDBUF, &buffer_size, sizeof(buffer_size));
-        setsockopt(sock->socket_fd, SOL_SOCKET, SO_RCVBUF, &buffer_size, sizeof(buffer_size));
-    }
-    
-    // Set TCP keepalive
-    int keepalive = 1;
-    setsockopt(sock->socket_fd, SOL_SOCKET, SO_KEEPALIVE, &keepalive, sizeof(keepalive));
-    
-    // Set TCP nodelay
-    int nodelay = 1;
-    setsockopt(sock->socket_fd, IPPROTO_TCP, TCP_NODELAY, &nodelay, sizeof(nodelay));
-    
-    // Set non-blocking if polling is enabled
-    if (sock->vma_options.use_polling) {
-        set_nonblocking(sock->socket_fd);
-    }
-    
     // Try to reconnect
     char ip[INET_ADDRSTRLEN];
     inet_ntop(AF_INET, &sock->remote_addr.sin_addr, ip, INET_ADDRSTRLEN);
@@ -413,6 +678,137 @@ tcp_result_t tcp_socket_reconnect(tcp_socket_t* sock, int timeout_ms) {
         return TCP_ERROR_RECONNECT;
     }
     
+    sock->stats.reconnects++;
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_connect_any(tcp_socket_t* sock, const struct sockaddr_in* addrs, size_t count,
+                                    int attempt_timeout_ms, int stagger_ms, size_t* winner) {
+    if (!sock || sock->socket_fd < 0 || !addrs || !winner || count == 0 || count > TCP_CONNECT_ANY_MAX) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state == TCP_STATE_CONNECTED) {
+        return TCP_ERROR_ALREADY_CONNECTED;
+    }
+    
+    // One descriptor per attempt; -1 once it failed or timed out (poll skips those)
+    struct pollfd attempts[TCP_CONNECT_ANY_MAX];
+    uint64_t started_at[TCP_CONNECT_ANY_MAX];
+    uint64_t attempt_timeout_ns = (uint64_t)attempt_timeout_ms * 1000000ULL;
+    uint64_t next_start = 0;
+    size_t started = 0;
+    size_t pending = 0;
+    bool timed_out = false;
+    int won = -1;
+    
+    sock->state = TCP_STATE_CONNECTING;
+    
+    while (won < 0 && (pending > 0 || started < count)) {
+        uint64_t now = vma_monotonic_ns();
+        
+        // Start the next address once nothing is in flight, or when the stagger delay is up
+        if (started < count && (pending == 0 || (stagger_ms >= 0 && now >= next_start))) {
+            size_t i = started++;
+            attempts[i].fd = -1;
+            attempts[i].events = POLLOUT;
+            attempts[i].revents = 0;
+            started_at[i] = now;
+            next_start = now + (uint64_t)(stagger_ms > 0 ? stagger_ms : 0) * 1000000ULL;
+            
+            int fd = open_socket(&sock->vma_options);
+            if (fd < 0) {
+                continue;
+            }
+            if (set_nonblocking(fd) < 0) {
+                close(fd);
+                continue;
+            }
+            
+            if (connect(fd, (const struct sockaddr*)&addrs[i], sizeof(addrs[i])) == 0) {
+                attempts[i].fd = fd;
+                won = (int)i;
+            } else if (errno == EINPROGRESS) {
+                attempts[i].fd = fd;
+                pending++;
+            } else {
+                close(fd);
+            }
+            continue;
+        }
+        
+        // Sleep until an attempt completes, the next one is due or the oldest one expires
+        int wait_ms = -1;
+        if (started < count && stagger_ms >= 0) {
+            wait_ms = next_start > now ? (int)((next_start - now + 999999ULL) / 1000000ULL) : 0;
+        }
+        if (attempt_timeout_ms >= 0) {
+            for (size_t i = 0; i < started; i++) {
+                if (attempts[i].fd < 0) {
+                    continue;
+                }
+                uint64_t deadline = started_at[i] + attempt_timeout_ns;
+                int remaining_ms = deadline > now ? (int)((deadline - now + 999999ULL) / 1000000ULL) : 0;
+                if (wait_ms < 0 || remaining_ms < wait_ms) {
+                    wait_ms = remaining_ms;
+                }
+            }
+        }
+        
+        if (poll(attempts, started, wait_ms) < 0 && errno != EINTR) {
+            break;
+        }
+        
+        now = vma_monotonic_ns();
+        for (size_t i = 0; i < started && won < 0; i++) {
+            if (attempts[i].fd < 0) {
+                continue;
+            }
+            
+            if (attempts[i].revents) {
+                int error = 0;
+                socklen_t error_len = sizeof(error);
+                if (getsockopt(attempts[i].fd, SOL_SOCKET, SO_ERROR, &error, &error_len) == 0 && error == 0) {
+                    won = (int)i;
+                    continue;
+                }
+            } else if (attempt_timeout_ms < 0 || now < started_at[i] + attempt_timeout_ns) {
+                continue;
+            } else {
+                timed_out = true;
+            }
+            
+            close(attempts[i].fd);
+            attempts[i].fd = -1;
+            pending--;
+        }
+    }
+    
+    // Close the attempts that lost the race
+    for (size_t i = 0; i < started; i++) {
+        if ((int)i != won && attempts[i].fd >= 0) {
+            close(attempts[i].fd);
+        }
+    }
+    
+    if (won < 0) {
+        sock->state = TCP_STATE_DISCONNECTED;
+        return timed_out ? TCP_ERROR_TIMEOUT : TCP_ERROR_CONNECT;
+    }
+    
+    int fd = attempts[won].fd;
+    if (!sock->vma_options.use_polling) {
+        set_blocking(fd);
+    }
+    
+    // The winning descriptor replaces the socket's own one
+    close(sock->socket_fd);
+    memset(&sock->zerocopy, 0, sizeof(sock->zerocopy));
+    sock->socket_fd = fd;
+    sock->is_bound = false;
+    sock->remote_addr = addrs[won];
+    sock->state = TCP_STATE_CONNECTED;
+    *winner = (size_t)won;
     return TCP_SUCCESS;
 }
 
@@ -435,7 +831,95 @@ bool tcp_socket_is_connected(tcp_socket_t* sock) {
     return true;
 }
 
+// Send on the connected socket with extra send flags (MSG_NOSIGNAL is always set)
+static tcp_result_t send_with_flags(tcp_socket_t* sock, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent) {
+    if (!sock || sock->socket_fd < 0 || !data || length == 0) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state != TCP_STATE_CONNECTED) {
+        return TCP_ERROR_NOT_INITIALIZED;
+    }
+    
+    ssize_t res = send(sock->socket_fd, data, length, MSG_NOSIGNAL | flags);
+    
+    if (res < 0) {
+        if (would_block()) {
+            sock->stats.tx_eagain++;
+            return TCP_ERROR_WOULD_BLOCK;
+        }
+        sock->stats.tx_errors++;
+        sock->state = TCP_STATE_DISCONNECTED;
+        return TCP_ERROR_SEND;
+    }
+    
+    if (bytes_sent) {
+        *bytes_sent = (size_t)res;
+    }
+    
+    sock->stats.tx_packets++;
+    sock->stats.tx_bytes += res;
+    sock->stats.last_tx_ns = vma_now_ns();
+    
+    return TCP_SUCCESS;
+}
+
 tcp_result_t tcp_socket_send(tcp_socket_t* sock, const void* data, size_t length, size_t* bytes_sent) {
+    return send_with_flags(sock, data, length, 0, bytes_sent);
+}
+
+tcp_result_t tcp_socket_send_flags(tcp_socket_t* sock, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent) {
+    return send_with_flags(sock, data, length, flags, bytes_sent);
+}
+
+tcp_result_t tcp_socket_send_timeout(tcp_socket_t* sock, const void* data, size_t length, 
+                                     int timeout_ms, size_t* bytes_sent) {
+    if (bytes_sent) {
+        *bytes_sent = 0;
+    }
+    
+    uint64_t deadline = timeout_ms >= 0 ? vma_monotonic_ns() + (uint64_t)timeout_ms * 1000000ULL : 0;
+    size_t total = 0;
+    tcp_result_t result = TCP_SUCCESS;
+    
+    while (total < length) {
+        size_t sent = 0;
+        result = send_with_flags(sock, (const char*)data + total, length - total, MSG_DONTWAIT, &sent);
+        
+        if (result == TCP_SUCCESS) {
+            total += sent;
+            continue;
+        } else if (result != TCP_ERROR_WOULD_BLOCK) {
+            break;
+        }
+        
+        // Send buffer full: wait for room until the deadline
+        int wait_ms = -1;
+        if (timeout_ms >= 0) {
+            uint64_t now = vma_monotonic_ns();
+            if (now >= deadline) {
+                result = TCP_ERROR_TIMEOUT;
+                break;
+            }
+            wait_ms = (int)((deadline - now + 999999ULL) / 1000000ULL);
+        }
+        
+        if (vma_common_wait_writable(sock->socket_fd, wait_ms) < 0) {
+            result = TCP_ERROR_SEND;
+            break;
+        }
+    }
+    
+    if (bytes_sent) {
+        *bytes_sent = total;
+    }
+    
+    return total == length ? TCP_SUCCESS : result;
+}
+
+tcp_result_t tcp_socket_send_dummy(tcp_socket_t* sock, const void* data, size_t length) {
     if (!sock || sock->socket_fd < 0 || !data || length == 0) {
         return TCP_ERROR_INVALID_PARAM;
     }
@@ -444,12 +928,38 @@ tcp_result_t tcp_socket_send(tcp_socket_t* sock, const void* data, size_t length
         return TCP_ERROR_NOT_INITIALIZED;
     }
     
-    ssize_t res = send(sock->socket_fd, data, length, MSG_NOSIGNAL);
+    // The kernel ignores the dummy flag and would put the data on the wire
+    if (!vma_common_is_offloaded(sock->socket_fd)) {
+        return TCP_ERROR_NOT_SUPPORTED;
+    }
+    
+    if (send(sock->socket_fd, data, length, VMA_SND_FLAGS_DUMMY | MSG_DONTWAIT | MSG_NOSIGNAL) < 0) {
+        if (would_block()) {
+            return TCP_ERROR_WOULD_BLOCK;
+        }
+        return TCP_ERROR_SEND;
+    }
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_send_zerocopy(tcp_socket_t* sock, const void* data, size_t length, size_t* bytes_sent) {
+    if (!sock || sock->socket_fd < 0 || !data || length == 0) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state != TCP_STATE_CONNECTED) {
+        return TCP_ERROR_NOT_INITIALIZED;
+    }
+    
+    ssize_t res = vma_common_send_zerocopy(sock->socket_fd, &sock->zerocopy, data, length, MSG_NOSIGNAL);
     
     if (res < 0) {
         if (would_block()) {
+            sock->stats.tx_eagain++;
             return TCP_ERROR_WOULD_BLOCK;
         }
+        sock->stats.tx_errors++;
         sock->state = TCP_STATE_DISCONNECTED;
         return TCP_ERROR_SEND;
     }
@@ -458,18 +968,28 @@ tcp_result_t tcp_socket_send(tcp_socket_t* sock, const void* data, size_t length
         *bytes_sent = (size_t)res;
     }
     
-    sock->tx_packets++;
-    sock->tx_bytes += res;
+    sock->stats.tx_packets++;
+    sock->stats.tx_bytes += res;
+    sock->stats.last_tx_ns = vma_now_ns();
     
     return TCP_SUCCESS;
 }
 
-tcp_result_t tcp_socket_send_to_client(tcp_client_t* client, const void* data, size_t length, size_t* bytes_sent) {
+uint32_t tcp_socket_zerocopy_pending(tcp_socket_t* sock) {
+    if (!sock) {
+        return 0;
+    }
+    return vma_common_zerocopy_pending(sock->socket_fd, &sock->zerocopy);
+}
+
+// Send to a client connection with extra send flags (MSG_NOSIGNAL is always set)
+static tcp_result_t send_client_with_flags(tcp_client_t* client, const void* data, size_t length, 
+                                      int flags, size_t* bytes_sent) {
     if (!client || client->socket_fd < 0 || !data || length == 0) {
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    ssize_t res = send(client->socket_fd, data, length, MSG_NOSIGNAL);
+    ssize_t res = send(client->socket_fd, data, length, MSG_NOSIGNAL | flags);
     
     if (res < 0) {
         if (would_block()) {
@@ -487,8 +1007,18 @@ tcp_result_t tcp_socket_send_to_client(tcp_client_t* client, const void* data, s
     return TCP_SUCCESS;
 }
 
-tcp_result_t tcp_socket_recv(tcp_socket_t* sock, void* buffer, size_t buffer_size, 
-                            int timeout_ms, size_t* bytes_received) {
+tcp_result_t tcp_socket_send_to_client(tcp_client_t* client, const void* data, size_t length, size_t* bytes_sent) {
+    return send_client_with_flags(client, data, length, 0, bytes_sent);
+}
+
+tcp_result_t tcp_socket_send_to_client_flags(tcp_client_t* client, const void* data, size_t length, 
+                                      int flags, size_t* bytes_sent) {
+    return send_client_with_flags(client, data, length, flags, bytes_sent);
+}
+
+// Receive on the connected socket with extra recv flags (MSG_PEEK leaves the bytes queued and the counters untouched)
+static tcp_result_t recv_with_flags(tcp_socket_t* sock, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received) {
     if (!sock || sock->socket_fd < 0 || !buffer || buffer_size == 0) {
         return TCP_ERROR_INVALID_PARAM;
     }
@@ -497,24 +1027,33 @@ tcp_result_t tcp_socket_recv(tcp_socket_t* sock, void* buffer, size_t buffer_siz
         return TCP_ERROR_NOT_INITIALIZED;
     }
     
-    // Handle timeout
-    if (timeout_ms != 0) {
-        int select_result = wait_for_socket(sock->socket_fd, true, timeout_ms);
+    // Wait according to the configured strategy (MSG_DONTWAIT never waits)
+    if (!(flags & MSG_DONTWAIT)) {
+        int wait_result = vma_common_wait_readable(sock->socket_fd, sock->vma_options.wait_strategy,
+                                                   sock->vma_options.spin_budget_us, sock->stats.last_rx_ns,
+                                                   timeout_ms);
         
-        if (select_result == 0) {
+        if (wait_result == 0) {
             return TCP_ERROR_TIMEOUT;
-        } else if (select_result < 0) {
+        } else if (wait_result < 0) {
             return TCP_ERROR_RECV;
         }
     }
     
+    // A zero timeout never blocks, even on a blocking socket
+    if (timeout_ms == 0) {
+        flags |= MSG_DONTWAIT;
+    }
+    
     // Receive data
-    ssize_t res = recv(sock->socket_fd, buffer, buffer_size, 0);
+    ssize_t res = recv(sock->socket_fd, buffer, buffer_size, flags);
     
     if (res < 0) {
         if (would_block()) {
+            sock->stats.rx_eagain++;
             return TCP_ERROR_TIMEOUT;
         }
+        sock->stats.rx_errors++;
         sock->state = TCP_STATE_DISCONNECTED;
         return TCP_ERROR_RECV;
     } else if (res == 0) {
@@ -527,31 +1066,92 @@ tcp_result_t tcp_socket_recv(tcp_socket_t* sock, void* buffer, size_t buffer_siz
         *bytes_received = (size_t)res;
     }
     
-    sock->rx_packets++;
-    sock->rx_bytes += res;
+    if (!(flags & MSG_PEEK)) {
+        sock->stats.rx_packets++;
+        sock->stats.rx_bytes += res;
+        sock->stats.last_rx_ns = vma_now_ns();
+    }
     
     return TCP_SUCCESS;
 }
 
-tcp_result_t tcp_socket_recv_from_client(tcp_client_t* client, void* buffer, size_t buffer_size, 
-                                      int timeout_ms, size_t* bytes_received) {
+tcp_result_t tcp_socket_recv(tcp_socket_t* sock, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received) {
+    return recv_with_flags(sock, buffer, buffer_size, timeout_ms, 0, bytes_received);
+}
+
+tcp_result_t tcp_socket_recv_at_least(tcp_socket_t* sock, void* buffer, size_t buffer_size,
+                                     size_t min_bytes, int timeout_ms, size_t* bytes_received) {
+    if (bytes_received) {
+        *bytes_received = 0;
+    }
+    if (min_bytes == 0 || min_bytes > buffer_size) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    uint64_t deadline = timeout_ms >= 0 ? vma_monotonic_ns() + (uint64_t)timeout_ms * 1000000ULL : 0;
+    size_t total = 0;
+    tcp_result_t result = TCP_SUCCESS;
+    
+    while (total < min_bytes) {
+        // Wait for the rest of the deadline; once it passed, take only what is queued
+        int wait_ms = -1;
+        if (timeout_ms >= 0) {
+            uint64_t now = vma_monotonic_ns();
+            wait_ms = now >= deadline ? 0 : (int)((deadline - now + 999999ULL) / 1000000ULL);
+        }
+        
+        size_t received = 0;
+        result = recv_with_flags(sock, (char*)buffer + total, buffer_size - total, wait_ms, 0, &received);
+        if (result != TCP_SUCCESS) {
+            break;
+        }
+        total += received;
+    }
+    
+    if (bytes_received) {
+        *bytes_received = total;
+    }
+    
+    return total >= min_bytes ? TCP_SUCCESS : result;
+}
+
+tcp_result_t tcp_socket_recv_flags(tcp_socket_t* sock, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received) {
+    return recv_with_flags(sock, buffer, buffer_size, timeout_ms, flags, bytes_received);
+}
+
+tcp_result_t tcp_socket_peek(tcp_socket_t* sock, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received) {
+    return recv_with_flags(sock, buffer, buffer_size, timeout_ms, MSG_PEEK, bytes_received);
+}
+
+// Receive from a client connection with extra recv flags
+static tcp_result_t recv_client_with_flags(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                      int timeout_ms, int flags, size_t* bytes_received) {
     if (!client || client->socket_fd < 0 || !buffer || buffer_size == 0) {
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    // Handle timeout
-    if (timeout_ms != 0) {
-        int select_result = wait_for_socket(client->socket_fd, true, timeout_ms);
+    // Wait according to the configured strategy (MSG_DONTWAIT never waits)
+    if (!(flags & MSG_DONTWAIT)) {
+        int wait_result = vma_common_wait_readable(client->socket_fd, client->wait_strategy,
+                                                   client->spin_budget_us, client->last_rx_ns, timeout_ms);
         
-        if (select_result == 0) {
+        if (wait_result == 0) {
             return TCP_ERROR_TIMEOUT;
-        } else if (select_result < 0) {
+        } else if (wait_result < 0) {
             return TCP_ERROR_RECV;
         }
     }
     
+    // A zero timeout never blocks, even on a blocking socket
+    if (timeout_ms == 0) {
+        flags |= MSG_DONTWAIT;
+    }
+    
     // Receive data
-    ssize_t res = recv(client->socket_fd, buffer, buffer_size, 0);
+    ssize_t res = recv(client->socket_fd, buffer, buffer_size, flags);
     
     if (res < 0) {
         if (would_block()) {
@@ -567,19 +1167,151 @@ tcp_result_t tcp_socket_recv_from_client(tcp_client_t* client, void* buffer, siz
         *bytes_received = (size_t)res;
     }
     
-    client->rx_bytes += res;
+    if (!(flags & MSG_PEEK)) {
+        client->rx_bytes += res;
+        client->last_rx_ns = vma_now_ns();
+    }
     
     return TCP_SUCCESS;
 }
 
+tcp_result_t tcp_socket_recv_from_client(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                      int timeout_ms, size_t* bytes_received) {
+    return recv_client_with_flags(client, buffer, buffer_size, timeout_ms, 0, bytes_received);
+}
+
+tcp_result_t tcp_socket_recv_from_client_flags(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                      int timeout_ms, int flags, size_t* bytes_received) {
+    return recv_client_with_flags(client, buffer, buffer_size, timeout_ms, flags, bytes_received);
+}
+
+tcp_result_t tcp_socket_peek_client(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                 int timeout_ms, size_t* bytes_received) {
+    return recv_client_with_flags(client, buffer, buffer_size, timeout_ms, MSG_PEEK, bytes_received);
+}
+
 tcp_result_t tcp_socket_close_client(tcp_client_t* client) {
     if (!client || client->socket_fd < 0) {
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    close(client->socket_fd);
+    int rc = close(client->socket_fd);
     client->socket_fd = -1;
     
+    return rc < 0 ? TCP_ERROR_CLOSE : TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_accept_new(tcp_socket_t* sock, tcp_client_t** client, int timeout_ms) {
+    if (!client) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    *client = NULL;
+    tcp_client_t* handle = calloc(1, sizeof(tcp_client_t));
+    if (!handle) {
+        return TCP_ERROR_ACCEPT;
+    }
+    
+    tcp_result_t result = tcp_socket_accept(sock, handle, timeout_ms);
+    if (result != TCP_SUCCESS) {
+        free(handle);
+        return result;
+    }
+    
+    *client = handle;
+    return TCP_SUCCESS;
+}
+
+void tcp_socket_free_client(tcp_client_t* client) {
+    if (!client) {
+        return;
+    }
+    
+    if (client->socket_fd >= 0) {
+        tcp_socket_close_client(client);
+    }
+    free(client);
+}
+
+int tcp_socket_client_fd(const tcp_client_t* client) {
+    return client ? client->socket_fd : -1;
+}
+
+void tcp_socket_get_client_addr(const tcp_client_t* client, struct sockaddr_in* addr) {
+    if (client && addr) {
+        *addr = client->addr;
+    }
+}
+
+void tcp_socket_get_client_stats(const tcp_client_t* client, uint64_t* rx_bytes,
+                                 uint64_t* tx_bytes, uint64_t* last_rx_ns) {
+    if (!client) {
+        return;
+    }
+    
+    if (rx_bytes) {
+        *rx_bytes = client->rx_bytes;
+    }
+    if (tx_bytes) {
+        *tx_bytes = client->tx_bytes;
+    }
+    if (last_rx_ns) {
+        *last_rx_ns = client->last_rx_ns;
+    }
+}
+
+void tcp_socket_set_client_wait_strategy(tcp_client_t* client, vma_wait_strategy_t strategy,
+                                         uint32_t spin_budget_us) {
+    if (client) {
+        client->wait_strategy = strategy;
+        client->spin_budget_us = spin_budget_us;
+    }
+}
+
+int tcp_socket_poll_readable(tcp_socket_t* sock, int timeout_ms) {
+    if (!sock) {
+        errno = EINVAL;
+        return -1;
+    }
+    return vma_common_poll_readable(sock->socket_fd, sock->vma_options.wait_strategy,
+                                    sock->vma_options.spin_budget_us, sock->stats.last_rx_ns, timeout_ms);
+}
+
+int tcp_socket_poll_readable_client(tcp_client_t* client, int timeout_ms) {
+    if (!client) {
+        errno = EINVAL;
+        return -1;
+    }
+    return vma_common_poll_readable(client->socket_fd, client->wait_strategy, client->spin_budget_us,
+                                    client->last_rx_ns, timeout_ms);
+}
+
+tcp_result_t tcp_socket_shutdown(tcp_socket_t* sock, int how) {
+    if (!sock || sock->socket_fd < 0 || (how != SHUT_RD && how != SHUT_WR && how != SHUT_RDWR)) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (sock->state != TCP_STATE_CONNECTED) {
+        return TCP_ERROR_NOT_INITIALIZED;
+    }
+    
+    // The state stays connected: after SHUT_WR the peer can still send until its FIN
+    if (shutdown(sock->socket_fd, how) < 0) {
+        return TCP_ERROR_SHUTDOWN;
+    }
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_shutdown_client(tcp_client_t* client, int how) {
+    if (!client || client->socket_fd < 0 || (how != SHUT_RD && how != SHUT_WR && how != SHUT_RDWR)) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (shutdown(client->socket_fd, how) < 0) {
+        return TCP_ERROR_SHUTDOWN;
+    }
+    
     return TCP_SUCCESS;
 }
 
@@ -596,6 +1328,57 @@ tcp_result_t tcp_socket_setopt(tcp_socket_t* sock, int level, int optname,
     return TCP_SUCCESS;
 }
 
+tcp_result_t tcp_socket_getopt(tcp_socket_t* sock, int level, int optname, 
+                            void* optval, socklen_t* optlen) {
+    if (!sock || sock->socket_fd < 0 || !optval || !optlen) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (getsockopt(sock->socket_fd, level, optname, optval, optlen) < 0) {
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_setopt_client(tcp_client_t* client, int level, int optname, 
+                                   const void* optval, socklen_t optlen) {
+    if (!client || client->socket_fd < 0 || !optval) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (setsockopt(client->socket_fd, level, optname, optval, optlen) < 0) {
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_getopt_client(tcp_client_t* client, int level, int optname, 
+                                   void* optval, socklen_t* optlen) {
+    if (!client || client->socket_fd < 0 || !optval || !optlen) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (getsockopt(client->socket_fd, level, optname, optval, optlen) < 0) {
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_set_tos(tcp_socket_t* sock, int tos) {
+    if (!sock || sock->socket_fd < 0 || tos < 0 || tos > 255) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    if (setsockopt(sock->socket_fd, IPPROTO_IP, IP_TOS, &tos, sizeof(tos)) < 0) {
+        return TCP_ERROR_SOCKET_OPTION;
+    }
+    
+    return TCP_SUCCESS;
+}
+
 tcp_result_t tcp_socket_get_stats(tcp_socket_t* sock, uint64_t* rx_packets, 
                                 uint64_t* tx_packets, uint64_t* rx_bytes, 
                                 uint64_t* tx_bytes) {
@@ -603,10 +1386,35 @@ tcp_result_t tcp_socket_get_stats(tcp_socket_t* sock, uint64_t* rx_packets,
         return TCP_ERROR_INVALID_PARAM;
     }
     
-    if (rx_packets) *rx_packets = sock->rx_packets;
-    if (tx_packets) *tx_packets = sock->tx_packets;
-    if (rx_bytes) *rx_bytes = sock->rx_bytes;
-    if (tx_bytes) *tx_bytes = sock->tx_bytes;
+    if (rx_packets) *rx_packets = sock->stats.rx_packets;
+    if (tx_packets) *tx_packets = sock->stats.tx_packets;
+    if (rx_bytes) *rx_bytes = sock->stats.rx_bytes;
+    if (tx_bytes) *tx_bytes = sock->stats.tx_bytes;
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_get_extended_stats(tcp_socket_t* sock, socket_stats_t* stats) {
+    if (!sock || !stats) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    *stats = sock->stats;
+    
+    return TCP_SUCCESS;
+}
+
+tcp_result_t tcp_socket_reset_stats(tcp_socket_t* sock) {
+    if (!sock) {
+        return TCP_ERROR_INVALID_PARAM;
+    }
+    
+    // Activity times are not counters and keep feeding idle detection
+    uint64_t last_rx_ns = sock->stats.last_rx_ns;
+    uint64_t last_tx_ns = sock->stats.last_tx_ns;
+    memset(&sock->stats, 0, sizeof(sock->stats));
+    sock->stats.last_rx_ns = last_rx_ns;
+    sock->stats.last_tx_ns = last_tx_ns;
     
     return TCP_SUCCESS;
-}
\ No newline at end of file
+}
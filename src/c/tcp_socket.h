@@ -11,6 +11,9 @@
 #include <sys/socket.h>
 #include "vma_common.h"
 
+// Maximum number of addresses tried by tcp_socket_connect_any (must match CONNECT_ANY_MAX in tcp.rs)
+#define TCP_CONNECT_ANY_MAX 16
+
 // TCP connection state
 typedef enum {
     TCP_STATE_DISCONNECTED = 0,
@@ -27,11 +30,9 @@ typedef struct {
     struct sockaddr_in remote_addr; // Remote address information
     bool is_bound;                  // Whether the socket is bound
     tcp_connection_state_t state;   // Connection state
-    uint64_t rx_packets;            // Number of received packets
-    uint64_t tx_packets;            // Number of transmitted packets
-    uint64_t rx_bytes;              // Number of received bytes
-    uint64_t tx_bytes;              // Number of transmitted bytes
+    socket_stats_t stats;           // Traffic counters
     int backlog;                    // Listen backlog
+    zerocopy_state_t zerocopy;      // MSG_ZEROCOPY send state
 } tcp_socket_t;
 
 // Client info structure (for accepted connections)
@@ -40,6 +41,9 @@ typedef struct {
     struct sockaddr_in addr;        // Client address
     uint64_t rx_bytes;              // Bytes received from this client
     uint64_t tx_bytes;              // Bytes sent to this client
+    vma_wait_strategy_t wait_strategy; // Receive wait strategy, inherited from the listener
+    uint32_t spin_budget_us;        // Busy-poll time before yielding or parking
+    uint64_t last_rx_ns;            // Time of the last successful receive (ns since epoch)
 } tcp_client_t;
 
 // Result codes
@@ -59,7 +63,10 @@ typedef enum {
     TCP_ERROR_NOT_INITIALIZED = -12,
     TCP_ERROR_CLOSED = -13,
     TCP_ERROR_WOULD_BLOCK = -14,
-    TCP_ERROR_ALREADY_CONNECTED = -15
+    TCP_ERROR_ALREADY_CONNECTED = -15,
+    TCP_ERROR_SHUTDOWN = -16,
+    TCP_ERROR_NOT_SUPPORTED = -17,
+    TCP_ERROR_CLOSE = -18
 } tcp_result_t;
 
 /**
@@ -74,11 +81,107 @@ tcp_result_t tcp_socket_init(tcp_socket_t* socket, const vma_options_t* options)
 /**
  * Release and close a TCP socket
  * 
+ * The descriptor is released even when close() fails; errno is then kept
+ * for the caller.
+ * 
  * @param socket Pointer to the TCP socket structure
- * @return Result code
+ * @return Result code (TCP_ERROR_CLOSE if close() failed)
  */
 tcp_result_t tcp_socket_close(tcp_socket_t* socket);
 
+/**
+ * Allocate and initialize a TCP socket handle
+ * 
+ * The structure stays private to this library: callers keep only the pointer, use the
+ * accessors below instead of reading fields, and release it with tcp_socket_free().
+ * 
+ * @param socket Output pointer receiving the new handle (NULL on failure)
+ * @param options VMA options (use default if NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_new(tcp_socket_t** socket, const vma_options_t* options);
+
+/**
+ * Close a handle from tcp_socket_new() if still open and free it
+ * 
+ * @param socket Handle to release (NULL is ignored)
+ */
+void tcp_socket_free(tcp_socket_t* socket);
+
+/**
+ * Allocate a TCP socket around an existing IPv4 stream descriptor
+ * 
+ * The descriptor keeps the options already set on it; the state (listening, connected
+ * or disconnected) and addresses are read back from the kernel. On failure the
+ * descriptor is left open.
+ * 
+ * @param sock Output pointer receiving the new socket (NULL on failure)
+ * @param fd Descriptor to adopt; owned by the socket on success
+ * @param options VMA options (use default if NULL)
+ * @return Result code (TCP_ERROR_INVALID_PARAM if fd is not an IPv4 TCP socket)
+ */
+tcp_result_t tcp_socket_from_fd(tcp_socket_t** sock, int fd, const vma_options_t* options);
+
+/**
+ * Detach the descriptor from a TCP socket without closing it
+ * 
+ * The socket is left closed and still has to be freed with tcp_socket_free().
+ * 
+ * @param sock Pointer to the TCP socket structure
+ * @return The descriptor, now owned by the caller, or -1 if the socket is closed
+ */
+int tcp_socket_release_fd(tcp_socket_t* sock);
+
+/**
+ * Get the file descriptor of a TCP socket
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @return File descriptor, or -1 if the socket is closed
+ */
+int tcp_socket_fd(const tcp_socket_t* socket);
+
+/**
+ * Get the connection state tracked by this library
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @return Connection state (TCP_STATE_DISCONNECTED for NULL)
+ */
+tcp_connection_state_t tcp_socket_get_state(const tcp_socket_t* socket);
+
+/**
+ * Get the address of the last connect, kept for reconnects
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param addr Output for the remote address
+ * @return true if a connect was attempted and addr was filled
+ */
+bool tcp_socket_get_peer(const tcp_socket_t* socket, struct sockaddr_in* addr);
+
+/**
+ * Record the TCP_NODELAY setting so reconnects and accepted connections apply it
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param nodelay Whether Nagle's algorithm is disabled
+ */
+void tcp_socket_set_nodelay_option(tcp_socket_t* socket, bool nodelay);
+
+/**
+ * Copy the options the socket was created with, including later runtime changes
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param options Receives the options
+ */
+void tcp_socket_get_options(const tcp_socket_t* socket, vma_options_t* options);
+
+/**
+ * Replace the recorded options; receives and accepts use the new wait strategy, and
+ * reconnects and accepted connections apply the new socket options
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param options New options
+ */
+void tcp_socket_set_options(tcp_socket_t* socket, const vma_options_t* options);
+
 /**
  * Bind a TCP socket to a local address
  * 
@@ -108,6 +211,27 @@ tcp_result_t tcp_socket_listen(tcp_socket_t* socket, int backlog);
  */
 tcp_result_t tcp_socket_accept(tcp_socket_t* socket, tcp_client_t* client, int timeout_ms);
 
+/**
+ * Accept a new client connection into a newly allocated client handle (server)
+ * 
+ * Release the handle with tcp_socket_free_client().
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param client Output pointer receiving the client handle (NULL on failure)
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_accept_new(tcp_socket_t* socket, tcp_client_t** client, int timeout_ms);
+
+/**
+ * Get the number of connections waiting in the accept queue (server)
+ * 
+ * @param socket Pointer to the listening TCP socket structure
+ * @param pending Output pointer to store the accept queue length
+ * @return Result code
+ */
+tcp_result_t tcp_socket_pending_connections(tcp_socket_t* socket, uint32_t* pending);
+
 /**
  * Connect to a server (client)
  * 
@@ -119,6 +243,46 @@ tcp_result_t tcp_socket_accept(tcp_socket_t* socket, tcp_client_t* client, int t
  */
 tcp_result_t tcp_socket_connect(tcp_socket_t* socket, const char* ip, uint16_t port, int timeout_ms);
 
+/**
+ * Start connecting to a server without waiting for completion
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param ip Server IP address
+ * @param port Server port
+ * @return TCP_SUCCESS if connected immediately, TCP_ERROR_WOULD_BLOCK if in progress, or an error code
+ */
+tcp_result_t tcp_socket_connect_start(tcp_socket_t* socket, const char* ip, uint16_t port);
+
+/**
+ * Wait for a connection started with tcp_socket_connect_start to complete
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @return TCP_SUCCESS once connected, TCP_ERROR_TIMEOUT if still in progress, or an error code
+ */
+tcp_result_t tcp_socket_connect_poll(tcp_socket_t* socket, int timeout_ms);
+
+/**
+ * Connect to the first reachable address of a prioritized list
+ * 
+ * Each attempt runs on its own descriptor configured like the socket's; the first to
+ * complete replaces the socket's descriptor and the others are closed. The next address
+ * is tried as soon as the previous attempt fails, or after stagger_ms while it is still
+ * in progress so that several attempts race. Socket options set after creation and a
+ * previous bind() are not carried over.
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param addrs Addresses in order of preference (at most TCP_CONNECT_ANY_MAX)
+ * @param count Number of addresses
+ * @param attempt_timeout_ms Timeout of each attempt in milliseconds (-1 for infinite wait)
+ * @param stagger_ms Delay before racing the next address (-1 to only try it after a failure)
+ * @param winner Set to the index of the address that connected
+ * @return TCP_SUCCESS once connected, TCP_ERROR_TIMEOUT if an attempt timed out and none
+ *         succeeded, TCP_ERROR_CONNECT if all were refused, or an error code
+ */
+tcp_result_t tcp_socket_connect_any(tcp_socket_t* socket, const struct sockaddr_in* addrs, size_t count,
+                                    int attempt_timeout_ms, int stagger_ms, size_t* winner);
+
 /**
  * Attempt to reconnect (when connection was lost)
  * 
@@ -147,6 +311,40 @@ bool tcp_socket_is_connected(tcp_socket_t* socket);
  */
 tcp_result_t tcp_socket_send(tcp_socket_t* socket, const void* data, size_t length, size_t* bytes_sent);
 
+/**
+ * Run a VMA dummy send (VMA_SND_FLAGS_DUMMY) on the connected socket
+ * 
+ * The segment goes through the VMA TX path to keep it warm but is never put on the wire.
+ * Not counted in the socket statistics.
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param data Representative payload
+ * @param length Payload length
+ * @return Result code (TCP_ERROR_NOT_SUPPORTED if the socket is not offloaded by VMA)
+ */
+tcp_result_t tcp_socket_send_dummy(tcp_socket_t* socket, const void* data, size_t length);
+
+/**
+ * Send data without copying it into socket buffers (MSG_ZEROCOPY, copying if unsupported)
+ * 
+ * The data must stay unchanged until tcp_socket_zerocopy_pending reports the send released.
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_send_zerocopy(tcp_socket_t* socket, const void* data, size_t length, size_t* bytes_sent);
+
+/**
+ * Get the number of zero-copy sends whose data is still in use
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @return Number of pending zero-copy sends
+ */
+uint32_t tcp_socket_zerocopy_pending(tcp_socket_t* socket);
+
 /**
  * Send data on a client socket
  * 
@@ -184,6 +382,119 @@ tcp_result_t tcp_socket_recv(tcp_socket_t* socket, void* buffer, size_t buffer_s
 tcp_result_t tcp_socket_recv_from_client(tcp_client_t* client, void* buffer, size_t buffer_size, 
                                     int timeout_ms, size_t* bytes_received);
 
+/**
+ * Send data with send(2) flags (MSG_NOSIGNAL is always added)
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param flags Flags passed to send (e.g., MSG_DONTWAIT, MSG_MORE)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_send_flags(tcp_socket_t* socket, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent);
+
+/**
+ * Send data, waiting for the socket to become writable until a timeout expires
+ * 
+ * Sends never block in send(2); while the send buffer is full the socket is polled for
+ * POLLOUT. On timeout bytes_sent holds the number of bytes written before it expired.
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param timeout_ms Timeout in milliseconds (-1 waits indefinitely, 0 makes a single attempt)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code (TCP_ERROR_TIMEOUT if not all data was sent in time)
+ */
+tcp_result_t tcp_socket_send_timeout(tcp_socket_t* socket, const void* data, size_t length, 
+                                     int timeout_ms, size_t* bytes_sent);
+
+/**
+ * Send data on a client socket with send(2) flags (MSG_NOSIGNAL is always added)
+ * 
+ * @param client Pointer to the client structure
+ * @param data Data to send
+ * @param length Data length
+ * @param flags Flags passed to send (e.g., MSG_DONTWAIT, MSG_MORE)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_send_to_client_flags(tcp_client_t* client, const void* data, size_t length, 
+                                    int flags, size_t* bytes_sent);
+
+/**
+ * Receive data with recv(2) flags
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait; ignored with MSG_DONTWAIT)
+ * @param flags Flags passed to recv (e.g., MSG_DONTWAIT, MSG_PEEK, MSG_WAITALL)
+ * @param bytes_received Number of bytes received (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_recv_flags(tcp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received);
+
+/**
+ * Receive data from a client with recv(2) flags
+ * 
+ * @param client Pointer to the client structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait; ignored with MSG_DONTWAIT)
+ * @param flags Flags passed to recv (e.g., MSG_DONTWAIT, MSG_PEEK, MSG_WAITALL)
+ * @param bytes_received Number of bytes received (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_recv_from_client_flags(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                    int timeout_ms, int flags, size_t* bytes_received);
+
+/**
+ * Receive at least min_bytes, looping over short reads until a timeout expires
+ * 
+ * Reads up to buffer_size bytes, so more than min_bytes may be returned. On timeout or
+ * when the peer closes the connection, bytes_received holds the bytes received so far.
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param min_bytes Minimum number of bytes to receive (1 to buffer_size)
+ * @param timeout_ms Timeout in milliseconds for the whole call (-1 waits indefinitely, 0 takes only queued data)
+ * @param bytes_received Number of bytes received (can be NULL)
+ * @return Result code (TCP_ERROR_TIMEOUT or TCP_ERROR_CLOSED if fewer than min_bytes arrived)
+ */
+tcp_result_t tcp_socket_recv_at_least(tcp_socket_t* socket, void* buffer, size_t buffer_size,
+                                     size_t min_bytes, int timeout_ms, size_t* bytes_received);
+
+/**
+ * Peek at received data without removing it from the stream (MSG_PEEK)
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @param bytes_received Number of bytes copied (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_peek(tcp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received);
+
+/**
+ * Peek at data received from a client without removing it from the stream (MSG_PEEK)
+ * 
+ * @param client Pointer to the client structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @param bytes_received Number of bytes copied (can be NULL)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_peek_client(tcp_client_t* client, void* buffer, size_t buffer_size, 
+                                 int timeout_ms, size_t* bytes_received);
+
 /**
  * Close a client connection
  * 
@@ -192,6 +503,86 @@ tcp_result_t tcp_socket_recv_from_client(tcp_client_t* client, void* buffer, siz
  */
 tcp_result_t tcp_socket_close_client(tcp_client_t* client);
 
+/**
+ * Close a handle from tcp_socket_accept_new() if still open and free it
+ * 
+ * @param client Handle to release (NULL is ignored)
+ */
+void tcp_socket_free_client(tcp_client_t* client);
+
+/**
+ * Get the file descriptor of a client connection
+ * 
+ * @param client Pointer to the client structure
+ * @return File descriptor, or -1 if the connection is closed
+ */
+int tcp_socket_client_fd(const tcp_client_t* client);
+
+/**
+ * Get the remote address of a client connection
+ * 
+ * @param client Pointer to the client structure
+ * @param addr Output for the client address
+ */
+void tcp_socket_get_client_addr(const tcp_client_t* client, struct sockaddr_in* addr);
+
+/**
+ * Get the traffic counters of a client connection
+ * 
+ * @param client Pointer to the client structure
+ * @param rx_bytes Output for bytes received (can be NULL)
+ * @param tx_bytes Output for bytes sent (can be NULL)
+ * @param last_rx_ns Output for the time of the last receive in ns since the epoch (can be NULL)
+ */
+void tcp_socket_get_client_stats(const tcp_client_t* client, uint64_t* rx_bytes,
+                                 uint64_t* tx_bytes, uint64_t* last_rx_ns);
+
+/**
+ * Override the receive wait strategy a client inherited from its listener
+ * 
+ * @param client Pointer to the client structure
+ * @param strategy Wait strategy for receives
+ * @param spin_budget_us Busy-poll time before yielding or parking
+ */
+void tcp_socket_set_client_wait_strategy(tcp_client_t* client, vma_wait_strategy_t strategy,
+                                         uint32_t spin_budget_us);
+
+/**
+ * Check or wait until data (or end-of-stream) can be received, following the socket's wait strategy
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param timeout_ms Timeout in milliseconds (0 only checks, -1 waits indefinitely)
+ * @return 1 if readable, 0 if not or on timeout, -1 on error (errno is set)
+ */
+int tcp_socket_poll_readable(tcp_socket_t* socket, int timeout_ms);
+
+/**
+ * Check or wait until data (or end-of-stream) can be received from a client
+ * 
+ * @param client Pointer to the client structure
+ * @param timeout_ms Timeout in milliseconds (0 only checks, -1 waits indefinitely)
+ * @return 1 if readable, 0 if not or on timeout, -1 on error (errno is set)
+ */
+int tcp_socket_poll_readable_client(tcp_client_t* client, int timeout_ms);
+
+/**
+ * Shut down one or both directions of a connection (half-close)
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param how SHUT_RD, SHUT_WR or SHUT_RDWR
+ * @return Result code
+ */
+tcp_result_t tcp_socket_shutdown(tcp_socket_t* socket, int how);
+
+/**
+ * Shut down one or both directions of a client connection (half-close)
+ * 
+ * @param client Pointer to the client structure
+ * @param how SHUT_RD, SHUT_WR or SHUT_RDWR
+ * @return Result code
+ */
+tcp_result_t tcp_socket_shutdown_client(tcp_client_t* client, int how);
+
 /**
  * Set socket options
  * 
@@ -205,6 +596,54 @@ tcp_result_t tcp_socket_close_client(tcp_client_t* client);
 tcp_result_t tcp_socket_setopt(tcp_socket_t* socket, int level, int optname, 
                             const void* optval, socklen_t optlen);
 
+/**
+ * Get socket options
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param level Option level (e.g., SOL_SOCKET)
+ * @param optname Option name
+ * @param optval Buffer receiving the option value
+ * @param optlen In: buffer size, out: option value length
+ * @return Result code
+ */
+tcp_result_t tcp_socket_getopt(tcp_socket_t* socket, int level, int optname, 
+                            void* optval, socklen_t* optlen);
+
+/**
+ * Set socket options on a client connection
+ * 
+ * @param client Pointer to the client structure
+ * @param level Option level (e.g., SOL_SOCKET)
+ * @param optname Option name
+ * @param optval Option value
+ * @param optlen Option value length
+ * @return Result code
+ */
+tcp_result_t tcp_socket_setopt_client(tcp_client_t* client, int level, int optname, 
+                                   const void* optval, socklen_t optlen);
+
+/**
+ * Get socket options of a client connection
+ * 
+ * @param client Pointer to the client structure
+ * @param level Option level (e.g., SOL_SOCKET)
+ * @param optname Option name
+ * @param optval Buffer receiving the option value
+ * @param optlen In: buffer size, out: option value length
+ * @return Result code
+ */
+tcp_result_t tcp_socket_getopt_client(tcp_client_t* client, int level, int optname, 
+                                   void* optval, socklen_t* optlen);
+
+/**
+ * Set the type-of-service byte of outgoing packets (IP_TOS)
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param tos TOS byte (DSCP in the upper 6 bits, ECN in the lower 2)
+ * @return Result code
+ */
+tcp_result_t tcp_socket_set_tos(tcp_socket_t* socket, int tos);
+
 /**
  * Get socket statistics
  * 
@@ -219,4 +658,21 @@ tcp_result_t tcp_socket_get_stats(tcp_socket_t* socket, uint64_t* rx_packets,
                                 uint64_t* tx_packets, uint64_t* rx_bytes, 
                                 uint64_t* tx_bytes);
 
+/**
+ * Get the full set of socket counters
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @param stats Output pointer to store the counters
+ * @return Result code
+ */
+tcp_result_t tcp_socket_get_extended_stats(tcp_socket_t* socket, socket_stats_t* stats);
+
+/**
+ * Zero the socket counters, keeping the last receive and send times
+ * 
+ * @param socket Pointer to the TCP socket structure
+ * @return Result code
+ */
+tcp_result_t tcp_socket_reset_stats(tcp_socket_t* socket);
+
 #endif /* TCP_SOCKET_H */
\ No newline at end of file
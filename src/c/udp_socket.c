@@ -16,15 +16,68 @@
 #include <signal.h>
 #include <errno.h>
 #include <arpa/inet.h>  // Include for inet_pton
+#include <netinet/udp.h>
 #include "udp_socket.h"
 #include "vma_common.h"
 #include <mellanox/vma_extra.h>
 
+#ifndef UDP_SEGMENT
+#define UDP_SEGMENT 103
+#endif
+
 // Fix this function to use the pointer correctly
 static void setup_vma_env(const vma_options_t* udp_options) {
     vma_setup_environment(udp_options);
 }
 
+// Configure a new descriptor from the options (rings, polling, buffers, timestamps)
+static udp_result_t configure_socket(int fd, const vma_options_t* options) {
+    // Select the VMA rings before bind/connect attaches them
+    if (vma_common_apply_ring_alloc(fd, options) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    // Set polling mode
+    if (options->use_polling) {
+        int flags = fcntl(fd, F_GETFL, 0);
+        if (flags >= 0) {
+            fcntl(fd, F_SETFL, flags | O_NONBLOCK);
+        }
+    }
+    
+    // Set buffer size
+    if (options->buffer_size > 0) {
+        int buffer_size = options->buffer_size;
+        
+        // Set send buffer size
+        if (setsockopt(fd, SOL_SOCKET, SO_SNDBUF, 
+                    &buffer_size, sizeof(buffer_size)) < 0) {
+            return UDP_ERROR_SOCKET_OPTION;
+        }
+        
+        // Set receive buffer size
+        if (setsockopt(fd, SOL_SOCKET, SO_RCVBUF, 
+                    &buffer_size, sizeof(buffer_size)) < 0) {
+            return UDP_ERROR_SOCKET_OPTION;
+        }
+    }
+    
+    // Enable timestamps if requested
+    if (options->enable_timestamps) {
+        int optval = 1;
+        // Use more precise hardware timestamps when available
+        setsockopt(fd, SOL_SOCKET, SO_TIMESTAMPNS, &optval, sizeof(optval));
+    }
+    
+    // Optimize VMA ring allocation when using SocketXtreme
+    if (options->use_socketxtreme) {
+        int optval = 1;
+        setsockopt(fd, SOL_SOCKET, SO_VMA_RING_ALLOC_LOGIC, &optval, sizeof(optval));
+    }
+    
+    return UDP_SUCCESS;
+}
+
 // Enhanced UDP socket initialization with additional optimizations
 udp_result_t udp_socket_init(udp_socket_t* udp_socket, const vma_options_t* options) {
     if (!udp_socket) {
@@ -50,58 +103,129 @@ udp_result_t udp_socket_init(udp_socket_t* udp_socket, const vma_options_t* opti
         return UDP_ERROR_SOCKET_CREATE;
     }
     
-    // Set polling mode
-    if (udp_socket->vma_options.use_polling) {
-        int flags = fcntl(udp_socket->socket_fd, F_GETFL, 0);
-        if (flags >= 0) {
-            fcntl(udp_socket->socket_fd, F_SETFL, flags | O_NONBLOCK);
-        }
+    return configure_socket(udp_socket->socket_fd, &udp_socket->vma_options);
+}
+
+udp_result_t udp_socket_close(udp_socket_t* socket) {
+    if (!socket || socket->socket_fd < 0) {
+        return UDP_ERROR_INVALID_PARAM;
     }
     
-    // Set buffer size
-    if (udp_socket->vma_options.buffer_size > 0) {
-        int buffer_size = udp_socket->vma_options.buffer_size;
-        
-        // Set send buffer size
-        if (setsockopt(udp_socket->socket_fd, SOL_SOCKET, SO_SNDBUF, 
-                    &buffer_size, sizeof(buffer_size)) < 0) {
-            return UDP_ERROR_SOCKET_OPTION;
-        }
-        
-        // Set receive buffer size
-        if (setsockopt(udp_socket->socket_fd, SOL_SOCKET, SO_RCVBUF, 
-                    &buffer_size, sizeof(buffer_size)) < 0) {
-            return UDP_ERROR_SOCKET_OPTION;
+    int rc = close(socket->socket_fd);
+    socket->socket_fd = -1;
+    socket->is_bound = false;
+    socket->is_connected = false;
+    
+    return rc < 0 ? UDP_ERROR_CLOSE : UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_new(udp_socket_t** socket, const vma_options_t* options) {
+    if (!socket) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    *socket = NULL;
+    udp_socket_t* handle = calloc(1, sizeof(udp_socket_t));
+    if (!handle) {
+        return UDP_ERROR_SOCKET_CREATE;
+    }
+    
+    udp_result_t result = udp_socket_init(handle, options);
+    if (result != UDP_SUCCESS) {
+        // init may fail after creating the descriptor
+        if (handle->socket_fd >= 0) {
+            close(handle->socket_fd);
         }
+        free(handle);
+        return result;
     }
     
-    // Enable timestamps if requested
-    if (udp_socket->vma_options.enable_timestamps) {
-        int optval = 1;
-        // Use more precise hardware timestamps when available
-        setsockopt(udp_socket->socket_fd, SOL_SOCKET, SO_TIMESTAMPNS, &optval, sizeof(optval));
+    *socket = handle;
+    return UDP_SUCCESS;
+}
+
+void udp_socket_free(udp_socket_t* socket) {
+    if (!socket) {
+        return;
     }
     
-    // Optimize VMA ring allocation when using SocketXtreme
-    if (udp_socket->vma_options.use_socketxtreme) {
-        int optval = 1;
-        setsockopt(udp_socket->socket_fd, SOL_SOCKET, SO_VMA_RING_ALLOC_LOGIC, &optval, sizeof(optval));
+    if (socket->socket_fd >= 0) {
+        udp_socket_close(socket);
+    }
+    free(socket);
+}
+
+udp_result_t udp_socket_from_fd(udp_socket_t** socket, int fd, const vma_options_t* options) {
+    if (!socket) {
+        return UDP_ERROR_INVALID_PARAM;
     }
     
+    *socket = NULL;
+    if (vma_common_check_socket_fd(fd, SOCK_DGRAM) < 0) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    udp_socket_t* handle = calloc(1, sizeof(udp_socket_t));
+    if (!handle) {
+        return UDP_ERROR_SOCKET_CREATE;
+    }
+    
+    if (options) {
+        handle->vma_options = *options;
+    } else {
+        set_default_options(&handle->vma_options);
+    }
+    handle->socket_fd = fd;
+    
+    if (vma_common_get_local_addr(fd, &handle->local_addr) == 0 && handle->local_addr.sin_port != 0) {
+        handle->is_bound = true;
+    }
+    socklen_t addr_len = sizeof(handle->remote_addr);
+    if (getpeername(fd, (struct sockaddr*)&handle->remote_addr, &addr_len) == 0) {
+        handle->is_connected = true;
+    } else {
+        memset(&handle->remote_addr, 0, sizeof(handle->remote_addr));
+    }
+    
+    *socket = handle;
     return UDP_SUCCESS;
 }
 
-udp_result_t udp_socket_close(udp_socket_t* socket) {
+int udp_socket_release_fd(udp_socket_t* socket) {
     if (!socket || socket->socket_fd < 0) {
-        return UDP_ERROR_INVALID_PARAM;
+        return -1;
     }
     
-    close(socket->socket_fd);
+    int fd = socket->socket_fd;
     socket->socket_fd = -1;
     socket->is_bound = false;
     socket->is_connected = false;
+    return fd;
+}
+
+int udp_socket_fd(const udp_socket_t* socket) {
+    return socket ? socket->socket_fd : -1;
+}
+
+void udp_socket_get_options(const udp_socket_t* socket, vma_options_t* options) {
+    if (socket && options) {
+        *options = socket->vma_options;
+    }
+}
+
+void udp_socket_set_options(udp_socket_t* socket, const vma_options_t* options) {
+    if (socket && options) {
+        socket->vma_options = *options;
+    }
+}
+
+bool udp_socket_get_peer(const udp_socket_t* socket, struct sockaddr_in* addr) {
+    if (!socket || !addr || !socket->is_connected) {
+        return false;
+    }
     
-    return UDP_SUCCESS;
+    *addr = socket->remote_addr;
+    return true;
 }
 
 udp_result_t udp_socket_bind(udp_socket_t* socket, const char* ip, uint16_t port) {
@@ -156,7 +280,231 @@ udp_result_t udp_socket_connect(udp_socket_t* socket, const char* ip, uint16_t p
     return UDP_SUCCESS;
 }
 
+udp_result_t udp_socket_disconnect(udp_socket_t* socket) {
+    if (!socket || socket->socket_fd < 0) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    // The kernel releases a port picked by bind(port 0) on disconnect: note it to take it back
+    struct sockaddr_in bound;
+    socklen_t bound_len = sizeof(bound);
+    bool rebind = socket->is_bound && socket->local_addr.sin_port == 0 &&
+                  getsockname(socket->socket_fd, (struct sockaddr*)&bound, &bound_len) == 0;
+    
+    // Connecting to AF_UNSPEC dissolves the association; an explicit bind is kept
+    struct sockaddr unspec;
+    memset(&unspec, 0, sizeof(unspec));
+    unspec.sa_family = AF_UNSPEC;
+    if (connect(socket->socket_fd, &unspec, sizeof(unspec)) < 0) {
+        return UDP_ERROR_CONNECT;
+    }
+    
+    memset(&socket->remote_addr, 0, sizeof(socket->remote_addr));
+    socket->is_connected = false;
+    
+    if (rebind) {
+        // getsockname reported the source address of the association, not the bound one
+        bound.sin_addr = socket->local_addr.sin_addr;
+        if (bind(socket->socket_fd, (struct sockaddr*)&bound, sizeof(bound)) < 0) {
+            socket->is_bound = false;
+            return UDP_ERROR_BIND;
+        }
+    }
+    return UDP_SUCCESS;
+}
+
+// Socket options carried over to the descriptor created by udp_socket_rebind()
+static const struct {
+    int level;
+    int optname;
+} rebind_options[] = {
+    { SOL_SOCKET, SO_RCVBUF },
+    { SOL_SOCKET, SO_SNDBUF },
+    { SOL_SOCKET, SO_REUSEADDR },
+    { SOL_SOCKET, SO_REUSEPORT },
+    { SOL_SOCKET, SO_PRIORITY },
+    { IPPROTO_IP, IP_TTL },
+    { IPPROTO_IP, IP_TOS },
+    { IPPROTO_IP, IP_MULTICAST_TTL },
+    { IPPROTO_IP, IP_MULTICAST_LOOP },
+    { IPPROTO_IP, IP_MULTICAST_ALL },
+    { IPPROTO_IP, IP_MULTICAST_IF },
+    { IPPROTO_IP, IP_PKTINFO },
+};
+
+static int copy_socket_options(int from, int to) {
+    for (size_t i = 0; i < sizeof(rebind_options) / sizeof(rebind_options[0]); i++) {
+        int value = 0;
+        socklen_t len = sizeof(value);
+        if (getsockopt(from, rebind_options[i].level, rebind_options[i].optname, &value, &len) < 0) {
+            continue;
+        }
+        // The kernel reports doubled buffer sizes
+        if (rebind_options[i].level == SOL_SOCKET &&
+            (rebind_options[i].optname == SO_RCVBUF || rebind_options[i].optname == SO_SNDBUF)) {
+            value /= 2;
+        }
+        if (setsockopt(to, rebind_options[i].level, rebind_options[i].optname, &value, len) < 0) {
+            return -1;
+        }
+    }
+    
+    // Timeouts are struct timeval
+    int timeouts[] = { SO_RCVTIMEO, SO_SNDTIMEO };
+    for (size_t i = 0; i < 2; i++) {
+        struct timeval tv;
+        socklen_t len = sizeof(tv);
+        if (getsockopt(from, SOL_SOCKET, timeouts[i], &tv, &len) == 0 &&
+            setsockopt(to, SOL_SOCKET, timeouts[i], &tv, len) < 0) {
+            return -1;
+        }
+    }
+    
+    // Keep blocking mode
+    int flags = fcntl(from, F_GETFL, 0);
+    if (flags >= 0 && (flags & O_NONBLOCK)) {
+        int to_flags = fcntl(to, F_GETFL, 0);
+        fcntl(to, F_SETFL, (to_flags < 0 ? 0 : to_flags) | O_NONBLOCK);
+    }
+    
+    return 0;
+}
+
+udp_result_t udp_socket_rebind(udp_socket_t* udp_socket, const char* ip, uint16_t port,
+                               const struct ip_mreq* groups, size_t group_count) {
+    if (!udp_socket || udp_socket->socket_fd < 0 || (!groups && group_count > 0)) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    struct sockaddr_in local_addr;
+    memset(&local_addr, 0, sizeof(local_addr));
+    local_addr.sin_family = AF_INET;
+    local_addr.sin_port = htons(port);
+    if (ip) {
+        if (inet_pton(AF_INET, ip, &local_addr.sin_addr) <= 0) {
+            return UDP_ERROR_INVALID_PARAM;
+        }
+    } else {
+        local_addr.sin_addr.s_addr = INADDR_ANY;
+    }
+    
+    // Not bound yet: bind in place and keep the descriptor with its ring. A connected
+    // socket was bound implicitly and fails with EINVAL, falling through to a new one
+    if (!udp_socket->is_bound) {
+        if (bind(udp_socket->socket_fd, (struct sockaddr*)&local_addr, sizeof(local_addr)) == 0) {
+            udp_socket->local_addr = local_addr;
+            udp_socket->is_bound = true;
+            return UDP_SUCCESS;
+        }
+        if (errno != EINVAL) {
+            return UDP_ERROR_BIND;
+        }
+    }
+    
+    // A bound socket cannot be bound again: build the replacement first, so a failure
+    // leaves the current descriptor untouched
+    int fd = socket(AF_INET, SOCK_DGRAM, IPPROTO_UDP);
+    if (fd < 0) {
+        return UDP_ERROR_SOCKET_CREATE;
+    }
+    
+    udp_result_t result = configure_socket(fd, &udp_socket->vma_options);
+    if (result == UDP_SUCCESS && copy_socket_options(udp_socket->socket_fd, fd) < 0) {
+        result = UDP_ERROR_SOCKET_OPTION;
+    }
+    if (result == UDP_SUCCESS &&
+        bind(fd, (struct sockaddr*)&local_addr, sizeof(local_addr)) < 0) {
+        result = UDP_ERROR_BIND;
+    }
+    if (result == UDP_SUCCESS && udp_socket->is_connected &&
+        connect(fd, (struct sockaddr*)&udp_socket->remote_addr, sizeof(udp_socket->remote_addr)) < 0) {
+        result = UDP_ERROR_CONNECT;
+    }
+    for (size_t i = 0; result == UDP_SUCCESS && i < group_count; i++) {
+        if (setsockopt(fd, IPPROTO_IP, IP_ADD_MEMBERSHIP, &groups[i], sizeof(groups[i])) < 0) {
+            result = UDP_ERROR_SOCKET_OPTION;
+        }
+    }
+    if (result != UDP_SUCCESS) {
+        close(fd);
+        return result;
+    }
+    
+    close(udp_socket->socket_fd);
+    udp_socket->socket_fd = fd;
+    udp_socket->local_addr = local_addr;
+    udp_socket->is_bound = true;
+    udp_socket->zerocopy = (zerocopy_state_t){ 0 };
+    return UDP_SUCCESS;
+}
+
+// Send on the connected socket with extra send flags
+static udp_result_t send_with_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent) {
+    if (!socket || socket->socket_fd < 0 || !data || length == 0) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (!socket->is_connected) {
+        return UDP_ERROR_NOT_INITIALIZED;
+    }
+    
+    ssize_t res = send(socket->socket_fd, data, length, flags);
+    
+    if (res < 0) {
+        if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            socket->stats.tx_eagain++;
+            return UDP_ERROR_TIMEOUT;
+        }
+        socket->stats.tx_errors++;
+        return UDP_ERROR_SEND;
+    }
+    
+    if (bytes_sent) {
+        *bytes_sent = (size_t)res;
+    }
+    
+    socket->stats.tx_packets++;
+    socket->stats.tx_bytes += res;
+    socket->stats.last_tx_ns = vma_now_ns();
+    
+    return UDP_SUCCESS;
+}
+
 udp_result_t udp_socket_send(udp_socket_t* socket, const void* data, size_t length, size_t* bytes_sent) {
+    return send_with_flags(socket, data, length, 0, bytes_sent);
+}
+
+udp_result_t udp_socket_send_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent) {
+    return send_with_flags(socket, data, length, flags, bytes_sent);
+}
+
+udp_result_t udp_socket_send_dummy(udp_socket_t* socket, const void* data, size_t length) {
+    if (!socket || socket->socket_fd < 0 || !data || length == 0) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (!socket->is_connected) {
+        return UDP_ERROR_NOT_INITIALIZED;
+    }
+    
+    // The kernel ignores the dummy flag and would put the packet on the wire
+    if (!vma_common_is_offloaded(socket->socket_fd)) {
+        return UDP_ERROR_NOT_SUPPORTED;
+    }
+    
+    if (send(socket->socket_fd, data, length, VMA_SND_FLAGS_DUMMY | MSG_DONTWAIT) < 0) {
+        if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            return UDP_ERROR_TIMEOUT;
+        }
+        return UDP_ERROR_SEND;
+    }
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_send_zerocopy(udp_socket_t* socket, const void* data, size_t length, int flags, size_t* bytes_sent) {
     if (!socket || socket->socket_fd < 0 || !data || length == 0) {
         return UDP_ERROR_INVALID_PARAM;
     }
@@ -165,12 +513,14 @@ udp_result_t udp_socket_send(udp_socket_t* socket, const void* data, size_t leng
         return UDP_ERROR_NOT_INITIALIZED;
     }
     
-    ssize_t res = send(socket->socket_fd, data, length, 0);
+    ssize_t res = vma_common_send_zerocopy(socket->socket_fd, &socket->zerocopy, data, length, flags);
     
     if (res < 0) {
         if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            socket->stats.tx_eagain++;
             return UDP_ERROR_TIMEOUT;
         }
+        socket->stats.tx_errors++;
         return UDP_ERROR_SEND;
     }
     
@@ -178,15 +528,53 @@ udp_result_t udp_socket_send(udp_socket_t* socket, const void* data, size_t leng
         *bytes_sent = (size_t)res;
     }
     
-    socket->tx_packets++;
-    socket->tx_bytes += res;
+    socket->stats.tx_packets++;
+    socket->stats.tx_bytes += res;
+    socket->stats.last_tx_ns = vma_now_ns();
     
     return UDP_SUCCESS;
 }
 
-udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t length, 
-                            const char* ip, uint16_t port, size_t* bytes_sent) {
-    if (!socket || socket->socket_fd < 0 || !data || length == 0 || !ip) {
+uint32_t udp_socket_zerocopy_pending(udp_socket_t* socket) {
+    if (!socket) {
+        return 0;
+    }
+    return vma_common_zerocopy_pending(socket->socket_fd, &socket->zerocopy);
+}
+
+// Send to a prepared address with extra send flags
+static udp_result_t sendto_addr_with_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            const struct sockaddr_in* dest_addr, int flags, size_t* bytes_sent) {
+    if (!socket || socket->socket_fd < 0 || !data || length == 0 || !dest_addr) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    ssize_t res = sendto(socket->socket_fd, data, length, flags, 
+                    (const struct sockaddr*)dest_addr, sizeof(*dest_addr));
+    
+    if (res < 0) {
+        if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            socket->stats.tx_eagain++;
+            return UDP_ERROR_TIMEOUT;
+        }
+        socket->stats.tx_errors++;
+        return UDP_ERROR_SEND;
+    }
+    
+    if (bytes_sent) {
+        *bytes_sent = (size_t)res;
+    }
+    
+    socket->stats.tx_packets++;
+    socket->stats.tx_bytes += res;
+    socket->stats.last_tx_ns = vma_now_ns();
+    
+    return UDP_SUCCESS;
+}
+
+static udp_result_t sendto_with_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            const char* ip, uint16_t port, int flags, size_t* bytes_sent) {
+    if (!ip) {
         return UDP_ERROR_INVALID_PARAM;
     }
     
@@ -199,13 +587,141 @@ udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t le
         return UDP_ERROR_INVALID_PARAM;
     }
     
-    ssize_t res = sendto(socket->socket_fd, data, length, 0, 
-                    (struct sockaddr*)&dest_addr, sizeof(dest_addr));
+    return sendto_addr_with_flags(socket, data, length, &dest_addr, flags, bytes_sent);
+}
+
+udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t length, 
+                            const char* ip, uint16_t port, size_t* bytes_sent) {
+    return sendto_with_flags(socket, data, length, ip, port, 0, bytes_sent);
+}
+
+udp_result_t udp_socket_sendto_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            const char* ip, uint16_t port, int flags, size_t* bytes_sent) {
+    return sendto_with_flags(socket, data, length, ip, port, flags, bytes_sent);
+}
+
+udp_result_t udp_socket_sendto_addr(udp_socket_t* socket, const void* data, size_t length, 
+                            const struct sockaddr_in* addr, int flags, size_t* bytes_sent) {
+    return sendto_addr_with_flags(socket, data, length, addr, flags, bytes_sent);
+}
+
+udp_result_t udp_socket_send_batch(udp_socket_t* socket, const void* data, const size_t* offsets,
+                                  const struct sockaddr_in* addrs, size_t count, int flags, size_t* sent) {
+    if (!socket || socket->socket_fd < 0 || !data || !offsets || count == 0 ||
+        count > UDP_SEND_BATCH_MAX || !sent) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    *sent = 0;
+    
+    struct mmsghdr msgs[UDP_SEND_BATCH_MAX];
+    struct iovec iovs[UDP_SEND_BATCH_MAX];
+    memset(msgs, 0, sizeof(msgs[0]) * count);
+    for (size_t i = 0; i < count; i++) {
+        if (offsets[i + 1] <= offsets[i]) {
+            return UDP_ERROR_INVALID_PARAM;
+        }
+        iovs[i].iov_base = (char*)data + offsets[i];
+        iovs[i].iov_len = offsets[i + 1] - offsets[i];
+        bool to_peer = !addrs || addrs[i].sin_family == 0;
+        if (to_peer && !socket->is_connected) {
+            return UDP_ERROR_NOT_INITIALIZED;
+        }
+        msgs[i].msg_hdr.msg_name = to_peer ? NULL : (void*)&addrs[i];
+        msgs[i].msg_hdr.msg_namelen = to_peer ? 0 : sizeof(addrs[i]);
+        msgs[i].msg_hdr.msg_iov = &iovs[i];
+        msgs[i].msg_hdr.msg_iovlen = 1;
+    }
+    
+    // sendmmsg may take only part of the batch; continue from where it stopped
+    size_t done = 0;
+    while (done < count) {
+        int res = sendmmsg(socket->socket_fd, &msgs[done], (unsigned int)(count - done), flags);
+        if (res < 0) {
+            *sent = done;
+            if (errno == EAGAIN || errno == EWOULDBLOCK) {
+                socket->stats.tx_eagain++;
+                return UDP_ERROR_TIMEOUT;
+            }
+            socket->stats.tx_errors++;
+            return UDP_ERROR_SEND;
+        }
+        for (int i = 0; i < res; i++) {
+            socket->stats.tx_bytes += msgs[done + i].msg_len;
+        }
+        socket->stats.tx_packets += (uint64_t)res;
+        socket->stats.last_tx_ns = vma_now_ns();
+        done += (size_t)res;
+    }
+    
+    *sent = done;
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_send_gso(udp_socket_t* socket, const void* data, size_t length,
+                            uint16_t segment_size, const struct sockaddr_in* dest_addr, int flags,
+                            size_t* bytes_sent) {
+    if (!socket || socket->socket_fd < 0 || !data || length == 0 || segment_size == 0) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    size_t segments = (length + segment_size - 1) / segment_size;
+    if (segments > UDP_GSO_MAX_SEGMENTS) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (!dest_addr && !socket->is_connected) {
+        return UDP_ERROR_NOT_INITIALIZED;
+    }
+    
+    // One datagram per segment_size bytes, split by the stack (UDP_SEGMENT ancillary data)
+    struct iovec iov = { .iov_base = (void*)data, .iov_len = length };
+    char control[CMSG_SPACE(sizeof(uint16_t))];
+    struct msghdr msg;
+    memset(&msg, 0, sizeof(msg));
+    memset(control, 0, sizeof(control));
+    msg.msg_name = (void*)dest_addr;
+    msg.msg_namelen = dest_addr ? sizeof(*dest_addr) : 0;
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control;
+    msg.msg_controllen = sizeof(control);
+    
+    struct cmsghdr* cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_UDP;
+    cmsg->cmsg_type = UDP_SEGMENT;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(uint16_t));
+    memcpy(CMSG_DATA(cmsg), &segment_size, sizeof(segment_size));
+    
+    ssize_t res = sendmsg(socket->socket_fd, &msg, flags);
+    
+    if (res < 0 && (errno == EOPNOTSUPP || errno == ENOPROTOOPT)) {
+        // No segmentation offload (old kernel or VMA path): one send per segment
+        size_t sent = 0;
+        while (sent < length) {
+            size_t chunk = length - sent < segment_size ? length - sent : segment_size;
+            udp_result_t result = dest_addr
+                ? sendto_addr_with_flags(socket, (const char*)data + sent, chunk, dest_addr, flags, NULL)
+                : send_with_flags(socket, (const char*)data + sent, chunk, flags, NULL);
+            if (result != UDP_SUCCESS) {
+                if (bytes_sent) {
+                    *bytes_sent = sent;
+                }
+                return result;
+            }
+            sent += chunk;
+        }
+        if (bytes_sent) {
+            *bytes_sent = sent;
+        }
+        return UDP_SUCCESS;
+    }
     
     if (res < 0) {
         if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            socket->stats.tx_eagain++;
             return UDP_ERROR_TIMEOUT;
         }
+        socket->stats.tx_errors++;
         return UDP_ERROR_SEND;
     }
     
@@ -213,124 +729,264 @@ udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t le
         *bytes_sent = (size_t)res;
     }
     
-    socket->tx_packets++;
-    socket->tx_bytes += res;
+    socket->stats.tx_packets += segments;
+    socket->stats.tx_bytes += res;
+    socket->stats.last_tx_ns = vma_now_ns();
     
     return UDP_SUCCESS;
 }
 
-udp_result_t udp_socket_recv(udp_socket_t* socket, void* buffer, size_t buffer_size, 
-                            int timeout_ms, size_t* bytes_received) {
+// Receive on the socket with extra recv flags (MSG_PEEK leaves the datagram queued and the counters untouched)
+static udp_result_t recv_with_flags(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received,
+                            size_t* datagram_length) {
     if (!socket || socket->socket_fd < 0 || !buffer || buffer_size == 0) {
         return UDP_ERROR_INVALID_PARAM;
     }
     
-    // Handle timeout based on socket mode
-    if (!socket->vma_options.use_polling && timeout_ms != -1) {
-        // For non-polling mode with timeout, use select
-        fd_set readfds;
-        struct timeval tv;
-        
-        FD_ZERO(&readfds);
-        FD_SET(socket->socket_fd, &readfds);
-        
-        if (timeout_ms >= 0) {
-            tv.tv_sec = timeout_ms / 1000;
-            tv.tv_usec = (timeout_ms % 1000) * 1000;
-        }
+    // Wait according to the configured strategy (MSG_DONTWAIT never waits)
+    if (!(flags & MSG_DONTWAIT)) {
+        int wait_result = vma_common_wait_readable(socket->socket_fd, socket->vma_options.wait_strategy,
+                                                   socket->vma_options.spin_budget_us, socket->stats.last_rx_ns,
+                                                   timeout_ms);
         
-        int select_result = select(socket->socket_fd + 1, &readfds, NULL, NULL, 
-                                timeout_ms >= 0 ? &tv : NULL);
-        
-        if (select_result == 0) {
+        if (wait_result == 0) {
             return UDP_ERROR_TIMEOUT;
-        } else if (select_result < 0) {
+        } else if (wait_result < 0) {
             return UDP_ERROR_RECV;
         }
     }
     
-    // Receive data
-    ssize_t res = recv(socket->socket_fd, buffer, buffer_size, 0);
+    // A zero timeout never blocks, even on a blocking socket
+    if (timeout_ms == 0) {
+        flags |= MSG_DONTWAIT;
+    }
+    
+    // Receive data (MSG_TRUNC makes recv report the full datagram length)
+    ssize_t res = recv(socket->socket_fd, buffer, buffer_size, MSG_TRUNC | flags);
     
     if (res < 0) {
         if (errno == EAGAIN || errno == EWOULDBLOCK) {
             // For polling mode or immediate timeout
+            socket->stats.rx_eagain++;
             return UDP_ERROR_TIMEOUT;
         }
+        socket->stats.rx_errors++;
         return UDP_ERROR_RECV;
     } else if (res == 0) {
         return UDP_ERROR_CLOSED;
     }
     
+    if (datagram_length) {
+        *datagram_length = (size_t)res;
+    }
+    
+    bool peek = (flags & MSG_PEEK) != 0;
+    if ((size_t)res > buffer_size) {
+        if (!peek) {
+            socket->stats.rx_truncated++;
+        }
+        res = (ssize_t)buffer_size;
+    }
+    
     if (bytes_received) {
         *bytes_received = (size_t)res;
     }
     
-    socket->rx_packets++;
-    socket->rx_bytes += res;
+    if (!peek) {
+        socket->stats.rx_packets++;
+        socket->stats.rx_bytes += res;
+        socket->stats.last_rx_ns = vma_now_ns();
+    }
     
     return UDP_SUCCESS;
 }
 
-udp_result_t udp_socket_recvfrom(udp_socket_t* socket, udp_packet_t* packet,
-                            void* buffer, size_t buffer_size, int timeout_ms) {
+udp_result_t udp_socket_recv(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received) {
+    return recv_with_flags(socket, buffer, buffer_size, timeout_ms, 0, bytes_received, NULL);
+}
+
+udp_result_t udp_socket_recv_full(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received, size_t* datagram_length) {
+    return recv_with_flags(socket, buffer, buffer_size, timeout_ms, 0, bytes_received, datagram_length);
+}
+
+udp_result_t udp_socket_recv_flags(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received) {
+    return recv_with_flags(socket, buffer, buffer_size, timeout_ms, flags, bytes_received, NULL);
+}
+
+udp_result_t udp_socket_peek(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received) {
+    return recv_with_flags(socket, buffer, buffer_size, timeout_ms, MSG_PEEK, bytes_received, NULL);
+}
+
+// Receive a datagram with its source address and pktinfo, with extra recv flags
+// Fill a received packet from its message header and update the receive counters
+static void fill_packet(udp_socket_t* socket, udp_packet_t* packet, struct msghdr* msg,
+                        size_t res, void* buffer, size_t buffer_size, bool peek) {
+    packet->full_length = res;
+    
+    if (res > buffer_size) {
+        if (!peek) {
+            socket->stats.rx_truncated++;
+        }
+        res = buffer_size;
+    }
+    
+    // Set packet structure
+    packet->data = buffer;
+    packet->length = res;
+    
+    packet->dst_addr.s_addr = INADDR_ANY;
+    packet->if_index = 0;
+    packet->timestamp = 0;
+    for (struct cmsghdr* cmsg = CMSG_FIRSTHDR(msg); cmsg; cmsg = CMSG_NXTHDR(msg, cmsg)) {
+        if (cmsg->cmsg_level == IPPROTO_IP && cmsg->cmsg_type == IP_PKTINFO) {
+            struct in_pktinfo info;
+            memcpy(&info, CMSG_DATA(cmsg), sizeof(info));
+            packet->dst_addr = info.ipi_addr;
+            packet->if_index = info.ipi_ifindex;
+        } else if (cmsg->cmsg_level == SOL_SOCKET && cmsg->cmsg_type == SCM_TIMESTAMPNS) {
+            // Receive timestamp taken by the kernel (or VMA) when the datagram arrived
+            struct timespec ts;
+            memcpy(&ts, CMSG_DATA(cmsg), sizeof(ts));
+            packet->timestamp = (uint64_t)ts.tv_sec * 1000000000ULL + ts.tv_nsec;
+        }
+    }
+    
+    if (!peek) {
+        socket->stats.rx_packets++;
+        socket->stats.rx_bytes += res;
+        socket->stats.last_rx_ns = vma_now_ns();
+    }
+}
+
+static udp_result_t recvfrom_with_flags(udp_socket_t* socket, udp_packet_t* packet,
+                            void* buffer, size_t buffer_size, int timeout_ms, int flags) {
     if (!socket || socket->socket_fd < 0 || !packet || !buffer || buffer_size == 0) {
         return UDP_ERROR_INVALID_PARAM;
     }
     
-    // Handle timeout based on socket mode
-    if (!socket->vma_options.use_polling && timeout_ms != -1) {
-        // For non-polling mode with timeout, use select
-        fd_set readfds;
-        struct timeval tv;
-        
-        FD_ZERO(&readfds);
-        FD_SET(socket->socket_fd, &readfds);
-        
-        if (timeout_ms >= 0) {
-            tv.tv_sec = timeout_ms / 1000;
-            tv.tv_usec = (timeout_ms % 1000) * 1000;
-        }
+    // Wait according to the configured strategy (MSG_DONTWAIT never waits)
+    if (!(flags & MSG_DONTWAIT)) {
+        int wait_result = vma_common_wait_readable(socket->socket_fd, socket->vma_options.wait_strategy,
+                                                   socket->vma_options.spin_budget_us, socket->stats.last_rx_ns,
+                                                   timeout_ms);
         
-        int select_result = select(socket->socket_fd + 1, &readfds, NULL, NULL, 
-                                timeout_ms >= 0 ? &tv : NULL);
-        
-        if (select_result == 0) {
+        if (wait_result == 0) {
             return UDP_ERROR_TIMEOUT;
-        } else if (select_result < 0) {
+        } else if (wait_result < 0) {
             return UDP_ERROR_RECV;
         }
     }
     
-    // Receive data and address
-    socklen_t addr_len = sizeof(packet->src_addr);
-    ssize_t res = recvfrom(socket->socket_fd, buffer, buffer_size, 0,
-                        (struct sockaddr*)&packet->src_addr, &addr_len);
+    // A zero timeout never blocks, even on a blocking socket
+    if (timeout_ms == 0) {
+        flags |= MSG_DONTWAIT;
+    }
+    
+    // Receive data, address and IP_PKTINFO ancillary data (present only when enabled)
+    struct iovec iov = { .iov_base = buffer, .iov_len = buffer_size };
+    // Room for SO_TIMESTAMPNS as well, or the kernel truncates the pktinfo cmsg
+    char control[CMSG_SPACE(sizeof(struct in_pktinfo)) + CMSG_SPACE(sizeof(struct timespec))];
+    struct msghdr msg;
+    memset(&msg, 0, sizeof(msg));
+    msg.msg_name = &packet->src_addr;
+    msg.msg_namelen = sizeof(packet->src_addr);
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control;
+    msg.msg_controllen = sizeof(control);
+    
+    ssize_t res = recvmsg(socket->socket_fd, &msg, MSG_TRUNC | flags);
     
     if (res < 0) {
         if (errno == EAGAIN || errno == EWOULDBLOCK) {
             // For polling mode or immediate timeout
+            socket->stats.rx_eagain++;
             return UDP_ERROR_TIMEOUT;
         }
+        socket->stats.rx_errors++;
         return UDP_ERROR_RECV;
     } else if (res == 0) {
         return UDP_ERROR_CLOSED;
     }
     
-    // Set packet structure
-    packet->data = buffer;
-    packet->length = (size_t)res;
+    fill_packet(socket, packet, &msg, (size_t)res, buffer, buffer_size, (flags & MSG_PEEK) != 0);
     
-    // Set timestamp
-    struct timespec ts;
-    if (clock_gettime(CLOCK_REALTIME, &ts) == 0) {
-        packet->timestamp = (uint64_t)ts.tv_sec * 1000000000ULL + ts.tv_nsec;
-    } else {
-        packet->timestamp = 0;
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_recvfrom(udp_socket_t* socket, udp_packet_t* packet,
+                            void* buffer, size_t buffer_size, int timeout_ms) {
+    return recvfrom_with_flags(socket, packet, buffer, buffer_size, timeout_ms, 0);
+}
+
+udp_result_t udp_socket_recvfrom_flags(udp_socket_t* socket, udp_packet_t* packet,
+                            void* buffer, size_t buffer_size, int timeout_ms, int flags) {
+    return recvfrom_with_flags(socket, packet, buffer, buffer_size, timeout_ms, flags);
+}
+
+udp_result_t udp_socket_peekfrom(udp_socket_t* socket, udp_packet_t* packet,
+                            void* buffer, size_t buffer_size, int timeout_ms) {
+    return recvfrom_with_flags(socket, packet, buffer, buffer_size, timeout_ms, MSG_PEEK);
+}
+
+udp_result_t udp_socket_recvfrom_burst(udp_socket_t* socket, udp_packet_t* packets, void* buffers,
+                            size_t slot_size, size_t max_packets, int timeout_ms, size_t* received) {
+    if (!socket || socket->socket_fd < 0 || !packets || !buffers || slot_size == 0 ||
+        max_packets == 0 || !received) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    *received = 0;
+    if (max_packets > UDP_RECV_BURST_MAX) {
+        max_packets = UDP_RECV_BURST_MAX;
     }
     
-    socket->rx_packets++;
-    socket->rx_bytes += res;
+    // Wait for the first datagram according to the configured strategy
+    int wait_result = vma_common_wait_readable(socket->socket_fd, socket->vma_options.wait_strategy,
+                                               socket->vma_options.spin_budget_us, socket->stats.last_rx_ns,
+                                               timeout_ms);
+    if (wait_result == 0) {
+        return UDP_ERROR_TIMEOUT;
+    } else if (wait_result < 0) {
+        return UDP_ERROR_RECV;
+    }
+    
+    // One message header per slot, each with room for IP_PKTINFO and SO_TIMESTAMPNS
+    enum { CONTROL_SIZE = CMSG_SPACE(sizeof(struct in_pktinfo)) + CMSG_SPACE(sizeof(struct timespec)) };
+    struct mmsghdr msgs[UDP_RECV_BURST_MAX];
+    struct iovec iovs[UDP_RECV_BURST_MAX];
+    char control[UDP_RECV_BURST_MAX][CONTROL_SIZE];
+    memset(msgs, 0, sizeof(msgs[0]) * max_packets);
+    for (size_t i = 0; i < max_packets; i++) {
+        iovs[i].iov_base = (char*)buffers + i * slot_size;
+        iovs[i].iov_len = slot_size;
+        msgs[i].msg_hdr.msg_name = &packets[i].src_addr;
+        msgs[i].msg_hdr.msg_namelen = sizeof(packets[i].src_addr);
+        msgs[i].msg_hdr.msg_iov = &iovs[i];
+        msgs[i].msg_hdr.msg_iovlen = 1;
+        msgs[i].msg_hdr.msg_control = control[i];
+        msgs[i].msg_hdr.msg_controllen = CONTROL_SIZE;
+    }
+    
+    // Take whatever is queued without waiting again
+    int res = recvmmsg(socket->socket_fd, msgs, (unsigned int)max_packets, MSG_DONTWAIT | MSG_TRUNC, NULL);
+    if (res < 0) {
+        if (errno == EAGAIN || errno == EWOULDBLOCK) {
+            socket->stats.rx_eagain++;
+            return UDP_ERROR_TIMEOUT;
+        }
+        socket->stats.rx_errors++;
+        return UDP_ERROR_RECV;
+    }
+    
+    for (int i = 0; i < res; i++) {
+        fill_packet(socket, &packets[i], &msgs[i].msg_hdr, msgs[i].msg_len, iovs[i].iov_base, slot_size, false);
+    }
+    *received = (size_t)res;
     
     return UDP_SUCCESS;
 }
@@ -348,6 +1004,84 @@ udp_result_t udp_socket_setopt(udp_socket_t* socket, int level, int optname,
     return UDP_SUCCESS;
 }
 
+udp_result_t udp_socket_getopt(udp_socket_t* socket, int level, int optname, 
+                            void* optval, socklen_t* optlen) {
+    if (!socket || socket->socket_fd < 0 || !optval || !optlen) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (getsockopt(socket->socket_fd, level, optname, optval, optlen) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_steer_flow(udp_socket_t* socket, const char* rule, uint32_t ring_id) {
+    if (!socket || socket->socket_fd < 0 || !rule) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    // Rings are attached at bind time
+    if (socket->is_bound) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (!vma_common_get_api()) {
+        return UDP_ERROR_NOT_SUPPORTED;
+    }
+    
+    if (vma_common_add_conf_rule(rule) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    socket->vma_options.ring_alloc_logic = RING_LOGIC_PER_USER_ID;
+    socket->vma_options.ring_user_id = ring_id;
+    if (vma_common_apply_ring_alloc(socket->socket_fd, &socket->vma_options) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_set_ttl(udp_socket_t* socket, int ttl) {
+    if (!socket || socket->socket_fd < 0 || ttl < 1 || ttl > 255) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (setsockopt(socket->socket_fd, IPPROTO_IP, IP_TTL, &ttl, sizeof(ttl)) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_set_tos(udp_socket_t* socket, int tos) {
+    if (!socket || socket->socket_fd < 0 || tos < 0 || tos > 255) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    if (setsockopt(socket->socket_fd, IPPROTO_IP, IP_TOS, &tos, sizeof(tos)) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_set_multicast_ttl(udp_socket_t* socket, int ttl) {
+    if (!socket || socket->socket_fd < 0 || ttl < 0 || ttl > 255) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    // IP_MULTICAST_TTL takes a single byte on Linux
+    unsigned char value = (unsigned char)ttl;
+    if (setsockopt(socket->socket_fd, IPPROTO_IP, IP_MULTICAST_TTL, &value, sizeof(value)) < 0) {
+        return UDP_ERROR_SOCKET_OPTION;
+    }
+    
+    return UDP_SUCCESS;
+}
+
 udp_result_t udp_socket_get_stats(udp_socket_t* socket, uint64_t* rx_packets, 
                                 uint64_t* tx_packets, uint64_t* rx_bytes, 
                                 uint64_t* tx_bytes) {
@@ -355,10 +1089,44 @@ udp_result_t udp_socket_get_stats(udp_socket_t* socket, uint64_t* rx_packets,
         return UDP_ERROR_INVALID_PARAM;
     }
     
-    if (rx_packets) *rx_packets = socket->rx_packets;
-    if (tx_packets) *tx_packets = socket->tx_packets;
-    if (rx_bytes) *rx_bytes = socket->rx_bytes;
-    if (tx_bytes) *tx_bytes = socket->tx_bytes;
+    if (rx_packets) *rx_packets = socket->stats.rx_packets;
+    if (tx_packets) *tx_packets = socket->stats.tx_packets;
+    if (rx_bytes) *rx_bytes = socket->stats.rx_bytes;
+    if (tx_bytes) *tx_bytes = socket->stats.tx_bytes;
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_get_extended_stats(udp_socket_t* socket, socket_stats_t* stats) {
+    if (!socket || !stats) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    *stats = socket->stats;
+    
+    return UDP_SUCCESS;
+}
+
+udp_result_t udp_socket_reset_stats(udp_socket_t* socket) {
+    if (!socket) {
+        return UDP_ERROR_INVALID_PARAM;
+    }
+    
+    // Activity times are not counters and keep feeding idle detection
+    uint64_t last_rx_ns = socket->stats.last_rx_ns;
+    uint64_t last_tx_ns = socket->stats.last_tx_ns;
+    memset(&socket->stats, 0, sizeof(socket->stats));
+    socket->stats.last_rx_ns = last_rx_ns;
+    socket->stats.last_tx_ns = last_tx_ns;
     
     return UDP_SUCCESS;
 }
+
+int udp_socket_poll_readable(udp_socket_t* socket, int timeout_ms) {
+    if (!socket) {
+        errno = EINVAL;
+        return -1;
+    }
+    return vma_common_poll_readable(socket->socket_fd, socket->vma_options.wait_strategy,
+                                    socket->vma_options.spin_budget_us, socket->stats.last_rx_ns, timeout_ms);
+}
@@ -11,6 +11,21 @@
 #include <sys/socket.h>
 #include "vma_common.h"
 
+// Maximum number of datagrams received by udp_socket_recvfrom_burst (must match RECV_BURST_MAX in udp.rs)
+#define UDP_RECV_BURST_MAX 64
+
+// Maximum number of datagrams sent by one udp_socket_send_batch call (must match SEND_BATCH_MAX in udp.rs)
+#define UDP_SEND_BATCH_MAX 64
+
+// Maximum number of segments of one udp_socket_send_gso call (UDP_MAX_SEGMENTS of the kernel)
+#define UDP_GSO_MAX_SEGMENTS 64
+
+// Truncated receives: every receive path (recv, recvfrom/recvmsg, recvmmsg) passes
+// MSG_TRUNC, so the kernel reports the full datagram length even when the buffer is
+// smaller. The copied length is clamped to the buffer, the full one is reported through
+// datagram_length / udp_packet_t.full_length, and stats.rx_truncated counts every
+// truncated datagram actually consumed (MSG_PEEK receives are not counted).
+
 // UDP socket structure
 typedef struct {
     int socket_fd;                 // Socket file descriptor
@@ -19,10 +34,8 @@ typedef struct {
     struct sockaddr_in remote_addr; // Remote address information
     bool is_bound;                 // Whether the socket is bound
     bool is_connected;             // Whether the socket is connected (default target set)
-    uint64_t rx_packets;           // Number of received packets
-    uint64_t tx_packets;           // Number of transmitted packets
-    uint64_t rx_bytes;             // Number of received bytes
-    uint64_t tx_bytes;             // Number of transmitted bytes
+    socket_stats_t stats;          // Traffic counters
+    zerocopy_state_t zerocopy;     // MSG_ZEROCOPY send state
 } udp_socket_t;
 
 // Packet structure
@@ -30,7 +43,10 @@ typedef struct {
     void* data;                   // Packet data
     size_t length;                // Data length
     struct sockaddr_in src_addr;  // Source address (on receive)
-    uint64_t timestamp;           // Timestamp
+    uint64_t timestamp;           // SO_TIMESTAMPNS receive time in ns since the epoch (0 if not enabled)
+    struct in_addr dst_addr;      // Destination address from IP_PKTINFO (INADDR_ANY if unavailable)
+    int if_index;                 // Incoming interface index from IP_PKTINFO (0 if unavailable)
+    size_t full_length;           // Full datagram length (greater than length if truncated)
 } udp_packet_t;
 
 // Result codes
@@ -45,7 +61,9 @@ typedef enum {
     UDP_ERROR_TIMEOUT = -7,
     UDP_ERROR_INVALID_PARAM = -8,
     UDP_ERROR_NOT_INITIALIZED = -9,
-    UDP_ERROR_CLOSED = -10
+    UDP_ERROR_CLOSED = -10,
+    UDP_ERROR_NOT_SUPPORTED = -11,
+    UDP_ERROR_CLOSE = -12
 } udp_result_t;
 
 /**
@@ -60,11 +78,89 @@ udp_result_t udp_socket_init(udp_socket_t* socket, const vma_options_t* options)
 /**
  * Release and close a UDP socket
  * 
+ * The descriptor is released even when close() fails; errno is then kept
+ * for the caller.
+ * 
  * @param socket Pointer to the UDP socket structure
- * @return Result code
+ * @return Result code (UDP_ERROR_CLOSE if close() failed)
  */
 udp_result_t udp_socket_close(udp_socket_t* socket);
 
+/**
+ * Allocate and initialize a UDP socket handle
+ * 
+ * The structure stays private to this library: callers keep only the pointer, use the
+ * accessors below instead of reading fields, and release it with udp_socket_free().
+ * 
+ * @param socket Output pointer receiving the new handle (NULL on failure)
+ * @param options VMA options (use default if NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_new(udp_socket_t** socket, const vma_options_t* options);
+
+/**
+ * Close a handle from udp_socket_new() if still open and free it
+ * 
+ * @param socket Handle to release (NULL is ignored)
+ */
+void udp_socket_free(udp_socket_t* socket);
+
+/**
+ * Allocate a UDP socket around an existing IPv4 datagram descriptor
+ * 
+ * The descriptor keeps the options already set on it; the bound and connected
+ * addresses are read back from the kernel. On failure the descriptor is left open.
+ * 
+ * @param socket Output pointer receiving the new socket (NULL on failure)
+ * @param fd Descriptor to adopt; owned by the socket on success
+ * @param options VMA options (use default if NULL)
+ * @return Result code (UDP_ERROR_INVALID_PARAM if fd is not an IPv4 UDP socket)
+ */
+udp_result_t udp_socket_from_fd(udp_socket_t** socket, int fd, const vma_options_t* options);
+
+/**
+ * Detach the descriptor from a UDP socket without closing it
+ * 
+ * The socket is left closed and still has to be freed with udp_socket_free().
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @return The descriptor, now owned by the caller, or -1 if the socket is closed
+ */
+int udp_socket_release_fd(udp_socket_t* socket);
+
+/**
+ * Get the file descriptor of a UDP socket
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @return File descriptor, or -1 if the socket is closed
+ */
+int udp_socket_fd(const udp_socket_t* socket);
+
+/**
+ * Copy the options the socket was created with, including later runtime changes
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param options Receives the options
+ */
+void udp_socket_get_options(const udp_socket_t* socket, vma_options_t* options);
+
+/**
+ * Replace the recorded options; receives use the new wait strategy
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param options New options
+ */
+void udp_socket_set_options(udp_socket_t* socket, const vma_options_t* options);
+
+/**
+ * Get the default destination set by udp_socket_connect()
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param addr Output for the remote address
+ * @return true if the socket is connected and addr was filled
+ */
+bool udp_socket_get_peer(const udp_socket_t* socket, struct sockaddr_in* addr);
+
 /**
  * Bind a UDP socket to a local address
  * 
@@ -85,6 +181,38 @@ udp_result_t udp_socket_bind(udp_socket_t* socket, const char* ip, uint16_t port
  */
 udp_result_t udp_socket_connect(udp_socket_t* socket, const char* ip, uint16_t port);
 
+/**
+ * Dissolve the association set by udp_socket_connect() (connect to AF_UNSPEC)
+ * 
+ * The socket then receives from any sender again; its bind is kept, including a port
+ * picked by binding to port 0, which the kernel releases on disconnect and is taken back.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @return Result code
+ */
+udp_result_t udp_socket_disconnect(udp_socket_t* socket);
+
+/**
+ * Move the socket to a new local address and port
+ * 
+ * A socket without a local address yet is bound in place, keeping its descriptor.
+ * A bound descriptor cannot be bound again, so otherwise a new one is created with
+ * the same VMA options, socket options (buffer sizes, timeouts, TTL/TOS, reuse,
+ * multicast, pktinfo), association and the multicast memberships in `groups`, and
+ * replaces the current one only once all of that succeeded. Closing the old
+ * descriptor releases its VMA ring attachment and buffers, and the new one is
+ * offloaded from scratch; zero-copy sends still in flight are lost. Stats are kept.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param ip Local IP address (NULL for INADDR_ANY)
+ * @param port Local port
+ * @param groups Multicast memberships to join on a new descriptor (can be NULL if group_count is 0)
+ * @param group_count Number of entries in groups
+ * @return Result code
+ */
+udp_result_t udp_socket_rebind(udp_socket_t* socket, const char* ip, uint16_t port,
+                               const struct ip_mreq* groups, size_t group_count);
+
 /**
  * Send data to the default target address
  * 
@@ -96,6 +224,54 @@ udp_result_t udp_socket_connect(udp_socket_t* socket, const char* ip, uint16_t p
  */
 udp_result_t udp_socket_send(udp_socket_t* socket, const void* data, size_t length, size_t* bytes_sent);
 
+/**
+ * Send data to the default target address with send(2) flags
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param flags Flags passed to send (e.g., MSG_DONTWAIT, MSG_MORE, MSG_CONFIRM)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_send_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            int flags, size_t* bytes_sent);
+
+/**
+ * Run a VMA dummy send (VMA_SND_FLAGS_DUMMY) on the connected socket
+ * 
+ * The packet goes through the VMA TX path to keep it warm but is never put on the wire.
+ * Not counted in the socket statistics.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Representative payload
+ * @param length Payload length
+ * @return Result code (UDP_ERROR_NOT_SUPPORTED if the socket is not offloaded by VMA)
+ */
+udp_result_t udp_socket_send_dummy(udp_socket_t* socket, const void* data, size_t length);
+
+/**
+ * Send data to the default target without copying it (MSG_ZEROCOPY, copying if unsupported)
+ * 
+ * The data must stay unchanged until udp_socket_zerocopy_pending reports the send released.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param flags send(2) flags (e.g. MSG_DONTWAIT)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_send_zerocopy(udp_socket_t* socket, const void* data, size_t length, int flags, size_t* bytes_sent);
+
+/**
+ * Get the number of zero-copy sends whose data is still in use
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @return Number of pending zero-copy sends
+ */
+uint32_t udp_socket_zerocopy_pending(udp_socket_t* socket);
+
 /**
  * Send data to a specified address
  * 
@@ -110,6 +286,74 @@ udp_result_t udp_socket_send(udp_socket_t* socket, const void* data, size_t leng
 udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t length, 
                             const char* ip, uint16_t port, size_t* bytes_sent);
 
+/**
+ * Send data to a specified address with sendto(2) flags
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param ip Target IP address
+ * @param port Target port
+ * @param flags Flags passed to sendto (e.g., MSG_DONTWAIT, MSG_MORE, MSG_CONFIRM)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_sendto_flags(udp_socket_t* socket, const void* data, size_t length, 
+                            const char* ip, uint16_t port, int flags, size_t* bytes_sent);
+
+/**
+ * Send data to a prepared destination address with sendto(2) flags
+ * 
+ * Skips the address parsing of udp_socket_sendto() for peers sent to repeatedly.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Data to send
+ * @param length Data length
+ * @param addr Target address (network byte order)
+ * @param flags Flags passed to sendto (0 for none)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_sendto_addr(udp_socket_t* socket, const void* data, size_t length, 
+                            const struct sockaddr_in* addr, int flags, size_t* bytes_sent);
+
+/**
+ * Send a payload as consecutive datagrams of segment_size bytes with one call (UDP_SEGMENT)
+ * 
+ * The stack splits the payload, the last datagram holding the remainder. Falls back to
+ * one send per segment when segmentation offload is not supported.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Payload to send
+ * @param length Payload length (at most UDP_GSO_MAX_SEGMENTS segments)
+ * @param segment_size Size of each datagram
+ * @param dest_addr Target address (network byte order), or NULL for the connected peer
+ * @param flags sendmsg(2) flags (e.g. MSG_DONTWAIT)
+ * @param bytes_sent Number of bytes sent (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_send_gso(udp_socket_t* socket, const void* data, size_t length,
+                                uint16_t segment_size, const struct sockaddr_in* dest_addr, int flags,
+                                size_t* bytes_sent);
+
+/**
+ * Send a batch of datagrams with sendmmsg, to the connected peer or per-datagram addresses
+ * 
+ * Datagram i is data[offsets[i] .. offsets[i + 1]] of the shared buffer. Stops at the
+ * first datagram the socket refuses; sent then holds the number sent before it.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param data Buffer holding the datagrams back to back
+ * @param offsets count + 1 offsets into data delimiting the datagrams
+ * @param addrs count target addresses (sin_family 0 for the connected peer), or NULL for the connected peer
+ * @param count Number of datagrams (at most UDP_SEND_BATCH_MAX)
+ * @param flags Flags passed to sendmmsg (e.g., MSG_DONTWAIT)
+ * @param sent Number of datagrams sent
+ * @return Result code
+ */
+udp_result_t udp_socket_send_batch(udp_socket_t* socket, const void* data, const size_t* offsets,
+                                  const struct sockaddr_in* addrs, size_t count, int flags, size_t* sent);
+
 /**
  * Receive data
  * 
@@ -123,6 +367,20 @@ udp_result_t udp_socket_sendto(udp_socket_t* socket, const void* data, size_t le
 udp_result_t udp_socket_recv(udp_socket_t* socket, void* buffer, size_t buffer_size, 
                             int timeout_ms, size_t* bytes_received);
 
+/**
+ * Receive data and report the full datagram length (MSG_TRUNC)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @param bytes_received Number of bytes copied into the buffer (can be NULL)
+ * @param datagram_length Full datagram length, greater than bytes_received if truncated (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_recv_full(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received, size_t* datagram_length);
+
 /**
  * Receive data (including source address information)
  * 
@@ -136,6 +394,79 @@ udp_result_t udp_socket_recv(udp_socket_t* socket, void* buffer, size_t buffer_s
 udp_result_t udp_socket_recvfrom(udp_socket_t* socket, udp_packet_t* packet,
                                 void* buffer, size_t buffer_size, int timeout_ms);
 
+/**
+ * Receive data with recv(2) flags
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait; ignored with MSG_DONTWAIT)
+ * @param flags Flags passed to recv (e.g., MSG_DONTWAIT, MSG_PEEK)
+ * @param bytes_received Number of bytes received (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_recv_flags(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, int flags, size_t* bytes_received);
+
+/**
+ * Receive data (including source address information) with recvmsg(2) flags
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param packet Received packet structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait; ignored with MSG_DONTWAIT)
+ * @param flags Flags passed to recvmsg (e.g., MSG_DONTWAIT, MSG_PEEK)
+ * @return Result code
+ */
+udp_result_t udp_socket_recvfrom_flags(udp_socket_t* socket, udp_packet_t* packet,
+                                void* buffer, size_t buffer_size, int timeout_ms, int flags);
+
+/**
+ * Peek at the next datagram without removing it from the queue (MSG_PEEK)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @param bytes_received Number of bytes copied (can be NULL)
+ * @return Result code
+ */
+udp_result_t udp_socket_peek(udp_socket_t* socket, void* buffer, size_t buffer_size, 
+                            int timeout_ms, size_t* bytes_received);
+
+/**
+ * Peek at the next datagram and its source address without removing it (MSG_PEEK)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param packet Received packet structure
+ * @param buffer Receive buffer
+ * @param buffer_size Buffer size
+ * @param timeout_ms Timeout in milliseconds (0 for non-blocking, -1 for infinite wait)
+ * @return Result code
+ */
+udp_result_t udp_socket_peekfrom(udp_socket_t* socket, udp_packet_t* packet,
+                                void* buffer, size_t buffer_size, int timeout_ms);
+
+/**
+ * Receive up to max_packets datagrams with one recvmmsg call
+ * 
+ * Waits for the first datagram according to the wait strategy, then takes whatever
+ * else is already queued without waiting. Datagram i is stored in the slot starting
+ * at buffers + i * slot_size and truncated to slot_size.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param packets Array of at least max_packets packet structures
+ * @param buffers Receive buffer of max_packets * slot_size bytes
+ * @param slot_size Size of each datagram slot
+ * @param max_packets Maximum number of datagrams (capped at UDP_RECV_BURST_MAX)
+ * @param timeout_ms Timeout in milliseconds for the first datagram (0 for non-blocking, -1 for infinite wait)
+ * @param received Number of datagrams received
+ * @return Result code
+ */
+udp_result_t udp_socket_recvfrom_burst(udp_socket_t* socket, udp_packet_t* packets, void* buffers,
+                                size_t slot_size, size_t max_packets, int timeout_ms, size_t* received);
+
 /**
  * Set socket options
  * 
@@ -149,6 +480,59 @@ udp_result_t udp_socket_recvfrom(udp_socket_t* socket, udp_packet_t* packet,
 udp_result_t udp_socket_setopt(udp_socket_t* socket, int level, int optname, 
                             const void* optval, socklen_t optlen);
 
+/**
+ * Get socket options
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param level Option level (e.g., SOL_SOCKET)
+ * @param optname Option name
+ * @param optval Buffer receiving the option value
+ * @param optlen In: buffer size, out: option value length
+ * @return Result code
+ */
+udp_result_t udp_socket_getopt(udp_socket_t* socket, int level, int optname, 
+                            void* optval, socklen_t* optlen);
+
+/**
+ * Steer a flow to a VMA ring shared by all sockets with the same ring id
+ * 
+ * Registers the libvma.conf rule so VMA offloads the flow, and assigns the socket to
+ * the ring with RING_LOGIC_PER_USER_ID. Must be called before the socket is bound.
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param rule libvma.conf rule matching the flow (e.g. "use vma udp_receiver 239.1.1.1:5000 *:*")
+ * @param ring_id Ring user id
+ * @return Result code (UDP_ERROR_NOT_SUPPORTED if libvma is not loaded)
+ */
+udp_result_t udp_socket_steer_flow(udp_socket_t* socket, const char* rule, uint32_t ring_id);
+
+/**
+ * Set the unicast time-to-live of outgoing packets (IP_TTL)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param ttl Time-to-live (1-255)
+ * @return Result code
+ */
+udp_result_t udp_socket_set_ttl(udp_socket_t* socket, int ttl);
+
+/**
+ * Set the type-of-service byte of outgoing packets (IP_TOS)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param tos TOS byte (DSCP in the upper 6 bits, ECN in the lower 2)
+ * @return Result code
+ */
+udp_result_t udp_socket_set_tos(udp_socket_t* socket, int tos);
+
+/**
+ * Set the time-to-live of outgoing multicast packets (IP_MULTICAST_TTL)
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param ttl Time-to-live (0-255, 0 keeps packets on the local host)
+ * @return Result code
+ */
+udp_result_t udp_socket_set_multicast_ttl(udp_socket_t* socket, int ttl);
+
 /**
  * Get socket statistics
  * 
@@ -163,4 +547,30 @@ udp_result_t udp_socket_get_stats(udp_socket_t* socket, uint64_t* rx_packets,
                                 uint64_t* tx_packets, uint64_t* rx_bytes, 
                                 uint64_t* tx_bytes);
 
+/**
+ * Get the full set of socket counters
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param stats Output pointer to store the counters
+ * @return Result code
+ */
+udp_result_t udp_socket_get_extended_stats(udp_socket_t* socket, socket_stats_t* stats);
+
+/**
+ * Zero the socket counters, keeping the last receive and send times
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @return Result code
+ */
+udp_result_t udp_socket_reset_stats(udp_socket_t* socket);
+
+/**
+ * Check or wait until a datagram can be received, following the socket's wait strategy
+ * 
+ * @param socket Pointer to the UDP socket structure
+ * @param timeout_ms Timeout in milliseconds (0 only checks, -1 waits indefinitely)
+ * @return 1 if readable, 0 if not or on timeout, -1 on error (errno is set)
+ */
+int udp_socket_poll_readable(udp_socket_t* socket, int timeout_ms);
+
 #endif /* UDP_SOCKET_H */
\ No newline at end of file
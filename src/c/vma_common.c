@@ -4,9 +4,39 @@
 
 #include <stdio.h>
 #include <stdlib.h>
+#include <errno.h>
+#include <poll.h>
+#include <pthread.h>
+#include <sched.h>
 #include <string.h>
+#include <time.h>
 #include <unistd.h>
+#include <sys/epoll.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <linux/errqueue.h>
+#include <linux/sockios.h>
 #include "vma_common.h"
+#include <mellanox/vma_extra.h>
+
+// Zero-copy definitions missing from older libc headers
+#ifndef SO_ZEROCOPY
+#define SO_ZEROCOPY 60
+#endif
+#ifndef MSG_ZEROCOPY
+#define MSG_ZEROCOPY 0x4000000
+#endif
+#ifndef SO_EE_ORIGIN_ZEROCOPY
+#define SO_EE_ORIGIN_ZEROCOPY 5
+#endif
+
+// Extra API pointer, probed once for the whole process
+static struct vma_api_t* vma_api = NULL;
+static pthread_once_t vma_api_once = PTHREAD_ONCE_INIT;
+
+static void probe_vma_api(void) {
+    vma_api = vma_get_api();
+}
 
 // Set up VMA environment variables based on options
 void vma_setup_environment(const vma_options_t* options) {
@@ -27,8 +57,12 @@ void vma_setup_environment(const vma_options_t* options) {
     }
     
     if (options->use_polling) {
-        setenv("VMA_RX_POLL", "1", 1);
-        setenv("VMA_SELECT_POLL", "1", 1);
+        if (options->rx_poll == VMA_POLL_UNSET) {
+            setenv("VMA_RX_POLL", "1", 1);
+        }
+        if (options->select_poll == VMA_POLL_UNSET) {
+            setenv("VMA_SELECT_POLL", "1", 1);
+        }
         
         // Polling optimizations
         if (options->disable_poll_yield) {
@@ -40,6 +74,25 @@ void vma_setup_environment(const vma_options_t* options) {
         }
     }
     
+    // Explicit poll tunables override the ones derived from use_polling
+    if (options->rx_poll != VMA_POLL_UNSET) {
+        char rx_poll[16];
+        snprintf(rx_poll, sizeof(rx_poll), "%d", options->rx_poll);
+        setenv("VMA_RX_POLL", rx_poll, 1);
+    }
+    
+    if (options->select_poll != VMA_POLL_UNSET) {
+        char select_poll[16];
+        snprintf(select_poll, sizeof(select_poll), "%d", options->select_poll);
+        setenv("VMA_SELECT_POLL", select_poll, 1);
+    }
+    
+    if (options->rx_poll_os_ratio != VMA_POLL_UNSET) {
+        char ratio[16];
+        snprintf(ratio, sizeof(ratio), "%d", options->rx_poll_os_ratio);
+        setenv("VMA_RX_POLL_OS_RATIO", ratio, 1);
+    }
+    
     if (options->ring_count > 0) {
         char ring_count[16];
         snprintf(ring_count, sizeof(ring_count), "%d", options->ring_count);
@@ -49,19 +102,33 @@ void vma_setup_environment(const vma_options_t* options) {
     // SocketXtreme optimizations
     if (options->use_socketxtreme) {
         setenv("VMA_RING_ALLOCATION_LOGIC_TX", "0", 1);
-        setenv("VMA_THREAD_MODE", "1", 1);
+        if (options->thread_mode < 0) {
+            setenv("VMA_THREAD_MODE", "1", 1);
+        }
         
         if (options->keep_qp_full) {
             setenv("VMA_CQ_KEEP_QP_FULL", "1", 1);
         }
     } else {
         // Multi-threaded mode when not using SocketXtreme
-        setenv("VMA_THREAD_MODE", "3", 1);
+        if (options->thread_mode < 0) {
+            setenv("VMA_THREAD_MODE", "3", 1);
+        }
+    }
+    
+    if (options->thread_mode >= 0) {
+        char thread_mode[16];
+        snprintf(thread_mode, sizeof(thread_mode), "%d", options->thread_mode);
+        setenv("VMA_THREAD_MODE", thread_mode, 1);
     }
     
     // Memory optimizations
-    if (options->use_hugepages) {
-        setenv("VMA_MEMORY_ALLOCATION_TYPE", "2", 1);
+    if (options->mem_alloc_type >= 0) {
+        char mem_alloc_type[16];
+        snprintf(mem_alloc_type, sizeof(mem_alloc_type), "%d", options->mem_alloc_type);
+        setenv("VMA_MEM_ALLOC_TYPE", mem_alloc_type, 1);
+    } else if (options->use_hugepages) {
+        setenv("VMA_MEM_ALLOC_TYPE", "2", 1);
     }
     
     // Buffer counts
@@ -77,33 +144,40 @@ void vma_setup_environment(const vma_options_t* options) {
         setenv("VMA_RX_BUFS", rx_bufs, 1);
     }
     
+    // Work requests posted per ring
+    if (options->tx_wre > 0) {
+        char tx_wre[16];
+        snprintf(tx_wre, sizeof(tx_wre), "%u", options->tx_wre);
+        setenv("VMA_TX_WRE", tx_wre, 1);
+    }
+    
+    if (options->rx_wre > 0) {
+        char rx_wre[16];
+        snprintf(rx_wre, sizeof(rx_wre), "%u", options->rx_wre);
+        setenv("VMA_RX_WRE", rx_wre, 1);
+    }
+    
     // CPU affinity settings - now using fixed array instead of pointer
     if (options->cpu_cores_count > 0) {
         setenv("VMA_THREAD_AFFINITY", "1", 1);
         
-        // Create a string like "0,1,2,3"
-        size_t str_size = options->cpu_cores_count * 4; // Allow up to 3 digits per core plus comma
-        char* cores_str = malloc(str_size);
-        if (cores_str) {
-            cores_str[0] = '\0';
-            int offset = 0;
-            
-            for (int i = 0; i < options->cpu_cores_count; i++) {
-                int written;
-                if (i == 0) {
-                    written = snprintf(cores_str + offset, str_size - offset, "%d", options->cpu_cores[i]);
-                } else {
-                    written = snprintf(cores_str + offset, str_size - offset, ",%d", options->cpu_cores[i]);
-                }
-                
-                if (written > 0) {
-                    offset += written;
-                }
+        // Create a string like "0,1,2,3" (room for any int per core plus comma)
+        char cores_str[MAX_CPU_CORES * 12];
+        size_t offset = 0;
+        cores_str[0] = '\0';
+        
+        for (int i = 0; i < options->cpu_cores_count && i < MAX_CPU_CORES; i++) {
+            int written = snprintf(cores_str + offset, sizeof(cores_str) - offset,
+                                   i == 0 ? "%d" : ",%d", options->cpu_cores[i]);
+            if (written < 0 || (size_t)written >= sizeof(cores_str) - offset) {
+                break;
             }
-            
-            setenv("VMA_THREAD_AFFINITY_ID", cores_str, 1);
-            free(cores_str);
+            offset += written;
         }
+        
+        setenv("VMA_THREAD_AFFINITY_ID", cores_str, 1);
+        // VMA's internal (progress/timer) thread takes the same core list
+        setenv("VMA_INTERNAL_THREAD_AFFINITY", cores_str, 1);
     }
     
     // TCP-specific optimizations (always set these as they don't hurt UDP)
@@ -136,4 +210,390 @@ void set_default_options(vma_options_t* options) {
     // Initialize CPU cores array to zero
     memset(options->cpu_cores, 0, sizeof(options->cpu_cores));
     options->cpu_cores_count = 0;
-}
\ No newline at end of file
+    
+    options->tcp_nodelay = true;
+    
+    // Without polling, block right away like a plain socket
+    options->wait_strategy = VMA_WAIT_SPIN_PARK;
+    options->spin_budget_us = 0;
+    
+    // Leave ring allocation to VMA's global configuration
+    options->ring_alloc_logic = -1;
+    options->ring_user_id = 0;
+    
+    // Derive thread mode and polling from the flags above
+    options->thread_mode = -1;
+    options->rx_poll = VMA_POLL_UNSET;
+    options->select_poll = VMA_POLL_UNSET;
+    options->rx_poll_os_ratio = VMA_POLL_UNSET;
+    
+    // Memory and work requests follow use_hugepages and VMA's defaults
+    options->mem_alloc_type = -1;
+    options->rx_wre = 0;
+    options->tx_wre = 0;
+}
+
+// Wall-clock time in nanoseconds, used for packet and activity timestamps
+uint64_t vma_now_ns(void) {
+    struct timespec ts;
+    if (clock_gettime(CLOCK_REALTIME, &ts) != 0) {
+        return 0;
+    }
+    return (uint64_t)ts.tv_sec * 1000000000ULL + ts.tv_nsec;
+}
+
+struct vma_api_t* vma_common_get_api(void) {
+    pthread_once(&vma_api_once, probe_vma_api);
+    return vma_api;
+}
+
+int vma_common_get_fd_stats(int fd, vma_fd_stats_t* stats) {
+    if (fd < 0 || !stats) {
+        return -1;
+    }
+    
+    memset(stats, 0, sizeof(*stats));
+    
+    struct vma_api_t* api = vma_common_get_api();
+    if (!api) {
+        return 0;
+    }
+    stats->vma_loaded = true;
+    
+    if (api->get_socket_rings_num) {
+        int rings = api->get_socket_rings_num(fd);
+        if (rings > 0) {
+            stats->ring_count = rings;
+            stats->offloaded = true;
+        }
+    }
+    
+    return 0;
+}
+
+bool vma_common_is_offloaded(int fd) {
+    vma_fd_stats_t stats;
+    return vma_common_get_fd_stats(fd, &stats) == 0 && stats.offloaded;
+}
+
+int vma_common_get_ring_fds(int fd, int* ring_fds, int max_fds) {
+    if (fd < 0 || !ring_fds || max_fds <= 0) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    struct vma_api_t* api = vma_common_get_api();
+    if (!api || !api->get_socket_rings_fds) {
+        errno = ENOTSUP;
+        return -1;
+    }
+    
+    int count = api->get_socket_rings_fds(fd, ring_fds, max_fds);
+    if (count < 0) {
+        return -1;
+    }
+    return count < max_fds ? count : max_fds;
+}
+
+int vma_common_dump_fd_stats(int fd, int log_level) {
+    struct vma_api_t* api = vma_common_get_api();
+    if (fd < 0 || !api || !api->dump_fd_stats) {
+        return -1;
+    }
+    
+    return api->dump_fd_stats(fd, log_level) < 0 ? -1 : 0;
+}
+
+int vma_common_get_local_addr(int fd, struct sockaddr_in* addr) {
+    if (fd < 0 || !addr) {
+        return -1;
+    }
+    
+    socklen_t addr_len = sizeof(*addr);
+    if (getsockname(fd, (struct sockaddr*)addr, &addr_len) < 0) {
+        return -1;
+    }
+    
+    return 0;
+}
+
+int vma_common_check_socket_fd(int fd, int type) {
+    int value = 0;
+    socklen_t len = sizeof(value);
+    if (fd < 0 || getsockopt(fd, SOL_SOCKET, SO_TYPE, &value, &len) < 0) {
+        if (fd < 0) {
+            errno = EBADF;
+        }
+        return -1;
+    }
+    if (value != type) {
+        errno = EPROTOTYPE;
+        return -1;
+    }
+    
+    len = sizeof(value);
+    if (getsockopt(fd, SOL_SOCKET, SO_DOMAIN, &value, &len) < 0) {
+        return -1;
+    }
+    if (value != AF_INET) {
+        errno = EAFNOSUPPORT;
+        return -1;
+    }
+    
+    return 0;
+}
+
+int vma_common_pending_tx_bytes(int fd, uint32_t* pending) {
+    if (fd < 0 || !pending) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    int queued = 0;
+    if (ioctl(fd, SIOCOUTQ, &queued) < 0) {
+        return -1;
+    }
+    
+    *pending = queued > 0 ? (uint32_t)queued : 0;
+    return 0;
+}
+
+ssize_t vma_common_send_zerocopy(int fd, zerocopy_state_t* zc, const void* data, size_t length, int flags) {
+    if (fd < 0 || !zc) {
+        return -1;
+    }
+    
+    if (!zc->enabled && !zc->unsupported) {
+        int one = 1;
+        if (setsockopt(fd, SOL_SOCKET, SO_ZEROCOPY, &one, sizeof(one)) == 0) {
+            zc->enabled = true;
+        } else {
+            zc->unsupported = true;
+        }
+    }
+    
+    if (!zc->enabled) {
+        return send(fd, data, length, flags);
+    }
+    
+    // Drain completions first so notifications never exhaust the socket's option memory
+    vma_common_zerocopy_pending(fd, zc);
+    
+    ssize_t res = send(fd, data, length, flags | MSG_ZEROCOPY);
+    if (res >= 0) {
+        zc->next_id++;
+    }
+    return res;
+}
+
+uint32_t vma_common_zerocopy_pending(int fd, zerocopy_state_t* zc) {
+    if (fd < 0 || !zc || !zc->enabled) {
+        return 0;
+    }
+    
+    // Room for timestamp cmsgs too (SO_TIMESTAMPNS applies to the error queue as well)
+    char control[CMSG_SPACE(sizeof(struct sock_extended_err) + sizeof(struct sockaddr_in)) +
+                 CMSG_SPACE(3 * sizeof(struct timespec))];
+    for (;;) {
+        struct msghdr msg;
+        memset(&msg, 0, sizeof(msg));
+        msg.msg_control = control;
+        msg.msg_controllen = sizeof(control);
+        
+        if (recvmsg(fd, &msg, MSG_ERRQUEUE | MSG_DONTWAIT) < 0) {
+            break;
+        }
+        
+        for (struct cmsghdr* cmsg = CMSG_FIRSTHDR(&msg); cmsg; cmsg = CMSG_NXTHDR(&msg, cmsg)) {
+            if (cmsg->cmsg_level != IPPROTO_IP || cmsg->cmsg_type != IP_RECVERR) {
+                continue;
+            }
+            struct sock_extended_err err;
+            memcpy(&err, CMSG_DATA(cmsg), sizeof(err));
+            if (err.ee_errno == 0 && err.ee_origin == SO_EE_ORIGIN_ZEROCOPY) {
+                // Completions cover the inclusive id range [ee_info, ee_data]
+                zc->completed += err.ee_data - err.ee_info + 1;
+            }
+        }
+    }
+    
+    return zc->next_id - zc->completed;
+}
+
+int vma_common_add_conf_rule(const char* rule) {
+    struct vma_api_t* api = vma_common_get_api();
+    if (!rule || !api || !api->add_conf_rule) {
+        return -1;
+    }
+    
+    return api->add_conf_rule(rule) == 0 ? 0 : -1;
+}
+
+int vma_common_apply_ring_alloc(int fd, const vma_options_t* options) {
+    if (fd < 0 || !options) {
+        errno = EINVAL;
+        return -1;
+    }
+    
+    // Nothing requested, or no VMA to honour it
+    if (options->ring_alloc_logic < 0 || !vma_common_get_api()) {
+        return 0;
+    }
+    
+    struct vma_ring_alloc_logic_attr attr;
+    memset(&attr, 0, sizeof(attr));
+    attr.comp_mask = VMA_RING_ALLOC_MASK_RING_INGRESS | VMA_RING_ALLOC_MASK_RING_ENGRESS;
+    attr.ring_alloc_logic = (ring_logic_t)options->ring_alloc_logic;
+    attr.ingress = 1;
+    attr.engress = 1;
+    
+    if (options->ring_alloc_logic == RING_LOGIC_PER_USER_ID) {
+        attr.comp_mask |= VMA_RING_ALLOC_MASK_RING_USER_ID;
+        attr.user_id = options->ring_user_id;
+    }
+    
+    return setsockopt(fd, SOL_SOCKET, SO_VMA_RING_ALLOC_LOGIC, &attr, sizeof(attr));
+}
+
+// Monotonic time in nanoseconds, used for wait deadlines
+uint64_t vma_monotonic_ns(void) {
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return (uint64_t)ts.tv_sec * 1000000000ULL + ts.tv_nsec;
+}
+
+// Check readiness without blocking: 1 ready, 0 not ready, -1 error
+static int poll_readable(int fd) {
+    struct pollfd pfd = { .fd = fd, .events = POLLIN };
+    int res = poll(&pfd, 1, 0);
+    if (res < 0 && errno == EINTR) {
+        return 0;
+    }
+    return res;
+}
+
+// Block in epoll_wait until the socket is readable: 1 ready, 0 timeout, -1 error
+static int park_readable(int fd, int timeout_ms) {
+    int epfd = epoll_create1(EPOLL_CLOEXEC);
+    if (epfd < 0) {
+        return -1;
+    }
+    
+    struct epoll_event event = { .events = EPOLLIN };
+    event.data.fd = fd;
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        int saved_errno = errno;
+        close(epfd);
+        errno = saved_errno;
+        return -1;
+    }
+    
+    int res = epoll_wait(epfd, &event, 1, timeout_ms);
+    int saved_errno = errno;
+    close(epfd);
+    errno = saved_errno;
+    
+    if (res < 0 && errno == EINTR) {
+        return 0;
+    }
+    return res > 0 ? 1 : res;
+}
+
+int vma_common_wait_readable(int fd, vma_wait_strategy_t strategy, uint32_t spin_budget_us,
+                             uint64_t last_rx_ns, int timeout_ms) {
+    if (fd < 0) {
+        errno = EBADF;
+        return -1;
+    }
+    
+    if (timeout_ms == 0) {
+        return 1;
+    }
+    
+    uint64_t start = vma_monotonic_ns();
+    uint64_t deadline = timeout_ms > 0 ? start + (uint64_t)timeout_ms * 1000000ULL : UINT64_MAX;
+    uint64_t budget_ns = (uint64_t)spin_budget_us * 1000ULL;
+    uint64_t spin_until;
+    if (strategy == VMA_WAIT_SPIN) {
+        spin_until = deadline;
+    } else if (strategy == VMA_WAIT_ADAPTIVE) {
+        // Keep polling until the flow has been silent for the budget; a first packet after parking resumes polling
+        uint64_t wall_now = vma_now_ns();
+        uint64_t idle_ns = last_rx_ns > 0 && wall_now >= last_rx_ns ? wall_now - last_rx_ns : UINT64_MAX;
+        spin_until = idle_ns < budget_ns ? start + (budget_ns - idle_ns) : start;
+    } else {
+        spin_until = start + budget_ns;
+    }
+    
+    for (;;) {
+        int res = poll_readable(fd);
+        if (res != 0) {
+            return res;
+        }
+        
+        uint64_t now = vma_monotonic_ns();
+        if (now >= deadline) {
+            return 0;
+        }
+        if (now < spin_until) {
+            continue;
+        }
+        
+        if (strategy == VMA_WAIT_SPIN_YIELD) {
+            sched_yield();
+            continue;
+        }
+        
+        // Park for the rest of the timeout (rounded up, so the deadline is never missed)
+        int remaining_ms = deadline == UINT64_MAX ? -1 : (int)((deadline - now + 999999ULL) / 1000000ULL);
+        res = park_readable(fd, remaining_ms);
+        if (res != 0) {
+            return res;
+        }
+        // Interrupted or timed out: the deadline check above decides
+    }
+}
+
+int vma_common_poll_readable(int fd, vma_wait_strategy_t strategy, uint32_t spin_budget_us,
+                             uint64_t last_rx_ns, int timeout_ms) {
+    if (fd < 0) {
+        errno = EBADF;
+        return -1;
+    }
+    
+    if (timeout_ms == 0) {
+        return poll_readable(fd);
+    }
+    
+    return vma_common_wait_readable(fd, strategy, spin_budget_us, last_rx_ns, timeout_ms);
+}
+
+int vma_common_wait_writable(int fd, int timeout_ms) {
+    if (fd < 0) {
+        errno = EBADF;
+        return -1;
+    }
+    
+    uint64_t deadline = timeout_ms > 0 ? vma_monotonic_ns() + (uint64_t)timeout_ms * 1000000ULL : 0;
+    struct pollfd pfd = { .fd = fd, .events = POLLOUT };
+    int wait_ms = timeout_ms;
+    
+    for (;;) {
+        int res = poll(&pfd, 1, wait_ms);
+        if (res >= 0) {
+            return res > 0 ? 1 : 0;
+        }
+        if (errno != EINTR) {
+            return -1;
+        }
+        
+        // Interrupted: wait out the rest of the timeout
+        if (timeout_ms > 0) {
+            uint64_t now = vma_monotonic_ns();
+            if (now >= deadline) {
+                return 0;
+            }
+            wait_ms = (int)((deadline - now + 999999ULL) / 1000000ULL);
+        }
+    }
+}
@@ -7,19 +7,32 @@
 
 #include <stdbool.h>
 #include <stdint.h>
+#include <limits.h>
 #include <stddef.h>
 #include <stdlib.h>  
 #include <stdio.h>
+#include <netinet/in.h>
+#include <sys/types.h>
 
-// Maximum number of CPU cores that can be specified
-#define MAX_CPU_CORES 64
+// Maximum number of CPU cores that can be specified (must match MAX_CPU_CORES in common.rs)
+#define MAX_CPU_CORES 128
+
+// Poll tunable left unset: keep the value derived from use_polling, or VMA's default (must match POLL_UNSET in common.rs)
+#define VMA_POLL_UNSET INT_MIN
+
+// How receive and accept calls wait for a socket to become ready
+typedef enum {
+    VMA_WAIT_SPIN = 0,             // Busy-poll until ready or the timeout expires
+    VMA_WAIT_SPIN_YIELD = 1,       // Busy-poll for the spin budget, then yield the CPU between polls
+    VMA_WAIT_SPIN_PARK = 2,        // Busy-poll for the spin budget, then block in epoll_wait
+    VMA_WAIT_ADAPTIVE = 3          // Busy-poll while the last receive was within the spin budget, else block in epoll_wait
+} vma_wait_strategy_t;
 
 // VMA options structure to be shared between TCP and UDP
 typedef struct {
     bool use_socketxtreme;       // Whether to use SocketXtreme mode
     bool optimize_for_latency;   // Whether to optimize for latency (useful for real-time applications)
     bool use_polling;            // Whether to use polling mode (reduces latency at the cost of higher CPU usage)
-    bool non_blocking;           // Whether to use non-blocking mode
     int ring_count;              // Number of rings (used for load balancing and performance optimization)
     int buffer_size;             // Default buffer size
     bool enable_timestamps;      // Whether to enable timestamps
@@ -31,8 +44,54 @@ typedef struct {
     bool keep_qp_full;           // Keep queue pairs full for better throughput
     int cpu_cores[MAX_CPU_CORES]; // Array of CPU cores to use for affinity (fixed size for thread safety)
     int cpu_cores_count;         // Number of CPU cores in the array
+    bool tcp_nodelay;            // Disable Nagle's algorithm (TCP_NODELAY) on TCP sockets
+    vma_wait_strategy_t wait_strategy; // How receive and accept calls wait for readiness
+    uint32_t spin_budget_us;     // Busy-poll time before yielding or parking; idle threshold for ADAPTIVE
+    int ring_alloc_logic;        // VMA ring allocation logic for the socket (ring_logic_t, -1 keeps VMA's global setting)
+    uint32_t ring_user_id;       // Ring shared by sockets with the same id (RING_LOGIC_PER_USER_ID)
+    int thread_mode;             // VMA_THREAD_MODE (0-3), -1 derives it from use_socketxtreme
+    int rx_poll;                 // VMA_RX_POLL: receive polls before blocking (-1 forever, VMA_POLL_UNSET derives it from use_polling)
+    int select_poll;             // VMA_SELECT_POLL: select/poll/epoll busy-poll time in usec (-1 forever, VMA_POLL_UNSET derives it from use_polling)
+    int rx_poll_os_ratio;        // VMA_RX_POLL_OS_RATIO: offloaded polls per OS poll (0 never polls the OS, VMA_POLL_UNSET keeps VMA's default)
+    int mem_alloc_type;          // VMA_MEM_ALLOC_TYPE (0 anonymous, 1 contiguous, 2 hugepages), -1 derives it from use_hugepages
+    uint32_t rx_wre;             // VMA_RX_WRE: receive work requests per ring (0 keeps VMA's default)
+    uint32_t tx_wre;             // VMA_TX_WRE: send work requests per ring (0 keeps VMA's default)
 } vma_options_t;
 
+// Per-socket counters shared between TCP and UDP
+typedef struct {
+    uint64_t rx_packets;           // Number of received packets
+    uint64_t tx_packets;           // Number of transmitted packets
+    uint64_t rx_bytes;             // Number of received bytes
+    uint64_t tx_bytes;             // Number of transmitted bytes
+    uint64_t rx_errors;             // Receive calls that failed with an error
+    uint64_t tx_errors;             // Send calls that failed with an error
+    uint64_t rx_truncated;         // Datagrams larger than the receive buffer
+    uint64_t rx_eagain;            // Receive calls that returned EAGAIN/EWOULDBLOCK
+    uint64_t tx_eagain;            // Send calls that returned EAGAIN/EWOULDBLOCK
+    uint64_t reconnects;           // Successful reconnects (TCP only)
+    uint64_t last_rx_ns;           // Time of the last successful receive (ns since epoch)
+    uint64_t last_tx_ns;           // Time of the last successful send (ns since epoch)
+} socket_stats_t;
+
+// MSG_ZEROCOPY send state of a socket
+typedef struct {
+    bool enabled;                  // SO_ZEROCOPY is enabled on the socket
+    bool unsupported;              // SO_ZEROCOPY was rejected; zero-copy sends fall back to copying
+    uint32_t next_id;              // Id the next zero-copy send will get
+    uint32_t completed;            // Number of zero-copy sends whose buffers were released
+} zerocopy_state_t;
+
+// VMA offload status of a single socket (from the Extra API)
+//
+// The Extra API has no call returning per-socket ring, CQ or packet counters; those are
+// only reachable through vma_common_dump_fd_stats (to the VMA log) or the vma_stats tool.
+typedef struct {
+    bool vma_loaded;               // Whether the VMA Extra API is available in this process
+    bool offloaded;                // Whether VMA offloads the socket (at least one ring)
+    int ring_count;                // Number of VMA rings backing the socket
+} vma_fd_stats_t;
+
 /**
  * Set up VMA environment variables based on options
  * 
@@ -47,4 +106,177 @@ void vma_setup_environment(const vma_options_t* options);
  */
 void set_default_options(vma_options_t* options);
 
+/**
+ * Get the current wall-clock time
+ * 
+ * @return Nanoseconds since the epoch (CLOCK_REALTIME), or 0 on failure
+ */
+uint64_t vma_now_ns(void);
+
+/**
+ * Get the VMA Extra API, probing it on the first call (thread-safe)
+ * 
+ * @return Pointer to the Extra API, or NULL when libvma is not loaded
+ */
+struct vma_api_t* vma_common_get_api(void);
+
+/**
+ * Get VMA offload information for a socket
+ * 
+ * @param fd Socket file descriptor
+ * @param stats Output pointer to store the offload information
+ * @return 0 on success, -1 on invalid parameters
+ */
+int vma_common_get_fd_stats(int fd, vma_fd_stats_t* stats);
+
+/**
+ * Check whether VMA offloads a socket (libvma loaded and at least one ring attached)
+ * 
+ * @param fd Socket file descriptor
+ * @return true if the socket is offloaded, false otherwise
+ */
+bool vma_common_is_offloaded(int fd);
+
+/**
+ * Get the descriptors of the VMA rings backing a socket (get_socket_rings_fds)
+ * 
+ * The descriptors belong to VMA: wait on them for readability, never close them.
+ * 
+ * @param fd Socket file descriptor
+ * @param ring_fds Output array for the ring descriptors
+ * @param max_fds Number of entries in ring_fds
+ * @return Number of descriptors stored, or -1 on failure (errno is set, ENOTSUP
+ *         when the Extra API is unavailable)
+ */
+int vma_common_get_ring_fds(int fd, int* ring_fds, int max_fds);
+
+/**
+ * Ask VMA to write its internal statistics for a socket to the VMA log
+ * 
+ * @param fd Socket file descriptor
+ * @param log_level VMA log level to write the statistics at
+ * @return 0 on success, -1 if the Extra API is unavailable or the call failed
+ */
+int vma_common_dump_fd_stats(int fd, int log_level);
+
+/**
+ * Get the local address a socket is bound to (resolves ephemeral ports)
+ * 
+ * @param fd Socket file descriptor
+ * @param addr Output pointer to store the address
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int vma_common_get_local_addr(int fd, struct sockaddr_in* addr);
+
+/**
+ * Check that a descriptor is an IPv4 socket of the given type, before adopting it
+ * 
+ * @param fd Socket file descriptor
+ * @param type Expected socket type (SOCK_DGRAM or SOCK_STREAM)
+ * @return 0 if it matches, -1 otherwise (errno is set, ENOTSOCK/EPROTOTYPE/EAFNOSUPPORT)
+ */
+int vma_common_check_socket_fd(int fd, int type);
+
+/**
+ * Get the number of bytes in a socket's send queue (SIOCOUTQ)
+ * 
+ * For TCP this counts data not yet acknowledged by the peer, so a growing value
+ * indicates a congested or stalled receiver.
+ * 
+ * @param fd Socket file descriptor
+ * @param pending Output pointer to store the byte count
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int vma_common_pending_tx_bytes(int fd, uint32_t* pending);
+
+/**
+ * Send data without copying it into socket buffers (MSG_ZEROCOPY)
+ * 
+ * Enables SO_ZEROCOPY on first use; when unavailable the data is sent with a regular copy.
+ * The memory must stay unchanged until vma_common_zerocopy_pending reports the send released.
+ * 
+ * @param fd Socket file descriptor
+ * @param zc Zero-copy state of the socket
+ * @param data Data to send
+ * @param length Data length
+ * @param flags Additional send flags
+ * @return Number of bytes sent, or -1 on failure (errno is set)
+ */
+ssize_t vma_common_send_zerocopy(int fd, zerocopy_state_t* zc, const void* data, size_t length, int flags);
+
+/**
+ * Collect zero-copy completions and count the sends still holding their buffers
+ * 
+ * @param fd Socket file descriptor
+ * @param zc Zero-copy state of the socket
+ * @return Number of zero-copy sends not yet released
+ */
+uint32_t vma_common_zerocopy_pending(int fd, zerocopy_state_t* zc);
+
+/**
+ * Add a libvma.conf rule at runtime through the Extra API
+ * 
+ * @param rule Rule line, e.g. "use vma udp_receiver 239.1.1.1:5000 *:*"
+ * @return 0 on success, -1 if the Extra API is unavailable or the rule was rejected
+ */
+int vma_common_add_conf_rule(const char* rule);
+
+/**
+ * Apply the per-socket ring allocation options (SO_VMA_RING_ALLOC_LOGIC)
+ * 
+ * Must be called before the socket is bound or connected. Does nothing when
+ * ring_alloc_logic is negative or libvma is not loaded.
+ * 
+ * @param fd Socket file descriptor
+ * @param options Options carrying the ring allocation settings
+ * @return 0 on success, -1 on failure (errno is set)
+ */
+int vma_common_apply_ring_alloc(int fd, const vma_options_t* options);
+
+/**
+ * Wait until a socket is readable, following a wait strategy
+ * 
+ * A zero timeout returns immediately so the caller makes a single non-blocking attempt.
+ * 
+ * @param fd Socket file descriptor
+ * @param strategy How to wait
+ * @param spin_budget_us Busy-poll time before yielding or parking (idle threshold for VMA_WAIT_ADAPTIVE)
+ * @param last_rx_ns Time of the last successful receive (vma_now_ns, 0 if none), used by VMA_WAIT_ADAPTIVE
+ * @param timeout_ms Timeout in milliseconds (-1 waits indefinitely)
+ * @return 1 if the socket is ready, 0 on timeout, -1 on error (errno is set)
+ */
+int vma_common_wait_readable(int fd, vma_wait_strategy_t strategy, uint32_t spin_budget_us,
+                             uint64_t last_rx_ns, int timeout_ms);
+
+/**
+ * Get the current monotonic time, for deadlines
+ * 
+ * @return Nanoseconds on CLOCK_MONOTONIC
+ */
+uint64_t vma_monotonic_ns(void);
+
+/**
+ * Check or wait until a socket is readable, following a wait strategy
+ * 
+ * Unlike vma_common_wait_readable(), a zero timeout checks readiness (poll for POLLIN).
+ * 
+ * @param fd Socket file descriptor
+ * @param strategy How to wait
+ * @param spin_budget_us Busy-poll time before yielding or parking
+ * @param last_rx_ns Time of the last successful receive (vma_now_ns, 0 if none)
+ * @param timeout_ms Timeout in milliseconds (0 only checks, -1 waits indefinitely)
+ * @return 1 if the socket is readable, 0 if not or on timeout, -1 on error (errno is set)
+ */
+int vma_common_poll_readable(int fd, vma_wait_strategy_t strategy, uint32_t spin_budget_us,
+                             uint64_t last_rx_ns, int timeout_ms);
+
+/**
+ * Wait until a socket is writable (poll for POLLOUT)
+ * 
+ * @param fd Socket file descriptor
+ * @param timeout_ms Timeout in milliseconds (-1 waits indefinitely)
+ * @return 1 if the socket is ready, 0 on timeout, -1 on error (errno is set)
+ */
+int vma_common_wait_writable(int fd, int timeout_ms);
+
 #endif /* VMA_COMMON_H */
\ No newline at end of file
@@ -0,0 +1,127 @@
+/**
+ * vma_layout.c - Compile-time checks of the structures shared with Rust
+ *
+ * Compiles to nothing; fails the build when a struct no longer matches vma_layout.h.
+ */
+
+#include <stddef.h>
+#include "udp_socket.h"
+#include "tcp_socket.h"
+#include "netmon.h"
+#include "hw.h"
+#include "raw_socket.h"
+#include "vma_layout.h"
+
+#if defined(__LP64__)
+
+typedef struct sockaddr_in sockaddr_in_t;
+
+#define CHECK_SIZE(type) \
+    _Static_assert(sizeof(type) == VMA_LAYOUT_##type##__SIZE, #type " size differs from vma_layout.h")
+#define CHECK_ALIGN(type) \
+    _Static_assert(_Alignof(type) == VMA_LAYOUT_##type##__ALIGN, #type " alignment differs from vma_layout.h")
+#define CHECK_FIELD(type, field) \
+    _Static_assert(offsetof(type, field) == VMA_LAYOUT_##type##__##field, #type "." #field " offset differs from vma_layout.h")
+
+CHECK_SIZE(vma_wait_strategy_t);
+CHECK_SIZE(udp_result_t);
+CHECK_SIZE(tcp_connection_state_t);
+CHECK_SIZE(tcp_result_t);
+
+CHECK_SIZE(vma_options_t);
+CHECK_ALIGN(vma_options_t);
+CHECK_FIELD(vma_options_t, use_socketxtreme);
+CHECK_FIELD(vma_options_t, optimize_for_latency);
+CHECK_FIELD(vma_options_t, use_polling);
+CHECK_FIELD(vma_options_t, ring_count);
+CHECK_FIELD(vma_options_t, buffer_size);
+CHECK_FIELD(vma_options_t, enable_timestamps);
+CHECK_FIELD(vma_options_t, use_hugepages);
+CHECK_FIELD(vma_options_t, tx_bufs);
+CHECK_FIELD(vma_options_t, rx_bufs);
+CHECK_FIELD(vma_options_t, disable_poll_yield);
+CHECK_FIELD(vma_options_t, skip_os_select);
+CHECK_FIELD(vma_options_t, keep_qp_full);
+CHECK_FIELD(vma_options_t, cpu_cores);
+CHECK_FIELD(vma_options_t, cpu_cores_count);
+CHECK_FIELD(vma_options_t, tcp_nodelay);
+CHECK_FIELD(vma_options_t, wait_strategy);
+CHECK_FIELD(vma_options_t, spin_budget_us);
+CHECK_FIELD(vma_options_t, ring_alloc_logic);
+CHECK_FIELD(vma_options_t, ring_user_id);
+CHECK_FIELD(vma_options_t, thread_mode);
+CHECK_FIELD(vma_options_t, rx_poll);
+CHECK_FIELD(vma_options_t, select_poll);
+CHECK_FIELD(vma_options_t, rx_poll_os_ratio);
+CHECK_FIELD(vma_options_t, mem_alloc_type);
+CHECK_FIELD(vma_options_t, rx_wre);
+CHECK_FIELD(vma_options_t, tx_wre);
+
+CHECK_SIZE(sockaddr_in_t);
+CHECK_ALIGN(sockaddr_in_t);
+CHECK_FIELD(sockaddr_in_t, sin_family);
+CHECK_FIELD(sockaddr_in_t, sin_port);
+CHECK_FIELD(sockaddr_in_t, sin_addr);
+CHECK_FIELD(sockaddr_in_t, sin_zero);
+
+CHECK_SIZE(socket_stats_t);
+CHECK_ALIGN(socket_stats_t);
+CHECK_FIELD(socket_stats_t, rx_packets);
+CHECK_FIELD(socket_stats_t, tx_packets);
+CHECK_FIELD(socket_stats_t, rx_bytes);
+CHECK_FIELD(socket_stats_t, tx_bytes);
+CHECK_FIELD(socket_stats_t, rx_errors);
+CHECK_FIELD(socket_stats_t, tx_errors);
+CHECK_FIELD(socket_stats_t, rx_truncated);
+CHECK_FIELD(socket_stats_t, rx_eagain);
+CHECK_FIELD(socket_stats_t, tx_eagain);
+CHECK_FIELD(socket_stats_t, reconnects);
+CHECK_FIELD(socket_stats_t, last_rx_ns);
+CHECK_FIELD(socket_stats_t, last_tx_ns);
+
+CHECK_SIZE(vma_fd_stats_t);
+CHECK_ALIGN(vma_fd_stats_t);
+CHECK_FIELD(vma_fd_stats_t, vma_loaded);
+CHECK_FIELD(vma_fd_stats_t, offloaded);
+CHECK_FIELD(vma_fd_stats_t, ring_count);
+
+CHECK_SIZE(udp_packet_t);
+CHECK_ALIGN(udp_packet_t);
+CHECK_FIELD(udp_packet_t, data);
+CHECK_FIELD(udp_packet_t, length);
+CHECK_FIELD(udp_packet_t, src_addr);
+CHECK_FIELD(udp_packet_t, timestamp);
+CHECK_FIELD(udp_packet_t, dst_addr);
+CHECK_FIELD(udp_packet_t, if_index);
+CHECK_FIELD(udp_packet_t, full_length);
+
+CHECK_SIZE(netmon_link_t);
+CHECK_ALIGN(netmon_link_t);
+CHECK_FIELD(netmon_link_t, if_index);
+CHECK_FIELD(netmon_link_t, flags);
+CHECK_FIELD(netmon_link_t, operstate);
+CHECK_FIELD(netmon_link_t, removed);
+CHECK_FIELD(netmon_link_t, name);
+
+CHECK_SIZE(hw_ts_info_t);
+CHECK_ALIGN(hw_ts_info_t);
+CHECK_FIELD(hw_ts_info_t, so_timestamping);
+CHECK_FIELD(hw_ts_info_t, phc_index);
+CHECK_FIELD(hw_ts_info_t, tx_types);
+CHECK_FIELD(hw_ts_info_t, rx_filters);
+
+CHECK_SIZE(hw_stat_t);
+CHECK_ALIGN(hw_stat_t);
+CHECK_FIELD(hw_stat_t, name);
+CHECK_FIELD(hw_stat_t, value);
+
+CHECK_SIZE(raw_packet_info_t);
+CHECK_ALIGN(raw_packet_info_t);
+CHECK_FIELD(raw_packet_info_t, length);
+CHECK_FIELD(raw_packet_info_t, full_length);
+CHECK_FIELD(raw_packet_info_t, timestamp);
+CHECK_FIELD(raw_packet_info_t, if_index);
+CHECK_FIELD(raw_packet_info_t, protocol);
+CHECK_FIELD(raw_packet_info_t, pkt_type);
+
+#endif /* __LP64__ */
@@ -0,0 +1,121 @@
+/**
+ * vma_layout.h - Expected layout of the structures shared with Rust
+ *
+ * The Rust side mirrors the structs of vma_common.h, udp_socket.h, tcp_socket.h, netmon.h, hw.h and raw_socket.h
+ * that cross the boundary by value with #[repr(C)] types; socket and client state is
+ * only reached through opaque handles and is not listed. Both sides check their layout
+ * against the sizes and offsets below at compile time (vma_layout.c here, src/layout.rs
+ * in Rust; build.rs hands this file to the Rust compiler), so a field added, removed,
+ * reordered or resized on one side only fails the build instead of corrupting memory.
+ *
+ * Entries are VMA_LAYOUT_<type>__SIZE, VMA_LAYOUT_<type>__ALIGN and
+ * VMA_LAYOUT_<type>__<field> (the field offset). When changing a shared struct, update
+ * the C header, the Rust mirror and this table together. Values are for LP64 Linux
+ * targets (x86_64, aarch64).
+ */
+
+#ifndef VMA_LAYOUT_H
+#define VMA_LAYOUT_H
+
+#define VMA_LAYOUT_vma_wait_strategy_t__SIZE 4
+#define VMA_LAYOUT_udp_result_t__SIZE 4
+#define VMA_LAYOUT_tcp_connection_state_t__SIZE 4
+#define VMA_LAYOUT_tcp_result_t__SIZE 4
+
+#define VMA_LAYOUT_vma_options_t__SIZE 592
+#define VMA_LAYOUT_vma_options_t__ALIGN 4
+#define VMA_LAYOUT_vma_options_t__use_socketxtreme 0
+#define VMA_LAYOUT_vma_options_t__optimize_for_latency 1
+#define VMA_LAYOUT_vma_options_t__use_polling 2
+#define VMA_LAYOUT_vma_options_t__ring_count 4
+#define VMA_LAYOUT_vma_options_t__buffer_size 8
+#define VMA_LAYOUT_vma_options_t__enable_timestamps 12
+#define VMA_LAYOUT_vma_options_t__use_hugepages 13
+#define VMA_LAYOUT_vma_options_t__tx_bufs 16
+#define VMA_LAYOUT_vma_options_t__rx_bufs 20
+#define VMA_LAYOUT_vma_options_t__disable_poll_yield 24
+#define VMA_LAYOUT_vma_options_t__skip_os_select 25
+#define VMA_LAYOUT_vma_options_t__keep_qp_full 26
+#define VMA_LAYOUT_vma_options_t__cpu_cores 28
+#define VMA_LAYOUT_vma_options_t__cpu_cores_count 540
+#define VMA_LAYOUT_vma_options_t__tcp_nodelay 544
+#define VMA_LAYOUT_vma_options_t__wait_strategy 548
+#define VMA_LAYOUT_vma_options_t__spin_budget_us 552
+#define VMA_LAYOUT_vma_options_t__ring_alloc_logic 556
+#define VMA_LAYOUT_vma_options_t__ring_user_id 560
+#define VMA_LAYOUT_vma_options_t__thread_mode 564
+#define VMA_LAYOUT_vma_options_t__rx_poll 568
+#define VMA_LAYOUT_vma_options_t__select_poll 572
+#define VMA_LAYOUT_vma_options_t__rx_poll_os_ratio 576
+#define VMA_LAYOUT_vma_options_t__mem_alloc_type 580
+#define VMA_LAYOUT_vma_options_t__rx_wre 584
+#define VMA_LAYOUT_vma_options_t__tx_wre 588
+
+#define VMA_LAYOUT_sockaddr_in_t__SIZE 16
+#define VMA_LAYOUT_sockaddr_in_t__ALIGN 4
+#define VMA_LAYOUT_sockaddr_in_t__sin_family 0
+#define VMA_LAYOUT_sockaddr_in_t__sin_port 2
+#define VMA_LAYOUT_sockaddr_in_t__sin_addr 4
+#define VMA_LAYOUT_sockaddr_in_t__sin_zero 8
+
+#define VMA_LAYOUT_socket_stats_t__SIZE 96
+#define VMA_LAYOUT_socket_stats_t__ALIGN 8
+#define VMA_LAYOUT_socket_stats_t__rx_packets 0
+#define VMA_LAYOUT_socket_stats_t__tx_packets 8
+#define VMA_LAYOUT_socket_stats_t__rx_bytes 16
+#define VMA_LAYOUT_socket_stats_t__tx_bytes 24
+#define VMA_LAYOUT_socket_stats_t__rx_errors 32
+#define VMA_LAYOUT_socket_stats_t__tx_errors 40
+#define VMA_LAYOUT_socket_stats_t__rx_truncated 48
+#define VMA_LAYOUT_socket_stats_t__rx_eagain 56
+#define VMA_LAYOUT_socket_stats_t__tx_eagain 64
+#define VMA_LAYOUT_socket_stats_t__reconnects 72
+#define VMA_LAYOUT_socket_stats_t__last_rx_ns 80
+#define VMA_LAYOUT_socket_stats_t__last_tx_ns 88
+
+#define VMA_LAYOUT_vma_fd_stats_t__SIZE 8
+#define VMA_LAYOUT_vma_fd_stats_t__ALIGN 4
+#define VMA_LAYOUT_vma_fd_stats_t__vma_loaded 0
+#define VMA_LAYOUT_vma_fd_stats_t__offloaded 1
+#define VMA_LAYOUT_vma_fd_stats_t__ring_count 4
+
+#define VMA_LAYOUT_udp_packet_t__SIZE 56
+#define VMA_LAYOUT_udp_packet_t__ALIGN 8
+#define VMA_LAYOUT_udp_packet_t__data 0
+#define VMA_LAYOUT_udp_packet_t__length 8
+#define VMA_LAYOUT_udp_packet_t__src_addr 16
+#define VMA_LAYOUT_udp_packet_t__timestamp 32
+#define VMA_LAYOUT_udp_packet_t__dst_addr 40
+#define VMA_LAYOUT_udp_packet_t__if_index 44
+#define VMA_LAYOUT_udp_packet_t__full_length 48
+
+#define VMA_LAYOUT_netmon_link_t__SIZE 28
+#define VMA_LAYOUT_netmon_link_t__ALIGN 4
+#define VMA_LAYOUT_netmon_link_t__if_index 0
+#define VMA_LAYOUT_netmon_link_t__flags 4
+#define VMA_LAYOUT_netmon_link_t__operstate 8
+#define VMA_LAYOUT_netmon_link_t__removed 9
+#define VMA_LAYOUT_netmon_link_t__name 10
+
+#define VMA_LAYOUT_hw_ts_info_t__SIZE 16
+#define VMA_LAYOUT_hw_ts_info_t__ALIGN 4
+#define VMA_LAYOUT_hw_ts_info_t__so_timestamping 0
+#define VMA_LAYOUT_hw_ts_info_t__phc_index 4
+#define VMA_LAYOUT_hw_ts_info_t__tx_types 8
+#define VMA_LAYOUT_hw_ts_info_t__rx_filters 12
+
+#define VMA_LAYOUT_hw_stat_t__SIZE 40
+#define VMA_LAYOUT_hw_stat_t__ALIGN 8
+#define VMA_LAYOUT_hw_stat_t__name 0
+#define VMA_LAYOUT_hw_stat_t__value 32
+
+#define VMA_LAYOUT_raw_packet_info_t__SIZE 32
+#define VMA_LAYOUT_raw_packet_info_t__ALIGN 8
+#define VMA_LAYOUT_raw_packet_info_t__length 0
+#define VMA_LAYOUT_raw_packet_info_t__full_length 8
+#define VMA_LAYOUT_raw_packet_info_t__timestamp 16
+#define VMA_LAYOUT_raw_packet_info_t__if_index 24
+#define VMA_LAYOUT_raw_packet_info_t__protocol 28
+#define VMA_LAYOUT_raw_packet_info_t__pkt_type 30
+
+#endif /* VMA_LAYOUT_H */
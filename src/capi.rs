@@ -0,0 +1,602 @@
+//! Stable C ABI over the high-level sockets.
+//!
+//! Enabled with the `capi` feature. The declarations are in `include/vma_socket.h`, so C
+//! and C++ applications can use [`VmaUdpSocket`], [`VmaTcpSocket`] and accepted [`Client`]s
+//! (timeouts, stats, reconnect state) instead of calling libvma directly. build.rs
+//! regenerates the header with cbindgen into `OUT_DIR` and the tests fail if the shipped
+//! copy differs, so a signature change comes with its header update.
+//!
+//! The crate is an `rlib` only; build the shared library for C with
+//! `cargo rustc --release --features capi --crate-type cdylib`, which writes
+//! `target/release/libvma_socket.so`.
+//!
+//! Sockets are opaque pointers created by a `*_new` function and released with the
+//! matching `*_free`; nothing about their layout is part of the ABI. Options are passed as
+//! the JSON form of [`VmaOptions`] (as in `vma_options.json`), or NULL for the defaults,
+//! so new options do not change any signature.
+//!
+//! Every function returning `int` returns 0 on success and a negated `errno` value on
+//! failure. A receive or accept that times out returns `-ETIMEDOUT`; a TCP receive that
+//! returns 0 with `*received == 0` means the peer closed the connection. Timeouts are in
+//! nanoseconds, and a negative timeout uses the socket's default read timeout (blocking
+//! when none is set). Addresses and ports are in host byte order.
+//!
+//! A handle must not be used from two threads at once.
+//!
+//! # Example
+//!
+//! ```c
+//! #include "vma_socket.h"
+//!
+//! vma_udp_socket_t* socket;
+//! if (vma_udp_socket_new("{\"use_polling\": true}", &socket) != 0) {
+//!     return 1;
+//! }
+//! vma_udp_bind(socket, "0.0.0.0", 5001);
+//!
+//! uint8_t buffer[1500];
+//! size_t received;
+//! int rc = vma_udp_recv(socket, buffer, sizeof(buffer), 1000000000, &received);
+//! if (rc == 0) {
+//!     // handle buffer[0..received]
+//! } else if (rc != -ETIMEDOUT) {
+//!     fprintf(stderr, "recv: %s\n", strerror(-rc));
+//! }
+//! vma_udp_socket_free(socket);
+//! ```
+
+use std::ffi::{c_char, CStr};
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::os::raw::c_int;
+use std::slice;
+
+use crate::common::{SocketStats, VmaOptions};
+use crate::tcp::{Client, VmaTcpSocket};
+use crate::udp::VmaUdpSocket;
+
+/// Traffic counters of a socket, as returned by `vma_udp_stats` and `vma_tcp_stats`.
+///
+/// Kept apart from `common::SocketStats` so the C layout only changes when this API does;
+/// fields are only ever appended.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VmaSocketStats {
+    /// Number of received packets
+    pub rx_packets: u64,
+    /// Number of transmitted packets
+    pub tx_packets: u64,
+    /// Number of received bytes
+    pub rx_bytes: u64,
+    /// Number of transmitted bytes
+    pub tx_bytes: u64,
+    /// Receive calls that failed with an error
+    pub rx_errors: u64,
+    /// Send calls that failed with an error
+    pub tx_errors: u64,
+    /// Datagrams that did not fit in the receive buffer (UDP only)
+    pub rx_truncated: u64,
+    /// Receive calls that returned `EAGAIN`/`EWOULDBLOCK`
+    pub rx_eagain: u64,
+    /// Send calls that returned `EAGAIN`/`EWOULDBLOCK`
+    pub tx_eagain: u64,
+    /// Successful reconnects (TCP only)
+    pub reconnects: u64,
+    /// Time of the last successful receive in nanoseconds since the epoch (0 if none)
+    pub last_rx_ns: u64,
+    /// Time of the last successful send in nanoseconds since the epoch (0 if none)
+    pub last_tx_ns: u64,
+}
+
+impl From<SocketStats> for VmaSocketStats {
+    fn from(stats: SocketStats) -> Self {
+        VmaSocketStats {
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            rx_errors: stats.rx_errors,
+            tx_errors: stats.tx_errors,
+            rx_truncated: stats.rx_truncated,
+            rx_eagain: stats.rx_eagain,
+            tx_eagain: stats.tx_eagain,
+            reconnects: stats.reconnects,
+            last_rx_ns: stats.last_rx_ns,
+            last_tx_ns: stats.last_tx_ns,
+        }
+    }
+}
+
+/// Negated `errno` value for an error, falling back on its kind for errors without one.
+fn errno(error: &Error) -> c_int {
+    if let Some(code) = error.raw_os_error() {
+        return -code;
+    }
+    -match error.kind() {
+        ErrorKind::TimedOut => libc::ETIMEDOUT,
+        ErrorKind::WouldBlock => libc::EAGAIN,
+        ErrorKind::InvalidInput | ErrorKind::InvalidData => libc::EINVAL,
+        ErrorKind::NotConnected => libc::ENOTCONN,
+        ErrorKind::ConnectionRefused => libc::ECONNREFUSED,
+        ErrorKind::ConnectionReset => libc::ECONNRESET,
+        ErrorKind::ConnectionAborted => libc::ECONNABORTED,
+        ErrorKind::AddrInUse => libc::EADDRINUSE,
+        ErrorKind::BrokenPipe => libc::EPIPE,
+        ErrorKind::AlreadyExists => libc::EISCONN,
+        ErrorKind::Unsupported => libc::EOPNOTSUPP,
+        _ => libc::EIO,
+    }
+}
+
+/// Turn a result into the C return code, storing the value on success.
+fn complete<T>(result: Result<T, Error>, out: *mut T) -> c_int {
+    match result {
+        Ok(value) => {
+            unsafe { out.write(value) };
+            0
+        }
+        Err(e) => errno(&e),
+    }
+}
+
+/// Parse the options JSON (NULL for the defaults).
+unsafe fn options(json: *const c_char) -> Result<VmaOptions, Error> {
+    if json.is_null() {
+        return Ok(VmaOptions::default());
+    }
+    let json = CStr::from_ptr(json).to_str().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    serde_json::from_str(json).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+unsafe fn ip<'a>(ip: *const c_char) -> Result<&'a str, Error> {
+    if ip.is_null() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Null address"));
+    }
+    CStr::from_ptr(ip).to_str().map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+unsafe fn output<'a>(buffer: *mut u8, len: usize) -> &'a mut [u8] {
+    if len == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(buffer, len)
+    }
+}
+
+fn timeout(timeout_ns: i64) -> Option<u64> {
+    u64::try_from(timeout_ns).ok()
+}
+
+fn timed_out() -> Error {
+    Error::new(ErrorKind::TimedOut, "Operation timed out")
+}
+
+/// Store an IPv4 address and port in host byte order (0 for IPv6 peers).
+unsafe fn write_addr(addr: SocketAddr, ip: *mut u32, port: *mut u16) {
+    if !ip.is_null() {
+        *ip = match addr {
+            SocketAddr::V4(addr) => u32::from(*addr.ip()),
+            SocketAddr::V6(_) => 0,
+        };
+    }
+    if !port.is_null() {
+        *port = addr.port();
+    }
+}
+
+macro_rules! handle {
+    ($ptr:expr) => {
+        match $ptr.as_mut() {
+            Some(handle) => handle,
+            None => return -libc::EINVAL,
+        }
+    };
+}
+
+macro_rules! try_errno {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(e) => return errno(&e),
+        }
+    };
+}
+
+// --- UDP ---
+
+/// Create a UDP socket with the given options JSON (NULL for the defaults).
+///
+/// # Safety
+/// `options_json` must be NULL or a NUL-terminated string; `socket` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_socket_new(options_json: *const c_char, socket: *mut *mut VmaUdpSocket) -> c_int {
+    if socket.is_null() {
+        return -libc::EINVAL;
+    }
+    *socket = std::ptr::null_mut();
+    let options = try_errno!(options(options_json));
+    complete(VmaUdpSocket::with_options(options).map(|s| Box::into_raw(Box::new(s))), socket)
+}
+
+/// Close and free a UDP socket (NULL is ignored).
+///
+/// # Safety
+/// `socket` must come from `vma_udp_socket_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_socket_free(socket: *mut VmaUdpSocket) {
+    if !socket.is_null() {
+        drop(Box::from_raw(socket));
+    }
+}
+
+/// Get the file descriptor of a UDP socket (-1 for NULL).
+///
+/// # Safety
+/// `socket` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_fd(socket: *const VmaUdpSocket) -> c_int {
+    socket.as_ref().map_or(-1, |socket| socket.fd())
+}
+
+/// Bind a UDP socket to a local IPv4 address and port.
+///
+/// # Safety
+/// `socket` must be a live handle and `ip` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_bind(socket: *mut VmaUdpSocket, ip: *const c_char, port: u16) -> c_int {
+    let socket = handle!(socket);
+    complete(self::ip(ip).and_then(|ip| socket.bind(ip, port)), &mut ())
+}
+
+/// Set the default destination of a UDP socket.
+///
+/// # Safety
+/// `socket` must be a live handle and `ip` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_connect(socket: *mut VmaUdpSocket, ip: *const c_char, port: u16) -> c_int {
+    let socket = handle!(socket);
+    complete(self::ip(ip).and_then(|ip| socket.connect(ip, port)), &mut ())
+}
+
+/// Send a datagram to the default destination.
+///
+/// # Safety
+/// `socket` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_send(socket: *mut VmaUdpSocket, data: *const u8, len: usize, sent: *mut usize) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.send(input(data, len)), sent)
+}
+
+/// Send a datagram to an IPv4 address and port.
+///
+/// # Safety
+/// `socket` must be a live handle, `data` readable for `len` bytes, `ip` a
+/// NUL-terminated string and `sent` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_send_to(
+    socket: *mut VmaUdpSocket,
+    data: *const u8,
+    len: usize,
+    ip: *const c_char,
+    port: u16,
+    sent: *mut usize,
+) -> c_int {
+    let socket = handle!(socket);
+    let result = self::ip(ip).and_then(|ip| socket.send_to(input(data, len), ip, port));
+    complete(result, sent)
+}
+
+/// Receive a datagram, waiting up to `timeout_ns` (`-ETIMEDOUT` when none arrived).
+///
+/// # Safety
+/// `socket` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_recv(
+    socket: *mut VmaUdpSocket,
+    buffer: *mut u8,
+    len: usize,
+    timeout_ns: i64,
+    received: *mut usize,
+) -> c_int {
+    let socket = handle!(socket);
+    let result = socket
+        .recv_full(output(buffer, len), timeout(timeout_ns))
+        .and_then(|length| length.map(|length| length.copied).ok_or_else(timed_out));
+    complete(result, received)
+}
+
+/// Receive a datagram and its source address (`src_ip` and `src_port` may be NULL).
+///
+/// # Safety
+/// As for `vma_udp_recv`; `src_ip` and `src_port` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_recv_from(
+    socket: *mut VmaUdpSocket,
+    buffer: *mut u8,
+    len: usize,
+    timeout_ns: i64,
+    received: *mut usize,
+    src_ip: *mut u32,
+    src_port: *mut u16,
+) -> c_int {
+    let socket = handle!(socket);
+    let packet = try_errno!(socket
+        .recv_from(output(buffer, len), timeout(timeout_ns))
+        .and_then(|packet| packet.ok_or_else(timed_out)));
+    write_addr(packet.src_addr, src_ip, src_port);
+    complete(Ok(packet.data.len()), received)
+}
+
+/// Get the traffic counters of a UDP socket.
+///
+/// # Safety
+/// `socket` must be a live handle and `stats` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_udp_stats(socket: *mut VmaUdpSocket, stats: *mut VmaSocketStats) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.stats().map(VmaSocketStats::from), stats)
+}
+
+// --- TCP ---
+
+/// Create a TCP socket with the given options JSON (NULL for the defaults).
+///
+/// # Safety
+/// `options_json` must be NULL or a NUL-terminated string; `socket` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_socket_new(options_json: *const c_char, socket: *mut *mut VmaTcpSocket) -> c_int {
+    if socket.is_null() {
+        return -libc::EINVAL;
+    }
+    *socket = std::ptr::null_mut();
+    let options = try_errno!(options(options_json));
+    complete(VmaTcpSocket::with_options(options).map(|s| Box::into_raw(Box::new(s))), socket)
+}
+
+/// Close and free a TCP socket (NULL is ignored).
+///
+/// Clients accepted from it stay open until they are freed themselves.
+///
+/// # Safety
+/// `socket` must come from `vma_tcp_socket_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_socket_free(socket: *mut VmaTcpSocket) {
+    if !socket.is_null() {
+        drop(Box::from_raw(socket));
+    }
+}
+
+/// Get the file descriptor of a TCP socket (-1 for NULL).
+///
+/// # Safety
+/// `socket` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_fd(socket: *const VmaTcpSocket) -> c_int {
+    socket.as_ref().map_or(-1, |socket| socket.fd())
+}
+
+/// Bind a TCP socket to a local IPv4 address and port.
+///
+/// # Safety
+/// `socket` must be a live handle and `ip` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_bind(socket: *mut VmaTcpSocket, ip: *const c_char, port: u16) -> c_int {
+    let socket = handle!(socket);
+    complete(self::ip(ip).and_then(|ip| socket.bind(ip, port)), &mut ())
+}
+
+/// Start listening for connections.
+///
+/// # Safety
+/// `socket` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_listen(socket: *mut VmaTcpSocket, backlog: c_int) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.listen(backlog), &mut ())
+}
+
+/// Accept a connection, waiting up to `timeout_ns` (`-ETIMEDOUT` when none arrived).
+///
+/// Release the client with `vma_tcp_client_free`.
+///
+/// # Safety
+/// `socket` must be a live handle and `client` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_accept(socket: *mut VmaTcpSocket, timeout_ns: i64, client: *mut *mut Client) -> c_int {
+    let socket = handle!(socket);
+    if client.is_null() {
+        return -libc::EINVAL;
+    }
+    *client = std::ptr::null_mut();
+    let result = socket
+        .accept(timeout(timeout_ns))
+        .and_then(|accepted| accepted.ok_or_else(timed_out))
+        .map(|accepted| Box::into_raw(Box::new(accepted)));
+    complete(result, client)
+}
+
+/// Connect to a server, waiting up to `timeout_ns` (`-ETIMEDOUT` when not connected in time).
+///
+/// # Safety
+/// `socket` must be a live handle and `ip` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_connect(socket: *mut VmaTcpSocket, ip: *const c_char, port: u16, timeout_ns: i64) -> c_int {
+    let socket = handle!(socket);
+    let result = self::ip(ip)
+        .and_then(|ip| socket.connect(ip, port, timeout(timeout_ns)))
+        .and_then(|connected| if connected { Ok(()) } else { Err(timed_out()) });
+    complete(result, &mut ())
+}
+
+/// Check whether the socket is connected.
+///
+/// # Safety
+/// `socket` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_is_connected(socket: *mut VmaTcpSocket) -> bool {
+    socket.as_mut().is_some_and(|socket| socket.is_connected())
+}
+
+/// Send on a connected socket (`*sent` may be 0 when the send buffer is full).
+///
+/// # Safety
+/// `socket` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_send(socket: *mut VmaTcpSocket, data: *const u8, len: usize, sent: *mut usize) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.send(input(data, len)), sent)
+}
+
+/// Receive from a connected socket (`*received == 0` once the peer closed the connection).
+///
+/// # Safety
+/// `socket` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_recv(
+    socket: *mut VmaTcpSocket,
+    buffer: *mut u8,
+    len: usize,
+    timeout_ns: i64,
+    received: *mut usize,
+) -> c_int {
+    let socket = handle!(socket);
+    let result = socket
+        .recv_checked(output(buffer, len), timeout(timeout_ns))
+        .and_then(|bytes| bytes.ok_or_else(timed_out));
+    complete(result, received)
+}
+
+/// Enable or disable TCP_NODELAY (kept across reconnects).
+///
+/// # Safety
+/// `socket` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_set_nodelay(socket: *mut VmaTcpSocket, nodelay: bool) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.set_nodelay(nodelay), &mut ())
+}
+
+/// Get the traffic counters of a TCP socket.
+///
+/// # Safety
+/// `socket` must be a live handle and `stats` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_stats(socket: *mut VmaTcpSocket, stats: *mut VmaSocketStats) -> c_int {
+    let socket = handle!(socket);
+    complete(socket.stats().map(VmaSocketStats::from), stats)
+}
+
+// --- Accepted clients ---
+
+/// Close and free an accepted client (NULL is ignored).
+///
+/// # Safety
+/// `client` must come from `vma_tcp_accept` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_client_free(client: *mut Client) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Get the file descriptor of an accepted client (-1 for NULL).
+///
+/// # Safety
+/// `client` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_client_fd(client: *const Client) -> c_int {
+    client.as_ref().map_or(-1, |client| client.fd())
+}
+
+/// Get the remote address of an accepted client (`ip` and `port` may be NULL).
+///
+/// # Safety
+/// `client` must be a live handle; `ip` and `port` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_client_peer(client: *const Client, ip: *mut u32, port: *mut u16) -> c_int {
+    let Some(client) = client.as_ref() else {
+        return -libc::EINVAL;
+    };
+    write_addr(client.address, ip, port);
+    0
+}
+
+/// Send to an accepted client.
+///
+/// # Safety
+/// `client` must be a live handle, `data` readable for `len` bytes and `sent` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_client_send(client: *mut Client, data: *const u8, len: usize, sent: *mut usize) -> c_int {
+    let client = handle!(client);
+    complete(client.send(input(data, len)).map_err(Error::from), sent)
+}
+
+/// Receive from an accepted client (`*received == 0` once the client closed the connection).
+///
+/// # Safety
+/// `client` must be a live handle, `buffer` writable for `len` bytes and `received` writable.
+#[no_mangle]
+pub unsafe extern "C" fn vma_tcp_client_recv(
+    client: *mut Client,
+    buffer: *mut u8,
+    len: usize,
+    timeout_ns: i64,
+    received: *mut usize,
+) -> c_int {
+    let client = handle!(client);
+    let result = match client.recv(output(buffer, len), timeout(timeout_ns)) {
+        Err(crate::tcp::TcpResult::TcpErrorClosed) => Ok(0),
+        result => result.map_err(Error::from),
+    };
+    complete(result, received)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_errno() {
+        assert_eq!(errno(&Error::from_raw_os_error(libc::EMFILE)), -libc::EMFILE);
+        assert_eq!(errno(&timed_out()), -libc::ETIMEDOUT);
+        assert_eq!(errno(&crate::tcp::TcpResult::TcpErrorWouldBlock.into()), -libc::EAGAIN);
+        assert_eq!(errno(&Error::other("other")), -libc::EIO);
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(unsafe { options(std::ptr::null()) }.unwrap(), VmaOptions::default());
+        let json = c"{\"use_polling\": true, \"ring_count\": 2}";
+        let parsed = unsafe { options(json.as_ptr()) }.unwrap();
+        assert!(parsed.use_polling);
+        assert_eq!(parsed.ring_count, 2);
+        assert_eq!(errno(&unsafe { options(c"{".as_ptr()) }.unwrap_err()), -libc::EINVAL);
+    }
+
+    #[test]
+    fn test_null_handles() {
+        let mut sent = 0;
+        unsafe {
+            assert_eq!(vma_udp_send(std::ptr::null_mut(), std::ptr::null(), 0, &mut sent), -libc::EINVAL);
+            assert_eq!(vma_tcp_fd(std::ptr::null()), -1);
+            assert!(!vma_tcp_is_connected(std::ptr::null_mut()));
+            vma_udp_socket_free(std::ptr::null_mut());
+            vma_tcp_client_free(std::ptr::null_mut());
+        }
+    }
+    #[test]
+    fn test_shipped_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/vma_socket.h"));
+        let shipped = include_str!("../include/vma_socket.h");
+        assert!(generated == shipped, "include/vma_socket.h is stale; copy $OUT_DIR/vma_socket.h over it");
+    }
+}
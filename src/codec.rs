@@ -0,0 +1,181 @@
+//! Codec-driven framing with `tokio_util::codec` encoders and decoders.
+//!
+//! Enabled with the `codec` feature. [`Framed`] drives any `tokio_util::codec`
+//! [`Decoder`]/[`Encoder`] over any [`FrameTransport`] (a [`VmaTcpSocket`], an accepted
+//! [`Client`] or a mock), blocking with the usual nanosecond timeouts, so tokio-util's
+//! own codecs ([`LengthDelimitedCodec`], [`LinesCodec`], ...) and third-party ones plug in
+//! as they are. Only the codec traits of `tokio-util` are used; no async runtime is needed.
+//!
+//! The default wire format of [`LengthDelimitedCodec`] (4-byte big-endian length prefix)
+//! is also the format of [`FramedTcp`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::codec::{Framed, LengthDelimitedCodec};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let mut framed = Framed::new(socket, LengthDelimitedCodec::new());
+//! framed.send(bytes::Bytes::from_static(b"subscribe AAPL")).unwrap();
+//! if let Some(frame) = framed.recv(Some(100_000_000)).unwrap() {
+//!     println!("reply of {} bytes", frame.len());
+//! }
+//! ```
+//!
+//! [`VmaTcpSocket`]: crate::tcp::VmaTcpSocket
+//! [`Client`]: crate::tcp::Client
+//! [`FramedTcp`]: crate::framed::FramedTcp
+
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+pub use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec, LinesCodec};
+
+use crate::framed::FrameTransport;
+
+/// Bytes requested from the transport per read call.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// A byte stream exchanging the frames of a codec.
+#[derive(Debug)]
+pub struct Framed<S, C> {
+    transport: S,
+    codec: C,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    eof: bool,
+}
+
+impl<S: FrameTransport, C> Framed<S, C> {
+    /// Wrap a connected transport.
+    pub fn new(transport: S, codec: C) -> Self {
+        Framed {
+            transport,
+            codec,
+            read_buf: BytesMut::with_capacity(READ_CHUNK),
+            write_buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Get the underlying transport.
+    pub fn get_ref(&self) -> &S {
+        &self.transport
+    }
+
+    /// Get the underlying transport mutably (reading from it directly breaks the framing).
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.transport
+    }
+
+    /// Get the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Get the codec mutably.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Get the received bytes not yet decoded.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.read_buf
+    }
+
+    /// Unwrap the transport and codec, discarding any partially received frame.
+    pub fn into_parts(self) -> (S, C) {
+        (self.transport, self.codec)
+    }
+
+    /// Encode `item` and send it, waiting until all of it is written.
+    pub fn send<I>(&mut self, item: I) -> Result<(), C::Error>
+    where
+        C: Encoder<I>,
+    {
+        self.write_buf.clear();
+        self.codec.encode(item, &mut self.write_buf)?;
+        self.transport.write_all(&self.write_buf)?;
+        Ok(())
+    }
+
+    /// Receive the next frame, waiting up to `timeout` nanoseconds in total.
+    ///
+    /// Returns `Ok(None)` on timeout, with partially received bytes kept for the next call,
+    /// and once the peer closed the connection and every frame was decoded.
+    pub fn recv(&mut self, timeout: Option<u64>) -> Result<Option<C::Item>, C::Error>
+    where
+        C: Decoder,
+    {
+        let deadline = timeout.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        loop {
+            if self.eof {
+                return self.codec.decode_eof(&mut self.read_buf);
+            }
+            if let Some(frame) = self.codec.decode(&mut self.read_buf)? {
+                return Ok(Some(frame));
+            }
+
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+            let filled = self.read_buf.len();
+            self.read_buf.resize(filled + READ_CHUNK, 0);
+            let received = self.transport.read(&mut self.read_buf[filled..], remaining);
+            self.read_buf.truncate(filled + received.as_ref().ok().copied().flatten().unwrap_or(0));
+
+            match received? {
+                Some(0) => self.eof = true,
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::framed::FramedTcp;
+    use crate::mock::MockTransport;
+    use bytes::Bytes;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_framed_codec() {
+        let (a, b) = MockTransport::stream_pair();
+        let mut tx = Framed::new(a, LinesCodec::new());
+        let mut rx = Framed::new(b, LinesCodec::new());
+        tx.send("hello").unwrap();
+        tx.send("world").unwrap();
+        assert_eq!(rx.recv(Some(0)).unwrap().as_deref(), Some("hello"));
+        assert_eq!(rx.recv(Some(0)).unwrap().as_deref(), Some("world"));
+        assert_eq!(rx.recv(Some(1_000_000)).unwrap(), None);
+
+        // LinesCodec hands out an unterminated last line at close, then the stream ends
+        tx.get_mut().send(b"cut").unwrap();
+        drop(tx);
+        assert_eq!(rx.recv(None).unwrap().as_deref(), Some("cut"));
+        assert_eq!(rx.recv(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_length_delimited_matches_framed_tcp() {
+        let (a, b) = MockTransport::stream_pair();
+        let mut tx = FramedTcp::new(a);
+        let mut rx = Framed::new(b, LengthDelimitedCodec::builder().max_frame_length(8).new_codec());
+        tx.send_frame(b"one").unwrap();
+        tx.send_frame(b"").unwrap();
+        tx.send_frame(b"too long!").unwrap();
+        assert_eq!(&rx.recv(Some(0)).unwrap().unwrap()[..], b"one");
+        assert!(rx.recv(Some(0)).unwrap().unwrap().is_empty());
+        assert_eq!(rx.recv(Some(0)).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let (a, b) = MockTransport::stream_pair();
+        let mut tx = Framed::new(a, LengthDelimitedCodec::new());
+        let mut rx = FramedTcp::new(b);
+        tx.send(Bytes::from_static(b"reply")).unwrap();
+        assert_eq!(rx.recv_frame(Some(0)).unwrap(), Some(&b"reply"[..]));
+    }
+}
@@ -1,15 +1,145 @@
 //! Common types and utilities for VMA socket implementations.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::fd::{OwnedFd, RawFd};
 use std::os::raw::c_int;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde::de::{self, Visitor};
 
+pub mod clock;
+mod libvma;
+
+pub use libvma::{find_libvma, LibvmaReport, VmaVersion, LIBVMA_PATH_ENV, MIN_VMA_VERSION};
+
 /// Maximum number of CPU cores that can be specified
 const MAX_CPU_CORES: usize = 128;
 
+/// Largest `ring_count` accepted by [`VmaOptions::validate`]
+pub const MAX_RING_COUNT: c_int = 64;
+
+/// Value of the `rx_poll`, `select_poll` and `rx_poll_os_ratio` options that leaves them unset
+/// (derived from `use_polling`, or VMA's default)
+pub const POLL_UNSET: c_int = c_int::MIN;
+
+/// How receive and accept calls wait for a socket to become ready.
+///
+/// Timeouts are honoured by every strategy; they differ in how the CPU is spent meanwhile.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitStrategy {
+    /// Busy-poll until ready or the timeout expires (lowest latency, one core fully used)
+    Spin = 0,
+    /// Busy-poll for `spin_budget_us`, then yield the CPU between polls
+    SpinYield = 1,
+    /// Busy-poll for `spin_budget_us`, then block in `epoll_wait` (no CPU used while idle)
+    SpinPark = 2,
+    /// Busy-poll while traffic is flowing, block in `epoll_wait` once the socket received
+    /// nothing for `spin_budget_us`; the first packet after parking resumes polling
+    Adaptive = 3,
+}
+
+/// How VMA assigns rings (hardware queues) to a socket (`ring_logic_t`).
+///
+/// Latency-critical sockets can get a dedicated ring with `PerSocket`, while bulk sockets
+/// share one through `PerUserId` or the global setting.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingAllocLogic {
+    /// Keep VMA's global configuration (`VMA_RING_ALLOCATION_LOGIC_RX/TX`)
+    Global = -1,
+    /// One ring per network interface
+    PerInterface = 0,
+    /// One ring per local IP address
+    PerIp = 1,
+    /// A dedicated ring for this socket
+    PerSocket = 10,
+    /// One ring shared by all sockets with the same `ring_user_id`
+    PerUserId = 11,
+    /// One ring per thread
+    PerThread = 20,
+    /// One ring per CPU core
+    PerCore = 30,
+    /// One ring per CPU core, with threads attached to their core
+    PerCoreAttachThreads = 31,
+}
+
+/// Memory VMA allocates its buffer pools from (`VMA_MEM_ALLOC_TYPE`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemAllocType {
+    /// `Huge` when `use_hugepages` is set, VMA's default otherwise
+    Auto = -1,
+    /// Anonymous memory from the heap
+    Anon = 0,
+    /// Physically contiguous pages
+    Contig = 1,
+    /// Hugepages, falling back to the heap when none are free
+    Huge = 2,
+}
+
+impl MemAllocType {
+    /// Convert a `VMA_MEM_ALLOC_TYPE` value.
+    fn from_raw(value: c_int) -> Option<Self> {
+        match value {
+            0 => Some(MemAllocType::Anon),
+            1 => Some(MemAllocType::Contig),
+            2 => Some(MemAllocType::Huge),
+            _ => None,
+        }
+    }
+}
+
+/// How VMA protects its internal state against concurrent threads (`VMA_THREAD_MODE`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadMode {
+    /// `MultiSpinLock` with SocketXtreme, `MultiOversubscribed` otherwise
+    Auto = -1,
+    /// No locking; every socket is used by a single thread
+    Single = 0,
+    /// Spin locks, for at most one thread per core
+    MultiSpinLock = 1,
+    /// Mutexes, for threads sharing cores
+    MultiMutex = 2,
+    /// More threads than cores
+    MultiOversubscribed = 3,
+}
+
+impl ThreadMode {
+    /// Convert a `VMA_THREAD_MODE` value.
+    fn from_raw(value: c_int) -> Option<Self> {
+        match value {
+            0 => Some(ThreadMode::Single),
+            1 => Some(ThreadMode::MultiSpinLock),
+            2 => Some(ThreadMode::MultiMutex),
+            3 => Some(ThreadMode::MultiOversubscribed),
+            _ => None,
+        }
+    }
+}
+
 /// C-compatible VMA options structure that directly matches the C definition.
 /// This version is thread-safe by using a fixed-size array instead of raw pointers.
+///
+/// # Memory footprint
+///
+/// VMA sizes its memory when the process creates its first socket, from these fields
+/// (0 or `Auto` keeps VMA's own default):
+///
+/// | Field | VMA variable | Sizes |
+/// |-------|--------------|-------|
+/// | `mem_alloc_type` (`use_hugepages` when `Auto`) | `VMA_MEM_ALLOC_TYPE` | Where the pools live |
+/// | `rx_bufs` | `VMA_RX_BUFS` | Receive buffer pool shared by all rings |
+/// | `tx_bufs` | `VMA_TX_BUFS` | Send buffer pool shared by all rings |
+/// | `rx_wre` | `VMA_RX_WRE` | Receive buffers posted to each ring |
+/// | `tx_wre` | `VMA_TX_WRE` | Send work requests of each ring |
+///
+/// Every posted receive work request holds a pool buffer, so `rx_wre` times the ring
+/// count should stay below `rx_bufs`; shrinking both lets many VMA processes share a host.
 #[repr(C)] 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VmaOptions {
@@ -41,6 +171,39 @@ pub struct VmaOptions {
     pub cpu_cores: [c_int; MAX_CPU_CORES],
     /// Number of CPU cores in the array
     pub cpu_cores_count: c_int,
+    /// Disable Nagle's algorithm (TCP_NODELAY) on TCP sockets
+    pub tcp_nodelay: bool,
+    /// How receive and accept calls wait for readiness
+    pub wait_strategy: WaitStrategy,
+    /// Busy-poll time in microseconds before yielding or parking (`SpinYield` and `SpinPark`),
+    /// or the idle threshold after which `Adaptive` parks
+    pub spin_budget_us: u32,
+    /// How VMA assigns rings to each socket created with these options
+    pub ring_alloc_logic: RingAllocLogic,
+    /// Ring shared by sockets with the same id (`RingAllocLogic::PerUserId` only)
+    pub ring_user_id: u32,
+    /// Locking of VMA's internal state (`VMA_THREAD_MODE`)
+    pub thread_mode: ThreadMode,
+    /// Receive polls before blocking, -1 to poll forever (`VMA_RX_POLL`; [`POLL_UNSET`]
+    /// derives it from `use_polling`)
+    pub rx_poll: c_int,
+    /// Busy-poll time of select/poll/epoll in microseconds, -1 forever (`VMA_SELECT_POLL`;
+    /// [`POLL_UNSET`] derives it from `use_polling`)
+    pub select_poll: c_int,
+    /// Offloaded receive polls per poll of the OS stack, 0 to never poll the OS
+    /// (`VMA_RX_POLL_OS_RATIO`; [`POLL_UNSET`] keeps VMA's default)
+    pub rx_poll_os_ratio: c_int,
+    /// Memory the buffer pools are allocated from (`VMA_MEM_ALLOC_TYPE`)
+    pub mem_alloc_type: MemAllocType,
+    /// Receive work requests posted to each ring, 0 for VMA's default (`VMA_RX_WRE`)
+    pub rx_wre: u32,
+    /// Send work requests of each ring, 0 for VMA's default (`VMA_TX_WRE`)
+    pub tx_wre: u32,
+}
+
+/// Serialize a poll option as `null` when it is [`POLL_UNSET`].
+fn poll_option(value: c_int) -> Option<c_int> {
+    (value != POLL_UNSET).then_some(value)
 }
 
 impl Serialize for VmaOptions {
@@ -50,7 +213,7 @@ impl Serialize for VmaOptions {
     {
         use serde::ser::SerializeStruct;
         
-        let mut state = serializer.serialize_struct("VmaOptions", 14)?;
+        let mut state = serializer.serialize_struct("VmaOptions", 26)?;
         state.serialize_field("use_socketxtreme", &self.use_socketxtreme)?;
         state.serialize_field("optimize_for_latency", &self.optimize_for_latency)?;
         state.serialize_field("use_polling", &self.use_polling)?;
@@ -68,6 +231,18 @@ impl Serialize for VmaOptions {
         let active_cores = &self.cpu_cores[0..self.cpu_cores_count as usize];
         state.serialize_field("cpu_cores", active_cores)?;
         state.serialize_field("cpu_cores_count", &self.cpu_cores_count)?;
+        state.serialize_field("tcp_nodelay", &self.tcp_nodelay)?;
+        state.serialize_field("wait_strategy", &self.wait_strategy)?;
+        state.serialize_field("spin_budget_us", &self.spin_budget_us)?;
+        state.serialize_field("ring_alloc_logic", &self.ring_alloc_logic)?;
+        state.serialize_field("ring_user_id", &self.ring_user_id)?;
+        state.serialize_field("thread_mode", &self.thread_mode)?;
+        state.serialize_field("rx_poll", &poll_option(self.rx_poll))?;
+        state.serialize_field("select_poll", &poll_option(self.select_poll))?;
+        state.serialize_field("rx_poll_os_ratio", &poll_option(self.rx_poll_os_ratio))?;
+        state.serialize_field("mem_alloc_type", &self.mem_alloc_type)?;
+        state.serialize_field("rx_wre", &self.rx_wre)?;
+        state.serialize_field("tx_wre", &self.tx_wre)?;
         
         state.end()
     }
@@ -95,6 +270,18 @@ impl<'de> Deserialize<'de> for VmaOptions {
             KeepQpFull,
             CpuCores,
             CpuCoresCount,
+            TcpNodelay,
+            WaitStrategy,
+            SpinBudgetUs,
+            RingAllocLogic,
+            RingUserId,
+            ThreadMode,
+            RxPoll,
+            SelectPoll,
+            RxPollOsRatio,
+            MemAllocType,
+            RxWre,
+            TxWre,
         }
 
         struct VmaOptionsVisitor;
@@ -157,6 +344,42 @@ impl<'de> Deserialize<'de> for VmaOptions {
                         Field::CpuCoresCount => {
                             options.cpu_cores_count = map.next_value()?;
                         }
+                        Field::TcpNodelay => {
+                            options.tcp_nodelay = map.next_value()?;
+                        }
+                        Field::WaitStrategy => {
+                            options.wait_strategy = map.next_value()?;
+                        }
+                        Field::SpinBudgetUs => {
+                            options.spin_budget_us = map.next_value()?;
+                        }
+                        Field::RingAllocLogic => {
+                            options.ring_alloc_logic = map.next_value()?;
+                        }
+                        Field::RingUserId => {
+                            options.ring_user_id = map.next_value()?;
+                        }
+                        Field::ThreadMode => {
+                            options.thread_mode = map.next_value()?;
+                        }
+                        Field::RxPoll => {
+                            options.rx_poll = map.next_value::<Option<c_int>>()?.unwrap_or(POLL_UNSET);
+                        }
+                        Field::SelectPoll => {
+                            options.select_poll = map.next_value::<Option<c_int>>()?.unwrap_or(POLL_UNSET);
+                        }
+                        Field::RxPollOsRatio => {
+                            options.rx_poll_os_ratio = map.next_value::<Option<c_int>>()?.unwrap_or(POLL_UNSET);
+                        }
+                        Field::MemAllocType => {
+                            options.mem_alloc_type = map.next_value()?;
+                        }
+                        Field::RxWre => {
+                            options.rx_wre = map.next_value()?;
+                        }
+                        Field::TxWre => {
+                            options.tx_wre = map.next_value()?;
+                        }
                     }
                 }
 
@@ -183,7 +406,10 @@ impl<'de> Deserialize<'de> for VmaOptions {
         const FIELDS: &[&str] = &[
             "use_socketxtreme", "optimize_for_latency", "use_polling", "ring_count",
             "buffer_size", "enable_timestamps", "use_hugepages", "tx_bufs", "rx_bufs",
-            "disable_poll_yield", "skip_os_select", "keep_qp_full", "cpu_cores", "cpu_cores_count"
+            "disable_poll_yield", "skip_os_select", "keep_qp_full", "cpu_cores", "cpu_cores_count",
+            "tcp_nodelay", "wait_strategy", "spin_budget_us",
+            "ring_alloc_logic", "ring_user_id", "thread_mode", "rx_poll", "select_poll",
+            "rx_poll_os_ratio", "mem_alloc_type", "rx_wre", "tx_wre"
         ];
 
         deserializer.deserialize_struct("VmaOptions", FIELDS, VmaOptionsVisitor)
@@ -207,6 +433,18 @@ impl Default for VmaOptions {
             keep_qp_full: true,
             cpu_cores: [0; MAX_CPU_CORES],
             cpu_cores_count: 0,
+            tcp_nodelay: true,
+            wait_strategy: WaitStrategy::Spin,
+            spin_budget_us: 0,
+            ring_alloc_logic: RingAllocLogic::Global,
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
         }
     }
 }
@@ -245,7 +483,132 @@ impl VmaOptions {
 
     /// Get the currently configured CPU cores as a slice
     pub fn get_cores(&self) -> &[c_int] {
-        &self.cpu_cores[0..self.cpu_cores_count as usize]
+        &self.cpu_cores[0..self.cpu_cores_count.clamp(0, MAX_CPU_CORES as c_int) as usize]
+    }
+
+    /// Check the options against the running system, listing every problem found.
+    ///
+    /// Checks that `ring_count`, `buffer_size`, the poll tunables and the core list are in range, that every
+    /// core exists on this machine, that the receive pool holds the work requests of every
+    /// ring and, when VMA is loaded, that hugepages are reserved if the options ask for them. Without VMA the offload settings have no effect,
+    /// so they are not held against the system; see
+    /// [`validate_offloaded`](Self::validate_offloaded). Sockets created with explicit
+    /// options run this check and fail with [`VmaError::Config`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if !(0..=MAX_RING_COUNT).contains(&self.ring_count) {
+            problems.push(ConfigProblem::RingCount { ring_count: self.ring_count });
+        }
+        if self.buffer_size < 0 {
+            problems.push(ConfigProblem::BufferSize { buffer_size: self.buffer_size });
+        }
+        if !(0..=MAX_CPU_CORES as c_int).contains(&self.cpu_cores_count) {
+            problems.push(ConfigProblem::CoreCount { count: self.cpu_cores_count });
+        }
+        let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) }.max(1) as c_int;
+        for &core in self.get_cores() {
+            if !(0..cpus).contains(&core) {
+                problems.push(ConfigProblem::MissingCore { core, cpus });
+            }
+        }
+        let polls = [
+            ("rx_poll", self.rx_poll, -1),
+            ("select_poll", self.select_poll, -1),
+            ("rx_poll_os_ratio", self.rx_poll_os_ratio, 0),
+        ];
+        for (field, value, min) in polls {
+            if value != POLL_UNSET && value < min {
+                problems.push(ConfigProblem::PollValue { field, value });
+            }
+        }
+        let rings = self.ring_count.max(1);
+        if self.rx_wre > 0 && self.rx_bufs > 0 && self.rx_wre as u64 * rings as u64 > self.rx_bufs as u64 {
+            problems.push(ConfigProblem::WorkRequests { rx_wre: self.rx_wre, rings, rx_bufs: self.rx_bufs });
+        }
+        if self.uses_hugepages() && vma_loaded() && reserved_hugepages() == Some(0) {
+            problems.push(ConfigProblem::NoHugepages);
+        }
+        ConfigError::check(problems)
+    }
+
+    /// Check whether VMA is asked to allocate its pools from hugepages.
+    pub fn uses_hugepages(&self) -> bool {
+        match self.mem_alloc_type {
+            MemAllocType::Auto => self.use_hugepages,
+            mem_alloc_type => mem_alloc_type == MemAllocType::Huge,
+        }
+    }
+
+    /// Like [`validate`](Self::validate), additionally requiring VMA to be loaded.
+    ///
+    /// For services that must not silently fall back to the kernel network stack.
+    pub fn validate_offloaded(&self) -> Result<(), ConfigError> {
+        let mut problems = self.validate().err().map_or_else(Vec::new, |e| e.problems);
+        if !vma_loaded() {
+            problems.push(ConfigProblem::VmaNotLoaded);
+        }
+        ConfigError::check(problems)
+    }
+
+    /// List the fields that differ from `other`, in declaration order.
+    ///
+    /// `cpu_cores` and `cpu_cores_count` compare as one `cpu_cores` list. Each change tells
+    /// whether a live socket can take it through `apply_options()`.
+    pub fn diff(&self, other: &VmaOptions) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        let mut field = |field: &'static str, from: &dyn std::fmt::Debug, to: &dyn std::fmt::Debug, runtime: bool| {
+            let (from, to) = (format!("{:?}", from), format!("{:?}", to));
+            if from != to {
+                changes.push(FieldChange { field, from, to, runtime });
+            }
+        };
+        field("use_socketxtreme", &self.use_socketxtreme, &other.use_socketxtreme, false);
+        field("optimize_for_latency", &self.optimize_for_latency, &other.optimize_for_latency, false);
+        field("use_polling", &self.use_polling, &other.use_polling, false);
+        field("ring_count", &self.ring_count, &other.ring_count, false);
+        field("buffer_size", &self.buffer_size, &other.buffer_size, true);
+        field("enable_timestamps", &self.enable_timestamps, &other.enable_timestamps, true);
+        field("use_hugepages", &self.use_hugepages, &other.use_hugepages, false);
+        field("tx_bufs", &self.tx_bufs, &other.tx_bufs, false);
+        field("rx_bufs", &self.rx_bufs, &other.rx_bufs, false);
+        field("disable_poll_yield", &self.disable_poll_yield, &other.disable_poll_yield, false);
+        field("skip_os_select", &self.skip_os_select, &other.skip_os_select, false);
+        field("keep_qp_full", &self.keep_qp_full, &other.keep_qp_full, false);
+        field("cpu_cores", &self.get_cores(), &other.get_cores(), false);
+        field("tcp_nodelay", &self.tcp_nodelay, &other.tcp_nodelay, true);
+        field("wait_strategy", &self.wait_strategy, &other.wait_strategy, true);
+        field("spin_budget_us", &self.spin_budget_us, &other.spin_budget_us, true);
+        field("ring_alloc_logic", &self.ring_alloc_logic, &other.ring_alloc_logic, false);
+        field("ring_user_id", &self.ring_user_id, &other.ring_user_id, false);
+        field("thread_mode", &self.thread_mode, &other.thread_mode, false);
+        field("rx_poll", &poll_option(self.rx_poll), &poll_option(other.rx_poll), false);
+        field("select_poll", &poll_option(self.select_poll), &poll_option(other.select_poll), false);
+        field("rx_poll_os_ratio", &poll_option(self.rx_poll_os_ratio), &poll_option(other.rx_poll_os_ratio), false);
+        field("mem_alloc_type", &self.mem_alloc_type, &other.mem_alloc_type, false);
+        field("rx_wre", &self.rx_wre, &other.rx_wre, false);
+        field("tx_wre", &self.tx_wre, &other.tx_wre, false);
+        changes
+    }
+
+    /// Get the delta of the runtime-tunable fields that differ in `other`.
+    ///
+    /// Applied to a socket created with `self`, it moves the socket as close to `other`
+    /// as possible without recreating it.
+    pub fn delta_to(&self, other: &VmaOptions) -> OptionsDelta {
+        OptionsDelta {
+            wait_strategy: (self.wait_strategy != other.wait_strategy).then_some(other.wait_strategy),
+            spin_budget_us: (self.spin_budget_us != other.spin_budget_us).then_some(other.spin_budget_us),
+            buffer_size: (self.buffer_size != other.buffer_size).then_some(other.buffer_size),
+            enable_timestamps: (self.enable_timestamps != other.enable_timestamps).then_some(other.enable_timestamps),
+            tcp_nodelay: (self.tcp_nodelay != other.tcp_nodelay).then_some(other.tcp_nodelay),
+        }
+    }
+
+    /// Get a copy of the options with `delta` applied.
+    pub fn merge(&self, delta: &OptionsDelta) -> Result<VmaOptions, ConfigError> {
+        let mut options = *self;
+        delta.merge_into(&mut options)?;
+        Ok(options)
     }
 
     /// Create options optimized for ultra-low latency
@@ -265,6 +628,18 @@ impl VmaOptions {
             keep_qp_full: true,
             cpu_cores: [0; MAX_CPU_CORES],
             cpu_cores_count: 0,
+            tcp_nodelay: true,
+            wait_strategy: WaitStrategy::Spin,
+            spin_budget_us: 0,
+            ring_alloc_logic: RingAllocLogic::Global,
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
         }
     }
     
@@ -285,8 +660,216 @@ impl VmaOptions {
             keep_qp_full: true,
             cpu_cores: [0; MAX_CPU_CORES],
             cpu_cores_count: 0,
+            tcp_nodelay: false, // Let Nagle coalesce small writes
+            wait_strategy: WaitStrategy::SpinPark, // Give the core back once traffic pauses
+            spin_budget_us: 50,
+            ring_alloc_logic: RingAllocLogic::Global,
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
+        }
+    }
+
+    /// Create options for receiving multicast market data feeds.
+    ///
+    /// Compared to the defaults: a deep receive pool (32768 buffers) absorbs microbursts
+    /// while the send pool shrinks to 256 buffers, since feed handlers rarely send. Rings
+    /// are allocated per interface, so the A and B sides of a feed on two NICs poll
+    /// separate rings, and hardware timestamps stay on for gap and latency analysis.
+    pub fn multicast_feed() -> Self {
+        VmaOptions {
+            use_socketxtreme: true,
+            optimize_for_latency: true,
+            use_polling: true,
+            ring_count: 2, // One per feed side
+            buffer_size: 65536,
+            enable_timestamps: true, // Exchange-to-host latency and gap analysis
+            use_hugepages: true,
+            tx_bufs: 256, // Feeds are receive-only apart from the odd IGMP join
+            rx_bufs: 32768, // Absorb bursts at the open
+            disable_poll_yield: true,
+            skip_os_select: true,
+            keep_qp_full: true,
+            cpu_cores: [0; MAX_CPU_CORES],
+            cpu_cores_count: 0,
+            tcp_nodelay: true,
+            wait_strategy: WaitStrategy::Spin,
+            spin_budget_us: 0,
+            ring_alloc_logic: RingAllocLogic::PerInterface,
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
+        }
+    }
+
+    /// Create options for order entry sessions (small TCP messages, latency first).
+    ///
+    /// Compared to the defaults: each socket gets a dedicated ring, so order traffic never
+    /// queues behind market data sharing the NIC, and a single small ring with modest
+    /// buffer pools (16KB buffers, 4096 TX / 1024 RX) keeps the working set in cache.
+    /// Nagle stays off and timestamps stay on to measure order-to-ack times.
+    pub fn order_entry() -> Self {
+        VmaOptions {
+            use_socketxtreme: true,
+            optimize_for_latency: true,
+            use_polling: true,
+            ring_count: 1,
+            buffer_size: 16384, // Orders and acks are a few hundred bytes
+            enable_timestamps: true, // Order-to-ack latency
+            use_hugepages: true,
+            tx_bufs: 4096,
+            rx_bufs: 1024,
+            disable_poll_yield: true,
+            skip_os_select: true,
+            keep_qp_full: true,
+            cpu_cores: [0; MAX_CPU_CORES],
+            cpu_cores_count: 0,
+            tcp_nodelay: true,
+            wait_strategy: WaitStrategy::Spin,
+            spin_budget_us: 0,
+            ring_alloc_logic: RingAllocLogic::PerSocket, // Isolated from feed traffic
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
         }
     }
+
+    /// Create options for bulk transfers such as end-of-day files and reference data loads.
+    ///
+    /// Compared to the defaults: polling is off and calls block in `epoll_wait`, so a
+    /// transfer does not hold a core at 100%; buffers grow to 256KB with 40000 per pool,
+    /// Nagle coalesces small writes and timestamps are off. Not for latency-sensitive paths.
+    pub fn bulk_transfer() -> Self {
+        VmaOptions {
+            use_socketxtreme: true,
+            optimize_for_latency: false,
+            use_polling: false, // Leave the cores to the trading threads
+            ring_count: 4,
+            buffer_size: 262144, // 256KB
+            enable_timestamps: false,
+            use_hugepages: true,
+            tx_bufs: 40000,
+            rx_bufs: 40000,
+            disable_poll_yield: false,
+            skip_os_select: true,
+            keep_qp_full: false,
+            cpu_cores: [0; MAX_CPU_CORES],
+            cpu_cores_count: 0,
+            tcp_nodelay: false, // Let Nagle coalesce small writes
+            wait_strategy: WaitStrategy::SpinPark,
+            spin_budget_us: 0, // Park as soon as nothing is ready
+            ring_alloc_logic: RingAllocLogic::Global,
+            ring_user_id: 0,
+            thread_mode: ThreadMode::Auto,
+            rx_poll: POLL_UNSET,
+            select_poll: POLL_UNSET,
+            rx_poll_os_ratio: POLL_UNSET,
+            mem_alloc_type: MemAllocType::Auto,
+            rx_wre: 0,
+            tx_wre: 0,
+        }
+    }
+}
+
+/// Changes to the subset of [`VmaOptions`] a live socket can take, for `apply_options()`.
+///
+/// Fields left `None` are unchanged. The rest of the options (rings, buffer pools,
+/// hugepages, SocketXtreme) configure VMA when the process creates its first socket and
+/// cannot change afterwards. `OptionsDelta::from(&options)` takes the runtime subset of
+/// a preset, e.g. to switch a socket to [`VmaOptions::bulk_transfer`] for a night's load.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptionsDelta {
+    /// How receives (and accepts) wait for readiness
+    pub wait_strategy: Option<WaitStrategy>,
+    /// Busy-poll budget of `SpinYield`/`SpinPark`, or idle threshold of `Adaptive`
+    pub spin_budget_us: Option<u32>,
+    /// Socket send and receive buffer size in bytes (SO_SNDBUF/SO_RCVBUF, 0 leaves them)
+    pub buffer_size: Option<c_int>,
+    /// Receive timestamps (SO_TIMESTAMPNS, UDP only)
+    pub enable_timestamps: Option<bool>,
+    /// Disable Nagle's algorithm (TCP only)
+    pub tcp_nodelay: Option<bool>,
+}
+
+impl OptionsDelta {
+    /// Check whether the delta changes nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == OptionsDelta::default()
+    }
+
+    /// Check the delta and merge it into `options`.
+    pub(crate) fn merge_into(&self, options: &mut VmaOptions) -> Result<(), ConfigError> {
+        if let Some(buffer_size) = self.buffer_size {
+            if buffer_size < 0 {
+                return ConfigError::check(vec![ConfigProblem::BufferSize { buffer_size }]);
+            }
+            options.buffer_size = buffer_size;
+        }
+        if let Some(wait_strategy) = self.wait_strategy {
+            options.wait_strategy = wait_strategy;
+        }
+        if let Some(spin_budget_us) = self.spin_budget_us {
+            options.spin_budget_us = spin_budget_us;
+        }
+        if let Some(enable_timestamps) = self.enable_timestamps {
+            options.enable_timestamps = enable_timestamps;
+        }
+        if let Some(tcp_nodelay) = self.tcp_nodelay {
+            options.tcp_nodelay = tcp_nodelay;
+        }
+        Ok(())
+    }
+}
+
+impl From<&VmaOptions> for OptionsDelta {
+    /// Take every runtime-tunable setting of `options`.
+    fn from(options: &VmaOptions) -> Self {
+        OptionsDelta {
+            wait_strategy: Some(options.wait_strategy),
+            spin_budget_us: Some(options.spin_budget_us),
+            buffer_size: Some(options.buffer_size),
+            enable_timestamps: Some(options.enable_timestamps),
+            tcp_nodelay: Some(options.tcp_nodelay),
+        }
+    }
+}
+
+/// A field that differs between two [`VmaOptions`], from [`VmaOptions::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Name of the field, as in the serialized options
+    pub field: &'static str,
+    /// Previous value
+    pub from: String,
+    /// New value
+    pub to: String,
+    /// Whether `apply_options()` can change it on a live socket; other fields need a new socket
+    pub runtime: bool,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.from, self.to)?;
+        if !self.runtime {
+            write!(f, " (new sockets only)")?;
+        }
+        Ok(())
+    }
 }
 
 /// Internal representation of socket address in C format.
@@ -299,6 +882,765 @@ pub struct SockAddrIn {
     pub sin_zero: [u8; 8],
 }
 
+/// Per-socket traffic counters maintained by the C layer.
+///
+/// The layout matches `socket_stats_t` in `vma_common.h`, so the structure is
+/// filled in directly by the C `*_get_extended_stats` functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketStats {
+    /// Number of received packets
+    pub rx_packets: u64,
+    /// Number of transmitted packets
+    pub tx_packets: u64,
+    /// Number of received bytes
+    pub rx_bytes: u64,
+    /// Number of transmitted bytes
+    pub tx_bytes: u64,
+    /// Receive calls that failed with an error
+    pub rx_errors: u64,
+    /// Send calls that failed with an error
+    pub tx_errors: u64,
+    /// Datagrams that did not fit in the receive buffer (UDP only)
+    pub rx_truncated: u64,
+    /// Receive calls that returned `EAGAIN`/`EWOULDBLOCK`
+    pub rx_eagain: u64,
+    /// Send calls that returned `EAGAIN`/`EWOULDBLOCK`
+    pub tx_eagain: u64,
+    /// Successful reconnects (TCP only)
+    pub reconnects: u64,
+    /// Time of the last successful receive in nanoseconds since the epoch (0 if none)
+    pub last_rx_ns: u64,
+    /// Time of the last successful send in nanoseconds since the epoch (0 if none)
+    pub last_tx_ns: u64,
+}
+
+impl SocketStats {
+    /// Get the counters accumulated since `earlier`, keeping the activity times of `self`.
+    ///
+    /// Subtraction wraps, so a counter that overflowed in between still yields its increase.
+    pub fn delta_since(&self, earlier: &SocketStats) -> SocketStats {
+        SocketStats {
+            rx_packets: self.rx_packets.wrapping_sub(earlier.rx_packets),
+            tx_packets: self.tx_packets.wrapping_sub(earlier.tx_packets),
+            rx_bytes: self.rx_bytes.wrapping_sub(earlier.rx_bytes),
+            tx_bytes: self.tx_bytes.wrapping_sub(earlier.tx_bytes),
+            rx_errors: self.rx_errors.wrapping_sub(earlier.rx_errors),
+            tx_errors: self.tx_errors.wrapping_sub(earlier.tx_errors),
+            rx_truncated: self.rx_truncated.wrapping_sub(earlier.rx_truncated),
+            rx_eagain: self.rx_eagain.wrapping_sub(earlier.rx_eagain),
+            tx_eagain: self.tx_eagain.wrapping_sub(earlier.tx_eagain),
+            reconnects: self.reconnects.wrapping_sub(earlier.reconnects),
+            last_rx_ns: self.last_rx_ns,
+            last_tx_ns: self.last_tx_ns,
+        }
+    }
+}
+
+/// VMA offload status of a single socket, obtained through the VMA Extra API.
+///
+/// This is only whether VMA handles the socket and on how many rings: the Extra API has
+/// no call that returns the per-socket ring, CQ or packet counters `vma_stats` shows.
+/// Those can be written to the VMA log with `dump_vma_stats()` or read with the
+/// `vma_stats` tool; the library's own traffic counters are in [`SocketStats`].
+///
+/// The layout matches `vma_fd_stats_t` in `vma_common.h`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmaFdStats {
+    /// Whether the VMA Extra API is available (libvma preloaded)
+    pub vma_loaded: bool,
+    /// Whether VMA offloads this socket; `false` means traffic takes the OS path
+    pub offloaded: bool,
+    /// Number of VMA rings backing the socket
+    pub ring_count: c_int,
+}
+
+/// VMA log level used when dumping per-socket statistics (INFO, visible with the default VMA_TRACELEVEL).
+const VMA_LOG_LEVEL_INFO: c_int = 3;
+
+// External declarations for the shared C helpers
+extern "C" {
+    fn vma_common_get_fd_stats(fd: c_int, stats: *mut VmaFdStats) -> c_int;
+    fn vma_common_dump_fd_stats(fd: c_int, log_level: c_int) -> c_int;
+    fn vma_common_get_ring_fds(fd: c_int, ring_fds: *mut c_int, max_fds: c_int) -> c_int;
+    fn vma_common_get_local_addr(fd: c_int, addr: *mut SockAddrIn) -> c_int;
+    fn vma_common_wait_writable(fd: c_int, timeout_ms: c_int) -> c_int;
+    fn vma_common_pending_tx_bytes(fd: c_int, pending: *mut u32) -> c_int;
+    fn vma_common_get_api() -> *mut libc::c_void;
+}
+
+/// Check whether libvma is loaded (its Extra API is available).
+pub(crate) fn vma_loaded() -> bool {
+    !unsafe { vma_common_get_api() }.is_null()
+}
+
+/// Get the number of hugepages reserved on the system, if it can be read.
+fn reserved_hugepages() -> Option<u64> {
+    std::fs::read_to_string("/proc/sys/vm/nr_hugepages").ok()?.trim().parse().ok()
+}
+
+/// Query VMA offload information for a socket file descriptor.
+pub(crate) fn fd_stats(fd: c_int) -> Result<VmaFdStats, std::io::Error> {
+    let mut stats = VmaFdStats::default();
+    if unsafe { vma_common_get_fd_stats(fd, &mut stats) } != 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid socket"));
+    }
+    Ok(stats)
+}
+
+/// Get the descriptors of the VMA rings backing a socket file descriptor (empty if not offloaded).
+pub(crate) fn ring_fds(fd: c_int) -> Result<Vec<RawFd>, std::io::Error> {
+    let stats = fd_stats(fd)?;
+    if !stats.vma_loaded {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "VMA Extra API not available"));
+    }
+    if stats.ring_count <= 0 {
+        return Ok(Vec::new());
+    }
+    let mut fds = vec![-1; stats.ring_count as usize];
+    let count = unsafe { vma_common_get_ring_fds(fd, fds.as_mut_ptr(), stats.ring_count) };
+    if count < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    fds.truncate(count as usize);
+    Ok(fds)
+}
+
+/// Ask VMA to write its internal statistics (ring, CQ and offload counters) for a socket to the VMA log.
+pub(crate) fn dump_fd_stats(fd: c_int) -> Result<(), std::io::Error> {
+    if unsafe { vma_common_dump_fd_stats(fd, VMA_LOG_LEVEL_INFO) } != 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "VMA Extra API not available"));
+    }
+    Ok(())
+}
+
+/// Get the local address a socket file descriptor is bound to.
+pub(crate) fn local_addr(fd: c_int) -> Result<SocketAddr, std::io::Error> {
+    let mut addr = SockAddrIn { sin_family: 0, sin_port: 0, sin_addr: 0, sin_zero: [0; 8] };
+    if unsafe { vma_common_get_local_addr(fd, &mut addr) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(sockaddr_to_rust(&addr))
+}
+
+/// Wait up to `timeout_nano` for a socket file descriptor to accept more data.
+pub(crate) fn wait_writable(fd: c_int, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+    match unsafe { vma_common_wait_writable(fd, unixnano_to_ms(timeout_nano)) } {
+        result if result < 0 => Err(std::io::Error::last_os_error()),
+        result => Ok(result > 0),
+    }
+}
+
+/// Get the number of bytes queued in a socket's send buffer.
+pub(crate) fn pending_tx_bytes(fd: c_int) -> Result<usize, std::io::Error> {
+    let mut pending: u32 = 0;
+    if unsafe { vma_common_pending_tx_bytes(fd, &mut pending) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(pending as usize)
+}
+
+/// Read an integer socket option of a socket file descriptor.
+fn getsockopt_int(fd: c_int, level: c_int, optname: c_int) -> Option<c_int> {
+    let mut value: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>() as libc::socklen_t;
+    let result = unsafe { libc::getsockopt(fd, level, optname, &mut value as *mut c_int as *mut libc::c_void, &mut len) };
+    (result == 0).then_some(value)
+}
+
+/// Fill in the options recorded for a socket with what is in effect.
+///
+/// Settings left to VMA (`Auto`, [`POLL_UNSET`], 0 pools) take the value of their VMA
+/// variable in the environment, if set. For an open socket, `buffer_size` becomes the
+/// receive buffer the kernel granted (half the SO_RCVBUF it reports, after clamping to
+/// `net.core.rmem_max`), and `enable_timestamps` (UDP) or `tcp_nodelay` (TCP) the state of
+/// the socket option.
+pub(crate) fn effective_options(fd: c_int, protocol: Protocol, mut options: VmaOptions) -> VmaOptions {
+    let env = |name: &str| std::env::var(name).ok()?.trim().parse::<c_int>().ok();
+    if options.thread_mode == ThreadMode::Auto {
+        options.thread_mode = env("VMA_THREAD_MODE").and_then(ThreadMode::from_raw).unwrap_or(ThreadMode::Auto);
+    }
+    if options.mem_alloc_type == MemAllocType::Auto {
+        options.mem_alloc_type = env("VMA_MEM_ALLOC_TYPE").and_then(MemAllocType::from_raw).unwrap_or(MemAllocType::Auto);
+    }
+    for (value, name) in [
+        (&mut options.rx_poll, "VMA_RX_POLL"),
+        (&mut options.select_poll, "VMA_SELECT_POLL"),
+        (&mut options.rx_poll_os_ratio, "VMA_RX_POLL_OS_RATIO"),
+    ] {
+        if *value == POLL_UNSET {
+            *value = env(name).unwrap_or(POLL_UNSET);
+        }
+    }
+    for (value, name) in [
+        (&mut options.rx_bufs, "VMA_RX_BUFS"),
+        (&mut options.tx_bufs, "VMA_TX_BUFS"),
+        (&mut options.rx_wre, "VMA_RX_WRE"),
+        (&mut options.tx_wre, "VMA_TX_WRE"),
+    ] {
+        if *value == 0 {
+            *value = env(name).and_then(|count| u32::try_from(count).ok()).unwrap_or(0);
+        }
+    }
+
+    if fd < 0 {
+        return options;
+    }
+    if let Some(size) = getsockopt_int(fd, libc::SOL_SOCKET, libc::SO_RCVBUF) {
+        options.buffer_size = size / 2;
+    }
+    match protocol {
+        Protocol::Udp => {
+            if let Some(enabled) = getsockopt_int(fd, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS) {
+                options.enable_timestamps = enabled != 0;
+            }
+        }
+        Protocol::Tcp => {
+            if let Some(nodelay) = getsockopt_int(fd, libc::IPPROTO_TCP, libc::TCP_NODELAY) {
+                options.tcp_nodelay = nodelay != 0;
+            }
+        }
+    }
+    options
+}
+
+/// Sockets that expose [`SocketStats`] together with the addresses identifying them.
+///
+/// Implemented by [`VmaUdpSocket`](crate::udp::VmaUdpSocket) and
+/// [`VmaTcpSocket`](crate::tcp::VmaTcpSocket) so reporting code can handle both.
+pub trait StatsSource {
+    /// Get the full set of socket counters.
+    fn stats(&mut self) -> Result<SocketStats, std::io::Error>;
+
+    /// Get the local address the socket is bound to.
+    fn local_addr(&self) -> Result<SocketAddr, std::io::Error>;
+
+    /// Get the remote address, if the socket is connected.
+    fn peer_addr(&self) -> Option<SocketAddr>;
+
+    /// Get the VMA offload information of the socket.
+    fn vma_fd_stats(&self) -> Result<VmaFdStats, std::io::Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "No VMA offload information"))
+    }
+}
+
+/// Snapshot of a socket's identity, state, options and counters, for support tickets.
+///
+/// Returned by `diagnostics()` on the UDP and TCP sockets. The `Display` output is a
+/// multi-line report meant to be pasted as is; parts that could not be read show as `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketDiagnostics {
+    /// Transport protocol
+    pub protocol: Protocol,
+    /// File descriptor
+    pub fd: c_int,
+    /// Local address, if bound
+    pub local_addr: Option<SocketAddr>,
+    /// Remote address, if connected
+    pub peer_addr: Option<SocketAddr>,
+    /// Socket state (`unbound`/`bound`/`connected` for UDP, the connection state for TCP)
+    pub state: String,
+    /// Options in effect as `(name, value)` pairs, unreadable ones omitted
+    pub options: Vec<(&'static str, String)>,
+    /// Traffic counters, if they could be read
+    pub stats: Option<SocketStats>,
+    /// VMA offload information, if the VMA Extra API answered
+    pub vma: Option<VmaFdStats>,
+}
+
+impl std::fmt::Display for SocketDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let addr = |addr: Option<SocketAddr>| addr.map_or_else(|| "-".to_string(), |addr| addr.to_string());
+        let protocol = match self.protocol {
+            Protocol::Udp => "udp",
+            Protocol::Tcp => "tcp",
+        };
+        writeln!(f, "{} socket fd={} state={}", protocol, self.fd, self.state)?;
+        writeln!(f, "  local={} remote={}", addr(self.local_addr), addr(self.peer_addr))?;
+
+        write!(f, "  options:")?;
+        for (name, value) in &self.options {
+            write!(f, " {}={}", name, value)?;
+        }
+        writeln!(f)?;
+
+        match &self.stats {
+            Some(stats) => writeln!(
+                f,
+                "  counters: rx_packets={} tx_packets={} rx_bytes={} tx_bytes={} rx_errors={} tx_errors={} \
+                 rx_truncated={} rx_eagain={} tx_eagain={} reconnects={} last_rx_ns={} last_tx_ns={}",
+                stats.rx_packets,
+                stats.tx_packets,
+                stats.rx_bytes,
+                stats.tx_bytes,
+                stats.rx_errors,
+                stats.tx_errors,
+                stats.rx_truncated,
+                stats.rx_eagain,
+                stats.tx_eagain,
+                stats.reconnects,
+                stats.last_rx_ns,
+                stats.last_tx_ns
+            )?,
+            None => writeln!(f, "  counters: -")?,
+        }
+
+        match &self.vma {
+            Some(vma) if vma.vma_loaded => {
+                write!(f, "  vma: loaded offloaded={} rings={}", vma.offloaded, vma.ring_count)
+            }
+            Some(_) => write!(f, "  vma: not loaded"),
+            None => write!(f, "  vma: -"),
+        }
+    }
+}
+
+/// Format an optional timeout in nanoseconds for [`SocketDiagnostics::options`].
+pub(crate) fn timeout_option(timeout_nano: Option<u64>) -> String {
+    timeout_nano.map_or_else(|| "none".to_string(), |t| format!("{}ns", t))
+}
+
+/// Typed errors carried inside the `std::io::Error`s returned by the sockets.
+///
+/// Recover one with [`VmaError::from_io`] (or `Error::get_ref` and `downcast_ref`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmaError {
+    /// A datagram of `len` bytes exceeds the socket's maximum datagram size `max`
+    DatagramTooLarge { len: usize, max: usize },
+    /// `close(2)` of descriptor `fd` failed with `errno`; the descriptor is released anyway
+    CloseFailed { fd: i32, errno: i32 },
+    /// The socket options were rejected by [`VmaOptions::validate`]
+    Config(ConfigError),
+}
+
+impl VmaError {
+    /// Get the `VmaError` an I/O error was created from, if any.
+    pub fn from_io(error: &std::io::Error) -> Option<&VmaError> {
+        error.get_ref().and_then(|inner| inner.downcast_ref::<VmaError>())
+    }
+}
+
+impl std::fmt::Display for VmaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmaError::DatagramTooLarge { len, max } => {
+                write!(f, "Datagram of {} bytes exceeds the maximum of {} bytes", len, max)
+            }
+            VmaError::CloseFailed { fd, errno } => {
+                write!(f, "Closing fd {} failed: {}", fd, std::io::Error::from_raw_os_error(*errno))
+            }
+            VmaError::Config(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for VmaError {}
+
+impl From<VmaError> for std::io::Error {
+    fn from(error: VmaError) -> Self {
+        let kind = match error {
+            VmaError::DatagramTooLarge { .. } => std::io::ErrorKind::InvalidInput,
+            VmaError::CloseFailed { errno, .. } => std::io::Error::from_raw_os_error(errno).kind(),
+            VmaError::Config(_) => std::io::ErrorKind::InvalidInput,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+
+/// Error of `VmaUdpSocket::from_fd` and `VmaTcpSocket::from_fd`, handing the descriptor back.
+///
+/// The descriptor is left open for the caller; converting the error into an
+/// `std::io::Error` (e.g. with `?`) closes it.
+#[derive(Debug)]
+pub struct FromFdError {
+    fd: OwnedFd,
+    error: std::io::Error,
+}
+
+impl FromFdError {
+    pub(crate) fn new(fd: OwnedFd, error: std::io::Error) -> Self {
+        FromFdError { fd, error }
+    }
+
+    /// Get the reason the descriptor was refused.
+    pub fn error(&self) -> &std::io::Error {
+        &self.error
+    }
+
+    /// Take the descriptor back.
+    pub fn into_fd(self) -> OwnedFd {
+        self.fd
+    }
+
+    /// Take the descriptor back along with the error.
+    pub fn into_parts(self) -> (OwnedFd, std::io::Error) {
+        (self.fd, self.error)
+    }
+}
+
+impl std::fmt::Display for FromFdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for FromFdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<FromFdError> for std::io::Error {
+    fn from(error: FromFdError) -> Self {
+        error.error
+    }
+}
+
+/// One reason [`VmaOptions::validate`] rejected a set of options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigProblem {
+    /// `ring_count` is outside `0..=MAX_RING_COUNT`
+    RingCount { ring_count: c_int },
+    /// `buffer_size` is negative
+    BufferSize { buffer_size: c_int },
+    /// `cpu_cores_count` is negative or larger than the core array
+    CoreCount { count: c_int },
+    /// A core in `cpu_cores` does not exist on this machine, which has `cpus` cores
+    MissingCore { core: c_int, cpus: c_int },
+    /// A poll tunable (`rx_poll`, `select_poll`, `rx_poll_os_ratio`) is below its smallest value
+    PollValue { field: &'static str, value: c_int },
+    /// `rx_wre` work requests on each of `rings` rings need more buffers than the `rx_bufs` pool
+    WorkRequests { rx_wre: u32, rings: c_int, rx_bufs: u32 },
+    /// Hugepages are requested but none are reserved (`/proc/sys/vm/nr_hugepages` is 0)
+    NoHugepages,
+    /// libvma is not preloaded, so sockets use the kernel network stack
+    VmaNotLoaded,
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigProblem::RingCount { ring_count } => {
+                write!(f, "ring_count {} is outside 0..={}", ring_count, MAX_RING_COUNT)
+            }
+            ConfigProblem::BufferSize { buffer_size } => write!(f, "buffer_size {} is negative", buffer_size),
+            ConfigProblem::CoreCount { count } => {
+                write!(f, "cpu_cores_count {} is outside 0..={}", count, MAX_CPU_CORES)
+            }
+            ConfigProblem::MissingCore { core, cpus } => {
+                write!(f, "core {} does not exist (the system has {} cores)", core, cpus)
+            }
+            ConfigProblem::PollValue { field, value } => write!(f, "{} {} is out of range", field, value),
+            ConfigProblem::WorkRequests { rx_wre, rings, rx_bufs } => {
+                write!(f, "rx_wre {} on {} rings exceeds rx_bufs {}", rx_wre, rings, rx_bufs)
+            }
+            ConfigProblem::NoHugepages => {
+                write!(f, "hugepages are requested but none are reserved (vm.nr_hugepages is 0)")
+            }
+            ConfigProblem::VmaNotLoaded => write!(f, "libvma is not loaded (run with LD_PRELOAD=libvma.so)"),
+        }
+    }
+}
+
+/// Every problem found in a set of [`VmaOptions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The problems, in the order they were found (never empty)
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl ConfigError {
+    fn check(problems: Vec<ConfigProblem>) -> Result<(), ConfigError> {
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError { problems })
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid VMA options: ")?;
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for std::io::Error {
+    fn from(error: ConfigError) -> Self {
+        VmaError::Config(error).into()
+    }
+}
+
+/// Flags passed to the underlying `send(2)`/`recv(2)` calls by the `*_with_flags` methods.
+///
+/// Flags combine with `|`. Which flags are meaningful depends on the call: `DONTWAIT` on
+/// both, `MORE` and `CONFIRM` on sends, `PEEK` and `WAITALL` on receives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MsgFlags(c_int);
+
+impl MsgFlags {
+    /// No flags
+    pub const NONE: MsgFlags = MsgFlags(0);
+    /// Never block: the call fails with `WouldBlock`/times out immediately instead of waiting
+    pub const DONTWAIT: MsgFlags = MsgFlags(libc::MSG_DONTWAIT);
+    /// More data follows: cork the write until a send without this flag (TCP and UDP)
+    pub const MORE: MsgFlags = MsgFlags(libc::MSG_MORE);
+    /// Tell the link layer the peer is reachable, skipping ARP revalidation (UDP)
+    pub const CONFIRM: MsgFlags = MsgFlags(libc::MSG_CONFIRM);
+    /// Return queued data without removing it (receive only)
+    pub const PEEK: MsgFlags = MsgFlags(libc::MSG_PEEK);
+    /// Block until the whole buffer is filled (TCP receive only, ignored when `use_polling` makes the socket non-blocking)
+    pub const WAITALL: MsgFlags = MsgFlags(libc::MSG_WAITALL);
+
+    /// Create flags from raw `MSG_*` bits.
+    pub const fn from_bits(bits: c_int) -> Self {
+        MsgFlags(bits)
+    }
+
+    /// Get the raw `MSG_*` bits.
+    pub const fn bits(&self) -> c_int {
+        self.0
+    }
+
+    /// Check whether no flag is set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check whether all flags in `other` are set.
+    pub const fn contains(&self, other: MsgFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for MsgFlags {
+    type Output = MsgFlags;
+
+    fn bitor(self, rhs: MsgFlags) -> MsgFlags {
+        MsgFlags(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for MsgFlags {
+    fn bitor_assign(&mut self, rhs: MsgFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Size of the hugepages requested with MAP_HUGETLB (the x86_64/aarch64 default).
+const HUGEPAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Zeroed, page-aligned memory backed by hugepages when available.
+///
+/// `VmaOptions::use_hugepages` only affects VMA's internal pools; allocate application
+/// RX/TX staging buffers with `HugeBuf` to avoid TLB misses on the hot path as well.
+/// When no hugepages are reserved (`/proc/sys/vm/nr_hugepages`), regular pages are used;
+/// [`is_hugepage`](Self::is_hugepage) tells which. Pages are prefaulted on allocation.
+#[derive(Debug)]
+pub struct HugeBuf {
+    ptr: std::ptr::NonNull<u8>,
+    len: usize,
+    mapped_len: usize,
+    hugepage: bool,
+}
+
+// The buffer owns its mapping exclusively, like a Box<[u8]>
+unsafe impl Send for HugeBuf {}
+unsafe impl Sync for HugeBuf {}
+
+impl HugeBuf {
+    /// Allocate `len` bytes from hugepages, falling back to regular pages.
+    pub fn new(len: usize) -> Result<Self, std::io::Error> {
+        Self::with_pages(len, true)
+    }
+
+    /// Allocate `len` bytes, trying hugepages only if `hugepages` is set.
+    pub fn with_pages(len: usize, hugepages: bool) -> Result<Self, std::io::Error> {
+        if len == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Buffer length must not be zero"));
+        }
+
+        if hugepages {
+            let mapped_len = len.next_multiple_of(HUGEPAGE_SIZE);
+            if let Ok(ptr) = map_anonymous(mapped_len, libc::MAP_HUGETLB) {
+                return Ok(HugeBuf { ptr, len, mapped_len, hugepage: true });
+            }
+        }
+
+        let ptr = map_anonymous(len, 0)?;
+        Ok(HugeBuf { ptr, len, mapped_len: len, hugepage: false })
+    }
+
+    /// Get the buffer length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check whether the buffer is empty (never true; kept for API symmetry with slices).
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check whether the buffer is backed by hugepages.
+    pub fn is_hugepage(&self) -> bool {
+        self.hugepage
+    }
+
+    /// Get the size of the underlying mapping (the length rounded up to whole pages).
+    pub fn mapped_len(&self) -> usize {
+        self.mapped_len
+    }
+}
+
+impl std::ops::Deref for HugeBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl std::ops::DerefMut for HugeBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for HugeBuf {
+    /// Unmap the buffer.
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.mapped_len);
+        }
+    }
+}
+
+/// Map `len` bytes of prefaulted anonymous memory with extra mmap flags.
+fn map_anonymous(len: usize, flags: c_int) -> Result<std::ptr::NonNull<u8>, std::io::Error> {
+    let addr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_POPULATE | flags,
+            -1,
+            0,
+        )
+    };
+    if addr == libc::MAP_FAILED {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(std::ptr::NonNull::new(addr as *mut u8).expect("mmap returned null"))
+}
+
+/// Transport protocol of a [`FiveTuple`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// UDP datagrams
+    Udp,
+    /// TCP connections
+    Tcp,
+}
+
+/// A flow selector: protocol plus local and remote endpoints, `None` matching any value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FiveTuple {
+    /// Transport protocol
+    pub protocol: Protocol,
+    /// Local (destination) address
+    pub local_ip: Option<Ipv4Addr>,
+    /// Local (destination) port
+    pub local_port: Option<u16>,
+    /// Remote (source) address
+    pub remote_ip: Option<Ipv4Addr>,
+    /// Remote (source) port
+    pub remote_port: Option<u16>,
+}
+
+impl FiveTuple {
+    /// Match UDP datagrams to `local_ip:local_port` from any sender.
+    pub fn udp(local_ip: Ipv4Addr, local_port: u16) -> Self {
+        FiveTuple {
+            protocol: Protocol::Udp,
+            local_ip: Some(local_ip),
+            local_port: Some(local_port),
+            remote_ip: None,
+            remote_port: None,
+        }
+    }
+
+    /// Restrict the flow to a single remote endpoint.
+    pub fn with_remote(mut self, remote_ip: Ipv4Addr, remote_port: u16) -> Self {
+        self.remote_ip = Some(remote_ip);
+        self.remote_port = Some(remote_port);
+        self
+    }
+
+    /// Format the flow as a libvma.conf rule offloading it through VMA.
+    ///
+    /// Flows without a remote endpoint use the `udp_receiver`/`tcp_server` roles, which
+    /// match on the local address; others use `udp_connect`/`tcp_client`, whose rules
+    /// list the remote address first.
+    pub fn to_conf_rule(&self) -> String {
+        fn endpoint(ip: Option<Ipv4Addr>, port: Option<u16>) -> String {
+            format!(
+                "{}:{}",
+                ip.map_or_else(|| "*".to_string(), |ip| ip.to_string()),
+                port.map_or_else(|| "*".to_string(), |port| port.to_string()),
+            )
+        }
+
+        let local = endpoint(self.local_ip, self.local_port);
+        let has_remote = self.remote_ip.is_some() || self.remote_port.is_some();
+        match (self.protocol, has_remote) {
+            (Protocol::Udp, false) => format!("use vma udp_receiver {}", local),
+            (Protocol::Tcp, false) => format!("use vma tcp_server {}", local),
+            (Protocol::Udp, true) => format!("use vma udp_connect {} {}", endpoint(self.remote_ip, self.remote_port), local),
+            (Protocol::Tcp, true) => format!("use vma tcp_client {} {}", endpoint(self.remote_ip, self.remote_port), local),
+        }
+    }
+}
+
+/// Pin the calling thread to the cores configured in `options.cpu_cores`.
+///
+/// The thread may then run on any of those cores. Does nothing when no cores are set.
+pub fn pin_current_thread(options: &VmaOptions) -> Result<(), std::io::Error> {
+    pin_current_thread_to(options.get_cores())
+}
+
+/// Pin the calling thread to `cores` (no-op for an empty slice).
+pub fn pin_current_thread_to(cores: &[c_int]) -> Result<(), std::io::Error> {
+    if cores.is_empty() {
+        return Ok(());
+    }
+
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    for &core in cores {
+        if core < 0 || core as usize >= libc::CPU_SETSIZE as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid CPU core: {}", core),
+            ));
+        }
+        unsafe { libc::CPU_SET(core as usize, &mut set) };
+    }
+
+    let res = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+    if res != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 /// Helper function to convert a Rust Duration to milliseconds for C API calls.
 pub fn unixnano_to_ms(duration: Option<u64>) -> c_int {
     match duration {
@@ -307,6 +1649,15 @@ pub fn unixnano_to_ms(duration: Option<u64>) -> c_int {
     }
 }
 
+/// Convert a timeout in nanoseconds to a `timeval` for SO_SNDTIMEO/SO_RCVTIMEO (`None` is zero, i.e. no timeout).
+pub(crate) fn unixnano_to_timeval(duration: Option<u64>) -> libc::timeval {
+    let nanos = duration.unwrap_or(0);
+    libc::timeval {
+        tv_sec: (nanos / 1_000_000_000) as libc::time_t,
+        tv_usec: ((nanos % 1_000_000_000) / 1_000) as libc::suseconds_t,
+    }
+}
+
 /// Convert a C socket address structure to a Rust SocketAddr.
 pub fn sockaddr_to_rust(sockaddr: &SockAddrIn) -> SocketAddr {
     let ip = Ipv4Addr::from(u32::from_be(sockaddr.sin_addr));
@@ -314,6 +1665,16 @@ pub fn sockaddr_to_rust(sockaddr: &SockAddrIn) -> SocketAddr {
     SocketAddr::new(IpAddr::V4(ip), port)
 }
 
+/// Convert a Rust SocketAddrV4 to a C socket address structure.
+pub fn sockaddr_from_rust(addr: &SocketAddrV4) -> SockAddrIn {
+    SockAddrIn {
+        sin_family: libc::AF_INET as u16,
+        sin_port: addr.port().to_be(),
+        sin_addr: u32::from(*addr.ip()).to_be(),
+        sin_zero: [0; 8],
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -331,6 +1692,157 @@ mod test {
         assert_eq!(options, deserialized);
     }
 
+    #[test]
+    fn test_option_presets() {
+        let presets = [
+            VmaOptions::multicast_feed(),
+            VmaOptions::order_entry(),
+            VmaOptions::bulk_transfer(),
+        ];
+        for (i, preset) in presets.iter().enumerate() {
+            assert!(presets[i + 1..].iter().all(|other| other != preset));
+            let json = serde_json::to_string(preset).unwrap();
+            assert_eq!(&serde_json::from_str::<VmaOptions>(&json).unwrap(), preset);
+        }
+        assert!(presets[0].rx_bufs > presets[0].tx_bufs);
+        assert_eq!(presets[1].ring_alloc_logic, RingAllocLogic::PerSocket);
+        assert!(!presets[2].use_polling && !presets[2].tcp_nodelay);
+    }
+
+    #[test]
+    fn test_validate_options() {
+        assert_eq!(VmaOptions::low_latency().validate(), Ok(()));
+
+        let mut options = VmaOptions::low_latency();
+        options.ring_count = -1;
+        options.buffer_size = -5;
+        options.set_cores(&[0, -1, 100_000]).unwrap();
+        let error = options.validate().unwrap_err();
+        assert_eq!(error.problems.len(), 4);
+        assert_eq!(error.problems[0], ConfigProblem::RingCount { ring_count: -1 });
+        assert!(matches!(error.problems[3], ConfigProblem::MissingCore { core: 100_000, .. }));
+        assert_eq!(error.to_string().matches("; ").count(), 3);
+        if !vma_loaded() {
+            let error = VmaOptions::low_latency().validate_offloaded().unwrap_err();
+            assert_eq!(error.problems, vec![ConfigProblem::VmaNotLoaded]);
+        }
+
+        let error = crate::udp::VmaUdpSocket::with_options(options).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(matches!(VmaError::from_io(&error), Some(VmaError::Config(config)) if config.problems.len() == 4));
+    }
+
+    #[test]
+    fn test_options_delta() {
+        let mut options = VmaOptions::low_latency();
+        let delta = OptionsDelta::from(&VmaOptions::bulk_transfer());
+        delta.merge_into(&mut options).unwrap();
+        assert_eq!(options.wait_strategy, WaitStrategy::SpinPark);
+        assert_eq!(options.buffer_size, 262144);
+        assert!(!options.tcp_nodelay && !options.enable_timestamps);
+        assert_eq!(options.rx_bufs, VmaOptions::low_latency().rx_bufs); // creation-time only
+
+        let before = options;
+        let bad = OptionsDelta { buffer_size: Some(-1), wait_strategy: Some(WaitStrategy::Spin), ..Default::default() };
+        assert!(bad.merge_into(&mut options).is_err());
+        assert_eq!(options, before);
+    }
+
+    #[test]
+    fn test_options_diff() {
+        let old = VmaOptions::low_latency();
+        assert!(old.diff(&old).is_empty() && old.delta_to(&old).is_empty());
+
+        let mut new = old;
+        new.wait_strategy = WaitStrategy::SpinPark;
+        new.rx_bufs = 64;
+        new.add_core(3).unwrap();
+        let changes = old.diff(&new);
+        let fields: Vec<_> = changes.iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["rx_bufs", "cpu_cores", "wait_strategy"]);
+        assert_eq!(changes[0].to_string(), "rx_bufs: 16 -> 64 (new sockets only)");
+        assert_eq!(changes[2].to_string(), "wait_strategy: Spin -> SpinPark");
+
+        let delta = old.delta_to(&new);
+        assert_eq!(delta, OptionsDelta { wait_strategy: Some(WaitStrategy::SpinPark), ..Default::default() });
+        let merged = old.merge(&delta).unwrap();
+        assert_eq!(merged.diff(&new).iter().filter(|change| change.runtime).count(), 0);
+    }
+
+    #[test]
+    fn test_poll_tunables() {
+        let mut options = VmaOptions::low_latency();
+        let json = serde_json::to_value(options).unwrap();
+        assert!(json["rx_poll"].is_null() && json["thread_mode"] == "auto");
+
+        options.thread_mode = ThreadMode::Single;
+        options.rx_poll = -1;
+        options.rx_poll_os_ratio = 424_242;
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(serde_json::from_str::<VmaOptions>(&json).unwrap(), options);
+        let fields: Vec<_> = VmaOptions::low_latency().diff(&options).iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["thread_mode", "rx_poll", "rx_poll_os_ratio"]);
+
+        // Translated to VMA's environment when the socket is created
+        let _socket = crate::udp::VmaUdpSocket::with_options(options).unwrap();
+        assert_eq!(std::env::var("VMA_RX_POLL_OS_RATIO").unwrap(), "424242");
+
+        options.select_poll = -2;
+        options.rx_poll_os_ratio = -1;
+        let error = options.validate().unwrap_err();
+        assert_eq!(error.problems, vec![
+            ConfigProblem::PollValue { field: "select_poll", value: -2 },
+            ConfigProblem::PollValue { field: "rx_poll_os_ratio", value: -1 },
+        ]);
+    }
+
+    #[test]
+    fn test_memory_options() {
+        let mut options = VmaOptions::low_latency();
+        assert!(options.uses_hugepages());
+        options.mem_alloc_type = MemAllocType::Anon;
+        assert!(!options.uses_hugepages());
+
+        options.rx_bufs = 4096;
+        options.ring_count = 2;
+        options.rx_wre = 2048;
+        options.tx_wre = 512;
+        assert_eq!(options.validate(), Ok(()));
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(serde_json::from_str::<VmaOptions>(&json).unwrap(), options);
+
+        options.rx_wre = 4096;
+        let error = options.validate().unwrap_err();
+        assert_eq!(error.problems, vec![ConfigProblem::WorkRequests { rx_wre: 4096, rings: 2, rx_bufs: 4096 }]);
+        assert_eq!(error.to_string(), "Invalid VMA options: rx_wre 4096 on 2 rings exceeds rx_bufs 4096");
+    }
+
+    #[test]
+    fn test_effective_options() {
+        std::env::set_var("VMA_TX_WRE", "777");
+        let requested = VmaOptions::low_latency();
+        let mut socket = crate::udp::VmaUdpSocket::with_options(requested).unwrap();
+        let effective = socket.options();
+        assert_eq!(effective.tx_wre, 777); // left to VMA, filled in from the environment
+        assert_eq!(effective.buffer_size, requested.buffer_size);
+        assert!(effective.enable_timestamps);
+
+        socket.apply_options(&OptionsDelta { enable_timestamps: Some(false), ..Default::default() }).unwrap();
+        assert!(!socket.options().enable_timestamps);
+        let fields: Vec<_> = requested.diff(&socket.options()).iter().map(|change| change.field).collect();
+        assert!(fields.contains(&"enable_timestamps") && fields.contains(&"tx_wre"));
+    }
+
+    #[test]
+    fn test_ring_fds() {
+        let socket = crate::udp::VmaUdpSocket::new().unwrap();
+        match socket.ring_fds() {
+            Ok(fds) => assert_eq!(fds.len(), socket.vma_fd_stats().unwrap().ring_count as usize),
+            Err(e) => assert!(!vma_loaded() && e.kind() == std::io::ErrorKind::Unsupported),
+        }
+        assert!(ring_fds(-1).is_err());
+    }
+
     #[test]
     fn test_add_core() {
         let mut options = VmaOptions::default();
@@ -348,4 +1860,124 @@ mod test {
         assert_eq!(options.cpu_cores[1], 2);
         assert_eq!(options.cpu_cores[2], 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pin_current_thread() {
+        let core = unsafe { libc::sched_getcpu() };
+        let mut options = VmaOptions::default();
+        assert!(pin_current_thread(&options).is_ok());
+
+        options.add_core(core).unwrap();
+        pin_current_thread(&options).unwrap();
+        assert_eq!(unsafe { libc::sched_getcpu() }, core);
+
+        let err = pin_current_thread_to(&[-1]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_five_tuple_conf_rule() {
+        let flow = FiveTuple::udp(Ipv4Addr::new(239, 1, 1, 1), 5000);
+        assert_eq!(flow.to_conf_rule(), "use vma udp_receiver 239.1.1.1:5000");
+
+        let flow = flow.with_remote(Ipv4Addr::new(10, 0, 0, 7), 6000);
+        assert_eq!(flow.to_conf_rule(), "use vma udp_connect 10.0.0.7:6000 239.1.1.1:5000");
+
+        let flow = FiveTuple { protocol: Protocol::Tcp, local_ip: None, ..flow };
+        assert_eq!(flow.to_conf_rule(), "use vma tcp_client 10.0.0.7:6000 *:5000");
+    }
+
+    #[test]
+    fn test_sockaddr_round_trip() {
+        let addr = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 5), 5001);
+        let c_addr = sockaddr_from_rust(&addr);
+        assert_eq!(c_addr.sin_family, libc::AF_INET as u16);
+        assert_eq!(sockaddr_to_rust(&c_addr), SocketAddr::V4(addr));
+    }
+
+    #[test]
+    fn test_stats_delta() {
+        let earlier = SocketStats { rx_packets: 10, tx_bytes: u64::MAX - 5, last_rx_ns: 1, ..SocketStats::default() };
+        let now = SocketStats { rx_packets: 25, tx_bytes: 10, last_rx_ns: 2, ..SocketStats::default() };
+        let delta = now.delta_since(&earlier);
+        assert_eq!(delta.rx_packets, 15);
+        assert_eq!(delta.tx_bytes, 16);
+        assert_eq!(delta.last_rx_ns, 2);
+        assert_eq!(now.delta_since(&SocketStats::default()), now);
+    }
+
+    #[test]
+    fn test_msg_flags() {
+        let mut flags = MsgFlags::DONTWAIT | MsgFlags::MORE;
+        assert_eq!(flags.bits(), libc::MSG_DONTWAIT | libc::MSG_MORE);
+        assert!(flags.contains(MsgFlags::MORE));
+        assert!(!flags.contains(MsgFlags::PEEK));
+        flags |= MsgFlags::PEEK;
+        assert!(flags.contains(MsgFlags::PEEK | MsgFlags::DONTWAIT));
+        assert!(MsgFlags::default().is_empty());
+    }
+
+    #[test]
+    fn test_vma_error() {
+        let error: std::io::Error = VmaError::DatagramTooLarge { len: 9000, max: 1472 }.into();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(VmaError::from_io(&error), Some(&VmaError::DatagramTooLarge { len: 9000, max: 1472 }));
+        assert_eq!(error.to_string(), "Datagram of 9000 bytes exceeds the maximum of 1472 bytes");
+        assert_eq!(VmaError::from_io(&std::io::Error::other("other")), None);
+
+        let error = VmaError::CloseFailed { fd: 7, errno: libc::EINTR };
+        assert!(error.to_string().starts_with("Closing fd 7 failed: Interrupted system call"));
+        assert_eq!(std::io::Error::from(error).kind(), std::io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_socket_diagnostics_display() {
+        let diagnostics = SocketDiagnostics {
+            protocol: Protocol::Udp,
+            fd: 7,
+            local_addr: Some("0.0.0.0:5001".parse().unwrap()),
+            peer_addr: None,
+            state: "bound".to_string(),
+            options: vec![("nonblocking", "false".to_string()), ("read_timeout", timeout_option(None))],
+            stats: Some(SocketStats { rx_packets: 3, ..SocketStats::default() }),
+            vma: Some(VmaFdStats::default()),
+        };
+        let report = diagnostics.to_string();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "udp socket fd=7 state=bound");
+        assert_eq!(lines[1], "  local=0.0.0.0:5001 remote=-");
+        assert_eq!(lines[2], "  options: nonblocking=false read_timeout=none");
+        assert!(lines[3].starts_with("  counters: rx_packets=3 tx_packets=0"));
+        assert_eq!(lines[4], "  vma: not loaded");
+    }
+
+    #[test]
+    fn test_huge_buf() {
+        // Falls back to regular pages when the machine has no hugepages reserved
+        let mut buffer = HugeBuf::new(10_000).unwrap();
+        assert_eq!(buffer.len(), 10_000);
+        assert!(buffer.mapped_len() >= 10_000);
+        assert!(buffer.iter().all(|&b| b == 0));
+        buffer[9_999] = 1;
+        assert_eq!(buffer[9_999], 1);
+
+        let buffer = HugeBuf::with_pages(100, false).unwrap();
+        assert!(!buffer.is_hugepage());
+        assert!(HugeBuf::new(0).is_err());
+    }
+
+    #[test]
+    fn test_from_fd_hands_back_descriptor() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let error = crate::udp::VmaUdpSocket::from_fd(OwnedFd::from(listener), None).unwrap_err();
+        assert_eq!(error.error().kind(), std::io::ErrorKind::InvalidInput);
+        let listener = std::net::TcpListener::from(error.into_fd());
+        assert!(listener.local_addr().is_ok());
+
+        let tcp = crate::tcp::VmaTcpSocket::from_fd(OwnedFd::from(listener), None).unwrap();
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let error = crate::tcp::VmaTcpSocket::from_fd(OwnedFd::from(udp), None).unwrap_err();
+        assert!(std::net::UdpSocket::from(error.into_fd()).local_addr().is_ok());
+        drop(tcp);
+    }
+}
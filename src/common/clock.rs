@@ -0,0 +1,206 @@
+//! TSC-calibrated high-resolution clock.
+//!
+//! Reading the time stamp counter costs a few nanoseconds, against 20-30ns for
+//! `clock_gettime` (and far more when the vDSO is unavailable), so hot loops can
+//! timestamp every message. The counter is calibrated once against the monotonic and
+//! wall clocks; [`now_nanos`] then extrapolates wall-clock time from it, on the same
+//! scale as the receive timestamps in [`Packet`](crate::udp::Packet).
+//!
+//! Calibration takes a few milliseconds on first use; call [`calibrate`] at startup to
+//! keep it out of the measured path. On targets without a TSC the functions fall back
+//! to the monotonic clock.
+//!
+//! The TSC must be invariant (constant rate, synchronized across cores), as on all
+//! x86-64 server CPUs of the last decade (`constant_tsc nonstop_tsc` in `/proc/cpuinfo`).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::clock;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! clock::calibrate();
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//!
+//! let mut buffer = [0u8; 1500];
+//! if let Some(packet) = socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap() {
+//!     let start = clock::ticks();
+//!     // ... handle the packet ...
+//!     let handled_in = clock::ticks_to_nanos(clock::ticks() - start);
+//!     // packet.timestamp is 0 unless the socket has enable_timestamps set
+//!     if packet.timestamp > 0 {
+//!         let wire_to_app = clock::now_nanos().saturating_sub(packet.timestamp);
+//!         println!("wire to app {} ns", wire_to_app);
+//!     }
+//!     println!("handled in {} ns", handled_in);
+//! }
+//! ```
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long the TSC is measured against the monotonic clock.
+const CALIBRATION_TIME: Duration = Duration::from_millis(5);
+
+/// Fixed-point shift of the ticks-to-nanoseconds factor.
+const SCALE_SHIFT: u32 = 32;
+
+/// Reference points taken at calibration.
+#[derive(Debug)]
+struct Calibration {
+    /// Counter value at `base_unix_ns`
+    base_ticks: u64,
+    /// Wall-clock time of `base_ticks` in nanoseconds since the epoch
+    base_unix_ns: u64,
+    /// Nanoseconds per tick, scaled by 2^SCALE_SHIFT
+    ns_per_tick: u64,
+    /// Ticks per nanosecond, scaled by 2^SCALE_SHIFT
+    ticks_per_ns: u64,
+    /// Counter frequency in Hz
+    hz: f64,
+}
+
+/// Read the raw counter (the TSC, or monotonic nanoseconds without one).
+#[inline]
+pub fn ticks() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        unsafe { core::arch::x86_64::_rdtsc() }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        monotonic_base().elapsed().as_nanos() as u64
+    }
+}
+
+/// Calibrate the clock now instead of on first use.
+pub fn calibrate() {
+    calibration();
+}
+
+/// Get the counter frequency in Hz.
+pub fn ticks_per_second() -> f64 {
+    calibration().hz
+}
+
+/// Convert a tick count (e.g. the difference of two [`ticks`] readings) to nanoseconds.
+#[inline]
+pub fn ticks_to_nanos(ticks: u64) -> u64 {
+    scale(ticks, calibration().ns_per_tick)
+}
+
+/// Convert nanoseconds to a tick count.
+#[inline]
+pub fn nanos_to_ticks(nanos: u64) -> u64 {
+    scale(nanos, calibration().ticks_per_ns)
+}
+
+/// Get the nanoseconds elapsed since calibration (monotonic, small enough for `f64`).
+#[inline]
+pub fn monotonic_nanos() -> u64 {
+    let calibration = calibration();
+    scale(ticks().wrapping_sub(calibration.base_ticks), calibration.ns_per_tick)
+}
+
+/// Get the current wall-clock time in nanoseconds since the epoch, from the counter.
+///
+/// Comparable with packet timestamps and `SystemTime::now()`, but drifts from
+/// the system clock by the calibration error (typically well under 100ppm) and does not
+/// follow NTP adjustments made after calibration.
+#[inline]
+pub fn now_nanos() -> u64 {
+    let calibration = calibration();
+    calibration.base_unix_ns + scale(ticks().wrapping_sub(calibration.base_ticks), calibration.ns_per_tick)
+}
+
+/// Convert a wall-clock timestamp in nanoseconds since the epoch (e.g. `Packet::timestamp`)
+/// to the counter value at that time.
+pub fn timestamp_to_ticks(timestamp_ns: u64) -> u64 {
+    let calibration = calibration();
+    if timestamp_ns >= calibration.base_unix_ns {
+        calibration.base_ticks.wrapping_add(nanos_to_ticks(timestamp_ns - calibration.base_unix_ns))
+    } else {
+        calibration.base_ticks.wrapping_sub(nanos_to_ticks(calibration.base_unix_ns - timestamp_ns))
+    }
+}
+
+/// Convert a counter value to a wall-clock timestamp in nanoseconds since the epoch.
+pub fn ticks_to_timestamp(ticks: u64) -> u64 {
+    let calibration = calibration();
+    let delta = ticks.wrapping_sub(calibration.base_ticks) as i64;
+    if delta >= 0 {
+        calibration.base_unix_ns + ticks_to_nanos(delta as u64)
+    } else {
+        calibration.base_unix_ns.saturating_sub(ticks_to_nanos(delta.unsigned_abs()))
+    }
+}
+
+/// Multiply by a 2^SCALE_SHIFT fixed-point factor.
+#[inline]
+fn scale(value: u64, factor: u64) -> u64 {
+    ((value as u128 * factor as u128) >> SCALE_SHIFT) as u64
+}
+
+fn calibration() -> &'static Calibration {
+    static CALIBRATION: OnceLock<Calibration> = OnceLock::new();
+    CALIBRATION.get_or_init(|| {
+        let start = Instant::now();
+        let start_ticks = ticks();
+        while start.elapsed() < CALIBRATION_TIME {
+            std::hint::spin_loop();
+        }
+
+        // Take the wall-clock reference between two counter reads to bound its error
+        let before = ticks();
+        let unix_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        let after = ticks();
+        let elapsed_ns = start.elapsed().as_nanos() as f64;
+
+        let elapsed_ticks = before.wrapping_sub(start_ticks).max(1) as f64;
+        let ns_per_tick = elapsed_ns / elapsed_ticks;
+        Calibration {
+            base_ticks: before + (after.wrapping_sub(before)) / 2,
+            base_unix_ns: unix_ns,
+            ns_per_tick: (ns_per_tick * (1u64 << SCALE_SHIFT) as f64) as u64,
+            ticks_per_ns: ((1u64 << SCALE_SHIFT) as f64 / ns_per_tick) as u64,
+            hz: 1e9 / ns_per_tick,
+        }
+    })
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn monotonic_base() -> &'static Instant {
+    static BASE: OnceLock<Instant> = OnceLock::new();
+    BASE.get_or_init(Instant::now)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        calibrate();
+        assert!(ticks_per_second() > 0.0);
+
+        let system_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        assert!(now_nanos().abs_diff(system_ns) < 1_000_000);
+
+        let start = ticks();
+        let started = Instant::now();
+        std::thread::sleep(Duration::from_millis(20));
+        let measured = ticks_to_nanos(ticks() - start);
+        let expected = started.elapsed().as_nanos() as u64;
+        assert!(measured.abs_diff(expected) < expected / 100);
+
+        let now = now_nanos();
+        assert!(ticks_to_timestamp(timestamp_to_ticks(now)).abs_diff(now) < 100);
+        assert!(ticks_to_timestamp(timestamp_to_ticks(now - 1_000_000)).abs_diff(now - 1_000_000) < 100);
+        assert!(nanos_to_ticks(ticks_to_nanos(1_000_000)).abs_diff(1_000_000) < 100);
+    }
+}
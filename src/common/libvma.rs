@@ -0,0 +1,217 @@
+//! Discovery of the installed libvma and its version.
+//!
+//! [`find_libvma`] looks for libvma where deployments put it ([`LIBVMA_PATH_ENV`], the
+//! standard library directories, then the `ldconfig` cache), reads the release from the
+//! versioned file name the library resolves to (`libvma.so.9.8.1`) and checks it against
+//! [`MIN_VMA_VERSION`]. The resulting [`LibvmaReport`] prints as one line for the
+//! startup log.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::vma_loaded;
+
+/// Environment variable naming the libvma to use, checked before the standard directories.
+pub const LIBVMA_PATH_ENV: &str = "VMA_SOCKET_LIBVMA";
+
+/// Oldest VMA release the crate supports.
+pub const MIN_VMA_VERSION: VmaVersion = VmaVersion { major: 9, minor: 0, revision: 0 };
+
+/// Directories searched for libvma, in order.
+const LIBVMA_DIRS: &[&str] = &[
+    "/usr/lib64",
+    "/usr/lib",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/usr/local/lib64",
+    "/usr/local/lib",
+];
+
+/// A VMA release number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VmaVersion {
+    /// Major release
+    pub major: u32,
+    /// Minor release
+    pub minor: u32,
+    /// Revision
+    pub revision: u32,
+}
+
+impl VmaVersion {
+    /// Read the version of a `libvma.so.X.Y.Z` file name.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let mut parts = name.strip_prefix("libvma.so.")?.split('.').map(|part| part.parse().ok());
+        let version = VmaVersion { major: parts.next()??, minor: parts.next()??, revision: parts.next()?? };
+        parts.next().is_none().then_some(version)
+    }
+}
+
+impl fmt::Display for VmaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.revision)
+    }
+}
+
+/// Where libvma was found and whether its version is supported, from [`find_libvma`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibvmaReport {
+    /// The libvma mapped in this process, if its Extra API is available
+    pub loaded: Option<PathBuf>,
+    /// Every libvma found on the system, in search order, symlinks resolved
+    pub found: Vec<PathBuf>,
+    /// Version of the loaded libvma, else of the first one found (`None` if unknown)
+    pub version: Option<VmaVersion>,
+    /// Oldest supported version ([`MIN_VMA_VERSION`])
+    pub minimum: VmaVersion,
+}
+
+impl LibvmaReport {
+    /// Get the libvma in use: the loaded one, else the first found.
+    pub fn path(&self) -> Option<&Path> {
+        self.loaded.as_deref().or_else(|| self.found.first().map(PathBuf::as_path))
+    }
+
+    /// Check whether a libvma was found and its version is known and supported.
+    pub fn is_supported(&self) -> bool {
+        self.path().is_some() && self.version.is_some_and(|version| version >= self.minimum)
+    }
+}
+
+impl fmt::Display for LibvmaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(path) = self.path() else {
+            return write!(f, "libvma not found (set {} or install libvma)", LIBVMA_PATH_ENV);
+        };
+        match self.version {
+            Some(version) => write!(f, "libvma {} at {}", version, path.display())?,
+            None => write!(f, "libvma of unknown version at {}", path.display())?,
+        }
+        write!(f, "{}", if self.loaded.is_some() { " (loaded)" } else { " (not loaded)" })?;
+        match self.version {
+            Some(version) if version < self.minimum => {
+                write!(f, ", older than the minimum supported {}", self.minimum)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Look for libvma on the system and check its version.
+///
+/// Searches [`LIBVMA_PATH_ENV`], the standard library directories and `ldconfig -p`,
+/// and looks for the libvma mapped in this process when it is loaded.
+///
+/// ```rust,no_run
+/// use vma_socket::common::find_libvma;
+///
+/// let report = find_libvma();
+/// eprintln!("{}", report); // libvma 9.8.1 at /usr/lib64/libvma.so.9.8.1 (loaded)
+/// assert!(report.is_supported(), "unsupported VMA installation");
+/// ```
+pub fn find_libvma() -> LibvmaReport {
+    let mut candidates: Vec<PathBuf> = std::env::var_os(LIBVMA_PATH_ENV).map(PathBuf::from).into_iter().collect();
+    candidates.extend(LIBVMA_DIRS.iter().filter_map(|dir| find_in(Path::new(dir))));
+    let ldconfig = Command::new("ldconfig")
+        .arg("-p")
+        .output()
+        .or_else(|_| Command::new("/sbin/ldconfig").arg("-p").output());
+    if let Ok(output) = ldconfig {
+        candidates.extend(parse_ldconfig(&String::from_utf8_lossy(&output.stdout)));
+    }
+    let loaded = if vma_loaded() { mapped_libvma() } else { None };
+    report(loaded, candidates)
+}
+
+/// Build the report of the `loaded` libvma and the `candidates` found, deduplicated.
+fn report(loaded: Option<PathBuf>, candidates: Vec<PathBuf>) -> LibvmaReport {
+    let mut seen = HashSet::new();
+    let found: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter_map(|path| std::fs::canonicalize(path).ok())
+        .filter(|path| seen.insert(path.clone()))
+        .collect();
+    let loaded = loaded.map(|path| std::fs::canonicalize(&path).unwrap_or(path));
+    let version = loaded
+        .as_deref()
+        .or_else(|| found.first().map(PathBuf::as_path))
+        .and_then(file_name)
+        .and_then(VmaVersion::from_file_name);
+    LibvmaReport { loaded, found, version, minimum: MIN_VMA_VERSION }
+}
+
+/// Find `libvma.so` in `dir`, or else its highest versioned `libvma.so.*`.
+fn find_in(dir: &Path) -> Option<PathBuf> {
+    let unversioned = dir.join("libvma.so");
+    if unversioned.is_file() {
+        return Some(unversioned);
+    }
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| file_name(path).is_some_and(|name| name.starts_with("libvma.so.")))
+        .max_by_key(|path| file_name(path).and_then(VmaVersion::from_file_name))
+}
+
+fn file_name(path: &Path) -> Option<&str> {
+    path.file_name()?.to_str()
+}
+
+/// Get the libvma paths listed by `ldconfig -p`.
+fn parse_ldconfig(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter(|line| line.trim_start().starts_with("libvma.so"))
+        .filter_map(|line| line.split_once("=>"))
+        .map(|(_, path)| PathBuf::from(path.trim()))
+        .collect()
+}
+
+/// Get the libvma mapped in this process, from `/proc/self/maps`.
+fn mapped_libvma() -> Option<PathBuf> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .map(PathBuf::from)
+        .find(|path| file_name(path).is_some_and(|name| name.starts_with("libvma.so")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_libvma() {
+        assert_eq!(
+            VmaVersion::from_file_name("libvma.so.9.10.1"),
+            Some(VmaVersion { major: 9, minor: 10, revision: 1 })
+        );
+        assert_eq!(VmaVersion::from_file_name("libvma.so.9"), None);
+
+        let dir = std::env::temp_dir().join(format!("vma-libvma-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = std::fs::canonicalize(dir).unwrap();
+        assert_eq!(find_in(&dir), None);
+        for name in ["libvma.so.8.9.4", "libvma.so.9.10.1", "libvma.so.9.2.2", "libvmaxyz.so"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let newest = dir.join("libvma.so.9.10.1");
+        assert_eq!(find_in(&dir), Some(newest.clone()));
+
+        let ldconfig = format!("\tlibvma.so.8 (libc6,x86-64) => {}/libvma.so.8.9.4\n\tlibc.so.6 => /lib/libc.so.6\n", dir.display());
+        let candidates = [vec![newest.clone()], parse_ldconfig(&ldconfig), vec![newest.clone()]].concat();
+        let found = report(None, candidates);
+        assert_eq!(found.found.len(), 2);
+        assert!(found.is_supported());
+        assert_eq!(found.to_string(), format!("libvma 9.10.1 at {} (not loaded)", newest.display()));
+
+        let old = report(None, vec![dir.join("libvma.so.8.9.4")]);
+        assert!(!old.is_supported());
+        assert!(old.to_string().ends_with("older than the minimum supported 9.0.0"));
+        assert!(!report(None, Vec::new()).is_supported());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
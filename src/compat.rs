@@ -0,0 +1,772 @@
+//! `std::net`-compatible facades over [`VmaUdpSocket`], [`VmaTcpSocket`] and [`Client`].
+//!
+//! [`UdpSocket`], [`TcpStream`] and [`TcpListener`] have the method signatures of their
+//! `std::net` counterparts (addresses through `ToSocketAddrs`, `&self` receivers,
+//! `Duration` timeouts, `std::io::Read`/`Write` on streams, `incoming()` on listeners),
+//! so existing code moves to VMA by replacing `use std::net::{...}` with
+//! `use vma_socket::compat::{...}`.
+//!
+//! The behaviour follows the standard library where the two differ from the native API:
+//! an expired read or write timeout and an operation that would block in non-blocking
+//! mode fail with `ErrorKind::WouldBlock` instead of returning no data, receives return
+//! the number of bytes copied into the buffer, and a stream read returns 0 at
+//! end-of-stream. Only IPv4 is supported.
+//!
+//! Each socket sits behind a mutex so `&self` methods can drive it and the facade can be
+//! shared between threads like the std sockets. Blocking calls wait for readiness with
+//! `poll(2)` on the descriptor without holding the mutex and lock it only for the
+//! non-blocking attempt, so a thread blocked in a read does not hold up a writer. Code
+//! that owns the socket on one thread gets lower latency from the native types,
+//! reachable through `into_inner()`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::{Read, Write};
+//! use std::time::Duration;
+//! use vma_socket::compat::{TcpListener, UdpSocket};
+//!
+//! let socket = UdpSocket::bind("0.0.0.0:5001").unwrap();
+//! socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+//!
+//! let mut buffer = [0u8; 1500];
+//! let (len, src) = socket.recv_from(&mut buffer).unwrap();
+//! socket.send_to(&buffer[..len], src).unwrap();
+//!
+//! let listener = TcpListener::bind("0.0.0.0:5002").unwrap();
+//! for stream in listener.incoming() {
+//!     let mut stream = stream.unwrap();
+//!     let len = stream.read(&mut buffer).unwrap();
+//!     stream.write_all(&buffer[..len]).unwrap();
+//! }
+//! ```
+
+use std::io::{self, Error, ErrorKind, Read, Result, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4, ToSocketAddrs};
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::common::MsgFlags;
+use crate::tcp::{Client, TcpConnectionState, TcpResult, VmaTcpSocket};
+use crate::udp::{Destination, VmaUdpSocket};
+
+/// Backlog of [`TcpListener::bind`], as used by the standard library.
+const LISTEN_BACKLOG: i32 = 128;
+
+/// UDP socket with the API of `std::net::UdpSocket`, backed by [`VmaUdpSocket`].
+#[derive(Debug)]
+pub struct UdpSocket {
+    inner: Mutex<VmaUdpSocket>,
+    fd: RawFd,
+}
+
+impl UdpSocket {
+    /// Create a socket bound to the first IPv4 address `addr` resolves to.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<UdpSocket> {
+        let addr = first_v4(addr)?;
+        let mut socket = VmaUdpSocket::new()?;
+        socket.bind(addr.ip().to_string(), addr.port())?;
+        Ok(UdpSocket::from_vma(socket))
+    }
+
+    /// Wrap an existing socket, e.g. one created with custom `VmaOptions`.
+    pub fn from_vma(socket: VmaUdpSocket) -> UdpSocket {
+        UdpSocket {
+            fd: socket.fd(),
+            inner: Mutex::new(socket),
+        }
+    }
+
+    /// Unwrap the underlying socket.
+    pub fn into_inner(self) -> VmaUdpSocket {
+        self.inner.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Receive a datagram, returning the bytes copied into `buf` and the sender.
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.read_ready(|socket| Ok(socket.recv_from(buf, Some(0))?.map(|packet| (packet.data.len(), packet.src_addr))))
+    }
+
+    /// Receive a datagram without removing it from the queue.
+    pub fn peek_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.read_ready(|socket| Ok(socket.peek_from(buf, Some(0))?.map(|packet| (packet.data.len(), packet.src_addr))))
+    }
+
+    /// Send `buf` to the first IPv4 address `addr` resolves to.
+    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        let dest = Destination::new(first_v4(addr)?);
+        self.write_ready(|socket| socket.send_to_dest_with_flags(buf, &dest, MsgFlags::DONTWAIT).map(Some))
+    }
+
+    /// Get the remote address the socket is connected to.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.lock()
+            .peer_addr()
+            .ok_or_else(|| Error::new(ErrorKind::NotConnected, "Socket is not connected"))
+    }
+
+    /// Get the local address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.lock().local_addr()
+    }
+
+    /// Set the read timeout; `None` blocks indefinitely and a zero duration is rejected.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> Result<()> {
+        let timeout = nonzero_nanos(dur)?;
+        self.lock().set_read_timeout(timeout);
+        Ok(())
+    }
+
+    /// Set the write timeout; `None` blocks indefinitely and a zero duration is rejected.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> Result<()> {
+        let timeout = nonzero_nanos(dur)?;
+        self.lock().set_write_timeout(timeout)
+    }
+
+    /// Get the read timeout.
+    pub fn read_timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.lock().read_timeout().map(Duration::from_nanos))
+    }
+
+    /// Get the write timeout.
+    pub fn write_timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.lock().write_timeout().map(Duration::from_nanos))
+    }
+
+    /// Set the multicast TTL (IP_MULTICAST_TTL).
+    pub fn set_multicast_ttl_v4(&self, multicast_ttl_v4: u32) -> Result<()> {
+        self.lock().set_multicast_ttl_v4(multicast_ttl_v4)
+    }
+
+    /// Get the multicast TTL.
+    pub fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.lock().multicast_ttl_v4()
+    }
+
+    /// Set the unicast TTL (IP_TTL).
+    pub fn set_ttl(&self, ttl: u32) -> Result<()> {
+        self.lock().set_ttl(ttl)
+    }
+
+    /// Get the unicast TTL.
+    pub fn ttl(&self) -> Result<u32> {
+        self.lock().ttl()
+    }
+
+    /// Join the multicast group `multiaddr` on the interface with address `interface`.
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.lock().join_multicast_v4(multiaddr, interface)
+    }
+
+    /// Leave the multicast group `multiaddr` on the interface with address `interface`.
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.lock().leave_multicast_v4(multiaddr, interface)
+    }
+
+    /// Connect the socket to the first IPv4 address `addr` resolves to.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
+        let addr = first_v4(addr)?;
+        self.lock().connect(addr.ip().to_string(), addr.port())
+    }
+
+    /// Send `buf` to the connected remote address.
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.write_ready(|socket| socket.send_with_flags(buf, MsgFlags::DONTWAIT).map(Some))
+    }
+
+    /// Receive a datagram from the connected remote address.
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.read_ready(|socket| Ok(socket.recv_full(buf, Some(0))?.map(|length| length.copied)))
+    }
+
+    /// Receive a datagram from the connected remote address without removing it.
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        self.peek_from(buf).map(|(len, _)| len)
+    }
+
+    /// Switch the socket between blocking and non-blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.lock().set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    /// Run the receive `op` once the socket is readable, within the read timeout.
+    fn read_ready<T>(&self, op: impl FnMut(&mut VmaUdpSocket) -> Result<Option<T>>) -> Result<T> {
+        let (nonblocking, timeout) = {
+            let socket = self.lock();
+            (socket.is_nonblocking(), socket.read_timeout())
+        };
+        run_ready(&self.inner, self.fd, libc::POLLIN, nonblocking, timeout, op)
+    }
+
+    /// Run the send `op` once the socket is writable, within the write timeout.
+    fn write_ready<T>(&self, op: impl FnMut(&mut VmaUdpSocket) -> Result<Option<T>>) -> Result<T> {
+        let (nonblocking, timeout) = {
+            let socket = self.lock();
+            (socket.is_nonblocking(), socket.write_timeout())
+        };
+        run_ready(&self.inner, self.fd, libc::POLLOUT, nonblocking, timeout, op)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VmaUdpSocket> {
+        lock(&self.inner)
+    }
+}
+
+impl From<VmaUdpSocket> for UdpSocket {
+    fn from(socket: VmaUdpSocket) -> Self {
+        UdpSocket::from_vma(socket)
+    }
+}
+
+impl AsRawFd for UdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// Socket behind a [`TcpStream`]: connected by this side or accepted from a listener.
+#[derive(Debug)]
+enum StreamSocket {
+    Connected(Box<VmaTcpSocket>),
+    Accepted(Client),
+}
+
+/// Stream state guarded by the mutex of a [`TcpStream`].
+#[derive(Debug)]
+struct StreamInner {
+    socket: StreamSocket,
+    read_timeout: Option<u64>,
+    write_timeout: Option<u64>,
+    nonblocking: bool,
+}
+
+impl StreamInner {
+    /// Receive what is queued without waiting, `None` if nothing is.
+    fn try_read(&mut self, buf: &mut [u8], peek: bool) -> Result<Option<usize>> {
+        match &mut self.socket {
+            StreamSocket::Connected(socket) if peek => match socket.peek(buf, Some(0))? {
+                0 if socket.state() != TcpConnectionState::Disconnected => Ok(None),
+                bytes => Ok(Some(bytes)),
+            },
+            StreamSocket::Connected(socket) => socket.recv_checked(buf, Some(0)),
+            StreamSocket::Accepted(client) => {
+                let result = if peek { client.peek(buf, Some(0)) } else { client.recv(buf, Some(0)) };
+                match result {
+                    Ok(bytes) => Ok(Some(bytes)),
+                    Err(TcpResult::TcpErrorTimeout) => Ok(None),
+                    Err(TcpResult::TcpErrorClosed) => Ok(Some(0)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// Send what fits in the send buffer without waiting, `None` if nothing does.
+    fn try_write(&mut self, buf: &[u8]) -> Result<Option<usize>> {
+        let sent = match &mut self.socket {
+            StreamSocket::Connected(socket) => socket.send_with_flags(buf, MsgFlags::DONTWAIT)?,
+            StreamSocket::Accepted(client) => match client.send_with_flags(buf, MsgFlags::DONTWAIT) {
+                Ok(bytes) => bytes,
+                Err(TcpResult::TcpErrorWouldBlock) => 0,
+                Err(e) => return Err(e.into()),
+            },
+        };
+        Ok(Some(sent).filter(|&sent| sent > 0))
+    }
+}
+
+/// TCP stream with the API of `std::net::TcpStream`, backed by [`VmaTcpSocket`] or [`Client`].
+#[derive(Debug)]
+pub struct TcpStream {
+    inner: Mutex<StreamInner>,
+    fd: RawFd,
+}
+
+impl TcpStream {
+    /// Connect to `addr`, trying each IPv4 address it resolves to in turn.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<TcpStream> {
+        let mut last_error = None;
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_v4(addr, None) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::new(ErrorKind::InvalidInput, "No IPv4 address to use")))
+    }
+
+    /// Connect to `addr`, failing with `ErrorKind::TimedOut` after `timeout`.
+    pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> Result<TcpStream> {
+        let timeout = nonzero_nanos(Some(timeout))?;
+        TcpStream::connect_v4(*addr, timeout)
+    }
+
+    fn connect_v4(addr: SocketAddr, timeout: Option<u64>) -> Result<TcpStream> {
+        let SocketAddr::V4(addr) = addr else {
+            return Err(Error::new(ErrorKind::InvalidInput, "No IPv4 address to use"));
+        };
+        let mut socket = VmaTcpSocket::new()?;
+        if !socket.connect(addr.ip().to_string(), addr.port(), timeout)? {
+            return Err(Error::new(ErrorKind::TimedOut, "Connection timed out"));
+        }
+        Ok(TcpStream::from_socket(StreamSocket::Connected(Box::new(socket))))
+    }
+
+    /// Wrap a connected socket, e.g. one created with custom `VmaOptions`.
+    pub fn from_vma(socket: VmaTcpSocket) -> TcpStream {
+        TcpStream::from_socket(StreamSocket::Connected(Box::new(socket)))
+    }
+
+    /// Wrap a client accepted by a [`VmaTcpSocket`] listener.
+    pub fn from_client(client: Client) -> TcpStream {
+        TcpStream::from_socket(StreamSocket::Accepted(client))
+    }
+
+    fn from_socket(socket: StreamSocket) -> TcpStream {
+        let fd = match &socket {
+            StreamSocket::Connected(socket) => socket.fd(),
+            StreamSocket::Accepted(client) => client.fd(),
+        };
+        TcpStream {
+            fd,
+            inner: Mutex::new(StreamInner {
+                socket,
+                read_timeout: None,
+                write_timeout: None,
+                nonblocking: false,
+            }),
+        }
+    }
+
+    /// Unwrap the underlying socket: `Ok` for a connected one, `Err` for an accepted client.
+    pub fn into_inner(self) -> std::result::Result<VmaTcpSocket, Client> {
+        match self.inner.into_inner().unwrap_or_else(|e| e.into_inner()).socket {
+            StreamSocket::Connected(socket) => Ok(*socket),
+            StreamSocket::Accepted(client) => Err(client),
+        }
+    }
+
+    /// Get the remote address of the stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        match &self.lock().socket {
+            StreamSocket::Connected(socket) => socket
+                .peer_addr()
+                .ok_or_else(|| Error::new(ErrorKind::NotConnected, "Socket is not connected")),
+            StreamSocket::Accepted(client) => Ok(client.address),
+        }
+    }
+
+    /// Get the local address of the stream.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        match &self.lock().socket {
+            StreamSocket::Connected(socket) => socket.local_addr(),
+            StreamSocket::Accepted(client) => client.local_addr(),
+        }
+    }
+
+    /// Shut down the read, write or both halves of the connection.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        match &mut self.lock().socket {
+            StreamSocket::Connected(socket) => socket.shutdown(how),
+            StreamSocket::Accepted(client) => client.shutdown(how).map_err(|e| e.into()),
+        }
+    }
+
+    /// Set the read timeout; `None` blocks indefinitely and a zero duration is rejected.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> Result<()> {
+        self.lock().read_timeout = nonzero_nanos(dur)?;
+        Ok(())
+    }
+
+    /// Set the write timeout; `None` blocks indefinitely and a zero duration is rejected.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> Result<()> {
+        self.lock().write_timeout = nonzero_nanos(dur)?;
+        Ok(())
+    }
+
+    /// Get the read timeout.
+    pub fn read_timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.lock().read_timeout.map(Duration::from_nanos))
+    }
+
+    /// Get the write timeout.
+    pub fn write_timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.lock().write_timeout.map(Duration::from_nanos))
+    }
+
+    /// Receive data without removing it from the queue.
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        self.read_ready(buf, true)
+    }
+
+    /// Enable or disable Nagle's algorithm (TCP_NODELAY).
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
+        match &mut self.lock().socket {
+            StreamSocket::Connected(socket) => socket.set_nodelay(nodelay),
+            StreamSocket::Accepted(client) => client.set_nodelay(nodelay).map_err(|e| e.into()),
+        }
+    }
+
+    /// Check whether Nagle's algorithm is disabled.
+    pub fn nodelay(&self) -> Result<bool> {
+        match &mut self.lock().socket {
+            StreamSocket::Connected(socket) => socket.nodelay(),
+            StreamSocket::Accepted(client) => client.nodelay().map_err(|e| e.into()),
+        }
+    }
+
+    /// Switch the stream between blocking and non-blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.lock().nonblocking = nonblocking;
+        Ok(())
+    }
+
+    fn read_ready(&self, buf: &mut [u8], peek: bool) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let (nonblocking, timeout) = {
+            let inner = self.lock();
+            (inner.nonblocking, inner.read_timeout)
+        };
+        run_ready(&self.inner, self.fd, libc::POLLIN, nonblocking, timeout, |inner| inner.try_read(buf, peek))
+    }
+
+    fn write_ready(&self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let (nonblocking, timeout) = {
+            let inner = self.lock();
+            (inner.nonblocking, inner.write_timeout)
+        };
+        run_ready(&self.inner, self.fd, libc::POLLOUT, nonblocking, timeout, |inner| inner.try_write(buf))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StreamInner> {
+        lock(&self.inner)
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_ready(buf, false)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_ready(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for &TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_ready(buf, false)
+    }
+}
+
+impl Write for &TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_ready(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl From<VmaTcpSocket> for TcpStream {
+    fn from(socket: VmaTcpSocket) -> Self {
+        TcpStream::from_vma(socket)
+    }
+}
+
+impl From<Client> for TcpStream {
+    fn from(client: Client) -> Self {
+        TcpStream::from_client(client)
+    }
+}
+
+impl AsRawFd for TcpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// TCP listener with the API of `std::net::TcpListener`, backed by [`VmaTcpSocket`].
+#[derive(Debug)]
+pub struct TcpListener {
+    inner: Mutex<VmaTcpSocket>,
+    fd: RawFd,
+}
+
+impl TcpListener {
+    /// Create a listener bound to the first IPv4 address `addr` resolves to.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<TcpListener> {
+        let addr = first_v4(addr)?;
+        let mut socket = VmaTcpSocket::new()?;
+        socket.bind(addr.ip().to_string(), addr.port())?;
+        socket.listen(LISTEN_BACKLOG)?;
+        Ok(TcpListener::from_vma(socket))
+    }
+
+    /// Wrap a listening socket, e.g. one created with custom `VmaOptions` or an accept filter.
+    pub fn from_vma(socket: VmaTcpSocket) -> TcpListener {
+        TcpListener {
+            fd: socket.fd(),
+            inner: Mutex::new(socket),
+        }
+    }
+
+    /// Unwrap the underlying socket.
+    pub fn into_inner(self) -> VmaTcpSocket {
+        self.inner.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the local address the listener is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.lock().local_addr()
+    }
+
+    /// Accept a connection, returning the stream and the remote address.
+    pub fn accept(&self) -> Result<(TcpStream, SocketAddr)> {
+        let nonblocking = self.lock().is_nonblocking();
+        let client = run_ready(&self.inner, self.fd, libc::POLLIN, nonblocking, None, |socket| socket.accept(Some(0)))?;
+        let address = client.address;
+        Ok((TcpStream::from_client(client), address))
+    }
+
+    /// Iterate over accepted connections; the iterator never ends.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+
+    /// Switch the listener between blocking and non-blocking accepts.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.lock().set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VmaTcpSocket> {
+        lock(&self.inner)
+    }
+}
+
+impl From<VmaTcpSocket> for TcpListener {
+    fn from(socket: VmaTcpSocket) -> Self {
+        TcpListener::from_vma(socket)
+    }
+}
+
+impl AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+/// Iterator over the connections of a [`TcpListener`], returned by `incoming()`.
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    listener: &'a TcpListener,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = Result<TcpStream>;
+
+    fn next(&mut self) -> Option<Result<TcpStream>> {
+        Some(self.listener.accept().map(|(stream, _)| stream))
+    }
+}
+
+/// Lock `mutex`, recovering the socket from a thread that panicked while holding it.
+fn lock<S>(mutex: &Mutex<S>) -> MutexGuard<'_, S> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run the non-blocking `op` on the socket behind `mutex` until it makes progress.
+///
+/// `op` returns `Ok(None)` or fails with `ErrorKind::WouldBlock` when it would block.
+/// Between attempts `fd` is polled for `events` with the mutex released, so other threads
+/// keep using the socket; another thread may also take the data first, in which case
+/// the wait resumes. Fails with `ErrorKind::WouldBlock` in non-blocking mode or once
+/// `timeout_nano` expires.
+fn run_ready<S, T>(
+    mutex: &Mutex<S>,
+    fd: RawFd,
+    events: libc::c_short,
+    nonblocking: bool,
+    timeout_nano: Option<u64>,
+    mut op: impl FnMut(&mut S) -> Result<Option<T>>,
+) -> Result<T> {
+    let deadline = timeout_nano.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+    loop {
+        match op(&mut lock(mutex)) {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        if nonblocking || !poll_fd(fd, events, deadline)? {
+            return Err(would_block());
+        }
+    }
+}
+
+/// Wait until `fd` reports one of `events` or `deadline` passes, returning whether it did.
+fn poll_fd(fd: RawFd, events: libc::c_short, deadline: Option<Instant>) -> Result<bool> {
+    let timeout_ms = match deadline {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            remaining.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32
+        }
+        None => -1,
+    };
+    let mut pollfd = libc::pollfd { fd, events, revents: 0 };
+    match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+        0 => Ok(false),
+        n if n > 0 => Ok(true),
+        _ => {
+            let e = Error::last_os_error();
+            // A signal only cuts the wait short: try again
+            if e.kind() == ErrorKind::Interrupted { Ok(true) } else { Err(e) }
+        }
+    }
+}
+
+/// Error returned by operations that timed out or would block, as the std sockets do.
+fn would_block() -> Error {
+    Error::new(ErrorKind::WouldBlock, "Resource temporarily unavailable")
+}
+
+/// Resolve `addr` to its first IPv4 address.
+fn first_v4<A: ToSocketAddrs>(addr: A) -> Result<SocketAddrV4> {
+    addr.to_socket_addrs()?
+        .find_map(|addr| match addr {
+            SocketAddr::V4(addr) => Some(addr),
+            SocketAddr::V6(_) => None,
+        })
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "No IPv4 address to use"))
+}
+
+/// Convert a timeout to nanoseconds, rejecting a zero duration like the std sockets.
+fn nonzero_nanos(dur: Option<Duration>) -> Result<Option<u64>> {
+    match dur {
+        Some(dur) if dur.is_zero() => Err(Error::new(ErrorKind::InvalidInput, "Cannot set a 0 duration timeout")),
+        Some(dur) => Ok(Some(dur.as_nanos().min(u64::MAX as u128) as u64)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compat_udp_socket() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        assert_eq!(client.peer_addr().unwrap_err().kind(), ErrorKind::NotConnected);
+
+        // An expired read timeout is reported like std does
+        server.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(server.read_timeout().unwrap(), Some(Duration::from_millis(10)));
+        let mut buffer = [0u8; 16];
+        assert_eq!(server.recv_from(&mut buffer).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(server.set_read_timeout(Some(Duration::ZERO)).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        client.send_to(b"ping", server_addr).unwrap();
+        let (len, src) = server.peek_from(&mut buffer).unwrap();
+        assert_eq!((&buffer[..len], src), (&b"ping"[..], client.local_addr().unwrap()));
+        let (len, src) = server.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"ping");
+
+        client.connect(server_addr).unwrap();
+        server.send_to(b"pong", src).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(client.recv(&mut buffer).unwrap(), 4);
+        assert_eq!(&buffer[..4], b"pong");
+
+        client.set_nonblocking(true).unwrap();
+        assert_eq!(client.recv(&mut buffer).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert!(UdpSocket::bind("[::1]:0").is_err());
+    }
+
+    #[test]
+    fn test_compat_tcp_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let mut buffer = [0u8; 16];
+            let len = stream.read(&mut buffer).unwrap();
+            stream.write_all(&buffer[..len]).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            stream.peer_addr().unwrap()
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+        stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        stream.write_all(b"echo").unwrap();
+
+        let mut buffer = [0u8; 16];
+        let mut len = 0;
+        while len < 4 {
+            len += stream.read(&mut buffer[len..]).unwrap();
+        }
+        assert_eq!(&buffer[..4], b"echo");
+        assert_eq!(server.join().unwrap(), stream.local_addr().unwrap());
+
+        // End-of-stream reads 0 like std
+        assert_eq!(stream.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_compat_send_during_blocked_recv() {
+        let socket = std::sync::Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+        socket.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let receiver = std::thread::spawn({
+            let socket = socket.clone();
+            move || {
+                let mut buffer = [0u8; 16];
+                socket.recv_from(&mut buffer).map(|(len, _)| buffer[..len].to_vec())
+            }
+        });
+
+        // The receiver waits without the lock, so sending on the same socket goes through
+        std::thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        socket.send_to(b"self", socket.local_addr().unwrap()).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(receiver.join().unwrap().unwrap(), b"self");
+
+        // Same for a stream blocked in a read while the other half writes
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::sync::Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let (mut peer, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let reader = std::thread::spawn({
+            let stream = stream.clone();
+            move || {
+                let mut buffer = [0u8; 16];
+                (&*stream).read(&mut buffer).map(|len| buffer[..len].to_vec())
+            }
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        (&*stream).write_all(b"ping").unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let mut buffer = [0u8; 4];
+        peer.read_exact(&mut buffer).unwrap();
+        peer.write_all(b"pong").unwrap();
+        assert_eq!(reader.join().unwrap().unwrap(), b"pong");
+    }
+}
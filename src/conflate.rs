@@ -0,0 +1,286 @@
+//! Conflated delivery of the latest update per key to slow consumers.
+//!
+//! A consumer that falls behind a fast feed rarely needs every intermediate quote: only
+//! the newest one per instrument matters. A [`ConflatingMap`] keeps one pending value per
+//! key, as extracted from each value by a user function. Publishing a value for a key that
+//! is still pending replaces it in place, so the consumer receives the most recent update
+//! of each key, in the order the keys first became pending, and never more than one entry
+//! per key however far it falls behind.
+//!
+//! [`ConflationStats`] counts published, delivered and replaced values, giving the
+//! conflation rate of the consumer. [`spawn_receiver`] feeds a map from a [`VmaUdpSocket`]
+//! on a pinned thread.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::conflate::{self, ConflatingMap};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//!
+//! // Instrument id in the first 4 bytes of each datagram
+//! let map = ConflatingMap::new(|packet: &vma_socket::udp::Packet| {
+//!     packet.data.get(..4).map(|id| u32::from_le_bytes(id.try_into().unwrap()))
+//! });
+//! let receiver = conflate::spawn_receiver(socket, map.clone(), &[2]).unwrap();
+//!
+//! while let Some(packet) = map.recv(Some(1_000_000_000)) {
+//!     println!("latest {} bytes from {}", packet.data.len(), packet.src_addr);
+//! }
+//! println!("conflation rate: {:.1}%", map.stats().conflation_rate() * 100.0);
+//! receiver.stop().unwrap();
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::io::Error;
+use std::os::raw::c_int;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::spsc::{self, ReceiverThread};
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// Counters of a [`ConflatingMap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConflationStats {
+    /// Values published with a key
+    pub published: u64,
+    /// Values handed to the consumer
+    pub delivered: u64,
+    /// Pending values replaced by a newer one for the same key
+    pub conflated: u64,
+    /// Values published without a key (the extractor returned `None`), discarded
+    pub unkeyed: u64,
+}
+
+impl ConflationStats {
+    /// Get the share of published values replaced before delivery, from 0.0 to 1.0.
+    pub fn conflation_rate(&self) -> f64 {
+        if self.published == 0 {
+            return 0.0;
+        }
+        self.conflated as f64 / self.published as f64
+    }
+}
+
+/// Pending values and the order their keys became pending.
+#[derive(Debug)]
+struct Pending<K, V> {
+    values: HashMap<K, V>,
+    order: VecDeque<K>,
+    stats: ConflationStats,
+}
+
+/// Function extracting the conflation key of a value.
+type KeyExtractor<K, V> = Box<dyn Fn(&V) -> Option<K> + Send + Sync>;
+
+struct Shared<K, V> {
+    pending: Mutex<Pending<K, V>>,
+    ready: Condvar,
+    extractor: KeyExtractor<K, V>,
+}
+
+/// Map keeping the latest undelivered value per key, shared by a producer and a consumer.
+///
+/// Clones share the same map.
+pub struct ConflatingMap<K, V = Packet> {
+    shared: Arc<Shared<K, V>>,
+}
+
+impl<K, V> Clone for ConflatingMap<K, V> {
+    fn clone(&self) -> Self {
+        ConflatingMap {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for ConflatingMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pending = self.lock();
+        f.debug_struct("ConflatingMap")
+            .field("pending", &pending.order.len())
+            .field("stats", &pending.stats)
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> ConflatingMap<K, V> {
+    /// Create an empty map keying values with `extractor`.
+    ///
+    /// Values for which `extractor` returns `None` are discarded and counted in
+    /// [`ConflationStats::unkeyed`].
+    pub fn new<F>(extractor: F) -> Self
+    where
+        F: Fn(&V) -> Option<K> + Send + Sync + 'static,
+    {
+        ConflatingMap {
+            shared: Arc::new(Shared {
+                pending: Mutex::new(Pending {
+                    values: HashMap::new(),
+                    order: VecDeque::new(),
+                    stats: ConflationStats::default(),
+                }),
+                ready: Condvar::new(),
+                extractor: Box::new(extractor),
+            }),
+        }
+    }
+
+    /// Publish `value`, replacing the pending value of its key if there is one.
+    ///
+    /// Returns `true` if a pending value was replaced.
+    pub fn publish(&self, value: V) -> bool {
+        let Some(key) = (self.shared.extractor)(&value) else {
+            self.lock().stats.unkeyed += 1;
+            return false;
+        };
+
+        let mut pending = self.lock();
+        pending.stats.published += 1;
+        let replaced = match pending.values.insert(key.clone(), value) {
+            Some(_) => {
+                pending.stats.conflated += 1;
+                true
+            }
+            None => {
+                pending.order.push_back(key);
+                false
+            }
+        };
+        drop(pending);
+
+        if !replaced {
+            self.shared.ready.notify_one();
+        }
+        replaced
+    }
+
+    /// Take the latest value of the key pending the longest, if any.
+    pub fn try_recv(&self) -> Option<V> {
+        Self::pop(&mut self.lock()).map(|(_, value)| value)
+    }
+
+    /// Take the latest value of the key pending the longest, waiting up to `timeout_nano`
+    /// nanoseconds (`None` waits forever).
+    ///
+    /// Returns `None` on timeout.
+    pub fn recv(&self, timeout_nano: Option<u64>) -> Option<V> {
+        self.recv_entry(timeout_nano).map(|(_, value)| value)
+    }
+
+    /// Like [`recv`](Self::recv), also returning the key of the value.
+    pub fn recv_entry(&self, timeout_nano: Option<u64>) -> Option<(K, V)> {
+        let deadline = timeout_nano.map(|t| Instant::now() + Duration::from_nanos(t));
+        let mut pending = self.lock();
+        loop {
+            if let Some(entry) = Self::pop(&mut pending) {
+                return Some(entry);
+            }
+            pending = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return None;
+                    }
+                    self.shared
+                        .ready
+                        .wait_timeout(pending, remaining)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.shared.ready.wait(pending).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+
+    /// Take every pending value, oldest key first, without waiting.
+    pub fn drain(&self) -> Vec<V> {
+        let mut pending = self.lock();
+        let mut values = Vec::with_capacity(pending.order.len());
+        while let Some((_, value)) = Self::pop(&mut pending) {
+            values.push(value);
+        }
+        values
+    }
+
+    /// Get the number of keys with a pending value.
+    pub fn pending(&self) -> usize {
+        self.lock().order.len()
+    }
+
+    /// Get the counters since the map was created.
+    pub fn stats(&self) -> ConflationStats {
+        self.lock().stats
+    }
+
+    fn pop(pending: &mut Pending<K, V>) -> Option<(K, V)> {
+        let key = pending.order.pop_front()?;
+        let value = pending.values.remove(&key).expect("pending key has a value");
+        pending.stats.delivered += 1;
+        Some((key, value))
+    }
+}
+
+impl<K, V> ConflatingMap<K, V> {
+    fn lock(&self) -> MutexGuard<'_, Pending<K, V>> {
+        self.shared.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Move `socket` onto a new thread, pinned to `cores` when not empty, that publishes every
+/// received packet into `map`.
+///
+/// The thread stops when asked, when a receive fails or at the first packet after every
+/// other handle of `map` is dropped.
+pub fn spawn_receiver<K>(
+    socket: VmaUdpSocket,
+    map: ConflatingMap<K, Packet>,
+    cores: &[c_int],
+) -> Result<ReceiverThread, Error>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+{
+    spsc::spawn_receiver_with(socket, cores, "vma-conflate-rx", move |packet| {
+        map.publish(packet);
+        Arc::strong_count(&map.shared) > 1
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conflating_map() {
+        let map = ConflatingMap::new(|update: &(char, u32)| (update.0 != '?').then_some(update.0));
+        assert!(!map.publish(('a', 1)));
+        assert!(!map.publish(('b', 1)));
+        assert!(map.publish(('a', 2)));
+        assert!(map.publish(('a', 3)));
+        assert!(!map.publish(('?', 0)));
+        assert_eq!(map.pending(), 2);
+
+        // Latest value per key, in the order the keys first arrived
+        assert_eq!(map.recv_entry(Some(0)), Some(('a', ('a', 3))));
+        assert!(!map.publish(('a', 4)));
+        assert_eq!(map.drain(), vec![('b', 1), ('a', 4)]);
+        assert_eq!(map.recv(Some(1_000_000)), None);
+
+        let stats = map.stats();
+        assert_eq!(stats, ConflationStats { published: 5, delivered: 3, conflated: 2, unkeyed: 1 });
+        assert!((stats.conflation_rate() - 0.4).abs() < 1e-9);
+
+        // A consumer waiting on another thread is woken by a publish
+        let producer = map.clone();
+        let publisher = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            producer.publish(('c', 7));
+        });
+        assert_eq!(map.recv(Some(1_000_000_000)), Some(('c', 7)));
+        publisher.join().unwrap();
+    }
+}
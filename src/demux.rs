@@ -0,0 +1,294 @@
+//! Many UDP sockets served by one thread.
+//!
+//! A [`Demux`] owns a set of bound [`VmaUdpSocket`]s, one per local port (e.g. 50
+//! multicast feeds), and polls them round-robin with non-blocking receives from a single
+//! thread, handing each packet to the handler registered for its port. This replaces a
+//! thread per feed, or an epoll registration per socket, with one loop on one pinned
+//! core. Every pass takes at most [`DEMUX_PASS_BURST`] packets from a socket and starts
+//! one socket further than the previous pass, so a busy feed cannot starve the others.
+//!
+//! Between idle passes the loop follows `VmaOptions::wait_strategy`: `Spin` keeps polling,
+//! `SpinYield` yields the CPU once nothing arrived for `spin_budget_us`, and `SpinPark`
+//! and `Adaptive` then block in a single `poll(2)` over all the sockets. Sharing one VMA
+//! ring between the sockets (`RingAllocLogic::PerUserId` with the same `ring_user_id`)
+//! keeps the polling on one completion queue.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::net::Ipv4Addr;
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::demux::Demux;
+//! use vma_socket::shutdown::ShutdownToken;
+//!
+//! let mut options = VmaOptions::low_latency();
+//! options.set_cores(&[3]).unwrap();
+//!
+//! let mut demux = Demux::new(options).unwrap();
+//! let interface = Ipv4Addr::new(10, 0, 0, 5);
+//! for feed in 0..50u16 {
+//!     let group = Ipv4Addr::new(239, 1, 1, feed as u8);
+//!     demux
+//!         .add_multicast(group, 30000 + feed, interface, move |packet: &vma_socket::udp::Packet| {
+//!             println!("feed {}: {} bytes", feed, packet.data.len());
+//!         })
+//!         .unwrap();
+//! }
+//!
+//! let token = ShutdownToken::ctrlc().unwrap();
+//! let mut demux = demux.spawn(token).unwrap().join().unwrap().unwrap();
+//! for (port, stats) in demux.stats().unwrap() {
+//!     println!("{}: {} packets", port, stats.rx_packets);
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::os::fd::AsRawFd;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::common::{self, SocketStats, VmaOptions, WaitStrategy};
+use crate::shutdown::{ShutdownToken, SHUTDOWN_POLL_NS};
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// Most packets taken from one socket in a pass, before moving to the next socket.
+pub const DEMUX_PASS_BURST: usize = 16;
+
+/// Receive buffer size used when `VmaOptions::buffer_size` is not set.
+const DEFAULT_DEMUX_BUFFER: usize = 65536;
+
+/// Handler called with every packet received on one port.
+type Handler = Box<dyn FnMut(&Packet) + Send>;
+
+/// A socket of the demultiplexer and the handler of its port.
+struct Feed {
+    port: u16,
+    socket: VmaUdpSocket,
+    handler: Handler,
+}
+
+/// A set of UDP sockets, one per local port, polled from a single thread.
+pub struct Demux {
+    options: VmaOptions,
+    feeds: Vec<Feed>,
+    buffer: Vec<u8>,
+    next: usize,
+}
+
+impl std::fmt::Debug for Demux {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Demux").field("options", &self.options).field("ports", &self.ports()).finish()
+    }
+}
+
+impl Demux {
+    /// Create an empty demultiplexer whose sockets are created with `options`.
+    ///
+    /// The first core of `options.cpu_cores`, if any, is the one [`Demux::run`] pins to.
+    pub fn new(options: VmaOptions) -> Result<Self, Error> {
+        options.validate()?;
+        let buffer_size = if options.buffer_size > 0 { options.buffer_size as usize } else { DEFAULT_DEMUX_BUFFER };
+        Ok(Demux { options, feeds: Vec::new(), buffer: vec![0u8; buffer_size], next: 0 })
+    }
+
+    /// Bind a socket to `addr:port` and deliver its packets to `handler`.
+    ///
+    /// Port 0 binds an ephemeral port. Returns the bound port.
+    pub fn add<A, H>(&mut self, addr: A, port: u16, handler: H) -> Result<u16, Error>
+    where
+        A: Into<String>,
+        H: FnMut(&Packet) + Send + 'static,
+    {
+        self.check_free(port)?;
+        let mut socket = VmaUdpSocket::with_options(self.options)?;
+        socket.bind(addr.into().as_str(), port)?;
+        self.add_socket(socket, handler)
+    }
+
+    /// Join the multicast `group` on `interface`, receive it on `port` and deliver its
+    /// packets to `handler`.
+    ///
+    /// The socket is bound to `0.0.0.0:port` with SO_REUSEPORT, so other processes can
+    /// receive the same feed. Returns the bound port.
+    pub fn add_multicast<H>(&mut self, group: Ipv4Addr, port: u16, interface: Ipv4Addr, handler: H) -> Result<u16, Error>
+    where
+        H: FnMut(&Packet) + Send + 'static,
+    {
+        if !group.is_multicast() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} is not a multicast group", group)));
+        }
+        self.check_free(port)?;
+        let mut socket = VmaUdpSocket::with_options(self.options)?;
+        socket.set_reuse_port(true)?;
+        socket.bind("0.0.0.0", port)?;
+        socket.join_multicast_v4(&group, &interface)?;
+        self.add_socket(socket, handler)
+    }
+
+    /// Take over a bound socket and deliver its packets to `handler`.
+    ///
+    /// Fails with `ErrorKind::AlreadyExists` if another socket has the same local port.
+    /// Returns the port.
+    pub fn add_socket<H>(&mut self, socket: VmaUdpSocket, handler: H) -> Result<u16, Error>
+    where
+        H: FnMut(&Packet) + Send + 'static,
+    {
+        let port = socket.local_addr()?.port();
+        self.check_free(port)?;
+        self.feeds.push(Feed { port, socket, handler: Box::new(handler) });
+        Ok(port)
+    }
+
+    /// Remove the socket of `port` and its handler, handing the socket back.
+    pub fn remove(&mut self, port: u16) -> Option<VmaUdpSocket> {
+        let index = self.feeds.iter().position(|feed| feed.port == port)?;
+        Some(self.feeds.remove(index).socket)
+    }
+
+    /// Get the ports served, in the order they are polled.
+    pub fn ports(&self) -> Vec<u16> {
+        self.feeds.iter().map(|feed| feed.port).collect()
+    }
+
+    /// Get the number of sockets.
+    pub fn len(&self) -> usize {
+        self.feeds.len()
+    }
+
+    /// Check whether there are no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.feeds.is_empty()
+    }
+
+    /// Get the local address of the socket serving `port`.
+    pub fn local_addr(&self, port: u16) -> Option<SocketAddr> {
+        self.feeds.iter().find(|feed| feed.port == port)?.socket.local_addr().ok()
+    }
+
+    /// Poll every socket once without blocking and dispatch what they received.
+    ///
+    /// Returns the number of packets dispatched.
+    pub fn poll(&mut self) -> Result<usize, Error> {
+        let count = self.feeds.len();
+        let mut dispatched = 0;
+        for offset in 0..count {
+            let feed = &mut self.feeds[(self.next + offset) % count];
+            for _ in 0..DEMUX_PASS_BURST {
+                match feed.socket.recv_from(&mut self.buffer, Some(0))? {
+                    Some(packet) => {
+                        (feed.handler)(&packet);
+                        dispatched += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        if count > 0 {
+            self.next = (self.next + 1) % count;
+        }
+        Ok(dispatched)
+    }
+
+    /// Poll the sockets until `token` is triggered, on the calling thread.
+    ///
+    /// Pins the thread to the first core of `options.cpu_cores`, if any.
+    pub fn run(&mut self, token: &ShutdownToken) -> Result<(), Error> {
+        if let Some(&core) = self.options.get_cores().first() {
+            common::pin_current_thread_to(&[core])?;
+        }
+        let budget = Duration::from_micros(self.options.spin_budget_us as u64);
+        let mut idle_since = Instant::now();
+        while !token.is_triggered() {
+            if self.poll()? > 0 {
+                idle_since = Instant::now();
+                continue;
+            }
+            match self.options.wait_strategy {
+                WaitStrategy::Spin => std::hint::spin_loop(),
+                _ if idle_since.elapsed() < budget => std::hint::spin_loop(),
+                WaitStrategy::SpinYield => thread::yield_now(),
+                WaitStrategy::SpinPark | WaitStrategy::Adaptive => self.park()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the demultiplexer on a thread of its own until `token` is triggered.
+    ///
+    /// The thread hands the demultiplexer back when it stops, for its counters.
+    pub fn spawn(mut self, token: ShutdownToken) -> Result<JoinHandle<Result<Demux, Error>>, Error> {
+        thread::Builder::new().name("vma-demux".to_string()).spawn(move || {
+            self.run(&token)?;
+            Ok(self)
+        })
+    }
+
+    /// Get the counters of every socket, by port.
+    pub fn stats(&mut self) -> Result<Vec<(u16, SocketStats)>, Error> {
+        self.feeds.iter_mut().map(|feed| Ok((feed.port, feed.socket.stats()?))).collect()
+    }
+
+    /// Fail with `ErrorKind::AlreadyExists` if a socket already serves `port` (0 is always free).
+    fn check_free(&self, port: u16) -> Result<(), Error> {
+        if port != 0 && self.feeds.iter().any(|feed| feed.port == port) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("Port {} is already served", port)));
+        }
+        Ok(())
+    }
+
+    /// Block until a socket is readable, for at most one shutdown poll interval.
+    fn park(&self) -> Result<(), Error> {
+        let mut fds: Vec<libc::pollfd> = self
+            .feeds
+            .iter()
+            .map(|feed| libc::pollfd { fd: feed.socket.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+            .collect();
+        let timeout_ms = (SHUTDOWN_POLL_NS / 1_000_000) as libc::c_int;
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) } < 0 {
+            let error = Error::last_os_error();
+            if error.kind() != ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_demux_dispatch() {
+        let options = VmaOptions { wait_strategy: WaitStrategy::SpinPark, ..Default::default() };
+        let mut demux = Demux::new(options).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut ports = Vec::new();
+        for feed in 0..3u8 {
+            let tx = tx.clone();
+            let handler = move |packet: &Packet| tx.send((feed, packet.data.clone())).unwrap();
+            ports.push(demux.add("127.0.0.1", 0, handler).unwrap());
+        }
+        assert_eq!(demux.ports(), ports);
+        let duplicate = demux.add("127.0.0.1", ports[0], |_: &Packet| {});
+        assert_eq!(duplicate.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+        let mut sender = VmaUdpSocket::new().unwrap();
+        for (feed, &port) in ports.iter().enumerate().rev() {
+            sender.send_to(&[feed as u8; 4], "127.0.0.1", port).unwrap();
+        }
+        let token = ShutdownToken::new();
+        let handle = demux.spawn(token.clone()).unwrap();
+        let mut received: Vec<_> = (0..3).map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap()).collect();
+        received.sort();
+        assert_eq!(received, (0..3u8).map(|feed| (feed, vec![feed; 4])).collect::<Vec<_>>());
+
+        token.trigger();
+        let mut demux = handle.join().unwrap().unwrap();
+        assert!(demux.stats().unwrap().iter().all(|(_, stats)| stats.rx_packets == 1));
+        assert!(demux.remove(ports[1]).is_some());
+        assert_eq!(demux.len(), 2);
+    }
+}
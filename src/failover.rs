@@ -0,0 +1,372 @@
+//! Dual-interface failover for multicast feeds.
+//!
+//! Colocated feed handlers usually receive the same multicast group on two NICs cabled to
+//! different switches. A [`FailoverReceiver`] binds one [`VmaUdpSocket`] per interface to
+//! the group and delivers the packets of the active feed only. When the active feed stays
+//! silent for the configured window while the other one is receiving, it switches over and
+//! reports a [`FailoverEvent`] through a callback or a bounded channel.
+//!
+//! The standby socket is drained on every call so its buffer never overflows; its packets
+//! are discarded, including those it received just before a switch. The receiver does not
+//! fail back by itself: it stays on the new feed until that one goes silent in turn, or
+//! until [`FailoverReceiver::switch_to`] is called.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::net::Ipv4Addr;
+//! use std::time::Duration;
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::failover::FailoverReceiver;
+//!
+//! let mut receiver = FailoverReceiver::new(
+//!     Ipv4Addr::new(239, 1, 1, 1),
+//!     5001,
+//!     Ipv4Addr::new(10, 0, 0, 2), // primary NIC
+//!     Ipv4Addr::new(10, 1, 0, 2), // backup NIC
+//!     Duration::from_millis(50),
+//!     VmaOptions::low_latency(),
+//! ).unwrap();
+//! receiver.on_failover(|event| eprintln!("{:?} -> {:?} after {:?}", event.from, event.to, event.silent_for));
+//!
+//! let mut buffer = [0u8; 65536];
+//! loop {
+//!     if let Some(packet) = receiver.recv_from(&mut buffer, Some(100_000_000)).unwrap() {
+//!         println!("{} bytes from {}", packet.data.len(), packet.src_addr);
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::Ipv4Addr;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::time::{Duration, Instant};
+
+use crate::common::{MsgFlags, VmaOptions};
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// One of the two feeds of a [`FailoverReceiver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feed {
+    /// Feed on the primary interface, active at start
+    Primary,
+    /// Feed on the backup interface
+    Backup,
+}
+
+impl Feed {
+    /// Get the other feed.
+    pub fn other(self) -> Feed {
+        match self {
+            Feed::Primary => Feed::Backup,
+            Feed::Backup => Feed::Primary,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A switch of the active feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverEvent {
+    /// Feed that was abandoned
+    pub from: Feed,
+    /// Feed now delivering packets
+    pub to: Feed,
+    /// Silence of the abandoned feed at the switch (zero for a manual switch of a live feed)
+    pub silent_for: Duration,
+}
+
+/// Callback of [`FailoverReceiver::on_failover`].
+struct FailoverHook(Box<dyn FnMut(FailoverEvent) + Send>);
+
+impl fmt::Debug for FailoverHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FailoverHook")
+    }
+}
+
+/// Activity of both feeds and the switching decision.
+#[derive(Debug, Clone)]
+struct Arbiter {
+    active: Feed,
+    silence: Duration,
+    started: Instant,
+    last_rx: [Option<Instant>; 2],
+    failovers: u64,
+}
+
+impl Arbiter {
+    fn new(silence: Duration, now: Instant) -> Self {
+        Arbiter {
+            active: Feed::Primary,
+            silence,
+            started: now,
+            last_rx: [None; 2],
+            failovers: 0,
+        }
+    }
+
+    fn record(&mut self, feed: Feed, now: Instant) {
+        self.last_rx[feed.index()] = Some(now);
+    }
+
+    /// Time since `feed` last received, or since start if it never did.
+    fn silent_for(&self, feed: Feed, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_rx[feed.index()].unwrap_or(self.started))
+    }
+
+    /// Switch to the standby feed if the active one is silent and the standby is not.
+    fn check(&mut self, now: Instant) -> Option<FailoverEvent> {
+        let silent_for = self.silent_for(self.active, now);
+        let standby = self.active.other();
+        let standby_alive = self.last_rx[standby.index()].is_some() && self.silent_for(standby, now) <= self.silence;
+        if silent_for <= self.silence || !standby_alive {
+            return None;
+        }
+        Some(self.switch_to(standby, now))
+    }
+
+    fn switch_to(&mut self, feed: Feed, now: Instant) -> FailoverEvent {
+        let event = FailoverEvent {
+            from: self.active,
+            to: feed,
+            silent_for: self.silent_for(self.active, now),
+        };
+        self.active = feed;
+        self.failovers += 1;
+        event
+    }
+}
+
+/// A multicast group received on two interfaces, delivering the packets of one at a time.
+#[derive(Debug)]
+pub struct FailoverReceiver {
+    sockets: [VmaUdpSocket; 2],
+    interfaces: [Ipv4Addr; 2],
+    group: Ipv4Addr,
+    arbiter: Arbiter,
+    hook: Option<FailoverHook>,
+    event_tx: Option<SyncSender<FailoverEvent>>,
+}
+
+impl FailoverReceiver {
+    /// Receive `group:port` on the interfaces with addresses `primary` and `backup`.
+    ///
+    /// The primary feed is active first. The active feed is abandoned once it received
+    /// nothing for `silence` while the other feed received within the same window. Each
+    /// socket only receives the group on its own interface (IP_MULTICAST_ALL disabled).
+    pub fn new(
+        group: Ipv4Addr,
+        port: u16,
+        primary: Ipv4Addr,
+        backup: Ipv4Addr,
+        silence: Duration,
+        options: VmaOptions,
+    ) -> Result<Self, Error> {
+        if !group.is_multicast() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Not a multicast group address"));
+        }
+
+        let primary_socket = Self::join(group, port, primary, options)?;
+        // Port 0 binds the backup to the port the primary was assigned
+        let port = primary_socket.local_addr()?.port();
+        let backup_socket = Self::join(group, port, backup, options)?;
+
+        Ok(FailoverReceiver {
+            sockets: [primary_socket, backup_socket],
+            interfaces: [primary, backup],
+            group,
+            arbiter: Arbiter::new(silence, Instant::now()),
+            hook: None,
+            event_tx: None,
+        })
+    }
+
+    fn join(group: Ipv4Addr, port: u16, interface: Ipv4Addr, options: VmaOptions) -> Result<VmaUdpSocket, Error> {
+        let mut socket = VmaUdpSocket::with_options(options)?;
+        socket.set_reuse_port(true)?;
+        socket.set_multicast_all_v4(false)?;
+        socket.bind(group.to_string(), port)?;
+        socket.join_multicast_v4(&group, &interface)?;
+        Ok(socket)
+    }
+
+    /// Register a callback invoked on every switch of the active feed.
+    ///
+    /// The callback runs on the thread calling `recv_from()`, so it should return quickly.
+    pub fn on_failover<F>(&mut self, callback: F)
+    where
+        F: FnMut(FailoverEvent) + Send + 'static,
+    {
+        self.hook = Some(FailoverHook(Box::new(callback)));
+    }
+
+    /// Subscribe to switches of the active feed through a bounded channel.
+    ///
+    /// Replaces any previous subscription. Events are dropped rather than blocking the
+    /// receiver when the channel is full.
+    pub fn failover_events(&mut self, capacity: usize) -> Receiver<FailoverEvent> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        self.event_tx = Some(tx);
+        rx
+    }
+
+    /// Receive the next packet of the active feed.
+    ///
+    /// Drains the standby feed and switches over when the active one has gone silent.
+    /// Returns `None` when nothing arrived on the active feed within the timeout.
+    pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, Error> {
+        let deadline = timeout_nano.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        loop {
+            let active = self.arbiter.active;
+            self.drain(active.other(), buffer)?;
+            if let Some(packet) = self.sockets[active.index()].recv_from_with_flags(buffer, None, MsgFlags::DONTWAIT)? {
+                self.arbiter.record(active, Instant::now());
+                return Ok(Some(packet));
+            }
+
+            let now = Instant::now();
+            if let Some(event) = self.arbiter.check(now) {
+                self.emit(event);
+                continue;
+            }
+
+            let remaining = match deadline {
+                Some(deadline) if now >= deadline => return Ok(None),
+                Some(deadline) => Some(deadline - now),
+                None => None,
+            };
+            self.wait_readable(remaining)?;
+        }
+    }
+
+    /// Read and discard everything queued on `feed`, recording its activity.
+    fn drain(&mut self, feed: Feed, buffer: &mut [u8]) -> Result<(), Error> {
+        let mut received = false;
+        while self.sockets[feed.index()].recv_from_with_flags(buffer, None, MsgFlags::DONTWAIT)?.is_some() {
+            received = true;
+        }
+        if received {
+            self.arbiter.record(feed, Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Wait until either socket is readable or `timeout` elapsed.
+    fn wait_readable(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        let mut fds = self.sockets.each_ref().map(|socket| libc::pollfd {
+            fd: socket.fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        let timeout_ms = timeout.map_or(-1, |timeout| timeout.as_millis().clamp(1, i32::MAX as u128) as i32);
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) } < 0 {
+            let error = Error::last_os_error();
+            if error.kind() != ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Deliver a switch to the callback and the event channel.
+    fn emit(&mut self, event: FailoverEvent) {
+        if let Some(hook) = self.hook.as_mut() {
+            (hook.0)(event);
+        }
+        if let Some(tx) = &self.event_tx {
+            if let Err(TrySendError::Disconnected(_)) = tx.try_send(event) {
+                self.event_tx = None;
+            }
+        }
+    }
+
+    /// Make `feed` the active feed, e.g. to fail back to the primary once it is repaired.
+    ///
+    /// Reports a [`FailoverEvent`] when the active feed changes.
+    pub fn switch_to(&mut self, feed: Feed) {
+        if feed != self.arbiter.active {
+            let event = self.arbiter.switch_to(feed, Instant::now());
+            self.emit(event);
+        }
+    }
+
+    /// Get the feed whose packets are delivered.
+    pub fn active(&self) -> Feed {
+        self.arbiter.active
+    }
+
+    /// Get the number of switches of the active feed so far.
+    pub fn failovers(&self) -> u64 {
+        self.arbiter.failovers
+    }
+
+    /// Get the time since `feed` last received a packet (or since creation if it never did).
+    pub fn silent_for(&self, feed: Feed) -> Duration {
+        self.arbiter.silent_for(feed, Instant::now())
+    }
+
+    /// Get the silence window after which the active feed is abandoned.
+    pub fn silence(&self) -> Duration {
+        self.arbiter.silence
+    }
+
+    /// Get the multicast group.
+    pub fn group(&self) -> Ipv4Addr {
+        self.group
+    }
+
+    /// Get the address of the interface `feed` is received on.
+    pub fn interface(&self, feed: Feed) -> Ipv4Addr {
+        self.interfaces[feed.index()]
+    }
+
+    /// Get the socket of `feed`, e.g. for its counters.
+    pub fn socket(&self, feed: Feed) -> &VmaUdpSocket {
+        &self.sockets[feed.index()]
+    }
+
+    /// Get the socket of `feed` mutably, e.g. to read `stats()`.
+    pub fn socket_mut(&mut self, feed: Feed) -> &mut VmaUdpSocket {
+        &mut self.sockets[feed.index()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arbiter() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut arbiter = Arbiter::new(Duration::from_millis(50), start);
+
+        // Nothing switches while the standby has not received anything
+        assert_eq!(arbiter.check(at(100)), None);
+
+        arbiter.record(Feed::Primary, at(100));
+        arbiter.record(Feed::Backup, at(100));
+        assert_eq!(arbiter.check(at(140)), None);
+
+        // Primary silent past the window, backup alive
+        arbiter.record(Feed::Backup, at(160));
+        let event = arbiter.check(at(170)).unwrap();
+        assert_eq!(event, FailoverEvent { from: Feed::Primary, to: Feed::Backup, silent_for: Duration::from_millis(70) });
+        assert_eq!(arbiter.active, Feed::Backup);
+
+        // No fail back while the backup keeps receiving
+        arbiter.record(Feed::Primary, at(200));
+        arbiter.record(Feed::Backup, at(200));
+        assert_eq!(arbiter.check(at(240)), None);
+
+        // Both silent: stay on the backup
+        assert_eq!(arbiter.check(at(400)), None);
+        assert_eq!(arbiter.active, Feed::Backup);
+        assert_eq!(arbiter.failovers, 1);
+    }
+}
@@ -0,0 +1,434 @@
+//! Deterministic fault injection for testing recovery logic.
+//!
+//! Enabled with the `faulty` feature. A [`Faulty`] wraps a transport and applies a
+//! [`FaultSchedule`] to its sends and another to its receives: chosen packets are dropped,
+//! duplicated, delayed or truncated, and chosen operations fail with `WouldBlock` or a
+//! closed connection. Schedules name packets by index, so a failure pattern replays
+//! identically on every run and reconnect, gap-recovery and retry paths can be tested
+//! against it.
+//!
+//! `Faulty` implements [`FrameTransport`] and [`HeartbeatSession`] when the wrapped
+//! transport does, so it slots under [`FramedTcp`](crate::framed::FramedTcp) and the
+//! session layer; a packet there is one `write_all` (a whole frame) or one read.
+//! `Faulty<VmaUdpSocket>` mirrors the datagram `send` / `recv` of the socket.
+//!
+//! Once a [`Fault::Closed`] fires, every later operation fails with
+//! `ErrorKind::ConnectionReset` until [`Faulty::reset`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::faulty::{Fault, FaultSchedule, Faulty};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5001).unwrap();
+//!
+//! // Lose every 100th datagram and the 1000th one ends the connection
+//! let schedule = FaultSchedule::new()
+//!     .every(100, Fault::Drop)
+//!     .at(999, Fault::Closed);
+//! let mut socket = Faulty::new(socket).with_send_faults(schedule);
+//!
+//! for seq in 0u64.. {
+//!     if let Err(e) = socket.send(&seq.to_le_bytes()) {
+//!         println!("closed after {} datagrams: {}", seq, e);
+//!         break;
+//!     }
+//! }
+//! println!("{:?}", socket.fault_stats());
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::framed::FrameTransport;
+use crate::heartbeat::HeartbeatSession;
+use crate::udp::VmaUdpSocket;
+
+/// What happens to a scheduled packet or operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The packet is lost: a send reports success, a receive skips it
+    Drop,
+    /// The packet is delivered twice
+    Duplicate,
+    /// The packet is held for the given time before it is sent or returned
+    Delay(Duration),
+    /// Only the first bytes of the packet are delivered
+    Truncate(usize),
+    /// The operation fails with `ErrorKind::WouldBlock` without touching the transport
+    WouldBlock,
+    /// The connection fails with `ErrorKind::ConnectionReset`, now and afterwards
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+enum Trigger {
+    At(u64),
+    Every(u64),
+    Between(Range<u64>),
+}
+
+impl Trigger {
+    fn matches(&self, packet: u64) -> bool {
+        match self {
+            Trigger::At(at) => packet == *at,
+            Trigger::Every(period) => (packet + 1) % *period == 0,
+            Trigger::Between(range) => range.contains(&packet),
+        }
+    }
+}
+
+/// Faults by packet index, counted from 0 per direction.
+///
+/// When several rules match a packet the one added first applies.
+#[derive(Debug, Clone, Default)]
+pub struct FaultSchedule {
+    rules: Vec<(Trigger, Fault)>,
+}
+
+impl FaultSchedule {
+    /// Create a schedule without faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `fault` to packet `packet`.
+    pub fn at(mut self, packet: u64, fault: Fault) -> Self {
+        self.rules.push((Trigger::At(packet), fault));
+        self
+    }
+
+    /// Apply `fault` to every `period`-th packet (indices `period - 1`, `2 * period - 1`, ...).
+    ///
+    /// A period of 0 matches nothing.
+    pub fn every(mut self, period: u64, fault: Fault) -> Self {
+        if period > 0 {
+            self.rules.push((Trigger::Every(period), fault));
+        }
+        self
+    }
+
+    /// Apply `fault` to every packet in `packets`, e.g. a burst of losses.
+    pub fn between(mut self, packets: Range<u64>, fault: Fault) -> Self {
+        self.rules.push((Trigger::Between(packets), fault));
+        self
+    }
+
+    /// Get the fault scheduled for packet `packet`, if any.
+    pub fn fault(&self, packet: u64) -> Option<Fault> {
+        self.rules
+            .iter()
+            .find(|(trigger, _)| trigger.matches(packet))
+            .map(|&(_, fault)| fault)
+    }
+
+    /// Check whether the schedule has no faults.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Counters of the faults a [`Faulty`] injected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub delayed: u64,
+    pub truncated: u64,
+    pub would_block: u64,
+    pub closed: u64,
+}
+
+/// Transport wrapper injecting scheduled faults.
+#[derive(Debug)]
+pub struct Faulty<T> {
+    inner: T,
+    send_faults: FaultSchedule,
+    recv_faults: FaultSchedule,
+    sent: u64,
+    received: u64,
+    /// Copy of a received packet to deliver again
+    duplicate: Option<Vec<u8>>,
+    closed: bool,
+    stats: FaultStats,
+}
+
+impl<T> Faulty<T> {
+    /// Wrap `inner` without any faults scheduled.
+    pub fn new(inner: T) -> Self {
+        Faulty {
+            inner,
+            send_faults: FaultSchedule::new(),
+            recv_faults: FaultSchedule::new(),
+            sent: 0,
+            received: 0,
+            duplicate: None,
+            closed: false,
+            stats: FaultStats::default(),
+        }
+    }
+
+    /// Set the faults applied to sent packets.
+    pub fn with_send_faults(mut self, schedule: FaultSchedule) -> Self {
+        self.send_faults = schedule;
+        self
+    }
+
+    /// Set the faults applied to received packets.
+    pub fn with_recv_faults(mut self, schedule: FaultSchedule) -> Self {
+        self.recv_faults = schedule;
+        self
+    }
+
+    /// Get the number of send operations so far, faulted ones included.
+    pub fn packets_sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// Get the number of receive operations that returned or consumed a packet.
+    pub fn packets_received(&self) -> u64 {
+        self.received
+    }
+
+    /// Get the counters of injected faults.
+    pub fn fault_stats(&self) -> FaultStats {
+        self.stats
+    }
+
+    /// Check whether a [`Fault::Closed`] has fired.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Reopen after a [`Fault::Closed`] and restart both schedules at packet 0.
+    pub fn reset(&mut self) {
+        self.sent = 0;
+        self.received = 0;
+        self.duplicate = None;
+        self.closed = false;
+        self.stats = FaultStats::default();
+    }
+
+    /// Get a reference to the wrapped transport.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped transport.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn check_open(&self) -> Result<(), Error> {
+        if self.closed {
+            return Err(Error::new(ErrorKind::ConnectionReset, "Fault injected: connection closed"));
+        }
+        Ok(())
+    }
+
+    /// Fail `WouldBlock` / `Closed` faults up front, before the transport is used.
+    fn fail_early(&mut self, fault: Option<Fault>) -> Result<(), Error> {
+        match fault {
+            Some(Fault::WouldBlock) => {
+                self.stats.would_block += 1;
+                Err(Error::new(ErrorKind::WouldBlock, "Fault injected: operation would block"))
+            }
+            Some(Fault::Closed) => {
+                self.stats.closed += 1;
+                self.closed = true;
+                self.check_open()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Send one packet through `send`, applying the next send fault.
+    fn send_packet<F>(&mut self, data: &[u8], mut send: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut T, &[u8]) -> Result<(), Error>,
+    {
+        self.check_open()?;
+        let fault = self.send_faults.fault(self.sent);
+        self.sent += 1;
+        self.fail_early(fault)?;
+
+        match fault {
+            Some(Fault::Drop) => {
+                self.stats.dropped += 1;
+                Ok(())
+            }
+            Some(Fault::Duplicate) => {
+                self.stats.duplicated += 1;
+                send(&mut self.inner, data)?;
+                send(&mut self.inner, data)
+            }
+            Some(Fault::Delay(delay)) => {
+                self.stats.delayed += 1;
+                std::thread::sleep(delay);
+                send(&mut self.inner, data)
+            }
+            Some(Fault::Truncate(len)) if len < data.len() => {
+                self.stats.truncated += 1;
+                send(&mut self.inner, &data[..len])
+            }
+            _ => send(&mut self.inner, data),
+        }
+    }
+
+    /// Receive one packet through `recv`, applying the next receive fault.
+    ///
+    /// Dropped packets are skipped by receiving again with the same timeout.
+    fn recv_packet<F>(&mut self, buffer: &mut [u8], timeout: Option<u64>, mut recv: F) -> Result<Option<usize>, Error>
+    where
+        F: FnMut(&mut T, &mut [u8], Option<u64>) -> Result<Option<usize>, Error>,
+    {
+        self.check_open()?;
+        if let Some(duplicate) = self.duplicate.take() {
+            let len = duplicate.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&duplicate[..len]);
+            return Ok(Some(len));
+        }
+
+        loop {
+            let fault = self.recv_faults.fault(self.received);
+            if matches!(fault, Some(Fault::WouldBlock | Fault::Closed)) {
+                self.received += 1;
+                self.fail_early(fault)?;
+            }
+
+            let len = match recv(&mut self.inner, buffer, timeout)? {
+                Some(len) if len > 0 => len,
+                // Timeouts and end-of-stream are not packets
+                other => return Ok(other),
+            };
+            self.received += 1;
+
+            return match fault {
+                Some(Fault::Drop) => {
+                    self.stats.dropped += 1;
+                    continue;
+                }
+                Some(Fault::Duplicate) => {
+                    self.stats.duplicated += 1;
+                    self.duplicate = Some(buffer[..len].to_vec());
+                    Ok(Some(len))
+                }
+                Some(Fault::Delay(delay)) => {
+                    self.stats.delayed += 1;
+                    std::thread::sleep(delay);
+                    Ok(Some(len))
+                }
+                Some(Fault::Truncate(truncated)) if truncated < len => {
+                    self.stats.truncated += 1;
+                    Ok(Some(truncated))
+                }
+                _ => Ok(Some(len)),
+            };
+        }
+    }
+}
+
+impl Faulty<VmaUdpSocket> {
+    /// Send a datagram to the connected peer, see [`VmaUdpSocket::send`].
+    ///
+    /// Dropped and truncated datagrams report the full length as sent.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, Error> {
+        self.send_packet(data, |socket, data| socket.send(data).map(|_| ()))?;
+        Ok(data.len())
+    }
+
+    /// Receive a datagram, see [`VmaUdpSocket::recv`]; returns 0 on timeout.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, Error> {
+        let received = self.recv_packet(buffer, timeout_nano, |socket, buffer, timeout| {
+            socket.recv(buffer, timeout).map(Some)
+        })?;
+        Ok(received.unwrap_or(0))
+    }
+}
+
+impl<T: FrameTransport> FrameTransport for Faulty<T> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.send_packet(data, T::write_all)
+    }
+
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        self.recv_packet(buffer, timeout, T::read)
+    }
+}
+
+impl<T: HeartbeatSession> HeartbeatSession for Faulty<T> {
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        self.send_packet(payload, T::send_heartbeat)
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        self.inner.last_rx_ns()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::framed::FramedTcp;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn test_send_faults() {
+        let (a, mut b) = MockTransport::datagram_pair();
+        let schedule = FaultSchedule::new()
+            .at(1, Fault::Drop)
+            .at(2, Fault::Duplicate)
+            .at(3, Fault::Truncate(2))
+            .at(4, Fault::WouldBlock)
+            .at(6, Fault::Closed);
+        let mut a = Faulty::new(a).with_send_faults(schedule);
+
+        for seq in 0u8..6 {
+            let result = a.write_all(&[seq; 4]);
+            assert_eq!(result.is_err(), seq == 4);
+        }
+        assert_eq!(a.write_all(b"gone").unwrap_err().kind(), ErrorKind::ConnectionReset);
+        assert!(a.write_all(b"gone").is_err());
+
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 8];
+        while let Some(len) = b.recv(&mut buffer, Some(1_000_000)).unwrap() {
+            received.push(buffer[..len].to_vec());
+        }
+        assert_eq!(received, [vec![0; 4], vec![2; 4], vec![2; 4], vec![3; 2], vec![5; 4]]);
+        let stats = a.fault_stats();
+        assert_eq!((stats.dropped, stats.duplicated, stats.truncated, stats.would_block, stats.closed), (1, 1, 1, 1, 1));
+
+        a.reset();
+        assert!(!a.is_closed());
+        a.write_all(b"back").unwrap();
+    }
+
+    #[test]
+    fn test_recv_faults() {
+        // Over datagrams every frame is one packet
+        let (a, b) = MockTransport::datagram_pair();
+        let mut sender = FramedTcp::new(a);
+        let schedule = FaultSchedule::new().every(3, Fault::Drop).at(0, Fault::WouldBlock);
+        let mut receiver = FramedTcp::new(Faulty::new(b).with_recv_faults(schedule));
+
+        for seq in 0u32..5 {
+            sender.send_frame(&seq.to_be_bytes()).unwrap();
+        }
+        assert_eq!(receiver.recv_frame(None).unwrap_err().kind(), ErrorKind::WouldBlock);
+        // Packets 2 and 5 are dropped: frames 1 and 4
+        for seq in [0u32, 2, 3] {
+            assert_eq!(receiver.recv_frame(None).unwrap(), Some(&seq.to_be_bytes()[..]));
+        }
+        assert_eq!(receiver.recv_frame(Some(1_000_000)).unwrap(), None);
+        assert_eq!(receiver.get_ref().fault_stats().dropped, 2);
+    }
+}
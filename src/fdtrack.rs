@@ -0,0 +1,224 @@
+//! Registry of the descriptors owned by the sockets, for catching misuse in debug builds.
+//!
+//! With `debug_assertions` enabled, every UDP socket, TCP socket and accepted [`Client`]
+//! records the descriptor it owns in a process-wide registry, and checks it on each call
+//! into the C layer:
+//!
+//! - [`live_fds`] lists the descriptors still owned, so a test or a shutdown path can
+//!   assert that every socket was dropped (leak detection).
+//! - A socket used after its descriptor number was closed outside the crate (e.g. with
+//!   `libc::close(socket.fd())`) and reopened by another socket panics instead of
+//!   silently sending, receiving or closing on the other socket's descriptor.
+//! - Dropping a connected TCP socket (in the default `CloseMode::Immediate`) or client
+//!   with unsent data in its send queue prints a warning.
+//!
+//! In release builds the registry is compiled out: the checks cost nothing and
+//! [`live_fds`] always returns an empty list.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::fdtrack;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! {
+//!     let mut socket = VmaUdpSocket::new().unwrap();
+//!     socket.bind("0.0.0.0", 5001).unwrap();
+//!     assert_eq!(fdtrack::live_fds().len(), 1);
+//! }
+//! assert!(fdtrack::live_fds().is_empty(), "leaked: {:?}", fdtrack::live_fds());
+//! ```
+//!
+//! [`Client`]: crate::tcp::Client
+
+use std::os::raw::c_int;
+use std::time::Duration;
+
+use crate::common::Protocol;
+
+/// Whether the registry is active (`debug_assertions` builds).
+pub const ENABLED: bool = cfg!(debug_assertions);
+
+/// A descriptor owned by a live socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedFd {
+    /// The descriptor
+    pub fd: c_int,
+    /// The protocol of the owning socket
+    pub protocol: Protocol,
+    /// Time since the socket took the descriptor
+    pub age: Duration,
+}
+
+/// List the descriptors owned by live sockets, oldest first (empty in release builds).
+pub fn live_fds() -> Vec<TrackedFd> {
+    #[cfg(debug_assertions)]
+    {
+        registry::live_fds()
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        Vec::new()
+    }
+}
+
+/// Tracking state of one socket, checked against the registry as its descriptor changes.
+#[derive(Debug)]
+pub(crate) struct FdTracker {
+    #[cfg(debug_assertions)]
+    inner: registry::Tracker,
+}
+
+impl FdTracker {
+    /// Start tracking a socket of `protocol`, currently owning `fd` (-1 if none).
+    #[allow(unused_variables)]
+    pub(crate) fn new(protocol: Protocol, fd: c_int) -> Self {
+        FdTracker {
+            #[cfg(debug_assertions)]
+            inner: registry::Tracker::new(protocol, fd),
+        }
+    }
+
+    /// Check the socket's current descriptor `fd` before a call, following a descriptor
+    /// replaced by the C layer (reconnect, rebind, close).
+    ///
+    /// Panics if `fd` is also the last descriptor seen but another socket has reopened it since.
+    #[allow(unused_variables)]
+    #[inline]
+    pub(crate) fn observe(&self, fd: c_int) {
+        #[cfg(debug_assertions)]
+        self.inner.observe(fd);
+    }
+
+    /// Stop tracking the descriptor, closed or handed out of the crate.
+    #[inline]
+    pub(crate) fn release(&self) {
+        #[cfg(debug_assertions)]
+        self.inner.release();
+    }
+}
+
+/// Warn that a connected socket is dropped with `pending` unsent bytes (debug builds only).
+#[allow(unused_variables)]
+pub(crate) fn warn_unsent(fd: c_int, pending: usize) {
+    #[cfg(debug_assertions)]
+    eprintln!(
+        "vma_socket: TCP fd {} dropped while connected with {} unsent bytes; \
+         use close_graceful() or CloseMode::Graceful to deliver them",
+        fd, pending
+    );
+}
+
+#[cfg(debug_assertions)]
+mod registry {
+    use std::collections::HashMap;
+    use std::os::raw::c_int;
+    use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+    use std::sync::{Mutex, MutexGuard, OnceLock};
+    use std::time::Instant;
+
+    use super::TrackedFd;
+    use crate::common::Protocol;
+
+    #[derive(Debug)]
+    struct Entry {
+        owner: u64,
+        protocol: Protocol,
+        since: Instant,
+    }
+
+    static NEXT_OWNER: AtomicU64 = AtomicU64::new(1);
+
+    fn entries() -> MutexGuard<'static, HashMap<c_int, Entry>> {
+        static ENTRIES: OnceLock<Mutex<HashMap<c_int, Entry>>> = OnceLock::new();
+        let entries = ENTRIES.get_or_init(Default::default);
+        entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(super) fn live_fds() -> Vec<TrackedFd> {
+        let mut fds: Vec<_> = entries()
+            .iter()
+            .map(|(&fd, entry)| (entry.since, TrackedFd { fd, protocol: entry.protocol, age: entry.since.elapsed() }))
+            .collect();
+        fds.sort_by_key(|(since, _)| *since);
+        fds.into_iter().map(|(_, fd)| fd).collect()
+    }
+
+    #[derive(Debug)]
+    pub(super) struct Tracker {
+        owner: u64,
+        protocol: Protocol,
+        fd: AtomicI32,
+    }
+
+    impl Tracker {
+        pub(super) fn new(protocol: Protocol, fd: c_int) -> Self {
+            let tracker = Tracker {
+                owner: NEXT_OWNER.fetch_add(1, Ordering::Relaxed),
+                protocol,
+                fd: AtomicI32::new(-1),
+            };
+            tracker.observe(fd);
+            tracker
+        }
+
+        pub(super) fn observe(&self, fd: c_int) {
+            let last = self.fd.swap(fd, Ordering::AcqRel);
+            let mut entries = entries();
+            if fd == last {
+                match entries.get(&fd) {
+                    Some(entry) if entry.owner != self.owner && !std::thread::panicking() => {
+                        drop(entries);
+                        panic!(
+                            "vma_socket: {:?} socket used after its fd {} was closed outside the crate \
+                             and reopened by another socket",
+                            self.protocol, fd
+                        );
+                    }
+                    _ => return,
+                }
+            }
+            if last >= 0 && entries.get(&last).is_some_and(|entry| entry.owner == self.owner) {
+                entries.remove(&last);
+            }
+            if fd >= 0 {
+                // The kernel just handed out `fd`, so any previous owner has lost it
+                entries.insert(fd, Entry { owner: self.owner, protocol: self.protocol, since: Instant::now() });
+            }
+        }
+
+        pub(super) fn release(&self) {
+            let last = self.fd.swap(-1, Ordering::AcqRel);
+            let mut entries = entries();
+            if last >= 0 && entries.get(&last).is_some_and(|entry| entry.owner == self.owner) {
+                entries.remove(&last);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod test {
+    use super::*;
+    use crate::udp::VmaUdpSocket;
+
+    #[test]
+    fn test_fd_registry() {
+        let socket = VmaUdpSocket::new().unwrap();
+        let fd = socket.fd();
+        let tracked = live_fds().into_iter().find(|tracked| tracked.fd == fd).unwrap();
+        assert_eq!(tracked.protocol, Protocol::Udp);
+        drop(socket);
+
+        // A second tracker reopening the descriptor number takes it over
+        let first = FdTracker::new(Protocol::Tcp, 100_000);
+        let second = FdTracker::new(Protocol::Udp, 100_000);
+        let misuse = std::panic::catch_unwind(|| first.observe(100_000));
+        assert!(misuse.is_err());
+        second.observe(100_000);
+        first.release();
+        assert!(live_fds().iter().any(|tracked| tracked.fd == 100_000));
+        second.release();
+        assert!(live_fds().iter().all(|tracked| tracked.fd != 100_000));
+    }
+}
@@ -0,0 +1,190 @@
+//! Length-prefixed message framing over TCP.
+//!
+//! TCP delivers a byte stream, so message boundaries are lost. A [`FramedTcp`] prefixes
+//! each frame with its length (4 bytes, big-endian) and reassembles complete frames on
+//! receipt, however the stream was split into segments. Both ends of a connection can
+//! be framed: a connecting [`VmaTcpSocket`] and an accepted [`Client`] implement
+//! [`FrameTransport`].
+//!
+//! With the `serde` feature, frames can carry typed messages (see the `message` module).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::framed::FramedTcp;
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let mut framed = FramedTcp::new(socket);
+//! framed.send_frame(b"subscribe AAPL").unwrap();
+//! if let Some(frame) = framed.recv_frame(Some(100_000_000)).unwrap() {
+//!     println!("reply of {} bytes", frame.len());
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+use crate::tcp::{Client, TcpResult, VmaTcpSocket};
+
+/// Size of the length prefix of each frame.
+pub const FRAME_HEADER_LEN: usize = 4;
+
+/// Default upper bound of a frame payload (16 MiB), protecting against corrupt prefixes.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Bytes requested from the socket per receive call.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Byte stream a [`FramedTcp`] runs over.
+pub trait FrameTransport {
+    /// Write all of `data`, waiting for room in the send buffer as needed.
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// Read into `buffer`, returning `Ok(None)` on timeout and `Ok(Some(0))` at end-of-stream.
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error>;
+}
+
+impl FrameTransport for VmaTcpSocket {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        let sent = self.send_timeout(data, None)?;
+        if sent < data.len() {
+            return Err(Error::new(ErrorKind::WriteZero, "Frame only partially sent"));
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        self.recv_checked(buffer, timeout)
+    }
+}
+
+impl FrameTransport for Client {
+    fn write_all(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        while !data.is_empty() {
+            match self.send(data) {
+                Ok(0) | Err(TcpResult::TcpErrorWouldBlock) => {
+                    self.poll_writable(None)?;
+                }
+                Ok(bytes) => data = &data[bytes..],
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        match self.recv(buffer, timeout) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(TcpResult::TcpErrorTimeout) => Ok(None), // timeout is not an error
+            Err(TcpResult::TcpErrorClosed) => Ok(Some(0)), // peer sent FIN
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// A TCP stream exchanging length-prefixed frames.
+#[derive(Debug)]
+pub struct FramedTcp<S = VmaTcpSocket> {
+    socket: S,
+    max_frame_len: usize,
+    read_buf: Vec<u8>,
+    /// Bytes of `read_buf` taken by the frame last returned, dropped on the next receive
+    consumed: usize,
+    write_buf: Vec<u8>,
+}
+
+impl<S: FrameTransport> FramedTcp<S> {
+    /// Wrap a connected socket.
+    pub fn new(socket: S) -> Self {
+        FramedTcp {
+            socket,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            read_buf: Vec::with_capacity(READ_CHUNK),
+            consumed: 0,
+            write_buf: Vec::new(),
+        }
+    }
+
+    /// Set the largest accepted frame payload; larger frames fail with `ErrorKind::InvalidData`.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len.min(u32::MAX as usize);
+        self
+    }
+
+    /// Get the largest accepted frame payload.
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Get the underlying socket.
+    pub fn get_ref(&self) -> &S {
+        &self.socket
+    }
+
+    /// Get the underlying socket mutably (reading from it directly breaks the framing).
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// Unwrap the socket, discarding any partially received frame.
+    pub fn into_inner(self) -> S {
+        self.socket
+    }
+
+    /// Send `payload` as one frame, waiting until all of it is written.
+    pub fn send_frame(&mut self, payload: &[u8]) -> Result<(), Error> {
+        if payload.len() > self.max_frame_len {
+            return Err(Error::new(ErrorKind::InvalidInput, "Frame exceeds the maximum frame length"));
+        }
+
+        // One send for header and payload, so small frames leave in a single segment
+        self.write_buf.clear();
+        self.write_buf.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        self.write_buf.extend_from_slice(payload);
+        self.socket.write_all(&self.write_buf)
+    }
+
+    /// Receive the next frame, waiting up to `timeout` nanoseconds in total.
+    ///
+    /// Returns `Ok(None)` on timeout; a partially received frame is kept for the next call.
+    /// The returned payload borrows the internal buffer until the next receive. A closed
+    /// connection fails with `ErrorKind::UnexpectedEof`, a length prefix above the maximum
+    /// with `ErrorKind::InvalidData`.
+    pub fn recv_frame(&mut self, timeout: Option<u64>) -> Result<Option<&[u8]>, Error> {
+        self.read_buf.drain(..self.consumed);
+        self.consumed = 0;
+
+        let deadline = timeout.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        loop {
+            if let Some(frame_len) = self.buffered_frame_len()? {
+                self.consumed = FRAME_HEADER_LEN + frame_len;
+                return Ok(Some(&self.read_buf[FRAME_HEADER_LEN..self.consumed]));
+            }
+
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+            let filled = self.read_buf.len();
+            self.read_buf.resize(filled + READ_CHUNK, 0);
+            let received = self.socket.read(&mut self.read_buf[filled..], remaining);
+            self.read_buf.truncate(filled + received.as_ref().ok().copied().flatten().unwrap_or(0));
+
+            match received? {
+                Some(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Connection closed")),
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Get the payload length of the frame at the start of the buffer, once complete.
+    fn buffered_frame_len(&self) -> Result<Option<usize>, Error> {
+        let Some(header) = self.read_buf.get(..FRAME_HEADER_LEN) else { return Ok(None) };
+        let frame_len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if frame_len > self.max_frame_len {
+            return Err(Error::new(ErrorKind::InvalidData, "Frame exceeds the maximum frame length"));
+        }
+        Ok((self.read_buf.len() >= FRAME_HEADER_LEN + frame_len).then_some(frame_len))
+    }
+}
@@ -0,0 +1,304 @@
+//! Application-level heartbeats for long-lived sessions.
+//!
+//! Exchange sessions expect a keepalive message after every interval without outbound
+//! traffic and declare the peer dead after a period of inbound silence. A [`Heartbeat`]
+//! tracks both on the thread that owns the session: call [`Heartbeat::poll`] from the
+//! event loop, and it sends a heartbeat when one is due and reports the session dead once
+//! nothing was received for the staleness threshold.
+//!
+//! Inbound traffic is detected from the socket's own receive timestamp, so the receive
+//! path needs no extra calls. Outbound traffic can postpone the next heartbeat with
+//! [`Heartbeat::record_sent`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use vma_socket::heartbeat::{Heartbeat, HeartbeatStatus};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let mut heartbeat = Heartbeat::new(Duration::from_secs(1), Duration::from_secs(3))
+//!     .with_payload(|payload| payload.extend_from_slice(b"35=0\x01"));
+//!
+//! let mut buffer = [0u8; 4096];
+//! loop {
+//!     // Wake up in time for the next heartbeat
+//!     let _ = socket.recv_checked(&mut buffer, Some(heartbeat.next_timeout()));
+//!     if let HeartbeatStatus::Dead { silent_for } = heartbeat.poll(&mut socket).unwrap() {
+//!         eprintln!("peer silent for {:?}, reconnecting", silent_for);
+//!         break;
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::framed::{FrameTransport, FramedTcp};
+use crate::tcp::{Client, VmaTcpSocket};
+use crate::udp::VmaUdpSocket;
+
+/// Sessions a [`Heartbeat`] can run on.
+pub trait HeartbeatSession {
+    /// Send one heartbeat message.
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error>;
+
+    /// Get the time of the last receive in nanoseconds since the epoch (0 if none).
+    fn last_rx_ns(&mut self) -> Result<u64, Error>;
+}
+
+impl HeartbeatSession for VmaTcpSocket {
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let sent = self.send_timeout(payload, None)?;
+        if sent < payload.len() {
+            return Err(Error::new(ErrorKind::WriteZero, "Heartbeat only partially sent"));
+        }
+        Ok(())
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        Ok(self.stats()?.last_rx_ns)
+    }
+}
+
+impl HeartbeatSession for Client {
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        FrameTransport::write_all(self, payload)
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        Ok(self.stats().last_rx_ns)
+    }
+}
+
+impl HeartbeatSession for VmaUdpSocket {
+    /// Send the heartbeat as one datagram to the connected address.
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        self.send(payload).map(|_| ())
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        Ok(self.stats()?.last_rx_ns)
+    }
+}
+
+impl<S: FrameTransport + HeartbeatSession> HeartbeatSession for FramedTcp<S> {
+    /// Send the heartbeat as one frame.
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        self.send_frame(payload)
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        self.get_mut().last_rx_ns()
+    }
+}
+
+/// Outcome of a [`Heartbeat::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatStatus {
+    /// Nothing was due
+    Alive,
+    /// A heartbeat was sent
+    Sent,
+    /// Nothing was received for longer than the staleness threshold
+    Dead {
+        /// Time since the last inbound traffic
+        silent_for: Duration,
+    },
+}
+
+/// Payload generator of a [`Heartbeat`].
+type PayloadFn = Box<dyn FnMut(&mut Vec<u8>) + Send>;
+
+/// Heartbeat sender and inbound-silence detector for one session.
+pub struct Heartbeat {
+    interval: Duration,
+    stale_after: Duration,
+    payload_fn: Option<PayloadFn>,
+    payload: Vec<u8>,
+    next_send: Instant,
+    /// Inbound activity known so far, in nanoseconds since the epoch
+    last_rx_ns: u64,
+    dead: bool,
+    sent: u64,
+}
+
+impl fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("interval", &self.interval)
+            .field("stale_after", &self.stale_after)
+            .field("next_send", &self.next_send)
+            .field("last_rx_ns", &self.last_rx_ns)
+            .field("dead", &self.dead)
+            .field("sent", &self.sent)
+            .finish()
+    }
+}
+
+impl Heartbeat {
+    /// Send a heartbeat every `interval` and declare the session dead after `stale_after`
+    /// without inbound traffic.
+    ///
+    /// The default payload is empty, which suits framed TCP and UDP; a raw TCP stream needs
+    /// [`with_payload`](Self::with_payload), as an empty send transmits nothing.
+    pub fn new(interval: Duration, stale_after: Duration) -> Self {
+        Heartbeat {
+            interval,
+            stale_after,
+            payload_fn: None,
+            payload: Vec::new(),
+            next_send: Instant::now() + interval,
+            last_rx_ns: wall_clock_ns(),
+            dead: false,
+            sent: 0,
+        }
+    }
+
+    /// Generate each heartbeat payload with `payload_fn`, e.g. to stamp a sequence number.
+    ///
+    /// The function appends to an empty buffer reused across heartbeats.
+    pub fn with_payload<F>(mut self, payload_fn: F) -> Self
+    where
+        F: FnMut(&mut Vec<u8>) + Send + 'static,
+    {
+        self.payload_fn = Some(Box::new(payload_fn));
+        self
+    }
+
+    /// Get the heartbeat interval.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Get the inbound staleness threshold.
+    pub fn stale_after(&self) -> Duration {
+        self.stale_after
+    }
+
+    /// Get the number of heartbeats sent.
+    pub fn heartbeats_sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// Check whether the session has been declared dead.
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Postpone the next heartbeat by a full interval after sending application traffic.
+    pub fn record_sent(&mut self) {
+        self.next_send = Instant::now() + self.interval;
+    }
+
+    /// Count inbound traffic the socket's receive timestamp does not see (e.g. on another socket).
+    pub fn record_received(&mut self) {
+        self.last_rx_ns = self.last_rx_ns.max(wall_clock_ns());
+    }
+
+    /// Start over after the session was re-established: not dead, full intervals ahead.
+    pub fn reset(&mut self) {
+        self.dead = false;
+        self.next_send = Instant::now() + self.interval;
+        self.last_rx_ns = wall_clock_ns();
+    }
+
+    /// Get the time until the next heartbeat is due, in nanoseconds (0 when overdue).
+    ///
+    /// Suited as the timeout of the session's receive call so `poll` runs in time.
+    pub fn next_timeout(&self) -> u64 {
+        self.next_send.saturating_duration_since(Instant::now()).as_nanos() as u64
+    }
+
+    /// Check the session: declare it dead after too long a silence, else send a heartbeat if due.
+    ///
+    /// Once dead, every call reports `Dead` without sending until [`reset`](Self::reset).
+    pub fn poll<S: HeartbeatSession>(&mut self, session: &mut S) -> Result<HeartbeatStatus, Error> {
+        let now_ns = wall_clock_ns();
+        self.last_rx_ns = self.last_rx_ns.max(session.last_rx_ns()?);
+        let silent_for = Duration::from_nanos(now_ns.saturating_sub(self.last_rx_ns));
+        if self.dead || silent_for > self.stale_after {
+            self.dead = true;
+            return Ok(HeartbeatStatus::Dead { silent_for });
+        }
+
+        let now = Instant::now();
+        if now < self.next_send {
+            return Ok(HeartbeatStatus::Alive);
+        }
+
+        self.payload.clear();
+        if let Some(payload_fn) = self.payload_fn.as_mut() {
+            payload_fn(&mut self.payload);
+        }
+        session.send_heartbeat(&self.payload)?;
+        self.sent += 1;
+        self.next_send = now + self.interval;
+        Ok(HeartbeatStatus::Sent)
+    }
+}
+
+/// Current wall-clock time in nanoseconds, on the clock of `SocketStats::last_rx_ns`.
+fn wall_clock_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Session {
+        sent: Vec<Vec<u8>>,
+        last_rx_ns: u64,
+    }
+
+    impl HeartbeatSession for Session {
+        fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+            self.sent.push(payload.to_vec());
+            Ok(())
+        }
+
+        fn last_rx_ns(&mut self) -> Result<u64, Error> {
+            Ok(self.last_rx_ns)
+        }
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let mut sequence = 0u8;
+        let mut heartbeat = Heartbeat::new(Duration::from_millis(20), Duration::from_millis(60))
+            .with_payload(move |payload| {
+                sequence += 1;
+                payload.push(sequence);
+            });
+        let mut session = Session::default();
+
+        assert_eq!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Alive);
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Sent);
+        assert_eq!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Alive);
+
+        // Inbound traffic keeps the session alive past the threshold
+        std::thread::sleep(Duration::from_millis(40));
+        session.last_rx_ns = wall_clock_ns();
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Sent);
+        assert_eq!(session.sent, [[1], [2]]);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(matches!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Dead { silent_for } if silent_for > Duration::from_millis(60)));
+        assert!(heartbeat.is_dead());
+
+        heartbeat.reset();
+        assert_eq!(heartbeat.poll(&mut session).unwrap(), HeartbeatStatus::Alive);
+        assert_eq!(heartbeat.heartbeats_sent(), 2);
+    }
+}
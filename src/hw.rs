@@ -0,0 +1,444 @@
+//! RDMA device and capability enumeration.
+//!
+//! VMA only offloads traffic through the ports of RDMA-capable NICs (Mellanox/NVIDIA
+//! ConnectX). [`rdma_interfaces`] lists every port of every RDMA device from sysfs
+//! (`/sys/class/infiniband`), with the network interface it backs, its state and link
+//! speed, the NUMA node of the device and its timestamping capabilities, so an
+//! application can pick the interface to bind (e.g. the active port on the NUMA node of
+//! its pinned cores) and [`require_rdma`] fails fast on hosts without any.
+//!
+//! [`nic_counters`] snapshots the drop, discard and pause frame counters of an interface,
+//! from the kernel and from the driver (`ethtool -S`), so gaps seen by a socket can be
+//! matched with losses on the NIC.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::hw;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let ports = hw::require_rdma().expect("no active RDMA port on this host");
+//! let port = ports
+//!     .iter()
+//!     .find(|port| port.numa_node == Some(0) && port.timestamping.is_hardware())
+//!     .unwrap_or(&ports[0]);
+//! println!("using {}", port);
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind(&port.ipv4.expect("port has an IPv4 address").to_string(), 5001).unwrap();
+//!
+//! let ifname = port.ifname.as_deref().unwrap();
+//! let before = hw::nic_counters(ifname).unwrap();
+//! // ... receive, spot a sequence gap
+//! let lost = hw::nic_counters(ifname).unwrap().delta_since(&before);
+//! if lost.rx_discards > 0 || lost.rx_missed > 0 {
+//!     eprintln!("gap matches NIC drops: {:?}", lost);
+//! }
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::Ipv4Addr;
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+
+/// Where the kernel lists RDMA devices.
+const RDMA_CLASS: &str = "/sys/class/infiniband";
+
+/// Where the kernel lists network interfaces.
+const NET_CLASS: &str = "/sys/class/net";
+
+/// Length of a driver statistic name, including the NUL (ETH_GSTRING_LEN).
+const STAT_NAME_LEN: usize = 32;
+
+/// Driver statistics summed into each [`NicCounters`] field; names differ between drivers.
+const RX_DISCARD_STATS: &[&str] = &["rx_discards_phy", "rx_out_of_buffer", "rx_discards"];
+const TX_DISCARD_STATS: &[&str] = &["tx_discards_phy", "tx_discards"];
+const RX_PAUSE_STATS: &[&str] = &["rx_pause_ctrl_phy", "rx_pause", "rx_flow_control_xoff"];
+const TX_PAUSE_STATS: &[&str] = &["tx_pause_ctrl_phy", "tx_pause", "tx_flow_control_xoff"];
+
+/// Timestamping capabilities, the layout matches `hw_ts_info_t` in `hw.h`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HwTsInfo {
+    pub(crate) so_timestamping: u32,
+    pub(crate) phc_index: i32,
+    pub(crate) tx_types: u32,
+    pub(crate) rx_filters: u32,
+}
+
+/// One driver statistic, the layout matches `hw_stat_t` in `hw.h`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HwStat {
+    pub(crate) name: [c_char; STAT_NAME_LEN],
+    pub(crate) value: u64,
+}
+
+extern "C" {
+    fn hw_get_ts_info(ifname: *const c_char, info: *mut HwTsInfo) -> c_int;
+    fn hw_get_ipv4_addr(ifname: *const c_char, addr: *mut u32) -> c_int;
+    fn hw_get_ethtool_stats(ifname: *const c_char, stats: *mut HwStat, capacity: u32) -> c_int;
+}
+
+/// Timestamping capabilities of a network interface (`ethtool -T`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamping {
+    /// The NIC timestamps received packets
+    pub hardware_rx: bool,
+    /// The NIC timestamps sent packets
+    pub hardware_tx: bool,
+    /// Index of the PTP hardware clock (`/dev/ptpN`), if any
+    pub phc_index: Option<i32>,
+}
+
+impl Timestamping {
+    /// Check whether the NIC timestamps packets in both directions.
+    pub fn is_hardware(&self) -> bool {
+        self.hardware_rx && self.hardware_tx
+    }
+
+    fn from_info(info: &HwTsInfo) -> Self {
+        let flags = info.so_timestamping;
+        Timestamping {
+            hardware_rx: flags & libc::SOF_TIMESTAMPING_RX_HARDWARE != 0,
+            hardware_tx: flags & libc::SOF_TIMESTAMPING_TX_HARDWARE != 0,
+            phc_index: (info.phc_index >= 0).then_some(info.phc_index),
+        }
+    }
+}
+
+/// One port of an RDMA device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdmaInterface {
+    /// RDMA device name (e.g. `mlx5_0`)
+    pub device: String,
+    /// Port number on the device, from 1
+    pub port: u32,
+    /// Network interface backed by the port (e.g. `ens1f0np0`), if any
+    pub ifname: Option<String>,
+    /// `Ethernet` (RoCE) or `InfiniBand`
+    pub link_layer: String,
+    /// Whether the port is ACTIVE
+    pub active: bool,
+    /// Link speed in Mb/s, if known
+    pub speed_mbps: Option<u32>,
+    /// NUMA node of the device, if the platform reports one
+    pub numa_node: Option<u32>,
+    /// Timestamping capabilities of the network interface
+    pub timestamping: Timestamping,
+    /// First IPv4 address of the network interface, to bind to
+    pub ipv4: Option<Ipv4Addr>,
+}
+
+impl fmt::Display for RdmaInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.device, self.port)?;
+        if let Some(ifname) = &self.ifname {
+            write!(f, " ({})", ifname)?;
+        }
+        write!(f, " {} {}", self.link_layer, if self.active { "active" } else { "down" })?;
+        if let Some(speed) = self.speed_mbps {
+            write!(f, " {} Mb/s", speed)?;
+        }
+        if let Some(node) = self.numa_node {
+            write!(f, " numa {}", node)?;
+        }
+        if self.timestamping.is_hardware() {
+            write!(f, " hw-timestamps")?;
+        }
+        Ok(())
+    }
+}
+
+/// List every port of every RDMA device, ordered by device and port.
+///
+/// Returns an empty list on hosts without RDMA devices.
+pub fn rdma_interfaces() -> Result<Vec<RdmaInterface>, Error> {
+    let mut interfaces = scan(Path::new(RDMA_CLASS), Path::new(NET_CLASS))?;
+    for interface in &mut interfaces {
+        if let Some(ifname) = &interface.ifname {
+            interface.timestamping = ts_info(ifname).map(|info| Timestamping::from_info(&info)).unwrap_or_default();
+            interface.ipv4 = ipv4_addr(ifname);
+        }
+    }
+    Ok(interfaces)
+}
+
+/// List the active RDMA ports, failing with `ErrorKind::NotFound` if there are none.
+pub fn require_rdma() -> Result<Vec<RdmaInterface>, Error> {
+    let active: Vec<_> = rdma_interfaces()?.into_iter().filter(|interface| interface.active).collect();
+    if active.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound, "No active RDMA port on this host; VMA cannot offload"));
+    }
+    Ok(active)
+}
+
+/// Find the RDMA port backing the network interface `ifname`.
+pub fn interface_by_name(ifname: &str) -> Result<Option<RdmaInterface>, Error> {
+    Ok(rdma_interfaces()?.into_iter().find(|interface| interface.ifname.as_deref() == Some(ifname)))
+}
+
+/// Drop, discard and pause frame counters of a network interface, from [`nic_counters`].
+///
+/// The kernel counters are always present; the driver counters are 0 when the driver
+/// reports none of the statistics they are read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NicCounters {
+    /// Network interface name
+    pub ifname: String,
+    /// Received packets dropped by the kernel stack (`rx_dropped`)
+    pub rx_dropped: u64,
+    /// Packets dropped before transmission by the kernel stack (`tx_dropped`)
+    pub tx_dropped: u64,
+    /// Received packets the NIC missed for lack of host buffers (`rx_missed_errors`)
+    pub rx_missed: u64,
+    /// Received packets discarded by the NIC (`rx_discards_phy`, `rx_out_of_buffer`, ...)
+    pub rx_discards: u64,
+    /// Packets discarded by the NIC before transmission (`tx_discards_phy`, ...)
+    pub tx_discards: u64,
+    /// Pause frames received, the link partner asking us to stop sending
+    pub rx_pause: u64,
+    /// Pause frames sent, the NIC asking the link partner to stop sending
+    pub tx_pause: u64,
+    /// Every driver statistic, in driver order (`ethtool -S`)
+    pub driver: Vec<(String, u64)>,
+}
+
+impl NicCounters {
+    /// Get a driver statistic by name.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.driver.iter().find(|(stat, _)| stat == name).map(|&(_, value)| value)
+    }
+
+    /// Get the counters accumulated since `earlier`.
+    ///
+    /// Subtraction wraps, so a counter that overflowed in between still yields its increase.
+    pub fn delta_since(&self, earlier: &NicCounters) -> NicCounters {
+        NicCounters {
+            ifname: self.ifname.clone(),
+            rx_dropped: self.rx_dropped.wrapping_sub(earlier.rx_dropped),
+            tx_dropped: self.tx_dropped.wrapping_sub(earlier.tx_dropped),
+            rx_missed: self.rx_missed.wrapping_sub(earlier.rx_missed),
+            rx_discards: self.rx_discards.wrapping_sub(earlier.rx_discards),
+            tx_discards: self.tx_discards.wrapping_sub(earlier.tx_discards),
+            rx_pause: self.rx_pause.wrapping_sub(earlier.rx_pause),
+            tx_pause: self.tx_pause.wrapping_sub(earlier.tx_pause),
+            driver: self
+                .driver
+                .iter()
+                .map(|(name, value)| (name.clone(), value.wrapping_sub(earlier.get(name).unwrap_or(0))))
+                .collect(),
+        }
+    }
+}
+
+/// Snapshot the drop, discard and pause frame counters of the network interface `ifname`.
+///
+/// Fails with `ErrorKind::NotFound` if the interface does not exist.
+pub fn nic_counters(ifname: &str) -> Result<NicCounters, Error> {
+    let driver = ethtool_stats(ifname)?;
+    counters(Path::new(NET_CLASS), ifname, driver)
+}
+
+/// Build the counters of `ifname` from the kernel statistics under `net_class`.
+fn counters(net_class: &Path, ifname: &str, driver: Vec<(String, u64)>) -> Result<NicCounters, Error> {
+    let statistics = net_class.join(ifname).join("statistics");
+    if !statistics.is_dir() {
+        return Err(Error::new(ErrorKind::NotFound, format!("No network interface {}", ifname)));
+    }
+    let kernel = |name: &str| read_trimmed(&statistics.join(name)).and_then(|value| value.parse().ok()).unwrap_or(0);
+    let sum = |names: &[&str]| {
+        driver.iter().filter(|(name, _)| names.contains(&name.as_str())).map(|&(_, value)| value).sum()
+    };
+    Ok(NicCounters {
+        ifname: ifname.to_string(),
+        rx_dropped: kernel("rx_dropped"),
+        tx_dropped: kernel("tx_dropped"),
+        rx_missed: kernel("rx_missed_errors"),
+        rx_discards: sum(RX_DISCARD_STATS),
+        tx_discards: sum(TX_DISCARD_STATS),
+        rx_pause: sum(RX_PAUSE_STATS),
+        tx_pause: sum(TX_PAUSE_STATS),
+        driver,
+    })
+}
+
+/// Read the ports listed under `rdma_class`, resolving interfaces under `net_class`.
+fn scan(rdma_class: &Path, net_class: &Path) -> Result<Vec<RdmaInterface>, Error> {
+    let devices = match std::fs::read_dir(rdma_class) {
+        Ok(devices) => devices,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut interfaces = Vec::new();
+    for device in devices {
+        let device = device?;
+        let name = device.file_name().to_string_lossy().into_owned();
+        let path = device.path();
+        let numa_node = read_trimmed(&path.join("device/numa_node")).and_then(|node| node.parse().ok());
+        let netdevs: Vec<String> = std::fs::read_dir(path.join("device/net"))
+            .map(|entries| {
+                entries.filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned())).collect()
+            })
+            .unwrap_or_default();
+        let Ok(ports) = std::fs::read_dir(path.join("ports")) else { continue };
+        for port in ports {
+            let port = port?;
+            let Some(number) = port.file_name().to_str().and_then(|number| number.parse::<u32>().ok()) else {
+                continue;
+            };
+            let port_path = port.path();
+            // Multi-port devices list all their interfaces; dev_port counts ports from 0
+            let dev_port = |netdev: &String| read_trimmed(&net_class.join(netdev).join("dev_port"))?.parse::<u32>().ok();
+            let ifname = netdevs.iter().find(|netdev| dev_port(netdev) == Some(number.saturating_sub(1))).or(netdevs.first()).cloned();
+            let speed_mbps = ifname
+                .as_ref()
+                .and_then(|ifname| read_trimmed(&net_class.join(ifname).join("speed"))?.parse::<i64>().ok())
+                .filter(|&speed| speed > 0)
+                .map(|speed| speed as u32)
+                .or_else(|| parse_rate(&read_trimmed(&port_path.join("rate"))?));
+            interfaces.push(RdmaInterface {
+                device: name.clone(),
+                port: number,
+                ifname,
+                link_layer: read_trimmed(&port_path.join("link_layer")).unwrap_or_default(),
+                active: read_trimmed(&port_path.join("state")).is_some_and(|state| state.ends_with("ACTIVE")),
+                speed_mbps,
+                numa_node,
+                timestamping: Timestamping::default(),
+                ipv4: None,
+            });
+        }
+    }
+    interfaces.sort_by(|a, b| (&a.device, a.port).cmp(&(&b.device, b.port)));
+    Ok(interfaces)
+}
+
+/// Read a sysfs attribute without its trailing newline.
+fn read_trimmed(path: &Path) -> Option<String> {
+    Some(std::fs::read_to_string(path).ok()?.trim().to_string())
+}
+
+/// Parse an RDMA port rate such as `100 Gb/sec (4X EDR)` into Mb/s.
+fn parse_rate(rate: &str) -> Option<u32> {
+    let gbps: f64 = rate.split_whitespace().next()?.parse().ok()?;
+    Some((gbps * 1000.0) as u32)
+}
+
+/// Query the timestamping capabilities of `ifname`.
+fn ts_info(ifname: &str) -> Result<HwTsInfo, Error> {
+    let name = CString::new(ifname).map_err(|_| Error::new(ErrorKind::InvalidInput, "Interface name contains NUL"))?;
+    let mut info = HwTsInfo::default();
+    if unsafe { hw_get_ts_info(name.as_ptr(), &mut info) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(info)
+}
+
+/// Read the driver statistics of `ifname`, empty if the driver has none.
+fn ethtool_stats(ifname: &str) -> Result<Vec<(String, u64)>, Error> {
+    let name = CString::new(ifname).map_err(|_| Error::new(ErrorKind::InvalidInput, "Interface name contains NUL"))?;
+    let mut stats = Vec::new();
+    loop {
+        let capacity = stats.capacity() as u32;
+        let count = unsafe { hw_get_ethtool_stats(name.as_ptr(), stats.as_mut_ptr(), capacity) };
+        if count < 0 {
+            let error = Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(libc::EOPNOTSUPP) => Ok(Vec::new()),
+                Some(libc::ENODEV) => Err(Error::new(ErrorKind::NotFound, format!("No network interface {}", ifname))),
+                _ => Err(error),
+            };
+        }
+        let count = count as usize;
+        if count <= stats.capacity() {
+            // SAFETY: the C side stored the first `count` entries
+            unsafe { stats.set_len(count) };
+            break;
+        }
+        stats.reserve_exact(count);
+    }
+    Ok(stats
+        .iter()
+        .map(|stat: &HwStat| (unsafe { CStr::from_ptr(stat.name.as_ptr()) }.to_string_lossy().into_owned(), stat.value))
+        .collect())
+}
+
+/// Get the first IPv4 address of `ifname`.
+fn ipv4_addr(ifname: &str) -> Option<Ipv4Addr> {
+    let name = CString::new(ifname).ok()?;
+    let mut addr: u32 = 0;
+    (unsafe { hw_get_ipv4_addr(name.as_ptr(), &mut addr) } == 1).then(|| Ipv4Addr::from(u32::from_be(addr)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rdma_scan() {
+        let root = std::env::temp_dir().join(format!("vma-hw-{}", std::process::id()));
+        let (rdma, net) = (root.join("infiniband"), root.join("net"));
+        assert!(scan(&rdma, &net).unwrap().is_empty());
+
+        let write = |path: std::path::PathBuf, value: &str| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, value).unwrap();
+        };
+        for (port, state, dev_port) in [(1, "4: ACTIVE\n", "0\n"), (2, "1: DOWN\n", "1\n")] {
+            let port_dir = rdma.join(format!("mlx5_0/ports/{}", port));
+            write(port_dir.join("state"), state);
+            write(port_dir.join("link_layer"), "Ethernet\n");
+            write(port_dir.join("rate"), "25 Gb/sec (1X EDR)\n");
+            let ifname = format!("ens1f{}", port - 1);
+            std::fs::create_dir_all(rdma.join("mlx5_0/device/net").join(&ifname)).unwrap();
+            write(net.join(&ifname).join("dev_port"), dev_port);
+        }
+        write(rdma.join("mlx5_0/device/numa_node"), "1\n");
+        write(net.join("ens1f0/speed"), "100000\n");
+
+        let ports = scan(&rdma, &net).unwrap();
+        assert_eq!(ports.len(), 2);
+        assert_eq!((ports[0].port, ports[0].ifname.as_deref()), (1, Some("ens1f0")));
+        assert!(ports[0].active && !ports[1].active);
+        assert_eq!((ports[0].speed_mbps, ports[1].speed_mbps), (Some(100_000), Some(25_000)));
+        assert_eq!(ports[1].numa_node, Some(1));
+        assert_eq!(ports[0].to_string(), "mlx5_0/1 (ens1f0) Ethernet active 100000 Mb/s numa 1");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // Loopback has an address but no hardware timestamping
+        assert_eq!(ipv4_addr("lo"), Some(Ipv4Addr::LOCALHOST));
+        assert!(!ts_info("lo").map(|info| Timestamping::from_info(&info).is_hardware()).unwrap_or(false));
+        assert!(rdma_interfaces().is_ok());
+    }
+
+    #[test]
+    fn test_nic_counters() {
+        let net = std::env::temp_dir().join(format!("vma-nic-{}", std::process::id()));
+        assert_eq!(counters(&net, "ens1f0", Vec::new()).unwrap_err().kind(), ErrorKind::NotFound);
+        std::fs::create_dir_all(net.join("ens1f0/statistics")).unwrap();
+        std::fs::write(net.join("ens1f0/statistics/rx_dropped"), "7\n").unwrap();
+        let driver = vec![
+            ("rx_out_of_buffer".to_string(), 3),
+            ("rx_discards_phy".to_string(), 2),
+            ("tx_pause_ctrl_phy".to_string(), 5),
+        ];
+        let before = counters(&net, "ens1f0", driver).unwrap();
+        assert_eq!((before.rx_dropped, before.rx_missed), (7, 0));
+        assert_eq!((before.rx_discards, before.tx_pause, before.rx_pause), (5, 5, 0));
+        std::fs::remove_dir_all(&net).unwrap();
+
+        let mut after = before.clone();
+        after.rx_discards += 4;
+        after.driver[0].1 += 4;
+        let delta = after.delta_since(&before);
+        assert_eq!((delta.rx_discards, delta.rx_dropped), (4, 0));
+        assert_eq!(delta.get("rx_out_of_buffer"), Some(4));
+
+        let lo = nic_counters("lo").unwrap();
+        assert_eq!(lo.ifname, "lo");
+        assert_eq!(nic_counters("vma-missing0").unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
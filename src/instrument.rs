@@ -0,0 +1,330 @@
+//! Latency instrumentation for socket calls.
+//!
+//! Enabled with the `histogram` feature. [`Instrumented`] wraps a [`VmaUdpSocket`] or
+//! [`VmaTcpSocket`] and records the duration of every receive and send call that moved
+//! data into HDR histograms. For UDP, the wire-to-app delta (time between the SO_TIMESTAMPNS
+//! receive timestamp and the moment the packet is handed to the application) is recorded too;
+//! it needs `enable_timestamps` in the socket options, packets without a timestamp are skipped.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::instrument::Instrumented;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = Instrumented::new(VmaUdpSocket::new().unwrap());
+//! socket.inner_mut().bind("0.0.0.0", 5001).unwrap();
+//!
+//! let mut buffer = vec![0u8; 4096];
+//! for _ in 0..100_000 {
+//!     let _ = socket.recv_from(&mut buffer, Some(100_000_000));
+//! }
+//!
+//! let recv = socket.recv_latency();
+//! println!("recv p50={}ns p99={}ns max={}ns", recv.p50, recv.p99, recv.max);
+//! ```
+
+use std::net::SocketAddr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+
+use crate::tcp::VmaTcpSocket;
+use crate::udp::{Destination, Packet, VmaUdpSocket};
+
+/// Highest latency tracked by the histograms (60 seconds, in nanoseconds).
+const MAX_TRACKABLE_NS: u64 = 60_000_000_000;
+
+/// Number of significant value digits kept by the histograms.
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+/// Percentile summary of a latency histogram, in nanoseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySnapshot {
+    /// Number of recorded samples
+    pub count: u64,
+    /// Smallest recorded latency
+    pub min: u64,
+    /// Mean latency
+    pub mean: f64,
+    /// Median latency
+    pub p50: u64,
+    /// 90th percentile
+    pub p90: u64,
+    /// 99th percentile
+    pub p99: u64,
+    /// 99.9th percentile
+    pub p999: u64,
+    /// Largest recorded latency
+    pub max: u64,
+}
+
+/// HDR histogram of latencies in nanoseconds.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    histogram: Histogram<u64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram tracking 1ns to 60s with 3 significant digits.
+    pub fn new() -> Self {
+        LatencyHistogram {
+            histogram: Histogram::new_with_bounds(1, MAX_TRACKABLE_NS, SIGNIFICANT_DIGITS)
+                .expect("valid histogram bounds"),
+        }
+    }
+
+    /// Record a latency sample (values outside the tracked range are clamped).
+    pub fn record(&mut self, nanos: u64) {
+        self.histogram.saturating_record(nanos.max(1));
+    }
+
+    /// Discard all recorded samples.
+    pub fn reset(&mut self) {
+        self.histogram.reset();
+    }
+
+    /// Add the samples of `other`, e.g. to aggregate per-thread histograms.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        self.histogram.add(&other.histogram).expect("histograms share bounds");
+    }
+
+    /// Get the number of recorded samples.
+    pub fn len(&self) -> u64 {
+        self.histogram.len()
+    }
+
+    /// Check whether no samples were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.histogram.is_empty()
+    }
+
+    /// Get the latency at a percentile (0.0 - 100.0).
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        self.histogram.value_at_percentile(percentile)
+    }
+
+    /// Summarize the recorded samples.
+    pub fn snapshot(&self) -> LatencySnapshot {
+        if self.histogram.is_empty() {
+            return LatencySnapshot::default();
+        }
+
+        LatencySnapshot {
+            count: self.histogram.len(),
+            min: self.histogram.min(),
+            mean: self.histogram.mean(),
+            p50: self.histogram.value_at_percentile(50.0),
+            p90: self.histogram.value_at_percentile(90.0),
+            p99: self.histogram.value_at_percentile(99.0),
+            p999: self.histogram.value_at_percentile(99.9),
+            max: self.histogram.max(),
+        }
+    }
+}
+
+/// Socket wrapper recording recv/send call latencies.
+#[derive(Debug)]
+pub struct Instrumented<T> {
+    inner: T,
+    recv: LatencyHistogram,
+    send: LatencyHistogram,
+    wire_to_app: LatencyHistogram,
+}
+
+impl<T> Instrumented<T> {
+    /// Wrap a socket.
+    pub fn new(inner: T) -> Self {
+        Instrumented {
+            inner,
+            recv: LatencyHistogram::new(),
+            send: LatencyHistogram::new(),
+            wire_to_app: LatencyHistogram::new(),
+        }
+    }
+
+    /// Get a reference to the wrapped socket.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped socket (calls made through it are not recorded).
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the socket, discarding the histograms.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Latency of receive calls that returned data.
+    pub fn recv_latency(&self) -> LatencySnapshot {
+        self.recv.snapshot()
+    }
+
+    /// Latency of successful send calls.
+    pub fn send_latency(&self) -> LatencySnapshot {
+        self.send.snapshot()
+    }
+
+    /// Delta between kernel receive timestamps and delivery to the application (UDP only).
+    ///
+    /// Empty unless the socket was created with `enable_timestamps`.
+    pub fn wire_to_app_latency(&self) -> LatencySnapshot {
+        self.wire_to_app.snapshot()
+    }
+
+    /// Get the raw receive histogram.
+    pub fn recv_histogram(&self) -> &LatencyHistogram {
+        &self.recv
+    }
+
+    /// Get the raw send histogram.
+    pub fn send_histogram(&self) -> &LatencyHistogram {
+        &self.send
+    }
+
+    /// Get the raw wire-to-app histogram.
+    pub fn wire_to_app_histogram(&self) -> &LatencyHistogram {
+        &self.wire_to_app
+    }
+
+    /// Discard all recorded samples.
+    pub fn reset(&mut self) {
+        self.recv.reset();
+        self.send.reset();
+        self.wire_to_app.reset();
+    }
+
+    fn record_wire_to_app(&mut self, packet: &Packet) {
+        // No SCM_TIMESTAMPNS cmsg, nothing to measure against
+        if packet.timestamp == 0 {
+            return;
+        }
+        if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            let now = now.as_nanos() as u64;
+            self.wire_to_app.record(now.saturating_sub(packet.timestamp));
+        }
+    }
+}
+
+impl Instrumented<VmaUdpSocket> {
+    /// Receive data from the connected remote address.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let received = self.inner.recv(buffer, timeout_nano)?;
+        if received > 0 {
+            self.recv.record(start.elapsed().as_nanos() as u64);
+        }
+        Ok(received)
+    }
+
+    /// Receive data and source address information.
+    pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, std::io::Error> {
+        let start = Instant::now();
+        let packet = self.inner.recv_from(buffer, timeout_nano)?;
+        if let Some(packet) = &packet {
+            self.recv.record(start.elapsed().as_nanos() as u64);
+            self.record_wire_to_app(packet);
+        }
+        Ok(packet)
+    }
+
+    /// Send data to the connected remote address.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send(data)?;
+        self.send.record(start.elapsed().as_nanos() as u64);
+        Ok(sent)
+    }
+
+    /// Send data to a specified address and port.
+    pub fn send_to<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send_to(data, addr, port)?;
+        self.send.record(start.elapsed().as_nanos() as u64);
+        Ok(sent)
+    }
+
+    /// Send data to a destination prepared by `resolve()`.
+    pub fn send_to_dest(&mut self, data: &[u8], dest: &Destination) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send_to_dest(data, dest)?;
+        self.send.record(start.elapsed().as_nanos() as u64);
+        Ok(sent)
+    }
+
+    /// Get the local address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        self.inner.local_addr()
+    }
+}
+
+impl Instrumented<VmaTcpSocket> {
+    /// Receive data from the connected socket.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let received = self.inner.recv(buffer, timeout)?;
+        if received > 0 {
+            self.recv.record(start.elapsed().as_nanos() as u64);
+        }
+        Ok(received)
+    }
+
+    /// Send data over the connected socket.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send(data)?;
+        if sent > 0 {
+            self.send.record(start.elapsed().as_nanos() as u64);
+        }
+        Ok(sent)
+    }
+
+    /// Get the local address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_latency_snapshot() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.snapshot(), LatencySnapshot::default());
+
+        for nanos in 1..=1000 {
+            histogram.record(nanos);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 1000);
+        assert_eq!(snapshot.min, 1);
+        assert_eq!(snapshot.max, 1000);
+        assert_eq!(snapshot.p50, 500);
+        assert_eq!(snapshot.p99, 990);
+
+        // Out-of-range samples are clamped instead of dropped
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.len(), 1001);
+
+        let mut merged = LatencyHistogram::new();
+        merged.record(5000);
+        merged.merge(&histogram);
+        assert_eq!(merged.len(), 1002);
+        assert_eq!(merged.snapshot().min, 1);
+
+        histogram.reset();
+        assert!(histogram.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! Round-trip latency measurement.
+//!
+//! Enabled with the `histogram` feature. [`PingPong`] sends sequence-stamped requests over
+//! a [`RoundTrip`] transport, waits for each echo and records the round-trip time from the
+//! TSC clock into a [`LatencyHistogram`], so the report shows the tail (p99, p99.9, max)
+//! and not just the average. Echoes that do not arrive within the timeout are counted as
+//! lost; late echoes of earlier requests are skipped.
+//!
+//! The peer only has to send every request back unchanged.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use vma_socket::latency::PingPong;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5001).unwrap();
+//!
+//! let mut ping_pong = PingPong::new(64).with_warmup(10_000);
+//! let rtt = ping_pong.run_for(&mut socket, Duration::from_secs(10)).unwrap();
+//! println!(
+//!     "rtt min={}ns p50={}ns p99={}ns p99.9={}ns max={}ns, {} lost",
+//!     rtt.min, rtt.p50, rtt.p99, rtt.p999, rtt.max, ping_pong.lost()
+//! );
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+use crate::common::clock;
+use crate::instrument::{LatencyHistogram, LatencySnapshot};
+use crate::tcp::VmaTcpSocket;
+use crate::udp::VmaUdpSocket;
+
+/// Bytes at the start of each request holding its sequence number.
+pub const SEQUENCE_LEN: usize = 8;
+
+/// Default wait for an echo before the request counts as lost (1 second, in nanoseconds).
+pub const DEFAULT_ECHO_TIMEOUT: u64 = 1_000_000_000;
+
+/// Transports a [`PingPong`] can measure.
+pub trait RoundTrip {
+    /// Send one request.
+    fn send_request(&mut self, request: &[u8]) -> Result<(), Error>;
+
+    /// Receive one reply of up to `reply.len()` bytes, `Ok(None)` on timeout.
+    fn recv_reply(&mut self, reply: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error>;
+}
+
+impl RoundTrip for VmaUdpSocket {
+    /// Send the request as one datagram to the connected address.
+    fn send_request(&mut self, request: &[u8]) -> Result<(), Error> {
+        if self.send(request)? < request.len() {
+            return Err(Error::new(ErrorKind::WriteZero, "Request only partially sent"));
+        }
+        Ok(())
+    }
+
+    fn recv_reply(&mut self, reply: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        Ok(self.recv_full(reply, timeout)?.map(|length| length.copied))
+    }
+}
+
+impl RoundTrip for VmaTcpSocket {
+    fn send_request(&mut self, request: &[u8]) -> Result<(), Error> {
+        if self.send_timeout(request, None)? < request.len() {
+            return Err(Error::new(ErrorKind::WriteZero, "Request only partially sent"));
+        }
+        Ok(())
+    }
+
+    /// Read exactly `reply.len()` bytes; a timeout after part of the reply breaks the stream
+    /// and fails with `ErrorKind::TimedOut`.
+    fn recv_reply(&mut self, reply: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        let mut filled = 0;
+        while filled < reply.len() {
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+            match self.recv_checked(&mut reply[filled..], remaining)? {
+                Some(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Connection closed by peer")),
+                Some(n) => filled += n,
+                None if filled == 0 => return Ok(None),
+                None => return Err(Error::new(ErrorKind::TimedOut, "Reply only partially received")),
+            }
+        }
+        Ok(Some(filled))
+    }
+}
+
+/// Request/echo latency harness.
+#[derive(Debug)]
+pub struct PingPong {
+    request: Vec<u8>,
+    reply: Vec<u8>,
+    timeout: Option<u64>,
+    warmup: u64,
+    seq: u64,
+    lost: u64,
+    histogram: LatencyHistogram,
+}
+
+impl PingPong {
+    /// Measure with requests of `size` bytes (at least [`SEQUENCE_LEN`]).
+    pub fn new(size: usize) -> Self {
+        let size = size.max(SEQUENCE_LEN);
+        PingPong {
+            request: vec![0u8; size],
+            reply: vec![0u8; size],
+            timeout: Some(DEFAULT_ECHO_TIMEOUT),
+            warmup: 0,
+            seq: 0,
+            lost: 0,
+            histogram: LatencyHistogram::new(),
+        }
+    }
+
+    /// Run `count` unrecorded round trips before measuring, to warm caches and VMA rings.
+    pub fn with_warmup(mut self, count: u64) -> Self {
+        self.warmup = count;
+        self
+    }
+
+    /// Wait up to `timeout` nanoseconds for each echo (`None` waits forever).
+    pub fn with_timeout(mut self, timeout: Option<u64>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the request size in bytes.
+    pub fn size(&self) -> usize {
+        self.request.len()
+    }
+
+    /// Get the number of requests whose echo did not arrive in time.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Get the raw round-trip histogram.
+    pub fn histogram(&self) -> &LatencyHistogram {
+        &self.histogram
+    }
+
+    /// Summarize the recorded round trips.
+    pub fn snapshot(&self) -> LatencySnapshot {
+        self.histogram.snapshot()
+    }
+
+    /// Discard the recorded round trips and losses (warm-up is not repeated).
+    pub fn reset(&mut self) {
+        self.histogram.reset();
+        self.lost = 0;
+    }
+
+    /// Run one round trip, returning its time in nanoseconds or `None` if the echo was lost.
+    ///
+    /// The result is recorded in the histogram.
+    pub fn round_trip<T: RoundTrip>(&mut self, transport: &mut T) -> Result<Option<u64>, Error> {
+        let rtt = self.measure(transport)?;
+        match rtt {
+            Some(rtt) => self.histogram.record(rtt),
+            None => self.lost += 1,
+        }
+        Ok(rtt)
+    }
+
+    /// Run `count` recorded round trips after the warm-up.
+    pub fn run<T: RoundTrip>(&mut self, transport: &mut T, count: u64) -> Result<LatencySnapshot, Error> {
+        self.warm_up(transport)?;
+        for _ in 0..count {
+            self.round_trip(transport)?;
+        }
+        Ok(self.snapshot())
+    }
+
+    /// Run recorded round trips for `duration` after the warm-up.
+    pub fn run_for<T: RoundTrip>(&mut self, transport: &mut T, duration: Duration) -> Result<LatencySnapshot, Error> {
+        self.warm_up(transport)?;
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            self.round_trip(transport)?;
+        }
+        Ok(self.snapshot())
+    }
+
+    /// Run the pending warm-up round trips, if any; `run` and `run_for` start with this.
+    pub fn warm_up<T: RoundTrip>(&mut self, transport: &mut T) -> Result<(), Error> {
+        for _ in 0..std::mem::take(&mut self.warmup) {
+            self.measure(transport)?;
+        }
+        Ok(())
+    }
+
+    /// Send the next request and wait for its echo, skipping stale replies.
+    fn measure<T: RoundTrip>(&mut self, transport: &mut T) -> Result<Option<u64>, Error> {
+        let seq = self.seq.to_le_bytes();
+        self.seq += 1;
+        self.request[..SEQUENCE_LEN].copy_from_slice(&seq);
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        let sent_at = clock::ticks();
+        transport.send_request(&self.request)?;
+        loop {
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+            match transport.recv_reply(&mut self.reply, remaining)? {
+                Some(n) if n >= SEQUENCE_LEN && self.reply[..SEQUENCE_LEN] == seq => {
+                    return Ok(Some(clock::ticks_to_nanos(clock::ticks().saturating_sub(sent_at))));
+                }
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Echo peer answering every request after `lag` further requests, dropping every `drop_every`th.
+    struct Echo {
+        queue: VecDeque<Vec<u8>>,
+        lag: usize,
+        sent: u64,
+        drop_every: u64,
+    }
+
+    impl RoundTrip for Echo {
+        fn send_request(&mut self, request: &[u8]) -> Result<(), Error> {
+            self.sent += 1;
+            if self.sent % self.drop_every != 0 {
+                self.queue.push_back(request.to_vec());
+            }
+            Ok(())
+        }
+
+        fn recv_reply(&mut self, reply: &mut [u8], _timeout: Option<u64>) -> Result<Option<usize>, Error> {
+            if self.queue.len() <= self.lag {
+                return Ok(None);
+            }
+            let echo = self.queue.pop_front().unwrap();
+            reply[..echo.len()].copy_from_slice(&echo);
+            Ok(Some(echo.len()))
+        }
+    }
+
+    #[test]
+    fn test_ping_pong() {
+        let mut echo = Echo { queue: VecDeque::new(), lag: 0, sent: 0, drop_every: 5 };
+        let mut ping_pong = PingPong::new(2).with_warmup(3).with_timeout(Some(0));
+        assert_eq!(ping_pong.size(), SEQUENCE_LEN);
+
+        let rtt = ping_pong.run(&mut echo, 8).unwrap();
+        assert_eq!(echo.sent, 11);
+        assert_eq!(rtt.count, 6);
+        assert_eq!(ping_pong.lost(), 2);
+
+        // A stale echo ahead of the expected one is skipped
+        echo.queue.push_back(u64::MAX.to_le_bytes().to_vec());
+        assert!(ping_pong.round_trip(&mut echo).unwrap().is_some());
+
+        ping_pong.reset();
+        assert!(ping_pong.histogram().is_empty());
+        assert_eq!(ping_pong.lost(), 0);
+    }
+}
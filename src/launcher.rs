@@ -0,0 +1,108 @@
+//! Re-executing the process with libvma preloaded.
+//!
+//! VMA only accelerates sockets when `libvma.so` is loaded ahead of libc with
+//! `LD_PRELOAD`; a deployment script that forgets it runs the application on the kernel
+//! network stack without any error. Called first thing in `main`, [`ensure_preloaded`]
+//! returns at once when libvma is loaded. Otherwise it looks for libvma with
+//! [`find_libvma`] and either
+//! fails with the `LD_PRELOAD` line to use or replaces the process with a copy of itself
+//! started with that preload, the same arguments and the same environment.
+//!
+//! A re-executed process that still finds libvma missing fails instead of re-executing
+//! again, so a broken library cannot loop.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::launcher;
+//!
+//! fn main() {
+//!     // Never returns when it re-executes; fails if libvma cannot be found
+//!     launcher::ensure_preloaded(true).expect("VMA acceleration unavailable");
+//!
+//!     // ... sockets created from here on are offloaded
+//! }
+//! ```
+//!
+//! [`find_libvma`]: crate::common::find_libvma
+
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::common::{self, LIBVMA_PATH_ENV};
+
+/// Environment variable marking a process re-executed by [`ensure_preloaded`].
+const REEXEC_ENV: &str = "VMA_SOCKET_REEXEC";
+
+/// Check whether libvma is loaded in this process.
+pub fn is_preloaded() -> bool {
+    common::vma_loaded()
+}
+
+/// Make sure libvma is loaded, re-executing the process with it preloaded if `reexec`.
+///
+/// Returns `Ok(())` when libvma is loaded. Otherwise fails with `ErrorKind::NotFound`
+/// if no libvma can be found, or, when `reexec` is false, with the `LD_PRELOAD` setting
+/// to start the process with. With `reexec`, the current executable is started again
+/// with libvma prepended to `LD_PRELOAD` and the call does not return unless `exec(2)`
+/// fails. Call it before creating threads or sockets.
+pub fn ensure_preloaded(reexec: bool) -> Result<(), Error> {
+    if is_preloaded() {
+        std::env::remove_var(REEXEC_ENV);
+        return Ok(());
+    }
+    if std::env::var_os(REEXEC_ENV).is_some() {
+        return Err(Error::other(format!(
+            "libvma is still not loaded after re-executing with LD_PRELOAD={}",
+            std::env::var("LD_PRELOAD").unwrap_or_default()
+        )));
+    }
+    let report = common::find_libvma();
+    let Some(libvma) = report.path() else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("libvma is not loaded and was not found; install it or set {}", LIBVMA_PATH_ENV),
+        ));
+    };
+    let preload = preload_with(libvma);
+    if !reexec {
+        return Err(Error::other(format!(
+            "libvma is not loaded; run with LD_PRELOAD={}",
+            preload.to_string_lossy()
+        )));
+    }
+
+    let mut args = std::env::args_os();
+    let mut command = Command::new(std::env::current_exe()?);
+    if let Some(arg0) = args.next() {
+        command.arg0(arg0);
+    }
+    let error = command.args(args).env("LD_PRELOAD", preload).env(REEXEC_ENV, "1").exec();
+    Err(Error::new(error.kind(), format!("Re-executing with libvma preloaded failed: {}", error)))
+}
+
+/// Get `LD_PRELOAD` with `libvma` put first.
+fn preload_with(libvma: &Path) -> OsString {
+    let mut preload = OsString::from(libvma);
+    if let Some(existing) = std::env::var_os("LD_PRELOAD").filter(|existing| !existing.is_empty()) {
+        preload.push(":");
+        preload.push(existing);
+    }
+    preload
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ensure_preloaded() {
+        assert!(preload_with(Path::new("/opt/libvma.so")).to_string_lossy().starts_with("/opt/libvma.so"));
+        if !is_preloaded() {
+            assert!(ensure_preloaded(false).is_err());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Compile-time checks that the `#[repr(C)]` mirrors match the C structs.
+//!
+//! The expected sizes, alignments and field offsets come from `src/c/vma_layout.h`, which
+//! `vma_layout.c` checks against the C definitions. A struct that drifts on either side
+//! fails the build of that side instead of corrupting memory at runtime.
+
+#![allow(non_upper_case_globals, non_camel_case_types)]
+
+use std::mem::{align_of, offset_of, size_of};
+use std::os::raw::c_int;
+
+use crate::common::{MemAllocType, RingAllocLogic, SockAddrIn, SocketStats, ThreadMode, VmaFdStats, VmaOptions, WaitStrategy};
+use crate::hw::{HwStat, HwTsInfo};
+use crate::netmon::NetmonLink;
+use crate::raw::RawPacketInfo;
+use crate::tcp::{TcpConnectionState, TcpResult};
+use crate::udp::{UdpPacket, UdpResult};
+
+/// Layout table generated by build.rs from `vma_layout.h`.
+mod c {
+    include!(concat!(env!("OUT_DIR"), "/vma_layout.rs"));
+}
+
+macro_rules! check_layout {
+    ($rust:ty, $c:ident) => {
+        const _: () = assert!(
+            size_of::<$rust>() == c::$c::SIZE,
+            concat!(stringify!($rust), " size differs from ", stringify!($c))
+        );
+    };
+    ($rust:ty, $c:ident { $($field:ident),* $(,)? }) => {
+        check_layout!($rust, $c);
+        const _: () = assert!(
+            align_of::<$rust>() == c::$c::ALIGN,
+            concat!(stringify!($rust), " alignment differs from ", stringify!($c))
+        );
+        $(
+            const _: () = assert!(
+                offset_of!($rust, $field) == c::$c::$field,
+                concat!(stringify!($rust), ".", stringify!($field), " offset differs from ", stringify!($c))
+            );
+        )*
+    };
+}
+
+check_layout!(WaitStrategy, vma_wait_strategy_t);
+check_layout!(UdpResult, udp_result_t);
+check_layout!(TcpConnectionState, tcp_connection_state_t);
+check_layout!(TcpResult, tcp_result_t);
+
+// `ring_alloc_logic`, `thread_mode` and `mem_alloc_type` are plain ints in C
+const _: () = assert!(size_of::<RingAllocLogic>() == size_of::<c_int>());
+const _: () = assert!(size_of::<ThreadMode>() == size_of::<c_int>());
+const _: () = assert!(size_of::<MemAllocType>() == size_of::<c_int>());
+
+check_layout!(VmaOptions, vma_options_t {
+    use_socketxtreme,
+    optimize_for_latency,
+    use_polling,
+    ring_count,
+    buffer_size,
+    enable_timestamps,
+    use_hugepages,
+    tx_bufs,
+    rx_bufs,
+    disable_poll_yield,
+    skip_os_select,
+    keep_qp_full,
+    cpu_cores,
+    cpu_cores_count,
+    tcp_nodelay,
+    wait_strategy,
+    spin_budget_us,
+    ring_alloc_logic,
+    ring_user_id,
+    thread_mode,
+    rx_poll,
+    select_poll,
+    rx_poll_os_ratio,
+    mem_alloc_type,
+    rx_wre,
+    tx_wre,
+});
+
+check_layout!(SockAddrIn, sockaddr_in_t { sin_family, sin_port, sin_addr, sin_zero });
+
+check_layout!(SocketStats, socket_stats_t {
+    rx_packets,
+    tx_packets,
+    rx_bytes,
+    tx_bytes,
+    rx_errors,
+    tx_errors,
+    rx_truncated,
+    rx_eagain,
+    tx_eagain,
+    reconnects,
+    last_rx_ns,
+    last_tx_ns,
+});
+
+check_layout!(VmaFdStats, vma_fd_stats_t { vma_loaded, offloaded, ring_count });
+
+check_layout!(UdpPacket, udp_packet_t { data, length, src_addr, timestamp, dst_addr, if_index, full_length });
+
+check_layout!(NetmonLink, netmon_link_t { if_index, flags, operstate, removed, name });
+
+check_layout!(HwTsInfo, hw_ts_info_t { so_timestamping, phc_index, tx_types, rx_filters });
+
+check_layout!(HwStat, hw_stat_t { name, value });
+
+check_layout!(RawPacketInfo, raw_packet_info_t { length, full_length, timestamp, if_index, protocol, pkt_type });
@@ -19,7 +19,7 @@
 //! ```rust,no_run
 //! use vma_socket::udp::VmaUdpSocket;
 //! 
-//! fn udp_example() -> Result<(), String> {
+//! fn udp_example() -> std::io::Result<()> {
 //!     // Create a socket with default options
 //!     let mut socket = VmaUdpSocket::new()?;
 //!     
@@ -28,7 +28,7 @@
 //!     
 //!     // Receive data with timeout
 //!     let mut buffer = vec![0u8; 4096];
-//!     match socket.recv_from(&mut buffer, Some(100_000_000)) { // 100ms timeout
+//!     match socket.recv_from(&mut buffer, Some(100_000_000))? { // 100ms timeout
 //!         Some(packet) => {
 //!             println!("Received {} bytes", packet.data.len());
 //!             
@@ -49,7 +49,7 @@
 //! ```rust,no_run
 //! use vma_socket::tcp::VmaTcpSocket;
 //! 
-//! fn tcp_server_example() -> Result<(), String> {
+//! fn tcp_server_example() -> std::io::Result<()> {
 //!     // Create a TCP socket
 //!     let mut socket = VmaTcpSocket::new()?;
 //!     
@@ -79,11 +79,53 @@
 //! LD_PRELOAD=/usr/lib64/libvma.so.x.x.x ./your_application
 //! ```
 //!
+//! or call [`launcher::ensure_preloaded`] at the start of `main` to re-execute the
+//! application with libvma preloaded when it was started without it.
+//!
 //! ## Module Structure
 //!
 //! - [`udp`]: UDP socket implementation
 //! - [`tcp`]: TCP socket implementation
 //! - [`common`]: Shared types and configuration options
+//! - [`sharded`]: Multi-core UDP receiver over SO_REUSEPORT
+//! - [`demux`]: Many UDP sockets polled round-robin from one pinned thread, dispatched by port
+//! - [`subscription`]: Named multicast channels joined on demand, with per-channel rates
+//! - [`spsc`]: Lock-free bounded channel from a pinned receive thread to the application
+//! - [`conflate`]: Latest-value-per-key delivery for slow consumers of fast feeds
+//! - [`failover`]: Multicast feed received on two interfaces with failover on silence
+//! - [`netmon`]: Interface Up/Down notifications over rtnetlink
+//! - [`reporter`]: Background thread sampling socket counters at an interval
+//! - [`notify`]: Eventfd signalled when a socket becomes readable, for host epoll/io_uring loops
+//! - [`fdtrack`]: Debug-build registry of socket descriptors catching leaks and use after close
+//! - [`shutdown`]: Shutdown tokens interrupting blocking receives and accepts, e.g. on Ctrl-C
+//! - [`reconnect`]: Reconnect policies with backoff for TCP sockets
+//! - [`warm`]: VMA dummy sends keeping the TX path hot between real sends
+//! - [`registered`]: Pinned send buffers for zero-copy transmission
+//! - [`server`]: Multi-threaded TCP server with per-core workers
+//! - [`pacing`]: Token-bucket pacing of UDP sends
+//! - [`framed`]: Length-prefixed frames over TCP
+//! - [`buffered`]: Aligned, hugepage-backed read and write buffers with explicit flushes
+//! - [`heartbeat`]: Session keepalives and inbound-silence detection
+//! - [`compat`]: `std::net`-compatible UdpSocket, TcpStream and TcpListener for drop-in migration
+//! - [`mock`]: In-memory loopback transports for tests without libvma
+//! - [`launcher`]: Re-execution of the process with libvma preloaded
+//! - [`hw`]: RDMA ports with link speed, NUMA node and timestamping capabilities
+//! - [`raw`]: AF_PACKET sockets for whole Ethernet frames with BPF filters
+//! - `metrics`: Socket counter export (requires the `metrics` feature)
+//! - `instrument`: Latency histograms for recv/send calls (requires the `histogram` feature)
+//! - `slowlog`: Call timing with `tracing` warnings for slow calls (requires the `tracing` feature)
+//! - `latency`: Ping-pong round-trip latency percentiles (requires the `histogram` feature)
+//! - `owned`: `Bytes`/`BytesMut` send and receive variants (requires the `bytes` feature)
+//! - `codec`: `tokio_util::codec` encoders and decoders over TCP (requires the `codec` feature)
+//! - `stream`: `futures` Stream of received packets and a batching datagram sink (requires the `futures` feature)
+//! - `message`: Typed messages over framed TCP and UDP (requires the `serde` feature)
+//! - `archived`: Zero-copy rkyv views over received datagrams (requires the `rkyv` feature)
+//! - `compressed`: LZ4/Zstd compression of TCP frames (requires the `compression` feature)
+//! - `tls`: rustls sessions over `VmaTcpSocket` and `Client` (requires the `tls` feature)
+//! - `session`: Sequenced logon/resend session layer over framed TCP (requires the `session` feature)
+//! - `faulty`: Scheduled drops, duplicates, delays, truncation and errors (requires the `faulty` feature)
+//! - `pcap`: pcap capture of offloaded traffic to files or ring buffers (requires the `pcap` feature)
+//! - `capi`: `extern "C"` functions behind `include/vma_socket.h` (requires the `capi` feature)
 
 /// UDP socket implementation
 pub mod udp;
@@ -92,4 +134,140 @@ pub mod udp;
 pub mod tcp;
 
 /// Common types and utilities
-pub mod common;
\ No newline at end of file
+pub mod common;
+
+/// Multi-core UDP reception over SO_REUSEPORT
+pub mod sharded;
+
+/// Single-thread polling of many UDP sockets with per-port handlers
+pub mod demux;
+
+/// Multicast channel subscriptions by name, loaded from channel files
+pub mod subscription;
+
+/// Single-producer/single-consumer packet handoff between threads
+pub mod spsc;
+
+/// Conflating per-key delivery of the most recent update
+pub mod conflate;
+
+/// Dual-interface multicast reception with failover on silence
+pub mod failover;
+
+/// Link state monitoring of network interfaces
+pub mod netmon;
+
+/// Periodic socket statistics reporting
+pub mod reporter;
+
+/// Readiness eventfds for integration with external event loops
+pub mod notify;
+
+/// Descriptor ownership tracking for leak and misuse detection in debug builds
+pub mod fdtrack;
+
+/// Shutdown tokens observed by blocking socket calls
+pub mod shutdown;
+
+/// Reconnect policies and background reconnection for TCP sockets
+pub mod reconnect;
+
+/// Background dummy sends keeping the VMA TX path warm
+pub mod warm;
+
+/// Pinned, optionally hugepage-backed buffers for zero-copy sends
+pub mod registered;
+
+/// TCP server dispatching accepted clients to pinned worker threads
+pub mod server;
+
+/// Userspace packets/sec and bits/sec rate limiting with TSC busy-waits
+pub mod pacing;
+
+/// Length-prefixed message framing over TCP streams
+pub mod framed;
+
+/// Buffered TCP readers and writers with hugepage-backed buffers
+pub mod buffered;
+
+/// Application-level heartbeats and dead-peer detection
+pub mod heartbeat;
+
+/// Drop-in replacements for the `std::net` socket types
+pub mod compat;
+
+/// In-process socket pairs implementing the transport traits
+pub mod mock;
+
+/// LD_PRELOAD detection and re-execution with libvma
+pub mod launcher;
+
+/// RDMA device, port and NIC capability enumeration
+pub mod hw;
+
+/// AF_PACKET sockets for custom protocols and latency probes
+pub mod raw;
+
+// Compile-time layout checks of the structs shared with C
+#[cfg(target_pointer_width = "64")]
+mod layout;
+
+/// Socket counter export to the `metrics` facade and Prometheus
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+/// Latency histogram instrumentation for socket calls
+#[cfg(feature = "histogram")]
+pub mod instrument;
+
+/// Per-operation call timing and slow-call logging
+#[cfg(feature = "tracing")]
+pub mod slowlog;
+
+/// Round-trip latency harness reporting HDR-histogram percentiles
+#[cfg(feature = "histogram")]
+pub mod latency;
+
+/// Refcounted `Bytes` receive buffers and `Buf` sends
+#[cfg(feature = "bytes")]
+pub mod owned;
+
+/// Codec-driven framing with `Encoder` and `Decoder` traits
+#[cfg(feature = "codec")]
+pub mod codec;
+
+/// Async packet streams and datagram sinks for `futures` pipelines
+#[cfg(feature = "futures")]
+pub mod stream;
+
+/// Serde-encoded typed messages with pluggable codecs
+#[cfg(feature = "serde")]
+pub mod message;
+
+/// Validated zero-copy access to rkyv-archived datagrams
+#[cfg(feature = "rkyv")]
+pub mod archived;
+
+/// Negotiated LZ4/Zstd compression of framed TCP streams
+#[cfg(feature = "compression")]
+pub mod compressed;
+
+/// TLS client and server sessions over VMA TCP with rustls
+#[cfg(feature = "tls")]
+pub mod tls;
+
+/// FIX-style sequenced sessions with logon, heartbeats and resends
+#[cfg(feature = "session")]
+pub mod session;
+
+/// Fault-injecting transport wrapper for recovery tests
+#[cfg(feature = "faulty")]
+pub mod faulty;
+
+/// pcap taps on socket send and receive paths
+#[cfg(feature = "pcap")]
+pub mod pcap;
+
+/// Stable C ABI over the UDP and TCP sockets
+#[cfg(feature = "capi")]
+pub mod capi;
@@ -0,0 +1,179 @@
+//! Typed message exchange over framed TCP and UDP sockets.
+//!
+//! Enabled with the `serde` feature. Any `Serialize` / `DeserializeOwned` type can be sent
+//! as one message: a frame on a [`FramedTcp`] (either end of the connection), a datagram on a [`VmaUdpSocket`]. The wire
+//! format is a pluggable [`Codec`]; [`Bincode`] (compact, the default) and [`Json`]
+//! (readable, interoperable) are provided.
+//!
+//! Meant for small control-plane protocols (subscriptions, heartbeats, admin commands);
+//! each message is encoded into a scratch buffer, so hot data paths should stay on bytes.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use serde::{Deserialize, Serialize};
+//! use vma_socket::framed::FramedTcp;
+//! use vma_socket::message::Json;
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Control {
+//!     Subscribe { symbol: String },
+//!     Ack { id: u64 },
+//! }
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//! let mut framed = FramedTcp::new(socket);
+//!
+//! framed.send_msg(&Control::Subscribe { symbol: "AAPL".into() }).unwrap();
+//! if let Some(Control::Ack { id }) = framed.recv_msg::<Control>(Some(100_000_000)).unwrap() {
+//!     println!("subscribed as {}", id);
+//! }
+//!
+//! // Same exchange with a JSON peer
+//! framed.send_msg_with(&Json, &Control::Subscribe { symbol: "MSFT".into() }).unwrap();
+//! ```
+
+use std::io::{Error, ErrorKind};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::framed::{FrameTransport, FramedTcp};
+use crate::udp::VmaUdpSocket;
+
+/// Wire format of typed messages.
+pub trait Codec {
+    /// Append the encoding of `msg` to `buffer`.
+    fn encode<T: Serialize>(&self, msg: &T, buffer: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Decode one message occupying all of `bytes`.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Error>;
+}
+
+/// Compact binary encoding with `bincode` (the default codec).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T: Serialize>(&self, msg: &T, buffer: &mut Vec<u8>) -> Result<(), Error> {
+        bincode::serialize_into(buffer, msg).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Error> {
+        bincode::deserialize(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// JSON encoding with `serde_json`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize>(&self, msg: &T, buffer: &mut Vec<u8>) -> Result<(), Error> {
+        serde_json::to_writer(buffer, msg).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Error> {
+        serde_json::from_slice(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl<S: FrameTransport> FramedTcp<S> {
+    /// Send `msg` as one frame, encoded with [`Bincode`].
+    pub fn send_msg<T: Serialize>(&mut self, msg: &T) -> Result<(), Error> {
+        self.send_msg_with(&Bincode, msg)
+    }
+
+    /// Send `msg` as one frame, encoded with `codec`.
+    pub fn send_msg_with<C: Codec, T: Serialize>(&mut self, codec: &C, msg: &T) -> Result<(), Error> {
+        let mut payload = Vec::new();
+        codec.encode(msg, &mut payload)?;
+        self.send_frame(&payload)
+    }
+
+    /// Receive the next frame as a [`Bincode`] message, waiting up to `timeout` nanoseconds.
+    ///
+    /// Returns `Ok(None)` on timeout. A frame that does not decode fails with
+    /// `ErrorKind::InvalidData` and is discarded; the stream stays usable.
+    pub fn recv_msg<T: DeserializeOwned>(&mut self, timeout: Option<u64>) -> Result<Option<T>, Error> {
+        self.recv_msg_with(&Bincode, timeout)
+    }
+
+    /// Receive the next frame as a message encoded with `codec`.
+    pub fn recv_msg_with<C: Codec, T: DeserializeOwned>(&mut self, codec: &C, timeout: Option<u64>) -> Result<Option<T>, Error> {
+        match self.recv_frame(timeout)? {
+            Some(frame) => codec.decode(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl VmaUdpSocket {
+    /// Send `msg` as one datagram to the connected address, encoded with [`Bincode`].
+    pub fn send_msg<T: Serialize>(&mut self, msg: &T) -> Result<(), Error> {
+        self.send_msg_with(&Bincode, msg)
+    }
+
+    /// Send `msg` as one datagram to the connected address, encoded with `codec`.
+    ///
+    /// Messages larger than a datagram fail with the socket's send error.
+    pub fn send_msg_with<C: Codec, T: Serialize>(&mut self, codec: &C, msg: &T) -> Result<(), Error> {
+        let mut payload = Vec::new();
+        codec.encode(msg, &mut payload)?;
+        self.send(&payload).map(|_| ())
+    }
+
+    /// Receive one datagram into `buffer` and decode it as a [`Bincode`] message.
+    ///
+    /// Returns `Ok(None)` on timeout. A datagram longer than `buffer` fails with
+    /// `ErrorKind::InvalidData` instead of decoding a truncated message.
+    pub fn recv_msg<T: DeserializeOwned>(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<T>, Error> {
+        self.recv_msg_with(&Bincode, buffer, timeout_nano)
+    }
+
+    /// Receive one datagram into `buffer` and decode it with `codec`.
+    pub fn recv_msg_with<C: Codec, T: DeserializeOwned>(
+        &mut self,
+        codec: &C,
+        buffer: &mut [u8],
+        timeout_nano: Option<u64>,
+    ) -> Result<Option<T>, Error> {
+        let Some(length) = self.recv_full(buffer, timeout_nano)? else { return Ok(None) };
+        if length.is_truncated() {
+            return Err(Error::new(ErrorKind::InvalidData, "Message truncated to the receive buffer"));
+        }
+        codec.decode(&buffer[..length.copied]).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Control {
+        Subscribe { symbol: String, depth: u32 },
+        Heartbeat(u64),
+    }
+
+    fn roundtrip<C: Codec>(codec: &C) {
+        let msg = Control::Subscribe { symbol: "AAPL".into(), depth: 5 };
+        let mut buffer = Vec::new();
+        codec.encode(&msg, &mut buffer).unwrap();
+        assert_eq!(codec.decode::<Control>(&buffer).unwrap(), msg);
+
+        buffer.clear();
+        codec.encode(&Control::Heartbeat(7), &mut buffer).unwrap();
+        let err = codec.decode::<Control>(&buffer[..buffer.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_codecs() {
+        roundtrip(&Bincode);
+        roundtrip(&Json);
+    }
+}
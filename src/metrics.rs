@@ -0,0 +1,272 @@
+//! Export of socket counters to the `metrics` facade and a built-in Prometheus endpoint.
+//!
+//! Enabled with the `metrics` feature. A [`MetricsExporter`] samples [`SocketStats`] from
+//! any [`StatsSource`] (UDP or TCP socket) at a configurable interval and publishes every
+//! counter labelled with the socket's local and remote address:
+//!
+//! - to whatever recorder is installed for the [`metrics`](https://docs.rs/metrics) facade, and
+//! - to an in-process snapshot that can be served as Prometheus text with
+//!   [`MetricsExporter::serve_prometheus`].
+//!
+//! Sampling happens on the thread that owns the socket, so no socket state is shared
+//! with the exporter thread. There is no timer: the counters are only published when the
+//! application calls [`MetricsExporter::observe`], typically once per turn of its receive
+//! loop (the call returns at once until the interval has elapsed). A loop that can block
+//! indefinitely stops publishing while it waits, so give its receives a timeout no longer
+//! than the interval, as in the example below. The endpoint keeps serving the last
+//! published values in the meantime.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use vma_socket::metrics::MetricsExporter;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut exporter = MetricsExporter::new(Duration::from_secs(1));
+//! exporter.serve_prometheus("0.0.0.0:9100").unwrap();
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//!
+//! let mut buffer = vec![0u8; 4096];
+//! loop {
+//!     let _ = socket.recv_from(&mut buffer, Some(100_000_000));
+//!     // Cheap when the interval has not elapsed yet
+//!     exporter.observe(&mut socket).unwrap();
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::{SocketStats, StatsSource};
+
+/// Longest wait for a scrape request or for its response to be written (5s), so a stalled
+/// client cannot hold up the endpoint.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Labels identifying a socket in exported metrics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SocketLabels {
+    /// Local address of the socket
+    pub local: String,
+    /// Remote address of the socket (empty when not connected)
+    pub remote: String,
+}
+
+impl SocketLabels {
+    /// Build labels from a socket's addresses.
+    pub fn new(local: SocketAddr, remote: Option<SocketAddr>) -> Self {
+        SocketLabels {
+            local: local.to_string(),
+            remote: remote.map(|addr| addr.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Kind of an exported metric.
+#[derive(Clone, Copy)]
+enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+/// An exported metric.
+struct MetricDef {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    value: fn(&SocketStats) -> u64,
+}
+
+/// Every exported metric.
+const METRICS: &[MetricDef] = &[
+    MetricDef { name: "vma_socket_rx_packets", help: "Number of received packets", kind: MetricKind::Counter, value: |s| s.rx_packets },
+    MetricDef { name: "vma_socket_tx_packets", help: "Number of transmitted packets", kind: MetricKind::Counter, value: |s| s.tx_packets },
+    MetricDef { name: "vma_socket_rx_bytes", help: "Number of received bytes", kind: MetricKind::Counter, value: |s| s.rx_bytes },
+    MetricDef { name: "vma_socket_tx_bytes", help: "Number of transmitted bytes", kind: MetricKind::Counter, value: |s| s.tx_bytes },
+    MetricDef { name: "vma_socket_rx_errors", help: "Receive calls that failed with an error", kind: MetricKind::Counter, value: |s| s.rx_errors },
+    MetricDef { name: "vma_socket_tx_errors", help: "Send calls that failed with an error", kind: MetricKind::Counter, value: |s| s.tx_errors },
+    MetricDef { name: "vma_socket_rx_truncated", help: "Datagrams larger than the receive buffer", kind: MetricKind::Counter, value: |s| s.rx_truncated },
+    MetricDef { name: "vma_socket_rx_eagain", help: "Receive calls that returned EAGAIN", kind: MetricKind::Counter, value: |s| s.rx_eagain },
+    MetricDef { name: "vma_socket_tx_eagain", help: "Send calls that returned EAGAIN", kind: MetricKind::Counter, value: |s| s.tx_eagain },
+    MetricDef { name: "vma_socket_reconnects", help: "Successful reconnects", kind: MetricKind::Counter, value: |s| s.reconnects },
+    MetricDef { name: "vma_socket_last_rx_ns", help: "Time of the last successful receive (ns since epoch)", kind: MetricKind::Gauge, value: |s| s.last_rx_ns },
+    MetricDef { name: "vma_socket_last_tx_ns", help: "Time of the last successful send (ns since epoch)", kind: MetricKind::Gauge, value: |s| s.last_tx_ns },
+];
+
+/// Periodic publisher of socket counters.
+#[derive(Debug, Clone)]
+pub struct MetricsExporter {
+    interval: Duration,
+    last_publish: HashMap<SocketLabels, Instant>,
+    snapshot: Arc<Mutex<BTreeMap<SocketLabels, SocketStats>>>,
+}
+
+impl MetricsExporter {
+    /// Create an exporter that publishes each socket at most once per `interval`.
+    pub fn new(interval: Duration) -> Self {
+        MetricsExporter {
+            interval,
+            last_publish: HashMap::new(),
+            snapshot: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Sample and publish a socket's counters if its interval has elapsed.
+    ///
+    /// Must be called regularly from the thread owning the socket: nothing is published
+    /// between calls. Returns `true` if the counters were published.
+    pub fn observe<S: StatsSource>(&mut self, socket: &mut S) -> Result<bool, std::io::Error> {
+        let labels = SocketLabels::new(socket.local_addr()?, socket.peer_addr());
+        let now = Instant::now();
+        if let Some(last) = self.last_publish.get(&labels) {
+            if now.duration_since(*last) < self.interval {
+                return Ok(false);
+            }
+        }
+
+        let stats = socket.stats()?;
+        self.publish(labels.clone(), &stats);
+        self.last_publish.insert(labels, now);
+        Ok(true)
+    }
+
+    /// Publish counters for a socket unconditionally.
+    pub fn publish(&self, labels: SocketLabels, stats: &SocketStats) {
+        for metric in METRICS {
+            let metric_labels = [("local", labels.local.clone()), ("remote", labels.remote.clone())];
+            let value = (metric.value)(stats);
+            match metric.kind {
+                MetricKind::Counter => ::metrics::counter!(metric.name, &metric_labels).absolute(value),
+                MetricKind::Gauge => ::metrics::gauge!(metric.name, &metric_labels).set(value as f64),
+            }
+        }
+
+        self.snapshot.lock().unwrap().insert(labels, *stats);
+    }
+
+    /// Stop exporting a socket (e.g. after it was closed).
+    pub fn remove(&mut self, labels: &SocketLabels) {
+        self.last_publish.remove(labels);
+        self.snapshot.lock().unwrap().remove(labels);
+    }
+
+    /// Render the latest published counters in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        render(&self.snapshot.lock().unwrap())
+    }
+
+    /// Serve the latest published counters as Prometheus text over HTTP.
+    ///
+    /// Spawns a background thread answering every request on `addr` with the current
+    /// snapshot, giving up on a client after [`SCRAPE_TIMEOUT`] of silence. Returns the
+    /// address the endpoint is listening on.
+    pub fn serve_prometheus<A: ToSocketAddrs>(&self, addr: A) -> Result<SocketAddr, std::io::Error> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let snapshot = Arc::clone(&self.snapshot);
+
+        thread::Builder::new()
+            .name("vma-metrics".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { continue };
+                    if stream.set_read_timeout(Some(SCRAPE_TIMEOUT)).is_err()
+                        || stream.set_write_timeout(Some(SCRAPE_TIMEOUT)).is_err()
+                    {
+                        continue;
+                    }
+                    // The request itself is irrelevant; every path returns the metrics
+                    let mut request = [0u8; 1024];
+                    let _ = stream.read(&mut request);
+                    let body = render(&snapshot.lock().unwrap());
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                }
+            })?;
+
+        Ok(local_addr)
+    }
+}
+
+/// Render a snapshot in the Prometheus text exposition format.
+fn render(snapshot: &BTreeMap<SocketLabels, SocketStats>) -> String {
+    let mut out = String::new();
+    for metric in METRICS {
+        let kind = match metric.kind {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        };
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", metric.name, metric.help, metric.name, kind));
+        for (labels, stats) in snapshot {
+            out.push_str(&format!(
+                "{}{{local=\"{}\",remote=\"{}\"}} {}\n",
+                metric.name,
+                escape_label(&labels.local),
+                escape_label(&labels.remote),
+                (metric.value)(stats)
+            ));
+        }
+    }
+    out
+}
+
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus() {
+        let exporter = MetricsExporter::new(Duration::from_secs(1));
+        let labels = SocketLabels {
+            local: "127.0.0.1:5001".to_string(),
+            remote: String::new(),
+        };
+        let stats = SocketStats { rx_packets: 42, rx_truncated: 2, ..SocketStats::default() };
+        exporter.publish(labels.clone(), &stats);
+
+        let text = exporter.render_prometheus();
+        assert!(text.contains("# TYPE vma_socket_rx_packets counter"));
+        assert!(text.contains("vma_socket_rx_packets{local=\"127.0.0.1:5001\",remote=\"\"} 42"));
+        assert!(text.contains("vma_socket_rx_truncated{local=\"127.0.0.1:5001\",remote=\"\"} 2"));
+        assert!(text.contains("# TYPE vma_socket_last_rx_ns gauge"));
+
+        let mut exporter = exporter;
+        exporter.remove(&labels);
+        assert!(!exporter.render_prometheus().contains("127.0.0.1:5001"));
+    }
+
+    #[test]
+    fn test_serve_prometheus_skips_silent_client() {
+        use std::net::TcpStream;
+
+        let exporter = MetricsExporter::new(Duration::from_secs(1));
+        let labels = SocketLabels { local: "127.0.0.1:5002".to_string(), remote: String::new() };
+        exporter.publish(labels, &SocketStats { tx_packets: 7, ..SocketStats::default() });
+        let addr = exporter.serve_prometheus("127.0.0.1:0").unwrap();
+
+        // A client that never sends its request only delays the next one
+        let _silent = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.set_read_timeout(Some(SCRAPE_TIMEOUT * 2)).unwrap();
+        client.write_all(b"GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.contains("vma_socket_tx_packets{local=\"127.0.0.1:5002\",remote=\"\"} 7"));
+    }
+}
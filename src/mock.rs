@@ -0,0 +1,366 @@
+//! In-memory loopback transports for tests.
+//!
+//! A [`MockTransport`] pair behaves like two connected sockets without libvma, a NIC or
+//! the kernel network stack: whatever one end sends, the other receives. A
+//! [`stream_pair`](MockTransport::stream_pair) is a byte stream like TCP (reads may return
+//! part of a write, or several writes at once); a
+//! [`datagram_pair`](MockTransport::datagram_pair) keeps message boundaries like connected
+//! UDP and truncates datagrams larger than the receive buffer.
+//!
+//! The ends implement the traits the higher layers are written against
+//! ([`FrameTransport`], [`HeartbeatSession`], `latency::RoundTrip` and `std::io::Read` /
+//! `Write`), so framing, sessions and application code on top of them can be unit tested
+//! and run in CI. Both ends may be used from different threads; receives block until data
+//! arrives, the peer closes or the timeout expires.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::framed::FramedTcp;
+//! use vma_socket::mock::MockTransport;
+//!
+//! let (client, server) = MockTransport::stream_pair();
+//! let mut client = FramedTcp::new(client);
+//! let mut server = FramedTcp::new(server);
+//!
+//! client.send_frame(b"order").unwrap();
+//! assert_eq!(server.recv_frame(Some(1_000_000)).unwrap(), Some(&b"order"[..]));
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::common::SocketStats;
+use crate::framed::FrameTransport;
+use crate::heartbeat::HeartbeatSession;
+
+/// Message semantics of a [`MockTransport`] pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockKind {
+    /// Byte stream (TCP-like)
+    Stream,
+    /// Datagrams with preserved boundaries (UDP-like)
+    Datagram,
+}
+
+/// Data queued towards one end.
+#[derive(Debug, Default)]
+struct Inbox {
+    messages: VecDeque<Vec<u8>>,
+    /// Bytes of the front message already read (stream pairs)
+    offset: usize,
+    /// The sending end was closed
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    inboxes: Mutex<[Inbox; 2]>,
+    arrived: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, [Inbox; 2]> {
+        // A panicking test thread must not hide the data from the other end
+        self.inboxes.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// One end of an in-memory connection.
+#[derive(Debug)]
+pub struct MockTransport {
+    shared: Arc<Shared>,
+    side: usize,
+    kind: MockKind,
+    read_timeout: Option<u64>,
+    stats: SocketStats,
+}
+
+impl MockTransport {
+    /// Create two connected ends of a byte stream.
+    pub fn stream_pair() -> (Self, Self) {
+        Self::pair(MockKind::Stream)
+    }
+
+    /// Create two connected ends exchanging datagrams.
+    pub fn datagram_pair() -> (Self, Self) {
+        Self::pair(MockKind::Datagram)
+    }
+
+    /// Create two connected ends of the given kind.
+    pub fn pair(kind: MockKind) -> (Self, Self) {
+        let shared = Arc::new(Shared {
+            inboxes: Mutex::new([Inbox::default(), Inbox::default()]),
+            arrived: Condvar::new(),
+        });
+        let end = |side| MockTransport {
+            shared: Arc::clone(&shared),
+            side,
+            kind,
+            read_timeout: None,
+            stats: SocketStats::default(),
+        };
+        (end(0), end(1))
+    }
+
+    /// Get the message semantics of the pair.
+    pub fn kind(&self) -> MockKind {
+        self.kind
+    }
+
+    /// Set the default receive timeout in nanoseconds (`None` blocks).
+    pub fn set_read_timeout(&mut self, timeout_nano: Option<u64>) {
+        self.read_timeout = timeout_nano;
+    }
+
+    /// Get the default receive timeout in nanoseconds.
+    pub fn read_timeout(&self) -> Option<u64> {
+        self.read_timeout
+    }
+
+    /// Send `data` to the peer, returning its length.
+    ///
+    /// Fails with `ErrorKind::BrokenPipe` once either end was closed. Empty datagrams are
+    /// delivered; empty stream writes transmit nothing.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut inboxes = self.shared.lock();
+        if inboxes[self.side].closed || inboxes[1 - self.side].closed {
+            return Err(Error::new(ErrorKind::BrokenPipe, "Mock connection closed"));
+        }
+        if self.kind == MockKind::Datagram || !data.is_empty() {
+            inboxes[1 - self.side].messages.push_back(data.to_vec());
+        }
+        drop(inboxes);
+        self.shared.arrived.notify_all();
+
+        self.stats.tx_packets += 1;
+        self.stats.tx_bytes += data.len() as u64;
+        self.stats.last_tx_ns = wall_clock_ns();
+        Ok(data.len())
+    }
+
+    /// Receive into `buffer`, waiting up to `timeout_nano` (or the read timeout).
+    ///
+    /// Returns `Ok(None)` on timeout and `Ok(Some(0))` once the peer closed and everything
+    /// it sent was received. Datagrams larger than `buffer` are truncated.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<usize>, Error> {
+        let deadline = timeout_nano
+            .or(self.read_timeout)
+            .map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        let mut inboxes = self.shared.lock();
+        loop {
+            let (own, peer) = split(&mut inboxes, self.side);
+            if let Some(front) = own.messages.front() {
+                let received = match self.kind {
+                    MockKind::Datagram => {
+                        let n = front.len().min(buffer.len());
+                        buffer[..n].copy_from_slice(&front[..n]);
+                        if n < front.len() {
+                            self.stats.rx_truncated += 1;
+                        }
+                        own.messages.pop_front();
+                        n
+                    }
+                    MockKind::Stream => read_stream(own, buffer),
+                };
+                self.stats.rx_packets += 1;
+                self.stats.rx_bytes += received as u64;
+                self.stats.last_rx_ns = wall_clock_ns();
+                return Ok(Some(received));
+            }
+            if peer.closed || own.closed {
+                return Ok(Some(0));
+            }
+
+            inboxes = match deadline {
+                None => self.shared.arrived.wait(inboxes).unwrap_or_else(|poisoned| poisoned.into_inner()),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Ok(None);
+                    }
+                    self.shared
+                        .arrived
+                        .wait_timeout(inboxes, remaining)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+            };
+        }
+    }
+
+    /// Get the number of bytes (streams) or datagrams waiting to be received.
+    pub fn pending(&self) -> usize {
+        let inboxes = self.shared.lock();
+        let own = &inboxes[self.side];
+        match self.kind {
+            MockKind::Datagram => own.messages.len(),
+            MockKind::Stream => own.messages.iter().map(Vec::len).sum::<usize>() - own.offset,
+        }
+    }
+
+    /// Close this end: the peer receives what was already sent, then end-of-stream.
+    ///
+    /// Dropping an end closes it as well.
+    pub fn close(&mut self) {
+        self.shared.lock()[self.side].closed = true;
+        self.shared.arrived.notify_all();
+    }
+
+    /// Check whether the peer has closed its end.
+    pub fn is_peer_closed(&self) -> bool {
+        self.shared.lock()[1 - self.side].closed
+    }
+
+    /// Get this end's traffic counters.
+    pub fn stats(&self) -> SocketStats {
+        self.stats
+    }
+}
+
+impl Drop for MockTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Borrow the inbox of `side` and of its peer.
+fn split(inboxes: &mut [Inbox; 2], side: usize) -> (&mut Inbox, &mut Inbox) {
+    let [first, second] = inboxes;
+    if side == 0 {
+        (first, second)
+    } else {
+        (second, first)
+    }
+}
+
+/// Copy queued stream bytes into `buffer`, across writes.
+fn read_stream(inbox: &mut Inbox, buffer: &mut [u8]) -> usize {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let Some(front) = inbox.messages.front() else {
+            break;
+        };
+        let available = &front[inbox.offset..];
+        let n = available.len().min(buffer.len() - filled);
+        buffer[filled..filled + n].copy_from_slice(&available[..n]);
+        filled += n;
+        inbox.offset += n;
+        if inbox.offset == front.len() {
+            inbox.messages.pop_front();
+            inbox.offset = 0;
+        }
+    }
+    filled
+}
+
+fn wall_clock_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+impl FrameTransport for MockTransport {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.send(data).map(|_| ())
+    }
+
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        self.recv(buffer, timeout)
+    }
+}
+
+impl HeartbeatSession for MockTransport {
+    fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+        self.send(payload).map(|_| ())
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        Ok(self.stats.last_rx_ns)
+    }
+}
+
+#[cfg(feature = "histogram")]
+impl crate::latency::RoundTrip for MockTransport {
+    fn send_request(&mut self, request: &[u8]) -> Result<(), Error> {
+        self.send(request).map(|_| ())
+    }
+
+    fn recv_reply(&mut self, reply: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        self.recv(reply, timeout)
+    }
+}
+
+impl std::io::Read for MockTransport {
+    /// Receive, honouring the read timeout; a timeout fails with `ErrorKind::WouldBlock`.
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.recv(buffer, None)?
+            .ok_or_else(|| Error::new(ErrorKind::WouldBlock, "Receive timed out"))
+    }
+}
+
+impl std::io::Write for MockTransport {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.send(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::framed::FramedTcp;
+
+    #[test]
+    fn test_stream_pair() {
+        let (client, server) = MockTransport::stream_pair();
+        let mut server = FramedTcp::new(server);
+        let sender = std::thread::spawn(move || {
+            let mut client = FramedTcp::new(client);
+            for i in 0..100u32 {
+                client.send_frame(&i.to_be_bytes()).unwrap();
+            }
+            client.get_ref().stats().tx_packets
+        });
+
+        for i in 0..100u32 {
+            assert_eq!(server.recv_frame(None).unwrap(), Some(&i.to_be_bytes()[..]));
+        }
+        // Header and payload of every frame are written together
+        assert_eq!(sender.join().unwrap(), 100);
+
+        // The client end was dropped: end of stream
+        let mut buffer = [0u8; 16];
+        assert_eq!(server.get_mut().recv(&mut buffer, Some(1_000_000)).unwrap(), Some(0));
+        assert!(server.get_mut().send(b"late").is_err());
+    }
+
+    #[test]
+    fn test_datagram_pair() {
+        let (mut a, mut b) = MockTransport::datagram_pair();
+        let mut buffer = [0u8; 4];
+        assert_eq!(b.recv(&mut buffer, Some(1_000_000)).unwrap(), None);
+
+        a.send(b"ab").unwrap();
+        a.send(b"").unwrap();
+        a.send(b"abcdef").unwrap();
+        assert_eq!(b.pending(), 3);
+        assert_eq!(b.recv(&mut buffer, None).unwrap(), Some(2));
+        assert_eq!(b.recv(&mut buffer, None).unwrap(), Some(0));
+        assert_eq!(b.recv(&mut buffer, None).unwrap(), Some(4));
+        assert_eq!(&buffer, b"abcd");
+
+        let stats = b.stats();
+        assert_eq!((stats.rx_packets, stats.rx_bytes, stats.rx_truncated), (3, 6, 1));
+        assert!(b.last_rx_ns().unwrap() > 0);
+        assert!(!b.is_peer_closed());
+        a.close();
+        assert!(b.is_peer_closed());
+    }
+}
@@ -0,0 +1,324 @@
+//! Link state monitoring over rtnetlink.
+//!
+//! A port flap on the NIC a session runs over only shows up as a TCP timeout or a silent
+//! feed seconds later. A [`NetMonitor`] listens to the kernel's link notifications
+//! (RTMGRP_LINK) on a background thread and reports every Up/Down transition of the
+//! watched interfaces as a [`LinkEvent`], through a callback ([`NetMonitor::spawn`]) or a
+//! bounded channel ([`NetMonitor::spawn_with_channel`]).
+//!
+//! The first notification of each watched interface is reported too, so the consumer
+//! starts from its current state. [`interface_for_addr`] finds the interface a socket is
+//! bound to from its local address.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::netmon::{self, LinkState, NetMonitor};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let interface = netmon::interface_for_addr(socket.local_addr().unwrap().ip())
+//!     .unwrap()
+//!     .expect("bound to a local interface");
+//! let (monitor, events) = NetMonitor::spawn_with_channel([interface], 16).unwrap();
+//!
+//! for event in events {
+//!     if event.state == LinkState::Down {
+//!         eprintln!("{} went down ({:?}), failing over", event.interface, event.oper_state);
+//!         break;
+//!     }
+//! }
+//! monitor.stop().unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Wait of the monitor thread for notifications (100ms), bounding how long `stop()` waits.
+const MONITOR_POLL_TIMEOUT_MS: c_int = 100;
+
+/// Links read per call (must match NETMON_MAX_LINKS in netmon.h).
+const MAX_LINKS: usize = 64;
+
+/// Length of an interface name including the NUL (IF_NAMESIZE).
+const IF_NAMESIZE: usize = 16;
+
+/// One link notification, the layout matches `netmon_link_t` in `netmon.h`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NetmonLink {
+    pub(crate) if_index: u32,
+    pub(crate) flags: u32,
+    pub(crate) operstate: u8,
+    pub(crate) removed: bool,
+    pub(crate) name: [c_char; IF_NAMESIZE],
+}
+
+// External declarations for the netlink helpers
+extern "C" {
+    fn netmon_open(dump: bool) -> c_int;
+    fn netmon_read(fd: c_int, links: *mut NetmonLink, max_links: usize, timeout_ms: c_int) -> c_int;
+    fn netmon_close(fd: c_int);
+    fn netmon_interface_for_addr(addr: u32, name: *mut c_char) -> c_int;
+}
+
+/// RFC 2863 operational state of an interface (IF_OPER_*).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperState {
+    /// State not reported by the driver (loopback, some virtual devices)
+    Unknown,
+    /// Interface missing, e.g. a removed device
+    NotPresent,
+    /// Interface down
+    Down,
+    /// Down because an underlying interface is down (bond slave, VLAN parent)
+    LowerLayerDown,
+    /// In test mode
+    Testing,
+    /// Waiting for an external event, e.g. 802.1X authentication
+    Dormant,
+    /// Interface up and able to pass packets
+    Up,
+}
+
+impl OperState {
+    fn from_raw(value: u8) -> Self {
+        match value {
+            1 => OperState::NotPresent,
+            2 => OperState::Down,
+            3 => OperState::LowerLayerDown,
+            4 => OperState::Testing,
+            5 => OperState::Dormant,
+            6 => OperState::Up,
+            _ => OperState::Unknown,
+        }
+    }
+}
+
+/// Whether an interface can carry traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkState {
+    /// Administratively up with carrier and an Up (or Unknown) operational state
+    Up,
+    /// Anything else, including a removed interface
+    Down,
+}
+
+/// A transition of a watched interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkEvent {
+    /// Interface name
+    pub interface: String,
+    /// Interface index
+    pub if_index: u32,
+    /// New state
+    pub state: LinkState,
+    /// Operational state reported by the kernel
+    pub oper_state: OperState,
+    /// Whether the physical link has carrier (IFF_LOWER_UP)
+    pub carrier: bool,
+    /// Whether the interface is administratively up (IFF_UP)
+    pub admin_up: bool,
+}
+
+impl LinkEvent {
+    fn from_link(link: &NetmonLink) -> Self {
+        let interface = unsafe { CStr::from_ptr(link.name.as_ptr()) }.to_string_lossy().into_owned();
+        let oper_state = if link.removed { OperState::NotPresent } else { OperState::from_raw(link.operstate) };
+        let carrier = !link.removed && link.flags & libc::IFF_LOWER_UP as u32 != 0;
+        let admin_up = !link.removed && link.flags & libc::IFF_UP as u32 != 0;
+        let state = if admin_up && carrier && matches!(oper_state, OperState::Up | OperState::Unknown) {
+            LinkState::Up
+        } else {
+            LinkState::Down
+        };
+        LinkEvent {
+            interface,
+            if_index: link.if_index,
+            state,
+            oper_state,
+            carrier,
+            admin_up,
+        }
+    }
+}
+
+/// Background thread reporting link transitions of a set of interfaces.
+#[derive(Debug)]
+pub struct NetMonitor {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl NetMonitor {
+    /// Watch `interfaces` (all interfaces when empty) and call `callback` on each transition.
+    ///
+    /// The callback runs on the monitor thread.
+    pub fn spawn<I, S, F>(interfaces: I, callback: F) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+        F: FnMut(&LinkEvent) + Send + 'static,
+    {
+        let watched: Vec<String> = interfaces.into_iter().map(Into::into).collect();
+        let fd = unsafe { netmon_open(true) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        let spawned = thread::Builder::new()
+            .name("vma-netmon".to_string())
+            .spawn(move || {
+                let result = run_monitor(fd, &watched, callback, &thread_running);
+                unsafe { netmon_close(fd) };
+                thread_running.store(false, Ordering::Release);
+                result
+            });
+        match spawned {
+            Ok(thread) => Ok(NetMonitor {
+                running,
+                thread: Some(thread),
+            }),
+            Err(e) => {
+                unsafe { netmon_close(fd) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Watch `interfaces` (all interfaces when empty) and deliver transitions through a bounded channel.
+    ///
+    /// Events are dropped rather than stalling the monitor when the channel is full.
+    pub fn spawn_with_channel<I, S>(interfaces: I, capacity: usize) -> Result<(Self, Receiver<LinkEvent>), Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let mut connected = true;
+        let monitor = Self::spawn(interfaces, move |event| {
+            if connected {
+                if let Err(TrySendError::Disconnected(_)) = tx.try_send(event.clone()) {
+                    connected = false;
+                }
+            }
+        })?;
+        Ok((monitor, rx))
+    }
+
+    /// Check whether the monitor thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the monitor thread, returning the error it stopped with, if any.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Error> {
+        self.running.store(false, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::other("Monitor thread panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for NetMonitor {
+    /// Stop and join the monitor thread.
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+/// Notification loop of the monitor thread.
+fn run_monitor<F: FnMut(&LinkEvent)>(
+    fd: c_int,
+    watched: &[String],
+    mut callback: F,
+    running: &AtomicBool,
+) -> Result<(), Error> {
+    let mut links = [NetmonLink {
+        if_index: 0,
+        flags: 0,
+        operstate: 0,
+        removed: false,
+        name: [0; IF_NAMESIZE],
+    }; MAX_LINKS];
+    let mut states: HashMap<u32, LinkState> = HashMap::new();
+
+    while running.load(Ordering::Acquire) {
+        let count = unsafe { netmon_read(fd, links.as_mut_ptr(), links.len(), MONITOR_POLL_TIMEOUT_MS) };
+        if count < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        for link in &links[..count as usize] {
+            let event = LinkEvent::from_link(link);
+            if !watched.is_empty() && !watched.contains(&event.interface) {
+                continue;
+            }
+            // Report the first state of an interface and every change after it
+            if states.insert(event.if_index, event.state) != Some(event.state) {
+                callback(&event);
+            }
+            if link.removed {
+                states.remove(&event.if_index);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Find the interface holding the local address `addr`, e.g. a socket's `local_addr()`.
+///
+/// Returns `None` for an address no interface holds, including `0.0.0.0`.
+pub fn interface_for_addr(addr: IpAddr) -> Result<Option<String>, Error> {
+    let IpAddr::V4(addr) = addr else {
+        return Err(Error::new(ErrorKind::InvalidInput, "IPv6 addresses are not supported"));
+    };
+
+    let mut name = [0 as c_char; IF_NAMESIZE];
+    match unsafe { netmon_interface_for_addr(u32::from(addr).to_be(), name.as_mut_ptr()) } {
+        result if result < 0 => Err(Error::last_os_error()),
+        0 => Ok(None),
+        _ => Ok(Some(unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    #[test]
+    fn test_loopback_link() {
+        let interface = interface_for_addr(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap().unwrap();
+        assert_eq!(interface, "lo");
+        assert_eq!(interface_for_addr(IpAddr::V4(Ipv4Addr::UNSPECIFIED)).unwrap(), None);
+
+        // The initial dump reports the current state of the watched interface
+        let (monitor, events) = NetMonitor::spawn_with_channel([interface], 4).unwrap();
+        let event = events.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.interface, "lo");
+        assert_eq!(event.state, LinkState::Up);
+        assert!(event.admin_up && event.carrier);
+        assert!(monitor.is_running());
+        monitor.stop().unwrap();
+    }
+}
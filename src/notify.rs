@@ -0,0 +1,299 @@
+//! Readiness notification for external event loops.
+//!
+//! Offloaded sockets are driven by polling VMA's rings from the application thread, so a
+//! host loop built on its own epoll or io_uring instance cannot wait on them directly and
+//! ends up spinning on `recv` with zero timeouts. A [`ReadyNotifier`] waits for the socket
+//! on a helper thread (through libvma's poll, so offloaded traffic is seen) and signals a
+//! plain kernel eventfd, which any event loop can sleep on.
+//!
+//! The notification is level-triggered by hand: after the eventfd fires, the host drains
+//! the socket (non-blocking receives until none is left) and calls
+//! [`rearm`](ReadyNotifier::rearm), which clears the eventfd and fires it again right away
+//! if data arrived in the meantime.
+//!
+//! A notifier borrows the descriptor it watches. `ready_notifier()` on the sockets returns
+//! a [`Notified`] guard holding both the socket and its notifier, so the socket keeps
+//! being used (through `Deref`) and cannot be closed while the helper thread polls it.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//! socket.set_nonblocking(true);
+//! let mut socket = socket.ready_notifier().unwrap();
+//!
+//! // Register socket.notifier().fd() with the host's epoll instance; when it is readable:
+//! let mut buffer = [0u8; 2048];
+//! // Drain until the receive fails with WouldBlock
+//! while let Ok(Some(packet)) = socket.recv_from(&mut buffer, None) {
+//!     println!("{} bytes from {}", packet.data.len(), packet.src_addr);
+//! }
+//! socket.notifier().rearm().unwrap();
+//! ```
+
+use std::io::Error;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Wait of the helper thread per round (100ms), bounding how long `stop()` waits.
+const NOTIFY_POLL_TIMEOUT_MS: c_int = 100;
+
+/// Whether the helper thread may signal again, shared with `rearm()`.
+#[derive(Debug, Default)]
+struct Arm {
+    armed: Mutex<bool>,
+    changed: Condvar,
+}
+
+/// Helper thread signalling an eventfd when a socket becomes readable.
+///
+/// Borrows the watched descriptor for `'a`.
+#[derive(Debug)]
+pub struct ReadyNotifier<'a> {
+    eventfd: c_int,
+    arm: Arc<Arm>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+    _fd: PhantomData<BorrowedFd<'a>>,
+}
+
+impl<'a> ReadyNotifier<'a> {
+    /// Watch the socket descriptor `fd`, armed for the first notification.
+    pub fn spawn(fd: BorrowedFd<'a>) -> Result<Self, Error> {
+        // The borrow keeps `fd` open for as long as the notifier lives
+        unsafe { ReadyNotifier::spawn_raw(fd.as_raw_fd()) }
+    }
+
+    /// Watch the raw descriptor `fd`; the caller keeps it open until the notifier is dropped.
+    unsafe fn spawn_raw(fd: c_int) -> Result<Self, Error> {
+        let eventfd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if eventfd < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let arm = Arc::new(Arm {
+            armed: Mutex::new(true),
+            changed: Condvar::new(),
+        });
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_arm = Arc::clone(&arm);
+        let thread_running = Arc::clone(&running);
+        let spawned = thread::Builder::new()
+            .name("vma-notify".to_string())
+            .spawn(move || {
+                let result = run_notifier(fd, eventfd, &thread_arm, &thread_running);
+                thread_running.store(false, Ordering::Release);
+                result
+            });
+        match spawned {
+            Ok(thread) => Ok(ReadyNotifier {
+                eventfd,
+                arm,
+                running,
+                thread: Some(thread),
+                _fd: PhantomData,
+            }),
+            Err(e) => {
+                unsafe { libc::close(eventfd) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Get the eventfd to register with the host event loop (readable when the socket is).
+    pub fn fd(&self) -> c_int {
+        self.eventfd
+    }
+
+    /// Clear the eventfd and wait for the socket to become readable again.
+    ///
+    /// Call it once the socket has been drained; data still queued fires the eventfd
+    /// again immediately.
+    pub fn rearm(&self) -> Result<(), Error> {
+        let mut count: u64 = 0;
+        let res = unsafe { libc::read(self.eventfd, &mut count as *mut u64 as *mut libc::c_void, 8) };
+        if res < 0 {
+            let error = Error::last_os_error();
+            if error.kind() != std::io::ErrorKind::WouldBlock {
+                return Err(error);
+            }
+        }
+
+        let mut armed = self.arm.armed.lock().unwrap_or_else(|e| e.into_inner());
+        *armed = true;
+        self.arm.changed.notify_one();
+        Ok(())
+    }
+
+    /// Check whether the helper thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the helper thread, returning the error it stopped with, if any.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Error> {
+        self.running.store(false, Ordering::Release);
+        self.arm.changed.notify_one();
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::other("Notifier thread panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AsRawFd for ReadyNotifier<'_> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.eventfd
+    }
+}
+
+impl Drop for ReadyNotifier<'_> {
+    /// Stop the helper thread and close the eventfd.
+    fn drop(&mut self) {
+        let _ = self.join();
+        unsafe { libc::close(self.eventfd) };
+    }
+}
+
+/// A socket borrowed together with the [`ReadyNotifier`] watching it.
+///
+/// Dereferences to the socket, so it is drained through the guard; dropping the guard
+/// stops the notifier and releases the socket.
+#[derive(Debug)]
+pub struct Notified<'a, S> {
+    // Declared first so the helper thread stops before the borrow of the socket ends
+    notifier: ReadyNotifier<'a>,
+    socket: &'a mut S,
+}
+
+impl<'a, S: AsRawFd> Notified<'a, S> {
+    /// Borrow `socket` and watch its descriptor.
+    pub fn new(socket: &'a mut S) -> Result<Self, Error> {
+        // The socket stays borrowed, and so open, for as long as the notifier lives
+        let notifier = unsafe { ReadyNotifier::spawn_raw(socket.as_raw_fd())? };
+        Ok(Notified { notifier, socket })
+    }
+}
+
+impl<'a, S> Notified<'a, S> {
+    /// Get the notifier, for its eventfd and [`rearm`](ReadyNotifier::rearm).
+    pub fn notifier(&self) -> &ReadyNotifier<'a> {
+        &self.notifier
+    }
+
+    /// Stop the notifier and hand the socket back, returning the error the helper thread
+    /// stopped with, if any.
+    pub fn stop(self) -> Result<&'a mut S, Error> {
+        let Notified { notifier, socket } = self;
+        notifier.stop().map(|()| socket)
+    }
+}
+
+impl<S> Deref for Notified<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.socket
+    }
+}
+
+impl<S> DerefMut for Notified<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.socket
+    }
+}
+
+/// Loop of the helper thread: wait until armed, then until `fd` is readable, then signal.
+fn run_notifier(fd: c_int, eventfd: c_int, arm: &Arm, running: &AtomicBool) -> Result<(), Error> {
+    while running.load(Ordering::Acquire) {
+        {
+            let armed = arm.armed.lock().unwrap_or_else(|e| e.into_inner());
+            let (armed, _) = arm
+                .changed
+                .wait_timeout_while(armed, Duration::from_millis(NOTIFY_POLL_TIMEOUT_MS as u64), |armed| {
+                    !*armed && running.load(Ordering::Acquire)
+                })
+                .unwrap_or_else(|e| e.into_inner());
+            if !*armed {
+                continue;
+            }
+        }
+
+        let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        let res = unsafe { libc::poll(&mut pfd, 1, NOTIFY_POLL_TIMEOUT_MS) };
+        if res < 0 {
+            let error = Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        if res == 0 {
+            continue;
+        }
+        if pfd.revents & libc::POLLNVAL != 0 {
+            return Err(Error::from_raw_os_error(libc::EBADF));
+        }
+
+        // Readable, or an error/hang-up the next receive reports
+        *arm.armed.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        let one: u64 = 1;
+        if unsafe { libc::write(eventfd, &one as *const u64 as *const libc::c_void, 8) } < 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::os::fd::AsFd;
+
+    fn eventfd_readable(fd: c_int, timeout_ms: c_int) -> bool {
+        let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        unsafe { libc::poll(&mut pfd, 1, timeout_ms) == 1 }
+    }
+
+    #[test]
+    fn test_ready_notifier() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let notifier = ReadyNotifier::spawn(socket.as_fd()).unwrap();
+        assert!(!eventfd_readable(notifier.fd(), 50));
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"one", socket.local_addr().unwrap()).unwrap();
+        sender.send_to(b"two", socket.local_addr().unwrap()).unwrap();
+        assert!(eventfd_readable(notifier.fd(), 1000));
+
+        // Rearming with a datagram still queued fires again
+        let mut buffer = [0u8; 16];
+        socket.recv(&mut buffer).unwrap();
+        notifier.rearm().unwrap();
+        assert!(eventfd_readable(notifier.fd(), 1000));
+
+        // Once drained, the eventfd stays quiet
+        socket.recv(&mut buffer).unwrap();
+        notifier.rearm().unwrap();
+        assert!(!eventfd_readable(notifier.fd(), 50));
+        assert!(notifier.is_running());
+        notifier.stop().unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+//! Send and receive with `bytes` owned buffers.
+//!
+//! Enabled with the `bytes` feature. Received data is returned as [`Bytes`], a refcounted
+//! view that is cloned without copying the payload, so one received packet can be handed
+//! to several consumers (threads, queues, subscribers) at the cost of a reference count.
+//!
+//! `recv_bytes()` and `recv_buf()` receive straight into the spare capacity of a
+//! [`BytesMut`] and split the data off, so successive packets share its allocation until
+//! every view of them is dropped. The receive region is zeroed before each call, like the
+//! read buffer of [`FramedTcp`]; lowering `set_max_datagram_size()` to the largest expected
+//! datagram keeps that cheap on UDP. The `send_buf()` variants take any [`Buf`], including
+//! chained and partially consumed buffers.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use bytes::BytesMut;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5001).unwrap();
+//! socket.set_max_datagram_size(1500).unwrap();
+//!
+//! let mut arena = BytesMut::with_capacity(1 << 20);
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! while let Some(packet) = socket.recv_bytes(&mut arena, Some(100_000_000)).unwrap() {
+//!     tx.send(packet.clone()).unwrap(); // same payload, no copy
+//!     println!("{} bytes", packet.len());
+//! }
+//! # drop(rx);
+//! ```
+//!
+//! [`FramedTcp`]: crate::framed::FramedTcp
+
+use std::io::Error;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::tcp::VmaTcpSocket;
+use crate::udp::{Destination, Packet, VmaUdpSocket};
+
+/// Bytes reserved by `recv_buf()` when the buffer has no spare capacity.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// A received UDP packet whose payload is a shared [`Bytes`].
+#[derive(Clone, Debug)]
+pub struct BytesPacket {
+    /// The packet payload data.
+    pub data: Bytes,
+
+    /// The source address from which the packet was received.
+    pub src_addr: SocketAddr,
+
+    /// Kernel receive timestamp (SO_TIMESTAMPNS) in nanoseconds since the epoch, 0 unless
+    /// `enable_timestamps` is set.
+    pub timestamp: u64,
+
+    /// The destination address the packet was sent to (requires `set_pktinfo(true)`).
+    pub dst_addr: Option<Ipv4Addr>,
+
+    /// Index of the interface the packet arrived on (requires `set_pktinfo(true)`, 0 if unknown).
+    pub if_index: u32,
+
+    /// Full length of the datagram on the wire, larger than `data.len()` if it was truncated.
+    pub full_length: usize,
+}
+
+impl BytesPacket {
+    /// Check whether the datagram was larger than the receive buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.full_length > self.data.len()
+    }
+}
+
+impl From<Packet> for BytesPacket {
+    /// Take over the payload of `packet` without copying it.
+    fn from(packet: Packet) -> Self {
+        BytesPacket {
+            data: Bytes::from(packet.data),
+            src_addr: packet.src_addr,
+            timestamp: packet.timestamp,
+            dst_addr: packet.dst_addr,
+            if_index: packet.if_index,
+            full_length: packet.full_length,
+        }
+    }
+}
+
+/// Run `recv` on `len` zeroed bytes appended to `buf`, keeping only what it received.
+fn recv_into<F>(buf: &mut BytesMut, len: usize, recv: F) -> Result<Option<usize>, Error>
+where
+    F: FnOnce(&mut [u8]) -> Result<Option<usize>, Error>,
+{
+    let filled = buf.len();
+    buf.resize(filled + len, 0);
+    let received = recv(&mut buf[filled..]);
+    buf.truncate(filled + received.as_ref().ok().copied().flatten().unwrap_or(0));
+    received
+}
+
+impl VmaUdpSocket {
+    /// Receive a datagram from the connected remote address into `buf`, returning it as
+    /// a `Bytes` split off `buf`.
+    ///
+    /// `buf` serves as a receive arena: bytes left in it are discarded, and its spare
+    /// capacity is reused by the next call. Returns `Ok(None)` on timeout; datagrams over
+    /// `max_datagram_size()` are truncated.
+    pub fn recv_bytes(&mut self, buf: &mut BytesMut, timeout_nano: Option<u64>) -> Result<Option<Bytes>, Error> {
+        buf.clear();
+        let max = self.max_datagram_size();
+        let received = recv_into(buf, max, |region| {
+            Ok(self.recv_full(region, timeout_nano)?.map(|length| length.copied))
+        })?;
+        Ok(received.map(|_| buf.split().freeze()))
+    }
+
+    /// Receive a datagram and its source address, the payload becoming a `Bytes`.
+    ///
+    /// `buffer` is only the receive area; the payload is moved out of it once, as with
+    /// `recv_from()`. Returns `Ok(None)` on timeout.
+    pub fn recv_bytes_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<BytesPacket>, Error> {
+        Ok(self.recv_from(buffer, timeout_nano)?.map(BytesPacket::from))
+    }
+
+    /// Send the remaining bytes of `buf` as one datagram to the connected remote address.
+    pub fn send_buf<B: Buf>(&mut self, mut buf: B) -> Result<usize, Error> {
+        let data = buf.copy_to_bytes(buf.remaining());
+        self.send(&data)
+    }
+
+    /// Send the remaining bytes of `buf` as one datagram to a destination prepared by `resolve()`.
+    pub fn send_buf_to_dest<B: Buf>(&mut self, mut buf: B, dest: &Destination) -> Result<usize, Error> {
+        let data = buf.copy_to_bytes(buf.remaining());
+        self.send_to_dest(&data, dest)
+    }
+}
+
+impl VmaTcpSocket {
+    /// Receive into the spare capacity of `buf`, reserving more if it has none, and append
+    /// the data to it.
+    ///
+    /// Returns `Ok(None)` on timeout, `Ok(Some(0))` once the peer has sent its FIN and
+    /// `Ok(Some(n))` when `n` bytes were appended; `buf.split_to(..)` then hands complete
+    /// messages out as `Bytes`.
+    pub fn recv_buf(&mut self, buf: &mut BytesMut, timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        if buf.capacity() == buf.len() {
+            buf.reserve(READ_CHUNK);
+        }
+        let spare = buf.capacity() - buf.len();
+        recv_into(buf, spare, |region| self.recv_checked(region, timeout))
+    }
+
+    /// Send from `buf` and advance it past the bytes written.
+    ///
+    /// Like `send()`, may write only part of `buf` and returns `Ok(0)` when the send
+    /// would block.
+    pub fn send_buf<B: Buf>(&mut self, buf: &mut B) -> Result<usize, Error> {
+        let sent = self.send(buf.chunk())?;
+        buf.advance(sent);
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_owned_buffers() {
+        let mut rx = VmaUdpSocket::new().unwrap();
+        rx.bind("127.0.0.1", 0).unwrap();
+        rx.set_max_datagram_size(64).unwrap();
+        let target = rx.local_addr().unwrap();
+        let mut tx = VmaUdpSocket::new().unwrap();
+        tx.connect("127.0.0.1", target.port()).unwrap();
+        rx.connect("127.0.0.1", tx.local_addr().unwrap().port()).unwrap();
+
+        tx.send_buf(Bytes::from_static(b"first")).unwrap();
+        tx.send_buf(Buf::chain(&b"sec"[..], &b"ond"[..])).unwrap();
+        let mut arena = BytesMut::with_capacity(1024);
+        let first = rx.recv_bytes(&mut arena, Some(1_000_000_000)).unwrap().unwrap();
+        let second = rx.recv_bytes(&mut arena, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!((&first[..], &second[..]), (&b"first"[..], &b"second"[..]));
+        assert_eq!(second.as_ptr(), first[5..].as_ptr()); // same allocation
+        let shared = first.clone();
+        assert_eq!(shared.as_ptr(), first.as_ptr());
+        assert_eq!(rx.recv_bytes(&mut arena, Some(1_000_000)).unwrap(), None);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut socket = VmaTcpSocket::new().unwrap();
+        assert!(socket.connect("127.0.0.1", port, Some(1_000_000_000)).unwrap());
+        let (mut peer, _) = listener.accept().unwrap();
+
+        let mut out = Bytes::from_static(b"hello");
+        while out.has_remaining() {
+            socket.send_buf(&mut out).unwrap();
+        }
+        let mut echoed = [0u8; 5];
+        peer.read_exact(&mut echoed).unwrap();
+        peer.write_all(&echoed).unwrap();
+        drop(peer);
+
+        let mut buf = BytesMut::new();
+        while buf.len() < 5 {
+            assert!(socket.recv_buf(&mut buf, Some(1_000_000_000)).unwrap().unwrap() > 0);
+        }
+        assert_eq!(&buf.split_to(5).freeze()[..], b"hello");
+        assert_eq!(socket.recv_buf(&mut buf, Some(1_000_000_000)).unwrap(), Some(0));
+    }
+}
@@ -0,0 +1,181 @@
+//! Userspace rate limiting for UDP sends.
+//!
+//! A [`Pacer`] enforces a packets/sec or bits/sec [`Rate`] as a token bucket (in its
+//! GCRA form: each send pushes a theoretical arrival time forward by its cost). Waiting is
+//! a busy-wait on the TSC ([`common::clock`](crate::common::clock)), so packets leave at
+//! the configured spacing instead of the tens of microseconds of jitter a `thread::sleep` adds.
+//!
+//! Attach one to a socket with [`VmaUdpSocket::set_pacer`](crate::udp::VmaUdpSocket::set_pacer)
+//! and every send waits for its turn.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::pacing::{Pacer, Rate};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5001).unwrap();
+//!
+//! // 1 Gbit/s, allowing bursts of up to 10 full-size packets
+//! let pacer = Pacer::new(Rate::BitsPerSec(1_000_000_000)).unwrap().with_burst(10 * 1472 * 8);
+//! socket.set_pacer(Some(pacer));
+//!
+//! let payload = [0u8; 1472];
+//! loop {
+//!     socket.send(&payload).unwrap();
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::thread;
+use std::time::Duration;
+
+use crate::common::clock;
+
+/// Waits longer than this sleep for all but the last part, which is spun.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Target send rate of a [`Pacer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rate {
+    /// Datagrams per second, regardless of size
+    PacketsPerSec(u64),
+    /// Payload bits per second
+    BitsPerSec(u64),
+}
+
+impl Rate {
+    /// Get the cost of a datagram of `len` bytes, in the rate's unit.
+    fn cost(&self, len: usize) -> u64 {
+        match *self {
+            Rate::PacketsPerSec(_) => 1,
+            Rate::BitsPerSec(_) => len as u64 * 8,
+        }
+    }
+
+    fn per_sec(&self) -> u64 {
+        match *self {
+            Rate::PacketsPerSec(rate) | Rate::BitsPerSec(rate) => rate,
+        }
+    }
+}
+
+/// Token-bucket pacer with busy-wait precision.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    rate: Rate,
+    burst: u64,
+    ns_per_unit: f64,
+    /// Theoretical arrival time of the next send, in `clock::monotonic_nanos()`
+    next_ns: f64,
+}
+
+impl Pacer {
+    /// Create a pacer sending at `rate` without bursts.
+    ///
+    /// Fails with `ErrorKind::InvalidInput` for a zero rate.
+    pub fn new(rate: Rate) -> Result<Self, Error> {
+        if rate.per_sec() == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Rate must be non-zero"));
+        }
+
+        Ok(Pacer {
+            rate,
+            burst: 0,
+            ns_per_unit: 1e9 / rate.per_sec() as f64,
+            next_ns: clock::monotonic_nanos() as f64,
+        })
+    }
+
+    /// Allow sending `burst` units (packets or bits) back to back after an idle period.
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// Get the configured rate.
+    pub fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    /// Get the burst allowance in the rate's unit.
+    pub fn burst(&self) -> u64 {
+        self.burst
+    }
+
+    /// Get how long a datagram of `len` bytes has to wait for its turn.
+    pub fn delay(&self, len: usize) -> Duration {
+        let now = clock::monotonic_nanos() as f64;
+        Duration::from_nanos((self.earliest_ns(len) - now).max(0.0) as u64)
+    }
+
+    /// Take the turn for a datagram of `len` bytes if it is due, without waiting.
+    pub fn try_acquire(&mut self, len: usize) -> bool {
+        let now = clock::monotonic_nanos() as f64;
+        if now < self.earliest_ns(len) {
+            return false;
+        }
+        self.consume(now, len);
+        true
+    }
+
+    /// Busy-wait until a datagram of `len` bytes is due and take its turn.
+    ///
+    /// Waits longer than a millisecond sleep first and spin only for the last stretch.
+    pub fn acquire(&mut self, len: usize) {
+        let delay = self.delay(len);
+        if delay > SPIN_THRESHOLD {
+            thread::sleep(delay - SPIN_THRESHOLD);
+        }
+
+        let earliest = self.earliest_ns(len);
+        let mut now = clock::monotonic_nanos() as f64;
+        while now < earliest {
+            std::hint::spin_loop();
+            now = clock::monotonic_nanos() as f64;
+        }
+        self.consume(now, len);
+    }
+
+    /// Forget accumulated debt and idle credit, e.g. after changing traffic patterns.
+    pub fn reset(&mut self) {
+        self.next_ns = clock::monotonic_nanos() as f64;
+    }
+
+    /// Time from which a send of `len` bytes conforms, allowing for the burst.
+    fn earliest_ns(&self, len: usize) -> f64 {
+        // A datagram costlier than the burst only has to wait for the bucket to be full
+        let slack = self.burst.saturating_sub(self.rate.cost(len)) as f64 * self.ns_per_unit;
+        self.next_ns - slack
+    }
+
+    fn consume(&mut self, now: f64, len: usize) {
+        self.next_ns = self.next_ns.max(now) + self.rate.cost(len) as f64 * self.ns_per_unit;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_pacer() {
+        assert!(Pacer::new(Rate::PacketsPerSec(0)).is_err());
+
+        // 10 packets/s: the burst goes out at once, the next packet waits ~100ms
+        let mut pacer = Pacer::new(Rate::PacketsPerSec(10)).unwrap().with_burst(3);
+        assert!((0..3).all(|_| pacer.try_acquire(100)));
+        assert!(!pacer.try_acquire(100));
+        assert!(pacer.delay(100) > Duration::from_millis(50));
+
+        // 80 kbit/s: a 1000-byte datagram costs 100ms
+        let mut pacer = Pacer::new(Rate::BitsPerSec(80_000)).unwrap();
+        let start = Instant::now();
+        pacer.acquire(1000);
+        pacer.acquire(1000);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(95) && elapsed < Duration::from_millis(150));
+    }
+}
@@ -0,0 +1,1175 @@
+//! Packet capture of VMA-offloaded traffic.
+//!
+//! Enabled with the `pcap` feature. Traffic that libvma moves in user space never reaches
+//! the kernel, so tcpdump on the interface does not see it. A [`PcapTap`] records the
+//! payloads an application sends and receives as pcap packets instead, with nanosecond
+//! timestamps and synthesized IPv4/IPv6 and UDP/TCP headers, so the capture opens in
+//! Wireshark or tcpdump like a wire capture.
+//!
+//! A tap writes to a file (or any writer) as packets are recorded, or keeps the latest
+//! packets in a ring buffer that can be dumped on demand, e.g. after an incident. Capture
+//! can be switched on and off at runtime with [`PcapTap::set_enabled`]; a disabled tap
+//! costs one atomic load per call. Taps are cheap to clone and shared across sockets and
+//! threads.
+//!
+//! [`Tapped`] wraps a [`VmaUdpSocket`] or [`VmaTcpSocket`] and records every send and
+//! receive that moved data; other transports can call [`PcapTap::record`] directly.
+//!
+//! Synthesized TCP sequence numbers count the bytes of each direction from the start of
+//! the capture; they are not the ones of the real connection.
+//!
+//! The other way round, a [`PcapReplayer`] reads the UDP datagrams of a pcap or pcapng
+//! capture (from a tap or from a wire capture) and hands them to a receiver through the
+//! `recv` / `recv_from` calls of [`VmaUdpSocket`], or sends them onto the wire, optionally
+//! with the captured inter-packet gaps. Feed handlers can so be regression-tested against
+//! recorded market data.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::pcap::{PcapTap, Tapped};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let tap = PcapTap::ring(100_000);
+//! let mut socket = Tapped::new(VmaUdpSocket::new().unwrap(), tap.clone());
+//! socket.get_mut().bind("0.0.0.0", 5001).unwrap();
+//!
+//! let mut buffer = vec![0u8; 4096];
+//! for _ in 0..1_000_000 {
+//!     let _ = socket.recv_from(&mut buffer, Some(100_000_000));
+//! }
+//!
+//! // Keep the last 100k datagrams for analysis
+//! let file = std::fs::File::create("/tmp/feed.pcap").unwrap();
+//! tap.write_ring(file).unwrap();
+//! ```
+//!
+//! Replaying a recorded feed into the same handler code:
+//!
+//! ```rust,no_run
+//! use vma_socket::pcap::{PcapReplayer, Timing};
+//!
+//! let mut feed = PcapReplayer::open("/tmp/feed.pcap").unwrap().with_timing(Timing::Original);
+//! let mut buffer = vec![0u8; 4096];
+//! while !feed.is_finished() {
+//!     if let Some(packet) = feed.recv_from(&mut buffer, Some(100_000_000)).unwrap() {
+//!         println!("{} bytes from {}", packet.data.len(), packet.src_addr);
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::common::clock;
+use crate::framed::FrameTransport;
+use crate::tcp::VmaTcpSocket;
+use crate::udp::{Destination, Packet, VmaUdpSocket};
+
+/// Captured bytes per packet unless set with [`PcapTap::with_snaplen`].
+pub const DEFAULT_SNAPLEN: usize = 65535;
+
+/// pcap magic number for nanosecond timestamps.
+const PCAP_MAGIC_NS: u32 = 0xa1b2_3c4d;
+/// Link type of packets starting with the IP header.
+const LINKTYPE_RAW: u32 = 101;
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+const TCP_HEADER_LEN: usize = 20;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+/// TCP flags of synthesized data segments (PSH, ACK).
+const TCP_PSH_ACK: u8 = 0x18;
+/// Largest payload fitting in one synthesized packet.
+const MAX_PAYLOAD: usize = u16::MAX as usize - IPV6_HEADER_LEN - TCP_HEADER_LEN;
+/// Replay waits longer than this sleep for all but the last part, which is spun.
+const SPIN_THRESHOLD_NS: u64 = 1_000_000;
+
+/// Transport header synthesized for a recorded payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Udp,
+    /// A TCP data segment with the given sequence and acknowledgement numbers
+    Tcp { seq: u32, ack: u32 },
+}
+
+/// Counters of a [`PcapTap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TapStats {
+    /// Packets written or kept in the ring
+    pub captured: u64,
+    /// Packets lost to write errors
+    pub failed: u64,
+}
+
+enum Sink {
+    Writer(Box<dyn Write + Send>),
+    Ring { packets: VecDeque<Vec<u8>>, capacity: usize },
+}
+
+struct TapState {
+    sink: Mutex<Sink>,
+    enabled: AtomicBool,
+    snaplen: usize,
+    captured: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Shared handle recording packets to a pcap stream or ring buffer.
+#[derive(Clone)]
+pub struct PcapTap {
+    state: Arc<TapState>,
+}
+
+impl std::fmt::Debug for PcapTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapTap")
+            .field("enabled", &self.is_enabled())
+            .field("snaplen", &self.state.snaplen)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl PcapTap {
+    /// Create a tap writing to a new pcap file at `path`.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::to_writer(BufWriter::new(File::create(path)?))
+    }
+
+    /// Create a tap writing a pcap stream to `writer`, starting with the file header.
+    pub fn to_writer<W: Write + Send + 'static>(writer: W) -> Result<Self, Error> {
+        Self::with_writer(Box::new(writer), DEFAULT_SNAPLEN)
+    }
+
+    /// Create a tap keeping the latest `capacity` packets in memory.
+    pub fn ring(capacity: usize) -> Self {
+        Self::ring_with_snaplen(capacity, DEFAULT_SNAPLEN)
+    }
+
+    fn with_writer(mut writer: Box<dyn Write + Send>, snaplen: usize) -> Result<Self, Error> {
+        writer.write_all(&file_header(snaplen))?;
+        Ok(Self::with_sink(Sink::Writer(writer), snaplen))
+    }
+
+    fn with_sink(sink: Sink, snaplen: usize) -> Self {
+        PcapTap {
+            state: Arc::new(TapState {
+                sink: Mutex::new(sink),
+                enabled: AtomicBool::new(true),
+                snaplen,
+                captured: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Create a ring tap capturing at most `snaplen` bytes of each packet.
+    pub fn ring_with_snaplen(capacity: usize, snaplen: usize) -> Self {
+        let packets = VecDeque::with_capacity(capacity.min(65536));
+        Self::with_sink(Sink::Ring { packets, capacity }, snaplen.max(1))
+    }
+
+    /// Create a tap writing to `writer`, capturing at most `snaplen` bytes of each packet.
+    pub fn with_snaplen<W: Write + Send + 'static>(writer: W, snaplen: usize) -> Result<Self, Error> {
+        Self::with_writer(Box::new(writer), snaplen.max(1))
+    }
+
+    /// Switch capturing on or off; the tap starts enabled.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Check whether packets are being captured.
+    pub fn is_enabled(&self) -> bool {
+        self.state.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of packets captured and lost.
+    pub fn stats(&self) -> TapStats {
+        TapStats {
+            captured: self.state.captured.load(Ordering::Relaxed),
+            failed: self.state.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Record `payload` sent from `src` to `dst`, timestamped now.
+    pub fn record(&self, protocol: Protocol, src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Result<(), Error> {
+        self.record_at(wall_clock_ns(), protocol, src, dst, payload)
+    }
+
+    /// Record `payload` with an explicit timestamp in nanoseconds since the epoch.
+    ///
+    /// Does nothing while the tap is disabled. Payloads too large for one IP packet are
+    /// cut to fit; the original length is kept in the record.
+    pub fn record_at(&self, timestamp_ns: u64, protocol: Protocol, src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Result<(), Error> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let packet = build_packet(protocol, src, dst, &payload[..payload.len().min(MAX_PAYLOAD)]);
+        let orig_len = packet.len() + payload.len().saturating_sub(MAX_PAYLOAD);
+        let record = encode_record(timestamp_ns, &packet[..packet.len().min(self.state.snaplen)], orig_len);
+
+        let mut sink = self.state.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let result = match &mut *sink {
+            Sink::Writer(writer) => writer.write_all(&record),
+            Sink::Ring { packets, capacity } => {
+                if *capacity > 0 {
+                    if packets.len() == *capacity {
+                        packets.pop_front();
+                    }
+                    packets.push_back(record);
+                }
+                Ok(())
+            }
+        };
+        match &result {
+            Ok(()) => self.state.captured.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.state.failed.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    /// Flush a file or writer tap.
+    pub fn flush(&self) -> Result<(), Error> {
+        match &mut *self.state.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            Sink::Writer(writer) => writer.flush(),
+            Sink::Ring { .. } => Ok(()),
+        }
+    }
+
+    /// Get the number of packets held by a ring tap (0 for writer taps).
+    pub fn ring_len(&self) -> usize {
+        match &*self.state.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            Sink::Ring { packets, .. } => packets.len(),
+            Sink::Writer(_) => 0,
+        }
+    }
+
+    /// Write the packets held by a ring tap to `writer` as a pcap file.
+    ///
+    /// The ring keeps its packets; a writer tap writes just the file header.
+    pub fn write_ring<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(&file_header(self.state.snaplen))?;
+        if let Sink::Ring { packets, .. } = &*self.state.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            for record in packets {
+                writer.write_all(record)?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Drop the packets held by a ring tap.
+    pub fn clear_ring(&self) {
+        if let Sink::Ring { packets, .. } = &mut *self.state.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            packets.clear();
+        }
+    }
+}
+
+/// Socket wrapper recording its traffic to a [`PcapTap`].
+#[derive(Debug)]
+pub struct Tapped<T> {
+    inner: T,
+    tap: PcapTap,
+    /// Bytes sent and received so far, the synthesized TCP sequence numbers
+    tx_seq: u32,
+    rx_seq: u32,
+}
+
+impl<T> Tapped<T> {
+    /// Wrap `inner`, recording to `tap`.
+    pub fn new(inner: T, tap: PcapTap) -> Self {
+        Tapped { inner, tap, tx_seq: 0, rx_seq: 0 }
+    }
+
+    /// Get the tap the traffic is recorded to.
+    pub fn tap(&self) -> &PcapTap {
+        &self.tap
+    }
+
+    /// Get a reference to the wrapped socket.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped socket.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the socket.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl Tapped<VmaUdpSocket> {
+    /// Send to the connected peer, see [`VmaUdpSocket::send`].
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let sent = self.inner.send(data)?;
+        if sent > 0 && self.tap.is_enabled() {
+            let peer = self.inner.peer_addr().unwrap_or_else(unspecified);
+            self.capture(Protocol::Udp, self.local_addr(), peer, &data[..sent]);
+        }
+        Ok(sent)
+    }
+
+    /// Send to `addr:port`, see [`VmaUdpSocket::send_to`].
+    pub fn send_to<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16) -> Result<usize, Error> {
+        let addr = addr.into();
+        let sent = self.inner.send_to(data, addr.as_str(), port)?;
+        if sent > 0 && self.tap.is_enabled() {
+            let ip = addr.parse().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+            self.capture(Protocol::Udp, self.local_addr(), SocketAddr::new(ip, port), &data[..sent]);
+        }
+        Ok(sent)
+    }
+
+    /// Send to a prepared destination, see [`VmaUdpSocket::send_to_dest`].
+    pub fn send_to_dest(&mut self, data: &[u8], dest: &Destination) -> Result<usize, Error> {
+        let sent = self.inner.send_to_dest(data, dest)?;
+        if sent > 0 && self.tap.is_enabled() {
+            self.capture(Protocol::Udp, self.local_addr(), SocketAddr::V4(dest.addr()), &data[..sent]);
+        }
+        Ok(sent)
+    }
+
+    /// Receive from the connected peer, see [`VmaUdpSocket::recv`].
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, Error> {
+        let received = self.inner.recv(buffer, timeout_nano)?;
+        if received > 0 && self.tap.is_enabled() {
+            let peer = self.inner.peer_addr().unwrap_or_else(unspecified);
+            self.capture(Protocol::Udp, peer, self.local_addr(), &buffer[..received]);
+        }
+        Ok(received)
+    }
+
+    /// Receive with the source address, see [`VmaUdpSocket::recv_from`].
+    ///
+    /// Packets carrying a receive timestamp (`enable_timestamps`) are recorded with it.
+    pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, Error> {
+        let packet = self.inner.recv_from(buffer, timeout_nano)?;
+        if let Some(packet) = packet.as_ref().filter(|_| self.tap.is_enabled()) {
+            let local = match (packet.dst_addr, self.local_addr()) {
+                (Some(dst), local) => SocketAddr::new(IpAddr::V4(dst), local.port()),
+                (None, local) => local,
+            };
+            let timestamp = if packet.timestamp > 0 { packet.timestamp } else { wall_clock_ns() };
+            // Capture is best effort: a failing tap must not fail the receive
+            let _ = self.tap.record_at(timestamp, Protocol::Udp, packet.src_addr, local, &packet.data);
+        }
+        Ok(packet)
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr().unwrap_or_else(|_| unspecified())
+    }
+
+    fn capture(&self, protocol: Protocol, src: SocketAddr, dst: SocketAddr, payload: &[u8]) {
+        // Capture is best effort: a failing tap must not fail the socket call
+        let _ = self.tap.record(protocol, src, dst, payload);
+    }
+}
+
+impl Tapped<VmaTcpSocket> {
+    /// Send on the connection, see [`VmaTcpSocket::send`].
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let sent = self.inner.send(data)?;
+        self.capture_sent(&data[..sent]);
+        Ok(sent)
+    }
+
+    /// Receive from the connection, see [`VmaTcpSocket::recv`].
+    pub fn recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, Error> {
+        let received = self.inner.recv(buffer, timeout)?;
+        self.capture_received(&buffer[..received]);
+        Ok(received)
+    }
+
+    fn addrs(&self) -> (SocketAddr, SocketAddr) {
+        let local = self.inner.local_addr().unwrap_or_else(|_| unspecified());
+        (local, self.inner.peer_addr().unwrap_or_else(unspecified))
+    }
+
+    fn capture_sent(&mut self, data: &[u8]) {
+        if data.is_empty() || !self.tap.is_enabled() {
+            return;
+        }
+        let (local, peer) = self.addrs();
+        for segment in data.chunks(MAX_PAYLOAD) {
+            let protocol = Protocol::Tcp { seq: self.tx_seq, ack: self.rx_seq };
+            // Capture is best effort: a failing tap must not fail the socket call
+            let _ = self.tap.record(protocol, local, peer, segment);
+            self.tx_seq = self.tx_seq.wrapping_add(segment.len() as u32);
+        }
+    }
+
+    fn capture_received(&mut self, data: &[u8]) {
+        if data.is_empty() || !self.tap.is_enabled() {
+            return;
+        }
+        let (local, peer) = self.addrs();
+        for segment in data.chunks(MAX_PAYLOAD) {
+            let protocol = Protocol::Tcp { seq: self.rx_seq, ack: self.tx_seq };
+            let _ = self.tap.record(protocol, peer, local, segment);
+            self.rx_seq = self.rx_seq.wrapping_add(segment.len() as u32);
+        }
+    }
+}
+
+impl FrameTransport for Tapped<VmaTcpSocket> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        FrameTransport::write_all(&mut self.inner, data)?;
+        self.capture_sent(data);
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, Error> {
+        let received = FrameTransport::read(&mut self.inner, buffer, timeout)?;
+        if let Some(n) = received {
+            self.capture_received(&buffer[..n]);
+        }
+        Ok(received)
+    }
+}
+
+/// Pace of a [`PcapReplayer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Deliver packets as fast as they are asked for
+    Immediate,
+    /// Keep the captured gaps between packets
+    Original,
+    /// Keep the captured gaps divided by the factor (2.0 replays twice as fast)
+    Speed(f64),
+}
+
+/// A UDP datagram read from a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayPacket {
+    /// Capture timestamp in nanoseconds since the epoch
+    pub timestamp_ns: u64,
+    pub src_addr: SocketAddr,
+    pub dst_addr: SocketAddr,
+    /// Captured payload, shorter than `full_length` if the capture was cut at its snap length
+    pub data: Vec<u8>,
+    /// Payload length on the wire
+    pub full_length: usize,
+}
+
+/// Counters of a [`PcapReplayer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// Datagrams delivered or sent
+    pub packets: u64,
+    /// Payload bytes delivered or sent
+    pub bytes: u64,
+    /// Capture records that were not UDP datagrams or did not pass the filter
+    pub skipped: u64,
+}
+
+/// Predicate choosing the datagrams a [`PcapReplayer`] delivers.
+type ReplayFilter = Box<dyn FnMut(&ReplayPacket) -> bool + Send>;
+
+/// Link-layer framing of a capture's packets.
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    linktype: u32,
+    /// Timestamp units per second
+    resolution: u64,
+}
+
+#[derive(Debug)]
+enum CaptureFormat {
+    Pcap { big_endian: bool, interface: Interface },
+    PcapNg { big_endian: bool, interfaces: Vec<Interface> },
+}
+
+/// Feed of recorded UDP datagrams for regression tests of receivers.
+///
+/// Reads pcap (microsecond or nanosecond) and pcapng captures with Ethernet (VLAN tags
+/// included), Linux cooked, loopback or raw IP framing, and yields the UDP datagrams in
+/// them; TCP, other protocols and IP fragments are skipped. The receive methods mirror
+/// [`VmaUdpSocket`] so a handler can read from a capture instead of the wire, and
+/// [`replay_to`](Self::replay_to) sends the datagrams out again. With [`Timing::Original`]
+/// the captured inter-packet gaps are kept.
+pub struct PcapReplayer<R = BufReader<File>> {
+    reader: R,
+    format: CaptureFormat,
+    timing: Timing,
+    filter: Option<ReplayFilter>,
+    /// Next datagram, read but not yet due
+    pending: Option<ReplayPacket>,
+    /// Capture timestamp and monotonic time of the first delivered datagram
+    origin: Option<(u64, u64)>,
+    finished: bool,
+    stats: ReplayStats,
+}
+
+impl<R> std::fmt::Debug for PcapReplayer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapReplayer")
+            .field("format", &self.format)
+            .field("timing", &self.timing)
+            .field("finished", &self.finished)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl PcapReplayer {
+    /// Open the capture file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PcapReplayer<R> {
+    /// Read a capture from `reader`, starting with its file header.
+    ///
+    /// Fails with `ErrorKind::InvalidData` when the stream is not a pcap or pcapng capture.
+    pub fn from_reader(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let format = match magic {
+            [0x0a, 0x0d, 0x0d, 0x0a] => {
+                let mut header = [0u8; 8];
+                reader.read_exact(&mut header)?;
+                let big_endian = match &header[4..8] {
+                    [0x1a, 0x2b, 0x3c, 0x4d] => true,
+                    [0x4d, 0x3c, 0x2b, 0x1a] => false,
+                    _ => return Err(invalid("Invalid pcapng byte-order magic")),
+                };
+                let total_len = read_u32(&header[0..4], big_endian) as usize;
+                skip_block_rest(&mut reader, total_len, 12)?;
+                CaptureFormat::PcapNg { big_endian, interfaces: Vec::new() }
+            }
+            _ => {
+                let (big_endian, nanos) = match u32::from_le_bytes(magic) {
+                    0xa1b2_c3d4 => (false, false),
+                    0xd4c3_b2a1 => (true, false),
+                    PCAP_MAGIC_NS => (false, true),
+                    0x4d3c_b2a1 => (true, true),
+                    _ => return Err(invalid("Not a pcap or pcapng capture")),
+                };
+                let mut header = [0u8; 20];
+                reader.read_exact(&mut header)?;
+                let interface = Interface {
+                    linktype: read_u32(&header[16..20], big_endian) & 0xffff,
+                    resolution: if nanos { 1_000_000_000 } else { 1_000_000 },
+                };
+                CaptureFormat::Pcap { big_endian, interface }
+            }
+        };
+
+        Ok(PcapReplayer {
+            reader,
+            format,
+            timing: Timing::Immediate,
+            filter: None,
+            pending: None,
+            origin: None,
+            finished: false,
+            stats: ReplayStats::default(),
+        })
+    }
+
+    /// Set the pace of delivery (default [`Timing::Immediate`]).
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Deliver only the datagrams `filter` accepts, e.g. one multicast group.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnMut(&ReplayPacket) -> bool + Send + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Get the pace of delivery.
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Check whether every datagram of the capture was delivered.
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.pending.is_none()
+    }
+
+    /// Get the replay counters.
+    pub fn stats(&self) -> ReplayStats {
+        self.stats
+    }
+
+    /// Read the next datagram that passes the filter, ignoring the timing.
+    ///
+    /// Returns `Ok(None)` at the end of the capture.
+    pub fn next_packet(&mut self) -> Result<Option<ReplayPacket>, Error> {
+        if let Some(packet) = self.pending.take() {
+            return Ok(Some(packet));
+        }
+        while !self.finished {
+            let Some((interface, timestamp, frame)) = self.read_record()? else {
+                self.finished = true;
+                break;
+            };
+            let Some(packet) = parse_datagram(interface.linktype, &frame, timestamp_ns(timestamp, interface.resolution)) else {
+                self.stats.skipped += 1;
+                continue;
+            };
+            if self.filter.as_mut().is_some_and(|filter| !filter(&packet)) {
+                self.stats.skipped += 1;
+                continue;
+            }
+            return Ok(Some(packet));
+        }
+        Ok(None)
+    }
+
+    /// Receive the next datagram like [`VmaUdpSocket::recv_from`], waiting for it to be due.
+    ///
+    /// Returns `Ok(None)` when the datagram is not due within `timeout_nano` (it stays
+    /// queued) and, immediately, at the end of the capture. Payloads longer than `buffer`
+    /// are truncated, with the length on the wire in `full_length`.
+    pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, Error> {
+        let Some(replayed) = self.next_due(timeout_nano)? else {
+            return Ok(None);
+        };
+        let len = replayed.data.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&replayed.data[..len]);
+        Ok(Some(Packet {
+            data: replayed.data[..len].to_vec(),
+            src_addr: replayed.src_addr,
+            timestamp: replayed.timestamp_ns,
+            dst_addr: match replayed.dst_addr.ip() {
+                IpAddr::V4(dst) => Some(dst),
+                IpAddr::V6(_) => None,
+            },
+            if_index: 0,
+            full_length: replayed.full_length,
+        }))
+    }
+
+    /// Receive the next payload like [`VmaUdpSocket::recv`]; returns 0 on timeout and at the end.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, Error> {
+        let Some(replayed) = self.next_due(timeout_nano)? else {
+            return Ok(0);
+        };
+        let len = replayed.data.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&replayed.data[..len]);
+        Ok(len)
+    }
+
+    /// Send the remaining datagrams through `socket`, returning the replay counters.
+    ///
+    /// Datagrams go to their captured destination, or to `destination` when set.
+    pub fn replay_to(&mut self, socket: &mut VmaUdpSocket, destination: Option<SocketAddr>) -> Result<ReplayStats, Error> {
+        while let Some(packet) = self.next_due(None)? {
+            match destination.unwrap_or(packet.dst_addr) {
+                SocketAddr::V4(addr) => socket.send_to_dest(&packet.data, &Destination::from(addr))?,
+                addr => socket.send_to(&packet.data, addr.ip().to_string(), addr.port())?,
+            };
+        }
+        Ok(self.stats)
+    }
+
+    /// Take the next datagram once it is due, or `None` on timeout or at the end.
+    fn next_due(&mut self, timeout_nano: Option<u64>) -> Result<Option<ReplayPacket>, Error> {
+        let Some(packet) = self.next_packet()? else {
+            return Ok(None);
+        };
+        let now = clock::monotonic_nanos();
+        let speed = match self.timing {
+            Timing::Immediate => None,
+            Timing::Original => Some(1.0),
+            Timing::Speed(speed) => Some(speed.max(f64::MIN_POSITIVE)),
+        };
+        if let Some(speed) = speed {
+            let (first_ts, first_mono) = *self.origin.get_or_insert((packet.timestamp_ns, now));
+            let gap = packet.timestamp_ns.saturating_sub(first_ts) as f64 / speed;
+            let due = first_mono + gap as u64;
+            if let Some(timeout) = timeout_nano.filter(|&timeout| due > now.saturating_add(timeout)) {
+                wait_until(now + timeout);
+                self.pending = Some(packet);
+                return Ok(None);
+            }
+            wait_until(due);
+        }
+        self.stats.packets += 1;
+        self.stats.bytes += packet.data.len() as u64;
+        Ok(Some(packet))
+    }
+
+    /// Read the next packet record with its interface and raw timestamp.
+    fn read_record(&mut self) -> Result<Option<(Interface, u64, Vec<u8>)>, Error> {
+        match &mut self.format {
+            CaptureFormat::Pcap { big_endian, interface } => {
+                let mut header = [0u8; 16];
+                if !read_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let seconds = read_u32(&header[0..4], *big_endian) as u64;
+                let fraction = read_u32(&header[4..8], *big_endian) as u64;
+                let mut frame = vec![0u8; read_u32(&header[8..12], *big_endian) as usize];
+                self.reader.read_exact(&mut frame)?;
+                Ok(Some((*interface, seconds * interface.resolution + fraction, frame)))
+            }
+            CaptureFormat::PcapNg { big_endian, interfaces } => loop {
+                let mut header = [0u8; 8];
+                if !read_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let block_type = read_u32(&header[0..4], *big_endian);
+                let total_len = read_u32(&header[4..8], *big_endian) as usize;
+                if total_len < 12 {
+                    return Err(invalid("Invalid pcapng block length"));
+                }
+                let mut body = vec![0u8; total_len - 12];
+                self.reader.read_exact(&mut body)?;
+                self.reader.read_exact(&mut header[4..8])?;
+
+                match block_type {
+                    // Section header: a new section restarts the interface list
+                    0x0a0d_0d0a => interfaces.clear(),
+                    // Interface description
+                    1 if body.len() >= 8 => interfaces.push(Interface {
+                        linktype: read_u16(&body[0..2], *big_endian) as u32,
+                        resolution: interface_resolution(&body[8..], *big_endian),
+                    }),
+                    // Enhanced packet
+                    6 if body.len() >= 20 => {
+                        let interface = *interfaces
+                            .get(read_u32(&body[0..4], *big_endian) as usize)
+                            .ok_or_else(|| invalid("pcapng packet on an undeclared interface"))?;
+                        let timestamp = ((read_u32(&body[4..8], *big_endian) as u64) << 32) | read_u32(&body[8..12], *big_endian) as u64;
+                        let captured = (read_u32(&body[12..16], *big_endian) as usize).min(body.len() - 20);
+                        return Ok(Some((interface, timestamp, body[20..20 + captured].to_vec())));
+                    }
+                    _ => {}
+                }
+            },
+        }
+    }
+}
+
+/// Get the timestamp units per second from the options of an interface description block.
+fn interface_resolution(mut options: &[u8], big_endian: bool) -> u64 {
+    while options.len() >= 4 {
+        let code = read_u16(&options[0..2], big_endian);
+        let len = read_u16(&options[2..4], big_endian) as usize;
+        let value = &options[4..options.len().min(4 + len)];
+        match (code, value) {
+            // End of options
+            (0, _) => break,
+            // if_tsresol: a power of ten, or of two with the high bit set
+            (9, [resolution, ..]) => {
+                return if resolution & 0x80 == 0 {
+                    10u64.saturating_pow(*resolution as u32)
+                } else {
+                    2u64.saturating_pow((resolution & 0x7f) as u32)
+                };
+            }
+            _ => {}
+        }
+        options = &options[options.len().min(4 + len.div_ceil(4) * 4)..];
+    }
+    1_000_000
+}
+
+fn timestamp_ns(timestamp: u64, resolution: u64) -> u64 {
+    (timestamp as u128 * 1_000_000_000 / resolution.max(1) as u128) as u64
+}
+
+/// Extract the UDP datagram of a captured frame, if it carries an unfragmented one.
+fn parse_datagram(linktype: u32, frame: &[u8], timestamp_ns: u64) -> Option<ReplayPacket> {
+    let ip = match linktype {
+        // Ethernet, possibly VLAN tagged
+        1 => {
+            let mut offset = 12;
+            while matches!(frame.get(offset..offset + 2)?, [0x81, 0x00] | [0x88, 0xa8]) {
+                offset += 4;
+            }
+            &frame[offset + 2..]
+        }
+        // BSD loopback: 4-byte address family
+        0 => frame.get(4..)?,
+        // Raw IP, IPv4, IPv6
+        101 | 228 | 229 => frame,
+        // Linux cooked capture v1 and v2
+        113 => frame.get(16..)?,
+        276 => frame.get(20..)?,
+        _ => return None,
+    };
+
+    let (src, dst, segment) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            let total_len = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            if *ip.get(9)? != IPPROTO_UDP || fragment & 0x3fff != 0 || ip.len() < header_len {
+                return None;
+            }
+            let src: [u8; 4] = ip[12..16].try_into().ok()?;
+            let dst: [u8; 4] = ip[16..20].try_into().ok()?;
+            // Drop Ethernet padding behind short packets
+            let end = total_len.clamp(header_len, ip.len());
+            (IpAddr::from(src), IpAddr::from(dst), &ip[header_len..end])
+        }
+        6 => {
+            if *ip.get(6)? != IPPROTO_UDP || ip.len() < IPV6_HEADER_LEN {
+                return None;
+            }
+            let src: [u8; 16] = ip[8..24].try_into().ok()?;
+            let dst: [u8; 16] = ip[24..40].try_into().ok()?;
+            (IpAddr::from(src), IpAddr::from(dst), &ip[IPV6_HEADER_LEN..])
+        }
+        _ => return None,
+    };
+
+    let header = segment.get(..UDP_HEADER_LEN)?;
+    let full_length = (u16::from_be_bytes([header[4], header[5]]) as usize).saturating_sub(UDP_HEADER_LEN);
+    let payload = &segment[UDP_HEADER_LEN..];
+    Some(ReplayPacket {
+        timestamp_ns,
+        src_addr: SocketAddr::new(src, u16::from_be_bytes([header[0], header[1]])),
+        dst_addr: SocketAddr::new(dst, u16::from_be_bytes([header[2], header[3]])),
+        data: payload[..payload.len().min(full_length)].to_vec(),
+        full_length,
+    })
+}
+
+/// Sleep through most of the wait and spin the last stretch, like `pacing::Pacer`.
+fn wait_until(target_ns: u64) {
+    let now = clock::monotonic_nanos();
+    if target_ns > now + SPIN_THRESHOLD_NS {
+        std::thread::sleep(Duration::from_nanos(target_ns - now - SPIN_THRESHOLD_NS));
+    }
+    while clock::monotonic_nanos() < target_ns {
+        std::hint::spin_loop();
+    }
+}
+
+/// Fill `buffer`, returning `false` on a clean end of stream before the first byte.
+fn read_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated capture record")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn skip_block_rest<R: Read>(reader: &mut R, total_len: usize, read: usize) -> Result<(), Error> {
+    let rest = total_len.checked_sub(read).ok_or_else(|| invalid("Invalid pcapng block length"))?;
+    std::io::copy(&mut reader.take(rest as u64), &mut std::io::sink())?;
+    Ok(())
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn unspecified() -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)
+}
+
+fn wall_clock_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn file_header(snaplen: usize) -> [u8; 24] {
+    let mut header = [0u8; 24];
+    header[0..4].copy_from_slice(&PCAP_MAGIC_NS.to_le_bytes());
+    header[4..6].copy_from_slice(&2u16.to_le_bytes());
+    header[6..8].copy_from_slice(&4u16.to_le_bytes());
+    // Time zone offset and timestamp accuracy stay zero
+    header[16..20].copy_from_slice(&(snaplen as u32).to_le_bytes());
+    header[20..24].copy_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    header
+}
+
+fn encode_record(timestamp_ns: u64, captured: &[u8], orig_len: usize) -> Vec<u8> {
+    let mut record = Vec::with_capacity(16 + captured.len());
+    record.extend_from_slice(&((timestamp_ns / 1_000_000_000) as u32).to_le_bytes());
+    record.extend_from_slice(&((timestamp_ns % 1_000_000_000) as u32).to_le_bytes());
+    record.extend_from_slice(&(captured.len() as u32).to_le_bytes());
+    record.extend_from_slice(&(orig_len as u32).to_le_bytes());
+    record.extend_from_slice(captured);
+    record
+}
+
+/// Build an IP packet carrying `payload` in a UDP datagram or TCP segment.
+fn build_packet(protocol: Protocol, src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let (next_header, mut segment) = match protocol {
+        Protocol::Udp => {
+            let mut segment = Vec::with_capacity(UDP_HEADER_LEN + payload.len());
+            segment.extend_from_slice(&src.port().to_be_bytes());
+            segment.extend_from_slice(&dst.port().to_be_bytes());
+            segment.extend_from_slice(&((UDP_HEADER_LEN + payload.len()) as u16).to_be_bytes());
+            segment.extend_from_slice(&[0, 0]);
+            (IPPROTO_UDP, segment)
+        }
+        Protocol::Tcp { seq, ack } => {
+            let mut segment = Vec::with_capacity(TCP_HEADER_LEN + payload.len());
+            segment.extend_from_slice(&src.port().to_be_bytes());
+            segment.extend_from_slice(&dst.port().to_be_bytes());
+            segment.extend_from_slice(&seq.to_be_bytes());
+            segment.extend_from_slice(&ack.to_be_bytes());
+            segment.extend_from_slice(&[(TCP_HEADER_LEN as u8 / 4) << 4, TCP_PSH_ACK]);
+            segment.extend_from_slice(&u16::MAX.to_be_bytes());
+            segment.extend_from_slice(&[0, 0, 0, 0]);
+            (IPPROTO_TCP, segment)
+        }
+    };
+    segment.extend_from_slice(payload);
+    let checksum_at = if next_header == IPPROTO_UDP { 6 } else { 16 };
+
+    let mut packet = Vec::with_capacity(IPV6_HEADER_LEN + segment.len());
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            let mut pseudo = Vec::with_capacity(12);
+            pseudo.extend_from_slice(&src.octets());
+            pseudo.extend_from_slice(&dst.octets());
+            pseudo.extend_from_slice(&[0, next_header]);
+            pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+            let checksum = transport_checksum(&pseudo, &segment);
+            segment[checksum_at..checksum_at + 2].copy_from_slice(&checksum.to_be_bytes());
+
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&((IPV4_HEADER_LEN + segment.len()) as u16).to_be_bytes());
+            // Identification, don't fragment, TTL 64
+            packet.extend_from_slice(&[0, 0, 0x40, 0, 64, next_header, 0, 0]);
+            packet.extend_from_slice(&src.octets());
+            packet.extend_from_slice(&dst.octets());
+            let checksum = !ones_complement_sum(0, &packet);
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+        (src, dst) => {
+            let src = to_ipv6(src).octets();
+            let dst = to_ipv6(dst).octets();
+            let mut pseudo = Vec::with_capacity(40);
+            pseudo.extend_from_slice(&src);
+            pseudo.extend_from_slice(&dst);
+            pseudo.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, next_header]);
+            let checksum = transport_checksum(&pseudo, &segment);
+            segment[checksum_at..checksum_at + 2].copy_from_slice(&checksum.to_be_bytes());
+
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&[next_header, 64]);
+            packet.extend_from_slice(&src);
+            packet.extend_from_slice(&dst);
+        }
+    }
+    packet.extend_from_slice(&segment);
+    packet
+}
+
+fn to_ipv6(ip: IpAddr) -> std::net::Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+/// Checksum of a UDP datagram or TCP segment behind its pseudo header.
+fn transport_checksum(pseudo: &[u8], segment: &[u8]) -> u16 {
+    let checksum = !ones_complement_sum(ones_complement_sum(0, pseudo) as u32, segment);
+    // A zero UDP checksum means "none"; its one's complement equivalent is sent instead
+    if checksum == 0 {
+        0xffff
+    } else {
+        checksum
+    }
+}
+
+/// Fold `data` into a 16-bit one's complement sum, starting from `initial`.
+fn ones_complement_sum(initial: u32, data: &[u8]) -> u16 {
+    let mut sum = initial;
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u32;
+    }
+    if let [last] = words.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn records(capture: &[u8]) -> Vec<&[u8]> {
+        let mut records = Vec::new();
+        let mut rest = &capture[24..];
+        while !rest.is_empty() {
+            let len = u32::from_le_bytes(rest[8..12].try_into().unwrap()) as usize;
+            records.push(&rest[16..16 + len]);
+            rest = &rest[16 + len..];
+        }
+        records
+    }
+
+    #[test]
+    fn test_ring_capture() {
+        let tap = PcapTap::ring(2);
+        let a: SocketAddr = "10.0.0.1:5001".parse().unwrap();
+        let b: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        tap.record(Protocol::Udp, a, b, b"first").unwrap();
+        tap.set_enabled(false);
+        tap.record(Protocol::Udp, a, b, b"ignored").unwrap();
+        tap.set_enabled(true);
+        tap.record(Protocol::Udp, b, a, b"odd").unwrap();
+        tap.record(Protocol::Tcp { seq: 7, ack: 9 }, a, b, b"segment").unwrap();
+        assert_eq!(tap.ring_len(), 2);
+        assert_eq!(tap.stats(), TapStats { captured: 3, failed: 0 });
+
+        let mut capture = Vec::new();
+        tap.write_ring(&mut capture).unwrap();
+        assert_eq!(u32::from_le_bytes(capture[0..4].try_into().unwrap()), PCAP_MAGIC_NS);
+        assert_eq!(u32::from_le_bytes(capture[20..24].try_into().unwrap()), LINKTYPE_RAW);
+
+        let packets = records(&capture);
+        assert_eq!(packets.len(), 2);
+        let (udp, tcp) = (packets[0], packets[1]);
+        assert_eq!(udp.len(), IPV4_HEADER_LEN + UDP_HEADER_LEN + 3);
+        assert_eq!((udp[9], &udp[12..16], &udp[28..]), (IPPROTO_UDP, &[10, 0, 0, 2][..], &b"odd"[..]));
+        assert_eq!(tcp[9], IPPROTO_TCP);
+        assert_eq!(u32::from_be_bytes(tcp[24..28].try_into().unwrap()), 7);
+        assert_eq!(&tcp[40..], b"segment");
+
+        // Valid checksums sum to all ones
+        for packet in [udp, tcp] {
+            assert_eq!(ones_complement_sum(0, &packet[..IPV4_HEADER_LEN]), 0xffff);
+            let mut pseudo = packet[12..20].to_vec();
+            pseudo.extend_from_slice(&[0, packet[9]]);
+            pseudo.extend_from_slice(&((packet.len() - IPV4_HEADER_LEN) as u16).to_be_bytes());
+            let sum = ones_complement_sum(ones_complement_sum(0, &pseudo) as u32, &packet[IPV4_HEADER_LEN..]);
+            assert_eq!(sum, 0xffff);
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let tap = PcapTap::ring(16);
+        let feed: SocketAddr = "239.1.1.1:5001".parse().unwrap();
+        let other: SocketAddr = "239.1.1.2:5002".parse().unwrap();
+        let src: SocketAddr = "10.0.0.1:40000".parse().unwrap();
+        tap.record_at(1_000_000_000, Protocol::Udp, src, feed, b"first").unwrap();
+        tap.record_at(1_000_000_500, Protocol::Udp, src, other, b"other").unwrap();
+        tap.record_at(1_000_001_000, Protocol::Tcp { seq: 0, ack: 0 }, src, feed, b"stream").unwrap();
+        tap.record_at(1_020_000_000, Protocol::Udp, src, feed, b"second").unwrap();
+        let mut capture = Vec::new();
+        tap.write_ring(&mut capture).unwrap();
+
+        let mut replayer = PcapReplayer::from_reader(&capture[..])
+            .unwrap()
+            .with_timing(Timing::Original)
+            .with_filter(move |packet| packet.dst_addr == feed);
+        let mut buffer = [0u8; 4];
+        let packet = replayer.recv_from(&mut buffer, None).unwrap().unwrap();
+        assert_eq!((packet.src_addr, packet.timestamp, packet.full_length), (src, 1_000_000_000, 5));
+        assert_eq!((&packet.data[..], &buffer), (&b"firs"[..], b"firs"));
+        assert_eq!(packet.dst_addr, Some(Ipv4Addr::new(239, 1, 1, 1)));
+
+        // The second datagram is due 20ms after the first
+        let start = std::time::Instant::now();
+        assert_eq!(replayer.recv(&mut buffer, Some(1_000_000)).unwrap(), 0);
+        assert!(!replayer.is_finished());
+        let mut buffer = [0u8; 16];
+        assert_eq!(replayer.recv(&mut buffer, None).unwrap(), 6);
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        assert!(replayer.recv_from(&mut buffer, None).unwrap().is_none());
+        assert!(replayer.is_finished());
+        assert_eq!(replayer.stats(), ReplayStats { packets: 2, bytes: 11, skipped: 2 });
+    }
+
+    #[test]
+    fn test_replay_pcapng_ethernet() {
+        // Ethernet frame with a VLAN tag, IPv4 and UDP, padded to the minimum frame size
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x64, 0x08, 0x00]);
+        let ip = build_packet(Protocol::Udp, "10.0.0.1:1000".parse().unwrap(), "10.0.0.2:2000".parse().unwrap(), b"md");
+        frame.extend_from_slice(&ip);
+        frame.resize(64, 0);
+
+        let block = |block_type: u32, body: &[u8]| {
+            let mut block = block_type.to_le_bytes().to_vec();
+            block.extend_from_slice(&((body.len() + 12) as u32).to_le_bytes());
+            block.extend_from_slice(body);
+            block.extend_from_slice(&((body.len() + 12) as u32).to_le_bytes());
+            block
+        };
+        let mut capture = block(0x0a0d_0d0a, &[0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        // Ethernet interface with nanosecond timestamps (if_tsresol = 9)
+        capture.extend(block(1, &[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]));
+        let mut packet = vec![0u8; 4];
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&42u32.to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&frame);
+        capture.extend(block(6, &packet));
+
+        let mut replayer = PcapReplayer::from_reader(&capture[..]).unwrap();
+        let packet = replayer.next_packet().unwrap().unwrap();
+        assert_eq!(packet.timestamp_ns, 42);
+        assert_eq!(packet.dst_addr, "10.0.0.2:2000".parse::<SocketAddr>().unwrap());
+        assert_eq!(packet.data, b"md");
+        assert_eq!(replayer.next_packet().unwrap(), None);
+        assert!(PcapReplayer::from_reader(&b"not a capture"[..]).is_err());
+    }
+
+    #[test]
+    fn test_writer_capture() {
+        let path = std::env::temp_dir().join(format!("vma-socket-pcap-{}.pcap", std::process::id()));
+        let tap = PcapTap::to_file(&path).unwrap();
+        let a: SocketAddr = "[::1]:5001".parse().unwrap();
+        let b: SocketAddr = "127.0.0.1:6001".parse().unwrap();
+        tap.record_at(1_500_000_000, Protocol::Udp, a, b, &[0u8; 100]).unwrap();
+        tap.flush().unwrap();
+
+        let capture = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let packets = records(&capture);
+        assert_eq!(packets.len(), 1);
+        // Mixed families are captured as IPv6 with the IPv4 address mapped
+        assert_eq!(packets[0][0] >> 4, 6);
+        assert_eq!(packets[0].len(), IPV6_HEADER_LEN + UDP_HEADER_LEN + 100);
+        assert_eq!(&capture[24..32], &[1, 0, 0, 0, 0x00, 0x65, 0xcd, 0x1d]);
+    }
+}
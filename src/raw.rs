@@ -0,0 +1,404 @@
+//! Packet sockets receiving and sending whole Ethernet frames.
+//!
+//! A [`RawSocket`] is an `AF_PACKET` socket bound to one interface, for custom protocol
+//! decoders and latency probes working below UDP and TCP on the same ports the
+//! offloaded sockets use. It takes the same [`VmaOptions`] as the UDP and TCP sockets:
+//! receives wait with `wait_strategy` and `spin_budget_us`, so a pinned capture thread
+//! busy-polls like the offloaded ones, `buffer_size` sizes the socket buffers and
+//! `enable_timestamps` stamps frames in the kernel.
+//!
+//! libvma offloads UDP and TCP only; frames of a packet socket take the kernel path, and
+//! [`RawSocket::vma_fd_stats`] tells whether a future libvma changes that. Frames
+//! consumed by VMA's offloaded sockets are steered to their rings and never reach the
+//! packet socket, so capture the traffic of other protocols, or of flows not offloaded.
+//!
+//! A classic BPF [`BpfProgram`] (e.g. from `tcpdump -dd`) is attached before the socket
+//! is bound, so rejected frames are never queued. Opening a packet socket needs
+//! `CAP_NET_RAW`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::raw::{BpfProgram, RawSocket, ALL_PROTOCOLS};
+//!
+//! // PTP over Ethernet only, interface in promiscuous mode while the socket is open
+//! let filter = BpfProgram::ethertype(0x88f7);
+//! let mut socket =
+//!     RawSocket::open("ens1f0", ALL_PROTOCOLS, true, Some(&filter), VmaOptions::low_latency()).unwrap();
+//!
+//! let mut buffer = vec![0u8; 2048];
+//! while let Some(frame) = socket.recv(&mut buffer, Some(100_000_000)).unwrap() {
+//!     println!("{:?} {} bytes at {}", frame.kind, frame.data.len(), frame.timestamp);
+//! }
+//! println!("dropped by the kernel: {}", socket.stats().unwrap().drops);
+//! ```
+
+use std::ffi::CString;
+use std::io::{Error, ErrorKind};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::raw::{c_char, c_int, c_void};
+
+use crate::common::{self, unixnano_to_ms, VmaFdStats, VmaOptions, WaitStrategy};
+
+/// Protocol receiving frames of every EtherType (`ETH_P_ALL`).
+pub const ALL_PROTOCOLS: u16 = libc::ETH_P_ALL as u16;
+
+/// Longest classic BPF program the kernel accepts (`BPF_MAXINSNS`).
+pub const BPF_MAX_INSTRUCTIONS: usize = 4096;
+
+/// Frame metadata, the layout matches `raw_packet_info_t` in `raw_socket.h`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RawPacketInfo {
+    pub(crate) length: usize,
+    pub(crate) full_length: usize,
+    pub(crate) timestamp: u64,
+    pub(crate) if_index: c_int,
+    pub(crate) protocol: u16,
+    pub(crate) pkt_type: u8,
+}
+
+// External declarations for the packet socket helpers
+extern "C" {
+    fn raw_socket_open(
+        ifname: *const c_char,
+        protocol: u16,
+        promiscuous: bool,
+        filter: *const libc::sock_filter,
+        filter_len: u16,
+        options: *const VmaOptions,
+    ) -> c_int;
+    fn raw_socket_set_filter(fd: c_int, filter: *const libc::sock_filter, filter_len: u16) -> c_int;
+    fn raw_socket_recv(
+        fd: c_int,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        info: *mut RawPacketInfo,
+        strategy: WaitStrategy,
+        spin_budget_us: u32,
+        last_rx_ns: u64,
+        timeout_ms: c_int,
+    ) -> c_int;
+    fn raw_socket_send(fd: c_int, frame: *const c_void, length: usize) -> isize;
+    fn raw_socket_stats(fd: c_int, packets: *mut u32, drops: *mut u32) -> c_int;
+    fn raw_socket_close(fd: c_int) -> c_int;
+}
+
+/// A classic BPF program deciding which frames a [`RawSocket`] receives.
+#[derive(Debug, Clone)]
+pub struct BpfProgram {
+    instructions: Vec<libc::sock_filter>,
+}
+
+impl BpfProgram {
+    /// Wrap compiled instructions, failing with `ErrorKind::InvalidInput` if there are none
+    /// or more than [`BPF_MAX_INSTRUCTIONS`].
+    pub fn new(instructions: Vec<libc::sock_filter>) -> Result<Self, Error> {
+        if instructions.is_empty() || instructions.len() > BPF_MAX_INSTRUCTIONS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("A BPF program has 1 to {} instructions", BPF_MAX_INSTRUCTIONS),
+            ));
+        }
+        Ok(BpfProgram { instructions })
+    }
+
+    /// Parse the output of `tcpdump -dd <expression>`, one `{ code, jt, jf, k },` per line.
+    pub fn from_tcpdump(text: &str) -> Result<Self, Error> {
+        let invalid = |line: &str| Error::new(ErrorKind::InvalidData, format!("Invalid BPF instruction: {}", line));
+        let mut instructions = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let fields: Vec<u32> = line
+                .trim_end_matches(',')
+                .trim_start_matches('{')
+                .trim_end_matches('}')
+                .split(',')
+                .map(|field| parse_number(field.trim()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid(line))?;
+            let [code, jt, jf, k] = fields[..] else { return Err(invalid(line)) };
+            let (Ok(code), Ok(jt), Ok(jf)) = (u16::try_from(code), u8::try_from(jt), u8::try_from(jf)) else {
+                return Err(invalid(line));
+            };
+            instructions.push(libc::sock_filter { code, jt, jf, k });
+        }
+        BpfProgram::new(instructions)
+    }
+
+    /// Accept only the frames of one EtherType (e.g. `0x88f7` for PTP).
+    pub fn ethertype(ethertype: u16) -> Self {
+        const LDH_ABS: u16 = (libc::BPF_LD | libc::BPF_H | libc::BPF_ABS) as u16;
+        const JEQ_K: u16 = (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16;
+        const RET_K: u16 = (libc::BPF_RET | libc::BPF_K) as u16;
+        BpfProgram {
+            instructions: vec![
+                libc::sock_filter { code: LDH_ABS, jt: 0, jf: 0, k: 12 },
+                libc::sock_filter { code: JEQ_K, jt: 0, jf: 1, k: ethertype as u32 },
+                libc::sock_filter { code: RET_K, jt: 0, jf: 0, k: u32::MAX },
+                libc::sock_filter { code: RET_K, jt: 0, jf: 0, k: 0 },
+            ],
+        }
+    }
+
+    /// Get the instructions of the program.
+    pub fn instructions(&self) -> &[libc::sock_filter] {
+        &self.instructions
+    }
+}
+
+/// Parse a decimal or `0x` hexadecimal number.
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Who a received frame was addressed to (`sll_pkttype`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketKind {
+    /// Addressed to this host
+    Host,
+    /// Link-layer broadcast
+    Broadcast,
+    /// Link-layer multicast
+    Multicast,
+    /// Addressed to another host, seen in promiscuous mode
+    OtherHost,
+    /// Sent by this host
+    Outgoing,
+    /// Any other type reported by the kernel
+    Other(u8),
+}
+
+impl PacketKind {
+    fn from_raw(value: u8) -> Self {
+        match value {
+            0 => PacketKind::Host,
+            1 => PacketKind::Broadcast,
+            2 => PacketKind::Multicast,
+            3 => PacketKind::OtherHost,
+            4 => PacketKind::Outgoing,
+            other => PacketKind::Other(other),
+        }
+    }
+}
+
+/// A frame received by [`RawSocket::recv`], borrowing the receive buffer.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// The frame, starting with the Ethernet header
+    pub data: &'a [u8],
+    /// Full length of the frame, larger than `data.len()` if it was truncated
+    pub full_length: usize,
+    /// Receive time in nanoseconds since the epoch (stamped by the kernel with `enable_timestamps`)
+    pub timestamp: u64,
+    /// EtherType of the frame
+    pub protocol: u16,
+    /// Who the frame was addressed to
+    pub kind: PacketKind,
+    /// Index of the interface the frame was received on
+    pub if_index: u32,
+}
+
+impl Frame<'_> {
+    /// Check whether the frame was larger than the receive buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.full_length > self.data.len()
+    }
+}
+
+/// Counters of a packet socket, from [`RawSocket::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RawStats {
+    /// Frames that passed the filter since the socket was opened
+    pub packets: u64,
+    /// Frames dropped because the receive queue was full
+    pub drops: u64,
+}
+
+/// An `AF_PACKET` socket bound to one interface.
+#[derive(Debug)]
+pub struct RawSocket {
+    fd: c_int,
+    interface: String,
+    options: VmaOptions,
+    last_rx_ns: u64,
+    stats: RawStats,
+}
+
+impl RawSocket {
+    /// Open a socket receiving every frame of `interface`, with default options.
+    pub fn new(interface: &str) -> Result<Self, Error> {
+        RawSocket::open(interface, ALL_PROTOCOLS, false, None, VmaOptions::default())
+    }
+
+    /// Open a socket on `interface` receiving the frames of EtherType `protocol`
+    /// ([`ALL_PROTOCOLS`] for all) that pass `filter`.
+    ///
+    /// With `promiscuous` the interface also delivers frames addressed to other hosts
+    /// while the socket is open. Fails with `ErrorKind::PermissionDenied` without
+    /// `CAP_NET_RAW`.
+    pub fn open(
+        interface: &str,
+        protocol: u16,
+        promiscuous: bool,
+        filter: Option<&BpfProgram>,
+        options: VmaOptions,
+    ) -> Result<Self, Error> {
+        options.validate()?;
+        let name = CString::new(interface)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Interface name contains NUL"))?;
+        let (filter_ptr, filter_len) = filter_parts(filter);
+        let fd = unsafe { raw_socket_open(name.as_ptr(), protocol, promiscuous, filter_ptr, filter_len, &options) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(RawSocket {
+            fd,
+            interface: interface.to_string(),
+            options,
+            last_rx_ns: 0,
+            stats: RawStats::default(),
+        })
+    }
+
+    /// Get the interface the socket is bound to.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// Get the options the socket was opened with.
+    pub fn options(&self) -> &VmaOptions {
+        &self.options
+    }
+
+    /// Replace the BPF filter, or remove it with `None`.
+    pub fn set_filter(&mut self, filter: Option<&BpfProgram>) -> Result<(), Error> {
+        let (filter_ptr, filter_len) = filter_parts(filter);
+        if unsafe { raw_socket_set_filter(self.fd, filter_ptr, filter_len) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Receive a frame into `buffer`, waiting up to `timeout_nano` (`None` waits forever).
+    ///
+    /// Returns `None` on timeout. A frame larger than `buffer` is truncated (see
+    /// [`Frame::is_truncated`]).
+    pub fn recv<'a>(&mut self, buffer: &'a mut [u8], timeout_nano: Option<u64>) -> Result<Option<Frame<'a>>, Error> {
+        let mut info = RawPacketInfo::default();
+        let result = unsafe {
+            raw_socket_recv(
+                self.fd,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                &mut info,
+                self.options.wait_strategy,
+                self.options.spin_budget_us,
+                self.last_rx_ns,
+                unixnano_to_ms(timeout_nano),
+            )
+        };
+        match result {
+            result if result < 0 => Err(Error::last_os_error()),
+            0 => Ok(None),
+            _ => {
+                self.last_rx_ns = info.timestamp;
+                Ok(Some(Frame {
+                    data: &buffer[..info.length],
+                    full_length: info.full_length,
+                    timestamp: info.timestamp,
+                    protocol: info.protocol,
+                    kind: PacketKind::from_raw(info.pkt_type),
+                    if_index: info.if_index as u32,
+                }))
+            }
+        }
+    }
+
+    /// Send a whole frame, starting with the Ethernet header, on the bound interface.
+    pub fn send(&mut self, frame: &[u8]) -> Result<usize, Error> {
+        let sent = unsafe { raw_socket_send(self.fd, frame.as_ptr() as *const c_void, frame.len()) };
+        if sent < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(sent as usize)
+    }
+
+    /// Get the frames received and dropped since the socket was opened.
+    pub fn stats(&mut self) -> Result<RawStats, Error> {
+        let (mut packets, mut drops) = (0u32, 0u32);
+        if unsafe { raw_socket_stats(self.fd, &mut packets, &mut drops) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        // The kernel resets its counters on every read
+        self.stats.packets += packets as u64;
+        self.stats.drops += drops as u64;
+        Ok(self.stats)
+    }
+
+    /// Get VMA offload information for the socket (not offloaded with current libvma releases).
+    pub fn vma_fd_stats(&self) -> Result<VmaFdStats, Error> {
+        common::fd_stats(self.fd)
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for RawSocket {
+    /// Close the socket, leaving promiscuous mode if it was entered.
+    fn drop(&mut self) {
+        unsafe { raw_socket_close(self.fd) };
+    }
+}
+
+/// Split an optional filter into the pointer and length passed to C.
+fn filter_parts(filter: Option<&BpfProgram>) -> (*const libc::sock_filter, u16) {
+    match filter {
+        Some(filter) => (filter.instructions.as_ptr(), filter.instructions.len() as u16),
+        None => (std::ptr::null(), 0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_raw_socket() {
+        let dump = "{ 0x28, 0, 0, 0x0000000c },\n{ 0x15, 0, 1, 0x000088b5 },\n{ 0x6, 0, 0, 0x00040000 },\n{ 0x6, 0, 0, 0x00000000 },\n";
+        let program = BpfProgram::from_tcpdump(dump).unwrap();
+        let ethertype = BpfProgram::ethertype(0x88b5);
+        assert_eq!(program.instructions().len(), 4);
+        assert_eq!((program.instructions()[1].code, program.instructions()[1].k), (0x15, 0x88b5));
+        assert_eq!(ethertype.instructions()[1].k, program.instructions()[1].k);
+        assert!(BpfProgram::from_tcpdump("{ 0x28, 0, 0 },").is_err());
+        assert!(BpfProgram::from_tcpdump("").is_err());
+
+        let mut receiver = match RawSocket::open("lo", ALL_PROTOCOLS, false, Some(&ethertype), VmaOptions::default()) {
+            Ok(socket) => socket,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => return, // no CAP_NET_RAW
+            Err(e) => panic!("{}", e),
+        };
+        let mut sender = RawSocket::new("lo").unwrap();
+        let mut frame = vec![0u8; 64];
+        frame[12..14].copy_from_slice(&0x88b5u16.to_be_bytes());
+        frame[14..19].copy_from_slice(b"probe");
+        assert_eq!(sender.send(&frame).unwrap(), frame.len());
+
+        let mut buffer = [0u8; 32];
+        let received = receiver.recv(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!((received.protocol, received.kind), (0x88b5, PacketKind::Host));
+        assert_eq!(&received.data[14..19], b"probe");
+        assert!(received.is_truncated() && received.full_length == frame.len());
+        assert_eq!(receiver.recv(&mut buffer, Some(0)).unwrap(), None);
+        assert_eq!(receiver.stats().unwrap().packets, 1);
+        assert!(RawSocket::new("vma-missing0").is_err());
+    }
+}
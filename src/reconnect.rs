@@ -0,0 +1,255 @@
+//! Automatic reconnection for TCP client sockets.
+//!
+//! A [`ReconnectPolicy`] describes how often and how fast a lost connection is retried.
+//! Install it with [`VmaTcpSocket::set_reconnect_policy`] and call
+//! [`VmaTcpSocket::reconnect_with_policy`] when a send or receive reports the connection
+//! as lost, or hand the socket to a [`Reconnector`] that restores it in the background.
+//!
+//! State transitions (`Connecting`, `Connected`, `Disconnected`) are reported through
+//! [`VmaTcpSocket::on_state_change`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//! use vma_socket::reconnect::{Backoff, ReconnectPolicy, Reconnector};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.set_reconnect_policy(Some(ReconnectPolicy {
+//!     max_attempts: Some(20),
+//!     backoff: Backoff::Exponential { initial: Duration::from_millis(10), max: Duration::from_secs(2) },
+//!     jitter: 0.2,
+//!     attempt_timeout: Some(500_000_000),
+//! }));
+//! socket.on_state_change(|state| println!("connection state: {:?}", state));
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let socket = Arc::new(Mutex::new(socket));
+//! let _reconnector = Reconnector::spawn(Arc::clone(&socket), Duration::from_millis(100)).unwrap();
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::tcp::VmaTcpSocket;
+
+/// Longest uninterrupted sleep of the reconnector thread, bounding how long `stop()` waits.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Delay between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// Always wait the same time
+    Fixed(Duration),
+    /// Double the delay after each failed attempt, starting at `initial`, capped at `max`
+    Exponential {
+        /// Delay after the first failed attempt
+        initial: Duration,
+        /// Upper bound of the delay
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Get the delay after the failed attempt `attempt` (0-based), without jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32.checked_shl(attempt.min(31)).unwrap_or(u32::MAX);
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+/// How a lost TCP connection is retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Maximum number of attempts before giving up (`None` retries forever)
+    pub max_attempts: Option<u32>,
+    /// Delay between attempts
+    pub backoff: Backoff,
+    /// Random spread applied to each delay, as a fraction of it (0.0 - 1.0)
+    pub jitter: f64,
+    /// Timeout of a single attempt in nanoseconds (`None` waits indefinitely)
+    pub attempt_timeout: Option<u64>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_attempts: Some(10),
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(10),
+                max: Duration::from_secs(1),
+            },
+            jitter: 0.1,
+            attempt_timeout: Some(1_000_000_000), // 1s
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Check whether another attempt is allowed after `attempts` failed ones.
+    pub fn allows(&self, attempts: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempts < max)
+    }
+
+    /// Get the delay after the failed attempt `attempt` (0-based), with jitter applied.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.backoff.delay(attempt);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        // Uniform factor in [1 - jitter, 1 + jitter]
+        let factor = 1.0 + jitter * (2.0 * random_unit(attempt) - 1.0);
+        delay.mul_f64(factor.max(0.0))
+    }
+}
+
+/// Cheap pseudo-random number in [0, 1) (xorshift over the clock; not for cryptography).
+fn random_unit(salt: u32) -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let mut x = nanos ^ ((salt as u64) << 32) ^ 0x9E37_79B9_7F4A_7C15;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Background thread restoring a shared socket's connection according to its policy.
+///
+/// The socket lock is held only while checking the connection and during a single
+/// attempt, never while backing off. The thread exits once the policy gives up.
+#[derive(Debug)]
+pub struct Reconnector {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Reconnector {
+    /// Start watching `socket`, checking the connection every `check_interval`.
+    pub fn spawn(socket: Arc<Mutex<VmaTcpSocket>>, check_interval: Duration) -> Result<Self, std::io::Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+
+        let handle = thread::Builder::new()
+            .name("vma-tcp-reconnect".to_string())
+            .spawn(move || run_reconnector(socket, check_interval, thread_running))?;
+
+        Ok(Reconnector {
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// Check whether the reconnector is still watching (it stops once the policy gives up).
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the reconnector and wait for its thread to exit.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Reconnector {
+    /// Stop the reconnector thread.
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Main loop of the reconnector thread.
+fn run_reconnector(socket: Arc<Mutex<VmaTcpSocket>>, check_interval: Duration, running: Arc<AtomicBool>) {
+    let mut failed_attempts = 0u32;
+
+    while running.load(Ordering::Acquire) {
+        let delay = {
+            let Ok(mut socket) = socket.lock() else { break };
+            if socket.is_connected() || !socket.has_remote() {
+                failed_attempts = 0;
+                check_interval
+            } else {
+                let policy = socket.reconnect_policy().copied().unwrap_or_default();
+                if !policy.allows(failed_attempts) {
+                    break;
+                }
+                match socket.try_reconnect(policy.attempt_timeout) {
+                    Ok(true) => {
+                        failed_attempts = 0;
+                        check_interval
+                    }
+                    _ => {
+                        let delay = policy.delay(failed_attempts);
+                        failed_attempts += 1;
+                        delay
+                    }
+                }
+            }
+        };
+
+        sleep_while_running(delay, &running);
+    }
+
+    running.store(false, Ordering::Release);
+}
+
+/// Sleep for `duration`, returning early once `running` is cleared.
+pub(crate) fn sleep_while_running(duration: Duration, running: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    loop {
+        let now = Instant::now();
+        if now >= deadline || !running.load(Ordering::Acquire) {
+            return;
+        }
+        thread::sleep((deadline - now).min(STOP_CHECK_INTERVAL));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(100),
+        };
+        assert_eq!(backoff.delay(0), Duration::from_millis(10));
+        assert_eq!(backoff.delay(2), Duration::from_millis(40));
+        assert_eq!(backoff.delay(10), Duration::from_millis(100));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(100));
+
+        let policy = ReconnectPolicy {
+            max_attempts: Some(3),
+            backoff: Backoff::Fixed(Duration::from_millis(100)),
+            jitter: 0.5,
+            attempt_timeout: None,
+        };
+        assert!(policy.allows(2));
+        assert!(!policy.allows(3));
+        for attempt in 0..100 {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+        }
+    }
+}
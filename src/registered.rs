@@ -0,0 +1,131 @@
+//! Pre-allocated, pinned send buffers for zero-copy transmission.
+//!
+//! A [`RegisteredBuffer`] is mapped and locked into memory once, up front, and is backed
+//! by hugepages when `VmaOptions::use_hugepages` is set. Ranges of it are sent with
+//! `send_registered()` on [`VmaTcpSocket`](crate::tcp::VmaTcpSocket) and
+//! [`VmaUdpSocket`](crate::udp::VmaUdpSocket), which hands the pages to the stack with
+//! MSG_ZEROCOPY instead of copying them into socket buffers.
+//!
+//! Since the stack reads the pages after the call returns, a sent range must not be
+//! rewritten until the socket's `zerocopy_pending()` drops to zero.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::registered::RegisteredBuffer;
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let options = VmaOptions::low_latency();
+//! let mut socket = VmaTcpSocket::with_options(options).unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let mut buffer = RegisteredBuffer::new(64 * 1024, &options).unwrap();
+//! buffer[..5].copy_from_slice(b"order");
+//! socket.send_registered(&buffer, 0..5).unwrap();
+//!
+//! // Wait until the stack released the pages before reusing the range
+//! while socket.zerocopy_pending() > 0 {
+//!     std::hint::spin_loop();
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::ops::{Deref, DerefMut, Range};
+
+use crate::common::{HugeBuf, VmaOptions};
+
+/// Memory mapped and locked once, for sending without per-call copies.
+#[derive(Debug)]
+pub struct RegisteredBuffer {
+    memory: HugeBuf,
+    pinned: bool,
+}
+
+impl RegisteredBuffer {
+    /// Allocate and pin a zeroed buffer of `len` bytes.
+    ///
+    /// Uses hugepages when `options.use_hugepages` is set, falling back to regular pages
+    /// when none are available. Pinning failures (e.g. a low `RLIMIT_MEMLOCK`) are not
+    /// fatal; check [`is_pinned`](Self::is_pinned).
+    pub fn new(len: usize, options: &VmaOptions) -> Result<Self, Error> {
+        let memory = HugeBuf::with_pages(len, options.use_hugepages)?;
+        let pinned = unsafe { libc::mlock(memory.as_ptr() as *const libc::c_void, memory.mapped_len()) } == 0;
+
+        Ok(RegisteredBuffer { memory, pinned })
+    }
+
+    /// Get the buffer length in bytes.
+    pub fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Check whether the buffer is empty (never true; kept for API symmetry with slices).
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty()
+    }
+
+    /// Check whether the buffer is backed by hugepages.
+    pub fn is_hugepage(&self) -> bool {
+        self.memory.is_hugepage()
+    }
+
+    /// Check whether the buffer is locked into memory.
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Get the byte range `range`, or an `InvalidInput` error if it is out of bounds.
+    pub(crate) fn get(&self, range: Range<usize>) -> Result<&[u8], Error> {
+        self.memory
+            .get(range)
+            .filter(|data| !data.is_empty())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Range out of buffer bounds or empty"))
+    }
+}
+
+impl Deref for RegisteredBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.memory
+    }
+}
+
+impl DerefMut for RegisteredBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+}
+
+impl Drop for RegisteredBuffer {
+    /// Unlock the buffer; the mapping is released by `HugeBuf` (pages still referenced by
+    /// in-flight sends stay valid for the stack).
+    fn drop(&mut self) {
+        if self.pinned {
+            unsafe {
+                libc::munlock(self.memory.as_ptr() as *const libc::c_void, self.memory.mapped_len());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registered_buffer() {
+        let options = VmaOptions { use_hugepages: true, ..VmaOptions::default() };
+        // Falls back to regular pages when the machine has no hugepages reserved
+        let mut buffer = RegisteredBuffer::new(10_000, &options).unwrap();
+        assert_eq!(buffer.len(), 10_000);
+        assert!(buffer.iter().all(|&b| b == 0));
+
+        buffer[100..105].copy_from_slice(b"hello");
+        assert_eq!(buffer.get(100..105).unwrap(), b"hello");
+        assert!(buffer.get(9_999..10_001).is_err());
+        assert!(buffer.get(5..5).is_err());
+        assert!(RegisteredBuffer::new(0, &options).is_err());
+    }
+}
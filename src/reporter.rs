@@ -0,0 +1,254 @@
+//! Periodic socket statistics reporting on a background thread.
+//!
+//! A [`StatsReporter`] samples the counters of a set of sockets every interval and hands
+//! one [`StatsRecord`] per socket to a callback ([`StatsReporter::spawn`]) or writes it as
+//! a logfmt line ([`StatsReporter::spawn_logging`]). Each record carries the totals, the
+//! increase since the previous sample and, when libvma is loaded, the VMA offload state,
+//! so a service gets rates and fallback detection without keeping previous values.
+//!
+//! The sockets are shared as `Arc<Mutex<_>>` and locked briefly once per interval. Sockets
+//! driven from a hot path are better sampled on their own thread with
+//! [`VmaUdpSocket::stats_delta`](crate::udp::VmaUdpSocket::stats_delta).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//! use vma_socket::common::StatsSource;
+//! use vma_socket::reporter::StatsReporter;
+//! use vma_socket::tcp::VmaTcpSocket;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let feed = Arc::new(Mutex::new(VmaUdpSocket::new().unwrap()));
+//! let orders = Arc::new(Mutex::new(VmaTcpSocket::new().unwrap()));
+//! let sockets: Vec<Arc<Mutex<dyn StatsSource + Send>>> = vec![feed.clone(), orders.clone()];
+//!
+//! let reporter = StatsReporter::spawn(Duration::from_secs(1), sockets, |record| {
+//!     if record.delta.rx_errors > 0 {
+//!         eprintln!("socket {}: {} receives failed", record.index, record.delta.rx_errors);
+//!     }
+//! }).unwrap();
+//!
+//! // ... use feed and orders ...
+//! reporter.stop();
+//! ```
+
+use std::fmt;
+use std::io::{Error, Write};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::common::{SocketStats, StatsSource, VmaFdStats};
+
+/// One sample of one socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsRecord {
+    /// Position of the socket in the list given to the reporter
+    pub index: usize,
+    /// Time of the sample in nanoseconds since the epoch
+    pub timestamp_ns: u64,
+    /// Time since the previous sample of this socket (since spawn for the first one)
+    pub elapsed: Duration,
+    /// Local address, if the socket is bound
+    pub local_addr: Option<SocketAddr>,
+    /// Remote address, if the socket is connected
+    pub peer_addr: Option<SocketAddr>,
+    /// Counters since the socket was created (or its counters were reset)
+    pub stats: SocketStats,
+    /// Counters accumulated since the previous sample
+    pub delta: SocketStats,
+    /// VMA offload state, when libvma is loaded
+    pub vma: Option<VmaFdStats>,
+}
+
+impl fmt::Display for StatsRecord {
+    /// Format the record as one logfmt line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addr = |addr: Option<SocketAddr>| addr.map_or_else(|| "-".to_string(), |addr| addr.to_string());
+        write!(
+            f,
+            "ts={} socket={} local={} remote={} elapsed_ms={}",
+            self.timestamp_ns,
+            self.index,
+            addr(self.local_addr),
+            addr(self.peer_addr),
+            self.elapsed.as_millis()
+        )?;
+        let counters = [
+            ("rx_packets", self.stats.rx_packets, self.delta.rx_packets),
+            ("tx_packets", self.stats.tx_packets, self.delta.tx_packets),
+            ("rx_bytes", self.stats.rx_bytes, self.delta.rx_bytes),
+            ("tx_bytes", self.stats.tx_bytes, self.delta.tx_bytes),
+            ("rx_errors", self.stats.rx_errors, self.delta.rx_errors),
+            ("tx_errors", self.stats.tx_errors, self.delta.tx_errors),
+            ("rx_truncated", self.stats.rx_truncated, self.delta.rx_truncated),
+            ("rx_eagain", self.stats.rx_eagain, self.delta.rx_eagain),
+            ("tx_eagain", self.stats.tx_eagain, self.delta.tx_eagain),
+            ("reconnects", self.stats.reconnects, self.delta.reconnects),
+        ];
+        for (name, total, delta) in counters {
+            write!(f, " {}={} {}_delta={}", name, total, name, delta)?;
+        }
+        if let Some(vma) = &self.vma {
+            write!(f, " offloaded={} rings={}", vma.offloaded, vma.ring_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Background thread sampling socket counters at a fixed interval.
+#[derive(Debug)]
+pub struct StatsReporter {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatsReporter {
+    /// Sample `sockets` every `interval` and call `callback` with one record per socket.
+    ///
+    /// The callback runs on the reporter thread. Sockets whose counters cannot be read
+    /// are skipped for that round.
+    pub fn spawn<S, F>(interval: Duration, sockets: Vec<Arc<Mutex<S>>>, mut callback: F) -> Result<Self, Error>
+    where
+        S: StatsSource + Send + ?Sized + 'static,
+        F: FnMut(&StatsRecord) + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        let thread = thread::Builder::new()
+            .name("vma-stats-reporter".to_string())
+            .spawn(move || {
+                let start = Instant::now();
+                let mut previous = vec![(SocketStats::default(), start); sockets.len()];
+                while thread_running.load(Ordering::Acquire) {
+                    thread::park_timeout(interval);
+                    if !thread_running.load(Ordering::Acquire) {
+                        break;
+                    }
+                    for (index, socket) in sockets.iter().enumerate() {
+                        let Ok(mut socket) = socket.lock() else { continue };
+                        if let Some(record) = sample(index, &mut *socket, &mut previous[index]) {
+                            drop(socket);
+                            callback(&record);
+                        }
+                    }
+                }
+            })?;
+
+        Ok(StatsReporter {
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Sample `sockets` every `interval` and write each record to `writer` as a logfmt line.
+    pub fn spawn_logging<S, W>(interval: Duration, sockets: Vec<Arc<Mutex<S>>>, mut writer: W) -> Result<Self, Error>
+    where
+        S: StatsSource + Send + ?Sized + 'static,
+        W: Write + Send + 'static,
+    {
+        Self::spawn(interval, sockets, move |record| {
+            let _ = writeln!(writer, "{}", record);
+        })
+    }
+
+    /// Check whether the reporter thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the reporter and wait for the current round to finish.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for StatsReporter {
+    /// Stop and join the reporter thread.
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Sample one socket, updating its previous counters and sample time.
+fn sample<S: StatsSource + ?Sized>(
+    index: usize,
+    socket: &mut S,
+    previous: &mut (SocketStats, Instant),
+) -> Option<StatsRecord> {
+    let stats = socket.stats().ok()?;
+    let now = Instant::now();
+    let record = StatsRecord {
+        index,
+        timestamp_ns: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+        elapsed: now.duration_since(previous.1),
+        local_addr: socket.local_addr().ok(),
+        peer_addr: socket.peer_addr(),
+        stats,
+        delta: stats.delta_since(&previous.0),
+        vma: socket.vma_fd_stats().ok().filter(|vma| vma.vma_loaded),
+    };
+    *previous = (stats, now);
+    Some(record)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+
+    struct Counter {
+        stats: SocketStats,
+    }
+
+    impl StatsSource for Counter {
+        fn stats(&mut self) -> Result<SocketStats, Error> {
+            self.stats.rx_packets += 5;
+            Ok(self.stats)
+        }
+
+        fn local_addr(&self) -> Result<SocketAddr, Error> {
+            Ok("127.0.0.1:5001".parse().unwrap())
+        }
+
+        fn peer_addr(&self) -> Option<SocketAddr> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_stats_reporter() {
+        let socket = Arc::new(Mutex::new(Counter { stats: SocketStats::default() }));
+        let (tx, rx) = mpsc::channel();
+        let reporter = StatsReporter::spawn(Duration::from_millis(10), vec![socket], move |record| {
+            let _ = tx.send(record.clone());
+        })
+        .unwrap();
+
+        let first = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        let second = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        reporter.stop();
+
+        assert_eq!((first.stats.rx_packets, first.delta.rx_packets), (5, 5));
+        assert_eq!((second.stats.rx_packets, second.delta.rx_packets), (10, 5));
+        assert_eq!(second.vma, None);
+        let line = second.to_string();
+        assert!(line.contains("socket=0 local=127.0.0.1:5001 remote=-"));
+        assert!(line.contains("rx_packets=10 rx_packets_delta=5"));
+    }
+}
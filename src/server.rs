@@ -0,0 +1,733 @@
+//! Multi-threaded TCP servers with per-core workers.
+//!
+//! A [`TcpServer`] owns a listening [`VmaTcpSocket`]. [`TcpServer::serve_with_workers`]
+//! starts an accept thread and N worker threads; accepted [`Client`]s are handed to the
+//! workers round-robin and served by the handler on the worker's thread.
+//!
+//! When `VmaOptions::cpu_cores` is set, worker `i` is pinned to `cpu_cores[i % cpu_cores_count]`.
+//! The accept thread parks between connections instead of busy-polling, so it does not
+//! occupy a core; accepted clients keep the configured wait strategy.
+//!
+//! For one-to-many distribution (e.g. market data), [`TcpServer::serve_broadcast`] instead
+//! registers every accepted client under a [`ClientId`] in a [`BroadcastServer`], whose
+//! [`broadcast`](BroadcastServer::broadcast) fans a message out to all of them. Clients that
+//! cannot keep up are handled by a [`SlowConsumerPolicy`].
+//!
+//! With [`TcpServer::set_idle_timeout`], accepted clients carry an idle timeout. Worker
+//! handlers check [`Client::is_idle`]; a broadcast server's accept thread reaps idle
+//! clients itself and reports them through [`BroadcastServer::take_reaped`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::server::TcpServer;
+//!
+//! let mut options = VmaOptions::low_latency();
+//! options.set_cores(&[2, 3]).unwrap();
+//!
+//! let server = TcpServer::bind("0.0.0.0", 5002, options).unwrap();
+//! let handle = server.serve_with_workers(2, |mut client, worker| {
+//!     let mut buffer = [0u8; 1024];
+//!     while worker.is_running() {
+//!         match client.recv(&mut buffer, Some(100_000_000)) {
+//!             Ok(received) => { let _ = client.send(&buffer[..received]); }
+//!             Err(_) => break,
+//!         }
+//!     }
+//! }).unwrap();
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! handle.stop().unwrap();
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::common::{self, MsgFlags, VmaOptions, WaitStrategy};
+use crate::shutdown::ShutdownToken;
+use crate::tcp::{AcceptFilter, Client, TcpResult, VmaTcpSocket};
+
+/// Accept timeout of the accept loop (100ms), bounding how long `stop()` waits.
+const ACCEPT_POLL_TIMEOUT_NS: u64 = 100_000_000;
+
+/// How long idle workers wait for a client before checking for shutdown.
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Pause after a failed accept (e.g. out of file descriptors) before retrying.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(1);
+
+/// Clients that may wait for each worker before new connections are refused.
+const WORKER_QUEUE_CAPACITY: usize = 64;
+
+/// Listen backlog of the server socket.
+const SERVER_BACKLOG: i32 = 128;
+
+/// Minimum time between idle scans of a broadcast server's clients.
+const REAP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Scratch size for discarding data broadcast clients send.
+const DRAIN_BUFFER_SIZE: usize = 4096;
+
+/// A bound and listening TCP server, ready to serve.
+#[derive(Debug)]
+pub struct TcpServer {
+    listener: VmaTcpSocket,
+    local_addr: SocketAddr,
+    options: VmaOptions,
+    idle_timeout: Option<Duration>,
+}
+
+impl TcpServer {
+    /// Bind to `addr:port` and start listening (port 0 picks an ephemeral port).
+    pub fn bind<A: Into<String>>(addr: A, port: u16, options: VmaOptions) -> Result<Self, Error> {
+        let mut listener = VmaTcpSocket::with_options(listener_options(options))?;
+        listener.bind(addr, port)?;
+        listener.listen(SERVER_BACKLOG)?;
+        let local_addr = listener.local_addr()?;
+
+        Ok(TcpServer {
+            listener,
+            local_addr,
+            options,
+            idle_timeout: None,
+        })
+    }
+
+    /// Get the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Only accept connections from peers for which `filter` returns `true`.
+    pub fn set_accept_filter(&mut self, filter: Option<AcceptFilter>) {
+        self.listener.set_accept_filter(filter);
+    }
+
+    /// Limit the number of clients served at once; further connections are closed.
+    pub fn set_max_connections(&mut self, max: Option<usize>) {
+        self.listener.set_max_connections(max);
+    }
+
+    /// Stop the server once `token` is triggered, as if its handle were stopped.
+    pub fn set_shutdown_token(&mut self, token: Option<ShutdownToken>) {
+        self.listener.set_shutdown_token(token);
+    }
+
+    /// Set the idle timeout given to every accepted client (`None` disables it).
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Accept connections on a background thread and serve them on `workers` threads.
+    ///
+    /// `handler` runs on the worker a client was dispatched to and owns the client until
+    /// it returns, so each worker serves its clients one at a time. Long-lived handlers
+    /// should return once [`Worker::is_running`] turns false. New connections are closed
+    /// right away when every worker's queue is full.
+    pub fn serve_with_workers<F>(self, workers: usize, handler: F) -> Result<ServerHandle, Error>
+    where
+        F: Fn(Client, &Worker) + Send + Sync + 'static,
+    {
+        if workers == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "At least one worker is required"));
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let handler = Arc::new(handler);
+        let cores = self.options.get_cores().to_vec();
+
+        let mut handle = ServerHandle {
+            local_addr: self.local_addr,
+            running: Arc::clone(&running),
+            acceptor: None,
+            workers: Vec::with_capacity(workers),
+        };
+
+        let mut queues = Vec::with_capacity(workers);
+        for index in 0..workers {
+            let (tx, rx) = mpsc::sync_channel(WORKER_QUEUE_CAPACITY);
+            let worker = Worker {
+                index,
+                core: if cores.is_empty() { None } else { Some(cores[index % cores.len()]) },
+                running: Arc::clone(&running),
+            };
+            let handler = Arc::clone(&handler);
+
+            // On failure the handle's Drop stops and joins the workers started so far
+            let spawned = thread::Builder::new()
+                .name(format!("vma-tcp-worker-{}", index))
+                .spawn(move || run_worker(worker, rx, handler))?;
+            handle.workers.push(spawned);
+            queues.push(tx);
+        }
+
+        let TcpServer { listener, options, idle_timeout, .. } = self;
+        let acceptor_running = Arc::clone(&running);
+        handle.acceptor = Some(
+            thread::Builder::new()
+                .name("vma-tcp-acceptor".to_string())
+                .spawn(move || run_acceptor(listener, options, idle_timeout, queues, acceptor_running))?,
+        );
+
+        Ok(handle)
+    }
+
+    /// Accept connections on a background thread and register them for broadcasting.
+    ///
+    /// Registered clients are only written to. With an idle timeout, data they send
+    /// (e.g. heartbeats) counts as activity and is discarded; otherwise it is never read.
+    pub fn serve_broadcast(self, policy: SlowConsumerPolicy) -> Result<BroadcastServer, Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let registry = Arc::new(Mutex::new(Registry::default()));
+
+        let TcpServer { listener, local_addr, idle_timeout, .. } = self;
+        let acceptor_running = Arc::clone(&running);
+        let acceptor_registry = Arc::clone(&registry);
+        let acceptor = thread::Builder::new()
+            .name("vma-tcp-acceptor".to_string())
+            .spawn(move || run_registry_acceptor(listener, idle_timeout, acceptor_registry, acceptor_running))?;
+
+        Ok(BroadcastServer {
+            local_addr,
+            policy,
+            registry,
+            running,
+            acceptor: Some(acceptor),
+        })
+    }
+}
+
+/// Identity of a worker thread, passed to the client handler.
+#[derive(Debug)]
+pub struct Worker {
+    index: usize,
+    core: Option<i32>,
+    running: Arc<AtomicBool>,
+}
+
+impl Worker {
+    /// Get the worker index (0-based).
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the CPU core the worker is pinned to, if any.
+    pub fn core(&self) -> Option<i32> {
+        self.core
+    }
+
+    /// Check whether the server is still running; handlers should return once it is not.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+}
+
+/// A running server; stopping or dropping it shuts the server down.
+#[derive(Debug)]
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Get the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the number of worker threads.
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Check whether the server is still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop accepting, let running handlers finish and wait for all threads to exit.
+    ///
+    /// Clients still queued for a worker are closed without being served. Returns an
+    /// error if a worker thread panicked.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.join_all()
+    }
+
+    fn join_all(&mut self) -> Result<(), Error> {
+        self.running.store(false, Ordering::Release);
+        let mut result = Ok(());
+        if let Some(acceptor) = self.acceptor.take() {
+            if acceptor.join().is_err() {
+                result = Err(Error::other("Acceptor thread panicked"));
+            }
+        }
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() && result.is_ok() {
+                result = Err(Error::other("Worker thread panicked"));
+            }
+        }
+        result
+    }
+}
+
+impl Drop for ServerHandle {
+    /// Stop the server and join its threads.
+    fn drop(&mut self) {
+        let _ = self.join_all();
+    }
+}
+
+/// Identifier of a client registered with a [`BroadcastServer`], unique per server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(u64);
+
+impl ClientId {
+    /// Get the numeric id (assigned in accept order, starting at 1).
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "client-{}", self.0)
+    }
+}
+
+/// What to do with a client whose socket cannot take a message right away.
+///
+/// The unsent tail of a partially written message is always queued (or the client
+/// disconnected), so the byte stream never loses part of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Skip messages for the client until its backlog is flushed
+    Drop,
+    /// Disconnect the client as soon as it falls behind
+    Disconnect,
+    /// Queue up to this many bytes for the client, disconnecting it beyond that
+    Buffer(usize),
+}
+
+/// Outcome of offering a message to one client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Written to the socket in full
+    Sent,
+    /// Partly or wholly queued behind the client's backlog
+    Queued,
+    /// Skipped under `SlowConsumerPolicy::Drop`
+    Dropped,
+    /// The client failed or fell too far behind and was removed
+    Disconnected,
+}
+
+/// Per-client outcome counts of a [`BroadcastServer::broadcast`] call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BroadcastReport {
+    /// Clients the message was written to in full
+    pub sent: usize,
+    /// Clients the message was queued for
+    pub queued: usize,
+    /// Clients the message was dropped for
+    pub dropped: usize,
+    /// Clients removed during the call
+    pub disconnected: Vec<ClientId>,
+}
+
+/// A client closed by the idle reaper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReapedClient {
+    /// Registry id of the client
+    pub id: ClientId,
+    /// Remote address of the client
+    pub address: SocketAddr,
+    /// How long the client had been silent
+    pub idle: Duration,
+    /// Whether the peer had already closed the connection
+    pub closed_by_peer: bool,
+}
+
+/// A registered client and the bytes it has not taken yet.
+#[derive(Debug)]
+struct Subscriber {
+    client: Client,
+    backlog: VecDeque<u8>,
+}
+
+/// Write without blocking, returning the bytes the socket took.
+fn write_nonblocking(client: &mut Client, data: &[u8]) -> Result<usize, TcpResult> {
+    match client.send_with_flags(data, MsgFlags::DONTWAIT) {
+        Ok(sent) => Ok(sent),
+        Err(TcpResult::TcpErrorWouldBlock) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+impl Subscriber {
+    /// Discard pending input, returning `false` once the peer closed or the socket failed.
+    fn drain(&mut self, scratch: &mut [u8]) -> bool {
+        loop {
+            match self.client.recv_with_flags(scratch, Some(0), MsgFlags::DONTWAIT) {
+                Ok(_) => {}
+                Err(TcpResult::TcpErrorTimeout) | Err(TcpResult::TcpErrorWouldBlock) => return true,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Write as much of the backlog as the socket takes.
+    fn flush(&mut self) -> Result<(), TcpResult> {
+        while !self.backlog.is_empty() {
+            let (chunk, _) = self.backlog.as_slices();
+            let len = chunk.len();
+            let sent = write_nonblocking(&mut self.client, chunk)?;
+            self.backlog.drain(..sent);
+            if sent < len {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Offer a message, applying `policy` if the client is behind.
+    fn offer(&mut self, data: &[u8], policy: SlowConsumerPolicy) -> Delivery {
+        // The backlog goes first so messages stay in order
+        if self.flush().is_err() {
+            return Delivery::Disconnected;
+        }
+
+        if !self.backlog.is_empty() {
+            return match policy {
+                SlowConsumerPolicy::Drop => Delivery::Dropped,
+                SlowConsumerPolicy::Disconnect => Delivery::Disconnected,
+                SlowConsumerPolicy::Buffer(limit) if self.backlog.len() + data.len() > limit => Delivery::Disconnected,
+                SlowConsumerPolicy::Buffer(_) => {
+                    self.backlog.extend(data);
+                    Delivery::Queued
+                }
+            };
+        }
+
+        let sent = match write_nonblocking(&mut self.client, data) {
+            Ok(sent) => sent,
+            Err(_) => return Delivery::Disconnected,
+        };
+        if sent == data.len() {
+            return Delivery::Sent;
+        }
+
+        let rest = &data[sent..];
+        match policy {
+            SlowConsumerPolicy::Disconnect => Delivery::Disconnected,
+            SlowConsumerPolicy::Buffer(limit) if rest.len() > limit => Delivery::Disconnected,
+            // Drop still keeps the tail of a started message
+            _ => {
+                self.backlog.extend(rest);
+                Delivery::Queued
+            }
+        }
+    }
+}
+
+/// Clients of a broadcast server, in accept order.
+#[derive(Debug, Default)]
+struct Registry {
+    next_id: u64,
+    clients: BTreeMap<ClientId, Subscriber>,
+    reaped: Vec<ReapedClient>,
+}
+
+impl Registry {
+    /// Close clients with an idle timeout that are idle or closed by the peer.
+    fn reap(&mut self) -> Vec<ReapedClient> {
+        let mut reaped = Vec::new();
+        let mut scratch = [0u8; DRAIN_BUFFER_SIZE];
+        self.clients.retain(|&id, subscriber| {
+            if subscriber.client.idle_timeout().is_none() {
+                return true;
+            }
+            let open = subscriber.drain(&mut scratch);
+            if open && !subscriber.client.is_idle() {
+                return true;
+            }
+            reaped.push(ReapedClient {
+                id,
+                address: subscriber.client.address,
+                idle: subscriber.client.idle_time(),
+                closed_by_peer: !open,
+            });
+            false
+        });
+        reaped
+    }
+}
+
+/// A running broadcast server; stopping or dropping it closes all clients.
+#[derive(Debug)]
+pub struct BroadcastServer {
+    local_addr: SocketAddr,
+    policy: SlowConsumerPolicy,
+    registry: Arc<Mutex<Registry>>,
+    running: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl BroadcastServer {
+    /// Get the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the slow-consumer policy.
+    pub fn policy(&self) -> SlowConsumerPolicy {
+        self.policy
+    }
+
+    /// Get the number of registered clients.
+    pub fn client_count(&self) -> usize {
+        self.registry().clients.len()
+    }
+
+    /// List the registered clients with their remote addresses.
+    pub fn clients(&self) -> Vec<(ClientId, SocketAddr)> {
+        self.registry()
+            .clients
+            .iter()
+            .map(|(&id, subscriber)| (id, subscriber.client.address))
+            .collect()
+    }
+
+    /// Get the number of bytes queued for a client, or `None` if it is not registered.
+    pub fn backlog(&self, id: ClientId) -> Option<usize> {
+        self.registry().clients.get(&id).map(|subscriber| subscriber.backlog.len())
+    }
+
+    /// Send `data` to every registered client without blocking.
+    ///
+    /// Clients that fail, or fall behind further than the policy allows, are closed
+    /// and removed.
+    pub fn broadcast(&self, data: &[u8]) -> BroadcastReport {
+        let mut report = BroadcastReport::default();
+        if data.is_empty() {
+            return report;
+        }
+
+        let mut registry = self.registry();
+        registry.clients.retain(|&id, subscriber| match subscriber.offer(data, self.policy) {
+            Delivery::Sent => {
+                report.sent += 1;
+                true
+            }
+            Delivery::Queued => {
+                report.queued += 1;
+                true
+            }
+            Delivery::Dropped => {
+                report.dropped += 1;
+                true
+            }
+            Delivery::Disconnected => {
+                report.disconnected.push(id);
+                false
+            }
+        });
+        report
+    }
+
+    /// Send `data` to a single client, under the same policy as `broadcast`.
+    ///
+    /// Fails with `ErrorKind::NotFound` if the client is not registered.
+    pub fn send_to(&self, id: ClientId, data: &[u8]) -> Result<Delivery, Error> {
+        let mut registry = self.registry();
+        let subscriber = registry
+            .clients
+            .get_mut(&id)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Unknown client"))?;
+        if data.is_empty() {
+            return Ok(Delivery::Sent);
+        }
+
+        let delivery = subscriber.offer(data, self.policy);
+        if delivery == Delivery::Disconnected {
+            registry.clients.remove(&id);
+        }
+        Ok(delivery)
+    }
+
+    /// Retry the queued bytes of every client, returning the clients removed on failure.
+    ///
+    /// Call it between broadcasts when traffic pauses so backlogs drain.
+    pub fn flush(&self) -> Vec<ClientId> {
+        let mut failed = Vec::new();
+        self.registry().clients.retain(|&id, subscriber| {
+            let ok = subscriber.flush().is_ok();
+            if !ok {
+                failed.push(id);
+            }
+            ok
+        });
+        failed
+    }
+
+    /// Close and remove a client, returning whether it was registered.
+    pub fn disconnect(&self, id: ClientId) -> bool {
+        self.registry().clients.remove(&id).is_some()
+    }
+
+    /// Close idle clients now, returning them (they are not reported by `take_reaped`).
+    pub fn reap_idle(&self) -> Vec<ReapedClient> {
+        self.registry().reap()
+    }
+
+    /// Take the clients the accept thread reaped since the last call.
+    pub fn take_reaped(&self) -> Vec<ReapedClient> {
+        std::mem::take(&mut self.registry().reaped)
+    }
+
+    /// Check whether the server is still accepting.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop accepting and close all clients (queued bytes are discarded).
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+        self.registry().clients.clear();
+    }
+
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        // Registry updates cannot be left half-done, so a poisoned lock is still usable
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for BroadcastServer {
+    /// Stop the acceptor and close all clients.
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Accept loop: hand new clients to the workers round-robin.
+fn run_acceptor(
+    mut listener: VmaTcpSocket,
+    options: VmaOptions,
+    idle_timeout: Option<Duration>,
+    queues: Vec<SyncSender<Client>>,
+    running: Arc<AtomicBool>,
+) {
+    let mut next = 0;
+    while running.load(Ordering::Acquire) {
+        let mut client = match listener.accept(Some(ACCEPT_POLL_TIMEOUT_NS)) {
+            Ok(Some(client)) => client,
+            Ok(None) => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {
+                // Shutdown token triggered: stop the workers too
+                running.store(false, Ordering::Release);
+                break;
+            }
+            Err(_) => {
+                // Transient (aborted handshake, fd exhaustion); keep serving
+                thread::sleep(ACCEPT_ERROR_BACKOFF);
+                continue;
+            }
+        };
+        client.set_wait_strategy(options.wait_strategy, options.spin_budget_us);
+        client.set_idle_timeout(idle_timeout);
+
+        // First worker with room, starting after the last one used
+        for offset in 0..queues.len() {
+            let index = (next + offset) % queues.len();
+            match queues[index].try_send(client) {
+                Ok(()) => {
+                    next = index + 1;
+                    break;
+                }
+                Err(TrySendError::Full(rejected)) | Err(TrySendError::Disconnected(rejected)) => {
+                    client = rejected;
+                }
+            }
+        }
+        // A client nobody took is dropped here, closing the connection
+    }
+}
+
+/// Accept loop of a broadcast server: register new clients and reap idle ones.
+fn run_registry_acceptor(
+    mut listener: VmaTcpSocket,
+    idle_timeout: Option<Duration>,
+    registry: Arc<Mutex<Registry>>,
+    running: Arc<AtomicBool>,
+) {
+    let mut last_reap = Instant::now();
+    while running.load(Ordering::Acquire) {
+        match listener.accept(Some(ACCEPT_POLL_TIMEOUT_NS)) {
+            Ok(Some(mut client)) => {
+                client.set_idle_timeout(idle_timeout);
+                let mut registry = registry.lock().unwrap_or_else(|e| e.into_inner());
+                registry.next_id += 1;
+                let id = ClientId(registry.next_id);
+                registry.clients.insert(id, Subscriber { client, backlog: VecDeque::new() });
+            }
+            Ok(None) => {}
+            Err(e) if e.kind() == ErrorKind::Interrupted => {
+                running.store(false, Ordering::Release);
+                break;
+            }
+            // Transient (aborted handshake, fd exhaustion); keep serving
+            Err(_) => thread::sleep(ACCEPT_ERROR_BACKOFF),
+        }
+
+        if idle_timeout.is_some() && last_reap.elapsed() >= REAP_INTERVAL {
+            last_reap = Instant::now();
+            let mut registry = registry.lock().unwrap_or_else(|e| e.into_inner());
+            let reaped = registry.reap();
+            registry.reaped.extend(reaped);
+        }
+    }
+}
+
+/// Worker loop: serve dispatched clients until the server stops.
+fn run_worker<F>(worker: Worker, queue: Receiver<Client>, handler: Arc<F>)
+where
+    F: Fn(Client, &Worker),
+{
+    if let Some(core) = worker.core {
+        // Serving unpinned beats not serving
+        let _ = common::pin_current_thread_to(&[core]);
+    }
+
+    while worker.is_running() {
+        match queue.recv_timeout(WORKER_POLL_INTERVAL) {
+            Ok(client) => handler(client, &worker),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// Options of the listening socket: the same as `options`, but parking between connections.
+fn listener_options(options: VmaOptions) -> VmaOptions {
+    VmaOptions {
+        wait_strategy: WaitStrategy::SpinPark,
+        spin_budget_us: 0,
+        ..options
+    }
+}
@@ -0,0 +1,681 @@
+//! Sequenced session layer for order-entry links, modelled on the FIX session protocol.
+//!
+//! Enabled with the `session` feature. A [`Session`] runs over a [`FramedTcp`] and adds
+//! what exchange gateways expect from a session engine:
+//!
+//! - logon with identification and the heartbeat interval, logout with a reason;
+//! - a sequence number on every message, checked on receipt;
+//! - gap detection: a message beyond the expected sequence number triggers a resend
+//!   request, and messages are delivered in order once the gap is filled;
+//! - resend handling: requested application messages are sent again (flagged as possible
+//!   duplicates) from a bounded store, and anything no longer stored is skipped with a
+//!   gap-fill sequence reset;
+//! - heartbeats while idle and dead-peer detection (see [`crate::heartbeat`]).
+//!
+//! Sequence numbers survive reconnects when the caller persists [`Session::next_out_seq`]
+//! and [`Session::next_in_seq`] and passes them back through [`SessionConfig`].
+//!
+//! # Wire format
+//!
+//! Each frame is a message type byte, a flags byte, the sequence number (8 bytes,
+//! big-endian) and a type-specific body. The layout mirrors FIX semantics but is binary;
+//! it is not FIX tag=value.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use vma_socket::framed::FramedTcp;
+//! use vma_socket::session::{Session, SessionConfig, SessionEvent};
+//! use vma_socket::tcp::VmaTcpSocket;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 9000, Some(1_000_000_000)).unwrap();
+//!
+//! let config = SessionConfig::new("GATEWAY1").with_heartbeat_interval(Duration::from_secs(1));
+//! let mut session = Session::initiate(FramedTcp::new(socket), config, Some(5_000_000_000)).unwrap();
+//! println!("logged on to {}", session.peer_id());
+//!
+//! session.send(b"new order AAPL 100@150.25").unwrap();
+//! loop {
+//!     match session.recv(Some(100_000_000)).unwrap() {
+//!         Some(SessionEvent::Message { seq, payload, .. }) => println!("#{}: {} bytes", seq, payload.len()),
+//!         Some(SessionEvent::Logout { reason }) => {
+//!             println!("logged out: {}", reason);
+//!             break;
+//!         }
+//!         None => {}
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+use crate::framed::{FrameTransport, FramedTcp};
+use crate::heartbeat::{Heartbeat, HeartbeatSession, HeartbeatStatus};
+use crate::tcp::VmaTcpSocket;
+
+/// Default heartbeat interval proposed at logon.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default number of sent application messages kept for resends.
+pub const DEFAULT_RESEND_CAPACITY: usize = 10_000;
+
+/// Length of the type, flags and sequence number preceding every body.
+const HEADER_LEN: usize = 10;
+
+/// Flag of messages sent again in reply to a resend request.
+const FLAG_POSS_DUP: u8 = 0x01;
+
+/// Message types on the wire.
+const MSG_LOGON: u8 = b'A';
+const MSG_LOGOUT: u8 = b'5';
+const MSG_HEARTBEAT: u8 = b'0';
+const MSG_RESEND_REQUEST: u8 = b'2';
+const MSG_SEQUENCE_RESET: u8 = b'4';
+const MSG_APPLICATION: u8 = b'D';
+
+/// Settings of a [`Session`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Identification sent in the logon
+    pub sender_id: String,
+    /// Identification the peer must log on with (`None` accepts any)
+    pub expected_peer_id: Option<String>,
+    /// Heartbeat interval proposed by the initiator; the acceptor adopts the initiator's
+    pub heartbeat_interval: Duration,
+    /// Intervals of inbound silence after which the peer is considered dead
+    pub stale_intervals: u32,
+    /// Sequence number of the first message this end sends
+    pub next_out_seq: u64,
+    /// Sequence number expected for the first message from the peer
+    pub next_in_seq: u64,
+    /// Sent application messages kept for answering resend requests
+    pub resend_capacity: usize,
+}
+
+impl SessionConfig {
+    /// Create a configuration for a fresh session (both sequences starting at 1).
+    pub fn new<S: Into<String>>(sender_id: S) -> Self {
+        SessionConfig {
+            sender_id: sender_id.into(),
+            expected_peer_id: None,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            stale_intervals: 2,
+            next_out_seq: 1,
+            next_in_seq: 1,
+            resend_capacity: DEFAULT_RESEND_CAPACITY,
+        }
+    }
+
+    /// Only accept a peer logging on as `peer_id`.
+    pub fn with_expected_peer<S: Into<String>>(mut self, peer_id: S) -> Self {
+        self.expected_peer_id = Some(peer_id.into());
+        self
+    }
+
+    /// Set the heartbeat interval proposed at logon.
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Resume persisted sequence numbers after a reconnect.
+    pub fn with_sequences(mut self, next_out_seq: u64, next_in_seq: u64) -> Self {
+        self.next_out_seq = next_out_seq;
+        self.next_in_seq = next_in_seq;
+        self
+    }
+
+    /// Set how many sent application messages are kept for resends.
+    pub fn with_resend_capacity(mut self, resend_capacity: usize) -> Self {
+        self.resend_capacity = resend_capacity;
+        self
+    }
+}
+
+/// Session lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Logged on, exchanging messages
+    Active,
+    /// Logout sent, waiting for the peer's logout
+    LoggingOut,
+    /// Logouts exchanged (or the peer was declared dead); no more messages
+    LoggedOut,
+}
+
+/// Event returned by [`Session::recv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// An application message, in sequence order
+    Message {
+        /// Sequence number of the message
+        seq: u64,
+        /// Whether the peer resent it (it may have been seen before a reconnect)
+        poss_dup: bool,
+        /// Application payload
+        payload: Vec<u8>,
+    },
+    /// The session ended with a logout
+    Logout {
+        /// Reason given by the peer (or by this end when it initiated the logout)
+        reason: String,
+    },
+}
+
+/// Decoded session message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Message<'a> {
+    Logon { heartbeat_ms: u32, sender_id: &'a str },
+    Logout { reason: &'a str },
+    Heartbeat,
+    ResendRequest { begin: u64, end: u64 },
+    SequenceReset { new_seq: u64 },
+    Application(&'a [u8]),
+}
+
+impl<'a> Message<'a> {
+    /// Append the frame of this message to `buffer`.
+    fn encode(&self, seq: u64, flags: u8, buffer: &mut Vec<u8>) {
+        let msg_type = match self {
+            Message::Logon { .. } => MSG_LOGON,
+            Message::Logout { .. } => MSG_LOGOUT,
+            Message::Heartbeat => MSG_HEARTBEAT,
+            Message::ResendRequest { .. } => MSG_RESEND_REQUEST,
+            Message::SequenceReset { .. } => MSG_SEQUENCE_RESET,
+            Message::Application(_) => MSG_APPLICATION,
+        };
+        buffer.push(msg_type);
+        buffer.push(flags);
+        buffer.extend_from_slice(&seq.to_be_bytes());
+        match self {
+            Message::Logon { heartbeat_ms, sender_id } => {
+                buffer.extend_from_slice(&heartbeat_ms.to_be_bytes());
+                buffer.extend_from_slice(sender_id.as_bytes());
+            }
+            Message::Logout { reason } => buffer.extend_from_slice(reason.as_bytes()),
+            Message::Heartbeat => {}
+            Message::ResendRequest { begin, end } => {
+                buffer.extend_from_slice(&begin.to_be_bytes());
+                buffer.extend_from_slice(&end.to_be_bytes());
+            }
+            Message::SequenceReset { new_seq } => buffer.extend_from_slice(&new_seq.to_be_bytes()),
+            Message::Application(payload) => buffer.extend_from_slice(payload),
+        }
+    }
+
+    /// Decode a frame into its sequence number, flags and message.
+    fn decode(frame: &'a [u8]) -> Result<(u64, u8, Message<'a>), Error> {
+        let invalid = |what: &str| Error::new(ErrorKind::InvalidData, format!("Malformed session message: {}", what));
+        if frame.len() < HEADER_LEN {
+            return Err(invalid("short header"));
+        }
+        let seq = u64::from_be_bytes(frame[2..HEADER_LEN].try_into().unwrap());
+        let body = &frame[HEADER_LEN..];
+        let u64_at = |offset: usize| {
+            body.get(offset..offset + 8)
+                .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap()))
+                .ok_or_else(|| invalid("short body"))
+        };
+        let text = |bytes: &'a [u8]| std::str::from_utf8(bytes).map_err(|_| invalid("text is not UTF-8"));
+
+        let message = match frame[0] {
+            MSG_LOGON => {
+                let heartbeat_ms = body.get(..4).ok_or_else(|| invalid("short logon"))?;
+                Message::Logon {
+                    heartbeat_ms: u32::from_be_bytes(heartbeat_ms.try_into().unwrap()),
+                    sender_id: text(&body[4..])?,
+                }
+            }
+            MSG_LOGOUT => Message::Logout { reason: text(body)? },
+            MSG_HEARTBEAT => Message::Heartbeat,
+            MSG_RESEND_REQUEST => Message::ResendRequest { begin: u64_at(0)?, end: u64_at(8)? },
+            MSG_SEQUENCE_RESET => Message::SequenceReset { new_seq: u64_at(0)? },
+            MSG_APPLICATION => Message::Application(body),
+            _ => return Err(invalid("unknown message type")),
+        };
+        Ok((seq, frame[1], message))
+    }
+}
+
+/// Sending half of a session, shared by the engine and its heartbeat.
+#[derive(Debug)]
+struct Outbound<S> {
+    framed: FramedTcp<S>,
+    next_seq: u64,
+    encoded: Vec<u8>,
+}
+
+impl<S: FrameTransport> Outbound<S> {
+    /// Send `message` with the next sequence number, returning that number.
+    fn send(&mut self, message: &Message) -> Result<u64, Error> {
+        let seq = self.next_seq;
+        self.send_as(seq, 0, message)?;
+        self.next_seq += 1;
+        Ok(seq)
+    }
+
+    /// Send `message` with an explicit sequence number, as when resending.
+    fn send_as(&mut self, seq: u64, flags: u8, message: &Message) -> Result<(), Error> {
+        self.encoded.clear();
+        message.encode(seq, flags, &mut self.encoded);
+        self.framed.send_frame(&self.encoded)
+    }
+}
+
+impl<S: FrameTransport + HeartbeatSession> HeartbeatSession for Outbound<S> {
+    fn send_heartbeat(&mut self, _payload: &[u8]) -> Result<(), Error> {
+        self.send(&Message::Heartbeat).map(|_| ())
+    }
+
+    fn last_rx_ns(&mut self) -> Result<u64, Error> {
+        self.framed.get_mut().last_rx_ns()
+    }
+}
+
+/// Sequenced, heartbeated session over a framed TCP connection.
+#[derive(Debug)]
+pub struct Session<S = VmaTcpSocket> {
+    outbound: Outbound<S>,
+    next_in_seq: u64,
+    /// Highest sequence number seen beyond a gap, while a resend is outstanding
+    resend_until: Option<u64>,
+    store: VecDeque<(u64, Vec<u8>)>,
+    resend_capacity: usize,
+    heartbeat: Heartbeat,
+    state: SessionState,
+    peer_id: String,
+    logout_reason: String,
+}
+
+impl<S: FrameTransport + HeartbeatSession> Session<S> {
+    /// Log on as the initiator: send the logon and wait up to `timeout` nanoseconds for the reply.
+    ///
+    /// Fails with `ErrorKind::TimedOut` without a reply, `ErrorKind::PermissionDenied`
+    /// when the peer is not the expected one or answers with a logout, and
+    /// `ErrorKind::InvalidData` on anything other than a logon.
+    pub fn initiate(framed: FramedTcp<S>, config: SessionConfig, timeout: Option<u64>) -> Result<Self, Error> {
+        let mut outbound = Outbound { framed, next_seq: config.next_out_seq, encoded: Vec::new() };
+        outbound.send(&Message::Logon { heartbeat_ms: duration_ms(config.heartbeat_interval), sender_id: &config.sender_id })?;
+        let (seq, peer_id, _) = recv_logon(&mut outbound.framed, &config, timeout)?;
+        Self::logged_on(outbound, config.heartbeat_interval, &config, seq, peer_id)
+    }
+
+    /// Log on as the acceptor: wait up to `timeout` nanoseconds for the peer's logon and reply.
+    ///
+    /// The heartbeat interval is the one proposed by the initiator. Errors as for
+    /// [`initiate`](Self::initiate); a rejected peer is sent a logout first.
+    pub fn accept(framed: FramedTcp<S>, config: SessionConfig, timeout: Option<u64>) -> Result<Self, Error> {
+        let mut outbound = Outbound { framed, next_seq: config.next_out_seq, encoded: Vec::new() };
+        let (seq, peer_id, interval) = match recv_logon(&mut outbound.framed, &config, timeout) {
+            Ok(logon) => logon,
+            Err(e) => {
+                if e.kind() == ErrorKind::PermissionDenied {
+                    let _ = outbound.send(&Message::Logout { reason: "Unexpected peer" });
+                }
+                return Err(e);
+            }
+        };
+        outbound.send(&Message::Logon { heartbeat_ms: duration_ms(interval), sender_id: &config.sender_id })?;
+        Self::logged_on(outbound, interval, &config, seq, peer_id)
+    }
+
+    fn logged_on(outbound: Outbound<S>, interval: Duration, config: &SessionConfig, logon_seq: u64, peer_id: String) -> Result<Self, Error> {
+        let stale_after = interval * config.stale_intervals.max(1);
+        let mut session = Session {
+            outbound,
+            next_in_seq: config.next_in_seq,
+            resend_until: None,
+            store: VecDeque::new(),
+            resend_capacity: config.resend_capacity,
+            heartbeat: Heartbeat::new(interval, stale_after),
+            state: SessionState::Active,
+            peer_id,
+            logout_reason: String::new(),
+        };
+        session.check_sequence(logon_seq, 0)?;
+        Ok(session)
+    }
+
+    /// Get the identification the peer logged on with.
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// Get the session state.
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// Get the agreed heartbeat interval.
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.heartbeat.interval()
+    }
+
+    /// Get the sequence number of the next message sent (persist it to resume the session).
+    pub fn next_out_seq(&self) -> u64 {
+        self.outbound.next_seq
+    }
+
+    /// Get the sequence number expected next from the peer (persist it to resume the session).
+    pub fn next_in_seq(&self) -> u64 {
+        self.next_in_seq
+    }
+
+    /// Get the underlying framed stream.
+    pub fn get_ref(&self) -> &FramedTcp<S> {
+        &self.outbound.framed
+    }
+
+    /// Unwrap the framed stream.
+    pub fn into_inner(self) -> FramedTcp<S> {
+        self.outbound.framed
+    }
+
+    /// Send an application message, returning its sequence number.
+    ///
+    /// Fails with `ErrorKind::NotConnected` once a logout was sent or received.
+    pub fn send(&mut self, payload: &[u8]) -> Result<u64, Error> {
+        if self.state != SessionState::Active {
+            return Err(Error::new(ErrorKind::NotConnected, "Session is logged out"));
+        }
+        let seq = self.outbound.send(&Message::Application(payload))?;
+        self.heartbeat.record_sent();
+
+        if self.resend_capacity > 0 {
+            if self.store.len() == self.resend_capacity {
+                self.store.pop_front();
+            }
+            self.store.push_back((seq, payload.to_vec()));
+        }
+        Ok(seq)
+    }
+
+    /// Start logging out; [`recv`](Self::recv) reports `SessionEvent::Logout` once the peer confirms.
+    pub fn logout(&mut self, reason: &str) -> Result<(), Error> {
+        if self.state != SessionState::Active {
+            return Ok(());
+        }
+        self.outbound.send(&Message::Logout { reason })?;
+        self.state = SessionState::LoggingOut;
+        self.logout_reason = reason.to_owned();
+        Ok(())
+    }
+
+    /// Wait up to `timeout` nanoseconds for the next application message or the logout.
+    ///
+    /// Session messages (heartbeats, resend requests, sequence resets) are handled on the
+    /// way, and heartbeats are sent while idle. Returns `Ok(None)` on timeout. A peer
+    /// silent beyond the staleness threshold fails with `ErrorKind::TimedOut`, a sequence
+    /// number below the expected one with `ErrorKind::InvalidData`; both end the session.
+    pub fn recv(&mut self, timeout: Option<u64>) -> Result<Option<SessionEvent>, Error> {
+        if self.state == SessionState::LoggedOut {
+            return Err(Error::new(ErrorKind::NotConnected, "Session is logged out"));
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        loop {
+            if let HeartbeatStatus::Dead { .. } = self.heartbeat.poll(&mut self.outbound)? {
+                self.state = SessionState::LoggedOut;
+                return Err(Error::new(ErrorKind::TimedOut, "Peer silent beyond the heartbeat threshold"));
+            }
+
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+            let wait = remaining.map_or(self.heartbeat.next_timeout(), |remaining| remaining.min(self.heartbeat.next_timeout()));
+            let Some(frame) = self.outbound.framed.recv_frame(Some(wait))? else {
+                if remaining == Some(0) {
+                    return Ok(None);
+                }
+                continue;
+            };
+            // Owned, so the message can be decoded while the session state changes
+            let mut frame = frame.to_vec();
+            let (seq, flags, message) = Message::decode(&frame)?;
+
+            // Gap fills move the expected number and are exempt from the sequence check
+            if let Message::SequenceReset { new_seq } = message {
+                if new_seq > self.next_in_seq {
+                    self.next_in_seq = new_seq;
+                }
+                self.finish_resend();
+                continue;
+            }
+            if !self.check_sequence(seq, flags)? {
+                continue;
+            }
+
+            let event = match message {
+                Message::Application(_) => {
+                    frame.drain(..HEADER_LEN);
+                    SessionEvent::Message { seq, poss_dup: flags & FLAG_POSS_DUP != 0, payload: frame }
+                }
+                Message::Logout { reason } => {
+                    let reason = reason.to_owned();
+                    if self.state == SessionState::Active {
+                        self.outbound.send(&Message::Logout { reason: "Logout acknowledged" })?;
+                    }
+                    self.state = SessionState::LoggedOut;
+                    let reason = if self.logout_reason.is_empty() { reason } else { std::mem::take(&mut self.logout_reason) };
+                    SessionEvent::Logout { reason }
+                }
+                Message::ResendRequest { begin, end } => {
+                    self.resend(begin, end)?;
+                    continue;
+                }
+                Message::Heartbeat => continue,
+                Message::Logon { .. } => {
+                    return Err(Error::new(ErrorKind::InvalidData, "Logon received on an active session"));
+                }
+                Message::SequenceReset { .. } => unreachable!("handled above"),
+            };
+            return Ok(Some(event));
+        }
+    }
+
+    /// Check an inbound sequence number, returning whether the message is next in order.
+    ///
+    /// A gap requests a resend and discards messages until it is filled; duplicates
+    /// flagged as resent are skipped.
+    fn check_sequence(&mut self, seq: u64, flags: u8) -> Result<bool, Error> {
+        if seq == self.next_in_seq {
+            self.next_in_seq += 1;
+            self.finish_resend();
+            return Ok(true);
+        }
+        if seq < self.next_in_seq {
+            if flags & FLAG_POSS_DUP != 0 {
+                return Ok(false);
+            }
+            let _ = self.outbound.send(&Message::Logout { reason: "Sequence number too low" });
+            self.state = SessionState::LoggedOut;
+            return Err(Error::new(ErrorKind::InvalidData, "Sequence number lower than expected"));
+        }
+
+        // Ask once for everything from the gap on; the peer resends up to its latest message
+        if self.resend_until.is_none() {
+            self.outbound.send(&Message::ResendRequest { begin: self.next_in_seq, end: 0 })?;
+        }
+        self.resend_until = Some(self.resend_until.map_or(seq, |until| until.max(seq)));
+        Ok(false)
+    }
+
+    fn finish_resend(&mut self) {
+        if self.resend_until.is_some_and(|until| self.next_in_seq > until) {
+            self.resend_until = None;
+        }
+    }
+
+    /// Answer a resend request for `begin..=end` (`end` 0 meaning the latest message sent).
+    fn resend(&mut self, begin: u64, end: u64) -> Result<(), Error> {
+        let last = self.outbound.next_seq.saturating_sub(1);
+        let end = if end == 0 || end > last { last } else { end };
+
+        // Stored application messages go out again; every stretch without one is gap-filled
+        let mut next = begin;
+        for (seq, payload) in self.store.iter().filter(|(seq, _)| (begin..=end).contains(seq)) {
+            if *seq > next {
+                self.outbound.send_as(next, FLAG_POSS_DUP, &Message::SequenceReset { new_seq: *seq })?;
+            }
+            self.outbound.send_as(*seq, FLAG_POSS_DUP, &Message::Application(payload))?;
+            next = seq + 1;
+        }
+        if next <= end {
+            self.outbound.send_as(next, FLAG_POSS_DUP, &Message::SequenceReset { new_seq: end + 1 })?;
+        }
+        Ok(())
+    }
+}
+
+/// Wait for the peer's logon, returning its sequence number, identification and heartbeat interval.
+fn recv_logon<S: FrameTransport>(framed: &mut FramedTcp<S>, config: &SessionConfig, timeout: Option<u64>) -> Result<(u64, String, Duration), Error> {
+    let Some(frame) = framed.recv_frame(timeout)? else {
+        return Err(Error::new(ErrorKind::TimedOut, "No logon from the peer"));
+    };
+    match Message::decode(frame)? {
+        (seq, _, Message::Logon { heartbeat_ms, sender_id }) => {
+            if config.expected_peer_id.as_deref().is_some_and(|expected| expected != sender_id) {
+                return Err(Error::new(ErrorKind::PermissionDenied, format!("Unexpected peer {}", sender_id)));
+            }
+            if heartbeat_ms == 0 {
+                return Err(Error::new(ErrorKind::InvalidData, "Logon with a zero heartbeat interval"));
+            }
+            Ok((seq, sender_id.to_owned(), Duration::from_millis(heartbeat_ms as u64)))
+        }
+        (_, _, Message::Logout { reason }) => Err(Error::new(ErrorKind::PermissionDenied, format!("Logon rejected: {}", reason))),
+        _ => Err(Error::new(ErrorKind::InvalidData, "Expected a logon")),
+    }
+}
+
+fn duration_ms(duration: Duration) -> u32 {
+    duration.as_millis().clamp(1, u32::MAX as u128) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// One end of an in-memory byte pipe.
+    struct Pipe {
+        rx: Rc<RefCell<VecDeque<u8>>>,
+        tx: Rc<RefCell<VecDeque<u8>>>,
+    }
+
+    fn pipe() -> (Pipe, Pipe) {
+        let (a, b) = (Rc::default(), Rc::default());
+        (Pipe { rx: Rc::clone(&a), tx: Rc::clone(&b) }, Pipe { rx: b, tx: a })
+    }
+
+    impl FrameTransport for Pipe {
+        fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.tx.borrow_mut().extend(data);
+            Ok(())
+        }
+
+        fn read(&mut self, buffer: &mut [u8], _timeout: Option<u64>) -> Result<Option<usize>, Error> {
+            let mut rx = self.rx.borrow_mut();
+            if rx.is_empty() {
+                return Ok(None);
+            }
+            let n = buffer.len().min(rx.len());
+            for (slot, byte) in buffer.iter_mut().zip(rx.drain(..n)) {
+                *slot = byte;
+            }
+            Ok(Some(n))
+        }
+    }
+
+    impl HeartbeatSession for Pipe {
+        fn send_heartbeat(&mut self, payload: &[u8]) -> Result<(), Error> {
+            self.write_all(payload)
+        }
+
+        fn last_rx_ns(&mut self) -> Result<u64, Error> {
+            Ok(0)
+        }
+    }
+
+    fn frame(message: Message, seq: u64) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        message.encode(seq, 0, &mut encoded);
+        let mut framed = (encoded.len() as u32).to_be_bytes().to_vec();
+        framed.extend(encoded);
+        framed
+    }
+
+    #[test]
+    fn test_message_codec() {
+        let messages = [
+            Message::Logon { heartbeat_ms: 1000, sender_id: "GW1" },
+            Message::Logout { reason: "done" },
+            Message::Heartbeat,
+            Message::ResendRequest { begin: 3, end: 0 },
+            Message::SequenceReset { new_seq: 9 },
+            Message::Application(b"order"),
+        ];
+        for message in messages {
+            let mut encoded = Vec::new();
+            message.encode(42, FLAG_POSS_DUP, &mut encoded);
+            assert_eq!(Message::decode(&encoded).unwrap(), (42, FLAG_POSS_DUP, message));
+        }
+        assert_eq!(Message::decode(b"D\0\0\0").unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(Message::decode(b"2\0\0\0\0\0\0\0\0\x01\0").unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_gap_and_resend() {
+        let (local, remote) = pipe();
+        let peer = Rc::clone(&remote.tx);
+        peer.borrow_mut().extend(frame(Message::Logon { heartbeat_ms: 60_000, sender_id: "EXCH" }, 1));
+
+        let config = SessionConfig::new("GW1").with_expected_peer("EXCH").with_resend_capacity(2);
+        let mut session = Session::initiate(FramedTcp::new(local), config, Some(0)).unwrap();
+        let mut wire = FramedTcp::new(remote);
+        assert_eq!(session.peer_id(), "EXCH");
+        assert_eq!(session.heartbeat_interval(), DEFAULT_HEARTBEAT_INTERVAL);
+        assert!(matches!(Message::decode(wire.recv_frame(Some(0)).unwrap().unwrap()).unwrap(), (1, 0, Message::Logon { .. })));
+
+        // Seq 3 arrives before 2: resend requested, 3 discarded until the gap is filled
+        peer.borrow_mut().extend(frame(Message::Application(b"three"), 3));
+        assert_eq!(session.recv(Some(0)).unwrap(), None);
+        assert_eq!(Message::decode(wire.recv_frame(Some(0)).unwrap().unwrap()).unwrap(), (2, 0, Message::ResendRequest { begin: 2, end: 0 }));
+        peer.borrow_mut().extend(frame(Message::Application(b"two"), 2));
+        peer.borrow_mut().extend(frame(Message::Application(b"three"), 3));
+        assert_eq!(session.recv(Some(0)).unwrap(), Some(SessionEvent::Message { seq: 2, poss_dup: false, payload: b"two".to_vec() }));
+        assert_eq!(session.recv(Some(0)).unwrap(), Some(SessionEvent::Message { seq: 3, poss_dup: false, payload: b"three".to_vec() }));
+        assert_eq!(session.next_in_seq(), 4);
+
+        // Only the last two application messages are stored; seq 3 and earlier are gap-filled
+        for payload in [&b"a"[..], b"b", b"c"] {
+            session.send(payload).unwrap();
+        }
+        assert_eq!(session.next_out_seq(), 6);
+        for _ in 0..3 {
+            wire.recv_frame(Some(0)).unwrap();
+        }
+        peer.borrow_mut().extend(frame(Message::ResendRequest { begin: 1, end: 0 }, 4));
+        assert_eq!(session.recv(Some(0)).unwrap(), None);
+        let mut resent = Vec::new();
+        while let Some(frame) = wire.recv_frame(Some(0)).unwrap() {
+            let (seq, flags, message) = Message::decode(frame).unwrap();
+            assert_eq!(flags, FLAG_POSS_DUP);
+            resent.push((seq, format!("{:?}", message)));
+        }
+        assert_eq!(resent, [
+            (1, "SequenceReset { new_seq: 4 }".to_string()),
+            (4, "Application([98])".to_string()),
+            (5, "Application([99])".to_string()),
+        ]);
+
+        // Logout initiated here completes with the peer's logout
+        session.logout("end of day").unwrap();
+        assert_eq!(session.send(b"late").unwrap_err().kind(), ErrorKind::NotConnected);
+        peer.borrow_mut().extend(frame(Message::Logout { reason: "bye" }, 5));
+        assert_eq!(session.recv(Some(0)).unwrap(), Some(SessionEvent::Logout { reason: "end of day".to_string() }));
+        assert_eq!(session.state(), SessionState::LoggedOut);
+    }
+}
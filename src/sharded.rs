@@ -0,0 +1,226 @@
+//! Multi-core UDP reception over SO_REUSEPORT.
+//!
+//! A [`ShardedUdpReceiver`] binds N [`VmaUdpSocket`]s to the same address and port with
+//! SO_REUSEPORT and drives each one from its own thread. Incoming flows are spread across
+//! the shards by the kernel (or VMA) flow hash, so throughput scales with the number of
+//! cores as long as traffic comes from several sources.
+//!
+//! When `VmaOptions::cpu_cores` is set, shard `i` is pinned to `cpu_cores[i % cpu_cores_count]`.
+//! Packets are delivered either to per-shard callbacks ([`ShardedUdpReceiver::spawn`]) or
+//! through per-shard bounded queues ([`ShardedUdpReceiver::spawn_with_queues`]).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::sharded::ShardedUdpReceiver;
+//!
+//! let mut options = VmaOptions::low_latency();
+//! options.set_cores(&[2, 3, 4, 5]).unwrap();
+//!
+//! let receiver = ShardedUdpReceiver::spawn("0.0.0.0", 5001, 4, options, |shard| {
+//!     move |packet: &vma_socket::udp::Packet| {
+//!         println!("shard {} got {} bytes from {}", shard, packet.data.len(), packet.src_addr);
+//!     }
+//! }).unwrap();
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! let stats = receiver.stop().unwrap();
+//! println!("per-shard rx packets: {:?}", stats.iter().map(|s| s.rx_packets).collect::<Vec<_>>());
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::common::{self, SocketStats, VmaOptions};
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// Receive timeout of the shard loops (100ms), bounding how long `stop()` waits.
+const SHARD_POLL_TIMEOUT_NS: u64 = 100_000_000;
+
+/// Receive buffer size used when `VmaOptions::buffer_size` is not set.
+const DEFAULT_SHARD_BUFFER: usize = 65536;
+
+/// A set of SO_REUSEPORT sockets on the same port, each served by its own thread.
+#[derive(Debug)]
+pub struct ShardedUdpReceiver {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    shards: Vec<JoinHandle<Result<SocketStats, Error>>>,
+}
+
+impl ShardedUdpReceiver {
+    /// Bind `shards` sockets to `addr:port` and deliver packets to per-shard callbacks.
+    ///
+    /// `handler_factory` is called once per shard with the shard index and returns the
+    /// callback run on that shard's thread, so per-shard state needs no synchronization.
+    /// Port 0 binds every shard to the same ephemeral port.
+    pub fn spawn<A, F, H>(
+        addr: A,
+        port: u16,
+        shards: usize,
+        options: VmaOptions,
+        mut handler_factory: F,
+    ) -> Result<Self, Error>
+    where
+        A: Into<String>,
+        F: FnMut(usize) -> H,
+        H: FnMut(&Packet) + Send + 'static,
+    {
+        if shards == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "At least one shard is required"));
+        }
+
+        let addr = addr.into();
+        let mut sockets = Vec::with_capacity(shards);
+        let mut local_addr: Option<SocketAddr> = None;
+        for _ in 0..shards {
+            let mut socket = VmaUdpSocket::with_options(options)?;
+            socket.set_reuse_port(true)?;
+            // Later shards join the port the first one was assigned
+            let bind_port = local_addr.map(|a| a.port()).unwrap_or(port);
+            socket.bind(addr.as_str(), bind_port)?;
+            if local_addr.is_none() {
+                local_addr = Some(socket.local_addr()?);
+            }
+            sockets.push(socket);
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let buffer_size = if options.buffer_size > 0 { options.buffer_size as usize } else { DEFAULT_SHARD_BUFFER };
+        let cores = options.get_cores().to_vec();
+
+        let mut handles = Vec::with_capacity(shards);
+        for (index, socket) in sockets.into_iter().enumerate() {
+            let handler = handler_factory(index);
+            let shard_running = Arc::clone(&running);
+            let core = if cores.is_empty() { None } else { Some(cores[index % cores.len()]) };
+
+            let spawned = thread::Builder::new()
+                .name(format!("vma-udp-shard-{}", index))
+                .spawn(move || run_shard(socket, handler, shard_running, core, buffer_size));
+            match spawned {
+                Ok(handle) => handles.push(handle),
+                Err(e) => {
+                    running.store(false, Ordering::Release);
+                    for handle in handles {
+                        let _ = handle.join();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(ShardedUdpReceiver {
+            local_addr: local_addr.expect("at least one shard was bound"),
+            running,
+            shards: handles,
+        })
+    }
+
+    /// Bind `shards` sockets to `addr:port` and deliver packets through bounded queues.
+    ///
+    /// Returns one receiver per shard. A full queue blocks its shard, pushing back onto
+    /// the socket; packets for a dropped receiver are discarded.
+    pub fn spawn_with_queues<A: Into<String>>(
+        addr: A,
+        port: u16,
+        shards: usize,
+        options: VmaOptions,
+        capacity: usize,
+    ) -> Result<(Self, Vec<Receiver<Packet>>), Error> {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..shards)
+            .map(|_| mpsc::sync_channel(capacity))
+            .unzip();
+
+        let mut senders = senders.into_iter();
+        let receiver = Self::spawn(addr, port, shards, options, |_| {
+            let tx = senders.next().expect("one handler per shard");
+            let mut connected = true;
+            move |packet: &Packet| {
+                if connected && tx.send(packet.clone()).is_err() {
+                    connected = false;
+                }
+            }
+        })?;
+
+        Ok((receiver, receivers))
+    }
+
+    /// Get the address all shards are bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Check whether the shards are still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop all shards and return their final counters, in shard order.
+    ///
+    /// Returns the first error a shard stopped with, if any.
+    pub fn stop(mut self) -> Result<Vec<SocketStats>, Error> {
+        self.join_all()
+    }
+
+    fn join_all(&mut self) -> Result<Vec<SocketStats>, Error> {
+        self.running.store(false, Ordering::Release);
+        let mut stats = Vec::with_capacity(self.shards.len());
+        let mut first_error = None;
+        for handle in self.shards.drain(..) {
+            match handle.join() {
+                Ok(Ok(shard_stats)) => stats.push(shard_stats),
+                Ok(Err(e)) => {
+                    first_error.get_or_insert(e);
+                }
+                Err(_) => {
+                    first_error.get_or_insert(Error::other("Shard thread panicked"));
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(stats),
+        }
+    }
+}
+
+impl Drop for ShardedUdpReceiver {
+    /// Stop and join all shards.
+    fn drop(&mut self) {
+        let _ = self.join_all();
+    }
+}
+
+/// Receive loop of a single shard.
+fn run_shard<H: FnMut(&Packet)>(
+    mut socket: VmaUdpSocket,
+    mut handler: H,
+    running: Arc<AtomicBool>,
+    core: Option<i32>,
+    buffer_size: usize,
+) -> Result<SocketStats, Error> {
+    if let Some(core) = core {
+        common::pin_current_thread_to(&[core])?;
+    }
+
+    let mut buffer = vec![0u8; buffer_size];
+    while running.load(Ordering::Acquire) {
+        if let Some(packet) = socket.recv_from(&mut buffer, Some(SHARD_POLL_TIMEOUT_NS))? {
+            handler(&packet);
+        }
+    }
+
+    socket.stats()
+}
@@ -0,0 +1,175 @@
+//! Cooperative shutdown of blocking socket calls.
+//!
+//! A [`ShutdownToken`] is a shared flag that blocking calls observe. Set on a socket with
+//! `set_shutdown_token()`, it makes receives and accepts wait in slices of at most
+//! [`SHUTDOWN_POLL_NS`], checking the token in between, so a call with no timeout
+//! returns `ErrorKind::Interrupted` shortly after the token is triggered. The receive
+//! threads of [`spsc`], [`conflate`] and `stream`, and the accept loops of [`server`],
+//! stop cleanly when their socket is interrupted this way.
+//!
+//! [`ShutdownToken::ctrlc`] triggers a token on Ctrl-C (SIGINT), replacing the
+//! usual `ctrlc` handler plus short-timeout polling loops.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::ErrorKind;
+//! use vma_socket::shutdown::ShutdownToken;
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let token = ShutdownToken::ctrlc().unwrap();
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//! socket.set_shutdown_token(Some(token));
+//!
+//! let mut buffer = [0u8; 2048];
+//! loop {
+//!     match socket.recv_from(&mut buffer, None) {
+//!         Ok(Some(packet)) => println!("{} bytes", packet.data.len()),
+//!         Ok(None) => {}
+//!         Err(e) if e.kind() == ErrorKind::Interrupted => break, // Ctrl-C
+//!         Err(e) => panic!("{}", e),
+//!     }
+//! }
+//! ```
+//!
+//! [`spsc`]: crate::spsc
+//! [`conflate`]: crate::conflate
+//! [`server`]: crate::server
+
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Longest wait of a blocking call between two checks of its token (10ms).
+pub const SHUTDOWN_POLL_NS: u64 = 10_000_000;
+
+/// Shared shutdown flag observed by blocking calls; clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl ShutdownToken {
+    /// Create a token that is not triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token triggered on Ctrl-C (SIGINT).
+    ///
+    /// Installs the process-wide `ctrlc` handler, which fails if one is already set.
+    pub fn ctrlc() -> Result<Self, Error> {
+        let token = Self::new();
+        let handler_token = token.clone();
+        ctrlc::set_handler(move || handler_token.trigger()).map_err(Error::other)?;
+        Ok(token)
+    }
+
+    /// Ask every call observing the token to return.
+    pub fn trigger(&self) {
+        self.flag.store(true, Ordering::Release);
+    }
+
+    /// Check whether the token was triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.flag.load(Ordering::Acquire)
+    }
+
+    /// Fail with `ErrorKind::Interrupted` if the token was triggered.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_triggered() {
+            return Err(Error::new(ErrorKind::Interrupted, "Shutdown requested"));
+        }
+        Ok(())
+    }
+
+    /// Get the shared flag, e.g. for a loop of its own or a signal handler.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.flag)
+    }
+}
+
+impl From<Arc<AtomicBool>> for ShutdownToken {
+    /// Observe an existing flag, shutting down when it turns `true`.
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        ShutdownToken { flag }
+    }
+}
+
+/// Run the wait `op` for up to `timeout_nano` nanoseconds (`None` forever), in slices of
+/// at most [`SHUTDOWN_POLL_NS`] while `token` is set.
+///
+/// `op` gets the timeout of its slice; a result `is_timeout` accepts is retried until the
+/// whole timeout has elapsed. Fails with `ErrorKind::Interrupted` once the token is triggered.
+pub(crate) fn sliced<T, E>(
+    token: Option<&ShutdownToken>,
+    timeout_nano: Option<u64>,
+    is_timeout: impl Fn(&E) -> bool,
+    mut op: impl FnMut(Option<u64>) -> Result<T, E>,
+) -> Result<Result<T, E>, Error> {
+    let Some(token) = token else { return Ok(op(timeout_nano)) };
+    let deadline = timeout_nano.map(|t| Instant::now() + Duration::from_nanos(t));
+    loop {
+        token.check()?;
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+        let slice = remaining.map_or(SHUTDOWN_POLL_NS, |remaining| remaining.min(SHUTDOWN_POLL_NS));
+        match op(Some(slice)) {
+            Err(e) if is_timeout(&e) && remaining.is_none_or(|remaining| remaining > slice) => {}
+            result => return Ok(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sliced_wait() {
+        let token = ShutdownToken::new();
+        let mut slices = Vec::new();
+        let result = sliced(Some(&token), Some(25_000_000), |_: &()| true, |slice| {
+            slices.push(slice.unwrap());
+            Err::<(), ()>(())
+        });
+        assert_eq!(result.unwrap(), Err(()));
+        assert!(slices.len() >= 3 && slices.iter().all(|&slice| slice <= SHUTDOWN_POLL_NS));
+
+        // No token: one call with the whole timeout
+        let result = sliced(None, None, |_: &()| true, Ok::<_, ()>);
+        assert_eq!(result.unwrap(), Ok(None));
+
+        // Triggered from another thread while waiting forever
+        let trigger = ShutdownToken::from(token.flag());
+        let waker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            trigger.trigger();
+        });
+        let result = sliced(Some(&token), None, |_: &()| true, |_| {
+            std::thread::sleep(Duration::from_millis(5));
+            Err::<(), ()>(())
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Interrupted);
+        waker.join().unwrap();
+    }
+
+    #[test]
+    fn test_token_interrupts_peek() {
+        let mut socket = crate::udp::VmaUdpSocket::new().unwrap();
+        socket.bind("127.0.0.1", 0).unwrap();
+        let token = ShutdownToken::new();
+        socket.set_shutdown_token(Some(token.clone()));
+
+        let trigger = token.clone();
+        let waker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            trigger.trigger();
+        });
+        let mut buffer = [0u8; 16];
+        assert_eq!(socket.peek(&mut buffer, None).unwrap_err().kind(), ErrorKind::Interrupted);
+        assert_eq!(socket.peek_from(&mut buffer, None).unwrap_err().kind(), ErrorKind::Interrupted);
+        waker.join().unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Per-operation call timing with slow-call logging.
+//!
+//! Enabled with the `tracing` feature. [`SlowCallLog`] wraps a [`VmaUdpSocket`] or
+//! [`VmaTcpSocket`], times every receive, send, accept and connect call, and keeps
+//! per-operation counters ([`OpStats`]). A call taking longer than the threshold is
+//! reported as a `tracing` warning under the `vma_socket::slow` target, with the operation,
+//! duration, byte count, timeout, file descriptor and addresses, so intermittent latency
+//! spikes in production can be tied to a socket and a call.
+//!
+//! Receive and accept durations include the wait for data, so they are only meaningful
+//! with short timeouts or in non-blocking mode. Calls that time out without data are not
+//! recorded.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use vma_socket::slowlog::{Op, SlowCallLog};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = SlowCallLog::new(VmaUdpSocket::new().unwrap(), Duration::from_micros(50));
+//! socket.inner_mut().connect("192.168.1.100", 5001).unwrap();
+//!
+//! for _ in 0..100_000 {
+//!     socket.send(b"quote").unwrap(); // sends over 50us are logged
+//! }
+//! let send = socket.op_stats(Op::Send);
+//! println!("{} sends, {} slow, max {}ns", send.calls, send.slow, send.max_ns);
+//! ```
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::os::raw::c_int;
+use std::time::{Duration, Instant};
+
+use crate::tcp::{Client, VmaTcpSocket};
+use crate::udp::{Destination, Packet, VmaUdpSocket};
+
+/// Socket operation timed by [`SlowCallLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// `recv` and `recv_from`
+    Recv,
+    /// `send`, `send_to` and `send_to_dest`
+    Send,
+    /// `accept` on a listening TCP socket
+    Accept,
+    /// `connect` of a TCP socket
+    Connect,
+}
+
+impl Op {
+    const COUNT: usize = 4;
+
+    /// Get the name used in log events.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Op::Recv => "recv",
+            Op::Send => "send",
+            Op::Accept => "accept",
+            Op::Connect => "connect",
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Timing counters of one operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpStats {
+    /// Recorded calls
+    pub calls: u64,
+    /// Calls over the threshold
+    pub slow: u64,
+    /// Total duration of the recorded calls in nanoseconds
+    pub total_ns: u64,
+    /// Longest recorded call in nanoseconds
+    pub max_ns: u64,
+}
+
+impl OpStats {
+    /// Get the mean call duration in nanoseconds.
+    pub fn mean_ns(&self) -> u64 {
+        self.total_ns.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+/// Socket wrapper timing each call and logging the ones over a threshold.
+#[derive(Debug)]
+pub struct SlowCallLog<T> {
+    inner: T,
+    threshold: Duration,
+    stats: [OpStats; Op::COUNT],
+}
+
+impl<T> SlowCallLog<T> {
+    /// Wrap a socket, logging calls slower than `threshold`.
+    pub fn new(inner: T, threshold: Duration) -> Self {
+        SlowCallLog {
+            inner,
+            threshold,
+            stats: [OpStats::default(); Op::COUNT],
+        }
+    }
+
+    /// Set the duration over which calls are logged.
+    pub fn set_threshold(&mut self, threshold: Duration) {
+        self.threshold = threshold;
+    }
+
+    /// Get the duration over which calls are logged.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Get the counters of one operation.
+    pub fn op_stats(&self, op: Op) -> OpStats {
+        self.stats[op as usize]
+    }
+
+    /// Reset the counters of all operations.
+    pub fn reset(&mut self) {
+        self.stats = [OpStats::default(); Op::COUNT];
+    }
+
+    /// Get a reference to the wrapped socket.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped socket (calls made through it are not timed).
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the socket, discarding the counters.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Count a call of `op` started at `start`, returning whether it was over the threshold.
+    fn record(&mut self, op: Op, start: Instant) -> Option<Duration> {
+        let elapsed = start.elapsed();
+        let nanos = elapsed.as_nanos() as u64;
+        let stats = &mut self.stats[op as usize];
+        stats.calls += 1;
+        stats.total_ns += nanos;
+        stats.max_ns = stats.max_ns.max(nanos);
+        if elapsed <= self.threshold {
+            return None;
+        }
+        stats.slow += 1;
+        Some(elapsed)
+    }
+}
+
+/// Context of a slow call.
+struct SlowCall {
+    op: Op,
+    elapsed: Duration,
+    threshold: Duration,
+    bytes: usize,
+    timeout_nano: Option<u64>,
+    fd: c_int,
+    local: Option<SocketAddr>,
+    peer: Option<SocketAddr>,
+}
+
+fn log_slow(call: SlowCall) {
+    tracing::warn!(
+        target: "vma_socket::slow",
+        op = call.op.as_str(),
+        elapsed_ns = call.elapsed.as_nanos() as u64,
+        threshold_ns = call.threshold.as_nanos() as u64,
+        bytes = call.bytes,
+        timeout_ns = ?call.timeout_nano,
+        fd = call.fd,
+        local = ?call.local,
+        peer = ?call.peer,
+        "slow socket call"
+    );
+}
+
+impl SlowCallLog<VmaUdpSocket> {
+    /// Receive data from the connected remote address.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let received = self.inner.recv(buffer, timeout_nano)?;
+        if received > 0 {
+            self.finish(Op::Recv, start, received, timeout_nano, None);
+        }
+        Ok(received)
+    }
+
+    /// Receive data and source address information.
+    pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, std::io::Error> {
+        let start = Instant::now();
+        let packet = self.inner.recv_from(buffer, timeout_nano)?;
+        if let Some(packet) = &packet {
+            self.finish(Op::Recv, start, packet.data.len(), timeout_nano, Some(packet.src_addr));
+        }
+        Ok(packet)
+    }
+
+    /// Send data to the connected remote address.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send(data)?;
+        self.finish(Op::Send, start, sent, None, None);
+        Ok(sent)
+    }
+
+    /// Send data to a specified address and port.
+    pub fn send_to<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send_to(data, addr, port)?;
+        self.finish(Op::Send, start, sent, None, None);
+        Ok(sent)
+    }
+
+    /// Send data to a destination prepared by `resolve()`.
+    pub fn send_to_dest(&mut self, data: &[u8], dest: &Destination) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send_to_dest(data, dest)?;
+        self.finish(Op::Send, start, sent, None, Some(dest.addr().into()));
+        Ok(sent)
+    }
+
+    fn finish(&mut self, op: Op, start: Instant, bytes: usize, timeout_nano: Option<u64>, peer: Option<SocketAddr>) {
+        if let Some(elapsed) = self.record(op, start) {
+            log_slow(SlowCall {
+                op,
+                elapsed,
+                threshold: self.threshold,
+                bytes,
+                timeout_nano,
+                fd: self.inner.fd(),
+                local: self.inner.local_addr().ok(),
+                peer: peer.or_else(|| self.inner.peer_addr()),
+            });
+        }
+    }
+}
+
+impl SlowCallLog<VmaTcpSocket> {
+    /// Receive data from the connected socket.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let received = self.inner.recv(buffer, timeout)?;
+        if received > 0 {
+            self.finish(Op::Recv, start, received, timeout);
+        }
+        Ok(received)
+    }
+
+    /// Send data over the connected socket.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let start = Instant::now();
+        let sent = self.inner.send(data)?;
+        if sent > 0 {
+            self.finish(Op::Send, start, sent, None);
+        }
+        Ok(sent)
+    }
+
+    /// Accept a client connection, waiting up to `timeout_nano` nanoseconds.
+    pub fn accept(&mut self, timeout_nano: Option<u64>) -> Result<Option<Client>, std::io::Error> {
+        let start = Instant::now();
+        let client = self.inner.accept(timeout_nano)?;
+        if client.is_some() {
+            self.finish(Op::Accept, start, 0, timeout_nano);
+        }
+        Ok(client)
+    }
+
+    /// Connect to a remote address, waiting up to `timeout` nanoseconds.
+    pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        let start = Instant::now();
+        let connected = self.inner.connect(addr, port, timeout)?;
+        if connected {
+            self.finish(Op::Connect, start, 0, timeout);
+        }
+        Ok(connected)
+    }
+
+    fn finish(&mut self, op: Op, start: Instant, bytes: usize, timeout_nano: Option<u64>) {
+        if let Some(elapsed) = self.record(op, start) {
+            log_slow(SlowCall {
+                op,
+                elapsed,
+                threshold: self.threshold,
+                bytes,
+                timeout_nano,
+                fd: self.inner.fd(),
+                local: self.inner.local_addr().ok(),
+                peer: self.inner.peer_addr(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slow_call_record() {
+        let mut log = SlowCallLog::new((), Duration::from_millis(5));
+        assert!(log.record(Op::Send, Instant::now()).is_none());
+        let start = Instant::now() - Duration::from_millis(10);
+        assert!(log.record(Op::Send, start).is_some());
+
+        let send = log.op_stats(Op::Send);
+        assert_eq!((send.calls, send.slow), (2, 1));
+        assert!(send.max_ns >= 10_000_000);
+        assert_eq!(send.mean_ns(), send.total_ns / 2);
+        assert_eq!(log.op_stats(Op::Recv), OpStats::default());
+
+        log.reset();
+        assert_eq!(log.op_stats(Op::Send).calls, 0);
+    }
+}
@@ -0,0 +1,471 @@
+//! Lock-free single-producer/single-consumer handoff between threads.
+//!
+//! The usual layout of a feed handler is a receive thread pinned next to the NIC that
+//! only drains the socket, and a strategy thread that consumes the packets. A
+//! [`PacketChannel`] connects the two with a bounded ring: the head and tail indices sit
+//! on separate cache lines, each side caches the other's index so the shared line is only
+//! read when the ring looks full or empty, and the consumer busy-polls instead of parking.
+//!
+//! [`spawn_receiver`] runs the receive side: it moves a [`VmaUdpSocket`] onto a pinned
+//! thread and pushes every received [`Packet`] into the channel.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::spsc::{self, PacketChannel};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! let mut socket = VmaUdpSocket::new().unwrap();
+//! socket.bind("0.0.0.0", 5001).unwrap();
+//!
+//! let (producer, mut consumer) = PacketChannel::bounded(4096);
+//! let receiver = spsc::spawn_receiver(socket, producer, &[2]).unwrap();
+//!
+//! while let Some(packet) = consumer.recv(Some(1_000_000_000)) {
+//!     println!("{} bytes from {}", packet.data.len(), packet.src_addr);
+//! }
+//! let stats = receiver.stop().unwrap();
+//! println!("rx packets: {}", stats.rx_packets);
+//! ```
+
+use std::cell::UnsafeCell;
+use std::io::{Error, ErrorKind};
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::common::{self, SocketStats};
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// Receive timeout of the receiver thread (100ms), bounding how long `stop()` waits.
+const RECEIVER_POLL_TIMEOUT_NS: u64 = 100_000_000;
+
+/// Receive buffer size of the receiver thread, large enough for any datagram.
+const RECEIVER_BUFFER_SIZE: usize = 65536;
+
+/// Value aligned to its own cache line, so writes to it do not invalidate its neighbours.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Bounded ring shared by one [`Producer`] and one [`Consumer`].
+pub struct PacketChannel<T = Packet> {
+    /// Next slot to read, written by the consumer only
+    head: CachePadded<AtomicUsize>,
+    /// Next slot to write, written by the producer only
+    tail: CachePadded<AtomicUsize>,
+    /// Set when either end is dropped
+    closed: CachePadded<AtomicBool>,
+    /// Values refused by `Producer::push` because the ring was full
+    dropped: CachePadded<AtomicU64>,
+    mask: usize,
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+// The slots are only touched by the single producer (between tail and head + capacity) and
+// the single consumer (between head and tail), ordered by the release/acquire index updates
+unsafe impl<T: Send> Send for PacketChannel<T> {}
+unsafe impl<T: Send> Sync for PacketChannel<T> {}
+
+impl<T> PacketChannel<T> {
+    /// Create a channel holding at least `capacity` values (rounded up to a power of two).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn bounded(capacity: usize) -> (Producer<T>, Consumer<T>) {
+        assert!(capacity > 0, "PacketChannel capacity must be positive");
+        let capacity = capacity.next_power_of_two();
+        let channel = Arc::new(PacketChannel {
+            head: CachePadded::default(),
+            tail: CachePadded::default(),
+            closed: CachePadded::default(),
+            dropped: CachePadded::default(),
+            mask: capacity - 1,
+            slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+        });
+
+        let producer = Producer {
+            channel: Arc::clone(&channel),
+            tail: 0,
+            head_cache: 0,
+        };
+        let consumer = Consumer {
+            channel,
+            head: 0,
+            tail_cache: 0,
+        };
+        (producer, consumer)
+    }
+
+    fn capacity(&self) -> usize {
+        self.mask + 1
+    }
+
+    fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+}
+
+impl<T> std::fmt::Debug for PacketChannel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketChannel")
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .field("closed", &self.closed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T> Drop for PacketChannel<T> {
+    /// Drop the values still in the ring.
+    fn drop(&mut self) {
+        let tail = *self.tail.0.get_mut();
+        let mut head = *self.head.0.get_mut();
+        while head != tail {
+            unsafe { (*self.slots[head & self.mask].get()).assume_init_drop() };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+/// Sending end of a [`PacketChannel`], owned by one thread at a time.
+#[derive(Debug)]
+pub struct Producer<T = Packet> {
+    channel: Arc<PacketChannel<T>>,
+    tail: usize,
+    head_cache: usize,
+}
+
+impl<T> Producer<T> {
+    /// Add `value` to the ring, handing it back if the ring is full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        let channel = &*self.channel;
+        if self.tail.wrapping_sub(self.head_cache) == channel.capacity() {
+            self.head_cache = channel.head.load(Ordering::Acquire);
+            if self.tail.wrapping_sub(self.head_cache) == channel.capacity() {
+                return Err(value);
+            }
+        }
+
+        unsafe { (*channel.slots[self.tail & channel.mask].get()).write(value) };
+        self.tail = self.tail.wrapping_add(1);
+        channel.tail.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    /// Add `value` to the ring, dropping it and counting it in [`dropped`](Self::dropped)
+    /// if the ring is full.
+    ///
+    /// Returns whether the value was queued. A receive thread never waits on a slow
+    /// consumer this way; the overflow shows up in the counter instead of as socket drops.
+    pub fn push(&mut self, value: T) -> bool {
+        if self.try_push(value).is_err() {
+            self.channel.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    /// Get the number of values dropped by `push()` on a full ring.
+    pub fn dropped(&self) -> u64 {
+        self.channel.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of values in the ring.
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Check whether the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of values the ring holds.
+    pub fn capacity(&self) -> usize {
+        self.channel.capacity()
+    }
+
+    /// Check whether the consumer has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.channel.closed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    /// Let the consumer see the end of the stream once the ring is drained.
+    fn drop(&mut self) {
+        self.channel.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Receiving end of a [`PacketChannel`], owned by one thread at a time.
+#[derive(Debug)]
+pub struct Consumer<T = Packet> {
+    channel: Arc<PacketChannel<T>>,
+    head: usize,
+    tail_cache: usize,
+}
+
+impl<T> Consumer<T> {
+    /// Take the oldest value from the ring, if any.
+    pub fn try_recv(&mut self) -> Option<T> {
+        let channel = &*self.channel;
+        if self.head == self.tail_cache {
+            self.tail_cache = channel.tail.load(Ordering::Acquire);
+            if self.head == self.tail_cache {
+                return None;
+            }
+        }
+
+        let value = unsafe { (*channel.slots[self.head & channel.mask].get()).assume_init_read() };
+        self.head = self.head.wrapping_add(1);
+        channel.head.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    /// Busy-poll for the oldest value, up to `timeout_nano` nanoseconds (`None` waits forever).
+    ///
+    /// Returns `None` on timeout, or once the producer is dropped and the ring is drained.
+    /// The calling thread spins at full speed, so it should own its core.
+    pub fn recv(&mut self, timeout_nano: Option<u64>) -> Option<T> {
+        let deadline = timeout_nano.map(|t| Instant::now() + Duration::from_nanos(t));
+        loop {
+            if let Some(value) = self.try_recv() {
+                return Some(value);
+            }
+            if self.channel.closed.load(Ordering::Acquire) {
+                // Values pushed right before the producer was dropped
+                return self.try_recv();
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return None;
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Move up to `max` values into `out` without waiting, returning how many were moved.
+    pub fn drain_into(&mut self, out: &mut Vec<T>, max: usize) -> usize {
+        let mut moved = 0;
+        while moved < max {
+            let Some(value) = self.try_recv() else { break };
+            out.push(value);
+            moved += 1;
+        }
+        moved
+    }
+
+    /// Get the number of values dropped by the producer on a full ring.
+    pub fn dropped(&self) -> u64 {
+        self.channel.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of values in the ring.
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Check whether the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of values the ring holds.
+    pub fn capacity(&self) -> usize {
+        self.channel.capacity()
+    }
+
+    /// Check whether the producer has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.channel.closed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    /// Let the producer see that nobody reads the ring anymore.
+    fn drop(&mut self) {
+        self.channel.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Thread receiving from a UDP socket, started by [`spawn_receiver`].
+#[derive(Debug)]
+pub struct ReceiverThread {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<SocketStats, Error>>>,
+}
+
+impl ReceiverThread {
+    /// Check whether the receiver thread is still running.
+    ///
+    /// It stops on its own when a receive fails or nobody consumes the packets anymore.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the receiver thread and return the final counters of its socket.
+    ///
+    /// Returns the error the thread stopped with, if any.
+    pub fn stop(mut self) -> Result<SocketStats, Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<SocketStats, Error> {
+        self.running.store(false, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::other("Receiver thread panicked")),
+            None => Ok(SocketStats::default()),
+        }
+    }
+}
+
+impl Drop for ReceiverThread {
+    /// Stop and join the receiver thread.
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+/// Move `socket` onto a new thread, pinned to `cores` when not empty, that pushes every
+/// received packet into `producer`.
+///
+/// Packets arriving while the ring is full are dropped and counted by
+/// [`Consumer::dropped`]. The thread stops when asked, when a receive fails, when the
+/// shutdown token of `socket` is triggered or at the first packet after the consumer is
+/// dropped.
+pub fn spawn_receiver(
+    socket: VmaUdpSocket,
+    mut producer: Producer<Packet>,
+    cores: &[c_int],
+) -> Result<ReceiverThread, Error> {
+    spawn_receiver_with(socket, cores, "vma-spsc-rx", move |packet| {
+        producer.push(packet);
+        !producer.is_closed()
+    })
+}
+
+/// Move `socket` onto a new thread, pinned to `cores` when not empty, that hands every
+/// received packet to `handler` until it returns `false`.
+pub(crate) fn spawn_receiver_with<H>(
+    socket: VmaUdpSocket,
+    cores: &[c_int],
+    name: &str,
+    handler: H,
+) -> Result<ReceiverThread, Error>
+where
+    H: FnMut(Packet) -> bool + Send + 'static,
+{
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = Arc::clone(&running);
+    let cores = cores.to_vec();
+    let thread = thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let result = run_receiver(socket, handler, &cores, &thread_running);
+            thread_running.store(false, Ordering::Release);
+            result
+        })?;
+
+    Ok(ReceiverThread {
+        running,
+        thread: Some(thread),
+    })
+}
+
+/// Loop of the receiver thread.
+fn run_receiver<H: FnMut(Packet) -> bool>(
+    mut socket: VmaUdpSocket,
+    mut handler: H,
+    cores: &[c_int],
+    running: &AtomicBool,
+) -> Result<SocketStats, Error> {
+    common::pin_current_thread_to(cores)?;
+
+    let mut buffer = vec![0u8; RECEIVER_BUFFER_SIZE];
+    while running.load(Ordering::Acquire) {
+        match socket.recv_from(&mut buffer, Some(RECEIVER_POLL_TIMEOUT_NS)) {
+            Ok(Some(packet)) => {
+                if !handler(packet) {
+                    break;
+                }
+            }
+            Ok(None) => {}
+            Err(e) if e.kind() == ErrorKind::Interrupted => break, // shutdown token triggered
+            Err(e) => return Err(e),
+        }
+    }
+
+    socket.stats()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_packet_channel() {
+        let (mut producer, mut consumer) = PacketChannel::bounded(3);
+        assert_eq!(producer.capacity(), 4);
+        assert_eq!(consumer.try_recv(), None);
+        assert_eq!(consumer.recv(Some(1_000_000)), None);
+
+        for i in 0..4 {
+            producer.try_push(i).unwrap();
+        }
+        assert_eq!(producer.try_push(4), Err(4));
+        assert!(!producer.push(4));
+        assert_eq!((consumer.len(), consumer.dropped()), (4, 1));
+
+        assert_eq!(consumer.try_recv(), Some(0));
+        producer.try_push(4).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(consumer.drain_into(&mut out, 8), 4);
+        assert_eq!(out, vec![1, 2, 3, 4]);
+
+        // Values pushed before the producer is dropped are still delivered
+        producer.try_push(5).unwrap();
+        drop(producer);
+        assert!(consumer.is_closed());
+        assert_eq!(consumer.recv(None), Some(5));
+        assert_eq!(consumer.recv(None), None);
+    }
+
+    #[test]
+    fn test_packet_channel_threads() {
+        let (mut producer, mut consumer) = PacketChannel::bounded(64);
+        let sender = thread::spawn(move || {
+            for i in 0..10_000u64 {
+                let mut value = vec![i];
+                while let Err(back) = producer.try_push(value) {
+                    value = back;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut expected = 0u64;
+        while let Some(value) = consumer.recv(Some(5_000_000_000)) {
+            assert_eq!(value, vec![expected]);
+            expected += 1;
+        }
+        sender.join().unwrap();
+        assert_eq!(expected, 10_000);
+    }
+}
@@ -0,0 +1,364 @@
+//! `futures` adapters: a `Stream` of received packets and a sink of datagrams to send.
+//!
+//! Enabled with the `futures` feature. [`PacketStream`] moves a [`VmaUdpSocket`] onto a
+//! pinned receive thread, like [`spsc::spawn_receiver`], and yields its packets as a
+//! `futures_core::Stream<Item = Packet>`, so they compose with stream combinators on any
+//! executor. [`PacketSink`] takes `(Bytes, SocketAddr)` items, queues them on the socket
+//! and sends them in batches with `sendmmsg(2)` when flushed.
+//!
+//! `PacketSink` implements `futures_sink::Sink`, so `SinkExt::send`, `send_all` and
+//! friends drive it. There is no reactor: a flush blocked by a full send buffer hands its
+//! waker to a writer thread, started on first need, which waits for the socket to become
+//! writable and wakes the task.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use futures::{SinkExt, StreamExt};
+//! use vma_socket::stream::{PacketSink, PacketStream};
+//! use vma_socket::udp::VmaUdpSocket;
+//!
+//! async fn relay() -> std::io::Result<()> {
+//!     let mut rx = VmaUdpSocket::new()?;
+//!     rx.bind("0.0.0.0", 5001)?;
+//!     let packets = PacketStream::spawn(rx, 1024, &[2])?;
+//!
+//!     let mut sink = PacketSink::new(VmaUdpSocket::new()?);
+//!     let target: std::net::SocketAddr = "192.168.1.100:5002".parse().unwrap();
+//!     let mut forwarded = packets.map(|packet| Ok((bytes::Bytes::from(packet.data), target)));
+//!     sink.send_all(&mut forwarded).await
+//! }
+//! ```
+//!
+//! [`spsc::spawn_receiver`]: crate::spsc::spawn_receiver
+
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::os::fd::AsRawFd;
+use std::os::raw::c_int;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::common::SocketStats;
+use crate::spsc::{self, ReceiverThread};
+use crate::udp::{Destination, Packet, VmaUdpSocket, SEND_BATCH_MAX};
+
+/// Wait of the writer thread per round (100ms), bounding how long dropping the sink waits.
+const WRITABLE_POLL_TIMEOUT_MS: c_int = 100;
+
+/// Stream of the packets received by a UDP socket on a background thread.
+#[derive(Debug)]
+pub struct PacketStream {
+    packets: mpsc::Receiver<Packet>,
+    dropped: Arc<AtomicU64>,
+    receiver: ReceiverThread,
+}
+
+impl PacketStream {
+    /// Move `socket` onto a new thread, pinned to `cores` when not empty, buffering up to
+    /// `capacity` packets for the stream.
+    ///
+    /// Packets arriving while the buffer is full are dropped and counted by
+    /// [`dropped`](Self::dropped). The stream ends when the receive thread stops on an error.
+    pub fn spawn(socket: VmaUdpSocket, capacity: usize, cores: &[c_int]) -> Result<Self, Error> {
+        if capacity == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Stream capacity must be non-zero"));
+        }
+        // The channel holds one extra slot per sender
+        let (mut tx, packets) = mpsc::channel(capacity - 1);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let thread_dropped = Arc::clone(&dropped);
+        let receiver = spsc::spawn_receiver_with(socket, cores, "vma-stream-rx", move |packet| {
+            match tx.try_send(packet) {
+                Ok(()) => true,
+                Err(e) if e.is_full() => {
+                    thread_dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(_) => false, // stream dropped
+            }
+        })?;
+
+        Ok(PacketStream {
+            packets,
+            dropped,
+            receiver,
+        })
+    }
+
+    /// Get the number of packets dropped because the stream was not polled fast enough.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Check whether the receive thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_running()
+    }
+
+    /// Stop the receive thread and return the final counters of its socket.
+    ///
+    /// Packets not yet taken from the stream are discarded.
+    pub fn stop(self) -> Result<SocketStats, Error> {
+        self.receiver.stop()
+    }
+}
+
+impl Stream for PacketStream {
+    type Item = Packet;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Packet>> {
+        Pin::new(&mut self.packets).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.packets.size_hint()
+    }
+}
+
+/// Sink of `(payload, destination)` datagrams sent in batches by a UDP socket.
+#[derive(Debug)]
+pub struct PacketSink {
+    // Declared first so the writer thread stops before the socket closes
+    writer: Option<WritableWaiter>,
+    socket: VmaUdpSocket,
+}
+
+impl PacketSink {
+    /// Wrap `socket`, switching it to non-blocking mode so a flush never blocks the executor.
+    pub fn new(mut socket: VmaUdpSocket) -> Self {
+        socket.set_nonblocking(true);
+        PacketSink { writer: None, socket }
+    }
+
+    /// Get the underlying socket.
+    pub fn get_ref(&self) -> &VmaUdpSocket {
+        &self.socket
+    }
+
+    /// Get the underlying socket mutably.
+    pub fn get_mut(&mut self) -> &mut VmaUdpSocket {
+        &mut self.socket
+    }
+
+    /// Unwrap the socket, stopping the writer thread; datagrams still queued are sent when
+    /// it is next flushed.
+    pub fn into_inner(self) -> VmaUdpSocket {
+        let PacketSink { writer, socket } = self;
+        drop(writer);
+        socket
+    }
+
+    /// Get the number of datagrams accepted and not sent yet.
+    pub fn queued(&self) -> usize {
+        self.socket.queued()
+    }
+}
+
+impl Sink<(Bytes, SocketAddr)> for PacketSink {
+    type Error = Error;
+
+    /// Wait for room for one more datagram, flushing a full batch first.
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if self.socket.queued() < SEND_BATCH_MAX {
+            return Poll::Ready(Ok(()));
+        }
+        self.poll_flush(cx)
+    }
+
+    /// Queue a datagram; only IPv4 destinations are supported.
+    fn start_send(self: Pin<&mut Self>, item: (Bytes, SocketAddr)) -> Result<(), Error> {
+        let (payload, addr) = item;
+        let SocketAddr::V4(addr) = addr else {
+            return Err(Error::new(ErrorKind::InvalidInput, "Only IPv4 destinations are supported"));
+        };
+        Pin::get_mut(self).socket.queue_send_to_dest(&payload, &Destination::new(addr))
+    }
+
+    /// Send every queued datagram, waiting on the writer thread while the send buffer is full.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = Pin::get_mut(self);
+        while this.socket.queued() > 0 {
+            match this.socket.flush() {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if this.writer.is_none() {
+                        match WritableWaiter::spawn(this.socket.as_raw_fd()) {
+                            Ok(writer) => this.writer = Some(writer),
+                            Err(e) => return Poll::Ready(Err(e)),
+                        }
+                    }
+                    if let Some(writer) = &this.writer {
+                        writer.register(cx.waker());
+                    }
+                    return Poll::Pending;
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Send every queued datagram; the socket stays usable.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Waker of a blocked flush, shared with the writer thread.
+#[derive(Debug, Default)]
+struct Pending {
+    waker: Mutex<Option<Waker>>,
+    changed: Condvar,
+}
+
+/// Writer thread waking a blocked flush once its socket becomes writable.
+#[derive(Debug)]
+struct WritableWaiter {
+    pending: Arc<Pending>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WritableWaiter {
+    /// Watch the socket file descriptor `fd`, which must stay open until the waiter is dropped.
+    fn spawn(fd: c_int) -> Result<Self, Error> {
+        let pending = Arc::new(Pending::default());
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_pending = Arc::clone(&pending);
+        let thread_running = Arc::clone(&running);
+        let thread = thread::Builder::new()
+            .name("vma-sink-tx".to_string())
+            .spawn(move || run_writer(fd, &thread_pending, &thread_running))?;
+        Ok(WritableWaiter { pending, running, thread: Some(thread) })
+    }
+
+    /// Wake `waker` once the socket is writable, replacing the waker of an earlier flush.
+    fn register(&self, waker: &Waker) {
+        let mut pending = self.pending.waker.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.as_mut() {
+            Some(current) => current.clone_from(waker),
+            None => *pending = Some(waker.clone()),
+        }
+        self.pending.changed.notify_one();
+    }
+}
+
+impl Drop for WritableWaiter {
+    /// Stop the writer thread.
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        self.pending.changed.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Loop of the writer thread: wait for a waker, then until `fd` is writable, then wake it.
+fn run_writer(fd: c_int, pending: &Pending, running: &AtomicBool) {
+    while running.load(Ordering::Acquire) {
+        {
+            let waker = pending.waker.lock().unwrap_or_else(|e| e.into_inner());
+            let (waker, _) = pending
+                .changed
+                .wait_timeout_while(waker, Duration::from_millis(WRITABLE_POLL_TIMEOUT_MS as u64), |waker| {
+                    waker.is_none() && running.load(Ordering::Acquire)
+                })
+                .unwrap_or_else(|e| e.into_inner());
+            if waker.is_none() {
+                continue;
+            }
+        }
+
+        let mut pfd = libc::pollfd { fd, events: libc::POLLOUT, revents: 0 };
+        let res = unsafe { libc::poll(&mut pfd, 1, WRITABLE_POLL_TIMEOUT_MS) };
+        if res == 0 || (res < 0 && Error::last_os_error().kind() == ErrorKind::Interrupted) {
+            continue;
+        }
+
+        // Writable, or an error the next flush reports
+        if let Some(waker) = pending.waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::{stream, SinkExt};
+    use std::future::{self, Future};
+    use std::net::UdpSocket;
+    use std::sync::mpsc as std_mpsc;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_packet_stream_and_sink() {
+        let mut rx = VmaUdpSocket::new().unwrap();
+        rx.bind("127.0.0.1", 0).unwrap();
+        let target = rx.local_addr().unwrap();
+        let mut packets = PacketStream::spawn(rx, 16, &[]).unwrap();
+
+        let mut sink = PacketSink::new(VmaUdpSocket::new().unwrap());
+        let mut items = stream::iter((0..3u8).map(|i| Ok((Bytes::from(vec![i; 8]), target))));
+        block_on(sink.send_all(&mut items)).unwrap();
+        assert_eq!(sink.queued(), 0);
+        assert!(Pin::new(&mut sink).start_send((Bytes::from_static(b"v6"), "[::1]:1".parse().unwrap())).is_err());
+
+        let received: Vec<u8> = (0..3)
+            .map(|_| block_on(future::poll_fn(|cx| Pin::new(&mut packets).poll_next(cx))).unwrap().data[0])
+            .collect();
+        assert_eq!(received, vec![0, 1, 2]);
+        assert_eq!(packets.dropped(), 0);
+        assert!(packets.stop().is_ok());
+    }
+
+    struct Notify(std_mpsc::Sender<()>);
+
+    impl Wake for Notify {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.send(());
+        }
+    }
+
+    #[test]
+    fn test_writable_waiter_wakes() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let writer = WritableWaiter::spawn(socket.as_raw_fd()).unwrap();
+        let (tx, rx) = std_mpsc::channel();
+        writer.register(&Waker::from(Arc::new(Notify(tx))));
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_ok());
+        drop(writer);
+    }
+}
@@ -0,0 +1,381 @@
+//! Multicast feeds subscribed by channel name.
+//!
+//! A [`SubscriptionManager`] keeps a table of named channels, each a multicast group,
+//! port and interface, and joins or leaves them on demand: subscribing opens a socket
+//! bound to the group and port and joins the group on the interface, unsubscribing closes
+//! it. [`SubscriptionManager::poll`] receives from every subscribed channel round-robin
+//! on the calling thread, like [`Demux`], and tracks the packet and byte rates of each
+//! channel over [`RATE_WINDOW`].
+//!
+//! Channel tables are loaded from a plain text file, one channel per line:
+//!
+//! ```text
+//! # name          group       port   interface (optional, 0.0.0.0 lets the kernel pick)
+//! itch.a          239.1.1.1   30001  10.0.0.5
+//! itch.b          239.1.1.2   30002  10.0.0.5
+//! ```
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use vma_socket::common::VmaOptions;
+//! use vma_socket::subscription::SubscriptionManager;
+//!
+//! let mut manager = SubscriptionManager::new(VmaOptions::low_latency()).unwrap();
+//! let subscribed = manager.subscribe_from_file("/etc/feeds/channels.txt").unwrap();
+//! println!("subscribed to {} channels", subscribed);
+//!
+//! loop {
+//!     manager.poll(|channel, packet| println!("{}: {} bytes", channel, packet.data.len())).unwrap();
+//!     if let Some(stats) = manager.stats("itch.a") {
+//!         if stats.packets_per_sec == 0.0 {
+//!             manager.unsubscribe("itch.a");
+//!             manager.subscribe("itch.b").unwrap();
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`Demux`]: crate::demux::Demux
+
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::common::VmaOptions;
+use crate::demux::DEMUX_PASS_BURST;
+use crate::udp::{Packet, VmaUdpSocket};
+
+/// Period over which channel rates are measured.
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Receive buffer size used when `VmaOptions::buffer_size` is not set.
+const DEFAULT_SUBSCRIPTION_BUFFER: usize = 65536;
+
+/// Where a channel is published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Channel {
+    /// Multicast group
+    pub group: Ipv4Addr,
+    /// UDP port
+    pub port: u16,
+    /// Address of the local interface to join on (`0.0.0.0` lets the kernel pick)
+    pub interface: Ipv4Addr,
+}
+
+/// Traffic of a subscribed channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    /// Packets received since the channel was subscribed
+    pub packets: u64,
+    /// Bytes received since the channel was subscribed
+    pub bytes: u64,
+    /// Packets per second over the last complete [`RATE_WINDOW`]
+    pub packets_per_sec: f64,
+    /// Bytes per second over the last complete [`RATE_WINDOW`]
+    pub bytes_per_sec: f64,
+    /// When the last packet was received
+    pub last_packet: Option<Instant>,
+}
+
+/// Counters of a channel and the rate window being measured.
+#[derive(Debug)]
+struct Rate {
+    stats: ChannelStats,
+    window_start: Instant,
+    window_packets: u64,
+    window_bytes: u64,
+}
+
+impl Rate {
+    fn new(now: Instant) -> Self {
+        Rate {
+            stats: ChannelStats { packets: 0, bytes: 0, packets_per_sec: 0.0, bytes_per_sec: 0.0, last_packet: None },
+            window_start: now,
+            window_packets: 0,
+            window_bytes: 0,
+        }
+    }
+
+    fn record(&mut self, bytes: usize, now: Instant) {
+        self.roll(now);
+        self.stats.packets += 1;
+        self.stats.bytes += bytes as u64;
+        self.stats.last_packet = Some(now);
+        self.window_packets += 1;
+        self.window_bytes += bytes as u64;
+    }
+
+    /// Close the window if it is over, publishing its rates.
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+        let seconds = elapsed.as_secs_f64();
+        self.stats.packets_per_sec = self.window_packets as f64 / seconds;
+        self.stats.bytes_per_sec = self.window_bytes as f64 / seconds;
+        self.window_start = now;
+        self.window_packets = 0;
+        self.window_bytes = 0;
+    }
+}
+
+/// A joined channel.
+#[derive(Debug)]
+struct Subscription {
+    name: String,
+    socket: VmaUdpSocket,
+    rate: Rate,
+}
+
+/// Named multicast channels joined and left on demand.
+#[derive(Debug)]
+pub struct SubscriptionManager {
+    options: VmaOptions,
+    channels: BTreeMap<String, Channel>,
+    subscriptions: Vec<Subscription>,
+    buffer: Vec<u8>,
+    next: usize,
+}
+
+impl SubscriptionManager {
+    /// Create a manager without channels, whose sockets are created with `options`.
+    pub fn new(options: VmaOptions) -> Result<Self, Error> {
+        options.validate()?;
+        let buffer_size =
+            if options.buffer_size > 0 { options.buffer_size as usize } else { DEFAULT_SUBSCRIPTION_BUFFER };
+        Ok(SubscriptionManager {
+            options,
+            channels: BTreeMap::new(),
+            subscriptions: Vec::new(),
+            buffer: vec![0u8; buffer_size],
+            next: 0,
+        })
+    }
+
+    /// Define the channel `name`, replacing a previous definition.
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if `channel.group` is not a multicast group,
+    /// and `ErrorKind::AlreadyExists` if `name` is subscribed to another definition.
+    pub fn define<S: Into<String>>(&mut self, name: S, channel: Channel) -> Result<(), Error> {
+        let name = name.into();
+        if !channel.group.is_multicast() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} is not a multicast group", channel.group)));
+        }
+        if self.is_subscribed(&name) && self.channels.get(&name) != Some(&channel) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("Channel {} is subscribed", name)));
+        }
+        self.channels.insert(name, channel);
+        Ok(())
+    }
+
+    /// Define the channels of a channel file (see the module documentation).
+    ///
+    /// Returns the names defined, in file order.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<String>, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let mut names = Vec::new();
+        for (name, channel) in parse_channels(&text)? {
+            self.define(name.clone(), channel)?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    /// Define the channels of a channel file and subscribe to all of them.
+    ///
+    /// Returns the number of channels in the file. Channels subscribed before a failure
+    /// stay subscribed.
+    pub fn subscribe_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, Error> {
+        let names = self.load(path)?;
+        for name in &names {
+            self.subscribe(name)?;
+        }
+        Ok(names.len())
+    }
+
+    /// Get the definition of the channel `name`.
+    pub fn channel(&self, name: &str) -> Option<Channel> {
+        self.channels.get(name).copied()
+    }
+
+    /// Get the names of the defined channels, sorted.
+    pub fn channels(&self) -> Vec<&str> {
+        self.channels.keys().map(String::as_str).collect()
+    }
+
+    /// Join the channel `name`; does nothing if it is already subscribed.
+    ///
+    /// Fails with `ErrorKind::NotFound` if the channel is not defined.
+    pub fn subscribe(&mut self, name: &str) -> Result<(), Error> {
+        if self.is_subscribed(name) {
+            return Ok(());
+        }
+        let channel = self
+            .channels
+            .get(name)
+            .copied()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("No channel {}", name)))?;
+        let mut socket = VmaUdpSocket::with_options(self.options)?;
+        socket.set_reuse_port(true)?;
+        // Bound to the group, the socket only gets this channel even when several share a port
+        socket.bind(channel.group.to_string(), channel.port)?;
+        socket.join_multicast_v4(&channel.group, &channel.interface)?;
+        self.subscriptions.push(Subscription { name: name.to_string(), socket, rate: Rate::new(Instant::now()) });
+        Ok(())
+    }
+
+    /// Leave the channel `name`, returning whether it was subscribed.
+    pub fn unsubscribe(&mut self, name: &str) -> bool {
+        let Some(index) = self.subscriptions.iter().position(|subscription| subscription.name == name) else {
+            return false;
+        };
+        let mut subscription = self.subscriptions.remove(index);
+        if let Some(channel) = self.channels.get(name) {
+            // Closing the socket leaves the group too; leaving first releases it at once
+            let _ = subscription.socket.leave_multicast_v4(&channel.group, &channel.interface);
+        }
+        true
+    }
+
+    /// Check whether the channel `name` is subscribed.
+    pub fn is_subscribed(&self, name: &str) -> bool {
+        self.subscriptions.iter().any(|subscription| subscription.name == name)
+    }
+
+    /// Get the names of the subscribed channels, in subscription order.
+    pub fn subscribed(&self) -> Vec<&str> {
+        self.subscriptions.iter().map(|subscription| subscription.name.as_str()).collect()
+    }
+
+    /// Receive from every subscribed channel once without blocking, calling `handler` with
+    /// the channel name and packet.
+    ///
+    /// Returns the number of packets received.
+    pub fn poll<F: FnMut(&str, &Packet)>(&mut self, mut handler: F) -> Result<usize, Error> {
+        let count = self.subscriptions.len();
+        let mut received = 0;
+        for offset in 0..count {
+            let subscription = &mut self.subscriptions[(self.next + offset) % count];
+            for _ in 0..DEMUX_PASS_BURST {
+                let Some(packet) = subscription.socket.recv_from(&mut self.buffer, Some(0))? else { break };
+                subscription.rate.record(packet.data.len(), Instant::now());
+                handler(&subscription.name, &packet);
+                received += 1;
+            }
+        }
+        if count > 0 {
+            self.next = (self.next + 1) % count;
+        }
+        Ok(received)
+    }
+
+    /// Get the traffic of the subscribed channel `name`.
+    pub fn stats(&mut self, name: &str) -> Option<ChannelStats> {
+        let subscription = self.subscriptions.iter_mut().find(|subscription| subscription.name == name)?;
+        subscription.rate.roll(Instant::now());
+        Some(subscription.rate.stats)
+    }
+
+    /// Get the traffic of every subscribed channel, in subscription order.
+    pub fn all_stats(&mut self) -> Vec<(String, ChannelStats)> {
+        let now = Instant::now();
+        self.subscriptions
+            .iter_mut()
+            .map(|subscription| {
+                subscription.rate.roll(now);
+                (subscription.name.clone(), subscription.rate.stats)
+            })
+            .collect()
+    }
+}
+
+/// Parse a channel file: `name group port [interface]` per line, `#` starting a comment.
+pub fn parse_channels(text: &str) -> Result<Vec<(String, Channel)>, Error> {
+    let mut channels = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || Error::new(ErrorKind::InvalidData, format!("Invalid channel on line {}: {}", number + 1, line));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (name, group, port, interface) = match fields[..] {
+            [name, group, port] => (name, group, port, "0.0.0.0"),
+            [name, group, port, interface] => (name, group, port, interface),
+            _ => return Err(invalid()),
+        };
+        let channel = Channel {
+            group: group.parse().map_err(|_| invalid())?,
+            port: port.parse().map_err(|_| invalid())?,
+            interface: interface.parse().map_err(|_| invalid())?,
+        };
+        if !channel.group.is_multicast() {
+            return Err(invalid());
+        }
+        channels.push((name.to_string(), channel));
+    }
+    Ok(channels)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subscriptions() {
+        let text = "# name group port interface\nitch.a 239.1.1.1 30001 127.0.0.1\nitch.b 239.1.1.2 30002 # default interface\n";
+        let channels = parse_channels(text).unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[1].1, Channel { group: Ipv4Addr::new(239, 1, 1, 2), port: 30002, interface: Ipv4Addr::UNSPECIFIED });
+        assert!(parse_channels("itch.a 10.0.0.1 30001").is_err());
+        assert!(parse_channels("itch.a 239.1.1.1").is_err());
+
+        let mut manager = SubscriptionManager::new(VmaOptions::default()).unwrap();
+        for (name, channel) in channels {
+            manager.define(name, channel).unwrap();
+        }
+        assert_eq!(manager.channels(), ["itch.a", "itch.b"]);
+        assert_eq!(manager.subscribe("missing").unwrap_err().kind(), ErrorKind::NotFound);
+        if manager.subscribe("itch.a").is_err() {
+            return; // no multicast route in this environment
+        }
+        manager.subscribe("itch.a").unwrap();
+        assert_eq!(manager.subscribed(), ["itch.a"]);
+        let moved = Channel { group: Ipv4Addr::new(239, 1, 1, 9), port: 30001, interface: Ipv4Addr::LOCALHOST };
+        assert_eq!(manager.define("itch.a", moved).unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+        let mut rate = Rate::new(Instant::now() - RATE_WINDOW * 2);
+        rate.record(100, Instant::now());
+        assert_eq!((rate.stats.packets, rate.stats.bytes), (1, 100));
+        assert!(rate.stats.packets_per_sec == 0.0 && rate.window_packets == 1);
+        assert_eq!(manager.poll(|_, _| panic!("no traffic")).unwrap(), 0);
+
+        // Publish on the loopback interface the channel was joined on
+        let mut sender = VmaUdpSocket::new().unwrap();
+        let interface = libc::in_addr { s_addr: u32::from(Ipv4Addr::LOCALHOST).to_be() };
+        let size = std::mem::size_of_val(&interface) as libc::socklen_t;
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&sender);
+        let set = unsafe { libc::setsockopt(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_IF, &interface as *const _ as *const _, size) };
+        assert_eq!(set, 0);
+        sender.send_to(b"tick", "239.1.1.1", 30001).unwrap();
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            if manager.poll(|channel, packet| received.push((channel.to_string(), packet.data.clone()))).unwrap() > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received, [("itch.a".to_string(), b"tick".to_vec())]);
+        let stats = manager.stats("itch.a").unwrap();
+        assert_eq!((stats.packets, stats.bytes), (1, 4));
+        assert!(stats.last_packet.is_some());
+
+        assert!(manager.unsubscribe("itch.a"));
+        assert!(!manager.unsubscribe("itch.a"));
+        assert!(manager.stats("itch.a").is_none());
+    }
+}
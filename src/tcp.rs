@@ -29,7 +29,7 @@
 //! use vma_socket::udp::VmaUdpSocket;
 //! use vma_socket::common::VmaOptions;
 //!
-//! fn udp_example() -> Result<(), String> {
+//! fn udp_example() -> std::io::Result<()> {
 //!     // Create socket with low-latency profile
 //!     let vma_options = VmaOptions::low_latency();
 //!     let mut socket = VmaUdpSocket::with_options(vma_options)?;
@@ -63,7 +63,7 @@
 //! use vma_socket::tcp::VmaTcpSocket;
 //! use vma_socket::common::VmaOptions;
 //!
-//! fn tcp_server_example() -> Result<(), String> {
+//! fn tcp_server_example() -> std::io::Result<()> {
 //!     // Create socket with performance optimizations
 //!     let mut socket = VmaTcpSocket::with_options(VmaOptions::low_latency())?;
 //!     
@@ -103,20 +103,50 @@
 //! - [`tcp`]: High-performance TCP socket implementation
 //! - [`common`]: Shared types and utilities used by both implementations
 
-use crate::common::{unixnano_to_ms, sockaddr_to_rust, SockAddrIn, VmaOptions};
+use crate::notify::Notified;
+use crate::reconnect::ReconnectPolicy;
+use crate::registered::RegisteredBuffer;
+use crate::shutdown::{self, ShutdownToken};
+use crate::fdtrack::{self, FdTracker};
+use crate::common::{self, unixnano_to_ms, sockaddr_to_rust, MsgFlags, OptionsDelta, Protocol, SockAddrIn, SocketDiagnostics, SocketStats, StatsSource, FromFdError, VmaError, VmaFdStats, VmaOptions, WaitStrategy};
 use std::ffi::{c_void, CString};
+use std::marker::{PhantomData, PhantomPinned};
 use std::mem;
-use std::net::SocketAddr;
+use std::net::{Shutdown, SocketAddr};
+use std::ops::Range;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
 use std::os::raw::{c_char, c_int, c_ulonglong};
+use std::ptr::{self, NonNull};
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Maximum number of addresses tried by `connect_any()`
+pub const CONNECT_ANY_MAX: usize = 16;
 
 // External declarations for C functions - using VmaOptions directly
 extern "C" {
-    fn tcp_socket_init(socket: *mut TcpSocket, options: *const VmaOptions) -> c_int;
+    fn tcp_socket_new(socket: *mut *mut TcpSocket, options: *const VmaOptions) -> c_int;
+    fn tcp_socket_free(socket: *mut TcpSocket);
     fn tcp_socket_close(socket: *mut TcpSocket) -> c_int;
+    fn tcp_socket_fd(socket: *const TcpSocket) -> c_int;
+    fn tcp_socket_from_fd(socket: *mut *mut TcpSocket, fd: c_int, options: *const VmaOptions) -> c_int;
+    fn tcp_socket_release_fd(socket: *mut TcpSocket) -> c_int;
+    fn tcp_socket_get_state(socket: *const TcpSocket) -> TcpConnectionState;
+    fn tcp_socket_get_peer(socket: *const TcpSocket, addr: *mut SockAddrIn) -> bool;
+    fn tcp_socket_set_nodelay_option(socket: *mut TcpSocket, nodelay: bool);
+    fn tcp_socket_get_options(socket: *const TcpSocket, options: *mut VmaOptions);
+    fn tcp_socket_set_options(socket: *mut TcpSocket, options: *const VmaOptions);
     fn tcp_socket_bind(socket: *mut TcpSocket, ip: *const c_char, port: u16) -> c_int;
     fn tcp_socket_listen(socket: *mut TcpSocket, backlog: c_int) -> c_int;
-    fn tcp_socket_accept(socket: *mut TcpSocket, client: *mut TcpClient, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_accept_new(socket: *mut TcpSocket, client: *mut *mut TcpClient, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_pending_connections(socket: *mut TcpSocket, pending: *mut u32) -> c_int;
     fn tcp_socket_connect(socket: *mut TcpSocket, ip: *const c_char, port: u16, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_connect_start(socket: *mut TcpSocket, ip: *const c_char, port: u16) -> c_int;
+    fn tcp_socket_connect_poll(socket: *mut TcpSocket, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_connect_any(socket: *mut TcpSocket, addrs: *const SockAddrIn, count: usize, attempt_timeout_ms: c_int, stagger_ms: c_int, winner: *mut usize) -> c_int;
     fn tcp_socket_reconnect(socket: *mut TcpSocket, timeout_ms: c_int) -> c_int;
     fn tcp_socket_is_connected(socket: *mut TcpSocket) -> bool;
     fn tcp_socket_send(socket: *mut TcpSocket, data: *const c_void, length: usize, bytes_sent: *mut usize) -> c_int;
@@ -135,7 +165,74 @@ extern "C" {
         timeout_ms: c_int,
         bytes_received: *mut usize,
     ) -> c_int;
+    fn tcp_socket_send_zerocopy(socket: *mut TcpSocket, data: *const c_void, length: usize, bytes_sent: *mut usize) -> c_int;
+    fn tcp_socket_zerocopy_pending(socket: *mut TcpSocket) -> u32;
+    fn tcp_socket_send_dummy(socket: *mut TcpSocket, data: *const c_void, length: usize) -> c_int;
+    fn tcp_socket_send_flags(
+        socket: *mut TcpSocket,
+        data: *const c_void,
+        length: usize,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_send_timeout(
+        socket: *mut TcpSocket,
+        data: *const c_void,
+        length: usize,
+        timeout_ms: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_send_to_client_flags(
+        client: *mut TcpClient,
+        data: *const c_void,
+        length: usize,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_recv_at_least(
+        socket: *mut TcpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        min_bytes: usize,
+        timeout_ms: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_recv_flags(
+        socket: *mut TcpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        flags: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_recv_from_client_flags(
+        client: *mut TcpClient,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        flags: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_peek(
+        socket: *mut TcpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn tcp_socket_peek_client(
+        client: *mut TcpClient,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
     fn tcp_socket_close_client(client: *mut TcpClient) -> c_int;
+    fn tcp_socket_free_client(client: *mut TcpClient);
+    fn tcp_socket_client_fd(client: *const TcpClient) -> c_int;
+    fn tcp_socket_get_client_addr(client: *const TcpClient, addr: *mut SockAddrIn);
+    fn tcp_socket_get_client_stats(client: *const TcpClient, rx_bytes: *mut u64, tx_bytes: *mut u64, last_rx_ns: *mut u64);
+    fn tcp_socket_set_client_wait_strategy(client: *mut TcpClient, strategy: WaitStrategy, spin_budget_us: u32);
     fn tcp_socket_get_stats(
         socket: *mut TcpSocket,
         rx_packets: *mut c_ulonglong,
@@ -143,6 +240,17 @@ extern "C" {
         rx_bytes: *mut c_ulonglong,
         tx_bytes: *mut c_ulonglong,
     ) -> c_int;
+    fn tcp_socket_get_extended_stats(socket: *mut TcpSocket, stats: *mut SocketStats) -> c_int;
+    fn tcp_socket_reset_stats(socket: *mut TcpSocket) -> c_int;
+    fn tcp_socket_setopt(socket: *mut TcpSocket, level: c_int, optname: c_int, optval: *const c_void, optlen: libc::socklen_t) -> c_int;
+    fn tcp_socket_getopt(socket: *mut TcpSocket, level: c_int, optname: c_int, optval: *mut c_void, optlen: *mut libc::socklen_t) -> c_int;
+    fn tcp_socket_setopt_client(client: *mut TcpClient, level: c_int, optname: c_int, optval: *const c_void, optlen: libc::socklen_t) -> c_int;
+    fn tcp_socket_getopt_client(client: *mut TcpClient, level: c_int, optname: c_int, optval: *mut c_void, optlen: *mut libc::socklen_t) -> c_int;
+    fn tcp_socket_set_tos(socket: *mut TcpSocket, tos: c_int) -> c_int;
+    fn tcp_socket_poll_readable(socket: *mut TcpSocket, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_poll_readable_client(client: *mut TcpClient, timeout_ms: c_int) -> c_int;
+    fn tcp_socket_shutdown(socket: *mut TcpSocket, how: c_int) -> c_int;
+    fn tcp_socket_shutdown_client(client: *mut TcpClient, how: c_int) -> c_int;
 }
 
 /// Connection state enumeration for TCP sockets.
@@ -159,31 +267,67 @@ pub enum TcpConnectionState {
     Listening = 3,
 }
 
-/// C representation of a TCP socket.
+/// Map a shutdown direction to the `how` argument of shutdown(2).
+fn shutdown_how(how: Shutdown) -> c_int {
+    match how {
+        Shutdown::Read => libc::SHUT_RD,
+        Shutdown::Write => libc::SHUT_WR,
+        Shutdown::Both => libc::SHUT_RDWR,
+    }
+}
+
+/// How a [`VmaTcpSocket`] closes its connection when dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CloseMode {
+    /// Close right away and let the kernel send unsent data in the background (the default)
+    #[default]
+    Immediate,
+    /// Reset the connection (RST), discarding unsent data
+    Abortive,
+    /// Close like [`VmaTcpSocket::close_graceful`], blocking the drop up to `timeout`
+    Graceful {
+        /// Longest time to wait for the drain (and the peer's FIN)
+        timeout: Duration,
+        /// Also wait for the peer to close its side
+        wait_fin: bool,
+    },
+}
+
+/// Interval between two checks of the send queue while draining it before a close.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Convert a linger timeout to the C structure (`None` disables lingering).
+fn linger_from(timeout: Option<Duration>) -> libc::linger {
+    match timeout {
+        Some(duration) => libc::linger {
+            l_onoff: 1,
+            l_linger: duration.as_secs().min(c_int::MAX as u64) as c_int,
+        },
+        None => libc::linger { l_onoff: 0, l_linger: 0 },
+    }
+}
+
+/// Convert the C linger structure back to a timeout.
+fn linger_to(linger: libc::linger) -> Option<Duration> {
+    if linger.l_onoff != 0 {
+        Some(Duration::from_secs(linger.l_linger as u64))
+    } else {
+        None
+    }
+}
+
+/// Opaque C TCP socket state, allocated and freed by the C layer.
 #[repr(C)]
-#[derive(Debug, Clone)]
-pub struct TcpSocket {
-    pub socket_fd: c_int,
-    pub vma_options: VmaOptions,
-    pub local_addr: SockAddrIn,
-    pub remote_addr: SockAddrIn,
-    pub is_bound: bool,
-    pub state: TcpConnectionState,
-    pub rx_packets: c_ulonglong,
-    pub tx_packets: c_ulonglong,
-    pub rx_bytes: c_ulonglong,
-    pub tx_bytes: c_ulonglong,
-    pub backlog: c_int,
-}
-
-/// C representation of a TCP client connection.
+struct TcpSocket {
+    _private: [u8; 0],
+    _marker: PhantomData<(*mut u8, PhantomPinned)>,
+}
+
+/// Opaque C TCP client connection state, allocated and freed by the C layer.
 #[repr(C)]
-#[derive(Debug, Clone)]
-pub struct TcpClient {
-    pub socket_fd: c_int,
-    pub addr: SockAddrIn,
-    pub rx_bytes: c_ulonglong,
-    pub tx_bytes: c_ulonglong,
+struct TcpClient {
+    _private: [u8; 0],
+    _marker: PhantomData<(*mut u8, PhantomPinned)>,
 }
 
 /// Result codes returned by the C TCP socket functions.
@@ -206,6 +350,9 @@ pub enum TcpResult {
     TcpErrorClosed = -13,
     TcpErrorWouldBlock = -14,
     TcpErrorAlreadyConnected = -15,
+    TcpErrorShutdown = -16,
+    TcpErrorNotSupported = -17,
+    TcpErrorClose = -18,
 }
 
 use std::io::{Error, ErrorKind};
@@ -213,7 +360,7 @@ use std::io::{Error, ErrorKind};
 impl From<TcpResult> for std::io::Error {
     fn from(tcp_result: TcpResult) -> Self {
         match tcp_result {
-            TcpResult::TcpSuccess => Error::new(ErrorKind::Other, "Unexpected success"),
+            TcpResult::TcpSuccess => Error::other("Unexpected success"),
             TcpResult::TcpErrorSocketCreate => Error::new(ErrorKind::ConnectionRefused, "Socket creation failed"),
             TcpResult::TcpErrorSocketOption => Error::new(ErrorKind::InvalidInput, "Socket option error"),
             TcpResult::TcpErrorBind => Error::new(ErrorKind::AddrInUse, "Bind failed"),
@@ -229,39 +376,89 @@ impl From<TcpResult> for std::io::Error {
             TcpResult::TcpErrorClosed => Error::new(ErrorKind::ConnectionAborted, "Connection closed"),
             TcpResult::TcpErrorWouldBlock => Error::new(ErrorKind::WouldBlock, "Would block"),
             TcpResult::TcpErrorAlreadyConnected => Error::new(ErrorKind::AlreadyExists, "Already connected"),
+            TcpResult::TcpErrorShutdown => Error::new(ErrorKind::NotConnected, "Shutdown failed"),
+            TcpResult::TcpErrorNotSupported => Error::new(ErrorKind::Unsupported, "Not supported without VMA offload"),
+            TcpResult::TcpErrorClose => Error::other("Close failed"),
         }
     }
 }
 
+/// Current wall-clock time in nanoseconds, on the clock of `TcpClient::last_rx_ns`.
+fn wall_clock_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Traffic counters and timestamps of an accepted client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Number of received bytes
+    pub rx_bytes: u64,
+    /// Number of transmitted bytes
+    pub tx_bytes: u64,
+    /// Time the connection was accepted in nanoseconds since the epoch
+    pub connected_ns: u64,
+    /// Time of the last successful receive in nanoseconds since the epoch (0 if none)
+    pub last_rx_ns: u64,
+}
+
 /// Represents a connected client in a server context.
 ///
 /// This structure is created when a client connects to a listening socket,
 /// and provides methods for sending and receiving data to/from the client.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Client {
-    inner: TcpClient,
+    inner: NonNull<TcpClient>,
     /// The client's remote address and port
     pub address: SocketAddr,
+    connected_ns: u64,
+    idle_timeout: Option<Duration>,
+    slot: Option<ConnectionSlot>,
+    tracker: FdTracker,
 }
 
+// Same reasoning as for `TcpSocketWrapper`: the handle is owned by this `Client` alone,
+// and `&self` methods reach mutable C state, so it is `Send` but not `Sync`.
+unsafe impl Send for Client {}
+
 impl Client {
     /// Create a new Client from a TcpClient structure.
     ///
     /// This is used internally by the accept() method.
-    fn new(client: TcpClient) -> Self {
-        let address = sockaddr_to_rust(&client.addr);
+    fn new(client: NonNull<TcpClient>) -> Self {
+        let mut addr = unsafe { mem::zeroed::<SockAddrIn>() };
+        unsafe { tcp_socket_get_client_addr(client.as_ptr(), &mut addr) };
+        let address = sockaddr_to_rust(&addr);
+        let fd = unsafe { tcp_socket_client_fd(client.as_ptr()) };
         Client {
             inner: client,
             address,
+            connected_ns: wall_clock_ns(),
+            idle_timeout: None,
+            slot: None,
+            tracker: FdTracker::new(Protocol::Tcp, fd),
         }
     }
     
+    /// Get the C client, checking its descriptor against the fd registry.
+    fn as_ptr(&self) -> *mut TcpClient {
+        self.tracker.observe(unsafe { tcp_socket_client_fd(self.inner.as_ptr()) });
+        self.inner.as_ptr()
+    }
+    
+    /// Get the file descriptor of the connection.
+    pub fn fd(&self) -> c_int {
+        unsafe { tcp_socket_client_fd(self.as_ptr()) }
+    }
+    
     /// Send data to the client.
     pub fn send(&mut self, data: &[u8]) -> Result<usize, TcpResult> {
         let mut bytes_sent: usize = 0;
         let result = unsafe {
             tcp_socket_send_to_client(
-                &mut self.inner,
+                self.as_ptr(),
                 data.as_ptr() as *const c_void,
                 data.len(),
                 &mut bytes_sent,
@@ -283,7 +480,72 @@ impl Client {
         
         let result = unsafe {
             tcp_socket_recv_from_client(
-                &mut self.inner,
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                &mut bytes_received,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+    
+    /// Send data with `send(2)` flags (`MSG_NOSIGNAL` is always added).
+    pub fn send_with_flags(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, TcpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            tcp_socket_send_to_client_flags(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+    
+    /// Receive data with `recv(2)` flags (`MsgFlags::DONTWAIT` ignores the timeout).
+    pub fn recv_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<usize, TcpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            tcp_socket_recv_from_client_flags(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                flags.bits(),
+                &mut bytes_received,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+    
+    /// Copy received data into `buffer` without removing it from the stream.
+    pub fn peek(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, TcpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            tcp_socket_peek_client(
+                self.as_ptr(),
                 buffer.as_mut_ptr() as *mut c_void,
                 buffer.len(),
                 timeout_ms,
@@ -298,11 +560,171 @@ impl Client {
         Ok(bytes_received)
     }
     
+    /// Override the receive wait strategy inherited from the listening socket.
+    pub fn set_wait_strategy(&mut self, strategy: WaitStrategy, spin_budget_us: u32) {
+        unsafe { tcp_socket_set_client_wait_strategy(self.as_ptr(), strategy, spin_budget_us) };
+    }
+    
+    /// Get the local address of the connection (the listener address the client reached).
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        common::local_addr(self.fd())
+    }
+    
+    /// Wait up to `timeout_nano` for data or end-of-stream from the client (`Some(0)` only checks).
+    pub fn poll_readable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        match unsafe { tcp_socket_poll_readable_client(self.as_ptr(), unixnano_to_ms(timeout_nano)) } {
+            result if result < 0 => Err(Error::last_os_error()),
+            result => Ok(result > 0),
+        }
+    }
+    
+    /// Wait up to `timeout_nano` for room in the send buffer (`Some(0)` only checks).
+    ///
+    /// Returns `false` on timeout, i.e. while the client is not reading fast enough.
+    pub fn poll_writable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        common::wait_writable(self.fd(), timeout_nano)
+    }
+    
+    /// Get the number of bytes sent but not yet acknowledged by the client.
+    pub fn pending_tx_bytes(&self) -> Result<usize, std::io::Error> {
+        common::pending_tx_bytes(self.fd())
+    }
+    
+    /// Get the time the connection was accepted.
+    pub fn connected_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.connected_ns)
+    }
+    
+    /// Get the client's traffic counters and timestamps.
+    pub fn stats(&self) -> ClientStats {
+        let mut stats = ClientStats { connected_ns: self.connected_ns, ..ClientStats::default() };
+        unsafe {
+            tcp_socket_get_client_stats(
+                self.as_ptr(),
+                &mut stats.rx_bytes,
+                &mut stats.tx_bytes,
+                &mut stats.last_rx_ns,
+            )
+        };
+        stats
+    }
+    
+    /// Set how long the client may stay silent before it counts as idle (`None` never).
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+    
+    /// Get the idle timeout.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+    
+    /// Get the time since data was last received from the client (or since it was accepted).
+    pub fn idle_time(&self) -> Duration {
+        let last_activity = self.stats().last_rx_ns.max(self.connected_ns);
+        Duration::from_nanos(wall_clock_ns().saturating_sub(last_activity))
+    }
+    
+    /// Check whether the client has been silent for longer than its idle timeout.
+    pub fn is_idle(&self) -> bool {
+        self.idle_timeout.is_some_and(|timeout| self.idle_time() > timeout)
+    }
+    
+    /// Enable or disable TCP_NODELAY (Nagle's algorithm is disabled when `true`).
+    pub fn set_nodelay(&mut self, nodelay: bool) -> Result<(), TcpResult> {
+        self.setopt_int(libc::IPPROTO_TCP, libc::TCP_NODELAY, nodelay as c_int)
+    }
+    
+    /// Check whether TCP_NODELAY is enabled.
+    pub fn nodelay(&mut self) -> Result<bool, TcpResult> {
+        self.getopt_int(libc::IPPROTO_TCP, libc::TCP_NODELAY).map(|value| value != 0)
+    }
+    
+    /// Configure SO_LINGER for when the connection is closed.
+    ///
+    /// `None` (the default) returns from close immediately and lets the kernel drain
+    /// unsent data in the background. `Some(Duration::ZERO)` resets the connection
+    /// (RST) and discards unsent data. `Some(timeout)` blocks close until the data is
+    /// sent or the timeout (whole seconds) expires.
+    pub fn set_linger(&mut self, timeout: Option<Duration>) -> Result<(), TcpResult> {
+        self.setopt(libc::SOL_SOCKET, libc::SO_LINGER, &linger_from(timeout))
+    }
+    
+    /// Get the SO_LINGER setting.
+    pub fn linger(&mut self) -> Result<Option<Duration>, TcpResult> {
+        self.getopt::<libc::linger>(libc::SOL_SOCKET, libc::SO_LINGER).map(linger_to)
+    }
+    
+    /// Set an integer socket option on the client connection.
+    pub(crate) fn setopt_int(&mut self, level: c_int, optname: c_int, value: c_int) -> Result<(), TcpResult> {
+        self.setopt(level, optname, &value)
+    }
+    
+    /// Get an integer socket option of the client connection.
+    pub(crate) fn getopt_int(&mut self, level: c_int, optname: c_int) -> Result<c_int, TcpResult> {
+        self.getopt::<c_int>(level, optname)
+    }
+    
+    /// Set a socket option on the client connection from a plain C value (`c_int`, `libc::linger`, ...).
+    pub(crate) fn setopt<T: Copy>(&mut self, level: c_int, optname: c_int, value: &T) -> Result<(), TcpResult> {
+        let result = unsafe {
+            tcp_socket_setopt_client(
+                self.as_ptr(),
+                level,
+                optname,
+                value as *const T as *const c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Get a socket option of the client connection as a plain C value.
+    pub(crate) fn getopt<T: Copy>(&mut self, level: c_int, optname: c_int) -> Result<T, TcpResult> {
+        let mut value = unsafe { mem::zeroed::<T>() };
+        let mut len = mem::size_of::<T>() as libc::socklen_t;
+        let result = unsafe {
+            tcp_socket_getopt_client(
+                self.as_ptr(),
+                level,
+                optname,
+                &mut value as *mut T as *mut c_void,
+                &mut len,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(value)
+    }
+    
+    /// Shut down the read, write or both halves of the connection.
+    ///
+    /// `Shutdown::Write` sends a FIN while the client can still be read from; `recv()`
+    /// keeps returning data until the client's own FIN arrives as `TcpErrorClosed`.
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), TcpResult> {
+        let result = unsafe { tcp_socket_shutdown_client(self.as_ptr(), shutdown_how(how)) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
     /// Explicitly close the client connection.
     ///
     /// Note: The connection will be closed automatically when the Client is dropped.
     pub fn close(&mut self) -> Result<(), TcpResult> {
-        let result = unsafe { tcp_socket_close_client(&mut self.inner) };
+        let result = unsafe { tcp_socket_close_client(self.as_ptr()) };
+        self.tracker.release();
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
@@ -315,46 +737,138 @@ impl Client {
 impl Drop for Client {
     /// Automatically close the client connection when it goes out of scope.
     fn drop(&mut self) {
-        if self.inner.socket_fd >= 0 {
-            unsafe {
-                tcp_socket_close_client(&mut self.inner);
-            }
+        let client = self.as_ptr();
+        let fd = self.fd();
+        if fdtrack::ENABLED && fd >= 0 {
+            warn_unsent(fd);
+        }
+        self.tracker.release();
+        unsafe {
+            tcp_socket_free_client(client);
+        }
+    }
+}
+
+impl std::io::Read for Client {
+    /// Receive into `buffer`, blocking until data arrives; `Ok(0)` means the peer closed.
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self.recv(buffer, None) {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorClosed) => Ok(0), // peer sent FIN
+            Err(TcpResult::TcpErrorTimeout) => Err(Error::new(ErrorKind::WouldBlock, "Read timed out")),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl std::io::Write for Client {
+    /// Send from `buffer`, failing with `ErrorKind::WouldBlock` when nothing could be written.
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        match self.send(buffer) {
+            Ok(0) if !buffer.is_empty() => Err(Error::new(ErrorKind::WouldBlock, "Would block")),
+            Ok(bytes) => Ok(bytes),
+            Err(e) => Err(e.into()),
         }
     }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Low-level wrapper around the C TCP socket implementation.
-/// Uses stack allocation instead of heap allocation for better performance.
-#[derive(Debug, Clone)]
+/// Holds only a pointer to the socket state owned by the C layer.
+#[derive(Debug)]
 pub struct TcpSocketWrapper {
-    socket: TcpSocket,
+    socket: NonNull<TcpSocket>,
+    close_mode: CloseMode,
+    tracker: FdTracker,
 }
 
+// The handle is owned by this wrapper alone, so it may move to another thread. It is not
+// `Sync`: some `&self` methods pass the handle to C calls that update the counters and the
+// zero-copy state, which would race between threads sharing a reference.
+unsafe impl Send for TcpSocketWrapper {}
+
 impl TcpSocketWrapper {
+    /// Take ownership of a socket created by the C layer.
+    fn adopt(socket: NonNull<TcpSocket>) -> Self {
+        let fd = unsafe { tcp_socket_fd(socket.as_ptr()) };
+        TcpSocketWrapper {
+            socket,
+            close_mode: CloseMode::Immediate,
+            tracker: FdTracker::new(Protocol::Tcp, fd),
+        }
+    }
+    
+    /// Get the C socket, checking its descriptor against the fd registry.
+    fn as_ptr(&self) -> *mut TcpSocket {
+        self.tracker.observe(unsafe { tcp_socket_fd(self.socket.as_ptr()) });
+        self.socket.as_ptr()
+    }
+    
     /// Create a new TCP socket with the specified options.
     pub fn new(options: Option<VmaOptions>) -> Result<Self, TcpResult> {
-        let mut socket = unsafe { mem::zeroed::<TcpSocket>() };
+        let c_options = options.unwrap_or_default();
+        
+        let mut socket = ptr::null_mut();
+        let result = unsafe { tcp_socket_new(&mut socket, &c_options) };
         
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        NonNull::new(socket)
+            .map(TcpSocketWrapper::adopt)
+            .ok_or(TcpResult::TcpErrorSocketCreate)
+    }
+    
+    /// Adopt an existing IPv4 TCP descriptor, leaving it open on failure.
+    pub fn from_raw_fd(fd: c_int, options: Option<VmaOptions>) -> Result<Self, TcpResult> {
         let c_options = options.unwrap_or_default();
         
-        let result = unsafe { 
-            println!("Initializing TCP socket with options: use_socketxtreme={}, optimize_for_latency={}, ring_count={}",
-                c_options.use_socketxtreme, c_options.optimize_for_latency, c_options.ring_count);
-            tcp_socket_init(&mut socket, &c_options)
-        };
+        let mut socket = ptr::null_mut();
+        let result = unsafe { tcp_socket_from_fd(&mut socket, fd, &c_options) };
         
         if result != TcpResult::TcpSuccess as i32 {
-            println!("TCP socket initialization failed with code: {}", result);
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
         }
         
-        Ok(TcpSocketWrapper { socket })
+        NonNull::new(socket)
+            .map(TcpSocketWrapper::adopt)
+            .ok_or(TcpResult::TcpErrorSocketCreate)
+    }
+    
+    /// Detach the descriptor and free the socket without closing it (-1 if it has none).
+    pub fn into_raw_fd(self) -> c_int {
+        let fd = unsafe { tcp_socket_release_fd(self.as_ptr()) };
+        self.tracker.release();
+        fd
+    }
+    
+    /// Get the file descriptor of the socket.
+    pub fn fd(&self) -> c_int {
+        unsafe { tcp_socket_fd(self.as_ptr()) }
+    }
+    
+    /// Wait up to `timeout_nano` for data or end-of-stream, following the wait strategy (`Some(0)` only checks).
+    pub fn poll_readable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        match unsafe { tcp_socket_poll_readable(self.as_ptr(), unixnano_to_ms(timeout_nano)) } {
+            result if result < 0 => Err(Error::last_os_error()),
+            result => Ok(result > 0),
+        }
+    }
+    
+    /// Get the address of the last `connect()`, if any.
+    pub fn peer(&self) -> Option<SockAddrIn> {
+        let mut addr = unsafe { mem::zeroed::<SockAddrIn>() };
+        unsafe { tcp_socket_get_peer(self.as_ptr(), &mut addr) }.then_some(addr)
     }
     
     /// Bind the socket to a local address and port.
     pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), TcpResult> {
         let c_addr = CString::new(addr.into()).unwrap();
-        let result = unsafe { tcp_socket_bind(&mut self.socket, c_addr.as_ptr(), port) };
+        let result = unsafe { tcp_socket_bind(self.as_ptr(), c_addr.as_ptr(), port) };
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
@@ -365,7 +879,7 @@ impl TcpSocketWrapper {
     
     /// Put the socket in listening mode (server).
     pub fn listen(&mut self, backlog: i32) -> Result<(), TcpResult> {
-        let result = unsafe { tcp_socket_listen(&mut self.socket, backlog) };
+        let result = unsafe { tcp_socket_listen(self.as_ptr(), backlog) };
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
@@ -376,16 +890,28 @@ impl TcpSocketWrapper {
     
     /// Accept a client connection (server).
     pub fn accept(&mut self, timeout_nano: Option<u64>) -> Result<Client, TcpResult> {
-        let mut client = unsafe { mem::zeroed::<TcpClient>() };
+        let mut client = ptr::null_mut();
         let timeout_ms = unixnano_to_ms(timeout_nano);
         
-        let result = unsafe { tcp_socket_accept(&mut self.socket, &mut client, timeout_ms) };
+        let result = unsafe { tcp_socket_accept_new(self.as_ptr(), &mut client, timeout_ms) };
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
         }
         
-        Ok(Client::new(client))
+        NonNull::new(client).map(Client::new).ok_or(TcpResult::TcpErrorAccept)
+    }
+    
+    /// Get the number of connections waiting in the accept queue (server).
+    pub fn pending_connections(&mut self) -> Result<u32, TcpResult> {
+        let mut pending: u32 = 0;
+        let result = unsafe { tcp_socket_pending_connections(self.as_ptr(), &mut pending) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(pending)
     }
     
     /// Connect to a server (client).
@@ -393,7 +919,7 @@ impl TcpSocketWrapper {
         let c_addr = CString::new(addr.into()).unwrap();
         let timeout_ms = unixnano_to_ms(timeout_nano);
         
-        let result = unsafe { tcp_socket_connect(&mut self.socket, c_addr.as_ptr(), port, timeout_ms) };
+        let result = unsafe { tcp_socket_connect(self.as_ptr(), c_addr.as_ptr(), port, timeout_ms) };
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
@@ -402,10 +928,14 @@ impl TcpSocketWrapper {
         Ok(())
     }
     
-    /// Attempt to reconnect after a disconnection.
-    pub fn reconnect(&mut self, timeout: Option<u64>) -> Result<(), TcpResult> {
-        let timeout_ms = unixnano_to_ms(timeout);
-        let result = unsafe { tcp_socket_reconnect(&mut self.socket, timeout_ms) };
+    /// Start connecting to a server without waiting for the handshake.
+    ///
+    /// Returns `Err(TcpErrorWouldBlock)` while the connection is in progress; complete it
+    /// with [`poll_connect`](Self::poll_connect).
+    pub fn connect_start<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), TcpResult> {
+        let c_addr = CString::new(addr.into()).unwrap();
+        
+        let result = unsafe { tcp_socket_connect_start(self.as_ptr(), c_addr.as_ptr(), port) };
         
         if result != TcpResult::TcpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
@@ -414,20 +944,183 @@ impl TcpSocketWrapper {
         Ok(())
     }
     
-    /// Check if the socket is currently connected.
-    pub fn is_connected(&mut self) -> bool {
-        unsafe { tcp_socket_is_connected(&mut self.socket) }
+    /// Wait for a connection started with `connect_start()` to complete.
+    ///
+    /// Returns `Err(TcpErrorTimeout)` while the connection is still in progress.
+    pub fn poll_connect(&mut self, timeout_nano: Option<u64>) -> Result<(), TcpResult> {
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe { tcp_socket_connect_poll(self.as_ptr(), timeout_ms) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
     }
     
-    /// Send data over the connected socket.
-    pub fn send(&mut self, data: &[u8]) -> Result<usize, TcpResult> {
-        let mut bytes_sent: usize = 0;
+    /// Connect to the first reachable address of `addrs`, returning its index.
+    ///
+    /// The next address is tried when an attempt fails, or after `stagger` while it is
+    /// still in progress (`None` waits for the failure). The winning connection replaces
+    /// the socket's file descriptor.
+    pub fn connect_any(&mut self, addrs: &[SockAddrIn], attempt_timeout: Option<u64>, stagger: Option<u64>) -> Result<usize, TcpResult> {
+        let mut winner = 0;
         let result = unsafe {
-            tcp_socket_send(
-                &mut self.socket,
-                data.as_ptr() as *const c_void,
-                data.len(),
-                &mut bytes_sent,
+            tcp_socket_connect_any(
+                self.as_ptr(),
+                addrs.as_ptr(),
+                addrs.len(),
+                unixnano_to_ms(attempt_timeout),
+                unixnano_to_ms(stagger),
+                &mut winner,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(winner)
+    }
+    
+    /// Attempt to reconnect after a disconnection.
+    pub fn reconnect(&mut self, timeout: Option<u64>) -> Result<(), TcpResult> {
+        let timeout_ms = unixnano_to_ms(timeout);
+        let result = unsafe { tcp_socket_reconnect(self.as_ptr(), timeout_ms) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Check if the socket is currently connected.
+    pub fn is_connected(&mut self) -> bool {
+        unsafe { tcp_socket_is_connected(self.as_ptr()) }
+    }
+    
+    /// Get the connection state tracked by the C layer.
+    pub fn state(&self) -> TcpConnectionState {
+        unsafe { tcp_socket_get_state(self.as_ptr()) }
+    }
+    
+    /// Close the socket; it can be connected again afterwards.
+    ///
+    /// The descriptor is released even if `close(2)` fails (`TcpErrorClose`, errno kept).
+    pub fn close(&mut self) -> Result<(), TcpResult> {
+        let result = unsafe { tcp_socket_close(self.as_ptr()) };
+        self.tracker.release();
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Set how the connection is closed when the socket is dropped.
+    pub fn set_close_mode(&mut self, mode: CloseMode) {
+        self.close_mode = mode;
+    }
+    
+    /// Get the options recorded for the socket.
+    pub fn options(&self) -> VmaOptions {
+        let mut options = VmaOptions::default();
+        unsafe { tcp_socket_get_options(self.as_ptr(), &mut options) };
+        options
+    }
+    
+    /// Replace the recorded options, used by receives, accepts and reconnects from now on.
+    pub fn set_options(&mut self, options: &VmaOptions) {
+        unsafe { tcp_socket_set_options(self.as_ptr(), options) };
+    }
+    
+    /// Get how the connection is closed when the socket is dropped.
+    pub fn close_mode(&self) -> CloseMode {
+        self.close_mode
+    }
+    
+    /// Wait up to `timeout` for the send queue to be acknowledged, send a FIN, optionally
+    /// wait for the peer's FIN, then close.
+    ///
+    /// Returns `true` if everything completed in time. Otherwise the socket is closed
+    /// anyway and the kernel keeps sending unsent data in the background.
+    pub fn close_graceful(&mut self, timeout: Duration, wait_fin: bool) -> Result<bool, TcpResult> {
+        if self.fd() < 0 {
+            return Err(TcpResult::TcpErrorInvalidParam);
+        }
+        let deadline = Instant::now() + timeout;
+        let mut complete = true;
+        if self.state() == TcpConnectionState::Connected {
+            complete = self.drain_tx(deadline);
+            if complete {
+                complete = self.shutdown(Shutdown::Write).is_ok() && (!wait_fin || self.wait_fin(deadline));
+            }
+        }
+        self.close()?;
+        Ok(complete)
+    }
+    
+    /// Close the socket following its close mode.
+    pub fn close_with_mode(&mut self) -> Result<(), TcpResult> {
+        match self.close_mode {
+            CloseMode::Immediate => {}
+            CloseMode::Abortive => {
+                let _ = self.set_linger(Some(Duration::ZERO));
+            }
+            CloseMode::Graceful { timeout, wait_fin } => {
+                return self.close_graceful(timeout, wait_fin).map(|_| ());
+            }
+        }
+        self.close()
+    }
+    
+    /// Wait until the send queue is empty, returning `false` at the deadline.
+    fn drain_tx(&self, deadline: Instant) -> bool {
+        loop {
+            match common::pending_tx_bytes(self.fd()) {
+                Ok(0) | Err(_) => return true, // nothing left, or no way to tell
+                Ok(_) if Instant::now() >= deadline => return false,
+                Ok(_) => std::thread::sleep(DRAIN_POLL_INTERVAL),
+            }
+        }
+    }
+    
+    /// Read and discard data until the peer's FIN, returning `false` at the deadline.
+    fn wait_fin(&mut self, deadline: Instant) -> bool {
+        let mut scratch = [0u8; 4096];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.recv(&mut scratch, Some(remaining.as_nanos() as u64)) {
+                Ok(0) | Err(TcpResult::TcpErrorClosed) => return true,
+                Ok(_) if !remaining.is_zero() => {}
+                _ => return false,
+            }
+        }
+    }
+    
+    /// Shut down the read, write or both halves of the connection.
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), TcpResult> {
+        let result = unsafe { tcp_socket_shutdown(self.as_ptr(), shutdown_how(how)) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Send data over the connected socket.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, TcpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            tcp_socket_send(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                &mut bytes_sent,
             )
         };
         
@@ -445,7 +1138,162 @@ impl TcpSocketWrapper {
         
         let result = unsafe {
             tcp_socket_recv(
-                &mut self.socket,
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                &mut bytes_received,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+    
+    /// Send data with `send(2)` flags (`MSG_NOSIGNAL` is always added).
+    pub fn send_with_flags(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, TcpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            tcp_socket_send_flags(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+    
+    /// Send data, waiting up to `timeout_nano` for room in the send buffer.
+    ///
+    /// A timeout is not an error: the bytes written before it expired are returned.
+    pub fn send_timeout(&mut self, data: &[u8], timeout_nano: Option<u64>) -> Result<usize, TcpResult> {
+        let mut bytes_sent: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        let result = unsafe {
+            tcp_socket_send_timeout(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                timeout_ms,
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 && result != TcpResult::TcpErrorTimeout as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+    
+    /// Receive at least `min_bytes`, waiting up to `timeout_nano` for the whole call.
+    ///
+    /// A timeout, or a peer close after some data arrived, is not an error: the bytes
+    /// received before it are returned.
+    pub fn recv_at_least(&mut self, buffer: &mut [u8], min_bytes: usize, timeout_nano: Option<u64>) -> Result<usize, TcpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        let result = unsafe {
+            tcp_socket_recv_at_least(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                min_bytes,
+                timeout_ms,
+                &mut bytes_received,
+            )
+        };
+        
+        if result == TcpResult::TcpErrorClosed as i32 && bytes_received > 0 {
+            return Ok(bytes_received);
+        }
+        if result != TcpResult::TcpSuccess as i32 && result != TcpResult::TcpErrorTimeout as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+    
+    /// Receive data with `recv(2)` flags (`MsgFlags::DONTWAIT` ignores the timeout).
+    pub fn recv_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<usize, TcpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            tcp_socket_recv_flags(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                flags.bits(),
+                &mut bytes_received,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+    
+    /// Send data with MSG_ZEROCOPY, copying when the socket does not support it.
+    ///
+    /// `data` must stay unchanged until `zerocopy_pending()` reports the send released.
+    pub fn send_zerocopy(&mut self, data: &[u8]) -> Result<usize, TcpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            tcp_socket_send_zerocopy(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+    
+    /// Get the number of zero-copy sends whose data is still in use.
+    pub fn zerocopy_pending(&mut self) -> u32 {
+        unsafe { tcp_socket_zerocopy_pending(self.as_ptr()) }
+    }
+    
+    /// Run a VMA dummy send of `data` on the connected socket (never put on the wire).
+    pub fn send_dummy(&mut self, data: &[u8]) -> Result<(), TcpResult> {
+        let result = unsafe {
+            tcp_socket_send_dummy(self.as_ptr(), data.as_ptr() as *const c_void, data.len())
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Copy received data into `buffer` without removing it from the stream.
+    pub fn peek(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, TcpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            tcp_socket_peek(
+                self.as_ptr(),
                 buffer.as_mut_ptr() as *mut c_void,
                 buffer.len(),
                 timeout_ms,
@@ -469,7 +1317,7 @@ impl TcpSocketWrapper {
         
         let result = unsafe {
             tcp_socket_get_stats(
-                &mut self.socket as *mut _,
+                self.as_ptr(),
                 &mut rx_packets,
                 &mut tx_packets,
                 &mut rx_bytes,
@@ -483,107 +1331,1483 @@ impl TcpSocketWrapper {
         
         Ok((rx_packets, tx_packets, rx_bytes, tx_bytes))
     }
-}
-
-impl Drop for TcpSocketWrapper {
-    /// Automatically close the socket when it goes out of scope.
-    fn drop(&mut self) {
-        unsafe {
-            tcp_socket_close(&mut self.socket);
+    
+    /// Get the full set of socket counters.
+    pub fn stats(&mut self) -> Result<SocketStats, TcpResult> {
+        let mut stats = SocketStats::default();
+        let result = unsafe { tcp_socket_get_extended_stats(self.as_ptr(), &mut stats) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
         }
+        
+        Ok(stats)
     }
-}
-
-/// High-level Rust-friendly TCP socket implementation.
-#[derive(Debug, Clone)]
-pub struct VmaTcpSocket {
-    inner: TcpSocketWrapper,
-}
-
-impl VmaTcpSocket {
-    /// Create a new TCP socket with default VMA options.
-    pub fn new() -> Result<Self, std::io::Error> {
-        TcpSocketWrapper::new(None)
-            .map(|inner| VmaTcpSocket { inner })
-            .map_err(|e| e.into())
+    
+    /// Zero the counters, keeping the last receive and send times.
+    pub fn reset_stats(&mut self) -> Result<(), TcpResult> {
+        let result = unsafe { tcp_socket_reset_stats(self.as_ptr()) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
     }
     
-    /// Create a new TCP socket with custom VMA options.
-    pub fn with_options(options: VmaOptions) -> Result<Self, std::io::Error> {
-        TcpSocketWrapper::new(Some(options))
-            .map(|inner| VmaTcpSocket { inner })
-            .map_err(|e| e.into())
+    /// Enable or disable TCP_NODELAY (Nagle's algorithm is disabled when `true`).
+    ///
+    /// The setting is also kept in the socket options so it survives a reconnect.
+    pub fn set_nodelay(&mut self, nodelay: bool) -> Result<(), TcpResult> {
+        self.setopt_int(libc::IPPROTO_TCP, libc::TCP_NODELAY, nodelay as c_int)?;
+        unsafe { tcp_socket_set_nodelay_option(self.as_ptr(), nodelay) };
+        Ok(())
     }
     
-    /// Bind the socket to a local address and port.
-    pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
-        self.inner
-            .bind(addr, port)
-            .map_err(|e| e.into())
+    /// Check whether TCP_NODELAY is enabled.
+    pub fn nodelay(&mut self) -> Result<bool, TcpResult> {
+        self.getopt_int(libc::IPPROTO_TCP, libc::TCP_NODELAY).map(|value| value != 0)
     }
     
-    /// Put the socket in listening mode (server).
-    pub fn listen(&mut self, backlog: i32) -> Result<(), std::io::Error> {
-        self.inner
-            .listen(backlog)
-            .map_err(|e| e.into())
+    /// Enable or disable TCP_CORK (partial segments are held back while enabled).
+    pub fn set_cork(&mut self, cork: bool) -> Result<(), TcpResult> {
+        self.setopt_int(libc::IPPROTO_TCP, libc::TCP_CORK, cork as c_int)
     }
     
-    /// Accept a client connection (server).
-    pub fn accept(&mut self, timeout_nano: Option<u64>) -> Result<Option<Client>, std::io::Error> {
-        match self.inner.accept(timeout_nano) {
-            Ok(client) => Ok(Some(client)),
-            Err(TcpResult::TcpErrorTimeout) => Ok(None), // timeout is not an error
-            Err(e) => Err(e.into()),
-        }
+    /// Check whether TCP_CORK is enabled.
+    pub fn cork(&mut self) -> Result<bool, TcpResult> {
+        self.getopt_int(libc::IPPROTO_TCP, libc::TCP_CORK).map(|value| value != 0)
     }
     
-    /// Connect to a server (client).
-    pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16, timeout: Option<u64>) -> Result<bool, std::io::Error> {
-        match self.inner.connect(addr, port, timeout) {
-            Ok(_) => Ok(true),
-            Err(TcpResult::TcpErrorTimeout) => Ok(false), // timeout is not an error
-            Err(e) => Err(e.into()),
-        }
+    /// Set the socket receive buffer size (SO_RCVBUF) in bytes.
+    ///
+    /// Overrides the size applied from `VmaOptions::buffer_size` at creation.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), TcpResult> {
+        let size = c_int::try_from(size).map_err(|_| TcpResult::TcpErrorInvalidParam)?;
+        self.setopt_int(libc::SOL_SOCKET, libc::SO_RCVBUF, size)
     }
     
-    /// Attempt to reconnect after a disconnection.
-    pub fn try_reconnect(&mut self, timeout: Option<u64>) -> Result<bool, std::io::Error> {
-        match self.inner.reconnect(timeout) {
-            Ok(_) => Ok(true),
-            Err(TcpResult::TcpErrorTimeout) => Ok(false), // timeout is not an error
-            Err(TcpResult::TcpErrorReconnect) => Ok(false), // reconnect failure is treated as a false result
+    /// Get the socket receive buffer size (SO_RCVBUF) in bytes.
+    ///
+    /// Linux reports twice the requested size to account for bookkeeping overhead.
+    pub fn recv_buffer_size(&mut self) -> Result<usize, TcpResult> {
+        self.getopt_int(libc::SOL_SOCKET, libc::SO_RCVBUF).map(|size| size as usize)
+    }
+    
+    /// Set the socket send buffer size (SO_SNDBUF) in bytes.
+    ///
+    /// Overrides the size applied from `VmaOptions::buffer_size` at creation.
+    pub fn set_send_buffer_size(&mut self, size: usize) -> Result<(), TcpResult> {
+        let size = c_int::try_from(size).map_err(|_| TcpResult::TcpErrorInvalidParam)?;
+        self.setopt_int(libc::SOL_SOCKET, libc::SO_SNDBUF, size)
+    }
+    
+    /// Get the socket send buffer size (SO_SNDBUF) in bytes.
+    ///
+    /// Linux reports twice the requested size to account for bookkeeping overhead.
+    pub fn send_buffer_size(&mut self) -> Result<usize, TcpResult> {
+        self.getopt_int(libc::SOL_SOCKET, libc::SO_SNDBUF).map(|size| size as usize)
+    }
+    
+    /// Set the type-of-service byte of outgoing packets (IP_TOS).
+    ///
+    /// The DSCP code point goes in the upper 6 bits (`dscp << 2`), ECN in the lower 2.
+    pub fn set_tos(&mut self, tos: u8) -> Result<(), TcpResult> {
+        let result = unsafe { tcp_socket_set_tos(self.as_ptr(), tos as c_int) };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the type-of-service byte of outgoing packets.
+    pub fn tos(&mut self) -> Result<u8, TcpResult> {
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_TOS).map(|tos| tos as u8)
+    }
+    
+    /// Configure SO_LINGER for when the socket is closed.
+    ///
+    /// `None` (the default) returns from close immediately and lets the kernel drain
+    /// unsent data in the background. `Some(Duration::ZERO)` resets the connection
+    /// (RST) and discards unsent data. `Some(timeout)` blocks close until the data is
+    /// sent or the timeout (whole seconds) expires.
+    pub fn set_linger(&mut self, timeout: Option<Duration>) -> Result<(), TcpResult> {
+        self.setopt(libc::SOL_SOCKET, libc::SO_LINGER, &linger_from(timeout))
+    }
+    
+    /// Get the SO_LINGER setting.
+    pub fn linger(&mut self) -> Result<Option<Duration>, TcpResult> {
+        self.getopt::<libc::linger>(libc::SOL_SOCKET, libc::SO_LINGER).map(linger_to)
+    }
+    
+    /// Set an integer socket option.
+    pub(crate) fn setopt_int(&mut self, level: c_int, optname: c_int, value: c_int) -> Result<(), TcpResult> {
+        self.setopt(level, optname, &value)
+    }
+    
+    /// Get an integer socket option.
+    pub(crate) fn getopt_int(&mut self, level: c_int, optname: c_int) -> Result<c_int, TcpResult> {
+        self.getopt::<c_int>(level, optname)
+    }
+    
+    /// Set a socket option from a plain C value (`c_int`, `libc::linger`, ...).
+    pub(crate) fn setopt<T: Copy>(&mut self, level: c_int, optname: c_int, value: &T) -> Result<(), TcpResult> {
+        let result = unsafe {
+            tcp_socket_setopt(
+                self.as_ptr(),
+                level,
+                optname,
+                value as *const T as *const c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(())
+    }
+    
+    /// Get a socket option as a plain C value.
+    pub(crate) fn getopt<T: Copy>(&mut self, level: c_int, optname: c_int) -> Result<T, TcpResult> {
+        let mut value = unsafe { mem::zeroed::<T>() };
+        let mut len = mem::size_of::<T>() as libc::socklen_t;
+        let result = unsafe {
+            tcp_socket_getopt(
+                self.as_ptr(),
+                level,
+                optname,
+                &mut value as *mut T as *mut c_void,
+                &mut len,
+            )
+        };
+        
+        if result != TcpResult::TcpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, TcpResult>(result) });
+        }
+        
+        Ok(value)
+    }
+}
+
+impl Drop for TcpSocketWrapper {
+    /// Automatically close the socket when it goes out of scope, following its close mode.
+    fn drop(&mut self) {
+        let fd = self.fd();
+        if fd >= 0 {
+            if fdtrack::ENABLED && self.close_mode == CloseMode::Immediate && self.state() == TcpConnectionState::Connected {
+                warn_unsent(fd);
+            }
+            let _ = self.close_with_mode();
+        }
+        let socket = self.as_ptr();
+        self.tracker.release();
+        unsafe {
+            tcp_socket_free(socket);
+        }
+    }
+}
+
+/// Warn about a connected socket dropped with data still in its send queue.
+fn warn_unsent(fd: c_int) {
+    if let Ok(pending @ 1..) = common::pending_tx_bytes(fd) {
+        fdtrack::warn_unsent(fd, pending);
+    }
+}
+
+/// Connection lifecycle event delivered through [`VmaTcpSocket::state_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateEvent {
+    /// The connection was established (initially or after a reconnect)
+    Connected,
+    /// The peer closed the connection (FIN received)
+    ClosedByPeer,
+    /// The connection was lost for another reason (error, failed connect)
+    Disconnected,
+    /// A reconnect attempt was started
+    ReconnectAttempt,
+}
+
+/// Predicate deciding whether a connection from the given peer is accepted.
+pub type AcceptFilter = fn(SocketAddr) -> bool;
+
+/// Share of a listener's connection count held by an accepted client, released on drop.
+#[derive(Debug)]
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::AcqRel);
+        ConnectionSlot(Arc::clone(count))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Callback invoked on connection state transitions.
+#[derive(Clone)]
+struct StateHook(Arc<dyn Fn(TcpConnectionState) + Send + Sync>);
+
+impl fmt::Debug for StateHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StateHook")
+    }
+}
+
+/// High-level Rust-friendly TCP socket implementation.
+#[derive(Debug)]
+pub struct VmaTcpSocket {
+    inner: TcpSocketWrapper,
+    reconnect_policy: Option<ReconnectPolicy>,
+    state_hook: Option<StateHook>,
+    reported_state: TcpConnectionState,
+    event_tx: Option<SyncSender<StateEvent>>,
+    accept_filter: Option<AcceptFilter>,
+    max_connections: Option<usize>,
+    connections: Arc<AtomicUsize>,
+    rejected_connections: u64,
+    read_timeout: Option<u64>,
+    write_timeout: Option<u64>,
+    nonblocking: bool,
+    stats_baseline: SocketStats,
+    shutdown: Option<ShutdownToken>,
+}
+
+impl VmaTcpSocket {
+    /// Create a new TCP socket with default VMA options.
+    pub fn new() -> Result<Self, std::io::Error> {
+        TcpSocketWrapper::new(None)
+            .map(VmaTcpSocket::from_wrapper)
+            .map_err(|e| e.into())
+    }
+    
+    /// Create a new TCP socket with custom VMA options.
+    ///
+    /// The options are checked with [`VmaOptions::validate`] first; a failure lists every
+    /// problem in a [`VmaError::Config`] of kind `ErrorKind::InvalidInput`.
+    pub fn with_options(options: VmaOptions) -> Result<Self, std::io::Error> {
+        options.validate()?;
+        TcpSocketWrapper::new(Some(options))
+            .map(VmaTcpSocket::from_wrapper)
+            .map_err(|e| e.into())
+    }
+    
+    /// Take over an IPv4 TCP socket created elsewhere, e.g. configured through `socket2`.
+    ///
+    /// Options already set on the descriptor are kept, and the state (listening,
+    /// connected or not) and addresses are read back from it; `options` picks the wait
+    /// strategy and reconnect settings. A descriptor of another kind fails with
+    /// `ErrorKind::InvalidInput`, and the error hands it back open.
+    ///
+    /// Whether VMA offloads the socket is decided when it is created, so create it with
+    /// libvma preloaded to keep the acceleration.
+    pub fn from_fd(fd: OwnedFd, options: Option<VmaOptions>) -> Result<Self, FromFdError> {
+        let raw = fd.into_raw_fd();
+        match TcpSocketWrapper::from_raw_fd(raw, options) {
+            Ok(inner) => Ok(VmaTcpSocket::from_wrapper(inner)),
+            Err(e) => Err(FromFdError::new(unsafe { OwnedFd::from_raw_fd(raw) }, e.into())),
+        }
+    }
+    
+    /// Borrow the descriptor, e.g. for `socket2::SockRef::from`.
+    ///
+    /// Returns `None` after a failed reconnect left the socket without a descriptor.
+    pub fn borrowed_fd(&self) -> Option<BorrowedFd<'_>> {
+        let fd = self.inner.fd();
+        (fd >= 0).then(|| unsafe { BorrowedFd::borrow_raw(fd) })
+    }
+    
+    fn from_wrapper(inner: TcpSocketWrapper) -> Self {
+        let reported_state = inner.state();
+        VmaTcpSocket {
+            inner,
+            reconnect_policy: None,
+            state_hook: None,
+            reported_state,
+            event_tx: None,
+            accept_filter: None,
+            max_connections: None,
+            connections: Arc::new(AtomicUsize::new(0)),
+            rejected_connections: 0,
+            read_timeout: None,
+            write_timeout: None,
+            nonblocking: false,
+            stats_baseline: SocketStats::default(),
+            shutdown: None,
+        }
+    }
+    
+    /// Set the timeout used by receive calls that pass `None` (`None` waits indefinitely).
+    ///
+    /// A timeout passed to a call still takes precedence, so `set_read_timeout(Some(100_000_000))`
+    /// spares passing `Some(100_000_000)` to every `recv()`. Accept and connect are not affected.
+    pub fn set_read_timeout(&mut self, timeout: Option<u64>) {
+        self.read_timeout = timeout;
+    }
+    
+    /// Get the default receive timeout in nanoseconds.
+    pub fn read_timeout(&self) -> Option<u64> {
+        self.read_timeout
+    }
+    
+    /// Switch the socket between blocking and non-blocking mode.
+    ///
+    /// In non-blocking mode receives and `accept()` ignore their timeout and the read
+    /// timeout and return at once, failing with `ErrorKind::WouldBlock` when there is no
+    /// data or connection as the std sockets do (`recv_at_least()` returns what it got),
+    /// and sends add `MsgFlags::DONTWAIT` (`send_timeout()` makes a single attempt). `connect()` still
+    /// waits. The mode belongs to this object rather than the descriptor's O_NONBLOCK
+    /// flag, so it behaves the same with and without `use_polling` and survives reconnects.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+    
+    /// Check whether the socket is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+    
+    /// Get the timeout of a receive call: none at all in non-blocking mode.
+    fn recv_timeout(&self, timeout: Option<u64>) -> Option<u64> {
+        if self.nonblocking {
+            Some(0)
+        } else {
+            timeout.or(self.read_timeout)
+        }
+    }
+    
+    /// Report a receive or accept that found nothing: `value` after a timeout, but
+    /// `ErrorKind::WouldBlock` in non-blocking mode.
+    fn nothing_ready<T>(&self, value: T) -> Result<T, std::io::Error> {
+        if self.nonblocking {
+            Err(Error::new(ErrorKind::WouldBlock, "Operation would block"))
+        } else {
+            Ok(value)
+        }
+    }
+    
+    /// Observe `token` in receives and accepts (`None` stops observing one).
+    ///
+    /// They then wait in slices of at most `SHUTDOWN_POLL_NS` and fail with
+    /// `ErrorKind::Interrupted` once the token is triggered, even without a timeout.
+    pub fn set_shutdown_token(&mut self, token: Option<ShutdownToken>) {
+        self.shutdown = token;
+    }
+    
+    /// Get the shutdown token observed by receives and accepts, if any.
+    pub fn shutdown_token(&self) -> Option<&ShutdownToken> {
+        self.shutdown.as_ref()
+    }
+    
+    /// Receive with the timeout of the call, observing the shutdown token.
+    fn wait_recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Result<usize, TcpResult>, std::io::Error> {
+        let timeout = self.recv_timeout(timeout);
+        let inner = &mut self.inner;
+        shutdown::sliced(self.shutdown.as_ref(), timeout, |e| *e == TcpResult::TcpErrorTimeout, |slice| inner.recv(buffer, slice))
+    }
+    
+    /// Set how long a send may block (SO_SNDTIMEO, `None` blocks indefinitely).
+    ///
+    /// A send that times out is reported like one that would block (`Ok(0)`, or the bytes
+    /// written so far). The setting is kept across reconnects. A zero timeout is rejected
+    /// with `ErrorKind::InvalidInput`; use `MsgFlags::DONTWAIT` instead.
+    ///
+    /// Sockets created with `use_polling` are non-blocking and never wait in a send, so the
+    /// timeout only matters without it.
+    pub fn set_write_timeout(&mut self, timeout: Option<u64>) -> Result<(), std::io::Error> {
+        if timeout == Some(0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "Write timeout must be non-zero"));
+        }
+        self.inner.setopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO, &common::unixnano_to_timeval(timeout))?;
+        self.write_timeout = timeout;
+        Ok(())
+    }
+    
+    /// Get the send timeout in nanoseconds.
+    pub fn write_timeout(&self) -> Option<u64> {
+        self.write_timeout
+    }
+    
+    /// Bind the socket to a local address and port.
+    pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
+        self.inner
+            .bind(addr, port)
+            .map_err(|e| e.into())
+    }
+    
+    /// Put the socket in listening mode (server).
+    pub fn listen(&mut self, backlog: i32) -> Result<(), std::io::Error> {
+        self.inner
+            .listen(backlog)
+            .map_err(|e| e.into())
+    }
+    
+    /// Accept a client connection (server).
+    ///
+    /// Connections refused by the accept filter or the connection limit are closed right
+    /// away and accepting continues until the timeout expires.
+    pub fn accept(&mut self, timeout_nano: Option<u64>) -> Result<Option<Client>, std::io::Error> {
+        let timeout_nano = if self.nonblocking { Some(0) } else { timeout_nano };
+        let token = self.shutdown.clone();
+        match shutdown::sliced(token.as_ref(), timeout_nano, |e| *e == TcpResult::TcpErrorTimeout, |slice| self.accept_admitted(slice))? {
+            Ok(client) => Ok(Some(client)),
+            Err(TcpResult::TcpErrorTimeout) => self.nothing_ready(None), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Accept until a connection passes the filter and the connection limit.
+    fn accept_admitted(&mut self, timeout_nano: Option<u64>) -> Result<Client, TcpResult> {
+        let deadline = timeout_nano.map(|timeout| Instant::now() + Duration::from_nanos(timeout));
+        let mut timeout = timeout_nano;
+        loop {
+            let mut client = self.inner.accept(timeout)?;
+            if self.admits(client.address) {
+                client.slot = Some(ConnectionSlot::acquire(&self.connections));
+                return Ok(client);
+            }
+            // Dropping the client closes the connection
+            self.rejected_connections += 1;
+            drop(client);
+            
+            timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).as_nanos() as u64);
+        }
+    }
+    
+    fn admits(&self, peer: SocketAddr) -> bool {
+        let below_limit = self
+            .max_connections
+            .is_none_or(|max| self.connections.load(Ordering::Acquire) < max);
+        below_limit && self.accept_filter.is_none_or(|filter| filter(peer))
+    }
+    
+    /// Only accept connections from peers for which `filter` returns `true` (server).
+    ///
+    /// Refused connections are closed as soon as they are accepted, before any client
+    /// state is handed out. `None` accepts every peer.
+    pub fn set_accept_filter(&mut self, filter: Option<AcceptFilter>) {
+        self.accept_filter = filter;
+    }
+    
+    /// Limit the number of accepted clients alive at once (server).
+    ///
+    /// A client counts until it (and every clone of it) is dropped; connections beyond
+    /// the limit are closed as soon as they are accepted. `None` removes the limit.
+    pub fn set_max_connections(&mut self, max: Option<usize>) {
+        self.max_connections = max;
+    }
+    
+    /// Get the connection limit.
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+    
+    /// Get the number of accepted clients still alive (server).
+    pub fn connection_count(&self) -> usize {
+        self.connections.load(Ordering::Acquire)
+    }
+    
+    /// Get the number of connections closed by the accept filter or the connection limit.
+    pub fn rejected_connections(&self) -> u64 {
+        self.rejected_connections
+    }
+    
+    /// Accept a pending client connection without waiting (server).
+    ///
+    /// Unlike [`accept`](Self::accept), an empty accept queue is reported as
+    /// `ErrorKind::WouldBlock`, and a failed accept carries the OS error (e.g.
+    /// `ECONNABORTED` for a handshake reset by the peer, `EMFILE` when out of descriptors),
+    /// so transient failures can be told apart from fatal ones.
+    pub fn try_accept(&mut self) -> Result<Client, std::io::Error> {
+        match self.accept_admitted(Some(0)) {
+            Ok(client) => Ok(client),
+            Err(TcpResult::TcpErrorTimeout) => Err(Error::new(ErrorKind::WouldBlock, "No pending connection")),
+            Err(TcpResult::TcpErrorAccept) => Err(Error::last_os_error()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Get the number of connections waiting in the accept queue, for overload monitoring (server).
+    ///
+    /// Compare against the backlog given to [`listen`](Self::listen): a queue that stays
+    /// near it means connections are accepted too slowly and new ones will be dropped.
+    pub fn pending_connections(&mut self) -> Result<u32, std::io::Error> {
+        self.inner
+            .pending_connections()
+            .map_err(|e| e.into())
+    }
+    
+    /// Iterate over incoming connections, blocking until each one arrives (server).
+    ///
+    /// Like `std::net::TcpListener::incoming`, the iterator never ends; failed accepts are
+    /// yielded as errors and iteration can continue.
+    pub fn incoming(&mut self) -> Incoming<'_> {
+        Incoming {
+            socket: self,
+            nonblocking: false,
+        }
+    }
+    
+    /// Iterate over the connections already waiting in the backlog, without blocking (server).
+    ///
+    /// The iterator ends as soon as no connection is pending, so it can drain the backlog
+    /// from a polling loop.
+    pub fn incoming_nonblocking(&mut self) -> Incoming<'_> {
+        Incoming {
+            socket: self,
+            nonblocking: true,
+        }
+    }
+    
+    /// Connect to a server (client).
+    pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        self.report_state(TcpConnectionState::Connecting);
+        let result = self.inner.connect(addr, port, timeout);
+        self.sync_state();
+        match result {
+            Ok(_) => Ok(true),
+            Err(TcpResult::TcpErrorTimeout) => Ok(false), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Start connecting to a server without blocking (client).
+    ///
+    /// Returns `true` if the connection completed immediately and `false` if it is in
+    /// progress. Several sockets can be started from one thread and completed with
+    /// [`poll_connect`](Self::poll_connect) as they become ready.
+    pub fn connect_nonblocking<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<bool, std::io::Error> {
+        self.report_state(TcpConnectionState::Connecting);
+        let result = self.inner.connect_start(addr, port);
+        self.sync_state();
+        match result {
+            Ok(_) => Ok(true),
+            Err(TcpResult::TcpErrorWouldBlock) => Ok(false), // in progress is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Wait up to `timeout` for a non-blocking connect to complete.
+    ///
+    /// Returns `true` once connected and `false` while still in progress (`Some(0)` only
+    /// checks). A refused or failed connection is returned as an error.
+    pub fn poll_connect(&mut self, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        let result = self.inner.poll_connect(timeout);
+        self.sync_state();
+        match result {
+            Ok(_) => Ok(true),
+            Err(TcpResult::TcpErrorTimeout) => Ok(false), // still in progress is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Connect to the first reachable server of a prioritized list (client).
+    ///
+    /// Addresses are tried in order, each for up to `timeout`, moving on as soon as one
+    /// is refused. Returns the address that connected, or `None` when none did and at
+    /// least one attempt timed out. Useful for primary/backup gateways:
+    ///
+    /// ```rust,no_run
+    /// # use vma_socket::tcp::VmaTcpSocket;
+    /// # fn main() -> std::io::Result<()> {
+    /// let gateways = ["10.0.0.5:9000".parse().unwrap(), "10.0.1.5:9000".parse().unwrap()];
+    /// let mut socket = VmaTcpSocket::new()?;
+    /// if let Some(gateway) = socket.connect_any(&gateways, Some(500_000_000))? {
+    ///     println!("connected to {}", gateway);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Each attempt runs on a fresh descriptor created with the socket's `VmaOptions`, so
+    /// options set after creation (other than the write timeout) and a previous `bind()`
+    /// do not apply to the connection. At most [`CONNECT_ANY_MAX`] IPv4 addresses.
+    pub fn connect_any(&mut self, addrs: &[SocketAddr], timeout: Option<u64>) -> Result<Option<SocketAddr>, std::io::Error> {
+        self.connect_any_inner(addrs, timeout, None)
+    }
+    
+    /// Connect to the first server of a prioritized list to answer, racing the attempts (client).
+    ///
+    /// Like [`connect_any`](Self::connect_any), but an attempt still in progress after
+    /// `stagger` no longer holds back the next address (happy eyeballs): a slow primary
+    /// costs at most `stagger`, and whichever connection completes first is kept.
+    pub fn connect_any_racing(&mut self, addrs: &[SocketAddr], stagger: Duration, timeout: Option<u64>) -> Result<Option<SocketAddr>, std::io::Error> {
+        self.connect_any_inner(addrs, timeout, Some(stagger.as_nanos() as u64))
+    }
+    
+    fn connect_any_inner(&mut self, addrs: &[SocketAddr], timeout: Option<u64>, stagger: Option<u64>) -> Result<Option<SocketAddr>, std::io::Error> {
+        if addrs.is_empty() || addrs.len() > CONNECT_ANY_MAX {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("expected 1 to {} addresses", CONNECT_ANY_MAX)));
+        }
+        let c_addrs = addrs
+            .iter()
+            .map(|addr| match addr {
+                SocketAddr::V4(addr) => Ok(common::sockaddr_from_rust(addr)),
+                SocketAddr::V6(_) => Err(Error::new(ErrorKind::InvalidInput, "IPv6 addresses are not supported")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        self.report_state(TcpConnectionState::Connecting);
+        let result = self.inner.connect_any(&c_addrs, timeout, stagger);
+        self.sync_state();
+        if result.is_ok() && self.write_timeout.is_some() {
+            // The winning attempt replaced the file descriptor
+            self.inner.setopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO, &common::unixnano_to_timeval(self.write_timeout))?;
+        }
+        match result {
+            Ok(winner) => Ok(Some(addrs[winner])),
+            Err(TcpResult::TcpErrorTimeout) => Ok(None), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Attempt to reconnect after a disconnection.
+    pub fn try_reconnect(&mut self, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        if self.inner.state() != TcpConnectionState::Connected {
+            self.emit(StateEvent::ReconnectAttempt);
+            self.report_state(TcpConnectionState::Connecting);
+        }
+        let result = self.inner.reconnect(timeout);
+        self.sync_state();
+        if result.is_ok() && self.write_timeout.is_some() {
+            // The reconnect replaced the file descriptor
+            self.inner.setopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO, &common::unixnano_to_timeval(self.write_timeout))?;
+        }
+        match result {
+            Ok(_) => Ok(true),
+            Err(TcpResult::TcpErrorTimeout) => Ok(false), // timeout is not an error
+            Err(TcpResult::TcpErrorReconnect) => Ok(false), // reconnect failure is treated as a false result
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Reconnect according to the configured [`ReconnectPolicy`] (or the default one).
+    ///
+    /// Blocks while backing off between attempts. Returns `true` once connected and
+    /// `false` when the policy gave up.
+    pub fn reconnect_with_policy(&mut self) -> Result<bool, std::io::Error> {
+        if self.is_connected() {
+            return Ok(true);
+        }
+        
+        let policy = self.reconnect_policy.unwrap_or_default();
+        let mut failed_attempts = 0u32;
+        while policy.allows(failed_attempts) {
+            if self.try_reconnect(policy.attempt_timeout)? {
+                return Ok(true);
+            }
+            std::thread::sleep(policy.delay(failed_attempts));
+            failed_attempts += 1;
+        }
+        
+        Ok(false)
+    }
+    
+    /// Set the policy used by `reconnect_with_policy()` and [`crate::reconnect::Reconnector`].
+    pub fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.reconnect_policy = policy;
+    }
+    
+    /// Get the configured reconnect policy.
+    pub fn reconnect_policy(&self) -> Option<&ReconnectPolicy> {
+        self.reconnect_policy.as_ref()
+    }
+    
+    /// Register a callback invoked on every connection state transition.
+    ///
+    /// The callback runs on the thread performing the operation that changed the state
+    /// (connect, reconnect, send, recv, ...), so it should return quickly.
+    pub fn on_state_change<F>(&mut self, callback: F)
+    where
+        F: Fn(TcpConnectionState) + Send + Sync + 'static,
+    {
+        self.state_hook = Some(StateHook(Arc::new(callback)));
+    }
+    
+    /// Get the current connection state.
+    pub fn state(&self) -> TcpConnectionState {
+        self.inner.state()
+    }
+    
+    /// Subscribe to connection lifecycle events through a bounded channel.
+    ///
+    /// Replaces any previous subscription. Events are dropped rather than blocking the
+    /// socket when the channel is full, so size `capacity` for the consumer's lag.
+    pub fn state_events(&mut self, capacity: usize) -> Receiver<StateEvent> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        self.event_tx = Some(tx);
+        rx
+    }
+    
+    /// Check whether the socket has a remote address to reconnect to.
+    pub(crate) fn has_remote(&self) -> bool {
+        self.inner.peer().is_some()
+    }
+    
+    /// Report a state transition to the callback and event channel if the state changed.
+    fn report_state(&mut self, state: TcpConnectionState) {
+        self.report_state_closed(state, false);
+    }
+    
+    fn report_state_closed(&mut self, state: TcpConnectionState, by_peer: bool) {
+        if state == self.reported_state {
+            return;
+        }
+        self.reported_state = state;
+        if let Some(hook) = &self.state_hook {
+            (hook.0)(state);
+        }
+        match state {
+            TcpConnectionState::Connected => self.emit(StateEvent::Connected),
+            TcpConnectionState::Disconnected if by_peer => self.emit(StateEvent::ClosedByPeer),
+            TcpConnectionState::Disconnected => self.emit(StateEvent::Disconnected),
+            _ => {}
+        }
+    }
+    
+    /// Report the state of the C socket after an operation.
+    fn sync_state(&mut self) {
+        self.report_state(self.inner.state());
+    }
+    
+    /// Report the state of the C socket after a receive, telling a peer close apart.
+    fn sync_state_after_recv(&mut self, result: &Result<usize, TcpResult>) {
+        let by_peer = matches!(result, Err(TcpResult::TcpErrorClosed));
+        self.report_state_closed(self.inner.state(), by_peer);
+    }
+    
+    /// Deliver an event to the subscriber, if any, without blocking.
+    fn emit(&mut self, event: StateEvent) {
+        if let Some(tx) = &self.event_tx {
+            if let Err(TrySendError::Disconnected(_)) = tx.try_send(event) {
+                self.event_tx = None;
+            }
+        }
+    }
+    
+    /// Check if the socket is currently connected.
+    pub fn is_connected(&mut self) -> bool {
+        let connected = self.inner.is_connected();
+        self.sync_state();
+        connected
+    }
+    
+    /// Send data over the connected socket.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        if self.nonblocking {
+            return self.send_with_flags(data, MsgFlags::DONTWAIT);
+        }
+        let result = self.inner.send(data);
+        self.sync_state();
+        match result {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorWouldBlock) => Ok(0), // would block is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Receive data from the connected socket.
+    pub fn recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let result = self.wait_recv(buffer, timeout)?;
+        self.sync_state_after_recv(&result);
+        match result {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorTimeout) => self.nothing_ready(0), // timeout is not an error
+            Err(TcpResult::TcpErrorClosed) => Ok(0), // treat closed as EOF (0 bytes received)
             Err(e) => Err(e.into()),
         }
     }
     
-    /// Check if the socket is currently connected.
-    pub fn is_connected(&mut self) -> bool {
-        self.inner.is_connected()
+    /// Receive data, telling a timeout apart from end-of-stream.
+    ///
+    /// Returns `Ok(None)` on timeout, `Ok(Some(0))` once the peer has sent its FIN and
+    /// `Ok(Some(n))` when `n` bytes were received.
+    pub fn recv_checked(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<Option<usize>, std::io::Error> {
+        let result = self.wait_recv(buffer, timeout)?;
+        self.sync_state_after_recv(&result);
+        match result {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(TcpResult::TcpErrorTimeout) => self.nothing_ready(None), // timeout is not an error
+            Err(TcpResult::TcpErrorClosed) => Ok(Some(0)), // peer sent FIN
+            Err(e) => Err(e.into()),
+        }
     }
     
-    /// Send data over the connected socket.
-    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
-        match self.inner.send(data) {
+    /// Receive at least `n` bytes, e.g. a fixed-size header, without a retry loop around short reads.
+    ///
+    /// Reads up to `buffer.len()` bytes, so more than `n` may be returned. `timeout` bounds
+    /// the whole call; if it expires or the peer closes the connection first, the bytes
+    /// received so far are returned (fewer than `n`, `0` when none arrived) and
+    /// [`is_connected`](Self::is_connected) tells the two apart.
+    pub fn recv_at_least(&mut self, buffer: &mut [u8], n: usize, timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let result = self.inner.recv_at_least(buffer, n, self.recv_timeout(timeout));
+        let state = self.inner.state();
+        let by_peer = match result {
+            Ok(bytes) => bytes < n && state == TcpConnectionState::Disconnected,
+            Err(e) => e == TcpResult::TcpErrorClosed,
+        };
+        self.report_state_closed(state, by_peer);
+        match result {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorClosed) => Ok(0), // treat closed as EOF (0 bytes received)
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Send data with `send(2)` flags, e.g. `MsgFlags::MORE` to cork a partial message.
+    ///
+    /// Like `send()`, returns `Ok(0)` when the send would block (e.g. with `MsgFlags::DONTWAIT`).
+    pub fn send_with_flags(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, std::io::Error> {
+        let flags = if self.nonblocking { flags | MsgFlags::DONTWAIT } else { flags };
+        let result = self.inner.send_with_flags(data, flags);
+        self.sync_state();
+        match result {
             Ok(bytes) => Ok(bytes),
             Err(TcpResult::TcpErrorWouldBlock) => Ok(0), // would block is not an error
             Err(e) => Err(e.into()),
         }
     }
     
-    /// Receive data from the connected socket.
-    pub fn recv(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
-        match self.inner.recv(buffer, timeout) {
+    /// Send all of `data`, waiting up to `timeout` nanoseconds while the peer is not reading.
+    ///
+    /// Returns the number of bytes written, which is less than `data.len()` if the timeout
+    /// expired first; the rest can be retried or the connection given up. `None` waits
+    /// until everything is sent.
+    pub fn send_timeout(&mut self, data: &[u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let timeout = if self.nonblocking { Some(0) } else { timeout };
+        let result = self.inner.send_timeout(data, timeout);
+        self.sync_state();
+        result.map_err(|e| e.into())
+    }
+    
+    /// Send all of `data` unless `deadline` passes first, returning the bytes written.
+    ///
+    /// A deadline already in the past makes a single non-blocking attempt.
+    pub fn send_deadline(&mut self, data: &[u8], deadline: Instant) -> Result<usize, std::io::Error> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        self.send_timeout(data, Some(remaining.as_nanos() as u64))
+    }
+    
+    /// Signal an eventfd whenever the socket becomes readable, for host event loops.
+    ///
+    /// See [`ReadyNotifier`](crate::notify::ReadyNotifier); the socket is used through the
+    /// returned guard, which stops the notifier when dropped. A reconnect replaces the
+    /// descriptor it watches, so get a new guard after one. Fails with `EBADF` on a socket
+    /// without a descriptor.
+    pub fn ready_notifier(&mut self) -> Result<Notified<'_, Self>, std::io::Error> {
+        if self.inner.fd() < 0 {
+            return Err(Error::from_raw_os_error(libc::EBADF));
+        }
+        Notified::new(self)
+    }
+    
+    /// Wait up to `timeout` nanoseconds for data to receive (`Some(0)` only checks).
+    ///
+    /// Also `true` once the peer closed the connection, the next `recv_checked()` then
+    /// reporting end-of-stream. Follows the socket's wait strategy but ignores the read
+    /// timeout and non-blocking mode, and consumes nothing.
+    pub fn poll_readable(&self, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        self.inner.poll_readable(timeout)
+    }
+    
+    /// Wait up to `timeout` nanoseconds for room in the send buffer (`Some(0)` only checks).
+    ///
+    /// Returns `false` on timeout. Unlike a blocked `send`, this lets the caller apply its
+    /// own flow control (conflate, drop, disconnect) while the peer is congested.
+    pub fn poll_writable(&self, timeout: Option<u64>) -> Result<bool, std::io::Error> {
+        common::wait_writable(self.inner.fd(), timeout)
+    }
+    
+    /// Get the number of bytes sent but not yet acknowledged by the peer.
+    ///
+    /// A value that keeps growing means the peer (or the path to it) cannot keep up.
+    pub fn pending_tx_bytes(&self) -> Result<usize, std::io::Error> {
+        common::pending_tx_bytes(self.inner.fd())
+    }
+    
+    /// Send `range` of a registered buffer without copying it into socket buffers.
+    ///
+    /// Uses MSG_ZEROCOPY (falling back to a regular copy when unsupported). The range must
+    /// not be modified until [`zerocopy_pending`](Self::zerocopy_pending) drops to zero.
+    /// Like `send()`, returns `Ok(0)` when the send would block.
+    pub fn send_registered(&mut self, buffer: &RegisteredBuffer, range: Range<usize>) -> Result<usize, std::io::Error> {
+        let data = buffer.get(range)?;
+        let result = self.inner.send_zerocopy(data);
+        self.sync_state();
+        match result {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorWouldBlock) => Ok(0), // would block is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Get the number of `send_registered()` calls whose data is still in use by the stack.
+    pub fn zerocopy_pending(&mut self) -> u32 {
+        self.inner.zerocopy_pending()
+    }
+    
+    /// Keep the VMA TX path and caches hot by running `data` through it without sending it.
+    ///
+    /// Issues a VMA dummy send (`VMA_SND_FLAGS_DUMMY`); call it between real sends, or use
+    /// [`crate::warm::Warmer`] to do so periodically. Fails with `ErrorKind::Unsupported`
+    /// when the socket is not offloaded by VMA, since the kernel would transmit the data.
+    pub fn warm(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        match self.inner.send_dummy(data) {
+            Ok(()) => Ok(()),
+            Err(TcpResult::TcpErrorWouldBlock) => Ok(()), // send buffer full; the path is busy anyway
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Receive data with `recv(2)` flags, e.g. `MsgFlags::WAITALL` to fill the whole buffer.
+    ///
+    /// `MsgFlags::DONTWAIT` ignores the timeout and returns immediately.
+    pub fn recv_with_flags(&mut self, buffer: &mut [u8], timeout: Option<u64>, flags: MsgFlags) -> Result<usize, std::io::Error> {
+        let result = self.inner.recv_with_flags(buffer, self.recv_timeout(timeout), flags);
+        self.sync_state_after_recv(&result);
+        match result {
+            Ok(bytes) => Ok(bytes),
+            Err(TcpResult::TcpErrorTimeout) => self.nothing_ready(0), // timeout is not an error
+            Err(TcpResult::TcpErrorClosed) => Ok(0), // treat closed as EOF (0 bytes received)
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Copy received data into `buffer` without consuming it.
+    ///
+    /// A following `recv()` returns the same bytes. Peeked bytes are not counted in `stats()`.
+    pub fn peek(&mut self, buffer: &mut [u8], timeout: Option<u64>) -> Result<usize, std::io::Error> {
+        let result = self.inner.peek(buffer, self.recv_timeout(timeout));
+        self.sync_state_after_recv(&result);
+        match result {
             Ok(bytes) => Ok(bytes),
-            Err(TcpResult::TcpErrorTimeout) => Ok(0), // timeout is not an error
+            Err(TcpResult::TcpErrorTimeout) => self.nothing_ready(0), // timeout is not an error
             Err(TcpResult::TcpErrorClosed) => Ok(0), // treat closed as EOF (0 bytes received)
             Err(e) => Err(e.into()),
         }
     }
     
+    /// Shut down the read, write or both halves of the connection.
+    ///
+    /// `Shutdown::Write` signals end-of-stream to the peer while this socket keeps
+    /// receiving; use [`recv_checked`](Self::recv_checked) to detect the peer's FIN.
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), std::io::Error> {
+        self.inner
+            .shutdown(how)
+            .map_err(|e| e.into())
+    }
+    
     /// Get socket statistics.
     pub fn get_stats(&mut self) -> Result<(u64, u64, u64, u64), std::io::Error> {
         self.inner.get_stats()
             .map_err(|e| e.into())
     }
-}
\ No newline at end of file
+    
+    /// Get the full set of socket counters (errors, EAGAIN counts, reconnects, last activity).
+    pub fn stats(&mut self) -> Result<SocketStats, std::io::Error> {
+        self.inner.stats()
+            .map_err(|e| e.into())
+    }
+    
+    /// Zero the socket counters; the last receive and send times are kept.
+    pub fn reset_stats(&mut self) -> Result<(), std::io::Error> {
+        self.inner.reset_stats()?;
+        self.stats_baseline = SocketStats::default();
+        Ok(())
+    }
+    
+    /// Get the counters accumulated since the previous call (or creation, or `reset_stats()`).
+    ///
+    /// Suited to periodic reporters, which need not keep the previous values themselves.
+    pub fn stats_delta(&mut self) -> Result<SocketStats, std::io::Error> {
+        let stats = self.stats()?;
+        let delta = stats.delta_since(&self.stats_baseline);
+        self.stats_baseline = stats;
+        Ok(delta)
+    }
+    
+    /// Get the file descriptor of the socket, e.g. to register it with epoll.
+    pub fn fd(&self) -> c_int {
+        self.inner.fd()
+    }
+    
+    /// Get the local address the socket is bound to (resolves port 0 to the assigned port).
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        common::local_addr(self.inner.fd())
+    }
+    
+    /// Get the address of the connected server, if connected.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        if self.inner.state() == TcpConnectionState::Connected {
+            self.inner.peer().map(|addr| sockaddr_to_rust(&addr))
+        } else {
+            None
+        }
+    }
+    
+    /// Get the VMA offload status of this socket.
+    ///
+    /// `offloaded == false` while `vma_loaded == true` means VMA is running but this
+    /// socket's traffic is falling back to the OS path. Ring and CQ counters are not
+    /// available this way, see [`VmaFdStats`].
+    pub fn vma_fd_stats(&self) -> Result<VmaFdStats, std::io::Error> {
+        common::fd_stats(self.inner.fd())
+    }
+    
+    /// Get the descriptors of the VMA rings backing this socket, to wait on in an external event loop.
+    ///
+    /// A ring descriptor becomes readable when its completion queue has work; add it to
+    /// your own epoll set and call `recv` with a zero timeout when it fires. The
+    /// descriptors belong to VMA and may be shared with other sockets on the same ring.
+    /// Empty when the socket is not offloaded; fails with `ErrorKind::Unsupported`
+    /// without libvma.
+    pub fn ring_fds(&self) -> Result<Vec<BorrowedFd<'_>>, std::io::Error> {
+        let fds = common::ring_fds(self.inner.fd())?;
+        Ok(fds.into_iter().map(|fd| unsafe { BorrowedFd::borrow_raw(fd) }).collect())
+    }
+    
+    /// Write VMA's internal statistics for this socket (as shown by `vma_stats`) to the VMA log.
+    pub fn dump_vma_stats(&self) -> Result<(), std::io::Error> {
+        common::dump_fd_stats(self.inner.fd())
+    }
+    
+    /// Collect the fd, addresses, state, options in effect, counters and VMA offload status.
+    ///
+    /// Print it with `{}` for a report to attach to a support ticket.
+    pub fn diagnostics(&mut self) -> SocketDiagnostics {
+        let mut options = vec![
+            ("nonblocking", self.nonblocking.to_string()),
+            ("read_timeout", common::timeout_option(self.read_timeout)),
+            ("write_timeout", common::timeout_option(self.write_timeout)),
+            ("reconnect_policy", self.reconnect_policy.is_some().to_string()),
+        ];
+        if let Some(max) = self.max_connections {
+            options.push(("max_connections", max.to_string()));
+        }
+        let readable: [(&'static str, Result<String, std::io::Error>); 6] = [
+            ("recv_buffer_size", self.recv_buffer_size().map(|v| v.to_string())),
+            ("send_buffer_size", self.send_buffer_size().map(|v| v.to_string())),
+            ("tos", self.tos().map(|v| v.to_string())),
+            ("nodelay", self.nodelay().map(|v| v.to_string())),
+            ("cork", self.is_corked().map(|v| v.to_string())),
+            ("linger", self.linger().map(|v| v.map_or_else(|| "off".to_string(), |d| format!("{:?}", d)))),
+        ];
+        options.extend(readable.into_iter().filter_map(|(name, value)| Some((name, value.ok()?))));
+
+        SocketDiagnostics {
+            protocol: Protocol::Tcp,
+            fd: self.fd(),
+            local_addr: self.local_addr().ok(),
+            peer_addr: self.peer_addr(),
+            state: format!("{:?}", self.state()).to_lowercase(),
+            options,
+            stats: self.stats().ok(),
+            vma: self.vma_fd_stats().ok(),
+        }
+    }
+    
+    /// Set the socket receive buffer size (SO_RCVBUF) in bytes.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), std::io::Error> {
+        self.inner
+            .set_recv_buffer_size(size)
+            .map_err(|e| e.into())
+    }
+    
+    /// Get the socket receive buffer size (SO_RCVBUF) in bytes, as reported by the kernel.
+    pub fn recv_buffer_size(&mut self) -> Result<usize, std::io::Error> {
+        self.inner
+            .recv_buffer_size()
+            .map_err(|e| e.into())
+    }
+    
+    /// Set the socket send buffer size (SO_SNDBUF) in bytes.
+    pub fn set_send_buffer_size(&mut self, size: usize) -> Result<(), std::io::Error> {
+        self.inner
+            .set_send_buffer_size(size)
+            .map_err(|e| e.into())
+    }
+    
+    /// Get the socket send buffer size (SO_SNDBUF) in bytes, as reported by the kernel.
+    pub fn send_buffer_size(&mut self) -> Result<usize, std::io::Error> {
+        self.inner
+            .send_buffer_size()
+            .map_err(|e| e.into())
+    }
+    
+    /// Set the type-of-service byte of outgoing packets (IP_TOS), e.g. `46 << 2` for DSCP EF.
+    pub fn set_tos(&mut self, tos: u8) -> Result<(), std::io::Error> {
+        self.inner
+            .set_tos(tos)
+            .map_err(|e| e.into())
+    }
+    
+    /// Get the type-of-service byte of outgoing packets.
+    pub fn tos(&mut self) -> Result<u8, std::io::Error> {
+        self.inner
+            .tos()
+            .map_err(|e| e.into())
+    }
+    
+    /// Configure SO_LINGER for when the socket is closed.
+    ///
+    /// `None` closes immediately and drains in the background, `Some(Duration::ZERO)`
+    /// aborts with a RST, `Some(timeout)` waits up to `timeout` for unsent data on close.
+    pub fn set_linger(&mut self, timeout: Option<Duration>) -> Result<(), std::io::Error> {
+        self.inner
+            .set_linger(timeout)
+            .map_err(|e| e.into())
+    }
+    
+    /// Get the SO_LINGER setting.
+    pub fn linger(&mut self) -> Result<Option<Duration>, std::io::Error> {
+        self.inner
+            .linger()
+            .map_err(|e| e.into())
+    }
+    
+    /// Set how the connection is closed when the socket is dropped (kept across reconnects).
+    ///
+    /// With `CloseMode::Graceful`, dropping a connected socket blocks until its unsent data
+    /// is acknowledged (and the peer closed, if asked) or the timeout expires, so a process
+    /// exiting in the middle of a send burst does not cut the stream short.
+    pub fn set_close_mode(&mut self, mode: CloseMode) {
+        self.inner.set_close_mode(mode);
+    }
+    
+    /// Get how the connection is closed when the socket is dropped.
+    pub fn close_mode(&self) -> CloseMode {
+        self.inner.close_mode()
+    }
+    
+    /// Close the connection once the data sent so far is delivered.
+    ///
+    /// Waits up to `timeout` for the peer to acknowledge all unsent data, then sends a FIN
+    /// and, with `wait_fin`, waits for the peer to close its side, discarding what it still
+    /// sends. Returns `Ok(true)` if all of it completed in time; otherwise the socket is
+    /// closed anyway and the kernel keeps sending in the background.
+    pub fn close_graceful(mut self, timeout: Duration, wait_fin: bool) -> Result<bool, std::io::Error> {
+        self.inner
+            .close_graceful(timeout, wait_fin)
+            .map_err(|e| e.into())
+    }
+    
+    /// Close the socket following its close mode, reporting a failed `close(2)` that
+    /// dropping it would ignore.
+    ///
+    /// The descriptor is released even when an error is returned. A socket that is not
+    /// connected (or was closed already) closes with `Ok(())`.
+    pub fn close(mut self) -> Result<(), VmaError> {
+        let fd = self.inner.fd();
+        if fd < 0 {
+            return Ok(());
+        }
+        match self.inner.close_with_mode() {
+            Err(TcpResult::TcpErrorClose) => {
+                let errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+                Err(VmaError::CloseFailed { fd, errno })
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Get the options the socket is running with.
+    ///
+    /// Starts from the options it was created with, including changes made by
+    /// `apply_options()` and `set_nodelay()`, then fills in what is actually in effect:
+    /// VMA settings left to their default take the value of their variable in the
+    /// environment and, while the socket has a descriptor, `buffer_size` is the receive buffer
+    /// the kernel granted and `tcp_nodelay` follows TCP_NODELAY. `options().diff(&requested)`
+    /// lists what was adjusted.
+    pub fn options(&self) -> VmaOptions {
+        common::effective_options(self.fd(), Protocol::Tcp, self.inner.options())
+    }
+    
+    /// Change the runtime-tunable options of the live socket, without reconnecting.
+    ///
+    /// The wait strategy and spin budget take effect from the next receive or accept (and
+    /// for clients accepted afterwards), the buffer size and TCP_NODELAY immediately. All
+    /// of them are kept across reconnects; `enable_timestamps` is recorded but does not
+    /// apply to TCP. Nothing is changed if the delta is invalid.
+    pub fn apply_options(&mut self, delta: &OptionsDelta) -> Result<(), std::io::Error> {
+        let mut options = self.inner.options();
+        delta.merge_into(&mut options)?;
+        if self.inner.fd() >= 0 {
+            if let Some(size) = delta.buffer_size.filter(|&size| size > 0) {
+                self.inner.set_recv_buffer_size(size as usize)?;
+                self.inner.set_send_buffer_size(size as usize)?;
+            }
+            if let Some(nodelay) = delta.tcp_nodelay {
+                self.inner.setopt_int(libc::IPPROTO_TCP, libc::TCP_NODELAY, nodelay as c_int)?;
+            }
+        }
+        self.inner.set_options(&options);
+        Ok(())
+    }
+    
+    /// Enable or disable TCP_NODELAY (Nagle's algorithm is disabled when `true`).
+    ///
+    /// The default comes from [`VmaOptions::tcp_nodelay`]; the setting is re-applied on reconnect.
+    pub fn set_nodelay(&mut self, nodelay: bool) -> Result<(), std::io::Error> {
+        self.inner.set_nodelay(nodelay)
+            .map_err(|e| e.into())
+    }
+    
+    /// Check whether TCP_NODELAY is enabled.
+    pub fn nodelay(&mut self) -> Result<bool, std::io::Error> {
+        self.inner.nodelay()
+            .map_err(|e| e.into())
+    }
+    
+    /// Hold back partial segments (TCP_CORK) until [`uncork`](Self::uncork) or [`flush`](Self::flush).
+    ///
+    /// Lets a header and a body written in two sends leave in one segment without
+    /// enabling Nagle. Full segments are still sent, and the kernel sends held data after
+    /// 200ms regardless. Corking is not re-applied on reconnect.
+    pub fn cork(&mut self) -> Result<(), std::io::Error> {
+        self.inner.set_cork(true)
+            .map_err(|e| e.into())
+    }
+    
+    /// Stop holding back partial segments, sending whatever is held now.
+    pub fn uncork(&mut self) -> Result<(), std::io::Error> {
+        self.inner.set_cork(false)
+            .map_err(|e| e.into())
+    }
+    
+    /// Check whether the socket is corked.
+    pub fn is_corked(&mut self) -> Result<bool, std::io::Error> {
+        self.inner.cork()
+            .map_err(|e| e.into())
+    }
+    
+    /// Send the data held back by [`cork`](Self::cork) now and stay corked.
+    ///
+    /// Does nothing on a socket that is not corked.
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        if self.inner.cork()? {
+            self.inner.set_cork(false)?;
+            self.inner.set_cork(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<OwnedFd> for VmaTcpSocket {
+    type Error = FromFdError;
+    
+    /// Take over a TCP descriptor with default VMA options (see [`VmaTcpSocket::from_fd`]).
+    fn try_from(fd: OwnedFd) -> Result<Self, Self::Error> {
+        VmaTcpSocket::from_fd(fd, None)
+    }
+}
+
+impl TryFrom<VmaTcpSocket> for OwnedFd {
+    type Error = std::io::Error;
+    
+    /// Hand the descriptor over, e.g. to `socket2::Socket::from`, dropping the counters.
+    ///
+    /// Fails if a failed reconnect left the socket without a descriptor.
+    fn try_from(socket: VmaTcpSocket) -> Result<Self, Self::Error> {
+        let VmaTcpSocket { inner, .. } = socket;
+        match inner.into_raw_fd() {
+            fd if fd < 0 => Err(Error::from_raw_os_error(libc::EBADF)),
+            fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+        }
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl TryFrom<socket2::Socket> for VmaTcpSocket {
+    type Error = FromFdError;
+    
+    /// Take over a `socket2` TCP socket with default VMA options (see [`VmaTcpSocket::from_fd`]).
+    fn try_from(socket: socket2::Socket) -> Result<Self, Self::Error> {
+        VmaTcpSocket::from_fd(OwnedFd::from(socket), None)
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl TryFrom<VmaTcpSocket> for socket2::Socket {
+    type Error = std::io::Error;
+    
+    /// Hand the descriptor over to `socket2`, dropping the counters.
+    ///
+    /// Fails if a failed reconnect left the socket without a descriptor.
+    fn try_from(socket: VmaTcpSocket) -> Result<Self, Self::Error> {
+        OwnedFd::try_from(socket).map(socket2::Socket::from)
+    }
+}
+
+impl AsRawFd for VmaTcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.fd()
+    }
+}
+
+impl StatsSource for VmaTcpSocket {
+    fn stats(&mut self) -> Result<SocketStats, std::io::Error> {
+        VmaTcpSocket::stats(self)
+    }
+    
+    fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        VmaTcpSocket::local_addr(self)
+    }
+    
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        VmaTcpSocket::peer_addr(self)
+    }
+    
+    fn vma_fd_stats(&self) -> Result<VmaFdStats, std::io::Error> {
+        VmaTcpSocket::vma_fd_stats(self)
+    }
+}
+
+impl std::io::Read for VmaTcpSocket {
+    /// Receive into `buffer`, waiting up to the read timeout; `Ok(0)` means the peer closed.
+    ///
+    /// An expired read timeout fails with `ErrorKind::WouldBlock`, as with `std::net::TcpStream`.
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self.recv_checked(buffer, None)? {
+            Some(bytes) => Ok(bytes),
+            None => Err(Error::new(ErrorKind::WouldBlock, "Read timed out")),
+        }
+    }
+}
+
+impl std::io::Write for VmaTcpSocket {
+    /// Send from `buffer`, waiting up to the write timeout for room in the send buffer.
+    ///
+    /// Returns the bytes written before the timeout, or fails with `ErrorKind::WouldBlock`
+    /// if there were none.
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        match self.send_timeout(buffer, self.write_timeout)? {
+            0 if !buffer.is_empty() => Err(Error::new(ErrorKind::WouldBlock, "Write timed out")),
+            bytes => Ok(bytes),
+        }
+    }
+    
+    /// Send the data held back while corked, if any.
+    fn flush(&mut self) -> std::io::Result<()> {
+        VmaTcpSocket::flush(self)
+    }
+}
+
+/// Iterator over accepted connections, created by [`VmaTcpSocket::incoming`] and
+/// [`VmaTcpSocket::incoming_nonblocking`].
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    socket: &'a mut VmaTcpSocket,
+    nonblocking: bool,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = Result<Client, std::io::Error>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        let timeout = if self.nonblocking { Some(0) } else { None };
+        loop {
+            match self.socket.accept(timeout) {
+                Ok(Some(client)) => return Some(Ok(client)),
+                Ok(None) if self.nonblocking => return None,
+                Err(e) if self.nonblocking && e.kind() == ErrorKind::WouldBlock => return None,
+                Ok(None) => continue, // spurious wakeup
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Connect a socket to a listener on loopback, returning the listener, the connected
+    /// socket and the accepted client.
+    fn connected_pair() -> (VmaTcpSocket, VmaTcpSocket, Client) {
+        connected_pair_with(VmaOptions::default())
+    }
+
+    /// Like [`connected_pair`], with the connecting socket created from `options`.
+    fn connected_pair_with(options: VmaOptions) -> (VmaTcpSocket, VmaTcpSocket, Client) {
+        let mut listener = VmaTcpSocket::new().unwrap();
+        listener.bind("127.0.0.1", 0).unwrap();
+        listener.listen(16).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut socket = VmaTcpSocket::with_options(options).unwrap();
+        assert!(socket.connect("127.0.0.1", port, Some(1_000_000_000)).unwrap());
+        let client = listener.accept(Some(1_000_000_000)).unwrap().unwrap();
+        (listener, socket, client)
+    }
+
+    #[test]
+    fn test_nonblocking() {
+        let (mut listener, mut socket, mut client) = connected_pair();
+
+        // Nothing pending: would block like std, and the nonblocking iterator just ends
+        listener.set_nonblocking(true);
+        assert_eq!(listener.accept(None).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert!(listener.incoming_nonblocking().next().is_none());
+
+        socket.set_nonblocking(true);
+        let mut buffer = [0u8; 16];
+        assert_eq!(socket.recv(&mut buffer, None).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(socket.recv_checked(&mut buffer, Some(1_000_000_000)).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(socket.peek(&mut buffer, None).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        client.send(b"data").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let received = loop {
+            match socket.recv(&mut buffer, None) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => std::thread::yield_now(),
+                result => break result.unwrap(),
+            }
+        };
+        assert_eq!(&buffer[..received], b"data");
+
+        // End-of-stream still reads 0
+        drop(client);
+        let eof = loop {
+            match socket.recv_checked(&mut buffer, None) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => std::thread::yield_now(),
+                result => break result.unwrap(),
+            }
+        };
+        assert_eq!(eof, Some(0));
+    }
+
+    #[test]
+    fn test_write_timeout() {
+        let options = VmaOptions { use_polling: false, buffer_size: 4096, ..VmaOptions::default() };
+        let (_listener, mut socket, _client) = connected_pair_with(options);
+        assert_eq!(socket.set_write_timeout(Some(0)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        socket.set_write_timeout(Some(50_000_000)).unwrap();
+
+        // The client never reads: once the buffers are full a send gives up after the timeout
+        // with what it wrote so far, and eventually with nothing
+        let chunk = vec![0u8; 1 << 16];
+        let mut attempts = 0;
+        let (partial, elapsed) = loop {
+            attempts += 1;
+            assert!(attempts < 1000, "send buffer never filled");
+            let start = Instant::now();
+            let sent = socket.send(&chunk).unwrap();
+            if sent < chunk.len() {
+                break (sent, start.elapsed());
+            }
+        };
+        assert!(partial < chunk.len());
+        assert!(elapsed >= Duration::from_millis(40));
+        while socket.send(&chunk).unwrap() > 0 {
+            attempts += 1;
+            assert!(attempts < 1000, "send never timed out empty");
+        }
+        assert!(socket.is_connected());
+    }
+}
@@ -0,0 +1,197 @@
+//! TLS sessions over VMA TCP sockets with rustls.
+//!
+//! Enabled with the `tls` feature. [`TlsClient`] wraps a connected [`VmaTcpSocket`] and
+//! [`TlsServer`] an accepted [`Client`] (any `Read + Write` stream works), keeping the
+//! VMA transport underneath while the session is encrypted. Both complete the handshake
+//! before returning and then implement `Read` and `Write` for the plaintext.
+//!
+//! The handshake and reads block on the underlying socket; set a read timeout on it
+//! (`VmaTcpSocket::set_read_timeout`) to bound them. A timeout surfaces as
+//! `ErrorKind::WouldBlock`.
+//!
+//! The crypto provider is `ring`. The matching `rustls` version is re-exported for
+//! building configurations; [`client_config`] and [`server_config`] cover the common case.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::{Read, Write};
+//! use vma_socket::tcp::VmaTcpSocket;
+//! use vma_socket::tls::{self, rustls, TlsClient};
+//!
+//! let mut roots = rustls::RootCertStore::empty();
+//! # let exchange_ca = rustls::pki_types::CertificateDer::from(Vec::new());
+//! roots.add(exchange_ca).unwrap();
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.set_read_timeout(Some(5_000_000_000));
+//! socket.connect("192.168.1.100", 9443, Some(1_000_000_000)).unwrap();
+//!
+//! let mut session = TlsClient::connect(tls::client_config(roots), "oe.exchange.example", socket).unwrap();
+//! session.write_all(b"8=FIX.4.4\x01...").unwrap();
+//!
+//! let mut reply = [0u8; 4096];
+//! let n = session.read(&mut reply).unwrap();
+//! println!("{} bytes", n);
+//! ```
+
+use std::io::{Error, ErrorKind, Read, Write};
+use std::sync::Arc;
+
+pub use rustls;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, ConnectionCommon, RootCertStore, ServerConfig, ServerConnection, SideData, StreamOwned};
+
+use crate::tcp::{Client, VmaTcpSocket};
+
+/// Build a client configuration trusting `roots`, without client certificates.
+pub fn client_config(roots: RootCertStore) -> Arc<ClientConfig> {
+    Arc::new(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+}
+
+/// Build a server configuration presenting `cert_chain` (leaf first), without client authentication.
+///
+/// Fails with `ErrorKind::InvalidInput` when the key does not match the certificate.
+pub fn server_config(cert_chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Result<Arc<ServerConfig>, Error> {
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map(Arc::new)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// Client end of a TLS session.
+#[derive(Debug)]
+pub struct TlsClient<S: Read + Write = VmaTcpSocket> {
+    stream: StreamOwned<ClientConnection, S>,
+}
+
+impl<S: Read + Write> TlsClient<S> {
+    /// Run the handshake with `server_name` over a connected socket.
+    ///
+    /// The certificate must be valid for `server_name`, a DNS name or IP address.
+    /// Handshake failures (e.g. an untrusted certificate) fail with `ErrorKind::InvalidData`.
+    pub fn connect(config: Arc<ClientConfig>, server_name: &str, mut socket: S) -> Result<Self, Error> {
+        let server_name = ServerName::try_from(server_name.to_owned())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let mut connection = ClientConnection::new(config, server_name).map_err(tls_error)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+        Ok(TlsClient { stream: StreamOwned::new(connection, socket) })
+    }
+
+    /// Get the rustls session, e.g. for the negotiated protocol version or ALPN.
+    pub fn connection(&self) -> &ClientConnection {
+        &self.stream.conn
+    }
+
+    /// Get the underlying socket.
+    pub fn get_ref(&self) -> &S {
+        self.stream.get_ref()
+    }
+
+    /// Get the underlying socket mutably (reading or writing it directly corrupts the session).
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
+    }
+
+    /// Send `close_notify` so the peer sees a clean end of the session.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        close(&mut self.stream.conn, &mut self.stream.sock)
+    }
+
+    /// Unwrap the socket, dropping the session.
+    pub fn into_inner(self) -> S {
+        self.stream.into_parts().1
+    }
+}
+
+impl<S: Read + Write> Read for TlsClient<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.read(buffer)
+    }
+}
+
+impl<S: Read + Write> Write for TlsClient<S> {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Server end of a TLS session.
+#[derive(Debug)]
+pub struct TlsServer<S: Read + Write = Client> {
+    stream: StreamOwned<ServerConnection, S>,
+}
+
+impl<S: Read + Write> TlsServer<S> {
+    /// Run the handshake over an accepted connection.
+    ///
+    /// Handshake failures fail with `ErrorKind::InvalidData`.
+    pub fn accept(config: Arc<ServerConfig>, mut client: S) -> Result<Self, Error> {
+        let mut connection = ServerConnection::new(config).map_err(tls_error)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut client)?;
+        }
+        Ok(TlsServer { stream: StreamOwned::new(connection, client) })
+    }
+
+    /// Get the rustls session, e.g. for the SNI name the client asked for.
+    pub fn connection(&self) -> &ServerConnection {
+        &self.stream.conn
+    }
+
+    /// Get the underlying connection.
+    pub fn get_ref(&self) -> &S {
+        self.stream.get_ref()
+    }
+
+    /// Get the underlying connection mutably (reading or writing it directly corrupts the session).
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
+    }
+
+    /// Send `close_notify` so the peer sees a clean end of the session.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        close(&mut self.stream.conn, &mut self.stream.sock)
+    }
+
+    /// Unwrap the connection, dropping the session.
+    pub fn into_inner(self) -> S {
+        self.stream.into_parts().1
+    }
+}
+
+impl<S: Read + Write> Read for TlsServer<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.read(buffer)
+    }
+}
+
+impl<S: Read + Write> Write for TlsServer<S> {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Queue `close_notify` and write out everything pending.
+fn close<D: SideData, S: Write>(connection: &mut ConnectionCommon<D>, socket: &mut S) -> Result<(), Error> {
+    connection.send_close_notify();
+    while connection.wants_write() {
+        connection.write_tls(socket)?;
+    }
+    socket.flush()
+}
+
+fn tls_error(error: rustls::Error) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
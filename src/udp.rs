@@ -45,7 +45,7 @@
 //! let mut buffer = vec![0u8; 4096];
 //!
 //! // Receive data with timeout
-//! match socket.recv_from(&mut buffer, Some(100_000_000)) { // 100ms timeout
+//! match socket.recv_from(&mut buffer, Some(100_000_000)).unwrap() { // 100ms timeout
 //!     Some(packet) => {
 //!         println!("Received {} bytes from {}", packet.data.len(), packet.src_addr);
 //!         println!("Packet timestamp: {} ns", packet.timestamp);
@@ -74,6 +74,10 @@
 //!
 //! // Or send to a specific target without prior connect()
 //! socket.send_to(data, "192.168.1.101", 5002).unwrap();
+//!
+//! // Peers sent to repeatedly are parsed once
+//! let dest = socket.resolve("192.168.1.102", 5003).unwrap();
+//! socket.send_to_dest(data, &dest).unwrap();
 //! ```
 //!
 //! ## Performance statistics
@@ -88,28 +92,52 @@
 //! let (rx_packets, tx_packets, rx_bytes, tx_bytes) = socket.get_stats().unwrap();
 //! println!("Stats: RX {}p/{}b, TX {}p/{}b", 
 //!          rx_packets, rx_bytes, tx_packets, tx_bytes);
+//!
+//! // Or the full set of counters
+//! let stats = socket.stats().unwrap();
+//! println!("Errors: RX {} TX {}, truncated: {}", 
+//!          stats.rx_errors, stats.tx_errors, stats.rx_truncated);
 //! ```
 
 use std::ffi::{c_void, CString};
+use std::marker::{PhantomData, PhantomPinned};
 use std::mem;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::ops::Range;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::ptr::{self, NonNull};
 use std::os::raw::{c_char, c_int, c_ulonglong};
-use crate::common::{SockAddrIn, VmaOptions, unixnano_to_ms, sockaddr_to_rust};
+use std::time::{Duration, Instant};
+use crate::notify::Notified;
+use crate::pacing::Pacer;
+use crate::registered::RegisteredBuffer;
+use crate::shutdown::{self, ShutdownToken};
+use crate::fdtrack::FdTracker;
+use crate::common::{self, FiveTuple, MsgFlags, OptionsDelta, Protocol, SockAddrIn, SocketDiagnostics, SocketStats, StatsSource, FromFdError, VmaError, VmaFdStats, VmaOptions, unixnano_to_ms, sockaddr_to_rust};
+
+/// Maximum number of datagrams returned by one `recv_burst()` (must match UDP_RECV_BURST_MAX in udp_socket.h)
+pub const RECV_BURST_MAX: usize = 64;
+
+/// Receive buffer reserved per datagram by `recv_burst()`, enough for a jumbo frame
+pub const RECV_BURST_SLOT_SIZE: usize = 9216;
+
+/// Maximum number of datagrams sent by one `sendmmsg` of `flush()` (must match UDP_SEND_BATCH_MAX in udp_socket.h)
+pub const SEND_BATCH_MAX: usize = 64;
+
+/// Maximum number of datagrams sent by one `send_gso()` (must match UDP_GSO_MAX_SEGMENTS in udp_socket.h)
+pub const GSO_MAX_SEGMENTS: usize = 64;
+
+/// Largest UDP payload over IPv4 (65535 minus the IP and UDP headers), the default maximum datagram size
+pub const MAX_DATAGRAM_SIZE: usize = 65507;
+
+/// Interval between two checks of the send queue while draining it before a close.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
 
-/// C representation of a UDP socket.
+/// Opaque C UDP socket state, allocated and freed by the C layer.
 #[repr(C)]
-#[derive(Debug, Clone)]
-pub struct UdpSocket {
-    pub socket_fd: c_int,
-    pub vma_options: VmaOptions,
-    pub local_addr: SockAddrIn,
-    pub remote_addr: SockAddrIn,
-    pub is_bound: bool,
-    pub is_connected: bool,
-    pub rx_packets: c_ulonglong,
-    pub tx_packets: c_ulonglong,
-    pub rx_bytes: c_ulonglong,
-    pub tx_bytes: c_ulonglong,
+struct UdpSocket {
+    _private: [u8; 0],
+    _marker: PhantomData<(*mut u8, PhantomPinned)>,
 }
 
 /// C representation of a UDP packet.
@@ -120,6 +148,9 @@ pub struct UdpPacket {
     pub length: usize,
     pub src_addr: SockAddrIn,
     pub timestamp: c_ulonglong,
+    pub dst_addr: u32,
+    pub if_index: c_int,
+    pub full_length: usize,
 }
 
 /// Result codes returned by the C UDP socket functions.
@@ -137,13 +168,15 @@ pub enum UdpResult {
     UdpErrorInvalidParam = -8,
     UdpErrorNotInitialized = -9,
     UdpErrorClosed = -10,
+    UdpErrorNotSupported = -11,
+    UdpErrorClose = -12,
 }
 
 use std::io::{Error, ErrorKind};
 impl From<UdpResult> for std::io::Error {
     fn from(udp_result: UdpResult) -> Self {
         match udp_result {
-            UdpResult::UdpSuccess => Error::new(ErrorKind::Other, "Unexpected success"),
+            UdpResult::UdpSuccess => Error::other("Unexpected success"),
             UdpResult::UdpErrorSocketCreate => Error::new(ErrorKind::ConnectionRefused, "Socket creation failed"),
             UdpResult::UdpErrorSocketOption => Error::new(ErrorKind::InvalidInput, "Socket option error"),
             UdpResult::UdpErrorBind => Error::new(ErrorKind::AddrInUse, "Bind failed"),
@@ -154,16 +187,28 @@ impl From<UdpResult> for std::io::Error {
             UdpResult::UdpErrorInvalidParam => Error::new(ErrorKind::InvalidInput, "Invalid parameter"),
             UdpResult::UdpErrorNotInitialized => Error::new(ErrorKind::NotConnected, "Not initialized"),
             UdpResult::UdpErrorClosed => Error::new(ErrorKind::ConnectionAborted, "Socket closed"),
+            UdpResult::UdpErrorNotSupported => Error::new(ErrorKind::Unsupported, "Not supported without VMA offload"),
+            UdpResult::UdpErrorClose => Error::other("Close failed"),
         }
     }
 }
 
 // External declarations for C functions - using VmaOptions directly
 extern "C" {
-    fn udp_socket_init(socket: *mut UdpSocket, options: *const VmaOptions) -> c_int;
+    fn udp_socket_new(socket: *mut *mut UdpSocket, options: *const VmaOptions) -> c_int;
+    fn udp_socket_free(socket: *mut UdpSocket);
     fn udp_socket_close(socket: *mut UdpSocket) -> c_int;
+    fn udp_socket_get_options(socket: *const UdpSocket, options: *mut VmaOptions);
+    fn udp_socket_set_options(socket: *mut UdpSocket, options: *const VmaOptions);
+    fn udp_socket_fd(socket: *const UdpSocket) -> c_int;
+    fn udp_socket_from_fd(socket: *mut *mut UdpSocket, fd: c_int, options: *const VmaOptions) -> c_int;
+    fn udp_socket_release_fd(socket: *mut UdpSocket) -> c_int;
+    fn udp_socket_poll_readable(socket: *mut UdpSocket, timeout_ms: c_int) -> c_int;
+    fn udp_socket_get_peer(socket: *const UdpSocket, addr: *mut SockAddrIn) -> bool;
     fn udp_socket_bind(socket: *mut UdpSocket, ip: *const c_char, port: u16) -> c_int;
     fn udp_socket_connect(socket: *mut UdpSocket, ip: *const c_char, port: u16) -> c_int;
+    fn udp_socket_disconnect(socket: *mut UdpSocket) -> c_int;
+    fn udp_socket_rebind(socket: *mut UdpSocket, ip: *const c_char, port: u16, groups: *const libc::ip_mreq, group_count: usize) -> c_int;
     fn udp_socket_send(socket: *mut UdpSocket, data: *const c_void, length: usize, bytes_sent: *mut usize) -> c_int;
     fn udp_socket_sendto(
         socket: *mut UdpSocket,
@@ -187,6 +232,98 @@ extern "C" {
         buffer_size: usize,
         timeout_ms: c_int,
     ) -> c_int;
+    fn udp_socket_send_zerocopy(socket: *mut UdpSocket, data: *const c_void, length: usize, flags: c_int, bytes_sent: *mut usize) -> c_int;
+    fn udp_socket_zerocopy_pending(socket: *mut UdpSocket) -> u32;
+    fn udp_socket_send_dummy(socket: *mut UdpSocket, data: *const c_void, length: usize) -> c_int;
+    fn udp_socket_send_flags(
+        socket: *mut UdpSocket,
+        data: *const c_void,
+        length: usize,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn udp_socket_sendto_addr(
+        socket: *mut UdpSocket,
+        data: *const c_void,
+        length: usize,
+        addr: *const SockAddrIn,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn udp_socket_send_batch(
+        socket: *mut UdpSocket,
+        data: *const c_void,
+        offsets: *const usize,
+        addrs: *const SockAddrIn,
+        count: usize,
+        flags: c_int,
+        sent: *mut usize,
+    ) -> c_int;
+    fn udp_socket_send_gso(
+        socket: *mut UdpSocket,
+        data: *const c_void,
+        length: usize,
+        segment_size: u16,
+        dest_addr: *const SockAddrIn,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn udp_socket_sendto_flags(
+        socket: *mut UdpSocket,
+        data: *const c_void,
+        length: usize,
+        ip: *const c_char,
+        port: u16,
+        flags: c_int,
+        bytes_sent: *mut usize,
+    ) -> c_int;
+    fn udp_socket_recv_flags(
+        socket: *mut UdpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        flags: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn udp_socket_recvfrom_flags(
+        socket: *mut UdpSocket,
+        packet: *mut UdpPacket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        flags: c_int,
+    ) -> c_int;
+    fn udp_socket_recvfrom_burst(
+        socket: *mut UdpSocket,
+        packets: *mut UdpPacket,
+        buffers: *mut c_void,
+        slot_size: usize,
+        max_packets: usize,
+        timeout_ms: c_int,
+        received: *mut usize,
+    ) -> c_int;
+    fn udp_socket_recv_full(
+        socket: *mut UdpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        bytes_received: *mut usize,
+        datagram_length: *mut usize,
+    ) -> c_int;
+    fn udp_socket_peek(
+        socket: *mut UdpSocket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+        bytes_received: *mut usize,
+    ) -> c_int;
+    fn udp_socket_peekfrom(
+        socket: *mut UdpSocket,
+        packet: *mut UdpPacket,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        timeout_ms: c_int,
+    ) -> c_int;
     fn udp_socket_get_stats(
         socket: *mut UdpSocket,
         rx_packets: *mut c_ulonglong,
@@ -194,6 +331,14 @@ extern "C" {
         rx_bytes: *mut c_ulonglong,
         tx_bytes: *mut c_ulonglong,
     ) -> c_int;
+    fn udp_socket_get_extended_stats(socket: *mut UdpSocket, stats: *mut SocketStats) -> c_int;
+    fn udp_socket_reset_stats(socket: *mut UdpSocket) -> c_int;
+    fn udp_socket_setopt(socket: *mut UdpSocket, level: c_int, optname: c_int, optval: *const c_void, optlen: libc::socklen_t) -> c_int;
+    fn udp_socket_getopt(socket: *mut UdpSocket, level: c_int, optname: c_int, optval: *mut c_void, optlen: *mut libc::socklen_t) -> c_int;
+    fn udp_socket_steer_flow(socket: *mut UdpSocket, rule: *const c_char, ring_id: u32) -> c_int;
+    fn udp_socket_set_ttl(socket: *mut UdpSocket, ttl: c_int) -> c_int;
+    fn udp_socket_set_tos(socket: *mut UdpSocket, tos: c_int) -> c_int;
+    fn udp_socket_set_multicast_ttl(socket: *mut UdpSocket, ttl: c_int) -> c_int;
 }
 
 /// A received UDP packet with associated metadata.
@@ -205,46 +350,261 @@ pub struct Packet {
     /// The source address from which the packet was received.
     pub src_addr: SocketAddr,
     
-    /// Hardware timestamp (if available) in nanoseconds since the epoch.
+    /// Kernel receive timestamp (SO_TIMESTAMPNS) in nanoseconds since the epoch, 0 unless
+    /// `enable_timestamps` is set.
     pub timestamp: u64,
+    
+    /// The destination address the packet was sent to (requires `set_pktinfo(true)`).
+    ///
+    /// Tells which local alias or multicast group a datagram targeted on a socket bound
+    /// to `0.0.0.0`.
+    pub dst_addr: Option<Ipv4Addr>,
+    
+    /// Index of the interface the packet arrived on (requires `set_pktinfo(true)`, 0 if unknown).
+    pub if_index: u32,
+    
+    /// Full length of the datagram on the wire, larger than `data.len()` if it was truncated.
+    pub full_length: usize,
 }
 
-/// Low-level wrapper around the C UDP socket implementation.
-/// Uses stack allocation instead of heap allocation for better performance.
+impl Packet {
+    /// Check whether the datagram was larger than the receive buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.full_length > self.data.len()
+    }
+    
+    /// Build a packet from the C structure filled by `udp_socket_recvfrom`, copying the payload.
+    fn from_c(packet: &UdpPacket) -> Self {
+        // Convert sockaddr to Rust SocketAddr
+        let src_addr = sockaddr_to_rust(&packet.src_addr);
+        
+        // Copy data
+        let data = unsafe { std::slice::from_raw_parts(packet.data as *const u8, packet.length) }.to_vec();
+        
+        // IP_PKTINFO ancillary data (zeroed when not enabled)
+        let dst_addr = if packet.dst_addr != 0 {
+            Some(Ipv4Addr::from(u32::from_be(packet.dst_addr)))
+        } else {
+            None
+        };
+        
+        Packet {
+            data,
+            src_addr,
+            timestamp: packet.timestamp,
+            dst_addr,
+            if_index: packet.if_index as u32,
+            full_length: packet.full_length,
+        }
+    }
+}
+
+/// A peer address prepared once for repeated `send_to_dest()` calls.
+///
+/// Created by [`VmaUdpSocket::resolve`] or from a `SocketAddrV4`; sending to it skips
+/// the string parsing and `CString` allocation of `send_to()`.
 #[derive(Debug, Clone)]
+pub struct Destination {
+    addr: SocketAddrV4,
+    c_addr: SockAddrIn,
+}
+
+impl Destination {
+    /// Prepare the C address of `addr`.
+    pub fn new(addr: SocketAddrV4) -> Self {
+        Destination {
+            addr,
+            c_addr: common::sockaddr_from_rust(&addr),
+        }
+    }
+    
+    /// Get the address this destination sends to.
+    pub fn addr(&self) -> SocketAddrV4 {
+        self.addr
+    }
+}
+
+impl From<SocketAddrV4> for Destination {
+    fn from(addr: SocketAddrV4) -> Self {
+        Destination::new(addr)
+    }
+}
+
+/// Datagrams queued by `queue_send()`, stored back to back until `flush()`.
+#[derive(Debug, Default)]
+struct TxQueue {
+    data: Vec<u8>,
+    offsets: Vec<usize>,
+    addrs: Vec<SockAddrIn>,
+}
+
+impl TxQueue {
+    fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    fn push(&mut self, data: &[u8], addr: SockAddrIn) {
+        if self.offsets.is_empty() {
+            self.offsets.push(0);
+        }
+        self.data.extend_from_slice(data);
+        self.offsets.push(self.data.len());
+        self.addrs.push(addr);
+    }
+
+    /// Drop the first `count` datagrams.
+    fn consume(&mut self, count: usize) {
+        if count >= self.len() {
+            self.data.clear();
+            self.offsets.clear();
+            self.addrs.clear();
+            return;
+        }
+        let start = self.offsets[count];
+        self.data.drain(..start);
+        self.offsets.drain(..count);
+        self.offsets.iter_mut().for_each(|offset| *offset -= start);
+        self.addrs.drain(..count);
+    }
+}
+
+impl PartialEq for Destination {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr == other.addr
+    }
+}
+
+impl Eq for Destination {}
+
+/// Lengths reported by `recv_full()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvLength {
+    /// Number of bytes copied into the buffer
+    pub copied: usize,
+    /// Full length of the datagram; the excess over `copied` was discarded
+    pub datagram: usize,
+}
+
+impl RecvLength {
+    /// Check whether the datagram was larger than the receive buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.datagram > self.copied
+    }
+}
+
+/// Low-level wrapper around the C UDP socket implementation.
+/// Holds only a pointer to the socket state owned by the C layer.
+#[derive(Debug)]
 pub struct UdpSocketWrapper {
-    socket: UdpSocket,
+    socket: NonNull<UdpSocket>,
+    tracker: FdTracker,
 }
 
+// The handle is owned by this wrapper alone, so it may move to another thread. It is not
+// `Sync`: some `&self` methods pass the handle to C calls that update the counters and the
+// zero-copy state, which would race between threads sharing a reference.
+unsafe impl Send for UdpSocketWrapper {}
+
 impl UdpSocketWrapper {
+    /// Take ownership of a socket created by the C layer.
+    fn adopt(socket: NonNull<UdpSocket>) -> Self {
+        let fd = unsafe { udp_socket_fd(socket.as_ptr()) };
+        UdpSocketWrapper { socket, tracker: FdTracker::new(Protocol::Udp, fd) }
+    }
+
+    /// Get the C socket, checking its descriptor against the fd registry.
+    fn as_ptr(&self) -> *mut UdpSocket {
+        self.tracker.observe(unsafe { udp_socket_fd(self.socket.as_ptr()) });
+        self.socket.as_ptr()
+    }
+
     /// Create a new UDP socket with the specified options.
     pub fn new(options: Option<VmaOptions>) -> Result<Self, UdpResult> {
-        // Clear memory for new socket
-        let mut socket = unsafe { mem::zeroed::<UdpSocket>() };
-        
         // Get options - either use provided ones or defaults
         let c_options = options.unwrap_or_default();
 
-        // Initialize socket with options
-        let result = unsafe { 
-            // Print for debugging
-            println!("Initializing UDP socket with options: use_socketxtreme={}, optimize_for_latency={}, ring_count={}",
-                c_options.use_socketxtreme, c_options.optimize_for_latency, c_options.ring_count);
-            udp_socket_init(&mut socket, &c_options)
-        };
+        let mut socket = ptr::null_mut();
+        let result = unsafe { udp_socket_new(&mut socket, &c_options) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        NonNull::new(socket)
+            .map(UdpSocketWrapper::adopt)
+            .ok_or(UdpResult::UdpErrorSocketCreate)
+    }
+
+    /// Adopt an existing IPv4 UDP descriptor, leaving it open on failure.
+    pub fn from_raw_fd(fd: c_int, options: Option<VmaOptions>) -> Result<Self, UdpResult> {
+        let c_options = options.unwrap_or_default();
+
+        let mut socket = ptr::null_mut();
+        let result = unsafe { udp_socket_from_fd(&mut socket, fd, &c_options) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        NonNull::new(socket)
+            .map(UdpSocketWrapper::adopt)
+            .ok_or(UdpResult::UdpErrorSocketCreate)
+    }
+
+    /// Detach the descriptor and free the socket without closing it.
+    pub fn into_raw_fd(self) -> c_int {
+        let fd = unsafe { udp_socket_release_fd(self.as_ptr()) };
+        self.tracker.release();
+        fd
+    }
+
+    /// Get the file descriptor of the socket.
+    pub fn fd(&self) -> c_int {
+        unsafe { udp_socket_fd(self.as_ptr()) }
+    }
+
+    /// Get the options recorded for the socket.
+    pub fn options(&self) -> VmaOptions {
+        let mut options = VmaOptions::default();
+        unsafe { udp_socket_get_options(self.as_ptr(), &mut options) };
+        options
+    }
+
+    /// Replace the recorded options; receives follow the new wait strategy.
+    pub fn set_options(&mut self, options: &VmaOptions) {
+        unsafe { udp_socket_set_options(self.as_ptr(), options) };
+    }
+
+    /// Close the descriptor, which is released even if `close(2)` fails.
+    pub fn close(&mut self) -> Result<(), UdpResult> {
+        let result = unsafe { udp_socket_close(self.as_ptr()) };
+        self.tracker.release();
         
         if result != UdpResult::UdpSuccess as i32 {
-            println!("UDP socket initialization failed with code: {}", result);
             return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
         }
         
-        Ok(UdpSocketWrapper { socket })
+        Ok(())
+    }
+
+    /// Wait up to `timeout_nano` for a datagram to receive, following the wait strategy (`Some(0)` only checks).
+    pub fn poll_readable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        match unsafe { udp_socket_poll_readable(self.as_ptr(), unixnano_to_ms(timeout_nano)) } {
+            result if result < 0 => Err(Error::last_os_error()),
+            result => Ok(result > 0),
+        }
+    }
+
+    /// Get the default destination set by `connect()`, if any.
+    pub fn peer(&self) -> Option<SockAddrIn> {
+        let mut addr = unsafe { mem::zeroed::<SockAddrIn>() };
+        unsafe { udp_socket_get_peer(self.as_ptr(), &mut addr) }.then_some(addr)
     }
 
     /// Bind the socket to a local address and port.
     pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), UdpResult> {
         let c_addr = CString::new(addr.into()).unwrap();
-        let result = unsafe { udp_socket_bind(&mut self.socket, c_addr.as_ptr(), port) };
+        let result = unsafe { udp_socket_bind(self.as_ptr(), c_addr.as_ptr(), port) };
         
         if result != UdpResult::UdpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
@@ -256,7 +616,31 @@ impl UdpSocketWrapper {
     /// Connect the socket to a remote address and port.
     pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), UdpResult> {
         let c_addr = CString::new(addr.into()).unwrap();
-        let result = unsafe { udp_socket_connect(&mut self.socket, c_addr.as_ptr(), port) };
+        let result = unsafe { udp_socket_connect(self.as_ptr(), c_addr.as_ptr(), port) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Dissolve the association set by `connect()`.
+    pub fn disconnect(&mut self) -> Result<(), UdpResult> {
+        let result = unsafe { udp_socket_disconnect(self.as_ptr()) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Move the socket to a new local address and port, in place when it is not bound
+    /// yet, else on a new descriptor joining `groups`.
+    pub fn rebind<A: Into<String>>(&mut self, addr: A, port: u16, groups: &[libc::ip_mreq]) -> Result<(), UdpResult> {
+        let c_addr = CString::new(addr.into()).unwrap();
+        let result = unsafe { udp_socket_rebind(self.as_ptr(), c_addr.as_ptr(), port, groups.as_ptr(), groups.len()) };
         
         if result != UdpResult::UdpSuccess as i32 {
             return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
@@ -270,7 +654,7 @@ impl UdpSocketWrapper {
         let mut bytes_sent: usize = 0;
         let result = unsafe {
             udp_socket_send(
-                &mut self.socket,
+                self.as_ptr(),
                 data.as_ptr() as *const c_void,
                 data.len(),
                 &mut bytes_sent,
@@ -291,7 +675,7 @@ impl UdpSocketWrapper {
         
         let result = unsafe {
             udp_socket_sendto(
-                &mut self.socket,
+                self.as_ptr(),
                 data.as_ptr() as *const c_void,
                 data.len(),
                 c_addr.as_ptr(),
@@ -314,7 +698,7 @@ impl UdpSocketWrapper {
         
         let result = unsafe {
             udp_socket_recv(
-                &mut self.socket,
+                self.as_ptr(),
                 buffer.as_mut_ptr() as *mut c_void,
                 buffer.len(),
                 timeout_ms,
@@ -329,6 +713,33 @@ impl UdpSocketWrapper {
         Ok(bytes_received)
     }
 
+    /// Receive data and report the full datagram length alongside the copied length.
+    pub fn recv_full(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<RecvLength, UdpResult> {
+        let mut bytes_received: usize = 0;
+        let mut datagram_length: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            udp_socket_recv_full(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                &mut bytes_received,
+                &mut datagram_length,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(RecvLength {
+            copied: bytes_received,
+            datagram: datagram_length,
+        })
+    }
+
     /// Receive data and source address information.
     pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Packet, UdpResult> {
         let mut packet = unsafe { mem::zeroed::<UdpPacket>() };
@@ -336,7 +747,7 @@ impl UdpSocketWrapper {
         
         let result = unsafe {
             udp_socket_recvfrom(
-                &mut self.socket,
+                self.as_ptr(),
                 &mut packet,
                 buffer.as_mut_ptr() as *mut c_void,
                 buffer.len(),
@@ -348,33 +759,50 @@ impl UdpSocketWrapper {
             return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
         }
         
-        // Convert sockaddr to Rust SocketAddr
-        let src_addr = sockaddr_to_rust(&packet.src_addr);
+        Ok(Packet::from_c(&packet))
+    }
+
+    /// Receive up to `buffers.len() / slot_size` datagrams (at most `RECV_BURST_MAX`) with one call.
+    pub fn recv_from_burst(&mut self, buffers: &mut [u8], slot_size: usize, timeout_nano: Option<u64>) -> Result<Vec<Packet>, UdpResult> {
+        if slot_size == 0 {
+            return Err(UdpResult::UdpErrorInvalidParam);
+        }
+        let max_packets = (buffers.len() / slot_size).min(RECV_BURST_MAX);
+        let mut packets = vec![unsafe { mem::zeroed::<UdpPacket>() }; max_packets];
+        let mut received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
         
-        // Copy data
-        let data = unsafe { std::slice::from_raw_parts(packet.data as *const u8, packet.length) }.to_vec();
+        let result = unsafe {
+            udp_socket_recvfrom_burst(
+                self.as_ptr(),
+                packets.as_mut_ptr(),
+                buffers.as_mut_ptr() as *mut c_void,
+                slot_size,
+                max_packets,
+                timeout_ms,
+                &mut received,
+            )
+        };
         
-        Ok(Packet {
-            data,
-            src_addr,
-            timestamp: packet.timestamp,
-        })
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(packets[..received].iter().map(Packet::from_c).collect())
     }
 
-    /// Get socket statistics.
-    pub fn get_stats(&mut self) -> Result<(u64, u64, u64, u64), UdpResult> {
-        let mut rx_packets: c_ulonglong = 0;
-        let mut tx_packets: c_ulonglong = 0;
-        let mut rx_bytes: c_ulonglong = 0;
-        let mut tx_bytes: c_ulonglong = 0;
+    /// Copy the next datagram into `buffer` without removing it from the queue.
+    pub fn peek(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, UdpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
         
         let result = unsafe {
-            udp_socket_get_stats(
-                &mut self.socket as *mut _,
-                &mut rx_packets,
-                &mut tx_packets,
-                &mut rx_bytes,
-                &mut tx_bytes,
+            udp_socket_peek(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                &mut bytes_received,
             )
         };
         
@@ -382,77 +810,1171 @@ impl UdpSocketWrapper {
             return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
         }
         
-        Ok((rx_packets, tx_packets, rx_bytes, tx_bytes))
+        Ok(bytes_received)
     }
-}
 
-impl Drop for UdpSocketWrapper {
-    fn drop(&mut self) {
-        unsafe {
-            udp_socket_close(&mut self.socket);
+    /// Peek at the next datagram and its source address without removing it from the queue.
+    pub fn peek_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Packet, UdpResult> {
+        let mut packet = unsafe { mem::zeroed::<UdpPacket>() };
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            udp_socket_peekfrom(
+                self.as_ptr(),
+                &mut packet,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
         }
+        
+        Ok(Packet::from_c(&packet))
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct VmaUdpSocket {
-    inner: UdpSocketWrapper,
-}
-impl VmaUdpSocket {
-    /// Create a new UDP socket with default VMA options.
+    /// Send data to the connected remote address with `send(2)` flags.
+    pub fn send_with_flags(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, UdpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            udp_socket_send_flags(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+
+    /// Send data to the connected remote address with MSG_ZEROCOPY, copying when unsupported.
+    ///
+    /// `data` must stay unchanged until `zerocopy_pending()` reports the send released.
+    pub fn send_zerocopy(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, UdpResult> {
+        let mut bytes_sent: usize = 0;
+        let result = unsafe {
+            udp_socket_send_zerocopy(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+
+    /// Get the number of zero-copy sends whose data is still in use.
+    pub fn zerocopy_pending(&mut self) -> u32 {
+        unsafe { udp_socket_zerocopy_pending(self.as_ptr()) }
+    }
+
+    /// Run a VMA dummy send of `data` on the connected socket (never put on the wire).
+    pub fn send_dummy(&mut self, data: &[u8]) -> Result<(), UdpResult> {
+        let result = unsafe {
+            udp_socket_send_dummy(self.as_ptr(), data.as_ptr() as *const c_void, data.len())
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Send data to a specified address and port with `sendto(2)` flags.
+    pub fn send_to_with_flags<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16, flags: MsgFlags) -> Result<usize, UdpResult> {
+        let c_addr = CString::new(addr.into()).unwrap();
+        let mut bytes_sent: usize = 0;
+        
+        let result = unsafe {
+            udp_socket_sendto_flags(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                c_addr.as_ptr(),
+                port,
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+
+    /// Send data to a prepared destination address with `sendto(2)` flags.
+    pub fn send_to_addr(&mut self, data: &[u8], addr: &SockAddrIn, flags: MsgFlags) -> Result<usize, UdpResult> {
+        let mut bytes_sent: usize = 0;
+        
+        let result = unsafe {
+            udp_socket_sendto_addr(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                addr,
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+
+    /// Send the datagrams `data[offsets[i]..offsets[i + 1]]` to `addrs[i]` (family 0: the connected peer) with sendmmsg.
+    ///
+    /// On failure the error comes with the number of datagrams sent before it.
+    pub fn send_batch(&mut self, data: &[u8], offsets: &[usize], addrs: &[SockAddrIn], flags: MsgFlags) -> Result<usize, (UdpResult, usize)> {
+        let count = addrs.len();
+        if count == 0 || offsets.len() != count + 1 || offsets[count] > data.len() {
+            return Err((UdpResult::UdpErrorInvalidParam, 0));
+        }
+        let mut sent: usize = 0;
+        
+        let result = unsafe {
+            udp_socket_send_batch(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                offsets.as_ptr(),
+                addrs.as_ptr(),
+                count,
+                flags.bits(),
+                &mut sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err((unsafe { mem::transmute::<i32, UdpResult>(result) }, sent));
+        }
+        
+        Ok(sent)
+    }
+
+    /// Send `data` as datagrams of `segment_size` bytes with UDP_SEGMENT, to `addr` or the connected peer.
+    pub fn send_gso(&mut self, data: &[u8], segment_size: u16, addr: Option<&SockAddrIn>, flags: MsgFlags) -> Result<usize, UdpResult> {
+        let mut bytes_sent: usize = 0;
+        
+        let result = unsafe {
+            udp_socket_send_gso(
+                self.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                segment_size,
+                addr.map_or(ptr::null(), |addr| addr as *const SockAddrIn),
+                flags.bits(),
+                &mut bytes_sent,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_sent)
+    }
+
+    /// Receive data from the connected remote address with `recv(2)` flags.
+    pub fn recv_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<usize, UdpResult> {
+        let mut bytes_received: usize = 0;
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            udp_socket_recv_flags(
+                self.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                flags.bits(),
+                &mut bytes_received,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(bytes_received)
+    }
+
+    /// Receive data and source address information with `recvmsg(2)` flags.
+    pub fn recv_from_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<Packet, UdpResult> {
+        let mut packet = unsafe { mem::zeroed::<UdpPacket>() };
+        let timeout_ms = unixnano_to_ms(timeout_nano);
+        
+        let result = unsafe {
+            udp_socket_recvfrom_flags(
+                self.as_ptr(),
+                &mut packet,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len(),
+                timeout_ms,
+                flags.bits(),
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(Packet::from_c(&packet))
+    }
+
+    /// Get socket statistics.
+    pub fn get_stats(&mut self) -> Result<(u64, u64, u64, u64), UdpResult> {
+        let mut rx_packets: c_ulonglong = 0;
+        let mut tx_packets: c_ulonglong = 0;
+        let mut rx_bytes: c_ulonglong = 0;
+        let mut tx_bytes: c_ulonglong = 0;
+        
+        let result = unsafe {
+            udp_socket_get_stats(
+                self.as_ptr(),
+                &mut rx_packets,
+                &mut tx_packets,
+                &mut rx_bytes,
+                &mut tx_bytes,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok((rx_packets, tx_packets, rx_bytes, tx_bytes))
+    }
+
+    /// Get the full set of socket counters.
+    pub fn stats(&mut self) -> Result<SocketStats, UdpResult> {
+        let mut stats = SocketStats::default();
+        let result = unsafe { udp_socket_get_extended_stats(self.as_ptr(), &mut stats) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(stats)
+    }
+
+    /// Zero the counters, keeping the last receive and send times.
+    pub fn reset_stats(&mut self) -> Result<(), UdpResult> {
+        let result = unsafe { udp_socket_reset_stats(self.as_ptr()) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Allow several sockets to bind the same address and port (SO_REUSEPORT).
+    ///
+    /// Must be set before `bind()`. The kernel (or VMA) spreads incoming flows across
+    /// all sockets bound with this option.
+    pub fn set_reuse_port(&mut self, reuse: bool) -> Result<(), UdpResult> {
+        self.setopt_int(libc::SOL_SOCKET, libc::SO_REUSEPORT, reuse as c_int)
+    }
+
+    /// Check whether SO_REUSEPORT is enabled.
+    pub fn reuse_port(&mut self) -> Result<bool, UdpResult> {
+        self.getopt_int(libc::SOL_SOCKET, libc::SO_REUSEPORT).map(|value| value != 0)
+    }
+
+    /// Offload `flow` through VMA and attach the socket to the ring shared by `ring_id`.
+    ///
+    /// Must be called before `bind()`.
+    pub fn steer_flow(&mut self, flow: &FiveTuple, ring_id: u32) -> Result<(), UdpResult> {
+        let rule = CString::new(flow.to_conf_rule()).unwrap();
+        let result = unsafe { udp_socket_steer_flow(self.as_ptr(), rule.as_ptr(), ring_id) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Set the socket receive buffer size (SO_RCVBUF) in bytes.
+    ///
+    /// Overrides the size applied from `VmaOptions::buffer_size` at creation.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), UdpResult> {
+        let size = c_int::try_from(size).map_err(|_| UdpResult::UdpErrorInvalidParam)?;
+        self.setopt_int(libc::SOL_SOCKET, libc::SO_RCVBUF, size)
+    }
+
+    /// Get the socket receive buffer size (SO_RCVBUF) in bytes.
+    ///
+    /// Linux reports twice the requested size to account for bookkeeping overhead.
+    pub fn recv_buffer_size(&mut self) -> Result<usize, UdpResult> {
+        self.getopt_int(libc::SOL_SOCKET, libc::SO_RCVBUF).map(|size| size as usize)
+    }
+
+    /// Set the socket send buffer size (SO_SNDBUF) in bytes.
+    ///
+    /// Overrides the size applied from `VmaOptions::buffer_size` at creation.
+    pub fn set_send_buffer_size(&mut self, size: usize) -> Result<(), UdpResult> {
+        let size = c_int::try_from(size).map_err(|_| UdpResult::UdpErrorInvalidParam)?;
+        self.setopt_int(libc::SOL_SOCKET, libc::SO_SNDBUF, size)
+    }
+
+    /// Get the socket send buffer size (SO_SNDBUF) in bytes.
+    ///
+    /// Linux reports twice the requested size to account for bookkeeping overhead.
+    pub fn send_buffer_size(&mut self) -> Result<usize, UdpResult> {
+        self.getopt_int(libc::SOL_SOCKET, libc::SO_SNDBUF).map(|size| size as usize)
+    }
+
+    /// Set the time-to-live of outgoing unicast packets (IP_TTL, 1-255).
+    pub fn set_ttl(&mut self, ttl: u32) -> Result<(), UdpResult> {
+        let ttl = c_int::try_from(ttl).map_err(|_| UdpResult::UdpErrorInvalidParam)?;
+        let result = unsafe { udp_socket_set_ttl(self.as_ptr(), ttl) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Get the time-to-live of outgoing unicast packets.
+    pub fn ttl(&mut self) -> Result<u32, UdpResult> {
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_TTL).map(|ttl| ttl as u32)
+    }
+
+    /// Set the type-of-service byte of outgoing packets (IP_TOS).
+    ///
+    /// The DSCP code point goes in the upper 6 bits (`dscp << 2`), ECN in the lower 2.
+    pub fn set_tos(&mut self, tos: u8) -> Result<(), UdpResult> {
+        let result = unsafe { udp_socket_set_tos(self.as_ptr(), tos as c_int) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Get the type-of-service byte of outgoing packets.
+    pub fn tos(&mut self) -> Result<u8, UdpResult> {
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_TOS).map(|tos| tos as u8)
+    }
+
+    /// Set the time-to-live of outgoing multicast packets (IP_MULTICAST_TTL, 0-255).
+    pub fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<(), UdpResult> {
+        let ttl = c_int::try_from(ttl).map_err(|_| UdpResult::UdpErrorInvalidParam)?;
+        let result = unsafe { udp_socket_set_multicast_ttl(self.as_ptr(), ttl) };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Get the time-to-live of outgoing multicast packets.
+    pub fn multicast_ttl_v4(&mut self) -> Result<u32, UdpResult> {
+        // The kernel accepts an int-sized buffer and zero-extends the single-byte value
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_MULTICAST_TTL).map(|ttl| ttl as u32)
+    }
+
+    /// Join a multicast group on the interface with address `interface` (IP_ADD_MEMBERSHIP).
+    ///
+    /// `Ipv4Addr::UNSPECIFIED` lets the kernel pick the interface from the routing table.
+    pub fn join_multicast_v4(&mut self, group: &Ipv4Addr, interface: &Ipv4Addr) -> Result<(), UdpResult> {
+        self.setopt(libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP, &multicast_request(group, interface))
+    }
+
+    /// Leave a multicast group joined with `join_multicast_v4` (IP_DROP_MEMBERSHIP).
+    pub fn leave_multicast_v4(&mut self, group: &Ipv4Addr, interface: &Ipv4Addr) -> Result<(), UdpResult> {
+        self.setopt(libc::IPPROTO_IP, libc::IP_DROP_MEMBERSHIP, &multicast_request(group, interface))
+    }
+
+    /// Deliver datagrams of groups joined by other sockets too (IP_MULTICAST_ALL, on by default).
+    pub fn set_multicast_all_v4(&mut self, all: bool) -> Result<(), UdpResult> {
+        self.setopt_int(libc::IPPROTO_IP, libc::IP_MULTICAST_ALL, all as c_int)
+    }
+
+    /// Check whether IP_MULTICAST_ALL is enabled.
+    pub fn multicast_all_v4(&mut self) -> Result<bool, UdpResult> {
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_MULTICAST_ALL).map(|value| value != 0)
+    }
+
+    /// Report the destination address and interface of received packets (IP_PKTINFO).
+    pub fn set_pktinfo(&mut self, enable: bool) -> Result<(), UdpResult> {
+        self.setopt_int(libc::IPPROTO_IP, libc::IP_PKTINFO, enable as c_int)
+    }
+
+    /// Check whether IP_PKTINFO reporting is enabled.
+    pub fn pktinfo(&mut self) -> Result<bool, UdpResult> {
+        self.getopt_int(libc::IPPROTO_IP, libc::IP_PKTINFO).map(|value| value != 0)
+    }
+
+    /// Set an integer socket option.
+    pub(crate) fn setopt_int(&mut self, level: c_int, optname: c_int, value: c_int) -> Result<(), UdpResult> {
+        self.setopt(level, optname, &value)
+    }
+
+    /// Set a socket option from a plain C value (`c_int`, `libc::timeval`, ...).
+    pub(crate) fn setopt<T: Copy>(&mut self, level: c_int, optname: c_int, value: &T) -> Result<(), UdpResult> {
+        let result = unsafe {
+            udp_socket_setopt(
+                self.as_ptr(),
+                level,
+                optname,
+                value as *const T as *const c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(())
+    }
+
+    /// Get an integer socket option.
+    pub(crate) fn getopt_int(&mut self, level: c_int, optname: c_int) -> Result<c_int, UdpResult> {
+        let mut value: c_int = 0;
+        let mut len = mem::size_of::<c_int>() as libc::socklen_t;
+        let result = unsafe {
+            udp_socket_getopt(
+                self.as_ptr(),
+                level,
+                optname,
+                &mut value as *mut c_int as *mut c_void,
+                &mut len,
+            )
+        };
+        
+        if result != UdpResult::UdpSuccess as i32 {
+            return Err(unsafe { mem::transmute::<i32, UdpResult>(result) });
+        }
+        
+        Ok(value)
+    }
+}
+
+/// Build the `ip_mreq` of a multicast membership change.
+fn multicast_request(group: &Ipv4Addr, interface: &Ipv4Addr) -> libc::ip_mreq {
+    libc::ip_mreq {
+        imr_multiaddr: libc::in_addr { s_addr: u32::from(*group).to_be() },
+        imr_interface: libc::in_addr { s_addr: u32::from(*interface).to_be() },
+    }
+}
+
+/// Check whether a send failed only because the send buffer stayed full.
+fn is_full(e: &Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+impl Drop for UdpSocketWrapper {
+    fn drop(&mut self) {
+        let socket = self.as_ptr();
+        self.tracker.release();
+        unsafe {
+            udp_socket_free(socket);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VmaUdpSocket {
+    inner: UdpSocketWrapper,
+    read_timeout: Option<u64>,
+    write_timeout: Option<u64>,
+    nonblocking: bool,
+    pacer: Option<Pacer>,
+    stats_baseline: SocketStats,
+    burst_buffer: Vec<u8>,
+    max_datagram_size: usize,
+    tx_queue: TxQueue,
+    shutdown: Option<ShutdownToken>,
+    groups: Vec<(Ipv4Addr, Ipv4Addr)>,
+}
+impl VmaUdpSocket {
+    /// Create a new UDP socket with default VMA options.
     pub fn new() -> Result<Self, std::io::Error> {
         UdpSocketWrapper::new(None)
-            .map(|inner| VmaUdpSocket { inner })
+            .map(VmaUdpSocket::from_wrapper)
+            .map_err(|e| e.into())
+    }
+
+    /// Create a new UDP socket with custom VMA options.
+    ///
+    /// The options are checked with [`VmaOptions::validate`] first; a failure lists every
+    /// problem in a [`VmaError::Config`] of kind `ErrorKind::InvalidInput`.
+    pub fn with_options(options: VmaOptions) -> Result<Self, std::io::Error> {
+        options.validate()?;
+        UdpSocketWrapper::new(Some(options))
+            .map(VmaUdpSocket::from_wrapper)
+            .map_err(|e| e.into())
+    }
+
+    /// Take over an IPv4 UDP socket created elsewhere, e.g. configured through `socket2`.
+    ///
+    /// Options already set on the descriptor are kept, and the bound and connected
+    /// addresses are read back from it; `options` picks the wait strategy of receives. A
+    /// descriptor of another kind fails with `ErrorKind::InvalidInput`, and the error hands
+    /// it back open.
+    ///
+    /// Whether VMA offloads the socket is decided when it is created, so create it with
+    /// libvma preloaded to keep the acceleration.
+    pub fn from_fd(fd: OwnedFd, options: Option<VmaOptions>) -> Result<Self, FromFdError> {
+        let raw = fd.into_raw_fd();
+        match UdpSocketWrapper::from_raw_fd(raw, options) {
+            Ok(inner) => Ok(VmaUdpSocket::from_wrapper(inner)),
+            Err(e) => Err(FromFdError::new(unsafe { OwnedFd::from_raw_fd(raw) }, e.into())),
+        }
+    }
+
+    fn from_wrapper(inner: UdpSocketWrapper) -> Self {
+        VmaUdpSocket {
+            inner,
+            read_timeout: None,
+            write_timeout: None,
+            nonblocking: false,
+            pacer: None,
+            stats_baseline: SocketStats::default(),
+            burst_buffer: Vec::new(),
+            max_datagram_size: MAX_DATAGRAM_SIZE,
+            tx_queue: TxQueue::default(),
+            shutdown: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Pace all sends with `pacer` (`None` sends as fast as the socket takes them).
+    ///
+    /// Sends wait for their turn; `*_with_flags` sends with `MsgFlags::DONTWAIT` fail with
+    /// `ErrorKind::WouldBlock` instead of waiting. Dummy sends (`warm()`) are not paced.
+    pub fn set_pacer(&mut self, pacer: Option<Pacer>) {
+        self.pacer = pacer;
+    }
+
+    /// Get the attached pacer.
+    pub fn pacer(&self) -> Option<&Pacer> {
+        self.pacer.as_ref()
+    }
+
+    /// Wait for the pacer's turn to send `len` bytes (or check it, without waiting).
+    fn pace(&mut self, len: usize, flags: MsgFlags) -> Result<(), std::io::Error> {
+        let Some(pacer) = self.pacer.as_mut() else { return Ok(()) };
+        if flags.contains(MsgFlags::DONTWAIT) {
+            if !pacer.try_acquire(len) {
+                return Err(Error::new(ErrorKind::WouldBlock, "Send would exceed the pacing rate"));
+            }
+        } else {
+            pacer.acquire(len);
+        }
+        Ok(())
+    }
+
+    /// Set the largest datagram the send calls accept, e.g. the path MTU payload (1472 for
+    /// a 1500-byte MTU) to refuse datagrams that would be fragmented.
+    ///
+    /// Larger sends fail before reaching the socket with an `InvalidInput` error carrying
+    /// [`VmaError::DatagramTooLarge`]. Defaults to [`MAX_DATAGRAM_SIZE`].
+    pub fn set_max_datagram_size(&mut self, max: usize) -> Result<(), std::io::Error> {
+        if max == 0 || max > MAX_DATAGRAM_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Maximum datagram size must be between 1 and {}", MAX_DATAGRAM_SIZE),
+            ));
+        }
+        self.max_datagram_size = max;
+        Ok(())
+    }
+
+    /// Get the largest datagram the send calls accept.
+    pub fn max_datagram_size(&self) -> usize {
+        self.max_datagram_size
+    }
+
+    /// Refuse a datagram larger than the configured maximum.
+    fn check_datagram_len(&self, len: usize) -> Result<(), std::io::Error> {
+        if len > self.max_datagram_size {
+            return Err(VmaError::DatagramTooLarge { len, max: self.max_datagram_size }.into());
+        }
+        Ok(())
+    }
+
+    /// Set the timeout used by receive calls that pass `None` (`None` waits indefinitely).
+    ///
+    /// A timeout passed to a call still takes precedence, so `set_read_timeout(Some(100_000_000))`
+    /// spares passing `Some(100_000_000)` to every `recv()`.
+    pub fn set_read_timeout(&mut self, timeout_nano: Option<u64>) {
+        self.read_timeout = timeout_nano;
+    }
+
+    /// Get the default receive timeout in nanoseconds.
+    pub fn read_timeout(&self) -> Option<u64> {
+        self.read_timeout
+    }
+
+    /// Switch the socket between blocking and non-blocking mode.
+    ///
+    /// In non-blocking mode receives ignore their timeout and the read timeout and return
+    /// at once, failing with `ErrorKind::WouldBlock` when no datagram is queued as the std
+    /// sockets do, and every send adds `MsgFlags::DONTWAIT`. The mode belongs to this object rather than the descriptor's
+    /// O_NONBLOCK flag, so it behaves the same with and without `use_polling`.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Check whether the socket is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    /// Get the timeout of a receive call: none at all in non-blocking mode.
+    fn recv_timeout(&self, timeout_nano: Option<u64>) -> Option<u64> {
+        if self.nonblocking {
+            Some(0)
+        } else {
+            timeout_nano.or(self.read_timeout)
+        }
+    }
+
+    /// Observe `token` in receives (`None` stops observing one).
+    ///
+    /// Receives then wait in slices of at most `SHUTDOWN_POLL_NS` and fail with
+    /// `ErrorKind::Interrupted` once the token is triggered, even without a timeout.
+    pub fn set_shutdown_token(&mut self, token: Option<ShutdownToken>) {
+        self.shutdown = token;
+    }
+
+    /// Get the shutdown token observed by receives, if any.
+    pub fn shutdown_token(&self) -> Option<&ShutdownToken> {
+        self.shutdown.as_ref()
+    }
+
+    /// Run the receive `op` with the timeout of the call, observing the shutdown token.
+    ///
+    /// Finding no datagram in non-blocking mode fails with `ErrorKind::WouldBlock`.
+    fn wait_recv<T, F>(&mut self, timeout_nano: Option<u64>, mut op: F) -> Result<Result<T, UdpResult>, std::io::Error>
+    where
+        F: FnMut(&mut UdpSocketWrapper, Option<u64>) -> Result<T, UdpResult>,
+    {
+        let timeout_nano = self.recv_timeout(timeout_nano);
+        let inner = &mut self.inner;
+        match shutdown::sliced(self.shutdown.as_ref(), timeout_nano, |e| *e == UdpResult::UdpErrorTimeout, |slice| op(inner, slice))? {
+            Err(UdpResult::UdpErrorTimeout) if self.nonblocking => Err(Error::new(ErrorKind::WouldBlock, "No datagram queued")),
+            result => Ok(result),
+        }
+    }
+
+    /// Get the flags of a send call, adding `DONTWAIT` in non-blocking mode.
+    fn send_flags(&self, flags: MsgFlags) -> MsgFlags {
+        if self.nonblocking {
+            flags | MsgFlags::DONTWAIT
+        } else {
+            flags
+        }
+    }
+
+    /// Convert the error of a send made with `flags`.
+    ///
+    /// A full send buffer is `ErrorKind::TimedOut` when the call waited for the write
+    /// timeout, and `ErrorKind::WouldBlock` when it could not wait.
+    fn send_error(&self, e: UdpResult, flags: MsgFlags) -> Error {
+        match e {
+            UdpResult::UdpErrorTimeout if !self.send_waited(flags) => Error::new(ErrorKind::WouldBlock, "Send would block"),
+            e => e.into(),
+        }
+    }
+
+    /// Check whether a send with `flags` waits for the write timeout when the buffer is full:
+    /// not with `DONTWAIT`, without a timeout or on an O_NONBLOCK descriptor (`use_polling`).
+    fn send_waited(&self, flags: MsgFlags) -> bool {
+        if flags.contains(MsgFlags::DONTWAIT) || self.write_timeout.is_none() {
+            return false;
+        }
+        let status = unsafe { libc::fcntl(self.inner.fd(), libc::F_GETFL) };
+        status >= 0 && status & libc::O_NONBLOCK == 0
+    }
+
+    /// Set how long a send may block (SO_SNDTIMEO, `None` blocks indefinitely).
+    ///
+    /// Every send call fails with `ErrorKind::TimedOut` when the timeout expires, while a
+    /// send that may not wait (`MsgFlags::DONTWAIT`, non-blocking mode) and finds the send
+    /// buffer full fails with `ErrorKind::WouldBlock`. A zero timeout is rejected with
+    /// `ErrorKind::InvalidInput`; use `MsgFlags::DONTWAIT` instead.
+    ///
+    /// Sockets created with `use_polling` are non-blocking and never wait in a send, so the
+    /// timeout only matters without it; their full send buffer is always `WouldBlock`.
+    pub fn set_write_timeout(&mut self, timeout_nano: Option<u64>) -> Result<(), std::io::Error> {
+        if timeout_nano == Some(0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "Write timeout must be non-zero"));
+        }
+        self.inner.setopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO, &common::unixnano_to_timeval(timeout_nano))?;
+        self.write_timeout = timeout_nano;
+        Ok(())
+    }
+
+    /// Get the send timeout in nanoseconds.
+    pub fn write_timeout(&self) -> Option<u64> {
+        self.write_timeout
+    }
+
+    /// Bind the socket to a local address and port.
+    pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
+        self.inner
+            .bind(addr, port)
             .map_err(|e| e.into())
     }
 
-    /// Create a new UDP socket with custom VMA options.
-    pub fn with_options(options: VmaOptions) -> Result<Self, std::io::Error> {
-        UdpSocketWrapper::new(Some(options))
-            .map(|inner| VmaUdpSocket { inner })
-            .map_err(|e| e.into())
+    /// Connect the socket to a remote address and port.
+    ///
+    /// Calling it again switches the association to the new peer.
+    pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
+        self.inner
+            .connect(addr, port)
+            .map_err(|e| e.into())
+    }
+
+    /// Dissolve the association set by `connect()`.
+    ///
+    /// Datagrams from any sender are received again and `send()` needs a new `connect()`;
+    /// the local address, options and rings are kept. A port picked by binding to port 0
+    /// is released by the kernel on disconnect and bound again; if another socket took it
+    /// in between, this fails with the bind error and the socket is left unbound.
+    pub fn disconnect(&mut self) -> Result<(), std::io::Error> {
+        self.inner
+            .disconnect()
+            .map_err(|e| e.into())
+    }
+
+    /// Move the socket to a new local address and port.
+    ///
+    /// A socket without a local address yet is bound in place and keeps its descriptor.
+    /// A bound descriptor cannot be bound again, so otherwise the descriptor is replaced:
+    /// the C layer opens a new one with the same VMA options, socket options (buffer
+    /// sizes, timeouts, TTL/TOS, reuse flags, multicast settings, pktinfo), association
+    /// and the multicast groups joined with [`join_multicast_v4`](Self::join_multicast_v4),
+    /// and swaps it in only once all of that succeeded; on error the socket is unchanged.
+    ///
+    /// Replacing the descriptor is not free: VMA releases the old socket's ring attachment
+    /// and buffers and offloads the new one from scratch, so the first receives after a
+    /// rebind pay the ring setup again, and [`fd()`](Self::fd) changes. Stats, pacer and
+    /// timeouts are kept. Groups joined on the descriptor before [`from_fd`](Self::from_fd)
+    /// are not known to the socket and not carried over.
+    pub fn rebind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
+        let groups: Vec<libc::ip_mreq> = self
+            .groups
+            .iter()
+            .map(|(group, interface)| multicast_request(group, interface))
+            .collect();
+        self.inner
+            .rebind(addr, port, &groups)
+            .map_err(|e| e.into())
+    }
+
+    /// Send data to the connected remote address.
+    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        if self.nonblocking {
+            return self.send_with_flags(data, MsgFlags::DONTWAIT);
+        }
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), MsgFlags::NONE)?;
+        self.inner
+            .send(data)
+            .map_err(|e| self.send_error(e, MsgFlags::NONE))
+    }
+
+    /// Send data to a specified address and port.
+    pub fn send_to<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16) -> Result<usize, std::io::Error> {
+        if self.nonblocking {
+            return self.send_to_with_flags(data, addr, port, MsgFlags::DONTWAIT);
+        }
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), MsgFlags::NONE)?;
+        self.inner
+            .send_to(data, addr, port)
+            .map_err(|e| self.send_error(e, MsgFlags::NONE))
+    }
+
+    /// Prepare `addr:port` (an IPv4 address) as a [`Destination`] for `send_to_dest()`.
+    pub fn resolve(&self, addr: &str, port: u16) -> Result<Destination, std::io::Error> {
+        let ip = addr
+            .parse::<Ipv4Addr>()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("Invalid IPv4 address: {}", addr)))?;
+        Ok(Destination::new(SocketAddrV4::new(ip, port)))
+    }
+
+    /// Send data to a destination prepared by `resolve()`.
+    pub fn send_to_dest(&mut self, data: &[u8], dest: &Destination) -> Result<usize, std::io::Error> {
+        if self.nonblocking {
+            return self.send_to_dest_with_flags(data, dest, MsgFlags::DONTWAIT);
+        }
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), MsgFlags::NONE)?;
+        self.inner
+            .send_to_addr(data, &dest.c_addr, MsgFlags::NONE)
+            .map_err(|e| self.send_error(e, MsgFlags::NONE))
+    }
+
+    /// Queue `data` for the connected remote address, to be sent by [`flush`](Self::flush).
+    ///
+    /// Queued datagrams go out together with `sendmmsg(2)`, trading the latency of each
+    /// message for fewer calls into the stack. A full queue ([`SEND_BATCH_MAX`] datagrams)
+    /// is flushed first. The pacer, if any, is charged when a datagram is queued.
+    /// Datagrams still queued when the socket is dropped are discarded.
+    pub fn queue_send(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        self.queue(data, unsafe { mem::zeroed::<SockAddrIn>() })
+    }
+
+    /// Queue `data` for a destination prepared by `resolve()`, to be sent by [`flush`](Self::flush).
+    pub fn queue_send_to_dest(&mut self, data: &[u8], dest: &Destination) -> Result<(), std::io::Error> {
+        self.queue(data, dest.c_addr.clone())
+    }
+
+    fn queue(&mut self, data: &[u8], addr: SockAddrIn) -> Result<(), std::io::Error> {
+        if data.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Cannot queue an empty datagram"));
+        }
+        self.check_datagram_len(data.len())?;
+        if self.tx_queue.len() == SEND_BATCH_MAX {
+            self.flush()?;
+        }
+        self.pace(data.len(), self.send_flags(MsgFlags::NONE))?;
+        self.tx_queue.push(data, addr);
+        Ok(())
+    }
+
+    /// Get the number of datagrams queued by `queue_send()` and not sent yet.
+    pub fn queued(&self) -> usize {
+        self.tx_queue.len()
+    }
+
+    /// Send the queued datagrams, returning how many were sent.
+    ///
+    /// If the send buffer fills up, fails with `ErrorKind::WouldBlock` in non-blocking mode
+    /// or `ErrorKind::TimedOut` once the write timeout expires, and keeps the unsent
+    /// datagrams queued. A datagram the socket refuses otherwise is
+    /// dropped and its error returned; the datagrams after it stay queued.
+    pub fn flush(&mut self) -> Result<usize, std::io::Error> {
+        if self.tx_queue.len() == 0 {
+            return Ok(0);
+        }
+        let flags = self.send_flags(MsgFlags::NONE);
+        let queue = &self.tx_queue;
+        match self.inner.send_batch(&queue.data, &queue.offsets, &queue.addrs, flags) {
+            Ok(sent) => {
+                self.tx_queue.consume(sent);
+                Ok(sent)
+            }
+            Err((UdpResult::UdpErrorTimeout, sent)) => {
+                self.tx_queue.consume(sent);
+                Err(self.send_error(UdpResult::UdpErrorTimeout, flags))
+            }
+            Err((e, sent)) => {
+                self.tx_queue.consume(sent + 1);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Send the queued datagrams and wait up to `timeout` for the send queue to empty, then close.
+    ///
+    /// Returns `Ok(true)` if every datagram left the socket in time. Datagrams still queued
+    /// at the deadline are discarded; a datagram the socket refuses fails the close with
+    /// its error.
+    pub fn close_graceful(mut self, timeout: Duration) -> Result<bool, std::io::Error> {
+        let deadline = Instant::now() + timeout;
+        while self.queued() > 0 {
+            match self.flush() {
+                Ok(_) => {}
+                Err(e) if is_full(&e) && Instant::now() < deadline => {
+                    std::thread::sleep(DRAIN_POLL_INTERVAL);
+                }
+                Err(e) if is_full(&e) => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        loop {
+            match self.pending_tx_bytes() {
+                Ok(0) | Err(_) => return Ok(true), // nothing left, or no way to tell
+                Ok(_) if Instant::now() >= deadline => return Ok(false),
+                Ok(_) => std::thread::sleep(DRAIN_POLL_INTERVAL),
+            }
+        }
+    }
+
+    /// Close the socket, reporting a failed `close(2)` that dropping it would ignore.
+    ///
+    /// Datagrams still queued are discarded, as on drop; use `close_graceful()` to send
+    /// them first. The descriptor is released even when an error is returned.
+    pub fn close(mut self) -> Result<(), VmaError> {
+        let fd = self.inner.fd();
+        if fd < 0 {
+            return Ok(());
+        }
+        match self.inner.close() {
+            Err(UdpResult::UdpErrorClose) => {
+                let errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+                Err(VmaError::CloseFailed { fd, errno })
+            }
+            _ => Ok(()),
+        }
     }
 
-    /// Bind the socket to a local address and port.
-    pub fn bind<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
-        self.inner
-            .bind(addr, port)
-            .map_err(|e| e.into())
+    /// Send `payload` to the connected remote address as datagrams of `segment_size` bytes.
+    ///
+    /// Uses UDP segmentation offload (UDP_SEGMENT): one call emits up to
+    /// [`GSO_MAX_SEGMENTS`] datagrams, the last one holding the remainder, with a single
+    /// traversal of the stack. Falls back to one send per segment where GSO is not
+    /// supported. The pacer, if any, is charged for every segment.
+    ///
+    /// Like `send_to()`, fails with `ErrorKind::WouldBlock` in non-blocking mode when the
+    /// send buffer (or the pacer) has no room, and with `ErrorKind::TimedOut` when the
+    /// write timeout expires.
+    pub fn send_gso(&mut self, payload: &[u8], segment_size: usize) -> Result<usize, std::io::Error> {
+        self.send_gso_inner(payload, segment_size, None)
     }
 
-    /// Connect the socket to a remote address and port.
-    pub fn connect<A: Into<String>>(&mut self, addr: A, port: u16) -> Result<(), std::io::Error> {
-        self.inner
-            .connect(addr, port)
-            .map_err(|e| e.into())
+    /// Send `payload` to a destination prepared by `resolve()` as datagrams of `segment_size` bytes.
+    ///
+    /// Reports a full send buffer and the write timeout as `send_gso()` does.
+    pub fn send_gso_to_dest(&mut self, payload: &[u8], segment_size: usize, dest: &Destination) -> Result<usize, std::io::Error> {
+        self.send_gso_inner(payload, segment_size, Some(&dest.c_addr))
     }
 
-    /// Send data to the connected remote address.
-    pub fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+    fn send_gso_inner(&mut self, payload: &[u8], segment_size: usize, addr: Option<&SockAddrIn>) -> Result<usize, std::io::Error> {
+        let flags = self.send_flags(MsgFlags::NONE);
+        let segment_size = self.gso_segments(payload, segment_size, flags)?;
         self.inner
-            .send(data)
-            .map_err(|e| e.into())
+            .send_gso(payload, segment_size, addr, flags)
+            .map_err(|e| self.send_error(e, flags))
     }
 
-    /// Send data to a specified address and port.
-    pub fn send_to<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16) -> Result<usize, std::io::Error> {
+    /// Check a GSO send and pace its segments, returning the segment size for C.
+    ///
+    /// Without waiting (`DONTWAIT`), the pacer is checked for the whole payload at once so
+    /// a refused send does not use up part of the budget.
+    fn gso_segments(&mut self, payload: &[u8], segment_size: usize, flags: MsgFlags) -> Result<u16, std::io::Error> {
+        self.check_datagram_len(segment_size)?;
+        let size = u16::try_from(segment_size)
+            .ok()
+            .filter(|&size| size > 0)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "segment_size must be between 1 and 65535"))?;
+        if payload.is_empty() || payload.len().div_ceil(segment_size) > GSO_MAX_SEGMENTS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("payload must be 1 to {} segments of {} bytes", GSO_MAX_SEGMENTS, segment_size),
+            ));
+        }
+        if flags.contains(MsgFlags::DONTWAIT) {
+            self.pace(payload.len(), flags)?;
+        } else {
+            for segment in payload.chunks(segment_size) {
+                self.pace(segment.len(), flags)?;
+            }
+        }
+        Ok(size)
+    }
+
+    /// Send data to a destination prepared by `resolve()` with `sendto(2)` flags.
+    pub fn send_to_dest_with_flags(&mut self, data: &[u8], dest: &Destination, flags: MsgFlags) -> Result<usize, std::io::Error> {
+        let flags = self.send_flags(flags);
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), flags)?;
         self.inner
-            .send_to(data, addr, port)
-            .map_err(|e| e.into())
+            .send_to_addr(data, &dest.c_addr, flags)
+            .map_err(|e| self.send_error(e, flags))
     }
 
     /// Receive data from the connected remote address.
     pub fn recv(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, std::io::Error> {
-        match self.inner.recv(buffer, timeout_nano) {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.recv(buffer, timeout))? {
             Ok(bytes) => Ok(bytes),
             Err(UdpResult::UdpErrorTimeout) => Ok(0), // timeout is not an error
             Err(e) => Err(e.into()),
         }
     }
 
+    /// Receive data, reporting the full datagram length so truncation can be detected.
+    ///
+    /// Bytes beyond the buffer are discarded by the kernel; `RecvLength::is_truncated()`
+    /// tells when that happened. Returns `Ok(None)` on timeout.
+    pub fn recv_full(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<RecvLength>, std::io::Error> {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.recv_full(buffer, timeout))? {
+            Ok(length) => Ok(Some(length)),
+            Err(UdpResult::UdpErrorTimeout) => Ok(None), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Receive data and source address information.
     pub fn recv_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, std::io::Error> {
-        match self.inner.recv_from(buffer, timeout_nano) {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.recv_from(buffer, timeout))? {
+            Ok(packet) => Ok(Some(packet)),
+            Err(UdpResult::UdpErrorTimeout) => Ok(None), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Receive up to `max` datagrams (at most [`RECV_BURST_MAX`]) with one `recvmmsg(2)` call.
+    ///
+    /// Waits up to `timeout_nano` for the first datagram, then takes whatever else is
+    /// already queued without waiting. Returns an empty `Vec` on timeout. Datagrams larger
+    /// than [`RECV_BURST_SLOT_SIZE`] are truncated (see [`Packet::is_truncated`]).
+    pub fn recv_burst(&mut self, max: usize, timeout_nano: Option<u64>) -> Result<Vec<Packet>, std::io::Error> {
+        if max == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "max must be at least 1"));
+        }
+        let size = max.min(RECV_BURST_MAX) * RECV_BURST_SLOT_SIZE;
+        if self.burst_buffer.len() < size {
+            self.burst_buffer.resize(size, 0);
+        }
+        let mut buffer = mem::take(&mut self.burst_buffer);
+        let result = self.wait_recv(timeout_nano, |inner, timeout| {
+            inner.recv_from_burst(&mut buffer[..size], RECV_BURST_SLOT_SIZE, timeout)
+        });
+        self.burst_buffer = buffer;
+        match result? {
+            Ok(packets) => Ok(packets),
+            Err(UdpResult::UdpErrorTimeout) => Ok(Vec::new()), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Copy the next datagram into `buffer` without consuming it.
+    ///
+    /// A following `recv()` returns the same datagram. Peeked datagrams are not counted in `stats()`.
+    pub fn peek(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<usize, std::io::Error> {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.peek(buffer, timeout))? {
+            Ok(bytes) => Ok(bytes),
+            Err(UdpResult::UdpErrorTimeout) => Ok(0), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Peek at the next datagram and its source address without consuming it.
+    pub fn peek_from(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>) -> Result<Option<Packet>, std::io::Error> {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.peek_from(buffer, timeout))? {
+            Ok(packet) => Ok(Some(packet)),
+            Err(UdpResult::UdpErrorTimeout) => Ok(None), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Send data to the connected remote address with `send(2)` flags.
+    ///
+    /// With `MsgFlags::DONTWAIT` a full send queue fails with `ErrorKind::WouldBlock`
+    /// instead of blocking.
+    pub fn send_with_flags(&mut self, data: &[u8], flags: MsgFlags) -> Result<usize, std::io::Error> {
+        let flags = self.send_flags(flags);
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), flags)?;
+        self.inner
+            .send_with_flags(data, flags)
+            .map_err(|e| self.send_error(e, flags))
+    }
+
+    /// Signal an eventfd whenever the socket becomes readable, for host event loops.
+    ///
+    /// See [`ReadyNotifier`](crate::notify::ReadyNotifier); the socket is used through the
+    /// returned guard, which stops the notifier when dropped.
+    pub fn ready_notifier(&mut self) -> Result<Notified<'_, Self>, std::io::Error> {
+        Notified::new(self)
+    }
+
+    /// Wait up to `timeout_nano` for a datagram to receive (`Some(0)` only checks).
+    ///
+    /// Follows the socket's wait strategy but ignores the read timeout and non-blocking
+    /// mode, and consumes nothing, so readiness can be detected apart from the receive.
+    pub fn poll_readable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        self.inner.poll_readable(timeout_nano)
+    }
+
+    /// Wait up to `timeout_nano` for room in the send buffer (`Some(0)` only checks).
+    pub fn poll_writable(&self, timeout_nano: Option<u64>) -> Result<bool, std::io::Error> {
+        common::wait_writable(self.inner.fd(), timeout_nano)
+    }
+
+    /// Get the number of bytes queued in the send buffer and not yet transmitted.
+    pub fn pending_tx_bytes(&self) -> Result<usize, std::io::Error> {
+        common::pending_tx_bytes(self.inner.fd())
+    }
+
+    /// Send `range` of a registered buffer to the connected remote address without copying it.
+    ///
+    /// Uses MSG_ZEROCOPY (falling back to a regular copy when unsupported). The range must
+    /// not be modified until [`zerocopy_pending`](Self::zerocopy_pending) drops to zero.
+    /// Reports a full send buffer like `send()`, also in non-blocking mode.
+    pub fn send_registered(&mut self, buffer: &RegisteredBuffer, range: Range<usize>) -> Result<usize, std::io::Error> {
+        let data = buffer.get(range)?;
+        let flags = self.send_flags(MsgFlags::NONE);
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), flags)?;
+        self.inner
+            .send_zerocopy(data, flags)
+            .map_err(|e| self.send_error(e, flags))
+    }
+
+    /// Get the number of `send_registered()` calls whose data is still in use by the stack.
+    pub fn zerocopy_pending(&mut self) -> u32 {
+        self.inner.zerocopy_pending()
+    }
+
+    /// Keep the VMA TX path and caches hot by running `data` through it without sending it.
+    ///
+    /// Issues a VMA dummy send (`VMA_SND_FLAGS_DUMMY`) to the connected remote address;
+    /// call it between real sends, or use [`crate::warm::Warmer`] to do so periodically.
+    /// Fails with `ErrorKind::Unsupported` when the socket is not offloaded by VMA, since
+    /// the kernel would transmit the packet.
+    pub fn warm(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        match self.inner.send_dummy(data) {
+            Ok(()) => Ok(()),
+            Err(UdpResult::UdpErrorTimeout) => Ok(()), // TX queue full; the path is busy anyway
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Send data to a specified address and port with `sendto(2)` flags.
+    pub fn send_to_with_flags<A: Into<String>>(&mut self, data: &[u8], addr: A, port: u16, flags: MsgFlags) -> Result<usize, std::io::Error> {
+        let flags = self.send_flags(flags);
+        self.check_datagram_len(data.len())?;
+        self.pace(data.len(), flags)?;
+        self.inner
+            .send_to_with_flags(data, addr, port, flags)
+            .map_err(|e| self.send_error(e, flags))
+    }
+
+    /// Receive data from the connected remote address with `recv(2)` flags.
+    ///
+    /// `MsgFlags::DONTWAIT` ignores the timeout and returns immediately.
+    pub fn recv_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<usize, std::io::Error> {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.recv_with_flags(buffer, timeout, flags))? {
+            Ok(bytes) => Ok(bytes),
+            Err(UdpResult::UdpErrorTimeout) => Ok(0), // timeout is not an error
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Receive data and source address information with `recvmsg(2)` flags.
+    pub fn recv_from_with_flags(&mut self, buffer: &mut [u8], timeout_nano: Option<u64>, flags: MsgFlags) -> Result<Option<Packet>, std::io::Error> {
+        match self.wait_recv(timeout_nano, |inner, timeout| inner.recv_from_with_flags(buffer, timeout, flags))? {
             Ok(packet) => Ok(Some(packet)),
             Err(UdpResult::UdpErrorTimeout) => Ok(None), // timeout is not an error
             Err(e) => Err(e.into()),
@@ -465,4 +1987,549 @@ impl VmaUdpSocket {
             .get_stats()
             .map_err(|e| e.into())
     }
+
+    /// Get the full set of socket counters (errors, truncations, EAGAIN counts, last activity).
+    pub fn stats(&mut self) -> Result<SocketStats, std::io::Error> {
+        self.inner
+            .stats()
+            .map_err(|e| e.into())
+    }
+
+    /// Zero the socket counters; the last receive and send times are kept.
+    pub fn reset_stats(&mut self) -> Result<(), std::io::Error> {
+        self.inner.reset_stats()?;
+        self.stats_baseline = SocketStats::default();
+        Ok(())
+    }
+
+    /// Get the counters accumulated since the previous call (or creation, or `reset_stats()`).
+    ///
+    /// Suited to periodic reporters, which need not keep the previous values themselves.
+    pub fn stats_delta(&mut self) -> Result<SocketStats, std::io::Error> {
+        let stats = self.stats()?;
+        let delta = stats.delta_since(&self.stats_baseline);
+        self.stats_baseline = stats;
+        Ok(delta)
+    }
+
+    /// Get the file descriptor of the socket, e.g. to register it with epoll.
+    pub fn fd(&self) -> c_int {
+        self.inner.fd()
+    }
+
+    /// Get the local address the socket is bound to (resolves port 0 to the assigned port).
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        common::local_addr(self.inner.fd())
+    }
+
+    /// Get the default destination set by `connect()`, if any.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer().map(|addr| sockaddr_to_rust(&addr))
+    }
+
+    /// Get the VMA offload status of this socket.
+    ///
+    /// `offloaded == false` while `vma_loaded == true` means VMA is running but this
+    /// socket's traffic is falling back to the OS path. Ring and CQ counters are not
+    /// available this way, see [`VmaFdStats`].
+    pub fn vma_fd_stats(&self) -> Result<VmaFdStats, std::io::Error> {
+        common::fd_stats(self.inner.fd())
+    }
+    
+    /// Get the descriptors of the VMA rings backing this socket, to wait on in an external event loop.
+    ///
+    /// A ring descriptor becomes readable when its completion queue has work; add it to
+    /// your own epoll set and call `recv_from` with a zero timeout when it fires. The
+    /// descriptors belong to VMA and may be shared with other sockets on the same ring.
+    /// Empty when the socket is not offloaded; fails with `ErrorKind::Unsupported`
+    /// without libvma.
+    pub fn ring_fds(&self) -> Result<Vec<BorrowedFd<'_>>, std::io::Error> {
+        let fds = common::ring_fds(self.inner.fd())?;
+        Ok(fds.into_iter().map(|fd| unsafe { BorrowedFd::borrow_raw(fd) }).collect())
+    }
+
+    /// Write VMA's internal statistics for this socket (as shown by `vma_stats`) to the VMA log.
+    pub fn dump_vma_stats(&self) -> Result<(), std::io::Error> {
+        common::dump_fd_stats(self.inner.fd())
+    }
+
+    /// Collect the fd, addresses, state, options in effect, counters and VMA offload status.
+    ///
+    /// Print it with `{}` for a report to attach to a support ticket.
+    pub fn diagnostics(&mut self) -> SocketDiagnostics {
+        let local_addr = self.local_addr().ok().filter(|addr| addr.port() != 0);
+        let peer_addr = self.peer_addr();
+        let state = match (local_addr, peer_addr) {
+            (_, Some(_)) => "connected",
+            (Some(_), None) => "bound",
+            (None, None) => "unbound",
+        };
+
+        let mut options = vec![
+            ("nonblocking", self.nonblocking.to_string()),
+            ("read_timeout", common::timeout_option(self.read_timeout)),
+            ("write_timeout", common::timeout_option(self.write_timeout)),
+            ("max_datagram_size", self.max_datagram_size.to_string()),
+            ("pacing", self.pacer.is_some().to_string()),
+            ("queued", self.tx_queue.len().to_string()),
+        ];
+        let readable: [(&'static str, Result<String, std::io::Error>); 6] = [
+            ("recv_buffer_size", self.recv_buffer_size().map(|v| v.to_string())),
+            ("send_buffer_size", self.send_buffer_size().map(|v| v.to_string())),
+            ("ttl", self.ttl().map(|v| v.to_string())),
+            ("tos", self.tos().map(|v| v.to_string())),
+            ("reuse_port", self.reuse_port().map(|v| v.to_string())),
+            ("pktinfo", self.pktinfo().map(|v| v.to_string())),
+        ];
+        options.extend(readable.into_iter().filter_map(|(name, value)| Some((name, value.ok()?))));
+
+        SocketDiagnostics {
+            protocol: Protocol::Udp,
+            fd: self.fd(),
+            local_addr,
+            peer_addr,
+            state: state.to_string(),
+            options,
+            stats: self.stats().ok(),
+            vma: self.vma_fd_stats().ok(),
+        }
+    }
+
+    /// Set the socket receive buffer size (SO_RCVBUF) in bytes.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), std::io::Error> {
+        self.inner
+            .set_recv_buffer_size(size)
+            .map_err(|e| e.into())
+    }
+
+    /// Get the socket receive buffer size (SO_RCVBUF) in bytes, as reported by the kernel.
+    pub fn recv_buffer_size(&mut self) -> Result<usize, std::io::Error> {
+        self.inner
+            .recv_buffer_size()
+            .map_err(|e| e.into())
+    }
+
+    /// Set the socket send buffer size (SO_SNDBUF) in bytes.
+    pub fn set_send_buffer_size(&mut self, size: usize) -> Result<(), std::io::Error> {
+        self.inner
+            .set_send_buffer_size(size)
+            .map_err(|e| e.into())
+    }
+
+    /// Get the socket send buffer size (SO_SNDBUF) in bytes, as reported by the kernel.
+    pub fn send_buffer_size(&mut self) -> Result<usize, std::io::Error> {
+        self.inner
+            .send_buffer_size()
+            .map_err(|e| e.into())
+    }
+
+    /// Set the time-to-live of outgoing unicast packets (IP_TTL, 1-255).
+    pub fn set_ttl(&mut self, ttl: u32) -> Result<(), std::io::Error> {
+        self.inner
+            .set_ttl(ttl)
+            .map_err(|e| e.into())
+    }
+
+    /// Get the time-to-live of outgoing unicast packets.
+    pub fn ttl(&mut self) -> Result<u32, std::io::Error> {
+        self.inner
+            .ttl()
+            .map_err(|e| e.into())
+    }
+
+    /// Set the type-of-service byte of outgoing packets (IP_TOS), e.g. `46 << 2` for DSCP EF.
+    pub fn set_tos(&mut self, tos: u8) -> Result<(), std::io::Error> {
+        self.inner
+            .set_tos(tos)
+            .map_err(|e| e.into())
+    }
+
+    /// Get the type-of-service byte of outgoing packets.
+    pub fn tos(&mut self) -> Result<u8, std::io::Error> {
+        self.inner
+            .tos()
+            .map_err(|e| e.into())
+    }
+
+    /// Set the time-to-live of outgoing multicast packets (IP_MULTICAST_TTL, 0-255).
+    pub fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<(), std::io::Error> {
+        self.inner
+            .set_multicast_ttl_v4(ttl)
+            .map_err(|e| e.into())
+    }
+
+    /// Get the time-to-live of outgoing multicast packets.
+    pub fn multicast_ttl_v4(&mut self) -> Result<u32, std::io::Error> {
+        self.inner
+            .multicast_ttl_v4()
+            .map_err(|e| e.into())
+    }
+
+    /// Join a multicast group on the interface with address `interface` (IP_ADD_MEMBERSHIP).
+    ///
+    /// Bind to the group port (on `0.0.0.0` or the group address) to receive its datagrams;
+    /// with VMA the group is offloaded when the interface is a VMA-capable NIC.
+    ///
+    /// The membership is remembered and joined again by [`rebind`](Self::rebind).
+    pub fn join_multicast_v4(&mut self, group: &Ipv4Addr, interface: &Ipv4Addr) -> Result<(), std::io::Error> {
+        self.inner.join_multicast_v4(group, interface)?;
+        if !self.groups.contains(&(*group, *interface)) {
+            self.groups.push((*group, *interface));
+        }
+        Ok(())
+    }
+
+    /// Leave a multicast group joined with `join_multicast_v4` (IP_DROP_MEMBERSHIP).
+    pub fn leave_multicast_v4(&mut self, group: &Ipv4Addr, interface: &Ipv4Addr) -> Result<(), std::io::Error> {
+        self.inner.leave_multicast_v4(group, interface)?;
+        self.groups.retain(|membership| membership != &(*group, *interface));
+        Ok(())
+    }
+
+    /// Deliver datagrams of groups joined by other sockets too (IP_MULTICAST_ALL, on by default).
+    ///
+    /// Disabled, the socket only receives the groups it joined itself, on the interfaces it
+    /// joined them on, so two sockets bound to one group can each follow one interface.
+    pub fn set_multicast_all_v4(&mut self, all: bool) -> Result<(), std::io::Error> {
+        self.inner
+            .set_multicast_all_v4(all)
+            .map_err(|e| e.into())
+    }
+
+    /// Check whether IP_MULTICAST_ALL is enabled.
+    pub fn multicast_all_v4(&mut self) -> Result<bool, std::io::Error> {
+        self.inner
+            .multicast_all_v4()
+            .map_err(|e| e.into())
+    }
+
+    /// Report the destination address and interface of received packets (IP_PKTINFO).
+    ///
+    /// When enabled, [`Packet::dst_addr`] and [`Packet::if_index`] are filled by `recv_from()`.
+    pub fn set_pktinfo(&mut self, enable: bool) -> Result<(), std::io::Error> {
+        self.inner
+            .set_pktinfo(enable)
+            .map_err(|e| e.into())
+    }
+
+    /// Check whether IP_PKTINFO reporting is enabled.
+    pub fn pktinfo(&mut self) -> Result<bool, std::io::Error> {
+        self.inner
+            .pktinfo()
+            .map_err(|e| e.into())
+    }
+
+    /// Get the options the socket is running with.
+    ///
+    /// Starts from the options it was created with, including changes made by
+    /// `apply_options()` and `steer_flow()`, then fills in what is actually in effect:
+    /// VMA settings left to their default take the value of their variable in the
+    /// environment, `buffer_size` is the receive buffer the kernel granted and
+    /// `enable_timestamps` follows SO_TIMESTAMPNS. `options().diff(&requested)` lists
+    /// what was adjusted.
+    pub fn options(&self) -> VmaOptions {
+        common::effective_options(self.fd(), Protocol::Udp, self.inner.options())
+    }
+
+    /// Change the runtime-tunable options of the live socket.
+    ///
+    /// The wait strategy and spin budget take effect from the next receive, the buffer
+    /// size and timestamping immediately; `tcp_nodelay` is recorded but does not apply to
+    /// UDP. Nothing is changed if the delta is invalid.
+    pub fn apply_options(&mut self, delta: &OptionsDelta) -> Result<(), std::io::Error> {
+        let mut options = self.inner.options();
+        delta.merge_into(&mut options)?;
+        if let Some(size) = delta.buffer_size.filter(|&size| size > 0) {
+            self.inner.set_recv_buffer_size(size as usize)?;
+            self.inner.set_send_buffer_size(size as usize)?;
+        }
+        if let Some(enable) = delta.enable_timestamps {
+            self.inner.setopt_int(libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, enable as c_int)?;
+        }
+        self.inner.set_options(&options);
+        Ok(())
+    }
+
+    /// Allow several sockets to bind the same address and port (SO_REUSEPORT).
+    ///
+    /// Must be set before `bind()`. See [`crate::sharded::ShardedUdpReceiver`] for a
+    /// multi-core receiver built on it.
+    pub fn set_reuse_port(&mut self, reuse: bool) -> Result<(), std::io::Error> {
+        self.inner
+            .set_reuse_port(reuse)
+            .map_err(|e| e.into())
+    }
+
+    /// Check whether SO_REUSEPORT is enabled.
+    pub fn reuse_port(&mut self) -> Result<bool, std::io::Error> {
+        self.inner
+            .reuse_port()
+            .map_err(|e| e.into())
+    }
+
+    /// Pin a flow to a VMA ring, for deterministic latency on selected feeds.
+    ///
+    /// Registers a libvma.conf rule (`add_conf_rule`) so VMA offloads `flow`, and places
+    /// the socket on the ring shared by every socket steered with the same `ring_id`
+    /// (`RingAllocLogic::PerUserId`); the flow is then served on the core polling that
+    /// ring. Must be called before `bind()`. Fails with `ErrorKind::Unsupported` when
+    /// libvma is not loaded, and with `InvalidInput` for non-UDP flows or a bound socket.
+    pub fn steer_flow(&mut self, flow: FiveTuple, ring_id: u32) -> Result<(), std::io::Error> {
+        if flow.protocol != Protocol::Udp {
+            return Err(Error::new(ErrorKind::InvalidInput, "UDP sockets can only steer UDP flows"));
+        }
+        self.inner
+            .steer_flow(&flow, ring_id)
+            .map_err(|e| e.into())
+    }
+}
+
+impl TryFrom<OwnedFd> for VmaUdpSocket {
+    type Error = FromFdError;
+
+    /// Take over a UDP descriptor with default VMA options (see [`VmaUdpSocket::from_fd`]).
+    fn try_from(fd: OwnedFd) -> Result<Self, Self::Error> {
+        VmaUdpSocket::from_fd(fd, None)
+    }
+}
+
+impl From<VmaUdpSocket> for OwnedFd {
+    /// Hand the descriptor over, e.g. to `socket2::Socket::from`, dropping queued datagrams and counters.
+    fn from(socket: VmaUdpSocket) -> Self {
+        let fd = socket.inner.into_raw_fd();
+        unsafe { OwnedFd::from_raw_fd(fd) }
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl TryFrom<socket2::Socket> for VmaUdpSocket {
+    type Error = FromFdError;
+
+    /// Take over a `socket2` UDP socket with default VMA options (see [`VmaUdpSocket::from_fd`]).
+    fn try_from(socket: socket2::Socket) -> Result<Self, Self::Error> {
+        VmaUdpSocket::from_fd(OwnedFd::from(socket), None)
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl From<VmaUdpSocket> for socket2::Socket {
+    /// Hand the descriptor over to `socket2`, dropping queued datagrams and counters.
+    fn from(socket: VmaUdpSocket) -> Self {
+        socket2::Socket::from(OwnedFd::from(socket))
+    }
+}
+
+impl AsFd for VmaUdpSocket {
+    /// Borrow the descriptor, e.g. for `socket2::SockRef::from`.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        let fd = self.inner.fd();
+        // Only `close(self)` and `into_raw_fd(self)` give the descriptor up, consuming the socket
+        assert!(fd >= 0, "VmaUdpSocket without a descriptor");
+        unsafe { BorrowedFd::borrow_raw(fd) }
+    }
+}
+
+impl AsRawFd for VmaUdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.fd()
+    }
+}
+
+impl StatsSource for VmaUdpSocket {
+    fn stats(&mut self) -> Result<SocketStats, std::io::Error> {
+        VmaUdpSocket::stats(self)
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        VmaUdpSocket::local_addr(self)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        VmaUdpSocket::peer_addr(self)
+    }
+
+    fn vma_fd_stats(&self) -> Result<VmaFdStats, std::io::Error> {
+        VmaUdpSocket::vma_fd_stats(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Route multicast sends of `socket` through the loopback interface.
+    fn multicast_on_loopback(socket: &VmaUdpSocket) {
+        let interface = libc::in_addr { s_addr: u32::from(Ipv4Addr::LOCALHOST).to_be() };
+        let size = mem::size_of_val(&interface) as libc::socklen_t;
+        let set = unsafe {
+            libc::setsockopt(socket.as_raw_fd(), libc::IPPROTO_IP, libc::IP_MULTICAST_IF, &interface as *const _ as *const c_void, size)
+        };
+        assert_eq!(set, 0);
+    }
+
+    #[test]
+    fn test_rebind() {
+        // Not bound yet: bound in place
+        let mut socket = VmaUdpSocket::new().unwrap();
+        let fd = socket.fd();
+        socket.rebind("127.0.0.1", 0).unwrap();
+        assert_eq!(socket.fd(), fd);
+        let first = socket.local_addr().unwrap();
+        assert_ne!(first.port(), 0);
+
+        // Bound: moved to a new descriptor that receives on the new port
+        socket.set_read_timeout(Some(500_000_000));
+        socket.rebind("127.0.0.1", 0).unwrap();
+        let second = socket.local_addr().unwrap();
+        assert_ne!(second.port(), first.port());
+        assert_eq!(socket.read_timeout(), Some(500_000_000));
+        let mut sender = VmaUdpSocket::new().unwrap();
+        sender.send_to(b"moved", "127.0.0.1", second.port()).unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(socket.recv(&mut buffer, Some(1_000_000_000)).unwrap(), 5);
+
+        // Memberships follow the socket to the new descriptor
+        let group = Ipv4Addr::new(239, 1, 2, 7);
+        let mut member = VmaUdpSocket::new().unwrap();
+        member.bind("0.0.0.0", 0).unwrap();
+        if member.join_multicast_v4(&group, &Ipv4Addr::LOCALHOST).is_err() {
+            return; // no multicast route in this environment
+        }
+        member.rebind("0.0.0.0", 0).unwrap();
+        let port = member.local_addr().unwrap().port();
+        multicast_on_loopback(&sender);
+        sender.send_to(b"group", group.to_string(), port).unwrap();
+        assert_eq!(member.recv(&mut buffer, Some(1_000_000_000)).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"group");
+    }
+
+    #[test]
+    fn test_receive_timestamps() {
+        let wall_clock = || std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let mut sender = VmaUdpSocket::new().unwrap();
+        let mut buffer = [0u8; 16];
+        for enable_timestamps in [true, false] {
+            let mut receiver = VmaUdpSocket::with_options(VmaOptions { enable_timestamps, ..VmaOptions::default() }).unwrap();
+            receiver.bind("127.0.0.1", 0).unwrap();
+            let port = receiver.local_addr().unwrap().port();
+            let before = wall_clock();
+            sender.send_to(b"stamp", "127.0.0.1", port).unwrap();
+            let packet = receiver.recv_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+            if enable_timestamps {
+                // Taken by the kernel on arrival, not when recvmsg returned
+                assert!(packet.timestamp >= before && packet.timestamp <= wall_clock());
+            } else {
+                assert_eq!(packet.timestamp, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_send_timeout_mapping() {
+        let full = || UdpResult::UdpErrorTimeout;
+
+        // A polling (O_NONBLOCK) descriptor never waits, whatever the write timeout
+        let mut polling = VmaUdpSocket::new().unwrap();
+        polling.set_write_timeout(Some(10_000_000)).unwrap();
+        assert_eq!(polling.send_error(full(), MsgFlags::NONE).kind(), ErrorKind::WouldBlock);
+
+        // Nothing to wait for: the send would block
+        let mut socket = VmaUdpSocket::with_options(VmaOptions { use_polling: false, ..VmaOptions::default() }).unwrap();
+        assert_eq!(socket.send_error(full(), MsgFlags::NONE).kind(), ErrorKind::WouldBlock);
+
+        // A send that waited for the write timeout timed out, one that could not wait would block
+        socket.set_write_timeout(Some(10_000_000)).unwrap();
+        assert_eq!(socket.send_error(full(), MsgFlags::NONE).kind(), ErrorKind::TimedOut);
+        assert_eq!(socket.send_error(full(), MsgFlags::DONTWAIT).kind(), ErrorKind::WouldBlock);
+        socket.set_nonblocking(true);
+        let flags = socket.send_flags(MsgFlags::NONE);
+        assert_eq!(socket.send_error(full(), flags).kind(), ErrorKind::WouldBlock);
+        assert_eq!(socket.send_error(UdpResult::UdpErrorSend, flags).kind(), Error::from(UdpResult::UdpErrorSend).kind());
+    }
+
+    #[test]
+    fn test_nonblocking() {
+        let mut socket = VmaUdpSocket::new().unwrap();
+        socket.bind("127.0.0.1", 0).unwrap();
+        socket.set_nonblocking(true);
+        let port = socket.local_addr().unwrap().port();
+
+        // No datagram queued: would block like std, regardless of the timeout
+        let mut buffer = [0u8; 16];
+        assert_eq!(socket.recv(&mut buffer, None).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(socket.recv_burst(4, None).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        let mut sender = VmaUdpSocket::new().unwrap();
+        sender.send_to(b"ready", "127.0.0.1", port).unwrap();
+        assert_eq!(socket.peek(&mut buffer, None).unwrap(), 5);
+        assert_eq!(socket.recv(&mut buffer, None).unwrap(), 5);
+
+        // Registered sends do not wait for the pacer either
+        sender.connect("127.0.0.1", port).unwrap();
+        sender.set_nonblocking(true);
+        sender.set_pacer(Some(Pacer::new(crate::pacing::Rate::PacketsPerSec(1)).unwrap()));
+        let registered = RegisteredBuffer::new(64, &VmaOptions { use_hugepages: false, ..VmaOptions::default() }).unwrap();
+        assert_eq!(sender.send_registered(&registered, 0..8).unwrap(), 8);
+        let start = Instant::now();
+        assert_eq!(sender.send_registered(&registered, 0..8).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let mut socket = VmaUdpSocket::new().unwrap();
+        socket.bind("127.0.0.1", 0).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let mut peer = VmaUdpSocket::new().unwrap();
+        peer.bind("127.0.0.1", 0).unwrap();
+        let mut other = VmaUdpSocket::new().unwrap();
+        other.bind("127.0.0.1", 0).unwrap();
+
+        // Connected: only the peer gets through
+        let peer_port = peer.local_addr().unwrap().port();
+        socket.connect("127.0.0.1", peer_port).unwrap();
+        assert_eq!(socket.peer_addr(), Some(peer.local_addr().unwrap()));
+        other.send_to(b"other", "127.0.0.1", port).unwrap();
+        peer.send_to(b"peer", "127.0.0.1", port).unwrap();
+        let mut buffer = [0u8; 16];
+        let packet = socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!(packet.src_addr, peer.local_addr().unwrap());
+
+        // Disconnected: any sender again, and send() needs a new connect()
+        socket.disconnect().unwrap();
+        assert_eq!(socket.peer_addr(), None);
+        assert_eq!(socket.local_addr().unwrap().port(), port);
+        assert_eq!(socket.send(b"nowhere").unwrap_err().kind(), ErrorKind::NotConnected);
+        other.send_to(b"other", "127.0.0.1", port).unwrap();
+        let packet = socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!(packet.src_addr, other.local_addr().unwrap());
+    }
+
+    #[test]
+    fn test_truncation() {
+        let mut socket = VmaUdpSocket::new().unwrap();
+        socket.bind("127.0.0.1", 0).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let mut sender = VmaUdpSocket::new().unwrap();
+        let payload = [7u8; 32];
+        sender.send_to(&payload, "127.0.0.1", port).unwrap();
+        sender.send_to(&payload, "127.0.0.1", port).unwrap();
+        sender.send_to(&payload[..4], "127.0.0.1", port).unwrap();
+
+        // Peeking reports the full length but does not count as a truncation
+        let mut buffer = [0u8; 8];
+        let packet = socket.peek_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert!(packet.is_truncated());
+        assert_eq!(socket.stats().unwrap().rx_truncated, 0);
+
+        let length = socket.recv_full(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!((length.copied, length.datagram), (8, 32));
+        assert!(length.is_truncated());
+        let packet = socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert_eq!((packet.data.len(), packet.full_length), (8, 32));
+        let packet = socket.recv_from(&mut buffer, Some(1_000_000_000)).unwrap().unwrap();
+        assert!(!packet.is_truncated());
+        assert_eq!(socket.stats().unwrap().rx_truncated, 2);
+    }
 }
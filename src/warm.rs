@@ -0,0 +1,136 @@
+//! Keeping the VMA transmit path warm between real sends.
+//!
+//! After a quiet period the first send pays for cold caches, TLB entries and TX ring
+//! state. VMA can run a packet through its whole TX path without putting it on the wire
+//! ("dummy send", `VMA_SND_FLAGS_DUMMY`). [`VmaUdpSocket::warm`] and [`VmaTcpSocket::warm`]
+//! issue one such send; a [`Warmer`] repeats it from a background thread at a fixed interval.
+//!
+//! Dummy sends require the socket to be offloaded by VMA; otherwise they fail with
+//! `ErrorKind::Unsupported` rather than sending real traffic.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//! use vma_socket::tcp::VmaTcpSocket;
+//! use vma_socket::warm::Warmer;
+//!
+//! let mut socket = VmaTcpSocket::new().unwrap();
+//! socket.connect("192.168.1.100", 5002, Some(1_000_000_000)).unwrap();
+//!
+//! let socket = Arc::new(Mutex::new(socket));
+//! // Warm with a payload shaped like a typical order message
+//! let _warmer = Warmer::spawn(Arc::clone(&socket), Duration::from_micros(100), vec![0u8; 128]).unwrap();
+//!
+//! socket.lock().unwrap().send(b"real order").unwrap();
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::reconnect::sleep_while_running;
+use crate::tcp::VmaTcpSocket;
+use crate::udp::VmaUdpSocket;
+
+/// Sockets that support VMA dummy sends.
+pub trait Warm {
+    /// Run `data` through the TX path without sending it.
+    fn warm(&mut self, data: &[u8]) -> Result<(), Error>;
+}
+
+impl Warm for VmaUdpSocket {
+    fn warm(&mut self, data: &[u8]) -> Result<(), Error> {
+        VmaUdpSocket::warm(self, data)
+    }
+}
+
+impl Warm for VmaTcpSocket {
+    fn warm(&mut self, data: &[u8]) -> Result<(), Error> {
+        VmaTcpSocket::warm(self, data)
+    }
+}
+
+/// Background thread issuing dummy sends on a shared socket at a fixed interval.
+///
+/// A round is skipped when the socket lock is held (a real send is in progress), so the
+/// warmer never delays application traffic. The thread exits on `stop()`, when the socket
+/// turns out not to support dummy sends, or when the lock is poisoned.
+#[derive(Debug)]
+pub struct Warmer {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Warmer {
+    /// Start warming `socket` with `payload` every `interval`.
+    pub fn spawn<S>(socket: Arc<Mutex<S>>, interval: Duration, payload: Vec<u8>) -> Result<Self, Error>
+    where
+        S: Warm + Send + 'static,
+    {
+        if payload.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Warm payload must not be empty"));
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+
+        let handle = thread::Builder::new()
+            .name("vma-warmer".to_string())
+            .spawn(move || run_warmer(socket, interval, payload, thread_running))?;
+
+        Ok(Warmer {
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// Check whether the warmer is still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the warmer and wait for its thread to exit.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Warmer {
+    /// Stop the warmer thread.
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Main loop of the warmer thread.
+fn run_warmer<S: Warm>(socket: Arc<Mutex<S>>, interval: Duration, payload: Vec<u8>, running: Arc<AtomicBool>) {
+    while running.load(Ordering::Acquire) {
+        match socket.try_lock() {
+            Ok(mut socket) => {
+                // Transient errors (e.g. not connected during a reconnect) are retried next round
+                if let Err(e) = socket.warm(&payload) {
+                    if e.kind() == ErrorKind::Unsupported {
+                        break;
+                    }
+                }
+            }
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Poisoned(_)) => break,
+        }
+
+        sleep_while_running(interval, &running);
+    }
+
+    running.store(false, Ordering::Release);
+}